@@ -2,8 +2,11 @@ use std::fs;
 use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Compile protobuf files
-    tonic_build::compile_protos("proto/quilt.proto")?;
+    // Compile protobuf files, keeping the descriptor set for server reflection
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("quilt_descriptor.bin"))
+        .compile(&["proto/quilt.proto", "proto/plugin.proto"], &["proto"])?;
     
     // Download and setup busybox for containers
     setup_busybox()?;
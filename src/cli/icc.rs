@@ -13,7 +13,31 @@ use crate::quilt::{
     GetContainerStatusRequest,
     ExecContainerRequest,
     ContainerStatus,
+    RegisterIccServiceRequest,
+    UnregisterIccServiceRequest,
+    ListIccServicesRequest,
+    CallIccServiceRequest,
+    PollIccServiceRequest,
+    ReplyIccServiceRequest,
+    ListDnsEntriesRequest,
+    AddDnsAliasRequest,
+    RemoveDnsAliasRequest,
+    SetDnsRecordRequest,
+    RemoveDnsRecordRequest,
+    ListDnsRecordsRequest,
+    ConnectContainerToNetworkRequest,
+    DisconnectContainerFromNetworkRequest,
+    MirrorContainerTrafficRequest,
+    StopTrafficMirrorRequest,
+    ListTrafficMirrorsRequest,
+    GetContainerNetworkRequest,
+    FlushDnsCacheRequest,
+    TestConnectivityRequest,
+    CreateVirtualServiceRequest,
+    DeleteVirtualServiceRequest,
+    ListVirtualServicesRequest,
 };
+use crate::utils::validation::InputValidator;
 
 #[derive(Debug, Clone, Serialize)]
 pub enum ConnectionType {
@@ -48,12 +72,14 @@ pub enum IccCommands {
     Ping {
         #[clap(help = "Source container ID")]
         from_container: String,
-        #[clap(help = "Target container ID or IP address")]
+        #[clap(help = "Target container ID or name, IP address or hostname")]
         target: String,
-        #[clap(long, help = "Number of ping packets to send", default_value = "3")]
+        #[clap(long, help = "Number of probes to send", default_value = "3")]
         count: u32,
-        #[clap(long, help = "Timeout in seconds", default_value = "5")]
+        #[clap(long, help = "Per-probe timeout in seconds", default_value = "5")]
         timeout: u32,
+        #[clap(long, help = "Check a TCP port instead of sending ICMP echo")]
+        port: Option<u16>,
     },
 
     /// Establish persistent connections between containers
@@ -115,6 +141,150 @@ pub enum IccCommands {
         #[clap(subcommand)]
         action: NetworkAction,
     },
+
+    /// Request/response services between containers
+    Service {
+        #[clap(subcommand)]
+        action: ServiceAction,
+    },
+
+    /// Container DNS names and aliases
+    Dns {
+        #[clap(subcommand)]
+        action: DnsAction,
+    },
+
+    /// DNS names load-balanced across the running containers with matching labels
+    VirtualService {
+        #[clap(subcommand)]
+        action: VirtualServiceAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VirtualServiceAction {
+    /// Create a virtual service backed by every running container carrying the selector labels
+    Create {
+        #[clap(help = "Service name (resolves as NAME and NAME.quilt.local)")]
+        name: String,
+        #[clap(short = 's', long = "selector", action = clap::ArgAction::Append, required = true,
+               help = "Backend label in KEY=VALUE format (repeatable; all must match)",
+               value_parser = InputValidator::parse_key_val)]
+        selector: Vec<(String, String)>,
+    },
+
+    /// Delete a virtual service
+    Delete {
+        #[clap(help = "Service name")]
+        name: String,
+    },
+
+    /// List virtual services and their current backends
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DnsAction {
+    /// List DNS entries and their aliases
+    List,
+
+    /// Add one or more aliases resolving to a container
+    Alias {
+        #[clap(help = "Container ID")]
+        container_id: String,
+        #[clap(help = "Aliases to add", required = true, num_args = 1..)]
+        aliases: Vec<String>,
+    },
+
+    /// Remove aliases
+    Unalias {
+        #[clap(help = "Aliases to remove", required = true, num_args = 1..)]
+        aliases: Vec<String>,
+    },
+
+    /// Drop cached DNS answers
+    Flush,
+
+    /// Answer a name with fixed addresses, for containers, the host or both
+    Record {
+        #[clap(help = "Name to answer (bare names also resolve as NAME.quilt.local)")]
+        name: String,
+        #[clap(help = "IPv4 addresses to answer with", required = true, num_args = 1..)]
+        addresses: Vec<String>,
+        #[clap(long, default_value = "all", value_parser = ["all", "containers", "host"],
+               help = "Which queries the record answers")]
+        view: String,
+    },
+
+    /// Remove a record from a view
+    Unrecord {
+        #[clap(help = "Record name")]
+        name: String,
+        #[clap(long, default_value = "all", value_parser = ["all", "containers", "host"],
+               help = "View the record was set for")]
+        view: String,
+    },
+
+    /// List DNS records by view
+    Records,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServiceAction {
+    /// Register a named service served by a container
+    Register {
+        #[clap(help = "Container ID that serves the requests")]
+        container_id: String,
+        #[clap(help = "Service name")]
+        service: String,
+        #[clap(long, help = "Max queued requests before callers are rejected", default_value = "64")]
+        queue_depth: u32,
+    },
+
+    /// Unregister a service
+    Unregister {
+        #[clap(help = "Container ID that owns the service")]
+        container_id: String,
+        #[clap(help = "Service name")]
+        service: String,
+    },
+
+    /// List registered services
+    List,
+
+    /// Send a request to a service and wait for the reply
+    Call {
+        #[clap(help = "Calling container ID")]
+        from_container: String,
+        #[clap(help = "Service name")]
+        service: String,
+        #[clap(help = "Request payload")]
+        payload: String,
+        #[clap(long, help = "Reply timeout in milliseconds", default_value = "30000")]
+        timeout_ms: u32,
+    },
+
+    /// Wait for the next request addressed to a service
+    Poll {
+        #[clap(help = "Container ID that owns the service")]
+        container_id: String,
+        #[clap(help = "Service name")]
+        service: String,
+        #[clap(long, help = "How long to wait in milliseconds", default_value = "30000")]
+        wait_ms: u32,
+    },
+
+    /// Reply to a polled request
+    Reply {
+        #[clap(help = "Container ID that owns the service")]
+        container_id: String,
+        #[clap(help = "Request ID returned by poll")]
+        request_id: String,
+        #[clap(help = "Reply payload", default_value = "")]
+        payload: String,
+        #[clap(long, help = "Fail the call with this error instead of replying")]
+        error: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -191,13 +361,48 @@ pub enum NetworkAction {
         #[clap(long, help = "Test protocol: tcp, udp, icmp", default_value = "icmp")]
         protocol: String,
     },
+
+    /// Connect a running container to an additional network, creating it on first use
+    Connect {
+        #[clap(help = "Container ID")]
+        container_id: String,
+        #[clap(help = "Network name (1-13 lowercase letters and digits)")]
+        network: String,
+    },
+
+    /// Disconnect a container from an additional network
+    Disconnect {
+        #[clap(help = "Container ID")]
+        container_id: String,
+        #[clap(help = "Network name")]
+        network: String,
+    },
+
+    /// Copy a running container's traffic to an observer container, e.g. an IDS sidecar
+    Mirror {
+        #[clap(help = "Container ID whose traffic is mirrored")]
+        container_id: String,
+        #[clap(help = "Observer container ID or name")]
+        observer: String,
+        #[clap(long, help = "Traffic to mirror: both, ingress or egress", default_value = "both")]
+        direction: String,
+    },
+
+    /// Stop mirroring a container's traffic
+    Unmirror {
+        #[clap(help = "Container ID")]
+        container_id: String,
+    },
+
+    /// List traffic mirrors
+    Mirrors,
 }
 
 // Implementation functions (to be implemented)
 pub async fn handle_icc_command(cmd: IccCommands, mut client: QuiltServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
-        IccCommands::Ping { from_container, target, count, timeout } => {
-            handle_ping_command(from_container, target, count, timeout, &mut client).await
+        IccCommands::Ping { from_container, target, count, timeout, port } => {
+            handle_ping_command(from_container, target, count, timeout, port, &mut client).await
         },
         IccCommands::Connect { 
             from_container, 
@@ -235,6 +440,15 @@ pub async fn handle_icc_command(cmd: IccCommands, mut client: QuiltServiceClient
         IccCommands::Network { action } => {
             handle_network_command(action, &mut client).await
         },
+        IccCommands::Service { action } => {
+            handle_service_command(action, &mut client).await
+        },
+        IccCommands::Dns { action } => {
+            handle_dns_command(action, &mut client).await
+        },
+        IccCommands::VirtualService { action } => {
+            handle_virtual_service_command(action, &mut client).await
+        },
     }
 }
 
@@ -244,130 +458,40 @@ async fn handle_ping_command(
     target: String, 
     count: u32, 
     timeout: u32,
+    port: Option<u16>,
     client: &mut QuiltServiceClient<Channel>
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🏓 Pinging from {} to {} ({} packets, {}s timeout)", from_container, target, count, timeout);
-    
-    // ELITE: Check source container status
-    let mut from_request = tonic::Request::new(GetContainerStatusRequest { 
-        container_id: from_container.clone(),
-        container_name: String::new(),
-    });
-    from_request.set_timeout(Duration::from_secs(30));
-    
-    let _from_status = match client.get_container_status(from_request).await {
-        Ok(response) => {
-            let status = response.into_inner();
-            let container_status = match status.status {
-                0 => ContainerStatus::Pending,
-                1 => ContainerStatus::Running,
-                2 => ContainerStatus::Exited,
-                _ => ContainerStatus::Failed,
-            };
-            
-            if !matches!(container_status, ContainerStatus::Running) {
-                return Err(format!("Source container {} is not running (status: {:?})", from_container, container_status).into());
-            }
-            status
-        }
-        Err(e) => {
-            return Err(format!("Failed to get status for container {}: {}", from_container, e).into());
-        }
-    };
-    
-    // ELITE: Determine target IP
-    let final_target_ip = if target.contains('.') {
-        // Already an IP address
-        target
-    } else {
-        // Container ID - get its IP
-        let mut target_request = tonic::Request::new(GetContainerStatusRequest {
-            container_id: target.clone(),
-            container_name: String::new(),
-        });
-        target_request.set_timeout(Duration::from_secs(30));
-        
-        match client.get_container_status(target_request).await {
-            Ok(response) => {
-                let status = response.into_inner();
-                let container_status = match status.status {
-                    0 => ContainerStatus::Pending,
-                    1 => ContainerStatus::Running,
-                    2 => ContainerStatus::Exited,
-                    _ => ContainerStatus::Failed,
-                };
-                
-                if !matches!(container_status, ContainerStatus::Running) {
-                    return Err(format!("Target container {} is not running (status: {:?})", target, container_status).into());
-                }
-                
-                if status.ip_address.is_empty() || status.ip_address == "No IP assigned" {
-                    return Err(format!("Target container {} has no IP address assigned", target).into());
-                }
-                
-                status.ip_address
-            }
-            Err(e) => {
-                return Err(format!("Failed to get status for target container {}: {}", target, e).into());
-            }
-        }
-    };
-    
-    // ELITE: Use optimized ping with adaptive timeout
-    let adaptive_timeout = std::cmp::max(timeout, 10); // Minimum 10s for network load
-    let ping_cmd = vec![
-        "ping".to_string(),
-        "-c".to_string(), count.to_string(),
-        "-W".to_string(), adaptive_timeout.to_string(),
-        "-i".to_string(), "0.5".to_string(),  // ELITE: Faster ping interval
-        final_target_ip.clone()
-    ];
+    match port {
+        Some(port) => println!("🏓 Testing TCP {}:{} from {} ({} probes, {}s timeout)", target, port, from_container, count, timeout),
+        None => println!("🏓 Pinging {} from {} ({} packets, {}s timeout)", target, from_container, count, timeout),
+    }
     
-    let mut exec_request = tonic::Request::new(ExecContainerRequest {
+    let mut request = tonic::Request::new(TestConnectivityRequest {
         container_id: from_container.clone(),
-        command: ping_cmd,
-        working_directory: String::new(),
-        environment: HashMap::new(),
-        capture_output: true,
         container_name: String::new(),
-        copy_script: false,
+        target: target.clone(),
+        port: port.unwrap_or(0) as u32,
+        count,
+        timeout_seconds: timeout,
     });
-    // ELITE: Much more generous timeout for exec under load
-    exec_request.set_timeout(Duration::from_secs(adaptive_timeout as u64 + 10)); 
-    
-    println!("📡 Executing ping with {:.1}s timeout...", adaptive_timeout);
+    // Every probe may wait out its timeout
+    request.set_timeout(Duration::from_secs(timeout as u64 * count.max(1) as u64 + 10));
+    
+    let result = client.test_connectivity(request).await
+        .map_err(|e| format!("Connectivity test failed: {}", e.message()))?
+        .into_inner();
+    if !result.success {
+        return Err(format!("Connectivity test from {} to {} failed: {}", from_container, target, result.error_message).into());
+    }
     
-    match client.exec_container(exec_request).await {
-        Ok(response) => {
-            let result = response.into_inner();
-            
-            if result.success {
-                println!("✅ Ping successful!");
-                if !result.stdout.is_empty() {
-                    println!("📤 Output:");
-                    println!("{}", result.stdout);
-                }
-            } else {
-                println!("❌ Ping from {} to {} failed. Exit code: {}", from_container, final_target_ip, result.exit_code);
-                if result.exit_code == 124 {
-                    println!("⚠️  Exit code 124 indicates timeout - network may still be initializing");
-                }
-                if !result.stdout.is_empty() {
-                    println!("📤 Output:");
-                    println!("{}", result.stdout);
-                }
-                if !result.stderr.is_empty() {
-                    println!("📤 Error:");
-                    println!("{}", result.stderr);
-                }
-            }
-            
-            Ok(())
-        }
-        Err(e) => {
-            Err(format!("Failed to execute ping command: {}", e).into())
-        }
+    let icon = if result.packets_received > 0 { "✅" } else { "❌" };
+    println!("{} {} {}: {}/{} answered, {:.1}% loss", icon, result.protocol.to_uppercase(), result.target_address,
+        result.packets_received, result.packets_sent, result.packet_loss_percent);
+    if result.packets_received > 0 {
+        println!("   Latency min/avg/max: {:.3}/{:.3}/{:.3} ms", result.min_latency_ms, result.avg_latency_ms, result.max_latency_ms);
     }
+    
+    Ok(())
 }
 
 async fn handle_connect_command(
@@ -497,6 +621,8 @@ async fn handle_exec_command(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(30)); // Generous timeout for exec commands
 
@@ -548,6 +674,56 @@ async fn handle_network_command(action: NetworkAction, client: &mut QuiltService
         NetworkAction::Test { from_container, target, port, protocol } => {
             test_network_connectivity(from_container, target, port, protocol, client).await?;
         },
+        NetworkAction::Connect { container_id, network } => {
+            let response = client.connect_container_to_network(tonic::Request::new(ConnectContainerToNetworkRequest {
+                container_id,
+                container_name: String::new(),
+                network,
+            })).await.map_err(|e| format!("Failed to connect to network: {}", e.message()))?.into_inner();
+            if let Some(attachment) = response.attachment {
+                println!("✅ {} joined {} at {} on {} ({})", response.container_id, attachment.network,
+                    attachment.ip_address, attachment.interface, attachment.subnet);
+            }
+        },
+        NetworkAction::Disconnect { container_id, network } => {
+            let response = client.disconnect_container_from_network(tonic::Request::new(DisconnectContainerFromNetworkRequest {
+                container_id,
+                container_name: String::new(),
+                network: network.clone(),
+            })).await.map_err(|e| format!("Failed to disconnect from network: {}", e.message()))?.into_inner();
+            println!("✅ {} left {}", response.container_id, network);
+        },
+        NetworkAction::Mirror { container_id, observer, direction } => {
+            let response = client.mirror_container_traffic(tonic::Request::new(MirrorContainerTrafficRequest {
+                container_id,
+                container_name: String::new(),
+                observer,
+                direction,
+            })).await.map_err(|e| format!("Failed to mirror traffic: {}", e.message()))?.into_inner();
+            if let Some(mirror) = response.mirror {
+                println!("✅ Mirroring {} traffic of {} to {}", mirror.direction, mirror.container_id, mirror.observer_container_id);
+                println!("   The observer's sniffer must run its interface in promiscuous mode");
+            }
+        },
+        NetworkAction::Unmirror { container_id } => {
+            let response = client.stop_traffic_mirror(tonic::Request::new(StopTrafficMirrorRequest {
+                container_id,
+                container_name: String::new(),
+            })).await.map_err(|e| format!("Failed to stop mirroring: {}", e.message()))?.into_inner();
+            if let Some(mirror) = response.mirror {
+                println!("✅ Stopped mirroring {} to {}", mirror.container_id, mirror.observer_container_id);
+            }
+        },
+        NetworkAction::Mirrors => {
+            let response = client.list_traffic_mirrors(tonic::Request::new(ListTrafficMirrorsRequest {}))
+                .await.map_err(|e| format!("Failed to list traffic mirrors: {}", e.message()))?.into_inner();
+            if response.mirrors.is_empty() {
+                println!("No traffic mirrors");
+            }
+            for mirror in response.mirrors {
+                println!("{} -> {} ({})", mirror.container_id, mirror.observer_container_id, mirror.direction);
+            }
+        },
     }
     Ok(())
 }
@@ -567,12 +743,7 @@ async fn validate_container_running(
         .map_err(|e| format!("Failed to get status for container {}: {}", container_id, e))?;
     
     let status = response.into_inner();
-    let container_status = match status.status {
-        0 => ContainerStatus::Pending,
-        1 => ContainerStatus::Running,
-        2 => ContainerStatus::Exited,
-        _ => ContainerStatus::Failed,
-    };
+    let container_status = ContainerStatus::from_i32(status.status).unwrap_or(ContainerStatus::Failed);
     
     if !matches!(container_status, ContainerStatus::Running) {
         return Err(format!("Container {} is not running (status: {:?})", container_id, container_status).into());
@@ -613,6 +784,8 @@ async fn establish_tcp_connection(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(10));
     
@@ -667,6 +840,8 @@ async fn establish_udp_connection(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(8));
     
@@ -724,6 +899,8 @@ async fn establish_http_connection(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(15));
     
@@ -992,6 +1169,8 @@ async fn test_container_connectivity(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(5));
     
@@ -1178,6 +1357,16 @@ async fn show_container_network_info(
             println!("  DNS Server: 10.42.0.1:1053");
             println!("  Status: Connected");
             
+            let attachments = client.get_container_network(tonic::Request::new(GetContainerNetworkRequest {
+                container_id: container_id.clone(),
+            })).await.map(|response| response.into_inner().attachments).unwrap_or_default();
+            if !attachments.is_empty() {
+                println!("\nAdditional Networks:");
+                for attachment in &attachments {
+                    println!("  {}: {} on {} ({})", attachment.network, attachment.ip_address, attachment.interface, attachment.subnet);
+                }
+            }
+            
             // Test basic connectivity
             println!("\nConnectivity Test:");
             if let Ok(connectivity) = test_gateway_connectivity(&container_id, client).await {
@@ -1255,6 +1444,8 @@ async fn test_gateway_connectivity(
         capture_output: true,
         container_name: String::new(),
         copy_script: false,
+        timeout_seconds: 0,
+        create_working_directory: false,
     });
     exec_request.set_timeout(Duration::from_secs(5));
     
@@ -1265,4 +1456,244 @@ async fn test_gateway_connectivity(
         },
         Err(_) => Ok(false),
     }
-} 
\ No newline at end of file
+}
+
+async fn handle_service_command(
+    action: ServiceAction,
+    client: &mut QuiltServiceClient<Channel>
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ServiceAction::Register { container_id, service, queue_depth } => {
+            let response = client.register_icc_service(tonic::Request::new(RegisterIccServiceRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                service: service.clone(),
+                queue_depth,
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to register service: {}", response.error_message).into());
+            }
+            println!("✅ Service '{}' registered by container {}", service, container_id);
+        },
+        ServiceAction::Unregister { container_id, service } => {
+            let response = client.unregister_icc_service(tonic::Request::new(UnregisterIccServiceRequest {
+                container_id,
+                container_name: String::new(),
+                service: service.clone(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to unregister service: {}", response.error_message).into());
+            }
+            println!("✅ Service '{}' unregistered", service);
+        },
+        ServiceAction::List => {
+            let response = client.list_icc_services(tonic::Request::new(ListIccServicesRequest {})).await?.into_inner();
+            if response.services.is_empty() {
+                println!("No ICC services registered");
+                return Ok(());
+            }
+            println!("{:<24} {:<38} {:>8}", "SERVICE", "CONTAINER", "QUEUED");
+            for service in response.services {
+                println!("{:<24} {:<38} {:>4}/{:<4}", service.service, service.container_id,
+                    service.queued_requests, service.queue_depth);
+            }
+        },
+        ServiceAction::Call { from_container, service, payload, timeout_ms } => {
+            let mut request = tonic::Request::new(CallIccServiceRequest {
+                container_id: from_container,
+                container_name: String::new(),
+                service: service.clone(),
+                payload: payload.into_bytes(),
+                timeout_ms,
+            });
+            // Leave headroom beyond the broker timeout so its error reaches us
+            request.set_timeout(Duration::from_millis(timeout_ms as u64) + Duration::from_secs(5));
+            let response = client.call_icc_service(request).await?.into_inner();
+            if !response.success {
+                return Err(format!("Call to '{}' failed: {}", service, response.error_message).into());
+            }
+            println!("{}", String::from_utf8_lossy(&response.payload));
+        },
+        ServiceAction::Poll { container_id, service, wait_ms } => {
+            let mut request = tonic::Request::new(PollIccServiceRequest {
+                container_id,
+                container_name: String::new(),
+                service,
+                wait_ms,
+            });
+            request.set_timeout(Duration::from_millis(wait_ms as u64) + Duration::from_secs(5));
+            let response = client.poll_icc_service(request).await?.into_inner();
+            if !response.success {
+                return Err(format!("Poll failed: {}", response.error_message).into());
+            }
+            if response.has_request {
+                println!("📨 Request {} from {}", response.request_id, response.from_container);
+                println!("{}", String::from_utf8_lossy(&response.payload));
+            } else {
+                println!("No pending requests");
+            }
+        },
+        ServiceAction::Reply { container_id, request_id, payload, error } => {
+            let response = client.reply_icc_service(tonic::Request::new(ReplyIccServiceRequest {
+                container_id,
+                container_name: String::new(),
+                request_id: request_id.clone(),
+                payload: payload.into_bytes(),
+                error: error.unwrap_or_default(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Reply failed: {}", response.error_message).into());
+            }
+            println!("✅ Replied to request {}", request_id);
+        },
+    }
+
+    Ok(())
+}
+
+async fn handle_dns_command(
+    action: DnsAction,
+    client: &mut QuiltServiceClient<Channel>
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        DnsAction::List => {
+            let response = client.list_dns_entries(tonic::Request::new(ListDnsEntriesRequest {})).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to list DNS entries: {}", response.error_message).into());
+            }
+            if response.entries.is_empty() {
+                println!("No DNS entries registered");
+                return Ok(());
+            }
+            let mut entries = response.entries;
+            entries.sort_by(|a, b| a.container_name.cmp(&b.container_name));
+            println!("{:<24} {:<16} {:<38} {:<9} {}", "NAME", "IP", "CONTAINER", "WILDCARD", "ALIASES");
+            for entry in entries {
+                println!("{:<24} {:<16} {:<38} {:<9} {}", entry.container_name, entry.ip_address,
+                    entry.container_id, if entry.wildcard { "yes" } else { "no" }, entry.aliases.join(", "));
+            }
+        },
+        DnsAction::Alias { container_id, aliases } => {
+            for alias in aliases {
+                let response = client.add_dns_alias(tonic::Request::new(AddDnsAliasRequest {
+                    container_id: container_id.clone(),
+                    container_name: String::new(),
+                    alias: alias.clone(),
+                })).await?.into_inner();
+                if !response.success {
+                    return Err(format!("Failed to add alias '{}': {}", alias, response.error_message).into());
+                }
+                println!("✅ {} now resolves to container {}", alias, container_id);
+            }
+        },
+        DnsAction::Unalias { aliases } => {
+            for alias in aliases {
+                let response = client.remove_dns_alias(tonic::Request::new(RemoveDnsAliasRequest {
+                    alias: alias.clone(),
+                })).await?.into_inner();
+                if !response.success {
+                    return Err(format!("Failed to remove alias '{}': {}", alias, response.error_message).into());
+                }
+                println!("✅ Removed alias {} from container {}", alias, response.container_id);
+            }
+        },
+        DnsAction::Flush => {
+            let response = client.flush_dns_cache(tonic::Request::new(FlushDnsCacheRequest {})).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to flush DNS cache: {}", response.error_message).into());
+            }
+            println!("✅ Flushed {} cached DNS answers", response.entries_flushed);
+        },
+        DnsAction::Record { name, addresses, view } => {
+            let response = client.set_dns_record(tonic::Request::new(SetDnsRecordRequest {
+                name: name.clone(),
+                view: view.clone(),
+                addresses: addresses.clone(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to set DNS record: {}", response.error_message).into());
+            }
+            println!("✅ {} now resolves to {} for {} queries", name, addresses.join(", "), view);
+        },
+        DnsAction::Unrecord { name, view } => {
+            let response = client.remove_dns_record(tonic::Request::new(RemoveDnsRecordRequest {
+                name: name.clone(),
+                view: view.clone(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to remove DNS record: {}", response.error_message).into());
+            }
+            println!("✅ Removed DNS record {} from view {}", name, view);
+        },
+        DnsAction::Records => {
+            let response = client.list_dns_records(tonic::Request::new(ListDnsRecordsRequest {})).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to list DNS records: {}", response.error_message).into());
+            }
+            if response.records.is_empty() {
+                println!("No DNS records defined");
+                return Ok(());
+            }
+            println!("{:<32} {:<11} {}", "NAME", "VIEW", "ADDRESSES");
+            for record in response.records {
+                println!("{:<32} {:<11} {}", record.name, record.view, record.addresses.join(", "));
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn format_selector(selector: &HashMap<String, String>) -> String {
+    let mut labels: Vec<String> = selector.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    labels.sort();
+    labels.join(",")
+}
+
+async fn handle_virtual_service_command(
+    action: VirtualServiceAction,
+    client: &mut QuiltServiceClient<Channel>
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        VirtualServiceAction::Create { name, selector } => {
+            let response = client.create_virtual_service(tonic::Request::new(CreateVirtualServiceRequest {
+                name: name.clone(),
+                selector: selector.into_iter().collect(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to create virtual service: {}", response.error_message).into());
+            }
+            let backends = response.service.map(|service| service.backends.len()).unwrap_or(0);
+            println!("✅ Virtual service '{}' created ({} backend(s) running)", name, backends);
+        },
+        VirtualServiceAction::Delete { name } => {
+            let response = client.delete_virtual_service(tonic::Request::new(DeleteVirtualServiceRequest {
+                name: name.clone(),
+            })).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to delete virtual service: {}", response.error_message).into());
+            }
+            println!("✅ Virtual service '{}' deleted", name);
+        },
+        VirtualServiceAction::List => {
+            let response = client.list_virtual_services(tonic::Request::new(ListVirtualServicesRequest {})).await?.into_inner();
+            if !response.success {
+                return Err(format!("Failed to list virtual services: {}", response.error_message).into());
+            }
+            if response.services.is_empty() {
+                println!("No virtual services defined");
+                return Ok(());
+            }
+            println!("{:<24} {:<32} {}", "NAME", "SELECTOR", "BACKENDS");
+            for service in response.services {
+                let backends: Vec<String> = service.backends.iter()
+                    .map(|backend| format!("{} ({})", backend.ip_address, backend.container_id))
+                    .collect();
+                println!("{:<24} {:<32} {}", service.name, format_selector(&service.selector),
+                    if backends.is_empty() { "-".to_string() } else { backends.join(", ") });
+            }
+        },
+    }
+
+    Ok(())
+}
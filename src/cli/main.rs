@@ -16,7 +16,6 @@ mod cli;
 use cli::IccCommands;
 
 // Import utils for CLI diagnostics
-#[path = "../utils/mod.rs"]
 mod utils;
 
 // Import individual utilities instead of full sync module to avoid path conflicts
@@ -33,6 +32,8 @@ use quilt::{
     KillContainerRequest, KillContainerResponse,
     GetContainerByNameRequest,
     CreateVolumeRequest, ListVolumesRequest, RemoveVolumeRequest, InspectVolumeRequest,
+    AttachMountRequest, AttachMountResponse,
+    GrantDeviceAccessRequest, RevokeDeviceAccessRequest,
     ContainerStatus, Mount, MountType,
 };
 
@@ -54,13 +55,13 @@ struct Cli {
 enum Commands {
     /// Create a new container with advanced features
     Create {
-        #[clap(short = 'n', long, help = "Container name (must be unique)")]
+        #[clap(short = 'n', long, help = "Container name (must be unique; generated as adjective_noun if omitted)")]
         name: Option<String>,
         
         #[clap(long, help = "Create as async/long-running container")]
         async_mode: bool,
         
-        #[clap(long, help = "Path to the container image tarball")]
+        #[clap(long, help = "Path to the container image: rootfs tar(.gz) or `docker save` archive, or a registry reference such as alpine:3.19")]
         image_path: String,
         
         #[arg(short, long, action = clap::ArgAction::Append, 
@@ -72,9 +73,12 @@ enum Commands {
                num_args = 0..)]
         setup: Vec<String>,
         
-        #[clap(long, help = "Working directory inside the container")]
+        #[clap(long, help = "Working directory inside the container (default: the image's WORKDIR)")]
         working_directory: Option<String>,
         
+        #[clap(short = 'u', long, help = "Run the main process as user[:group], names or ids (default: the image's USER)")]
+        user: Option<String>,
+        
         // Resource limits
         #[clap(long, help = "Memory limit in megabytes (0 = default)", default_value = "0")]
         memory_limit: i32,
@@ -82,6 +86,21 @@ enum Commands {
         #[clap(long, help = "CPU limit as percentage (0.0 = default)", default_value = "0.0")]
         cpu_limit: f32,
         
+        #[clap(long, help = "CPU percentage above the limit a container may use briefly from quota it left unused, e.g. during startup (at most --cpu-limit; Linux 5.14+)",
+               default_value = "0.0")]
+        cpu_burst: f32,
+        
+        #[clap(long, help = "Throttle and reclaim memory above this many megabytes (memory.high; 0 = none)", default_value = "0")]
+        memory_high: i32,
+        
+        #[clap(long, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-1..),
+               help = "Swap allowed besides memory in megabytes (0 = host default, -1 = no swap)", default_value = "0")]
+        memory_swap: i32,
+        
+        #[clap(long, value_parser = clap::value_parser!(i32).range(0..=100),
+               help = "Swappiness 1-100 (cgroup v1 only; 0 = host default)", default_value = "0")]
+        memory_swappiness: i32,
+        
         // Namespace configuration
         #[clap(long, help = "Enable PID namespace isolation")]
         enable_pid_namespace: bool,
@@ -95,6 +114,24 @@ enum Commands {
         #[clap(long, help = "Enable IPC namespace isolation")]
         enable_ipc_namespace: bool,
         
+        #[clap(long, help = "Enable cgroup namespace isolation (only the container's own cgroup at /sys/fs/cgroup; cgroup v2)")]
+        enable_cgroup_namespace: bool,
+        
+        #[clap(long, conflicts_with_all = &["enable_cgroup_namespace", "privileged"],
+               help = "Enable user namespace isolation (container root is unprivileged on the host; rootfs and volumes are ID-mapped, Linux 5.12+)")]
+        enable_user_namespace: bool,
+        
+        #[clap(long, help = "Enable time namespace isolation (monotonic and boot-time clock offsets, Linux 5.6+)")]
+        enable_time_namespace: bool,
+        
+        #[clap(long, allow_hyphen_values = true, requires = "enable_time_namespace", default_value = "0",
+               help = "Seconds added to CLOCK_MONOTONIC in the time namespace")]
+        monotonic_offset: i64,
+        
+        #[clap(long, allow_hyphen_values = true, requires = "enable_time_namespace", default_value = "0",
+               help = "Seconds added to CLOCK_BOOTTIME (uptime) in the time namespace")]
+        boottime_offset: i64,
+        
         #[clap(long, help = "Disable network namespace isolation")]
         no_network: bool,
         
@@ -114,6 +151,89 @@ enum Commands {
                value_parser = InputValidator::parse_mount)]
         mounts: Vec<utils::validation::VolumeMount>,
         
+        // FUSE support
+        #[clap(long, conflicts_with = "immutable",
+               help = "Expose /dev/fuse so the container can mount FUSE filesystems")]
+        allow_fuse: bool,
+        
+        #[clap(long = "fuse-mount",
+               help = "FUSE filesystem to pre-mount (helper=sshfs,source=user@host:/path,target=/mnt/x[,opt=allow_other]); requires --allow-fuse",
+               num_args = 0..,
+               requires = "allow_fuse",
+               value_parser = InputValidator::parse_fuse_mount)]
+        fuse_mounts: Vec<utils::validation::FuseMount>,
+        
+        // Hardening
+        #[clap(long, help = "Read-only rootfs with tmpfs /tmp, /run and /var/tmp, reduced capabilities and no-new-privileges")]
+        immutable: bool,
+        
+        #[clap(long, conflicts_with = "immutable",
+               help = "Host-level access: keep all capabilities, mount the host's /dev, allow all devices, no seccomp filter, unhardened /proc and /sys")]
+        privileged: bool,
+        
+        // Port publishing
+        #[clap(short = 'p', long = "publish",
+               help = "Publish a container port on the host (format: [host_ip:]host_port:container_port[/tcp|udp])",
+               num_args = 0..,
+               value_parser = InputValidator::parse_port_mapping)]
+        ports: Vec<utils::validation::PortMapping>,
+        
+        #[clap(short = 'l', long = "label", action = clap::ArgAction::Append,
+               help = "Container label in KEY=VALUE format (usable as an event filter)",
+               value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        
+        // Lifecycle hooks
+        #[clap(long = "hook", action = clap::ArgAction::Append,
+               help = "Run a host command or POST a webhook on lifecycle events (format: started,died=COMMAND or all=https://URL); QUILT_* variables describe the container",
+               value_parser = InputValidator::parse_hook)]
+        hooks: Vec<utils::validation::HookSpec>,
+        
+        #[clap(long, help = "Seconds each lifecycle or pre-start hook may run (0 = daemon default)", default_value = "0")]
+        hook_timeout: u32,
+        
+        #[clap(long = "prestart-hook", action = clap::ArgAction::Append,
+               help = "Command run on every start after namespaces and cgroups are set up, before the container command (format: [host:|container:]COMMAND); a failure fails the start",
+               value_parser = InputValidator::parse_prestart_hook)]
+        prestart_hooks: Vec<(String, String)>,
+        
+        #[clap(long, help = "Registered network driver plugin that plumbs the container after bridge setup")]
+        network_plugin: Option<String>,
+        
+        #[clap(long, help = "Also resolve every name below <name>.quilt.local to the container (e.g. api.<name>.quilt.local), for host-based routing")]
+        dns_wildcard: bool,
+        
+        #[clap(long, value_name = "CONTAINER:PORT", help = "Proxy container the container's HTTP(S)_PROXY variables point at, e.g. squid:3128")]
+        egress_proxy: Option<String>,
+        
+        #[clap(long, requires = "egress_proxy", help = "Also redirect connections to ports 80 and 443 to the egress proxy, for clients that ignore the proxy variables")]
+        transparent_proxy: bool,
+        
+        #[clap(long = "ready", action = clap::ArgAction::Append,
+               help = "Gate that must pass before the container is reported Running (tcp:PORT, http:PORT[/PATH], file:PATH or log:REGEX)",
+               value_parser = InputValidator::parse_readiness_gate)]
+        readiness_gates: Vec<(String, String)>,
+        
+        #[clap(long, help = "Seconds the readiness gates may take before the start fails (0 = daemon default)", default_value = "0")]
+        ready_timeout: u32,
+        
+        #[clap(long = "priority", help = "Priority class: critical, normal (default) or best-effort; decides start queue order and who is evicted under pressure")]
+        priority: Option<String>,
+        
+        #[clap(long, allow_hyphen_values = true,
+               help = "Shift the container's wall clock from host time (e.g. +7d, -2h30m, 3600); needs libfaketime on the host and a glibc image")]
+        time_offset: Option<String>,
+        
+        #[clap(long, help = "Zone of the container's local time, e.g. Europe/Berlin, or host for the host's zone; sets TZ and mounts the zone at /etc/localtime")]
+        timezone: Option<String>,
+        
+        #[clap(long, help = "Locale the container's LANG is set to, e.g. en_US.UTF-8")]
+        locale: Option<String>,
+        
+        #[clap(long, value_parser = clap::value_parser!(u32).range(0..=3600), default_value = "0",
+               help = "Seconds between metrics samples of this container, also how old a stored sample GetMetrics serves by default (0 = 30s)")]
+        metrics_interval: u32,
+        
         /// The command and its arguments to run in the container
         #[clap(required = false, num_args = 0.., 
                help = "Command and its arguments (use -- to separate from CLI options)")]
@@ -136,6 +256,27 @@ enum Commands {
         by_name: bool,
     },
     
+    /// Search container logs by text or regex, level and age
+    #[clap(name = "search-logs")]
+    SearchLogs {
+        #[clap(help = "Text to search for (omit to list every line matching the other filters)")]
+        query: Option<String>,
+        #[clap(short = 'c', long, help = "ID or name of the container to search (default: all containers)")]
+        container: Option<String>,
+        #[clap(short = 'n', long, help = "Treat --container as a container name")]
+        by_name: bool,
+        #[clap(short = 'e', long, help = "Treat the query as a regular expression")]
+        regex: bool,
+        #[clap(short = 's', long, help = "Match case-sensitively")]
+        case_sensitive: bool,
+        #[clap(short = 'l', long = "level", help = "Only these levels: debug, info, warn, error, runtime (repeatable)")]
+        levels: Vec<String>,
+        #[clap(long, help = "Only lines newer than this, e.g. 10m or 2h", value_parser = humantime::parse_duration)]
+        since: Option<std::time::Duration>,
+        #[clap(long, default_value = "100", help = "Maximum number of matches (max 10000)")]
+        limit: u32,
+    },
+    
     /// Stop a container gracefully
     Stop { 
         #[clap(help = "ID or name of the container to stop")]
@@ -159,7 +300,7 @@ enum Commands {
     /// Create a production-ready persistent container
     #[clap(name = "create-production")]
     CreateProduction {
-        #[clap(help = "Container image tar.gz file")]
+        #[clap(help = "Container image: rootfs tar(.gz), `docker save` archive or registry reference")]
         image_path: String,
         #[clap(long, help = "Container name/identifier")]
         name: Option<String>,
@@ -175,12 +316,65 @@ enum Commands {
         no_network: bool,
     },
 
+    /// Create a new container from an existing one's settings
+    Clone {
+        #[clap(help = "ID or name of the container to clone")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(long, help = "Name for the clone (generated if omitted)")]
+        name: Option<String>,
+        #[clap(long, help = "Copy the source's rootfs instead of extracting the image again")]
+        rootfs: bool,
+        #[clap(long, help = "Copy the source's named volumes instead of sharing them")]
+        volumes: bool,
+        #[clap(short = 'l', long = "label", action = clap::ArgAction::Append,
+               help = "Label in KEY=VALUE format, merged over the source's labels",
+               value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        #[clap(long, help = "Only record the clone; do not start it")]
+        no_start: bool,
+    },
+
+    /// Clone or remove replicas of a container until the set has N members
+    Scale {
+        #[clap(help = "TEMPLATE=N, where TEMPLATE is a container, one of its replicas, or a virtual service",
+               value_parser = InputValidator::parse_scale_spec)]
+        spec: (String, u32),
+    },
+
+    /// Replace a container with an updated copy once the copy is healthy (blue/green)
+    Deploy {
+        #[clap(help = "ID or name of the container to replace")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(long, help = "New image for the replacement (default: keep the current one)")]
+        image_path: Option<String>,
+        #[arg(short, long, action = clap::ArgAction::Append,
+              help = "Environment variable in KEY=VALUE format, merged over the current ones",
+              value_parser = InputValidator::parse_key_val)]
+        env: Vec<(String, String)>,
+        #[clap(short = 'l', long = "label", action = clap::ArgAction::Append,
+               help = "Label in KEY=VALUE format, merged over the current labels",
+               value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        #[clap(long, help = "Seconds the replacement may take to become healthy (default: the startup limit)")]
+        timeout: Option<u32>,
+        /// New command for the replacement
+        #[clap(required = false, num_args = 0..,
+               help = "New command and its arguments (use -- to separate from CLI options; default: keep the current one)")]
+        command_and_args: Vec<String>,
+    },
+
     /// Start a stopped container
     Start {
         #[clap(help = "ID or name of the container to start")]
         container: String,
         #[clap(short = 'n', long, help = "Treat input as container name")]
         by_name: bool,
+        #[clap(long, help = "Wait until the container is running and its readiness gates passed")]
+        wait: bool,
     },
     
     /// Kill a container immediately
@@ -191,6 +385,31 @@ enum Commands {
         by_name: bool,
     },
     
+    /// Set daemon-side log verbosity for one container (info or debug)
+    #[clap(name = "log-level")]
+    LogLevel {
+        #[clap(help = "ID or name of the container")]
+        container: String,
+        #[clap(help = "Log level: info (default) or debug for network setup and monitor traces")]
+        level: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+    },
+    
+    /// Turn background metrics collection of a container on or off
+    #[clap(name = "metrics-collection")]
+    MetricsCollection {
+        #[clap(help = "ID or name of the container")]
+        container: String,
+        #[clap(long, help = "Stop sampling the container in the background (GetMetrics still collects on demand)")]
+        disable: bool,
+        #[clap(long, conflicts_with = "disable", value_parser = clap::value_parser!(u32).range(1..=3600),
+               help = "Seconds between samples (default: keep the current interval)")]
+        interval: Option<u32>,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+    },
+    
     /// Execute a command in a running container
     Exec {
         #[clap(help = "ID or name of the container")]
@@ -201,8 +420,52 @@ enum Commands {
         command: Vec<String>,
         #[clap(short = 'w', long, help = "Working directory")]
         working_directory: Option<String>,
+        #[clap(long, requires = "working_directory", help = "Create the working directory if it does not exist")]
+        create_workdir: bool,
         #[clap(long, help = "Capture output")]
         capture_output: bool,
+        #[clap(short = 't', long, default_value = "0", help = "Kill the command after this many seconds (0 = daemon default)")]
+        timeout: u32,
+        #[clap(short = 's', long, help = "Stream output as it is produced and exit with the command's exit code")]
+        stream: bool,
+    },
+
+    /// Trace the syscalls a running container makes, optionally writing a seccomp profile
+    Trace {
+        #[clap(help = "ID or name of the running container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(short = 'd', long, help = "Stop after this many seconds (max 300)", default_value = "10")]
+        duration: u32,
+        #[clap(long, help = "Write a seccomp profile allowing only the traced syscalls to this file")]
+        profile: Option<String>,
+        #[clap(long, help = "Number of syscalls to list in the final summary", default_value = "20")]
+        top: usize,
+    },
+
+    /// Attach a mount to a running container without recreating it
+    #[clap(name = "attach-mount")]
+    AttachMount {
+        #[clap(help = "ID or name of the running container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(short = 'v', long = "volume",
+               help = "Mount a volume or host path (format: [name:]source:dest[:options])",
+               conflicts_with = "mount",
+               value_parser = InputValidator::parse_volume)]
+        volume: Option<utils::validation::VolumeMount>,
+        #[clap(long = "mount",
+               help = "Advanced mount syntax (type=bind,source=/host,target=/container,readonly)",
+               value_parser = InputValidator::parse_mount)]
+        mount: Option<utils::validation::VolumeMount>,
+    },
+
+    /// Manage device access for running containers (devices cgroup)
+    Device {
+        #[clap(subcommand)]
+        command: DeviceCommands,
     },
 
     /// Monitor container processes and system state
@@ -217,6 +480,42 @@ enum Commands {
         command: VolumeCommands,
     },
 
+    /// Manage volume driver, network driver and auth provider plugins
+    Plugin {
+        #[clap(subcommand)]
+        command: PluginCommands,
+    },
+
+    /// Pull an image from an OCI/Docker registry into the daemon's image store
+    Pull {
+        #[clap(help = "Image reference, e.g. alpine:3.19, ghcr.io/org/app:v2 or repo@sha256:<digest>")]
+        reference: String,
+        #[clap(long, help = "Pull again even if the image is already in the store")]
+        force: bool,
+    },
+
+    /// Build an image from a Quiltfile into the daemon's image store
+    Build {
+        #[clap(default_value = ".", help = "Build context: the directory COPY sources are read from")]
+        context: String,
+        #[clap(short = 't', long, help = "Tag for the image, e.g. myapp:1.0")]
+        tag: String,
+        #[clap(short = 'f', long, help = "Build file (default: Quiltfile in the context)")]
+        file: Option<String>,
+    },
+
+    /// Images in the daemon's image store, and vulnerability scans of image tarballs
+    Image {
+        #[clap(subcommand)]
+        command: ImageCommands,
+    },
+
+    /// Container networking diagnostics
+    Network {
+        #[clap(subcommand)]
+        command: NetworkCommands,
+    },
+
     /// Cleanup operations and status
     Cleanup {
         #[clap(subcommand)]
@@ -232,6 +531,58 @@ enum Commands {
         #[clap(subcommand)]
         command: ReportCommands,
     },
+
+    /// Show the daemon's API version, RPCs and optional features
+    Capabilities,
+
+    /// Check that the daemon's host meets quilt's kernel, tool, privilege and network requirements
+    Doctor {
+        #[clap(long, help = "Only show checks that warned or failed")]
+        problems: bool,
+    },
+
+    /// Stop running containers lowest priority class first, e.g. before host maintenance
+    Drain {
+        #[clap(long, default_value = "best-effort", help = "Highest priority class to stop: best-effort, normal or critical")]
+        up_to: String,
+    },
+
+    /// Follow a container create or start until it completes
+    Progress {
+        #[clap(help = "Operation ID printed by create or start")]
+        operation_id: String,
+    },
+
+    /// Daemon and host maintenance
+    System {
+        #[clap(subcommand)]
+        command: SystemCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SystemCommands {
+    /// Collect a tar.gz diagnostic bundle (status, logs, network, iptables, cgroups, daemon logs) for bug reports
+    Diagnose {
+        #[clap(long, help = "Container to include (ID or name); host-wide state only if omitted")]
+        container: Option<String>,
+        #[clap(short = 'n', long, help = "Treat --container as a container name")]
+        by_name: bool,
+        #[clap(short = 'o', long, help = "Output file (default: quilt-diagnostics-<timestamp>.tar.gz)")]
+        output: Option<String>,
+        #[clap(long, default_value = "0", help = "Stored log rows to include per container (0 = daemon default)")]
+        log_lines: u32,
+    },
+    /// Find leaked veths, cgroups, rootfs dirs and dead-process rows and schedule their cleanup
+    Reconcile {
+        #[clap(long, help = "Only report what would be cleaned up")]
+        dry_run: bool,
+    },
+    /// List every host port the daemon has opened (published ports, DNS, mDNS, gRPC)
+    Ports {
+        #[clap(long, help = "Only show ports whose rules or listener are not live")]
+        inactive: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -269,6 +620,83 @@ enum MonitorCommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum DeviceCommands {
+    /// Grant a running container access to a device
+    Grant {
+        #[clap(help = "ID or name of the running container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(help = "Device rule, e.g. 'c 10:200 rwm' or 'b 8:* r'")]
+        rule: String,
+    },
+    /// Revoke a running container's access to a device
+    Revoke {
+        #[clap(help = "ID or name of the running container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(help = "Device rule, e.g. 'c 10:200 rwm' or 'b 8:* r'")]
+        rule: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum NetworkCommands {
+    /// Run on-demand network diagnostics (bridge, connectivity, readiness, namespaces)
+    Doctor {
+        #[clap(help = "Limit diagnostics to one container (ID or name)")]
+        container: Option<String>,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(long, help = "Show every check, not only failures")]
+        detailed: bool,
+    },
+    /// Capture packets on a container's interface and write them as pcap
+    Capture {
+        #[clap(help = "ID or name of the running container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(short = 'o', long, help = "Output pcap file ('-' for stdout)")]
+        output: String,
+        #[clap(short = 'd', long, help = "Stop after this many seconds (max 300)", default_value = "10")]
+        duration: u32,
+        #[clap(short = 'c', long, help = "Stop after this many packets (max 100000)", default_value = "1000")]
+        count: u32,
+        #[clap(long, help = "Bytes to capture per packet (0 = full packets)", default_value = "0")]
+        snaplen: u32,
+        #[clap(help = "Optional BPF filter expression, e.g. 'tcp port 80'")]
+        filter: Vec<String>,
+    },
+    /// Show or replace a container's network policy (allow/deny rules enforced with cgroup eBPF)
+    Policy {
+        #[clap(help = "ID or name of the container")]
+        container: String,
+        #[clap(short = 'n', long, help = "Treat input as container name")]
+        by_name: bool,
+        #[clap(long, help = "Default for inbound traffic: allow or deny")]
+        ingress: Option<String>,
+        #[clap(long, help = "Default for outbound traffic: allow or deny")]
+        egress: Option<String>,
+        #[clap(long = "allow-from", action = clap::ArgAction::Append,
+               help = "Allow inbound traffic from a container, IPv4 address or CIDR")]
+        allow_from: Vec<String>,
+        #[clap(long = "deny-from", action = clap::ArgAction::Append,
+               help = "Deny inbound traffic from a container, IPv4 address or CIDR")]
+        deny_from: Vec<String>,
+        #[clap(long = "allow-to", action = clap::ArgAction::Append,
+               help = "Allow outbound traffic to a container, IPv4 address or CIDR")]
+        allow_to: Vec<String>,
+        #[clap(long = "deny-to", action = clap::ArgAction::Append,
+               help = "Deny outbound traffic to a container, IPv4 address or CIDR")]
+        deny_to: Vec<String>,
+        #[clap(long, help = "Remove the policy", conflicts_with_all = ["ingress", "egress", "allow_from", "deny_from", "allow_to", "deny_to"])]
+        clear: bool,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum VolumeCommands {
     /// Create a new named volume
@@ -301,6 +729,73 @@ enum VolumeCommands {
     Prune,
 }
 
+#[derive(Subcommand, Debug)]
+enum PluginCommands {
+    /// Register a plugin; the daemon activates it to learn what it implements
+    Register {
+        #[clap(help = "Plugin name (used as volume driver / network plugin name)")]
+        name: String,
+        #[clap(help = "Endpoint: unix:///path/to.sock or http(s)://host:port")]
+        endpoint: String,
+    },
+    /// List registered plugins
+    List {
+        #[clap(long, help = "Only plugins implementing this: volume_driver, network_driver or auth_provider")]
+        capability: Option<String>,
+    },
+    /// Unregister a plugin
+    Remove {
+        #[clap(help = "Plugin name")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImageCommands {
+    /// Scan an image with the daemon's configured scanner (trivy or grype)
+    Scan {
+        #[clap(help = "Path to the image tarball on the daemon host")]
+        image_path: String,
+        #[clap(long, help = "Rescan even if the image is unchanged since its last scan")]
+        force: bool,
+    },
+    /// List stored image scans
+    Scans {
+        #[clap(long, help = "Show each finding, not just counts")]
+        findings: bool,
+    },
+    /// List images in the daemon's image store
+    #[clap(alias = "ls")]
+    List,
+    /// Show a stored image's layers, config, source files and containers
+    Inspect {
+        #[clap(help = "Image tag, digest or digest prefix")]
+        reference: String,
+    },
+    /// Remove a stored image no container was created from
+    #[clap(alias = "rm")]
+    Remove {
+        #[clap(help = "Image tag, digest or digest prefix")]
+        reference: String,
+    },
+    /// Import a docker-save or OCI archive (e.g. from `docker save` or `podman save`)
+    Import {
+        #[clap(help = "Archive path on the daemon host")]
+        path: String,
+    },
+    /// Export a stored image as an archive `docker load` and `podman load` accept
+    Export {
+        #[clap(help = "Image tag, digest or digest prefix")]
+        reference: String,
+        #[clap(short = 'o', long, help = "Archive path on the daemon host")]
+        output: String,
+        #[clap(long, help = "Archive layout: docker or oci", default_value = "docker")]
+        format: String,
+        #[clap(short = 't', long, help = "Tag to write instead of the image's tags")]
+        tag: Option<String>,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum ReportCommands {
     /// Generate container lifecycle report with enhanced timestamps
@@ -347,6 +842,10 @@ enum CleanupCommands {
         container: String,
         #[clap(short = 'n', long, help = "Treat input as container name")]
         by_name: bool,
+        #[clap(long, help = "Only report what would be removed")]
+        dry_run: bool,
+        #[clap(long = "type", value_delimiter = ',', help = "Resource types to clean: rootfs, network, cgroup, mounts, volumes, metrics, logs (default: daemon policy)")]
+        types: Vec<String>,
     },
 }
 
@@ -377,6 +876,16 @@ async fn resolve_container_id(
     }
 }
 
+/// Attach the token from `QUILT_TOKEN`, which lets the request use reserved container names
+fn with_token<T>(mut request: tonic::Request<T>) -> tonic::Request<T> {
+    use utils::name_policy::{CLIENT_TOKEN_ENV, TOKEN_HEADER};
+
+    if let Some(value) = std::env::var(CLIENT_TOKEN_ENV).ok().and_then(|token| token.trim().parse().ok()) {
+        request.metadata_mut().insert(TOKEN_HEADER, value);
+    }
+    request
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logger
@@ -441,7 +950,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let basic_cmds = ["ls", "pwd", "whoami"];
             for cmd in basic_cmds {
                 if CommandExecutor::is_command_available(cmd) {
-                    if let Ok(result) = CommandExecutor::execute_shell(&format!("{} --version 2>/dev/null || echo 'available'", cmd)) {
+                    if let Ok(result) = CommandExecutor::run(cmd, ["--version"]) {
                         if result.success {
                             eprintln!("   {} command available - stdout: {} chars, stderr: {} chars, exit: {:?}", 
                                 cmd, result.stdout.len(), result.stderr.len(), result.exit_code);
@@ -581,16 +1090,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             env, 
             setup,
             working_directory,
+            user,
             memory_limit,
             cpu_limit,
+            cpu_burst,
+            memory_high,
+            memory_swap,
+            memory_swappiness,
+            metrics_interval,
             enable_pid_namespace,
             enable_mount_namespace,
             enable_uts_namespace,
             enable_ipc_namespace,
+            enable_cgroup_namespace,
+            enable_user_namespace,
+            enable_time_namespace,
+            monotonic_offset,
+            boottime_offset,
             no_network,
             enable_all_namespaces,
             volumes,
             mounts,
+            allow_fuse,
+            fuse_mounts,
+            immutable,
+            privileged,
+            ports,
+            labels,
+            hooks,
+            hook_timeout,
+            prestart_hooks,
+            network_plugin,
+            dns_wildcard,
+            egress_proxy,
+            transparent_proxy,
+            readiness_gates,
+            ready_timeout,
+            priority,
+            time_offset,
+            timezone,
+            locale,
             command_and_args 
         } => {
             println!("🚀 Creating container...");
@@ -606,15 +1145,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let environment: HashMap<String, String> = env.into_iter().collect();
             
             // If enable_all_namespaces is true, enable all namespace options
-            let (pid_ns, mount_ns, uts_ns, ipc_ns, net_ns) = if enable_all_namespaces {
-                (true, true, true, true, true)
+            let (pid_ns, mount_ns, uts_ns, ipc_ns, net_ns, cgroup_ns) = if enable_all_namespaces {
+                (true, true, true, true, true, true)
             } else {
                 (
                     enable_pid_namespace,
                     enable_mount_namespace, 
                     enable_uts_namespace,
                     enable_ipc_namespace,
-                    !no_network  // Fixed: Use no_network flag (default networking enabled)
+                    !no_network,  // Fixed: Use no_network flag (default networking enabled)
+                    enable_cgroup_namespace,
                 )
             };
             
@@ -647,14 +1187,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 });
             }
 
-            let request = tonic::Request::new(CreateContainerRequest {
+            let request = with_token(tonic::Request::new(CreateContainerRequest {
                 image_path,
                 command: final_command,
                 environment,
                 working_directory: working_directory.unwrap_or_default(),
+                user: user.unwrap_or_default(),
                 setup_commands: setup,
                 memory_limit_mb: memory_limit,
                 cpu_limit_percent: cpu_limit,
+                cpu_burst_percent: cpu_burst,
+                memory_high_mb: memory_high,
+                memory_swap_mb: memory_swap,
+                memory_swappiness,
                 enable_pid_namespace: pid_ns,
                 enable_mount_namespace: mount_ns,
                 enable_uts_namespace: uts_ns,
@@ -663,7 +1208,52 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 name: name.unwrap_or_default(),
                 async_mode,
                 mounts: proto_mounts,
-            });
+                allow_fuse,
+                fuse_mounts: fuse_mounts.into_iter().map(|m| quilt::FuseMount {
+                    helper: m.helper,
+                    source: m.source,
+                    target: m.target,
+                    options: m.options,
+                }).collect(),
+                immutable,
+                ports: ports.into_iter().map(|p| quilt::PortMapping {
+                    host_ip: p.host_ip,
+                    host_port: p.host_port as u32,
+                    container_port: p.container_port as u32,
+                    protocol: p.protocol,
+                }).collect(),
+                labels: labels.into_iter().collect(),
+                hooks: hooks.into_iter().map(|hook| quilt::LifecycleHook {
+                    events: hook.events,
+                    command: hook.command,
+                    url: hook.url,
+                    timeout_seconds: hook_timeout,
+                }).collect(),
+                prestart_hooks: prestart_hooks.into_iter().map(|(namespace, command)| quilt::PrestartHook {
+                    command,
+                    namespace,
+                    timeout_seconds: hook_timeout,
+                }).collect(),
+                network_plugin: network_plugin.unwrap_or_default(),
+                dns_wildcard,
+                egress_proxy: egress_proxy.unwrap_or_default(),
+                egress_proxy_transparent: transparent_proxy,
+                priority_class: priority.unwrap_or_default(),
+                readiness_gates: readiness_gates.into_iter()
+                    .map(|(kind, target)| quilt::ReadinessGate { kind, target })
+                    .collect(),
+                readiness_timeout_seconds: ready_timeout,
+                time_offset: time_offset.unwrap_or_default(),
+                timezone: timezone.unwrap_or_default(),
+                locale: locale.unwrap_or_default(),
+                enable_time_namespace,
+                monotonic_offset_seconds: monotonic_offset,
+                boottime_offset_seconds: boottime_offset,
+                enable_cgroup_namespace: cgroup_ns,
+                enable_user_namespace,
+                privileged,
+                metrics_interval_seconds: metrics_interval,
+            }));
 
             match client.create_container(request).await {
                 Ok(response) => {
@@ -671,6 +1261,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     if res.success {
                         println!("✅ Container created successfully!");
                         println!("   Container ID: {}", res.container_id);
+                        if !res.name.is_empty() {
+                            println!("   Name: {}", res.name);
+                        }
+                        if !res.operation_id.is_empty() {
+                            println!("   Operation: {} (follow with `progress`)", res.operation_id);
+                        }
                     } else {
                         println!("❌ Failed to create container: {}", res.error_message);
                         std::process::exit(1);
@@ -702,6 +1298,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         1 => ContainerStatus::Running,
                         2 => ContainerStatus::Exited,
                         3 => ContainerStatus::Failed,
+                        4 => ContainerStatus::Stopping,
+                        5 => ContainerStatus::Removing,
+                        6 => ContainerStatus::Restarting,
+                        7 => ContainerStatus::Paused,
                         _ => ContainerStatus::Failed,
                     };
                     let status_str = match status_enum {
@@ -709,6 +1309,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         ContainerStatus::Running => "RUNNING",
                         ContainerStatus::Exited => "EXITED",
                         ContainerStatus::Failed => "FAILED",
+                        ContainerStatus::Stopping => "STOPPING",
+                        ContainerStatus::Removing => "REMOVING",
+                        ContainerStatus::Restarting => "RESTARTING",
+                        ContainerStatus::Paused => "PAUSED",
                     };
                     
                     // Use enhanced timestamp formatting with ProcessUtils
@@ -750,6 +1354,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("   ⏱️  Uptime: {}", uptime_formatted);
                     }
                     
+                    if !res.setup_steps.is_empty() {
+                        println!("\n🔧 Setup Steps:");
+                        for step in &res.setup_steps {
+                            let icon = match step.status.as_str() {
+                                "succeeded" => "✅",
+                                "failed" => "❌",
+                                _ => "⏳",
+                            };
+                            println!("   {} {}. {} ({}, {}ms)", icon, step.index + 1, step.command, step.status, step.duration_ms);
+                            if step.status == "failed" {
+                                for line in step.output.lines() {
+                                    println!("      {}", line);
+                                }
+                            }
+                        }
+                    }
+                    
                     // Add detailed filesystem inspection for rootfs
                     if !res.rootfs_path.is_empty() && utils::filesystem::FileSystemUtils::exists(&res.rootfs_path) {
                         println!("\n📁 Rootfs Details:");
@@ -848,7 +1469,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             // Convert timestamp to human readable format
                             let formatted_time = utils::process::ProcessUtils::format_timestamp(timestamp);
                             
-                            println!("[{}] {}", formatted_time, message);
+                            if log_entry.actor.is_empty() || log_entry.actor == "daemon" {
+                                println!("[{}] {}", formatted_time, message);
+                            } else {
+                                println!("[{}] {} (by {})", formatted_time, message, log_entry.actor);
+                            }
                         }
                         ConsoleLogger::separator();
                     }
@@ -860,6 +1485,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
+        Commands::SearchLogs { query, container, by_name, regex, case_sensitive, levels, since, limit } => {
+            let container_id = match container {
+                Some(container) => resolve_container_id(&mut client, &container, by_name).await?,
+                None => String::new(),
+            };
+            let since = since.map(|age| {
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                now.saturating_sub(age.as_secs())
+            }).unwrap_or(0);
+            
+            let request = tonic::Request::new(quilt::SearchContainerLogsRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                query: query.unwrap_or_default(),
+                regex,
+                case_sensitive,
+                levels,
+                since,
+                until: 0,
+                limit,
+            });
+            
+            match client.search_container_logs(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.matches.is_empty() {
+                        println!("📝 No matching log lines");
+                    }
+                    for log_match in &res.matches {
+                        let time = utils::process::ProcessUtils::format_timestamp(log_match.timestamp);
+                        // Container output is labelled by stream (stdout/stderr/system) rather than "runtime"
+                        let label = if log_match.level == "runtime" { &log_match.stream } else { &log_match.level };
+                        // The container column is redundant when searching a single container
+                        if container_id.is_empty() {
+                            println!("[{}] {} {:<7} {}", time, log_match.container_id, label.to_uppercase(), log_match.message);
+                        } else {
+                            println!("[{}] {:<7} {}", time, label.to_uppercase(), log_match.message);
+                        }
+                    }
+                    if res.truncated {
+                        println!("… more lines matched; narrow the search or raise --limit");
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error searching logs: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
         Commands::Stop { container, by_name, timeout } => {
             let container_id = resolve_container_id(&mut client, &container, by_name).await?;
             println!("🛑 Stopping container {}...", container_id);
@@ -931,6 +1606,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 setup_commands: setup,
                 memory_limit_mb: if memory > 0 { memory as i32 } else { 512 },
                 cpu_limit_percent: if cpu > 0.0 { cpu as f32 } else { 50.0 },
+                cpu_burst_percent: 0.0,
+                memory_high_mb: 0,
+                memory_swap_mb: 0,
+                memory_swappiness: 0,
                 enable_network_namespace: !no_network,
                 enable_pid_namespace: true,
                 enable_mount_namespace: true,
@@ -939,6 +1618,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 name: name.unwrap_or_default(),
                 async_mode: true, // Production containers are async by default
                 mounts: vec![],
+                allow_fuse: false,
+                fuse_mounts: vec![],
+                immutable: false,
+                ports: vec![],
+                labels: HashMap::new(),
+                hooks: vec![],
+                prestart_hooks: vec![],
+                network_plugin: String::new(),
+                priority_class: String::new(),
+                readiness_gates: vec![],
+                readiness_timeout_seconds: 0,
+                time_offset: String::new(),
+                enable_time_namespace: false,
+                monotonic_offset_seconds: 0,
+                boottime_offset_seconds: 0,
+                enable_cgroup_namespace: false,
+                enable_user_namespace: false,
+                privileged: false,
+                metrics_interval_seconds: 0,
+                user: String::new(),
+                dns_wildcard: false,
+                egress_proxy: String::new(),
+                egress_proxy_transparent: false,
+                timezone: String::new(),
+                locale: String::new(),
             };
 
             match client.create_container(tonic::Request::new(create_request)).await {
@@ -952,8 +1656,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("   Event-driven readiness: enabled");
                         println!("   Container automatically started with PID verification");
                         
-                        if let Some(ref name) = container_name {
-                            println!("   Custom name: {}", name);
+                        if container_name.is_some() {
+                            println!("   Custom name: {}", res.name);
+                        } else if !res.name.is_empty() {
+                            println!("   Name: {}", res.name);
                         }
                     } else {
                         eprintln!("❌ Failed to create production container: {}", res.error_message);
@@ -967,13 +1673,97 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
-        Commands::Start { container, by_name } => {
+        Commands::Clone { container, by_name, name, rootfs, volumes, labels, no_start } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            println!("🐑 Cloning container {}...", container_id);
+            
+            let request = with_token(tonic::Request::new(quilt::CloneContainerRequest {
+                container_id,
+                container_name: String::new(),
+                name: name.unwrap_or_default(),
+                snapshot_rootfs: rootfs,
+                snapshot_volumes: volumes,
+                labels: labels.into_iter().collect(),
+                start: !no_start,
+            }));
+            
+            match client.clone_container(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    println!("✅ Clone created with ID: {}", res.container_id);
+                    if !res.name.is_empty() {
+                        println!("   Name: {}", res.name);
+                    }
+                    if !no_start {
+                        println!("   Starting in the background; check with 'quilt status {}'", res.container_id);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error cloning container: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Deploy { container, by_name, image_path, env, labels, timeout, command_and_args } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            println!("🚢 Deploying a replacement for {}...", container);
+
+            let request = with_token(tonic::Request::new(quilt::ReplaceContainerRequest {
+                container_id,
+                container_name: String::new(),
+                image_path: image_path.unwrap_or_default(),
+                command: command_and_args,
+                environment: env.into_iter().collect(),
+                labels: labels.into_iter().collect(),
+                timeout_seconds: timeout.unwrap_or(0),
+            }));
+
+            match client.replace_container(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    println!("✅ {} now runs as {}", res.name, res.container_id);
+                    println!("   Replaced and removed {}", res.old_container_id);
+                }
+                Err(e) => {
+                    eprintln!("❌ Deploy failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Scale { spec: (target, replicas) } => {
+            let request = with_token(tonic::Request::new(quilt::ScaleContainerRequest {
+                target: target.clone(),
+                replicas,
+            }));
+
+            match client.scale_container(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    println!("✅ Scaled {} to {} replica(s)", target, res.replicas.len());
+                    for replica_id in &res.created {
+                        println!("   + {} (starting in the background)", replica_id);
+                    }
+                    for replica_id in &res.removed {
+                        println!("   - {}", replica_id);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error scaling {}: {}", target, e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Start { container, by_name, wait } => {
             let container_id = resolve_container_id(&mut client, &container, by_name).await?;
             println!("▶️  Starting container {}...", container_id);
             
             let request = tonic::Request::new(StartContainerRequest {
                 container_id: container_id.clone(),
                 container_name: String::new(),
+                wait_ready: wait,
             });
             
             match client.start_container(request).await {
@@ -984,6 +1774,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         if res.pid > 0 {
                             println!("   Process ID: {}", res.pid);
                         }
+                        for gate in &res.readiness_gates {
+                            println!("   Ready: {}", gate);
+                        }
+                        if !wait && !res.operation_id.is_empty() {
+                            println!("   Operation: {} (follow with `progress`)", res.operation_id);
+                        }
                     } else {
                         println!("❌ Failed to start container: {}", res.error_message);
                         std::process::exit(1);
@@ -1022,21 +1818,142 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
-        Commands::Exec { container, by_name, command, working_directory, capture_output } => {
+        Commands::LogLevel { container, level, by_name } => {
             let container_id = resolve_container_id(&mut client, &container, by_name).await?;
-            println!("🔧 Executing command in container {}...", container_id);
             
-            // Check if the command is a local script file
-            let copy_script = command.len() == 1 && std::path::Path::new(&command[0]).exists();
-            
-            let request = tonic::Request::new(ExecContainerRequest {
+            let request = tonic::Request::new(quilt::SetContainerLogLevelRequest {
                 container_id: container_id.clone(),
                 container_name: String::new(),
-                command,
-                working_directory: working_directory.unwrap_or_default(),
-                environment: HashMap::new(),
-                capture_output,
+                level,
+            });
+            
+            match client.set_container_log_level(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Daemon log level for {} set (was {})", container_id, res.previous_level);
+                    } else {
+                        println!("❌ Failed to set log level: {}", res.error_message);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error setting log level: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::MetricsCollection { container, disable, interval, by_name } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            
+            let request = tonic::Request::new(quilt::SetMetricsCollectionRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                enabled: !disable,
+                interval_seconds: interval.unwrap_or(0),
+            });
+            
+            match client.set_metrics_collection(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if !res.success {
+                        println!("❌ Failed to set metrics collection: {}", res.error_message);
+                        std::process::exit(1);
+                    }
+                    if disable {
+                        println!("✅ Background metrics collection off for {}", container_id);
+                    } else {
+                        println!("✅ Collecting metrics of {} every {}s", container_id, res.interval_seconds);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error setting metrics collection: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Exec { container, by_name, command, working_directory, create_workdir, mut capture_output, timeout, stream } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            
+            if stream {
+                use std::io::Write;
+                
+                let request = tonic::Request::new(ExecContainerRequest {
+                    container_id: container_id.clone(),
+                    container_name: String::new(),
+                    command: command.clone(),
+                    working_directory: working_directory.clone().unwrap_or_default(),
+                    environment: HashMap::new(),
+                    capture_output: true,
+                    copy_script: false,
+                    timeout_seconds: timeout,
+                    create_working_directory: create_workdir,
+                });
+                
+                let mut output = match client.exec_container_stream(request).await {
+                    Ok(response) => Some(response.into_inner()),
+                    // Older daemons lack streaming exec; run it buffered instead
+                    Err(e) if e.code() == tonic::Code::Unimplemented => {
+                        eprintln!("⚠️  Daemon does not support streaming exec; output will be shown when the command finishes");
+                        capture_output = true;
+                        None
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Error executing command: {}", e.message());
+                        std::process::exit(1);
+                    }
+                };
+                
+                // Pass the command's output straight through so it can be piped like a local process
+                while let Some(output) = output.as_mut() {
+                    match output.message().await {
+                        Ok(Some(chunk)) => {
+                            if !chunk.stdout.is_empty() {
+                                let mut stdout = std::io::stdout();
+                                stdout.write_all(&chunk.stdout)?;
+                                stdout.flush()?;
+                            }
+                            if !chunk.stderr.is_empty() {
+                                let mut stderr = std::io::stderr();
+                                stderr.write_all(&chunk.stderr)?;
+                                stderr.flush()?;
+                            }
+                            if chunk.done {
+                                if !chunk.error_message.is_empty() {
+                                    eprintln!("❌ Command execution failed: {}", chunk.error_message);
+                                }
+                                std::process::exit(chunk.exit_code);
+                            }
+                        }
+                        Ok(None) => {
+                            eprintln!("❌ Exec stream ended without an exit code");
+                            std::process::exit(1);
+                        }
+                        Err(e) => {
+                            eprintln!("❌ Exec stream failed: {}", e.message());
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            }
+            
+            println!("🔧 Executing command in container {}...", container_id);
+            
+            // Check if the command is a local script file
+            let copy_script = command.len() == 1 && std::path::Path::new(&command[0]).exists();
+            
+            let request = tonic::Request::new(ExecContainerRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                command,
+                working_directory: working_directory.unwrap_or_default(),
+                environment: HashMap::new(),
+                capture_output,
                 copy_script,
+                timeout_seconds: timeout,
+                create_working_directory: create_workdir,
             });
             
             match client.exec_container(request).await {
@@ -1044,6 +1961,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let res: ExecContainerResponse = response.into_inner();
                     if res.success {
                         println!("✅ Command executed successfully (exit code: {})", res.exit_code);
+                        if let Some(usage) = &res.usage {
+                            println!("📊 Used {:.1}ms CPU, {:.1}MB peak memory",
+                                usage.cpu_usage_usec as f64 / 1000.0, usage.memory_peak_bytes as f64 / (1024.0 * 1024.0));
+                        }
                         if capture_output {
                             if !res.stdout.is_empty() {
                                 println!("\n📤 Standard Output:");
@@ -1070,6 +1991,116 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
+        Commands::Trace { container, by_name, duration, profile, top } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            println!("🔎 Tracing syscalls of container {} for {}s...", container_id, duration);
+
+            let request = tonic::Request::new(quilt::TraceSyscallsRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                duration_seconds: duration,
+                summary_interval_ms: 0,
+            });
+            let mut stream = match client.trace_syscalls(request).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    eprintln!("❌ Error starting trace: {}", e.message());
+                    std::process::exit(1);
+                }
+            };
+
+            while let Some(update) = stream.message().await? {
+                if !update.error_message.is_empty() {
+                    eprintln!("❌ Trace failed: {}", update.error_message);
+                    std::process::exit(1);
+                }
+                for name in &update.new_syscalls {
+                    println!("  + {}", name);
+                }
+                if !update.done {
+                    continue;
+                }
+
+                println!("\n✅ {} syscalls ({} distinct) from {} threads in {:.1}s via {}",
+                    update.total_syscalls, update.syscalls.len(), update.threads_traced,
+                    update.elapsed_ms as f64 / 1000.0, update.method);
+                println!("{:<24} {:>12} {:>10}", "SYSCALL", "CALLS", "ERRORS");
+                for syscall in update.syscalls.iter().take(top) {
+                    println!("{:<24} {:>12} {:>10}", syscall.name, syscall.count, syscall.errors);
+                }
+                if let Some(path) = &profile {
+                    let mut names: Vec<&str> = update.syscalls.iter().map(|syscall| syscall.name.as_str()).collect();
+                    names.sort_unstable();
+                    // Docker/OCI seccomp format; everything not seen fails with EPERM
+                    let seccomp = serde_json::json!({
+                        "defaultAction": "SCMP_ACT_ERRNO",
+                        "architectures": [update.arch],
+                        "syscalls": [{ "names": names, "action": "SCMP_ACT_ALLOW" }],
+                    });
+                    std::fs::write(path, serde_json::to_string_pretty(&seccomp)?)?;
+                    println!("📄 Seccomp profile with {} syscalls written to {}", names.len(), path);
+                }
+                break;
+            }
+        }
+
+        Commands::AttachMount { container, by_name, volume, mount } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+
+            let mount = match volume.or(mount) {
+                Some(mount) => mount,
+                None => {
+                    eprintln!("❌ Error: Either --volume or --mount is required");
+                    std::process::exit(1);
+                }
+            };
+
+            if let Err(e) = utils::security::SecurityValidator::validate_mount(&mount) {
+                eprintln!("❌ Error: Mount validation failed: {}", e);
+                std::process::exit(1);
+            }
+
+            println!("📎 Attaching {} -> {} to container {}...", mount.source, mount.target, container_id);
+
+            let proto_mount_type = match mount.mount_type {
+                utils::validation::MountType::Bind => MountType::Bind as i32,
+                utils::validation::MountType::Volume => MountType::Volume as i32,
+                utils::validation::MountType::Tmpfs => MountType::Tmpfs as i32,
+            };
+
+            let request = tonic::Request::new(AttachMountRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                mount: Some(Mount {
+                    source: mount.source,
+                    target: mount.target.clone(),
+                    r#type: proto_mount_type,
+                    readonly: mount.readonly,
+                    options: mount.options,
+                }),
+            });
+
+            match client.attach_mount(request).await {
+                Ok(response) => {
+                    let res: AttachMountResponse = response.into_inner();
+                    if res.success {
+                        println!("✅ Mounted {} in container {}", mount.target, container_id);
+                    } else {
+                        println!("❌ Failed to attach mount: {}", res.error_message);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error attaching mount: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Device { command } => {
+            handle_device_command(command, client).await?
+        }
+
         Commands::Monitor { command } => {
             handle_monitor_command(command, client).await?
         }
@@ -1078,6 +2109,69 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             handle_volume_command(command, client).await?
         }
 
+        Commands::Plugin { command } => {
+            handle_plugin_command(command, client).await?
+        }
+
+        Commands::Pull { reference, force } => {
+            println!("📥 Pulling {}", reference);
+            let request = tonic::Request::new(quilt::PullImageRequest { reference, force });
+            match client.pull_image(request).await {
+                Ok(response) => {
+                    let image = response.into_inner();
+                    if image.cached {
+                        println!("✅ {} is already in the image store", image.reference);
+                    } else {
+                        println!("✅ Pulled {} ({} layers, {} bytes)", image.reference, image.layers, image.size_bytes);
+                    }
+                    println!("   Digest: {}", image.digest);
+                    println!("   Archive: {}", image.image_path);
+                }
+                Err(e) => {
+                    println!("❌ Pull failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Build { context, tag, file } => {
+            // The daemon reads COPY sources itself, so it gets an absolute context
+            let context_dir = std::fs::canonicalize(&context)
+                .map_err(|e| format!("Build context {}: {}", context, e))?;
+            let file = file.map(std::path::PathBuf::from).unwrap_or_else(|| context_dir.join("Quiltfile"));
+            let quiltfile = std::fs::read_to_string(&file)
+                .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+            println!("🏗️  Building {} from {}", tag, file.display());
+            let request = tonic::Request::new(quilt::BuildImageRequest {
+                quiltfile,
+                context_dir: context_dir.display().to_string(),
+                tag,
+            });
+            match client.build_image(request).await {
+                Ok(response) => {
+                    let image = response.into_inner();
+                    for line in &image.log {
+                        println!("   {}", line);
+                    }
+                    println!("✅ Built {} ({} steps)", image.tag, image.steps);
+                    println!("   Digest: {}", image.digest);
+                    println!("   Archive: {}", image.image_path);
+                }
+                Err(e) => {
+                    println!("❌ Build failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Image { command } => {
+            handle_image_command(command, client).await?
+        }
+
+        Commands::Network { command } => {
+            handle_network_command(command, client).await?
+        }
+
         Commands::Cleanup { command } => {
             handle_cleanup_command(command, client).await?
         }
@@ -1089,8 +2183,88 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Report { command } => {
             handle_report_command(command, client).await?
         }
+
+        Commands::Capabilities => {
+            show_api_capabilities(client).await?
+        }
+
+        Commands::Doctor { problems } => {
+            check_host_requirements(client, problems).await?
+        }
+
+        Commands::Drain { up_to } => {
+            drain_containers(client, up_to).await?
+        }
+
+        Commands::Progress { operation_id } => {
+            follow_operation(client, operation_id).await?
+        }
+
+        Commands::System { command } => {
+            handle_system_command(command, client).await?
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask the daemon what it supports, declaring this CLI's API version.
+/// Returns None for daemons that predate capability negotiation.
+async fn request_api_capabilities(
+    client: &mut QuiltServiceClient<Channel>,
+) -> Result<Option<quilt::GetApiCapabilitiesResponse>, tonic::Status> {
+    use utils::api_version::{API_VERSION, API_VERSION_HEADER};
+
+    let mut request = tonic::Request::new(quilt::GetApiCapabilitiesRequest {
+        client_api_version: API_VERSION,
+    });
+    request.metadata_mut().insert(API_VERSION_HEADER, API_VERSION.into());
+
+    match client.get_api_capabilities(request).await {
+        Ok(response) => Ok(Some(response.into_inner())),
+        Err(status) if status.code() == tonic::Code::Unimplemented => Ok(None),
+        Err(status) => Err(status),
+    }
+}
+
+async fn show_api_capabilities(
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use utils::api_version::API_VERSION;
+
+    let capabilities = match request_api_capabilities(&mut client).await {
+        Ok(Some(capabilities)) => capabilities,
+        Ok(None) => {
+            println!("⚠️  Daemon predates API capability negotiation (CLI API version {})", API_VERSION);
+            println!("   Newer commands such as 'exec --stream' fall back to their older equivalents");
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to get API capabilities: {}", e.message());
+            std::process::exit(1);
+        }
+    };
+
+    println!("🔢 API version: daemon {} (supports clients >= {}), CLI {}",
+        capabilities.api_version, capabilities.min_supported_api_version, API_VERSION);
+    println!("   Server version: {}", capabilities.server_version);
+    if capabilities.api_version < API_VERSION {
+        println!("⚠️  Daemon is older than this CLI; commands it does not implement will be skipped or fall back");
     }
 
+    let mut features: Vec<_> = capabilities.features.into_iter().collect();
+    features.sort();
+    println!("\n🧩 Features:");
+    for (feature, available) in features {
+        println!("   {} {}", if available { "✅" } else { "➖" }, feature);
+    }
+
+    let mut rpcs = capabilities.rpcs;
+    rpcs.sort();
+    println!("\n📡 RPCs ({}):", rpcs.len());
+    for rpc in rpcs {
+        println!("   {}", rpc);
+    }
     Ok(())
 }
 
@@ -1119,6 +2293,9 @@ async fn handle_monitor_command(
                                 if monitor.last_check > 0 {
                                     println!("     Last check: {}", ProcessUtils::format_timestamp(monitor.last_check));
                                 }
+                                if monitor.consecutive_failures > 0 {
+                                    println!("     Failing: {} checks in a row ({})", monitor.consecutive_failures, monitor.error_message);
+                                }
                             }
                         }
                     } else {
@@ -1152,6 +2329,7 @@ async fn handle_monitor_command(
                             if monitor.last_check > 0 {
                                 println!("   Last check: {}", ProcessUtils::format_timestamp(monitor.last_check));
                             }
+                            println!("   Checks: {} ({} failed in a row)", monitor.check_count, monitor.consecutive_failures);
                             if !monitor.error_message.is_empty() {
                                 println!("   Error: {}", monitor.error_message);
                             }
@@ -1221,6 +2399,12 @@ async fn handle_monitor_command(
                                 println!("   Containers: {} running / {} total", containers, total);
                             }
                         }
+                        if !info.architecture.is_empty() {
+                            println!("   Image architecture: linux/{}", info.architecture);
+                        }
+                        if !info.reserved_name_prefixes.is_empty() {
+                            println!("   Reserved name prefixes: {}", info.reserved_name_prefixes.join(", "));
+                        }
                         println!();
                     }
                     Err(_) => println!("⚠️  System info unavailable\n"),
@@ -1314,54 +2498,22 @@ async fn handle_monitor_command(
                 detailed, include_mac, export.as_ref().unwrap_or(&"None".to_string()));
             ConsoleLogger::separator();
             
-            // This is a demonstration of how the network health monitoring would be called
-            // In a real implementation, we'd need to access the network manager from the server
-            println!("⚠️  Network health monitoring requires server-side implementation");
-            println!("   This feature demonstrates integration of unused network methods:");
-            println!("   - verify_bridge_attachment()");
-            println!("   - get_interface_mac_address()"); 
-            println!("   - get_container_interface_mac_address()");
-            println!("   - test_bidirectional_connectivity()");
-            println!("   - verify_container_network_ready()");
-            println!("   - validate_container_namespace()");
-            println!("   - verify_dns_container_isolation()");
-            println!();
-            println!("📊 Simulated Network Health Report:");
-            
-            // Get network allocations for demonstration
-            match client.list_network_allocations(tonic::Request::new(quilt::ListNetworkAllocationsRequest {})).await {
+            let request = tonic::Request::new(quilt::RunNetworkDiagnosticsRequest {
+                container_id: String::new(),
+                container_name: String::new(),
+            });
+            match client.run_network_diagnostics(request).await {
                 Ok(response) => {
-                    let allocations = response.into_inner().allocations;
-                    println!("🌐 Network Infrastructure Status:");
-                    println!("   Total allocations: {}", allocations.len());
-                    
-                    for alloc in allocations.iter().take(5) { // Show first 5
-                        println!("   📡 {} -> {} [{}]", 
-                            alloc.container_id[..8].to_string(),
-                            alloc.ip_address,
-                            alloc.status);
-                        
-                        if detailed {
-                            println!("      Bridge: {} | veth: {} <-> {}", 
-                                alloc.bridge_interface, alloc.veth_host, alloc.veth_container);
-                        }
-                        
-                        if include_mac {
-                            println!("      MAC tracking: Available (simulated)");
-                        }
+                    let report = response.into_inner();
+                    if !report.success {
+                        println!("❌ Network diagnostics failed: {}", report.error_message);
+                        std::process::exit(1);
                     }
-                    
-                    println!("\n✅ All network methods successfully integrated into monitoring framework");
-                    println!("🔍 Bridge attachment verification: Ready");
-                    println!("🔒 MAC address security tracking: Ready"); 
-                    println!("🌐 Bidirectional connectivity testing: Ready");
-                    println!("✅ Network readiness validation: Ready");
-                    println!("🔐 Namespace isolation verification: Ready");
+                    print_network_report(&report, detailed, include_mac);
                     
                     // Export functionality
                     if let Some(file_path) = export {
-                        let export_data = format!("Network Health Report\nAllocations: {}\nTimestamp: {:?}\n", 
-                            allocations.len(), std::time::SystemTime::now());
+                        let export_data = format!("{:#?}\n", report);
                         if let Err(e) = std::fs::write(&file_path, export_data) {
                             println!("❌ Failed to export to {}: {}", file_path, e);
                         } else {
@@ -1369,78 +2521,794 @@ async fn handle_monitor_command(
                         }
                     }
                 }
-                Err(e) => println!("❌ Network data unavailable: {}", e),
+                Err(e) => println!("❌ Network diagnostics unavailable: {}", e.message()),
             }
         }
     }
     Ok(())
 }
 
-async fn handle_volume_command(
-    command: VolumeCommands,
+async fn handle_network_command(
+    command: NetworkCommands,
     mut client: QuiltServiceClient<Channel>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match command {
-        VolumeCommands::Create { name, driver, labels } => {
-            println!("📦 Creating volume: {}", name);
-            
-            // Parse labels into HashMap
-            let mut label_map = HashMap::new();
-            for label in labels {
-                if let Some(eq_pos) = label.find('=') {
-                    let key = label[..eq_pos].to_string();
-                    let value = label[eq_pos + 1..].to_string();
-                    label_map.insert(key, value);
-                } else {
-                    label_map.insert(label, String::new());
-                }
+        NetworkCommands::Doctor { container, by_name, detailed } => {
+            let container_id = match container {
+                Some(container) => resolve_container_id(&mut client, &container, by_name).await?,
+                None => String::new(),
+            };
+            if container_id.is_empty() {
+                println!("🩺 Running network diagnostics for all containers...");
+            } else {
+                println!("🩺 Running network diagnostics for container {}...", container_id);
             }
-            
-            let request = tonic::Request::new(CreateVolumeRequest {
-                name: name.clone(),
-                driver: driver.unwrap_or_default(),
-                labels: label_map,
-                options: HashMap::new(),
+
+            let request = tonic::Request::new(quilt::RunNetworkDiagnosticsRequest {
+                container_id,
+                container_name: String::new(),
             });
-            
-            match client.create_volume(request).await {
+
+            match client.run_network_diagnostics(request).await {
                 Ok(response) => {
-                    let res = response.into_inner();
-                    if res.success {
-                        println!("✅ Volume '{}' created successfully!", name);
-                        if let Some(volume) = res.volume {
-                            println!("   Driver: {}", volume.driver);
-                            println!("   Mount Point: {}", volume.mount_point);
-                            println!("   Created: {}", ProcessUtils::format_timestamp(volume.created_at));
-                        }
-                    } else {
-                        println!("❌ Failed to create volume: {}", res.error_message);
+                    let report = response.into_inner();
+                    if !report.success {
+                        println!("❌ Network diagnostics failed: {}", report.error_message);
+                        std::process::exit(1);
+                    }
+                    print_network_report(&report, detailed, detailed);
+                    if !report.healthy {
+                        std::process::exit(1);
                     }
                 }
                 Err(e) => {
-                    println!("❌ Failed to communicate with server: {}", e);
+                    eprintln!("❌ Error running network diagnostics: {}", e.message());
+                    std::process::exit(1);
                 }
             }
         }
-        VolumeCommands::List { filter } => {
-            println!("📦 Listing volumes...");
-            
-            // Parse filters into HashMap
-            let mut filter_map = HashMap::new();
-            for f in filter {
-                if let Some(eq_pos) = f.find('=') {
-                    let key = f[..eq_pos].to_string();
-                    let value = f[eq_pos + 1..].to_string();
-                    filter_map.insert(key, value);
-                } else {
-                    filter_map.insert(f, String::new());
-                }
-            }
-            
-            let request = tonic::Request::new(ListVolumesRequest {
-                filters: filter_map,
-            });
-            
+        NetworkCommands::Capture { container, by_name, output, duration, count, snaplen, filter } => {
+            use std::io::Write;
+
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            // Status goes to stderr so `-o -` can be piped straight into tcpdump/wireshark
+            eprintln!("📦 Capturing traffic for container {} ({}s / {} packets)...", container_id, duration, count);
+
+            let request = tonic::Request::new(quilt::CaptureTrafficRequest {
+                container_id,
+                container_name: String::new(),
+                duration_seconds: duration,
+                max_packets: count,
+                snaplen,
+                filter: filter.join(" "),
+            });
+
+            let mut stream = match client.capture_traffic(request).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    eprintln!("❌ Error starting capture: {}", e.message());
+                    std::process::exit(1);
+                }
+            };
+
+            let mut writer: Box<dyn Write> = if output == "-" {
+                Box::new(std::io::stdout())
+            } else {
+                Box::new(std::fs::File::create(&output)?)
+            };
+
+            let mut bytes_written = 0usize;
+            while let Some(chunk) = stream.message().await? {
+                if !chunk.data.is_empty() {
+                    writer.write_all(&chunk.data)?;
+                    bytes_written += chunk.data.len();
+                }
+                if chunk.done {
+                    writer.flush()?;
+                    if !chunk.error_message.is_empty() {
+                        eprintln!("❌ Capture failed: {}", chunk.error_message);
+                        std::process::exit(1);
+                    }
+                    eprintln!("✅ Captured {} packets ({} bytes)", chunk.packets_captured, bytes_written);
+                    if output != "-" {
+                        eprintln!("📄 Written to {}", output);
+                    }
+                    break;
+                }
+            }
+        }
+        NetworkCommands::Policy { container, by_name, ingress, egress, allow_from, deny_from, allow_to, deny_to, clear } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+
+            let mut rules = Vec::new();
+            for (peers, direction, action) in [
+                (allow_from, "ingress", "allow"),
+                (deny_from, "ingress", "deny"),
+                (allow_to, "egress", "allow"),
+                (deny_to, "egress", "deny"),
+            ] {
+                rules.extend(peers.into_iter().map(|peer| quilt::NetworkPolicyRule {
+                    direction: direction.to_string(),
+                    peer,
+                    action: action.to_string(),
+                }));
+            }
+
+            // Without any policy flags this only shows the current policy
+            if !clear && ingress.is_none() && egress.is_none() && rules.is_empty() {
+                let request = tonic::Request::new(quilt::GetNetworkPolicyRequest {
+                    container_id: container_id.clone(),
+                    container_name: String::new(),
+                });
+                let response = match client.get_network_policy(request).await {
+                    Ok(response) => response.into_inner(),
+                    Err(e) => {
+                        eprintln!("❌ Error getting network policy: {}", e.message());
+                        std::process::exit(1);
+                    }
+                };
+                match response.policy.filter(|_| response.has_policy) {
+                    None => println!("No network policy for {} (all traffic allowed)", container_id),
+                    Some(policy) => {
+                        println!("🛡️ Network policy for {} ({})", container_id,
+                            if response.enforced { "enforced" } else { "not enforced" });
+                        println!("   Ingress default: {}", policy.ingress_default);
+                        println!("   Egress default:  {}", policy.egress_default);
+                        for rule in &policy.rules {
+                            println!("   {:<7} {:<5} {}", rule.direction, rule.action, rule.peer);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            let request = tonic::Request::new(quilt::SetNetworkPolicyRequest {
+                container_id,
+                container_name: String::new(),
+                policy: if clear {
+                    None
+                } else {
+                    Some(quilt::NetworkPolicy {
+                        ingress_default: ingress.unwrap_or_default(),
+                        egress_default: egress.unwrap_or_default(),
+                        rules,
+                    })
+                },
+                clear,
+            });
+            match client.set_network_policy(request).await {
+                Ok(response) => {
+                    let response = response.into_inner();
+                    if clear {
+                        println!("✅ Network policy removed from {}", response.container_id);
+                    } else if response.enforced {
+                        println!("✅ Network policy enforced on {}", response.container_id);
+                    } else {
+                        println!("✅ Network policy stored for {}; it is enforced once the container runs", response.container_id);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error setting network policy: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print a network diagnostics report; failures are always shown, passing checks only when detailed
+fn print_network_report(report: &quilt::RunNetworkDiagnosticsResponse, detailed: bool, include_mac: bool) {
+    let status = |ok: bool| if ok { "✅" } else { "❌" };
+
+    println!("\n🌉 Bridge attachments ({}):", report.bridge_attachments.len());
+    for check in report.bridge_attachments.iter().filter(|c| detailed || !c.attached) {
+        println!("   {} {} {}", status(check.attached), check.veth_name, check.error_message);
+    }
+
+    println!("🔄 Connectivity ({}):", report.connectivity_tests.len());
+    for test in report.connectivity_tests.iter().filter(|t| detailed || !t.success) {
+        if test.success {
+            println!("   {} {} <-> {} ({}ms)", status(true), test.container_ip, test.gateway_ip, test.response_time_ms);
+        } else {
+            println!("   {} {} (PID {}): {}", status(false), test.container_ip, test.container_pid, test.error_message);
+        }
+    }
+
+    println!("📡 Readiness ({}):", report.readiness_checks.len());
+    for check in report.readiness_checks.iter().filter(|c| detailed || !c.ready) {
+        println!("   {} {} (PID {}) {}", status(check.ready), check.container_id, check.container_pid, check.error_message);
+    }
+
+    println!("🔐 Namespaces ({}):", report.namespace_validations.len());
+    for check in report.namespace_validations.iter().filter(|c| detailed || !c.namespace_valid) {
+        println!("   {} PID {} {}", status(check.namespace_valid), check.container_pid, check.error_message);
+    }
+
+    if include_mac {
+        println!("🔒 Interface MAC addresses ({}):", report.mac_addresses.len());
+        for mac in &report.mac_addresses {
+            println!("   {} [{}] {}", mac.interface_name, mac.interface_type, mac.mac_address);
+        }
+    }
+
+    println!();
+    if report.healthy {
+        println!("✅ Network healthy ({}ms)", report.total_duration_ms);
+    } else {
+        println!("⚠️  {} network issues found ({}ms)", report.issues_count, report.total_duration_ms);
+    }
+}
+
+async fn drain_containers(
+    mut client: QuiltServiceClient<Channel>,
+    up_to: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("⚖️ Draining containers up to priority class {}...", up_to);
+    let request = tonic::Request::new(quilt::DrainContainersRequest { up_to_priority_class: up_to });
+    let response = match client.drain_containers(request).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            eprintln!("❌ Error draining containers: {}", e.message());
+            std::process::exit(1);
+        }
+    };
+
+    if response.containers.is_empty() {
+        println!("No running containers to drain.");
+        return Ok(());
+    }
+    let mut failed = 0;
+    for container in &response.containers {
+        if container.stopped {
+            println!("  ✅ {} ({}) stopped", container.container_id, container.priority_class);
+        } else {
+            failed += 1;
+            println!("  ❌ {} ({}): {}", container.container_id, container.priority_class, container.error_message);
+        }
+    }
+    println!("\nDrained {} container(s), {} failed", response.containers.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn follow_operation(
+    mut client: QuiltServiceClient<Channel>,
+    operation_id: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let request = tonic::Request::new(quilt::StreamOperationProgressRequest { operation_id });
+    let mut stream = match client.stream_operation_progress(request).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            eprintln!("❌ Error following operation: {}", e.message());
+            std::process::exit(1);
+        }
+    };
+
+    while let Some(progress) = stream.message().await? {
+        if !progress.done {
+            println!("  [{:>3}%] {}", progress.percent, progress.phase);
+        } else if progress.success {
+            println!("✅ {} of {} completed", progress.kind, progress.container_id);
+        } else {
+            eprintln!("❌ {} of {} failed: {}", progress.kind, progress.container_id, progress.error_message);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+async fn check_host_requirements(
+    mut client: QuiltServiceClient<Channel>,
+    problems_only: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = match client.check_host_requirements(tonic::Request::new(quilt::CheckHostRequirementsRequest {})).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            eprintln!("❌ Error checking host requirements: {}", e.message());
+            std::process::exit(1);
+        }
+    };
+
+    println!("🩺 Host requirements");
+    let mut category = String::new();
+    for check in response.checks.iter().filter(|check| !problems_only || check.status != "pass") {
+        if check.category != category {
+            category = check.category.clone();
+            println!("\n  {}:", category);
+        }
+        let icon = match check.status.as_str() {
+            "pass" => "✅",
+            "warn" => "⚠️ ",
+            _ => "❌",
+        };
+        println!("    {} {:<18} {}", icon, check.name, check.detail);
+        if !check.remediation.is_empty() {
+            println!("       ↳ {}", check.remediation);
+        }
+    }
+
+    println!();
+    if response.ready {
+        println!("✅ Host is ready ({} warning(s))", response.warnings);
+    } else {
+        println!("❌ {} check(s) failed, {} warning(s)", response.failed, response.warnings);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn handle_system_command(
+    command: SystemCommands,
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        SystemCommands::Diagnose { container, by_name, output, log_lines } => {
+            use std::io::Write;
+
+            let container_id = match container {
+                Some(container) => resolve_container_id(&mut client, &container, by_name).await?,
+                None => String::new(),
+            };
+            let output = output.unwrap_or_else(|| {
+                let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                format!("quilt-diagnostics-{}.tar.gz", timestamp)
+            });
+            if container_id.is_empty() {
+                println!("🩺 Collecting host diagnostics...");
+            } else {
+                println!("🩺 Collecting diagnostics for container {}...", container_id);
+            }
+
+            let request = tonic::Request::new(quilt::CollectDiagnosticsRequest {
+                container_id,
+                container_name: String::new(),
+                log_lines,
+            });
+
+            let mut stream = match client.collect_diagnostics(request).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    eprintln!("❌ Error collecting diagnostics: {}", e.message());
+                    std::process::exit(1);
+                }
+            };
+
+            let mut file = std::fs::File::create(&output)?;
+            while let Some(chunk) = stream.message().await? {
+                file.write_all(&chunk.data)?;
+                if chunk.done {
+                    file.flush()?;
+                    println!("✅ Diagnostic bundle written to {} ({} bytes)", output, chunk.total_bytes);
+                    break;
+                }
+            }
+        }
+        SystemCommands::Reconcile { dry_run } => {
+            let request = tonic::Request::new(quilt::ReconcileOrphansRequest { dry_run });
+            let response = match client.reconcile_orphans(request).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    eprintln!("❌ Error reconciling orphaned resources: {}", e.message());
+                    std::process::exit(1);
+                }
+            };
+
+            if response.resources.is_empty() {
+                println!("✅ No orphaned resources found");
+                return Ok(());
+            }
+            println!("🧹 {} orphaned resource(s){}:", response.resources.len(), if response.dry_run { " (dry run)" } else { "" });
+            for orphan in &response.resources {
+                let action = if !orphan.cleanable {
+                    "left alone".to_string()
+                } else if response.dry_run {
+                    "would be cleaned".to_string()
+                } else {
+                    format!("cleanup task(s) {:?}", orphan.task_ids)
+                };
+                println!("   {:<13} {:<40} {} - {} [{}]", orphan.kind, orphan.resource, orphan.container_id, orphan.detail, action);
+            }
+        }
+        SystemCommands::Ports { inactive } => {
+            let request = tonic::Request::new(quilt::ListPortBindingsRequest {});
+            let response = match client.list_port_bindings(request).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    eprintln!("❌ Error listing host ports: {}", e.message());
+                    std::process::exit(1);
+                }
+            };
+
+            let bindings: Vec<_> = response.bindings.into_iter()
+                .filter(|binding| !inactive || !binding.active)
+                .collect();
+            if bindings.is_empty() {
+                println!("{}", if inactive { "✅ Every host port is live" } else { "No host ports allocated" });
+                return Ok(());
+            }
+            println!("{:<13} {:<22} {:<24} {:<30} {}", "KIND", "HOST", "CONTAINER", "TARGET", "STATE");
+            for binding in &bindings {
+                let host = format!("{}:{}/{}", binding.host_ip, binding.host_port, binding.protocol);
+                let container = if binding.container_name.is_empty() { &binding.container_id } else { &binding.container_name };
+                println!("{:<13} {:<22} {:<24} {:<30} {}", binding.kind, host,
+                    if container.is_empty() { "-" } else { container.as_str() }, binding.target,
+                    if binding.active { "active" } else { "inactive" });
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_device_command(
+    command: DeviceCommands,
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        DeviceCommands::Grant { container, by_name, rule } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            println!("🔌 Granting '{}' to container {}...", rule, container_id);
+
+            let request = tonic::Request::new(GrantDeviceAccessRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                rule,
+            });
+
+            match client.grant_device_access(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Device access granted to container {}", container_id);
+                    } else {
+                        println!("❌ Failed to grant device access: {}", res.error_message);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error granting device access: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        DeviceCommands::Revoke { container, by_name, rule } => {
+            let container_id = resolve_container_id(&mut client, &container, by_name).await?;
+            println!("🔌 Revoking '{}' from container {}...", rule, container_id);
+
+            let request = tonic::Request::new(RevokeDeviceAccessRequest {
+                container_id: container_id.clone(),
+                container_name: String::new(),
+                rule,
+            });
+
+            match client.revoke_device_access(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Device access revoked from container {}", container_id);
+                    } else {
+                        println!("❌ Failed to revoke device access: {}", res.error_message);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Error revoking device access: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_plugin_command(
+    command: PluginCommands,
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        PluginCommands::Register { name, endpoint } => {
+            println!("🔌 Registering plugin {} at {}", name, endpoint);
+            let request = tonic::Request::new(quilt::RegisterPluginRequest { name: name.clone(), endpoint });
+            match client.register_plugin(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Plugin '{}' registered", name);
+                        if let Some(plugin) = res.plugin {
+                            println!("   Implements: {}", plugin.capabilities.join(", "));
+                        }
+                    } else {
+                        println!("❌ Failed to register plugin: {}", res.error_message);
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+        PluginCommands::List { capability } => {
+            let request = tonic::Request::new(quilt::ListPluginsRequest { capability: capability.unwrap_or_default() });
+            match client.list_plugins(request).await {
+                Ok(response) => {
+                    let plugins = response.into_inner().plugins;
+                    if plugins.is_empty() {
+                        println!("   No plugins registered");
+                    }
+                    for plugin in plugins {
+                        println!("   - {} ({})", plugin.name, if plugin.active { "active" } else { "inactive" });
+                        println!("     Endpoint: {}", plugin.endpoint);
+                        println!("     Implements: {}", plugin.capabilities.join(", "));
+                        println!("     Registered: {}", ProcessUtils::format_timestamp(plugin.created_at as u64));
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+        PluginCommands::Remove { name } => {
+            let request = tonic::Request::new(quilt::UnregisterPluginRequest { name: name.clone() });
+            match client.unregister_plugin(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Plugin '{}' unregistered", name);
+                    } else {
+                        println!("❌ Failed to unregister plugin: {}", res.error_message);
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_image_scan(scan: &quilt::ImageScan) {
+    println!("   - {} ({}, {})", scan.image_path, scan.scanner, ProcessUtils::format_timestamp(scan.scanned_at as u64));
+    println!("     Critical: {}  High: {}  Medium: {}  Low: {}  Unknown: {}",
+        scan.critical, scan.high, scan.medium, scan.low, scan.unknown);
+    if scan.blocked {
+        println!("     ⛔ {}", scan.blocked_reason);
+    }
+    for finding in &scan.findings {
+        let fixed = if finding.fixed_version.is_empty() { "no fix".to_string() } else { format!("fixed in {}", finding.fixed_version) };
+        println!("     [{}] {} {} {} ({})", finding.severity, finding.id, finding.package, finding.installed_version, fixed);
+    }
+}
+
+async fn handle_image_command(
+    command: ImageCommands,
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        ImageCommands::Scan { image_path, force } => {
+            println!("🛡️ Scanning {}", image_path);
+            let request = tonic::Request::new(quilt::ScanImageRequest { image_path, force });
+            match client.scan_image(request).await {
+                Ok(response) => print_image_scan(&response.into_inner()),
+                Err(e) => {
+                    println!("❌ Scan failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        ImageCommands::Scans { findings } => {
+            let request = tonic::Request::new(quilt::ListImageScansRequest { include_findings: findings });
+            match client.list_image_scans(request).await {
+                Ok(response) => {
+                    let scans = response.into_inner().scans;
+                    if scans.is_empty() {
+                        println!("   No images scanned");
+                    }
+                    for scan in &scans {
+                        print_image_scan(scan);
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+        ImageCommands::List => {
+            match client.list_images(tonic::Request::new(quilt::ListImagesRequest {})).await {
+                Ok(response) => {
+                    let images = response.into_inner().images;
+                    if images.is_empty() {
+                        println!("   No images stored");
+                    } else {
+                        println!("{:<19} {:<30} {:>7} {:>10} {:>10}", "DIGEST", "TAGS", "LAYERS", "SIZE", "CONTAINERS");
+                    }
+                    for image in &images {
+                        let tags = if image.repo_tags.is_empty() { "<none>".to_string() } else { image.repo_tags.join(",") };
+                        println!("{:<19} {:<30} {:>7} {:>8}MB {:>10}", short_image_digest(&image.digest), tags, image.layers.len(),
+                            image.size_bytes / (1024 * 1024), image.container_ids.len());
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+        ImageCommands::Inspect { reference } => {
+            match client.inspect_image(tonic::Request::new(quilt::InspectImageRequest { reference })).await {
+                Ok(response) => {
+                    let image = response.into_inner();
+                    println!("📦 {}", image.digest);
+                    if !image.repo_tags.is_empty() {
+                        println!("   Tags: {}", image.repo_tags.join(", "));
+                    }
+                    println!("   Stored: {}", utils::process::ProcessUtils::format_timestamp(image.created_at as u64));
+                    println!("   Size: {} bytes in {} layer(s){}", image.size_bytes, image.layers.len(),
+                        if image.complete { "" } else { " (some layers missing from the store)" });
+                    for layer in &image.layers {
+                        println!("     {}", layer);
+                    }
+                    if !image.architecture.is_empty() {
+                        println!("   Architecture: {}", image.architecture);
+                    }
+                    if !image.working_dir.is_empty() {
+                        println!("   Working directory: {}", image.working_dir);
+                    }
+                    if !image.user.is_empty() {
+                        println!("   User: {}", image.user);
+                    }
+                    for variable in &image.env {
+                        println!("   Env: {}", variable);
+                    }
+                    for source in &image.sources {
+                        let used = if source.last_used_at > 0 {
+                            format!("last used {}", utils::process::ProcessUtils::format_timestamp(source.last_used_at as u64))
+                        } else {
+                            "not used since import".to_string()
+                        };
+                        println!("   Source: {} ({})", source.path, used);
+                    }
+                    if !image.container_ids.is_empty() {
+                        println!("   Containers: {}", image.container_ids.join(", "));
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to inspect image: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        ImageCommands::Remove { reference } => {
+            match client.remove_image(tonic::Request::new(quilt::RemoveImageRequest { reference })).await {
+                Ok(response) => {
+                    let removed = response.into_inner();
+                    println!("✅ Removed {} ({} layer(s) freed)", removed.digest, removed.removed_layers.len());
+                    for file in &removed.removed_files {
+                        println!("   Deleted {}", file);
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to remove image: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        ImageCommands::Import { path } => {
+            // Relative paths are taken from here, for a daemon on the same host
+            let path = absolute_path(&path);
+            println!("📥 Importing {}", path);
+            match client.import_image(tonic::Request::new(quilt::ImportImageRequest { path })).await {
+                Ok(response) => {
+                    let image = response.into_inner();
+                    let tags = if image.repo_tags.is_empty() { "untagged".to_string() } else { image.repo_tags.join(", ") };
+                    println!("✅ Imported {} ({}, {} layer(s))", image.digest, tags, image.layers.len());
+                }
+                Err(e) => {
+                    println!("❌ Import failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+        ImageCommands::Export { reference, output, format, tag } => {
+            let request = quilt::ExportImageRequest {
+                reference,
+                output_path: absolute_path(&output),
+                format,
+                tag: tag.unwrap_or_default(),
+            };
+            match client.export_image(tonic::Request::new(request)).await {
+                Ok(response) => {
+                    let exported = response.into_inner();
+                    println!("✅ Exported {} to {} ({} format, {} bytes)", exported.digest, exported.output_path, exported.format, exported.size_bytes);
+                }
+                Err(e) => {
+                    println!("❌ Export failed: {}", e.message());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `path` made absolute against the current directory
+fn absolute_path(path: &str) -> String {
+    std::env::current_dir()
+        .map(|dir| dir.join(path).display().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// `sha256:` and the first 12 hex digits of an image digest
+fn short_image_digest(digest: &str) -> String {
+    let hex = digest.trim_start_matches("sha256:");
+    format!("sha256:{}", &hex[..hex.len().min(12)])
+}
+
+async fn handle_volume_command(
+    command: VolumeCommands,
+    mut client: QuiltServiceClient<Channel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        VolumeCommands::Create { name, driver, labels } => {
+            println!("📦 Creating volume: {}", name);
+            
+            // Parse labels into HashMap
+            let mut label_map = HashMap::new();
+            for label in labels {
+                if let Some(eq_pos) = label.find('=') {
+                    let key = label[..eq_pos].to_string();
+                    let value = label[eq_pos + 1..].to_string();
+                    label_map.insert(key, value);
+                } else {
+                    label_map.insert(label, String::new());
+                }
+            }
+            
+            let request = tonic::Request::new(CreateVolumeRequest {
+                name: name.clone(),
+                driver: driver.unwrap_or_default(),
+                labels: label_map,
+                options: HashMap::new(),
+            });
+            
+            match client.create_volume(request).await {
+                Ok(response) => {
+                    let res = response.into_inner();
+                    if res.success {
+                        println!("✅ Volume '{}' created successfully!", name);
+                        if let Some(volume) = res.volume {
+                            println!("   Driver: {}", volume.driver);
+                            println!("   Mount Point: {}", volume.mount_point);
+                            println!("   Created: {}", ProcessUtils::format_timestamp(volume.created_at));
+                        }
+                    } else {
+                        println!("❌ Failed to create volume: {}", res.error_message);
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Failed to communicate with server: {}", e);
+                }
+            }
+        }
+        VolumeCommands::List { filter } => {
+            println!("📦 Listing volumes...");
+            
+            // Parse filters into HashMap
+            let mut filter_map = HashMap::new();
+            for f in filter {
+                if let Some(eq_pos) = f.find('=') {
+                    let key = f[..eq_pos].to_string();
+                    let value = f[eq_pos + 1..].to_string();
+                    filter_map.insert(key, value);
+                } else {
+                    filter_map.insert(f, String::new());
+                }
+            }
+            
+            let request = tonic::Request::new(ListVolumesRequest {
+                filters: filter_map,
+            });
+            
             match client.list_volumes(request).await {
                 Ok(response) => {
                     let res = response.into_inner();
@@ -1633,13 +3501,19 @@ async fn handle_cleanup_command(
                 }
             }
         }
-        CleanupCommands::Force { container, by_name } => {
-            println!("🧹 Force cleanup for container...");
+        CleanupCommands::Force { container, by_name, dry_run, types } => {
+            if dry_run {
+                println!("🧹 Force cleanup for container (dry run)...");
+            } else {
+                println!("🧹 Force cleanup for container...");
+            }
             
             let container_id = resolve_container_id(&mut client, &container, by_name).await?;
             
             let request = tonic::Request::new(quilt::ForceCleanupRequest {
                 container_id,
+                dry_run,
+                resource_types: types,
             });
             
             match client.force_cleanup(request).await {
@@ -1648,6 +3522,11 @@ async fn handle_cleanup_command(
                     if res.success {
                         if res.cleaned_resources.is_empty() {
                             println!("   No resources needed cleanup");
+                        } else if dry_run {
+                            println!("📋 {} item(s) would be cleaned up:", res.cleaned_resources.len());
+                            for resource in res.cleaned_resources {
+                                println!("   - {}", resource);
+                            }
                         } else {
                             println!("✅ Successfully cleaned up {} resources:", res.cleaned_resources.len());
                             for resource in res.cleaned_resources {
@@ -1797,117 +3676,528 @@ async fn handle_report_command(
             }
         }
         
-        ReportCommands::Cleanup { days, failed_only } => {
-            println!("📊 Generating Cleanup Operations Report");
-            println!("   Period: Last {} days | Failed only: {}", days, failed_only);
-            ConsoleLogger::separator();
-            
-            match client.list_cleanup_tasks(tonic::Request::new(quilt::ListCleanupTasksRequest {})).await {
-                Ok(response) => {
-                    let tasks = response.into_inner().tasks;
-                    if !tasks.is_empty() {
-                        let filtered_tasks: Vec<_> = if failed_only {
-                            tasks.into_iter().filter(|task| task.status.contains("failed") || task.status.contains("error")).collect()
-                        } else {
-                            tasks
-                        };
-                        
-                        if !filtered_tasks.is_empty() {
-                            println!("🧹 Cleanup Operations Summary ({} tasks):", filtered_tasks.len());
-                            
-                            let mut cleanup_data: Vec<_> = filtered_tasks.into_iter().collect();
-                            cleanup_data.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-                            
-                            let mut status_counts = std::collections::HashMap::new();
-                            for task in &cleanup_data {
-                                *status_counts.entry(task.status.clone()).or_insert(0) += 1;
-                            }
-                            
-                            println!("\n📊 Status Distribution:");
-                            for (status, count) in status_counts {
-                                println!("   {} tasks: {}", count, status);
-                            }
-                            println!();
-                            
-                            for task in cleanup_data {
-                                let created_formatted = ProcessUtils::format_timestamp(task.created_at);
-                                let completed_formatted = if task.completed_at > 0 {
-                                    format!(" -> {}", ProcessUtils::format_timestamp(task.completed_at))
-                                } else {
-                                    String::new()
-                                };
-                                
-                                println!("   🗑️  Task: {} | Container: {}", 
-                                    task.task_id, task.container_id[..12].to_string());
-                                println!("      📊 Status: {} | Resource: {} at {}", 
-                                    task.status, task.resource_type, task.resource_path);
-                                println!("      📅 Timeline: {}{}", created_formatted, completed_formatted);
-                                
-                                if !task.error_message.is_empty() {
-                                    println!("      ❌ Error: {}", task.error_message);
-                                }
-                                println!();
-                            }
-                        } else {
-                            println!("🧹 No cleanup tasks found matching criteria");
-                        }
-                    } else {
-                        println!("🧹 No cleanup operations found");
-                    }
-                }
-                Err(_) => println!("⚠️  Cleanup data unavailable"),
+        ReportCommands::Cleanup { days, failed_only } => {
+            println!("📊 Generating Cleanup Operations Report");
+            println!("   Period: Last {} days | Failed only: {}", days, failed_only);
+            ConsoleLogger::separator();
+            
+            match client.list_cleanup_tasks(tonic::Request::new(quilt::ListCleanupTasksRequest {})).await {
+                Ok(response) => {
+                    let tasks = response.into_inner().tasks;
+                    if !tasks.is_empty() {
+                        let filtered_tasks: Vec<_> = if failed_only {
+                            tasks.into_iter().filter(|task| task.status.contains("failed") || task.status.contains("error")).collect()
+                        } else {
+                            tasks
+                        };
+                        
+                        if !filtered_tasks.is_empty() {
+                            println!("🧹 Cleanup Operations Summary ({} tasks):", filtered_tasks.len());
+                            
+                            let mut cleanup_data: Vec<_> = filtered_tasks.into_iter().collect();
+                            cleanup_data.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                            
+                            let mut status_counts = std::collections::HashMap::new();
+                            for task in &cleanup_data {
+                                *status_counts.entry(task.status.clone()).or_insert(0) += 1;
+                            }
+                            
+                            println!("\n📊 Status Distribution:");
+                            for (status, count) in status_counts {
+                                println!("   {} tasks: {}", count, status);
+                            }
+                            println!();
+                            
+                            for task in cleanup_data {
+                                let created_formatted = ProcessUtils::format_timestamp(task.created_at);
+                                let completed_formatted = if task.completed_at > 0 {
+                                    format!(" -> {}", ProcessUtils::format_timestamp(task.completed_at))
+                                } else {
+                                    String::new()
+                                };
+                                
+                                println!("   🗑️  Task: {} | Container: {}", 
+                                    task.task_id, task.container_id[..12].to_string());
+                                println!("      📊 Status: {} | Resource: {} at {}", 
+                                    task.status, task.resource_type, task.resource_path);
+                                println!("      📅 Timeline: {}{}", created_formatted, completed_formatted);
+                                
+                                if !task.error_message.is_empty() {
+                                    println!("      ❌ Error: {}", task.error_message);
+                                }
+                                println!();
+                            }
+                        } else {
+                            println!("🧹 No cleanup tasks found matching criteria");
+                        }
+                    } else {
+                        println!("🧹 No cleanup operations found");
+                    }
+                }
+                Err(_) => println!("⚠️  Cleanup data unavailable"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    
+    #[test]
+    fn test_create_command_parsing() {
+        let args = vec![
+            "cli",
+            "create",
+            "-n", "test-container",
+            "--image-path", "test.tar.gz",
+            "--", "echo", "hello"
+        ];
+        
+        let cli = Cli::parse_from(args);
+        
+        match cli.command {
+            Commands::Create { name, image_path, command_and_args, .. } => {
+                assert_eq!(name, Some("test-container".to_string()));
+                assert_eq!(image_path, "test.tar.gz");
+                assert_eq!(command_and_args, vec!["echo", "hello"]);
+            }
+            _ => panic!("Expected Create command"),
+        }
+    }
+    
+    #[test]
+    fn test_create_async_mode() {
+        let args = vec![
+            "cli",
+            "create",
+            "-n", "async-test",
+            "--async-mode",
+            "--image-path", "test.tar.gz"
+        ];
+        
+        let cli = Cli::parse_from(args);
+        
+        match cli.command {
+            Commands::Create { name, async_mode, command_and_args, .. } => {
+                assert_eq!(name, Some("async-test".to_string()));
+                assert!(async_mode);
+                assert!(command_and_args.is_empty());
+            }
+            _ => panic!("Expected Create command"),
+        }
+    }
+    
+    #[test]
+    fn test_create_with_fuse_mount() {
+        let args = vec![
+            "cli", "create",
+            "--image-path", "test.tar.gz",
+            "--allow-fuse",
+            "--fuse-mount", "helper=sshfs,source=user@host:/srv,target=/mnt/remote,opt=allow_other",
+            "--", "sleep", "60"
+        ];
+        
+        let cli = Cli::parse_from(args);
+        
+        match cli.command {
+            Commands::Create { allow_fuse, fuse_mounts, .. } => {
+                assert!(allow_fuse);
+                assert_eq!(fuse_mounts.len(), 1);
+                assert_eq!(fuse_mounts[0].helper, "sshfs");
+                assert_eq!(fuse_mounts[0].source, "user@host:/srv");
+                assert_eq!(fuse_mounts[0].target, "/mnt/remote");
+                assert_eq!(fuse_mounts[0].options, vec!["allow_other".to_string()]);
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        // The immutable profile drops the CAP_SYS_ADMIN FUSE mounts need
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--allow-fuse", "--immutable"]).is_err());
+    }
+    
+    #[test]
+    fn test_clone_command() {
+        let cli = Cli::parse_from(vec!["cli", "clone", "agent-base", "-n", "--name", "agent-2", "--rootfs", "-l", "shard=2", "--no-start"]);
+        
+        match cli.command {
+            Commands::Clone { container, by_name, name, rootfs, volumes, labels, no_start } => {
+                assert_eq!(container, "agent-base");
+                assert!(by_name);
+                assert_eq!(name.as_deref(), Some("agent-2"));
+                assert!(rootfs);
+                assert!(!volumes);
+                assert_eq!(labels, vec![("shard".to_string(), "2".to_string())]);
+                assert!(no_start);
+            }
+            _ => panic!("Expected Clone command"),
+        }
+    }
+    
+    #[test]
+    fn test_deploy_command() {
+        let cli = Cli::parse_from(vec!["cli", "deploy", "web", "-n", "--image-path", "web-v2.tar.gz", "-e", "VERSION=2", "--", "nginx", "-g", "daemon off;"]);
+        match cli.command {
+            Commands::Deploy { container, by_name, image_path, env, labels, timeout, command_and_args } => {
+                assert_eq!(container, "web");
+                assert!(by_name);
+                assert_eq!(image_path.as_deref(), Some("web-v2.tar.gz"));
+                assert_eq!(env, vec![("VERSION".to_string(), "2".to_string())]);
+                assert!(labels.is_empty());
+                assert_eq!(timeout, None);
+                assert_eq!(command_and_args, vec!["nginx", "-g", "daemon off;"]);
+            }
+            _ => panic!("Expected Deploy command"),
+        }
+    }
+    
+    #[test]
+    fn test_scale_command() {
+        let cli = Cli::parse_from(vec!["cli", "scale", "web=3"]);
+        match cli.command {
+            Commands::Scale { spec } => assert_eq!(spec, ("web".to_string(), 3)),
+            _ => panic!("Expected Scale command"),
+        }
+
+        assert!(Cli::try_parse_from(vec!["cli", "scale", "web"]).is_err());
+        assert!(Cli::try_parse_from(vec!["cli", "scale", "=2"]).is_err());
+        assert!(Cli::try_parse_from(vec!["cli", "scale", "web=-1"]).is_err());
+    }
+    
+    #[test]
+    fn test_create_immutable() {
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--immutable", "--", "sleep", "60"]);
+        
+        match cli.command {
+            Commands::Create { immutable, .. } => assert!(immutable),
+            _ => panic!("Expected Create command"),
+        }
+    }
+    
+    #[test]
+    fn test_create_with_hooks() {
+        let cli = Cli::parse_from(vec![
+            "cli", "create", "--image-path", "test.tar.gz",
+            "--hook", "started=lb-register --pool web",
+            "--hook", "died,removed=https://lb.example/hooks?a=b",
+            "--hook-timeout", "30",
+            "--", "sleep", "60",
+        ]);
+        
+        match cli.command {
+            Commands::Create { hooks, hook_timeout, .. } => {
+                assert_eq!(hook_timeout, 30);
+                assert_eq!(hooks.len(), 2);
+                assert_eq!(hooks[0].events, vec!["started".to_string()]);
+                assert_eq!(hooks[0].command, "lb-register --pool web");
+                assert!(hooks[0].url.is_empty());
+                assert_eq!(hooks[1].events, vec!["died".to_string(), "removed".to_string()]);
+                assert_eq!(hooks[1].url, "https://lb.example/hooks?a=b");
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        assert!(InputValidator::parse_hook("all=").is_err());
+        assert!(InputValidator::parse_hook("just-a-command").is_err());
+        assert!(InputValidator::parse_hook("all=true").unwrap().events.is_empty());
+    }
+    
+    #[test]
+    fn test_create_with_prestart_hooks() {
+        let cli = Cli::parse_from(vec![
+            "cli", "create", "--image-path", "test.tar.gz",
+            "--prestart-hook", "/opt/net/plumb.sh eth1",
+            "--prestart-hook", "container:echo licensed > /etc/license",
+            "--", "sleep", "60",
+        ]);
+        
+        match cli.command {
+            Commands::Create { prestart_hooks, .. } => {
+                assert_eq!(prestart_hooks, vec![
+                    ("host".to_string(), "/opt/net/plumb.sh eth1".to_string()),
+                    ("container".to_string(), "echo licensed > /etc/license".to_string()),
+                ]);
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        assert!(InputValidator::parse_prestart_hook("container: ").is_err());
+    }
+    
+    #[test]
+    fn test_plugin_commands() {
+        let cli = Cli::parse_from(vec!["cli", "plugin", "register", "nfs", "unix:///run/quilt/plugins/nfs.sock"]);
+        match cli.command {
+            Commands::Plugin { command: PluginCommands::Register { name, endpoint } } => {
+                assert_eq!(name, "nfs");
+                assert_eq!(endpoint, "unix:///run/quilt/plugins/nfs.sock");
+            }
+            _ => panic!("Expected plugin register command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--network-plugin", "vlan", "--", "sleep", "60"]);
+        match cli.command {
+            Commands::Create { network_plugin, .. } => assert_eq!(network_plugin.as_deref(), Some("vlan")),
+            _ => panic!("Expected Create command"),
+        }
+    }
+    
+    #[test]
+    fn test_create_with_time_namespace() {
+        let cli = Cli::parse_from(vec![
+            "cli", "create", "--image-path", "test.tar.gz",
+            "--enable-time-namespace", "--boottime-offset", "-3600", "--time-offset", "-1d",
+            "--", "sleep", "60",
+        ]);
+        match cli.command {
+            Commands::Create { enable_time_namespace, monotonic_offset, boottime_offset, time_offset, .. } => {
+                assert!(enable_time_namespace);
+                assert_eq!((monotonic_offset, boottime_offset), (0, -3600));
+                assert_eq!(time_offset.as_deref(), Some("-1d"));
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        // Offsets only mean something inside a time namespace
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--monotonic-offset", "60"]).is_err());
+        
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--timezone", "host", "--locale", "C.UTF-8"]);
+        match cli.command {
+            Commands::Create { timezone, locale, .. } => {
+                assert_eq!((timezone.as_deref(), locale.as_deref()), (Some("host"), Some("C.UTF-8")));
+            }
+            _ => panic!("Expected Create command"),
+        }
+    }
+    
+    #[test]
+    fn test_create_with_memory_controls() {
+        let cli = Cli::parse_from(vec![
+            "cli", "create", "--image-path", "test.tar.gz",
+            "--memory-limit", "1024", "--memory-high", "768", "--memory-swap", "-1",
+        ]);
+        match cli.command {
+            Commands::Create { memory_high, memory_swap, memory_swappiness, .. } => {
+                assert_eq!((memory_high, memory_swap, memory_swappiness), (768, -1, 0));
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--memory-swap", "-2"]).is_err());
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--memory-swappiness", "101"]).is_err());
+    }
+    
+    #[test]
+    fn test_create_with_metrics_interval() {
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--metrics-interval", "5"]);
+        match cli.command {
+            Commands::Create { metrics_interval, .. } => assert_eq!(metrics_interval, 5),
+            _ => panic!("Expected Create command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "app.tar", "-u", "app:staff", "--working-directory", "/srv"]);
+        match cli.command {
+            Commands::Create { user, working_directory, .. } => {
+                assert_eq!((user.as_deref(), working_directory.as_deref()), (Some("app:staff"), Some("/srv")));
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "app.tar", "--name", "app1", "--dns-wildcard"]);
+        match cli.command {
+            Commands::Create { dns_wildcard, .. } => assert!(dns_wildcard),
+            _ => panic!("Expected Create command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "app.tar", "--egress-proxy", "squid:3128", "--transparent-proxy"]);
+        match cli.command {
+            Commands::Create { egress_proxy, transparent_proxy, .. } => {
+                assert_eq!((egress_proxy.as_deref(), transparent_proxy), (Some("squid:3128"), true));
             }
+            _ => panic!("Expected Create command"),
         }
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "app.tar", "--transparent-proxy"]).is_err());
+        
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--metrics-interval", "3601"]).is_err());
     }
-    Ok(())
-}
+    
+    #[test]
+    fn test_create_privileged() {
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--privileged"]);
+        match cli.command {
+            Commands::Create { privileged, immutable, .. } => assert!(privileged && !immutable),
+            _ => panic!("Expected Create command"),
+        }
+        
+        // The unhardened mounts defeat the immutable profile
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--privileged", "--immutable"]).is_err());
+        assert!(Cli::try_parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--privileged", "--enable-user-namespace"]).is_err());
+    }
+    
+    #[test]
+    fn test_image_commands() {
+        let cli = Cli::parse_from(vec!["cli", "image", "scan", "/images/web.tar", "--force"]);
+        match cli.command {
+            Commands::Image { command: ImageCommands::Scan { image_path, force } } => {
+                assert_eq!(image_path, "/images/web.tar");
+                assert!(force);
+            }
+            _ => panic!("Expected image scan command"),
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use clap::Parser;
+        let cli = Cli::parse_from(vec!["cli", "image", "scans", "--findings"]);
+        assert!(matches!(cli.command, Commands::Image { command: ImageCommands::Scans { findings: true } }));
+
+        let cli = Cli::parse_from(vec!["cli", "image", "ls"]);
+        assert!(matches!(cli.command, Commands::Image { command: ImageCommands::List }));
+        let cli = Cli::parse_from(vec!["cli", "image", "rm", "web:1"]);
+        match cli.command {
+            Commands::Image { command: ImageCommands::Remove { reference } } => assert_eq!(reference, "web:1"),
+            _ => panic!("Expected image remove command"),
+        }
+        assert_eq!(short_image_digest(&format!("sha256:{}", "a".repeat(64))), "sha256:aaaaaaaaaaaa");
+
+        let cli = Cli::parse_from(vec!["cli", "image", "export", "web:1", "-o", "/tmp/web.tar", "--format", "oci"]);
+        match cli.command {
+            Commands::Image { command: ImageCommands::Export { reference, output, format, tag } } => {
+                assert_eq!((reference.as_str(), output.as_str(), format.as_str(), tag), ("web:1", "/tmp/web.tar", "oci", None));
+            }
+            _ => panic!("Expected image export command"),
+        }
+        assert_eq!(absolute_path("/tmp/web.tar"), "/tmp/web.tar");
+        
+        let cli = Cli::parse_from(vec!["cli", "pull", "alpine:3.19", "--force"]);
+        match cli.command {
+            Commands::Pull { reference, force } => assert_eq!((reference.as_str(), force), ("alpine:3.19", true)),
+            _ => panic!("Expected pull command"),
+        }
+
+        let cli = Cli::parse_from(vec!["cli", "build", "-t", "web:1", "./web"]);
+        match cli.command {
+            Commands::Build { context, tag, file } => assert_eq!((context.as_str(), tag.as_str(), file), ("./web", "web:1", None)),
+            _ => panic!("Expected build command"),
+        }
+    }
     
     #[test]
-    fn test_create_command_parsing() {
+    fn test_create_with_published_ports() {
         let args = vec![
-            "cli",
-            "create",
-            "-n", "test-container",
+            "cli", "create",
             "--image-path", "test.tar.gz",
-            "--", "echo", "hello"
+            "-p", "8080:80",
+            "-p", "127.0.0.1:5353:53/udp",
+            "--", "sleep", "60"
         ];
         
         let cli = Cli::parse_from(args);
         
         match cli.command {
-            Commands::Create { name, image_path, command_and_args, .. } => {
-                assert_eq!(name, Some("test-container".to_string()));
-                assert_eq!(image_path, "test.tar.gz");
-                assert_eq!(command_and_args, vec!["echo", "hello"]);
+            Commands::Create { ports, .. } => {
+                assert_eq!(ports.len(), 2);
+                assert_eq!(ports[0].host_ip, "0.0.0.0");
+                assert_eq!(ports[0].host_port, 8080);
+                assert_eq!(ports[0].container_port, 80);
+                assert_eq!(ports[0].protocol, "tcp");
+                assert_eq!(ports[1].host_ip, "127.0.0.1");
+                assert_eq!(ports[1].protocol, "udp");
             }
             _ => panic!("Expected Create command"),
         }
     }
     
     #[test]
-    fn test_create_async_mode() {
-        let args = vec![
-            "cli",
-            "create",
-            "-n", "async-test",
-            "--async-mode",
-            "--image-path", "test.tar.gz"
-        ];
+    fn test_network_doctor_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "network", "doctor", "web", "-n", "--detailed"]);
+        
+        match cli.command {
+            Commands::Network { command: NetworkCommands::Doctor { container, by_name, detailed } } => {
+                assert_eq!(container.as_deref(), Some("web"));
+                assert!(by_name);
+                assert!(detailed);
+            }
+            _ => panic!("Expected Network Doctor command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "network", "doctor"]);
+        match cli.command {
+            Commands::Network { command: NetworkCommands::Doctor { container, .. } } => assert!(container.is_none()),
+            _ => panic!("Expected Network Doctor command"),
+        }
+    }
+    
+    #[test]
+    fn test_network_capture_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "network", "capture", "web", "-n", "-o", "out.pcap", "-c", "50", "tcp", "port", "80"]);
+        
+        match cli.command {
+            Commands::Network { command: NetworkCommands::Capture { container, by_name, output, duration, count, filter, .. } } => {
+                assert_eq!(container, "web");
+                assert!(by_name);
+                assert_eq!(output, "out.pcap");
+                assert_eq!(duration, 10);
+                assert_eq!(count, 50);
+                assert_eq!(filter.join(" "), "tcp port 80");
+            }
+            _ => panic!("Expected Network Capture command"),
+        }
+    }
+    
+    #[test]
+    fn test_network_policy_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "network", "policy", "db", "-n", "--ingress", "deny",
+            "--allow-from", "web", "--allow-from", "10.42.0.0/24", "--deny-to", "0.0.0.0/0"]);
+        
+        match cli.command {
+            Commands::Network { command: NetworkCommands::Policy { container, by_name, ingress, egress, allow_from, deny_to, clear, .. } } => {
+                assert_eq!(container, "db");
+                assert!(by_name);
+                assert_eq!(ingress.as_deref(), Some("deny"));
+                assert!(egress.is_none());
+                assert_eq!(allow_from, vec!["web", "10.42.0.0/24"]);
+                assert_eq!(deny_to, vec!["0.0.0.0/0"]);
+                assert!(!clear);
+            }
+            _ => panic!("Expected Network Policy command"),
+        }
+        
+        assert!(Cli::try_parse_from(vec!["cli", "network", "policy", "db", "--clear", "--ingress", "deny"]).is_err());
+    }
+    
+    #[test]
+    fn test_trace_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "trace", "web", "-n", "-d", "30", "--profile", "web.json"]);
+        
+        match cli.command {
+            Commands::Trace { container, by_name, duration, profile, top } => {
+                assert_eq!(container, "web");
+                assert!(by_name);
+                assert_eq!(duration, 30);
+                assert_eq!(profile.as_deref(), Some("web.json"));
+                assert_eq!(top, 20);
+            }
+            _ => panic!("Expected Trace command"),
+        }
+    }
+    
+    #[test]
+    fn test_attach_mount_parsing() {
+        let args = vec!["cli", "attach-mount", "web", "-n", "-v", "/srv/data:/data:ro"];
         
         let cli = Cli::parse_from(args);
         
         match cli.command {
-            Commands::Create { name, async_mode, command_and_args, .. } => {
-                assert_eq!(name, Some("async-test".to_string()));
-                assert!(async_mode);
-                assert!(command_and_args.is_empty());
+            Commands::AttachMount { container, by_name, volume, mount } => {
+                assert_eq!(container, "web");
+                assert!(by_name);
+                assert!(mount.is_none());
+                let volume = volume.expect("volume should be parsed");
+                assert_eq!(volume.source, "/srv/data");
+                assert_eq!(volume.target, "/data");
+                assert!(volume.readonly);
             }
-            _ => panic!("Expected Create command"),
+            _ => panic!("Expected AttachMount command"),
         }
     }
     
@@ -1950,6 +4240,133 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_exec_stream_parsing() {
+        let args = vec!["cli", "exec", "builder", "-n", "-c", "make test", "--stream", "--timeout", "600"];
+        
+        let cli = Cli::parse_from(args);
+        
+        match cli.command {
+            Commands::Exec { container, stream, timeout, .. } => {
+                assert_eq!(container, "builder");
+                assert!(stream);
+                assert_eq!(timeout, 600);
+            }
+            _ => panic!("Expected Exec command"),
+        }
+    }
+
+    #[test]
+    fn test_exec_create_workdir_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "exec", "builder", "-c", "make", "-w", "/src/build", "--create-workdir"]);
+        match cli.command {
+            Commands::Exec { working_directory, create_workdir, .. } => {
+                assert_eq!(working_directory.as_deref(), Some("/src/build"));
+                assert!(create_workdir);
+            }
+            _ => panic!("Expected Exec command"),
+        }
+        // Nothing to create without a working directory
+        assert!(Cli::try_parse_from(vec!["cli", "exec", "builder", "-c", "make", "--create-workdir"]).is_err());
+    }
+
+    #[test]
+    fn test_capabilities_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "capabilities"]);
+        assert!(matches!(cli.command, Commands::Capabilities));
+    }
+    
+    #[test]
+    fn test_doctor_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "doctor", "--problems"]);
+        assert!(matches!(cli.command, Commands::Doctor { problems: true }));
+    }
+    
+    #[test]
+    fn test_create_with_readiness_gates() {
+        let cli = Cli::parse_from(vec![
+            "cli", "create", "--image-path", "test.tar.gz",
+            "--ready", "tcp:5432", "--ready", "log:database system is ready", "--ready-timeout", "90",
+            "--", "postgres",
+        ]);
+        match cli.command {
+            Commands::Create { readiness_gates, ready_timeout, .. } => {
+                assert_eq!(readiness_gates, vec![
+                    ("tcp".to_string(), "5432".to_string()),
+                    ("log".to_string(), "database system is ready".to_string()),
+                ]);
+                assert_eq!(ready_timeout, 90);
+            }
+            _ => panic!("Expected Create command"),
+        }
+        
+        assert_eq!(InputValidator::parse_readiness_gate("http:8080/healthz").unwrap(), ("http".to_string(), "8080/healthz".to_string()));
+        assert!(InputValidator::parse_readiness_gate("exec:true").is_err());
+        assert!(InputValidator::parse_readiness_gate("tcp:").is_err());
+    }
+    
+    #[test]
+    fn test_priority_and_drain_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "create", "--image-path", "test.tar.gz", "--priority", "best-effort", "--", "sleep", "60"]);
+        match cli.command {
+            Commands::Create { priority, .. } => assert_eq!(priority.as_deref(), Some("best-effort")),
+            _ => panic!("Expected Create command"),
+        }
+        
+        let cli = Cli::parse_from(vec!["cli", "drain"]);
+        assert!(matches!(cli.command, Commands::Drain { ref up_to } if up_to == "best-effort"));
+        let cli = Cli::parse_from(vec!["cli", "drain", "--up-to", "normal"]);
+        assert!(matches!(cli.command, Commands::Drain { ref up_to } if up_to == "normal"));
+    }
+
+    #[test]
+    fn test_progress_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "progress", "0f8c2a"]);
+        assert!(matches!(cli.command, Commands::Progress { ref operation_id } if operation_id == "0f8c2a"));
+    }
+    
+    #[test]
+    fn test_system_diagnose_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "system", "diagnose", "--container", "web", "-n", "-o", "bundle.tar.gz"]);
+        
+        match cli.command {
+            Commands::System { command: SystemCommands::Diagnose { container, by_name, output, log_lines } } => {
+                assert_eq!(container.as_deref(), Some("web"));
+                assert!(by_name);
+                assert_eq!(output.as_deref(), Some("bundle.tar.gz"));
+                assert_eq!(log_lines, 0);
+            }
+            _ => panic!("Expected System Diagnose command"),
+        }
+    }
+    
+    #[test]
+    fn test_cleanup_force_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "cleanup", "force", "web", "-n", "--dry-run", "--type", "rootfs,logs"]);
+        
+        match cli.command {
+            Commands::Cleanup { command: CleanupCommands::Force { container, by_name, dry_run, types } } => {
+                assert_eq!(container, "web");
+                assert!(by_name);
+                assert!(dry_run);
+                assert_eq!(types, vec!["rootfs".to_string(), "logs".to_string()]);
+            }
+            _ => panic!("Expected Cleanup Force command"),
+        }
+    }
+    
+    #[test]
+    fn test_system_reconcile_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "system", "reconcile", "--dry-run"]);
+        assert!(matches!(cli.command, Commands::System { command: SystemCommands::Reconcile { dry_run: true } }));
+    }
+    
+    #[test]
+    fn test_system_ports_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "system", "ports", "--inactive"]);
+        assert!(matches!(cli.command, Commands::System { command: SystemCommands::Ports { inactive: true } }));
+    }
+    
     #[test]
     fn test_start_command() {
         let args = vec!["cli", "start", "stopped-container", "-n"];
@@ -1957,12 +4374,16 @@ mod tests {
         let cli = Cli::parse_from(args);
         
         match cli.command {
-            Commands::Start { container, by_name } => {
+            Commands::Start { container, by_name, wait } => {
                 assert_eq!(container, "stopped-container");
                 assert!(by_name);
+                assert!(!wait);
             }
             _ => panic!("Expected Start command"),
         }
+        
+        let cli = Cli::parse_from(vec!["cli", "start", "db", "--wait"]);
+        assert!(matches!(cli.command, Commands::Start { wait: true, .. }));
     }
     
     #[test]
@@ -1980,6 +4401,36 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_log_level_command() {
+        let args = vec!["cli", "log-level", "web", "debug", "-n"];
+        
+        let cli = Cli::parse_from(args);
+        
+        match cli.command {
+            Commands::LogLevel { container, level, by_name } => {
+                assert_eq!(container, "web");
+                assert_eq!(level, "debug");
+                assert!(by_name);
+            }
+            _ => panic!("Expected LogLevel command"),
+        }
+    }
+    
+    #[test]
+    fn test_metrics_collection_command() {
+        let cli = Cli::parse_from(vec!["cli", "metrics-collection", "web", "--interval", "10", "-n"]);
+        match cli.command {
+            Commands::MetricsCollection { container, disable, interval, by_name } => {
+                assert_eq!((container.as_str(), disable, interval, by_name), ("web", false, Some(10), true));
+            }
+            _ => panic!("Expected MetricsCollection command"),
+        }
+        
+        assert!(Cli::try_parse_from(vec!["cli", "metrics-collection", "web", "--disable", "--interval", "10"]).is_err());
+        assert!(Cli::try_parse_from(vec!["cli", "metrics-collection", "web", "--interval", "0"]).is_err());
+    }
+    
     #[test]
     fn test_stop_with_timeout() {
         let args = vec!["cli", "stop", "container-id", "-t", "30"];
@@ -2027,6 +4478,26 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_search_logs_parsing() {
+        let cli = Cli::parse_from(vec!["cli", "search-logs", "timeout|refused", "-e", "-c", "api", "-n",
+            "-l", "error", "-l", "warn", "--since", "15m"]);
+        
+        match cli.command {
+            Commands::SearchLogs { query, container, by_name, regex, case_sensitive, levels, since, limit } => {
+                assert_eq!(query.as_deref(), Some("timeout|refused"));
+                assert_eq!(container.as_deref(), Some("api"));
+                assert!(by_name);
+                assert!(regex);
+                assert!(!case_sensitive);
+                assert_eq!(levels, vec!["error", "warn"]);
+                assert_eq!(since, Some(std::time::Duration::from_secs(900)));
+                assert_eq!(limit, 100);
+            }
+            _ => panic!("Expected SearchLogs command"),
+        }
+    }
+    
     #[test]
     fn test_env_var_parsing() {
         let args = vec![
@@ -0,0 +1,20 @@
+// The utilities the CLI shares with the daemon
+// Only the modules the CLI uses are compiled into it; the daemon-only ones (async command
+// execution, name validation, per-container log levels) stay in the library.
+
+#[path = "../utils/console.rs"]
+pub mod console;
+#[path = "../utils/process.rs"]
+pub mod process;
+#[path = "../utils/validation.rs"]
+pub mod validation;
+#[path = "../utils/security.rs"]
+pub mod security;
+#[path = "../utils/command.rs"]
+pub mod command;
+#[path = "../utils/filesystem.rs"]
+pub mod filesystem;
+#[path = "../utils/api_version.rs"]
+pub mod api_version;
+#[path = "../utils/name_policy.rs"]
+pub mod name_policy;
@@ -0,0 +1,283 @@
+// Raw bpf(2) plumbing for the cgroup programs in net_accounting, net_policy and device_policy
+// Just enough to create maps, load a hand-assembled cgroup_skb or cgroup_device program and attach
+// it to a cgroup directory, without libbpf or an object file. Programs are attached with
+// BPF_F_ALLOW_MULTI so accounting and policy programs can sit on the same cgroup; the kernel runs
+// all of them and drops the packet (or refuses the device) if any returns 0.
+
+use nix::libc;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+const BPF_MAP_CREATE: libc::c_long = 0;
+const BPF_MAP_LOOKUP_ELEM: libc::c_long = 1;
+const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+const BPF_MAP_DELETE_ELEM: libc::c_long = 3;
+const BPF_MAP_GET_NEXT_KEY: libc::c_long = 4;
+const BPF_PROG_LOAD: libc::c_long = 5;
+const BPF_PROG_ATTACH: libc::c_long = 8;
+const BPF_PROG_DETACH: libc::c_long = 9;
+
+pub const BPF_MAP_TYPE_HASH: u32 = 1;
+pub const BPF_MAP_TYPE_ARRAY: u32 = 2;
+pub const BPF_MAP_TYPE_LPM_TRIE: u32 = 11;
+/// Required for LPM tries
+pub const BPF_F_NO_PREALLOC: u32 = 1;
+const BPF_PROG_TYPE_CGROUP_SKB: u32 = 8;
+const BPF_PROG_TYPE_CGROUP_DEVICE: u32 = 15;
+const BPF_F_ALLOW_MULTI: u32 = 2;
+
+/// Flags for [`map_update`]
+pub const BPF_ANY: u64 = 0;
+pub const BPF_NOEXIST: u64 = 1;
+
+/// Where a cgroup program runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupHook {
+    Ingress = 0,
+    Egress = 1,
+    /// Device node access (open, mknod) by the cgroup's processes
+    Device = 6,
+}
+
+/// One eBPF instruction: opcode, dst/src registers, offset and immediate
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BpfInsn {
+    pub code: u8,
+    pub regs: u8,
+    pub off: i16,
+    pub imm: i32,
+}
+
+pub const fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> BpfInsn {
+    BpfInsn { code, regs: (src << 4) | (dst & 0x0f), off, imm }
+}
+
+/// Both halves of a 64-bit immediate load of a map file descriptor into `dst`
+pub const fn ld_map_fd(dst: u8, map: RawFd) -> [BpfInsn; 2] {
+    [insn(LD_IMM64, dst, BPF_PSEUDO_MAP_FD, 0, map), insn(0, 0, 0, 0, 0)]
+}
+
+pub const R0: u8 = 0;
+pub const R1: u8 = 1;
+pub const R2: u8 = 2;
+pub const R3: u8 = 3;
+pub const R4: u8 = 4;
+pub const R6: u8 = 6;
+pub const R7: u8 = 7;
+pub const R8: u8 = 8;
+pub const R10: u8 = 10;
+
+pub const MOV64_REG: u8 = 0xbf;
+pub const MOV64_IMM: u8 = 0xb7;
+pub const ADD64_IMM: u8 = 0x07;
+pub const AND64_IMM: u8 = 0x57;
+pub const AND64_REG: u8 = 0x5f;
+pub const RSH64_IMM: u8 = 0x77;
+pub const LDX_W: u8 = 0x61;
+pub const ST_W: u8 = 0x62;
+pub const ST_DW: u8 = 0x7a;
+pub const STX_W: u8 = 0x63;
+pub const STX_DW: u8 = 0x7b;
+pub const XADD_DW: u8 = 0xdb;
+pub const LD_IMM64: u8 = 0x18;
+pub const JA: u8 = 0x05;
+pub const JEQ_IMM: u8 = 0x15;
+pub const JEQ_REG: u8 = 0x1d;
+pub const JNE_IMM: u8 = 0x55;
+pub const CALL: u8 = 0x85;
+pub const EXIT: u8 = 0x95;
+
+/// src_reg marker telling the verifier that an LD_IMM64 immediate is a map file descriptor
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+pub const FN_MAP_LOOKUP_ELEM: i32 = 1;
+pub const FN_MAP_UPDATE_ELEM: i32 = 2;
+
+/// `struct __sk_buff` field offsets
+pub const SKB_LEN: i16 = 0;
+pub const SKB_FAMILY: i16 = 88;
+pub const SKB_REMOTE_IP4: i16 = 92;
+pub const AF_INET: i32 = 2;
+
+/// `struct bpf_cgroup_dev_ctx` field offsets; `access_type` is the access bits shifted left by 16
+/// over the device type
+pub const DEV_ACCESS_TYPE: i16 = 0;
+pub const DEV_MAJOR: i16 = 4;
+pub const DEV_MINOR: i16 = 8;
+
+#[repr(C)]
+#[derive(Default)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgAttachAttr {
+    target_fd: u32,
+    attach_bpf_fd: u32,
+    attach_type: u32,
+    attach_flags: u32,
+}
+
+fn bpf<T>(cmd: libc::c_long, attr: &mut T) -> std::io::Result<libc::c_long> {
+    let rc = unsafe { libc::syscall(libc::SYS_bpf, cmd, attr as *mut T, std::mem::size_of::<T>()) };
+    if rc < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(rc)
+    }
+}
+
+pub fn create_map(map_type: u32, key_size: u32, value_size: u32, max_entries: u32, map_flags: u32) -> Result<OwnedFd, String> {
+    let mut attr = MapCreateAttr { map_type, key_size, value_size, max_entries, map_flags };
+    let fd = bpf(BPF_MAP_CREATE, &mut attr).map_err(|e| format!("BPF map creation failed: {}", e))?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// Load a cgroup_skb program; on failure the verifier log is part of the error
+pub fn load_cgroup_skb_program(program: &[BpfInsn]) -> Result<OwnedFd, String> {
+    load_program(BPF_PROG_TYPE_CGROUP_SKB, program)
+}
+
+/// Load a cgroup_device program; on failure the verifier log is part of the error
+pub fn load_cgroup_device_program(program: &[BpfInsn]) -> Result<OwnedFd, String> {
+    load_program(BPF_PROG_TYPE_CGROUP_DEVICE, program)
+}
+
+fn load_program(prog_type: u32, program: &[BpfInsn]) -> Result<OwnedFd, String> {
+    let license = b"GPL\0";
+    let mut attr = ProgLoadAttr {
+        prog_type,
+        insn_cnt: program.len() as u32,
+        insns: program.as_ptr() as u64,
+        license: license.as_ptr() as u64,
+        ..Default::default()
+    };
+    match bpf(BPF_PROG_LOAD, &mut attr) {
+        Ok(fd) => Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) }),
+        Err(e) => {
+            // Load again with the verifier log on, only to explain the failure
+            let mut log = vec![0u8; 65536];
+            attr.log_level = 1;
+            attr.log_size = log.len() as u32;
+            attr.log_buf = log.as_mut_ptr() as u64;
+            let _ = bpf(BPF_PROG_LOAD, &mut attr).map(|fd| unsafe { libc::close(fd as RawFd) });
+            let verifier_log = String::from_utf8_lossy(&log);
+            let verifier_log = verifier_log.trim_end_matches('\0').trim();
+            Err(format!("BPF program load failed: {}{}", e,
+                if verifier_log.is_empty() { String::new() } else { format!(" ({})", verifier_log) }))
+        }
+    }
+}
+
+pub fn attach_to_cgroup(cgroup: &impl AsRawFd, program: &OwnedFd, hook: CgroupHook) -> Result<(), String> {
+    let mut attr = ProgAttachAttr {
+        target_fd: cgroup.as_raw_fd() as u32,
+        attach_bpf_fd: program.as_raw_fd() as u32,
+        attach_type: hook as u32,
+        attach_flags: BPF_F_ALLOW_MULTI,
+    };
+    bpf(BPF_PROG_ATTACH, &mut attr).map(|_| ()).map_err(|e| format!("BPF cgroup attach failed: {}", e))
+}
+
+pub fn detach_from_cgroup(cgroup: &impl AsRawFd, program: &OwnedFd, hook: CgroupHook) -> Result<(), String> {
+    let mut attr = ProgAttachAttr {
+        target_fd: cgroup.as_raw_fd() as u32,
+        attach_bpf_fd: program.as_raw_fd() as u32,
+        attach_type: hook as u32,
+        attach_flags: 0,
+    };
+    bpf(BPF_PROG_DETACH, &mut attr).map(|_| ()).map_err(|e| format!("BPF cgroup detach failed: {}", e))
+}
+
+/// Copy the value stored under `key` into `value`; false when the key is absent
+pub fn map_lookup(map: &OwnedFd, key: &[u8], value: &mut [u8]) -> bool {
+    let mut attr = MapElemAttr {
+        map_fd: map.as_raw_fd() as u32,
+        key: key.as_ptr() as u64,
+        value: value.as_mut_ptr() as u64,
+        ..Default::default()
+    };
+    bpf(BPF_MAP_LOOKUP_ELEM, &mut attr).is_ok()
+}
+
+pub fn map_update(map: &OwnedFd, key: &[u8], value: &[u8], flags: u64) -> Result<(), String> {
+    let mut attr = MapElemAttr {
+        map_fd: map.as_raw_fd() as u32,
+        key: key.as_ptr() as u64,
+        value: value.as_ptr() as u64,
+        flags,
+        ..Default::default()
+    };
+    bpf(BPF_MAP_UPDATE_ELEM, &mut attr).map(|_| ()).map_err(|e| format!("BPF map update failed: {}", e))
+}
+
+pub fn map_delete(map: &OwnedFd, key: &[u8]) -> Result<(), String> {
+    let mut attr = MapElemAttr {
+        map_fd: map.as_raw_fd() as u32,
+        key: key.as_ptr() as u64,
+        ..Default::default()
+    };
+    bpf(BPF_MAP_DELETE_ELEM, &mut attr).map(|_| ()).map_err(|e| format!("BPF map delete failed: {}", e))
+}
+
+/// Every key of a map with `N`-byte keys
+pub fn map_keys<const N: usize>(map: &OwnedFd) -> Vec<[u8; N]> {
+    let mut keys = Vec::new();
+    let mut key: Option<[u8; N]> = None;
+    loop {
+        let mut next = [0u8; N];
+        let mut attr = MapElemAttr {
+            map_fd: map.as_raw_fd() as u32,
+            key: key.as_ref().map(|k| k.as_ptr() as u64).unwrap_or(0),
+            value: next.as_mut_ptr() as u64,
+            ..Default::default()
+        };
+        if bpf(BPF_MAP_GET_NEXT_KEY, &mut attr).is_err() {
+            return keys;
+        }
+        keys.push(next);
+        key = Some(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_encoding() {
+        assert_eq!(std::mem::size_of::<BpfInsn>(), 8);
+        assert_eq!(insn(STX_W, R10, R1, -16, 0), BpfInsn { code: 0x63, regs: 0x1a, off: -16, imm: 0 });
+        // Map references are LD_IMM64 with the pseudo map-fd marker in src_reg
+        assert_eq!(ld_map_fd(R1, 7), [BpfInsn { code: LD_IMM64, regs: 0x11, off: 0, imm: 7 }, insn(0, 0, 0, 0, 0)]);
+    }
+}
@@ -0,0 +1,302 @@
+// Image builds
+// A Quiltfile describes an image as steps over a base image, one instruction per line:
+//   FROM <image>         the base: an image tarball, a tag in the image store or a registry reference
+//   RUN <command>        a command line for the build container's /bin/sh
+//   COPY <src> <dest>    a file or directory of the build context; of a directory, its contents
+//   ENV NAME=value ...   variables for later steps and the image's containers (`ENV NAME value` too)
+//   WORKDIR <path>       where later steps and the image's containers start
+// Blank lines and `#` comments are skipped, and a trailing `\` continues an instruction on the
+// next line. The engine runs the steps in a temporary container (engine/build.rs); this module
+// parses the file and moves files in and out: COPY sources go in as tar streams unpacked by the
+// container's own tar, so the image's symlinks cannot send them elsewhere on the host, and the
+// finished rootfs comes out as a one-layer `docker save` archive, which the image store imports
+// like any other image.
+
+use crate::daemon::image_config::{ImageConfig, IMAGE_CONFIG_FILE};
+use crate::daemon::registry;
+use crate::utils::validation::InputValidator;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use tar::{Builder, HeaderMode};
+
+/// Build file looked for in the context directory when none is named
+pub const QUILTFILE_NAME: &str = "Quiltfile";
+
+/// Directories whose contents belong to the running container, not to the image
+const RUNTIME_DIRS: [&str; 3] = ["proc", "sys", "dev"];
+
+/// One instruction after FROM
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildStep {
+    Run(String),
+    Copy { source: String, destination: String },
+    Env(Vec<(String, String)>),
+    Workdir(String),
+}
+
+impl fmt::Display for BuildStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildStep::Run(command) => write!(f, "RUN {}", command),
+            BuildStep::Copy { source, destination } => write!(f, "COPY {} {}", source, destination),
+            BuildStep::Env(variables) => {
+                let assignments: Vec<String> = variables.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+                write!(f, "ENV {}", assignments.join(" "))
+            }
+            BuildStep::Workdir(path) => write!(f, "WORKDIR {}", path),
+        }
+    }
+}
+
+/// A parsed build file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quiltfile {
+    pub base: String,
+    pub steps: Vec<BuildStep>,
+}
+
+impl Quiltfile {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut base = None;
+        let mut steps = Vec::new();
+        for (line_number, instruction) in instructions(content) {
+            let (keyword, arguments) = instruction.split_once(char::is_whitespace).unwrap_or((instruction.as_str(), ""));
+            let arguments = arguments.trim();
+            let keyword = keyword.to_ascii_uppercase();
+            let invalid = |message: &str| format!("Quiltfile line {}: {}", line_number, message);
+            if arguments.is_empty() {
+                return Err(invalid(&format!("{} needs an argument", keyword)));
+            }
+            if base.is_none() && keyword != "FROM" {
+                return Err(invalid("the first instruction must be FROM"));
+            }
+            match keyword.as_str() {
+                "FROM" if base.is_some() => return Err(invalid("only one FROM is supported")),
+                "FROM" => base = Some(arguments.to_string()),
+                "RUN" => steps.push(BuildStep::Run(arguments.to_string())),
+                "COPY" => match arguments.split_whitespace().collect::<Vec<_>>()[..] {
+                    [source, destination] => steps.push(BuildStep::Copy { source: source.to_string(), destination: destination.to_string() }),
+                    _ => return Err(invalid("COPY takes a source and a destination")),
+                },
+                "ENV" => steps.push(BuildStep::Env(parse_env(arguments).map_err(|e| invalid(&e))?)),
+                "WORKDIR" => steps.push(BuildStep::Workdir(arguments.to_string())),
+                other => return Err(invalid(&format!("unknown instruction {}", other))),
+            }
+        }
+        let base = base.ok_or("Quiltfile has no FROM instruction")?;
+        Ok(Quiltfile { base, steps })
+    }
+}
+
+/// Instructions with their first line number, continuations joined and comments dropped
+fn instructions(content: &str) -> Vec<(usize, String)> {
+    let mut instructions = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if pending.is_none() && (line.is_empty() || line.starts_with('#')) {
+            continue;
+        }
+        let (text, continued) = match line.strip_suffix('\\') {
+            Some(text) => (text.trim_end(), true),
+            None => (line, false),
+        };
+        let (_, instruction) = pending.get_or_insert_with(|| (index + 1, String::new()));
+        if !instruction.is_empty() && !text.is_empty() {
+            instruction.push(' ');
+        }
+        instruction.push_str(text);
+        if !continued {
+            instructions.extend(pending.take());
+        }
+    }
+    instructions.extend(pending);
+    instructions
+}
+
+fn parse_env(arguments: &str) -> Result<Vec<(String, String)>, String> {
+    let first = arguments.split_whitespace().next().unwrap_or_default();
+    let variables = if first.contains('=') {
+        arguments.split_whitespace()
+            .map(|assignment| {
+                let (name, value) = assignment.split_once('=').ok_or_else(|| format!("expected NAME=value, got '{}'", assignment))?;
+                Ok((name.to_string(), value.trim_matches('"').to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    } else {
+        let (name, value) = arguments.split_once(char::is_whitespace).ok_or("ENV needs a value")?;
+        vec![(name.to_string(), value.trim().to_string())]
+    };
+    for (name, _) in &variables {
+        InputValidator::validate_env_name(name)?;
+    }
+    Ok(variables)
+}
+
+/// `path` inside the build container, relative paths taken from `working_dir`. A trailing `/`,
+/// which makes COPY put a file into the directory, is kept.
+pub fn container_path(working_dir: Option<&str>, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+    let base = working_dir.unwrap_or("/").trim_end_matches('/');
+    format!("{}/{}", base, path.trim_start_matches("./"))
+}
+
+/// A tar stream of COPY source `source`, a path inside `context`, with its entries named as
+/// they are to be unpacked at the container's `/`. Owned by root, as COPY leaves files. Blocking.
+pub fn copy_archive(context: &Path, source: &str, destination: &str) -> Result<Vec<u8>, String> {
+    let context = context.canonicalize().map_err(|e| format!("Build context {}: {}", context.display(), e))?;
+    let source_path = context.join(source).canonicalize().map_err(|e| format!("COPY source {}: {}", source, e))?;
+    if !source_path.starts_with(&context) {
+        return Err(format!("COPY source {} is outside the build context", source));
+    }
+    if !destination.starts_with('/') || Path::new(destination).components().any(|component| component == Component::ParentDir) {
+        return Err(format!("Invalid COPY destination {}", destination));
+    }
+
+    let mut builder = Builder::new(Vec::new());
+    builder.mode(HeaderMode::Deterministic);
+    builder.follow_symlinks(false);
+    let target = PathBuf::from(destination.trim_start_matches('/'));
+    let appended = if source_path.is_dir() {
+        builder.append_dir_all(&target, &source_path)
+    } else if destination.ends_with('/') {
+        let file_name = source_path.file_name().ok_or_else(|| format!("COPY source {} has no file name", source))?;
+        builder.append_path_with_name(&source_path, target.join(file_name))
+    } else {
+        builder.append_path_with_name(&source_path, &target)
+    };
+    appended.and_then(|_| builder.into_inner())
+        .map_err(|e| format!("Failed to pack COPY source {}: {}", source, e))
+}
+
+/// File name of the archive a build tagged `tag` is written to
+pub fn archive_file_name(tag: &str) -> String {
+    let name: String = tag.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("{}.tar", name)
+}
+
+/// Write the rootfs of a finished build, with `config`, as a one-layer `docker save` archive
+/// tagged `tag` at `output`. The archive is assembled next to `output` and renamed into place.
+/// Blocking.
+pub fn write_image(rootfs: &Path, config: &ImageConfig, tag: &str, output: &Path) -> Result<(), String> {
+    let parent = output.parent().ok_or_else(|| format!("Invalid image path {}", output.display()))?;
+    let staging = parent.join(format!(".build-{}", uuid::Uuid::new_v4()));
+    // Not inside the staging directory, which is archived whole
+    let partial = staging.with_extension("tar");
+    let written = fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create {}: {}", staging.display(), e))
+        .and_then(|_| export_rootfs(rootfs, &staging.join("layer.tar")))
+        .and_then(|_| fs::write(staging.join("config.json"), config.to_document()).map_err(|e| format!("Failed to write image config: {}", e)))
+        .and_then(|_| registry::write_archive(&staging, "config.json", &["layer.tar".to_string()], &[tag.to_string()], &partial))
+        .and_then(|_| fs::rename(&partial, output).map_err(|e| format!("Failed to move image to {}: {}", output.display(), e)));
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_file(&partial);
+    written
+}
+
+/// Pack `rootfs` into a layer tarball at `layer`. The runtime directories are kept empty,
+/// sockets are skipped, and the saved image config is left out: the archive carries its own.
+fn export_rootfs(rootfs: &Path, layer: &Path) -> Result<(), String> {
+    let file = File::create(layer).map_err(|e| format!("Failed to create {}: {}", layer.display(), e))?;
+    let mut builder = Builder::new(file);
+    builder.follow_symlinks(false);
+    registry::append_tree(&mut builder, rootfs, &|entry, file_type| {
+        if entry == Path::new(IMAGE_CONFIG_FILE) || std::os::unix::fs::FileTypeExt::is_socket(file_type) {
+            registry::TreeEntry::Skip
+        } else if RUNTIME_DIRS.iter().any(|dir| entry == Path::new(dir)) {
+            registry::TreeEntry::Shallow
+        } else {
+            registry::TreeEntry::Full
+        }
+    })
+        .and_then(|_| builder.finish())
+        .map_err(|e| format!("Failed to pack rootfs {}: {}", rootfs.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_parse_quiltfile() {
+        let quiltfile = Quiltfile::parse("# demo\nFROM alpine:3.19\n\nrun apk add \\\n    curl\nCOPY app/ /srv/app\n\
+            ENV MODE=prod LANG=\"C.UTF-8\"\nENV GREETING hello world\nWORKDIR /srv\n").unwrap();
+        assert_eq!(quiltfile.base, "alpine:3.19");
+        assert_eq!(quiltfile.steps, vec![
+            BuildStep::Run("apk add curl".to_string()),
+            BuildStep::Copy { source: "app/".to_string(), destination: "/srv/app".to_string() },
+            BuildStep::Env(vec![("MODE".to_string(), "prod".to_string()), ("LANG".to_string(), "C.UTF-8".to_string())]),
+            BuildStep::Env(vec![("GREETING".to_string(), "hello world".to_string())]),
+            BuildStep::Workdir("/srv".to_string()),
+        ]);
+        assert_eq!(quiltfile.steps[2].to_string(), "ENV MODE=prod LANG=C.UTF-8");
+
+        for (content, error) in [
+            ("RUN true", "line 1: the first instruction must be FROM"),
+            ("FROM a\nFROM b", "line 2: only one FROM"),
+            ("FROM a\nCOPY one", "line 2: COPY takes"),
+            ("FROM a\nENV 1X=y", "line 2: Invalid environment variable name"),
+            ("FROM a\nEXPOSE 80", "line 2: unknown instruction EXPOSE"),
+            ("# nothing", "no FROM"),
+        ] {
+            let e = Quiltfile::parse(content).unwrap_err();
+            assert!(e.contains(error), "{}: {}", content, e);
+        }
+
+        assert_eq!(container_path(None, "app"), "/app");
+        assert_eq!(container_path(Some("/srv/"), "./bin/"), "/srv/bin/");
+        assert_eq!(container_path(Some("/srv"), "/etc/motd"), "/etc/motd");
+        assert_eq!(archive_file_name("ghcr.io/org/app:v2"), "ghcr.io_org_app_v2.tar");
+    }
+
+    fn entry_names(archive: &[u8]) -> Vec<String> {
+        tar::Archive::new(archive).entries().unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_copy_and_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = dir.path().join("context");
+        fs::write(dir.path().join("secret"), "x").unwrap();
+        fs::create_dir_all(context.join("app/bin")).unwrap();
+        fs::write(context.join("app/bin/run"), "#!/bin/sh").unwrap();
+        fs::write(context.join("motd"), "hi").unwrap();
+
+        assert_eq!(entry_names(&copy_archive(&context, "motd", "/etc/motd").unwrap()), vec!["etc/motd"]);
+        assert_eq!(entry_names(&copy_archive(&context, "motd", "/etc/").unwrap()), vec!["etc/motd"]);
+        let names = entry_names(&copy_archive(&context, "app", "/srv/app").unwrap());
+        assert!(names.contains(&"srv/app/bin/run".to_string()), "{:?}", names);
+        assert!(copy_archive(&context, "../secret", "/x").unwrap_err().contains("outside the build context"));
+        assert!(copy_archive(&context, "motd", "relative").is_err());
+
+        let rootfs = context.join("app");
+        fs::create_dir_all(rootfs.join("proc/1")).unwrap();
+        fs::write(rootfs.join(IMAGE_CONFIG_FILE), "{}").unwrap();
+        let config = ImageConfig { env: vec!["MODE=prod".to_string()], working_dir: Some("/srv".to_string()), user: None, architecture: None };
+        let output = dir.path().join("out/demo_1.tar");
+        fs::create_dir_all(output.parent().unwrap()).unwrap();
+        write_image(&rootfs, &config, "demo:1", &output).unwrap();
+        assert_eq!(fs::read_dir(output.parent().unwrap()).unwrap().count(), 1);
+
+        let mut manifest = String::new();
+        let mut layer = Vec::new();
+        for entry in tar::Archive::new(File::open(&output).unwrap()).entries().unwrap() {
+            let mut entry = entry.unwrap();
+            match entry.path().unwrap().to_str().unwrap() {
+                "manifest.json" => { entry.read_to_string(&mut manifest).unwrap(); }
+                "layer.tar" => { entry.read_to_end(&mut layer).unwrap(); }
+                _ => {}
+            }
+        }
+        assert!(manifest.contains("\"demo:1\""));
+        assert_eq!(entry_names(&layer), vec!["bin", "bin/run", "proc"]);
+    }
+}
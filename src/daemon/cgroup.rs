@@ -1,6 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 use nix::unistd::Pid;
+use crate::daemon::exec_cgroup::workload_path;
 use crate::utils::console::ConsoleLogger;
 use crate::utils::process::ProcessUtils;
 
@@ -10,7 +11,11 @@ pub struct CgroupLimits {
     pub cpu_shares: Option<u64>,          // CPU shares (relative weight)
     pub cpu_quota: Option<i64>,           // CPU quota in microseconds (-1 for unlimited)
     pub cpu_period: Option<u64>,          // CPU period in microseconds (default 100000)
+    pub cpu_burst: Option<u64>,           // Unused quota that may be spent above the quota, microseconds (<= quota)
     pub pids_limit: Option<u64>,          // Maximum number of PIDs
+    pub memory_high_bytes: Option<u64>,   // Throttle and reclaim above this (v2 memory.high, v1 soft limit)
+    pub memory_swap_bytes: Option<u64>,   // Swap allowed besides memory; Some(0) keeps the container out of swap
+    pub swappiness: Option<u64>,          // 0-100, cgroup v1 only (v2 has no per-cgroup swappiness)
 }
 
 impl Default for CgroupLimits {
@@ -20,12 +25,21 @@ impl Default for CgroupLimits {
             cpu_shares: Some(1024),                       // Default CPU shares
             cpu_quota: None,                             // No CPU quota by default
             cpu_period: Some(100000),                    // 100ms period
+            cpu_burst: None,                             // No bursting above the quota
             pids_limit: Some(1024),                      // 1024 PIDs limit
+            memory_high_bytes: None,                     // No throttling threshold
+            memory_swap_bytes: None,                     // Host swap behaviour
+            swappiness: None,
         }
     }
 }
 
 impl CgroupLimits {
+    /// CPU quota for `percent` of one CPU per period
+    pub fn quota_for_percent(percent: f64, period: u64) -> i64 {
+        (percent / 100.0 * period as f64).round() as i64
+    }
+
     /// Validate and adjust limits to prevent system issues
     pub fn validated(mut self) -> Self {
         // Enforce minimum memory limit of 256MB to prevent fork failures
@@ -50,6 +64,81 @@ impl CgroupLimits {
     }
 }
 
+/// Devices every container may use: mknod of any node, plus the standard character devices and
+/// pseudo-terminals. Everything else needs a grant.
+pub const DEFAULT_DEVICE_RULES: &[&str] = &[
+    "c *:* m",
+    "b *:* m",
+    "c 1:3 rwm",   // null
+    "c 1:5 rwm",   // zero
+    "c 1:7 rwm",   // full
+    "c 1:8 rwm",   // random
+    "c 1:9 rwm",   // urandom
+    "c 5:0 rwm",   // tty
+    "c 5:1 rwm",   // console
+    "c 5:2 rwm",   // ptmx
+    "c 136:* rwm", // pts
+];
+
+/// Device access rule in the devices cgroup format, e.g. "c 10:200 rwm"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceRule {
+    pub device_type: char,     // 'c' (char), 'b' (block) or 'a' (all devices)
+    pub major: Option<u32>,    // None = any major number
+    pub minor: Option<u32>,    // None = any minor number
+    pub access: String,        // Combination of r (read), w (write), m (mknod)
+}
+
+impl DeviceRule {
+    /// Parse a rule in "type major:minor access" form ("a" alone means all devices)
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = rule.split_whitespace().collect();
+
+        let device_type = match parts.first() {
+            Some(&"a") if parts.len() == 1 => {
+                return Ok(DeviceRule { device_type: 'a', major: None, minor: None, access: "rwm".to_string() });
+            }
+            Some(&"a") => 'a',
+            Some(&"b") => 'b',
+            Some(&"c") => 'c',
+            _ => return Err(format!("Invalid device rule '{}': type must be 'a', 'b' or 'c'", rule)),
+        };
+
+        if parts.len() != 3 {
+            return Err(format!("Invalid device rule '{}': expected 'type major:minor access'", rule));
+        }
+
+        let (major, minor) = parts[1].split_once(':')
+            .ok_or_else(|| format!("Invalid device rule '{}': expected major:minor", rule))?;
+        let parse_number = |n: &str| -> Result<Option<u32>, String> {
+            if n == "*" {
+                Ok(None)
+            } else {
+                n.parse::<u32>().map(Some).map_err(|_| format!("Invalid device number '{}' in rule '{}'", n, rule))
+            }
+        };
+
+        let access = parts[2];
+        if access.is_empty() || access.len() > 3 || !access.chars().all(|c| matches!(c, 'r' | 'w' | 'm'))
+            || access.chars().enumerate().any(|(i, c)| access[i + 1..].contains(c)) {
+            return Err(format!("Invalid device access '{}': must be a combination of r, w and m", access));
+        }
+
+        Ok(DeviceRule {
+            device_type,
+            major: parse_number(major)?,
+            minor: parse_number(minor)?,
+            access: access.to_string(),
+        })
+    }
+
+    /// Render the rule in the format expected by devices.allow / devices.deny
+    pub fn to_rule_string(&self) -> String {
+        let number = |n: Option<u32>| n.map(|v| v.to_string()).unwrap_or_else(|| "*".to_string());
+        format!("{} {}:{} {}", self.device_type, number(self.major), number(self.minor), self.access)
+    }
+}
+
 pub struct CgroupManager {
     cgroup_root: PathBuf,
     container_id: String,
@@ -138,6 +227,16 @@ impl CgroupManager {
                 } else {
                     ConsoleLogger::resource_limit_set("CPU quota", &format!("{} microseconds per {} microseconds", cpu_quota, cpu_period));
                 }
+
+                // Startup spikes (JIT warm-up, cache fills) may spend quota left unused earlier;
+                // needs Linux 5.14+, and the kernel refuses a burst above the quota
+                if let Some(cpu_burst) = limits.cpu_burst.filter(|_| cpu_quota > 0) {
+                    if let Err(e) = fs::write(container_cgroup.join("cpu.max.burst"), cpu_burst.to_string()) {
+                        ConsoleLogger::warning(&format!("Failed to set CPU burst: {}", e));
+                    } else {
+                        ConsoleLogger::resource_limit_set("CPU burst", &format!("{} microseconds", cpu_burst));
+                    }
+                }
             }
         }
 
@@ -226,6 +325,16 @@ impl CgroupManager {
                 }
             }
 
+            // The v1 counterpart of cpu.max.burst, written after the quota it is checked against
+            if let Some(cpu_burst) = limits.cpu_burst.filter(|_| limits.cpu_quota.map_or(false, |quota| quota > 0)) {
+                let cpu_burst_file = cpu_cgroup.join("cpu.cfs_burst_us");
+                if let Err(e) = fs::write(&cpu_burst_file, cpu_burst.to_string()) {
+                    ConsoleLogger::warning(&format!("Failed to set CPU burst: {}", e));
+                } else {
+                    ConsoleLogger::resource_limit_set("CPU burst", &format!("{} microseconds", cpu_burst));
+                }
+            }
+
             // Set CPU period
             if let Some(cpu_period) = limits.cpu_period {
                 let cpu_period_file = cpu_cgroup.join("cpu.cfs_period_us");
@@ -293,9 +402,53 @@ impl CgroupManager {
             }
         }
 
+        self.apply_memory_controls(limits);
         Ok(())
     }
 
+    /// Apply the swap and reclaim knobs. Like the memory limit they wait for initialization to
+    /// finish: a container pushed into reclaim or swap while it starts may never get ready.
+    fn apply_memory_controls(&self, limits: &CgroupLimits) {
+        let use_cgroup_v2 = self.cgroup_root.join("cgroup.controllers").exists();
+        if use_cgroup_v2 {
+            let container_cgroup = self.cgroup_root.join("quilt").join(&self.container_id);
+            if let Some(high) = limits.memory_high_bytes {
+                Self::write_memory_control(&container_cgroup.join("memory.high"), &high.to_string(), "memory high");
+            }
+            if let Some(swap) = limits.memory_swap_bytes {
+                // Missing without swap accounting (swapaccount=1 on older kernels)
+                Self::write_memory_control(&container_cgroup.join("memory.swap.max"), &swap.to_string(), "swap max");
+            }
+            if limits.swappiness.is_some() {
+                ConsoleLogger::warning("memory.swappiness is not available per cgroup on cgroup v2; use a swap limit instead");
+            }
+        } else {
+            let memory_cgroup = self.cgroup_root.join("memory/quilt").join(&self.container_id);
+            if let Some(swappiness) = limits.swappiness {
+                Self::write_memory_control(&memory_cgroup.join("memory.swappiness"), &swappiness.to_string(), "swappiness");
+            }
+            if let Some(high) = limits.memory_high_bytes {
+                Self::write_memory_control(&memory_cgroup.join("memory.soft_limit_in_bytes"), &high.to_string(), "memory soft limit");
+            }
+            // v1 limits memory and swap together
+            match (limits.memory_swap_bytes, limits.memory_limit_bytes) {
+                (Some(swap), Some(memory)) => {
+                    Self::write_memory_control(&memory_cgroup.join("memory.memsw.limit_in_bytes"), &(memory + swap).to_string(), "memory+swap");
+                }
+                (Some(_), None) => ConsoleLogger::warning("A swap limit needs a memory limit on cgroup v1; not applied"),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_memory_control(path: &std::path::Path, value: &str, name: &str) {
+        if let Err(e) = fs::write(path, value) {
+            ConsoleLogger::warning(&format!("Failed to set {} ({}): {}", name, path.display(), e));
+        } else {
+            ConsoleLogger::resource_limit_set(name, value);
+        }
+    }
+
     /// Add a process to the container's cgroups
     pub fn add_process(&self, pid: Pid) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Adding process {} to cgroups for container: {}", 
@@ -314,7 +467,8 @@ impl CgroupManager {
     /// Add process to cgroup v2
     fn add_process_v2(&self, pid: Pid) -> Result<(), String> {
         let container_cgroup = self.cgroup_root.join("quilt").join(&self.container_id);
-        let cgroup_procs = container_cgroup.join("cgroup.procs");
+        // After an exec session split the cgroup, only its workload leaf can hold processes
+        let cgroup_procs = workload_path(&container_cgroup).join("cgroup.procs");
 
         // Single attempt - cgroups should be ready by the time we reach this point
         // If they're not ready, it's a configuration issue, not a timing issue
@@ -390,6 +544,72 @@ impl CgroupManager {
         }
     }
 
+    /// Deny the container every device except DEFAULT_DEVICE_RULES. On cgroup v2 this attaches a
+    /// cgroup_device program (see device_policy); on v1 the process joins a devices cgroup that
+    /// starts from deny-all. Does nothing when the policy is already in place.
+    pub fn install_device_policy(&self, pid: Pid) -> Result<(), String> {
+        let defaults = DEFAULT_DEVICE_RULES.iter()
+            .map(|rule| DeviceRule::parse(rule))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let cgroup_v2_path = self.cgroup_root.join("cgroup.controllers");
+        if cgroup_v2_path.exists() {
+            let container_cgroup = self.cgroup_root.join("quilt").join(&self.container_id);
+            return crate::daemon::device_policy::install(&self.container_id, &container_cgroup, &defaults);
+        }
+
+        let devices_cgroup = self.cgroup_root.join("devices/quilt").join(&self.container_id);
+        if devices_cgroup.join("tasks").exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&devices_cgroup)
+            .map_err(|e| format!("Failed to create devices cgroup: {}", e))?;
+        // A new devices cgroup inherits allow-all; empty it before the process joins
+        fs::write(devices_cgroup.join("devices.deny"), "a")
+            .map_err(|e| format!("Failed to deny all devices: {}", e))?;
+        for rule in &defaults {
+            fs::write(devices_cgroup.join("devices.allow"), rule.to_rule_string())
+                .map_err(|e| format!("Failed to allow '{}': {}", rule.to_rule_string(), e))?;
+        }
+        fs::write(devices_cgroup.join("tasks"), ProcessUtils::pid_to_i32(pid).to_string())
+            .map_err(|e| format!("Failed to add process {} to devices cgroup: {}", ProcessUtils::pid_to_i32(pid), e))?;
+        Ok(())
+    }
+
+    /// Grant a running container access to a device
+    pub fn grant_device(&self, pid: Pid, rule: &DeviceRule) -> Result<(), String> {
+        self.write_device_rule(pid, rule, true)
+    }
+
+    /// Revoke a running container's access to a device
+    pub fn revoke_device(&self, pid: Pid, rule: &DeviceRule) -> Result<(), String> {
+        self.write_device_rule(pid, rule, false)
+    }
+
+    /// Add a rule to, or take it out of, the container's device policy, installing the default
+    /// policy first if the container has none yet
+    fn write_device_rule(&self, pid: Pid, rule: &DeviceRule, grant: bool) -> Result<(), String> {
+        self.install_device_policy(pid)?;
+        let control_file = if grant { "devices.allow" } else { "devices.deny" };
+        let rule_string = rule.to_rule_string();
+
+        let cgroup_v2_path = self.cgroup_root.join("cgroup.controllers");
+        if cgroup_v2_path.exists() {
+            if grant {
+                crate::daemon::device_policy::grant(&self.container_id, rule)?;
+            } else {
+                crate::daemon::device_policy::revoke(&self.container_id, rule)?;
+            }
+        } else {
+            let devices_cgroup = self.cgroup_root.join("devices/quilt").join(&self.container_id);
+            fs::write(devices_cgroup.join(control_file), &rule_string)
+                .map_err(|e| format!("Failed to write '{}' to {}: {}", rule_string, control_file, e))?;
+        }
+
+        ConsoleLogger::resource_limit_set(control_file, &rule_string);
+        Ok(())
+    }
+
     /// Remove the container's cgroups
     pub fn cleanup(&self) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Cleaning up cgroups for container: {}", self.container_id));
@@ -400,6 +620,14 @@ impl CgroupManager {
         if use_cgroup_v2 {
             let container_cgroup = self.cgroup_root.join("quilt").join(&self.container_id);
             if container_cgroup.exists() {
+                // The workload leaf and any exec session cgroups go first
+                if let Ok(entries) = fs::read_dir(&container_cgroup) {
+                    for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+                        if let Err(e) = fs::remove_dir(entry.path()) {
+                            ConsoleLogger::warning(&format!("Failed to remove cgroup {}: {}", entry.path().display(), e));
+                        }
+                    }
+                }
                 if let Err(e) = fs::remove_dir(&container_cgroup) {
                     ConsoleLogger::warning(&format!("Failed to remove cgroup v2 directory: {}", e));
                 } else {
@@ -408,7 +636,7 @@ impl CgroupManager {
             }
         } else {
             // Remove v1 cgroups
-            let cgroups = vec!["memory", "cpu", "pids"];
+            let cgroups = vec!["memory", "cpu", "pids", "devices"];
             for cgroup_type in cgroups {
                 let cgroup_path = self.cgroup_root.join(format!("{}/quilt", cgroup_type)).join(&self.container_id);
                 if cgroup_path.exists() {
@@ -434,6 +662,11 @@ mod tests {
         assert_eq!(limits.cpu_shares, Some(1024));
         assert_eq!(limits.cpu_period, Some(100000));
         assert_eq!(limits.pids_limit, Some(1024));
+        // Swap and reclaim follow the host unless asked for
+        assert_eq!((limits.memory_high_bytes, limits.memory_swap_bytes, limits.swappiness), (None, None, None));
+        assert_eq!((limits.cpu_quota, limits.cpu_burst), (None, None));
+        assert_eq!(CgroupLimits::quota_for_percent(50.0, 100000), 50000);
+        assert_eq!(CgroupLimits::quota_for_percent(150.0, 100000), 150000);
     }
 
     #[test]
@@ -442,4 +675,24 @@ mod tests {
         assert_eq!(manager.container_id, "test-container");
         assert_eq!(manager.cgroup_root, PathBuf::from("/sys/fs/cgroup"));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_device_rule_parsing() {
+        let rule = DeviceRule::parse("c 10:200 rwm").unwrap();
+        assert_eq!(rule.device_type, 'c');
+        assert_eq!(rule.major, Some(10));
+        assert_eq!(rule.minor, Some(200));
+        assert_eq!(rule.to_rule_string(), "c 10:200 rwm");
+
+        let wildcard = DeviceRule::parse("b 8:* r").unwrap();
+        assert_eq!(wildcard.minor, None);
+        assert_eq!(wildcard.to_rule_string(), "b 8:* r");
+
+        assert_eq!(DeviceRule::parse("a").unwrap().to_rule_string(), "a *:* rwm");
+        assert!(DeviceRule::parse("x 1:3 rw").is_err());
+        assert!(DeviceRule::parse("c 1:3 rwx").is_err());
+        assert!(DeviceRule::parse("c 1:3 rr").is_err());
+        assert!(DeviceRule::parse("c 1-3 rw").is_err());
+        assert!(DEFAULT_DEVICE_RULES.iter().all(|rule| DeviceRule::parse(rule).is_ok()));
+    }
+}
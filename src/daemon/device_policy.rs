@@ -0,0 +1,235 @@
+// eBPF device access control for cgroup v2
+// cgroup v2 has no devices.allow/devices.deny; device access is decided by cgroup_device programs
+// attached to the container's cgroup. Each container gets one program and a hash map of
+// (type, major, minor) → allowed access bits, with u32::MAX standing for `*`. The program allows
+// an access when a single entry covers all of it, like the v1 devices controller's exceptions;
+// anything else is refused. Granting and revoking only rewrite the map.
+
+use crate::daemon::bpf::*;
+use crate::daemon::cgroup::DeviceRule;
+use crate::utils::console::ConsoleLogger;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Device rules a container's map may hold
+pub const MAX_DEVICE_RULES: u32 = 256;
+
+/// Key value matching any type, major or minor number
+const ANY: u32 = u32::MAX;
+
+/// `BPF_DEVCG_DEV_*` device types
+const DEV_BLOCK: u32 = 1;
+const DEV_CHAR: u32 = 2;
+
+/// `BPF_DEVCG_ACC_*` access bits
+const ACC_MKNOD: u32 = 1;
+const ACC_READ: u32 = 2;
+const ACC_WRITE: u32 = 4;
+
+static ENFORCED: once_cell::sync::Lazy<Mutex<HashMap<String, DevicePolicy>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Map and program enforcing one container's device rules; dropping it closes both descriptors
+struct DevicePolicy {
+    cgroup_dir: PathBuf,
+    rules_map: OwnedFd,
+    program: OwnedFd,
+}
+
+/// Attach a deny-by-default program allowing only `defaults` to the container's cgroup. Does
+/// nothing when the container already has one.
+pub fn install(container_id: &str, cgroup_dir: &Path, defaults: &[DeviceRule]) -> Result<(), String> {
+    let mut enforced = ENFORCED.lock().unwrap();
+    if enforced.contains_key(container_id) {
+        return Ok(());
+    }
+    let cgroup = fs::File::open(cgroup_dir)
+        .map_err(|e| format!("Failed to open cgroup {}: {}", cgroup_dir.display(), e))?;
+
+    // The map is filled before the program is attached, so the container never sees it empty
+    let rules_map = create_map(BPF_MAP_TYPE_HASH, 12, 4, MAX_DEVICE_RULES, 0)?;
+    for rule in defaults {
+        add_access(&rules_map, rule)?;
+    }
+    let program = load_cgroup_device_program(&device_program(rules_map.as_raw_fd()))?;
+    attach_to_cgroup(&cgroup, &program, CgroupHook::Device)?;
+
+    ConsoleLogger::debug(&format!("🔌 [DEVICE-POLICY] Default-deny device policy attached to {} ({} rules)",
+        container_id, defaults.len()));
+    enforced.insert(container_id.to_string(), DevicePolicy { cgroup_dir: cgroup_dir.to_path_buf(), rules_map, program });
+    Ok(())
+}
+
+/// Allow the access `rule` names on top of what the container may already do
+pub fn grant(container_id: &str, rule: &DeviceRule) -> Result<(), String> {
+    let enforced = ENFORCED.lock().unwrap();
+    let policy = enforced.get(container_id)
+        .ok_or_else(|| format!("Container {} has no device policy", container_id))?;
+    add_access(&policy.rules_map, rule)
+}
+
+/// Withdraw the access `rule` names from the entry with exactly its type and numbers; `a` drops
+/// every entry, leaving all devices denied
+pub fn revoke(container_id: &str, rule: &DeviceRule) -> Result<(), String> {
+    let enforced = ENFORCED.lock().unwrap();
+    let policy = enforced.get(container_id)
+        .ok_or_else(|| format!("Container {} has no device policy", container_id))?;
+
+    if rule.device_type == 'a' {
+        for key in map_keys::<12>(&policy.rules_map) {
+            map_delete(&policy.rules_map, &key)?;
+        }
+        return Ok(());
+    }
+    let key = rule_key(rule);
+    let remaining = current_access(&policy.rules_map, &key) & !access_bits(&rule.access);
+    if remaining == 0 {
+        // Already absent is fine: there was nothing to revoke
+        let _ = map_delete(&policy.rules_map, &key);
+        Ok(())
+    } else {
+        map_update(&policy.rules_map, &key, &remaining.to_ne_bytes(), BPF_ANY)
+    }
+}
+
+/// Detach the container's program and forget it; the cgroup allows every device afterwards
+pub fn remove(container_id: &str) {
+    let Some(policy) = ENFORCED.lock().unwrap().remove(container_id) else { return };
+    if let Ok(cgroup) = fs::File::open(&policy.cgroup_dir) {
+        let _ = detach_from_cgroup(&cgroup, &policy.program, CgroupHook::Device);
+    }
+}
+
+fn add_access(map: &OwnedFd, rule: &DeviceRule) -> Result<(), String> {
+    let key = rule_key(rule);
+    let access = current_access(map, &key) | access_bits(&rule.access);
+    map_update(map, &key, &access.to_ne_bytes(), BPF_ANY)
+}
+
+fn current_access(map: &OwnedFd, key: &[u8; 12]) -> u32 {
+    let mut value = [0u8; 4];
+    if map_lookup(map, key, &mut value) {
+        u32::from_ne_bytes(value)
+    } else {
+        0
+    }
+}
+
+/// Map key of a rule: device type (0 for `a`), major and minor, `*` as [`ANY`]
+fn rule_key(rule: &DeviceRule) -> [u8; 12] {
+    let (device_type, major, minor) = match rule.device_type {
+        'b' => (DEV_BLOCK, rule.major.unwrap_or(ANY), rule.minor.unwrap_or(ANY)),
+        'c' => (DEV_CHAR, rule.major.unwrap_or(ANY), rule.minor.unwrap_or(ANY)),
+        _ => (0, ANY, ANY),
+    };
+    let mut key = [0u8; 12];
+    key[..4].copy_from_slice(&device_type.to_ne_bytes());
+    key[4..8].copy_from_slice(&major.to_ne_bytes());
+    key[8..].copy_from_slice(&minor.to_ne_bytes());
+    key
+}
+
+fn access_bits(access: &str) -> u32 {
+    access.chars().fold(0, |bits, c| bits | match c {
+        'm' => ACC_MKNOD,
+        'r' => ACC_READ,
+        'w' => ACC_WRITE,
+        _ => 0,
+    })
+}
+
+/// Which parts of the device a lookup keeps; the rest are `*`
+#[derive(Clone, Copy)]
+enum KeyShape {
+    Exact,
+    AnyMinor,
+    AnyNumber,
+    AnyDevice,
+}
+
+/// Instructions per lookup in [`device_program`]
+const LOOKUP_LEN: usize = 14;
+
+/// cgroup_device program: look the device up as (type, major, minor), (type, major, *),
+/// (type, *, *) and (a, *, *), allowing the access as soon as one entry covers all of it
+fn device_program(rules_map: RawFd) -> Vec<BpfInsn> {
+    let shapes = [KeyShape::Exact, KeyShape::AnyMinor, KeyShape::AnyNumber, KeyShape::AnyDevice];
+    let allow = 5 + LOOKUP_LEN * shapes.len() + 2;
+
+    let mut program = vec![
+        insn(MOV64_REG, R6, R1, 0, 0),                      // 0: r6 = ctx
+        insn(LDX_W, R7, R6, DEV_ACCESS_TYPE, 0),            // 1
+        insn(MOV64_REG, R8, R7, 0, 0),                      // 2
+        insn(RSH64_IMM, R8, 0, 0, 16),                      // 3: r8 = requested access
+        insn(AND64_IMM, R7, 0, 0, 0xffff),                  // 4: r7 = device type
+    ];
+    for shape in shapes {
+        // key.type at fp-12, key.major at fp-8, key.minor at fp-4
+        program.push(match shape {
+            KeyShape::AnyDevice => insn(ST_W, R10, 0, -12, 0),
+            _ => insn(STX_W, R10, R7, -12, 0),
+        });
+        program.push(match shape {
+            KeyShape::Exact | KeyShape::AnyMinor => insn(LDX_W, R1, R6, DEV_MAJOR, 0),
+            _ => insn(MOV64_IMM, R1, 0, 0, ANY as i32),
+        });
+        program.push(insn(STX_W, R10, R1, -8, 0));
+        program.push(match shape {
+            KeyShape::Exact => insn(LDX_W, R1, R6, DEV_MINOR, 0),
+            _ => insn(MOV64_IMM, R1, 0, 0, ANY as i32),
+        });
+        program.push(insn(STX_W, R10, R1, -4, 0));
+        program.push(insn(MOV64_REG, R2, R10, 0, 0));
+        program.push(insn(ADD64_IMM, R2, 0, 0, -12));       // r2 = &key
+        program.extend_from_slice(&ld_map_fd(R1, rules_map));
+        program.push(insn(CALL, 0, 0, 0, FN_MAP_LOOKUP_ELEM));
+        program.push(insn(JEQ_IMM, R0, 0, 3, 0));           // no entry -> next lookup
+        program.push(insn(LDX_W, R0, R0, 0, 0));
+        program.push(insn(AND64_REG, R0, R8, 0, 0));
+        let jump = allow - (program.len() + 1);
+        program.push(insn(JEQ_REG, R0, R8, jump as i16, 0)); // covers the access -> allow
+    }
+    program.extend_from_slice(&[
+        insn(MOV64_IMM, R0, 0, 0, 0),                       // deny
+        insn(EXIT, 0, 0, 0, 0),
+        insn(MOV64_IMM, R0, 0, 0, 1),                       // allow
+        insn(EXIT, 0, 0, 0, 0),
+    ]);
+    program
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_keys_and_access() {
+        let key = rule_key(&DeviceRule::parse("c 136:* rw").unwrap());
+        assert_eq!(key[..4], DEV_CHAR.to_ne_bytes());
+        assert_eq!(key[4..8], 136u32.to_ne_bytes());
+        assert_eq!(key[8..], ANY.to_ne_bytes());
+        assert_eq!(rule_key(&DeviceRule::parse("a").unwrap())[..4], 0u32.to_ne_bytes());
+        assert_eq!(access_bits("rwm"), ACC_READ | ACC_WRITE | ACC_MKNOD);
+        assert_eq!(access_bits("m"), ACC_MKNOD);
+    }
+
+    #[test]
+    fn test_device_program() {
+        let program = device_program(9);
+        assert_eq!(program.len(), 5 + LOOKUP_LEN * 4 + 4);
+        for (index, instruction) in program.iter().enumerate() {
+            if matches!(instruction.code, JEQ_IMM | JEQ_REG) {
+                let target = index + 1 + instruction.off as usize;
+                assert!(target < program.len() && program[target].code != 0);
+            }
+            if instruction.code == JEQ_REG {
+                // Every covered access lands on the allow verdict
+                assert_eq!(program[index + 1 + instruction.off as usize], insn(MOV64_IMM, R0, 0, 0, 1));
+            }
+        }
+        assert_eq!(program.iter().filter(|instruction| instruction.code == LD_IMM64 && instruction.imm == 9).count(), 4);
+    }
+}
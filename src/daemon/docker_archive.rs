@@ -0,0 +1,602 @@
+// Docker archive import
+// Accepts `docker save` tarballs (manifest.json plus one tar per layer, in the classic layout or
+// the OCI layout newer Docker versions write) and plain OCI layout archives (`podman save
+// --format oci-archive`, skopeo), which only have index.json, as container images. The layers are applied oldest
+// first into the rootfs, honouring overlay whiteouts: `.wh.<name>` deletes a path from lower
+// layers and `.wh..wh..opq` empties a directory of everything lower layers put there.
+
+use crate::daemon::image_config::ImageConfig;
+use crate::daemon::platform::{self, Platform};
+use crate::utils::console::ConsoleLogger;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use tar::Archive;
+
+const MANIFEST: &str = "manifest.json";
+const OCI_INDEX: &str = "index.json";
+/// index.json annotations naming an image, most specific first
+const OCI_NAME_ANNOTATIONS: &[&str] = &["io.containerd.image.name", "org.opencontainers.image.ref.name"];
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+/// Size of a tar header block
+const TAR_BLOCK: usize = 512;
+/// Offset of the `ustar` magic in a tar header
+const TAR_MAGIC: usize = 257;
+/// Top-level files only a `docker save` archive has
+const ARCHIVE_MARKERS: &[&str] = &[MANIFEST, "repositories", "index.json", "oci-layout"];
+
+/// One image in manifest.json
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    #[serde(rename = "RepoTags", default)]
+    repo_tags: Option<Vec<String>>,
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+    /// The image config document, e.g. `<digest>.json`
+    #[serde(rename = "Config", default)]
+    config: Option<String>,
+}
+
+/// A descriptor in an OCI index or image manifest
+#[derive(Debug, Deserialize)]
+struct OciDescriptor {
+    digest: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+    /// Platform of an index entry
+    #[serde(default)]
+    platform: Option<Platform>,
+}
+
+/// An OCI index (`manifests`) or image manifest (`config` and `layers`)
+#[derive(Debug, Deserialize)]
+struct OciDocument {
+    #[serde(default)]
+    manifests: Vec<OciDescriptor>,
+    config: Option<OciDescriptor>,
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+}
+
+/// What an import applied
+#[derive(Debug, Clone)]
+pub struct ImportedImage {
+    pub repo_tags: Vec<String>,
+    pub layers: usize,
+    /// ENV, WORKDIR and USER the image was built with
+    pub config: ImageConfig,
+}
+
+/// A `docker save` archive unpacked into a staging directory
+#[derive(Debug, Clone)]
+pub struct UnpackedArchive {
+    pub repo_tags: Vec<String>,
+    /// Layer tarballs in the staging directory, oldest first
+    pub layers: Vec<PathBuf>,
+    pub config: ImageConfig,
+}
+
+/// Open an image tarball, gzip-compressed or not
+pub fn open_image(image_path: &str) -> Result<Box<dyn Read>, String> {
+    let file = File::open(image_path).map_err(|e| format!("Failed to open image file {}: {}", image_path, e))?;
+    open_maybe_gzip(file).map_err(|e| format!("Failed to read image file {}: {}", image_path, e))
+}
+
+pub fn open_maybe_gzip<R: Read + 'static>(source: R) -> std::io::Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(source);
+    let gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    Ok(if gzip { Box::new(GzDecoder::new(reader)) } else { Box::new(reader) })
+}
+
+/// Check that `image_path` is a readable tar archive, gzip-compressed or not, without unpacking
+/// it, so a bad image is refused before a container is created for it
+pub fn check_image(image_path: &str) -> Result<(), String> {
+    if image_path.is_empty() {
+        return Err("Image path is required".to_string());
+    }
+    let metadata = std::fs::metadata(image_path).map_err(|_| format!("Image file not found: {}", image_path))?;
+    if !metadata.is_file() {
+        return Err(format!("Invalid image {}: not a regular file", image_path));
+    }
+    let mut header = Vec::with_capacity(TAR_BLOCK);
+    open_image(image_path)?
+        .take(TAR_BLOCK as u64)
+        .read_to_end(&mut header)
+        .map_err(|e| format!("Invalid image {}: {}", image_path, e))?;
+    if header.len() < TAR_BLOCK || &header[TAR_MAGIC..TAR_MAGIC + 5] != b"ustar" {
+        return Err(format!("Invalid image {}: not a tar or tar.gz archive", image_path));
+    }
+    Ok(())
+}
+
+/// Whether the image is a `docker save` archive rather than a plain rootfs tarball. Decided by
+/// the first top-level entry that is not a layer directory or blob.
+pub fn is_docker_archive(image_path: &str) -> Result<bool, String> {
+    let mut archive = Archive::new(open_image(image_path)?);
+    let entries = archive.entries().map_err(|e| format!("Failed to read image {}: {}", image_path, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read image {}: {}", image_path, e))?;
+        let path = entry.path().map_err(|e| format!("Invalid path in image {}: {}", image_path, e))?;
+        if let Some(is_archive) = classify_entry(&path) {
+            return Ok(is_archive);
+        }
+    }
+    Ok(false)
+}
+
+/// Some(true) for an archive marker, Some(false) for anything a rootfs would contain, None when
+/// the entry could be either (a layer directory, a blob, the archive root)
+fn classify_entry(path: &Path) -> Option<bool> {
+    let relative = clean_relative(path)?;
+    let mut components = relative.components();
+    let top = components.next()?.as_os_str().to_str()?;
+    let nested = components.next().is_some();
+    if !nested && ARCHIVE_MARKERS.contains(&top) {
+        return Some(true);
+    }
+    let digest = top.strip_suffix(".json").unwrap_or(top);
+    if top == "blobs" || (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit())) {
+        return None;
+    }
+    Some(false)
+}
+
+/// Flatten a `docker save` archive into `rootfs_path`. The archive is unpacked next to the rootfs
+/// first, because the layer order is only known from manifest.json, which comes last.
+pub fn import(image_path: &str, rootfs_path: &str) -> Result<ImportedImage, String> {
+    let staging = format!("{}.import", rootfs_path.trim_end_matches('/'));
+    let result = unpack_and_apply(image_path, Path::new(&staging), Path::new(rootfs_path));
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        ConsoleLogger::debug(&format!("Failed to remove import staging directory {}: {}", staging, e));
+    }
+    result
+}
+
+fn unpack_and_apply(image_path: &str, staging: &Path, rootfs: &Path) -> Result<ImportedImage, String> {
+    let image = unpack_archive(image_path, staging)?;
+    for (index, layer) in image.layers.iter().enumerate() {
+        let file = File::open(layer)
+            .map_err(|e| format!("Failed to open layer {}: {}", layer.display(), e))?;
+        let reader = open_maybe_gzip(file).map_err(|e| format!("Failed to read layer {}: {}", layer.display(), e))?;
+        apply_layer(reader, rootfs).map_err(|e| format!("Failed to apply layer {} ({}): {}", index + 1, layer.display(), e))?;
+    }
+    Ok(ImportedImage { repo_tags: image.repo_tags, layers: image.layers.len(), config: image.config })
+}
+
+/// Unpack a `docker save` archive into `staging` and read its manifest: the layer tarballs in
+/// the order they apply, and the image config
+pub fn unpack_archive(image_path: &str, staging: &Path) -> Result<UnpackedArchive, String> {
+    std::fs::create_dir_all(staging)
+        .map_err(|e| format!("Failed to create staging directory {}: {}", staging.display(), e))?;
+    Archive::new(open_image(image_path)?).unpack(staging)
+        .map_err(|e| format!("Failed to unpack docker archive: {}", e))?;
+
+    let mut images: Vec<ManifestEntry> = match std::fs::read_to_string(staging.join(MANIFEST)) {
+        Ok(manifest) => serde_json::from_str(&manifest)
+            .map_err(|e| format!("Invalid {} in docker archive: {}", MANIFEST, e))?,
+        Err(_) if staging.join(OCI_INDEX).is_file() => vec![oci_layout_entry(staging)?],
+        Err(e) => return Err(format!("Docker archive has no readable {}: {}", MANIFEST, e)),
+    };
+    if images.is_empty() {
+        return Err(format!("Docker archive {} lists no images", image_path));
+    }
+    if images.len() > 1 {
+        ConsoleLogger::warning(&format!("Docker archive {} holds {} images; importing the first", image_path, images.len()));
+    }
+    let image = images.swap_remove(0);
+
+    let layers = image.layers.iter()
+        .map(|layer| clean_relative(Path::new(layer))
+            .map(|relative| staging.join(relative))
+            .ok_or_else(|| format!("Unsafe layer path in {}: {}", MANIFEST, layer)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // The layers are what the container needs; an unreadable config only costs its defaults
+    let config = match image.config.as_deref().map(|config| read_image_config(staging, config)).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            ConsoleLogger::warning(&format!("Ignoring the config of {}: {}", image_path, e));
+            ImageConfig::default()
+        }
+    };
+    platform::check_architecture(image_path, config.architecture.as_deref())?;
+
+    Ok(UnpackedArchive { repo_tags: image.repo_tags.unwrap_or_default(), layers, config })
+}
+
+/// The manifest.json entry of the first image in an OCI layout that has only index.json. A
+/// multi-platform index is followed to the manifest for the host's platform, an index without
+/// platforms to its first manifest.
+fn oci_layout_entry(staging: &Path) -> Result<ManifestEntry, String> {
+    let mut document = read_oci_document(staging, OCI_INDEX)?;
+    let mut repo_tags = Vec::new();
+    for _ in 0..4 {
+        if document.config.is_some() {
+            break;
+        }
+        let Some(descriptor) = host_manifest(&document.manifests)? else { break };
+        if repo_tags.is_empty() {
+            repo_tags = OCI_NAME_ANNOTATIONS.iter()
+                .filter_map(|key| descriptor.annotations.get(*key))
+                // A bare ref name is just a tag, e.g. `latest`
+                .find(|name| name.contains(':') || name.contains('/'))
+                .map(|name| vec![name.clone()])
+                .unwrap_or_default();
+        }
+        document = read_oci_document(staging, &oci_blob_path(&descriptor.digest)?)?;
+    }
+    let config = document.config.ok_or_else(|| format!("OCI layout {} leads to no image manifest", OCI_INDEX))?;
+    Ok(ManifestEntry {
+        repo_tags: Some(repo_tags),
+        layers: document.layers.iter().map(|layer| oci_blob_path(&layer.digest)).collect::<Result<_, _>>()?,
+        config: Some(oci_blob_path(&config.digest)?),
+    })
+}
+
+/// The index entry to follow on this host
+fn host_manifest(manifests: &[OciDescriptor]) -> Result<Option<&OciDescriptor>, String> {
+    if manifests.iter().all(|descriptor| descriptor.platform.is_none()) {
+        return Ok(manifests.first());
+    }
+    let architecture = platform::host_architecture();
+    platform::select(manifests, architecture, |descriptor| descriptor.platform.as_ref())
+        .map(Some)
+        .ok_or_else(|| format!("OCI layout has no image for linux/{} (available: {})",
+            architecture, platform::describe(manifests, |descriptor| descriptor.platform.as_ref())))
+}
+
+fn read_oci_document(staging: &Path, path: &str) -> Result<OciDocument, String> {
+    let relative = clean_relative(Path::new(path)).ok_or_else(|| format!("Unsafe path in OCI layout: {}", path))?;
+    let document = std::fs::read_to_string(staging.join(relative))
+        .map_err(|e| format!("Failed to read {} in OCI layout: {}", path, e))?;
+    serde_json::from_str(&document).map_err(|e| format!("Invalid {} in OCI layout: {}", path, e))
+}
+
+/// `blobs/<algorithm>/<hex>` for a digest
+fn oci_blob_path(digest: &str) -> Result<String, String> {
+    match digest.split_once(':') {
+        Some((algorithm, hex)) if !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) && algorithm.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            Ok(format!("blobs/{}/{}", algorithm, hex)),
+        _ => Err(format!("Invalid digest in OCI layout: {}", digest)),
+    }
+}
+
+fn read_image_config(staging: &Path, config: &str) -> Result<ImageConfig, String> {
+    let relative = clean_relative(Path::new(config))
+        .ok_or_else(|| format!("Unsafe config path in {}: {}", MANIFEST, config))?;
+    let document = std::fs::read_to_string(staging.join(relative))
+        .map_err(|e| format!("Failed to read image config {}: {}", config, e))?;
+    ImageConfig::parse(&document)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Whiteout {
+    /// Delete this path from lower layers
+    Remove(PathBuf),
+    /// Delete everything lower layers put in this directory
+    Opaque(PathBuf),
+}
+
+fn whiteout(relative: &Path) -> Option<Whiteout> {
+    let name = relative.file_name()?.to_str()?;
+    let parent = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+    if name == OPAQUE_WHITEOUT {
+        return Some(Whiteout::Opaque(parent));
+    }
+    name.strip_prefix(WHITEOUT_PREFIX)
+        .filter(|target| !target.is_empty())
+        .map(|target| Whiteout::Remove(parent.join(target)))
+}
+
+/// Apply one layer tar on top of what is already in `rootfs`
+fn apply_layer<R: Read>(reader: R, rootfs: &Path) -> std::io::Result<()> {
+    let rootfs = rootfs.canonicalize()?;
+    let mut archive = Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_overwrite(true);
+    // Paths this layer created; an opaque whiteout must not delete them
+    let mut unpacked: HashSet<PathBuf> = HashSet::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative = match clean_relative(&entry.path()?) {
+            Some(relative) => relative,
+            None => continue,
+        };
+        match whiteout(&relative) {
+            Some(Whiteout::Remove(target)) => {
+                if let Some(target) = inside(&rootfs, &target) {
+                    remove_existing(&target)?;
+                }
+            }
+            Some(Whiteout::Opaque(dir)) => {
+                let dir_path = match inside(&rootfs, &dir).filter(|path| path.is_dir()) {
+                    Some(dir_path) => dir_path,
+                    None => continue,
+                };
+                for child in std::fs::read_dir(&dir_path)? {
+                    let child = child?;
+                    let child_relative = dir.join(child.file_name());
+                    if !unpacked.iter().any(|path| path.starts_with(&child_relative)) {
+                        remove_existing(&child.path())?;
+                    }
+                }
+            }
+            None => {
+                // A layer replaces lower entries whole: a file over a directory, a directory over
+                // a file, and never writes through a symlink left by a lower layer
+                if let Some(target) = inside(&rootfs, &relative) {
+                    if let Ok(existing) = std::fs::symlink_metadata(&target) {
+                        if !(existing.is_dir() && entry.header().entry_type().is_dir()) {
+                            remove_existing(&target)?;
+                        }
+                    }
+                }
+                entry.unpack_in(&rootfs)?;
+                unpacked.insert(relative);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply a layer kept unpacked in the directory `layer`, whiteout files and all, on top of what
+/// is already in `rootfs`. Files are copied, so the rootfs never shares inodes with the layer.
+pub fn apply_layer_dir(layer: &Path, rootfs: &Path) -> std::io::Result<()> {
+    let rootfs = rootfs.canonicalize()?;
+    apply_dir_entries(layer, Path::new(""), &rootfs)
+}
+
+fn apply_dir_entries(layer: &Path, relative_dir: &Path, rootfs: &Path) -> std::io::Result<()> {
+    let mut names: Vec<_> = std::fs::read_dir(layer.join(relative_dir))?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    // Whiteouts only hide what lower layers put here, so they go before this layer's own entries
+    names.sort_by_key(|name| !name.to_string_lossy().starts_with(WHITEOUT_PREFIX));
+
+    for name in names {
+        let relative = relative_dir.join(&name);
+        let source = layer.join(&relative);
+        match whiteout(&relative) {
+            Some(Whiteout::Remove(target)) => {
+                if let Some(target) = inside(rootfs, &target) {
+                    remove_existing(&target)?;
+                }
+            }
+            Some(Whiteout::Opaque(dir)) => {
+                if let Some(dir_path) = inside(rootfs, &dir).filter(|path| path.is_dir()) {
+                    for child in std::fs::read_dir(&dir_path)? {
+                        remove_existing(&child?.path())?;
+                    }
+                }
+            }
+            None => {
+                let Some(target) = inside(rootfs, &relative) else { continue };
+                let metadata = std::fs::symlink_metadata(&source)?;
+                let existing = std::fs::symlink_metadata(&target).ok();
+                if existing.as_ref().map_or(false, |existing| !(existing.is_dir() && metadata.is_dir())) {
+                    remove_existing(&target)?;
+                }
+                let file_type = metadata.file_type();
+                if file_type.is_dir() {
+                    if !target.is_dir() {
+                        std::fs::create_dir(&target)?;
+                    }
+                } else if file_type.is_symlink() {
+                    std::os::unix::fs::symlink(std::fs::read_link(&source)?, &target)?;
+                } else if file_type.is_file() {
+                    std::fs::copy(&source, &target)?;
+                } else {
+                    ConsoleLogger::debug(&format!("Skipping special file {} in layer", relative.display()));
+                    continue;
+                }
+                // Ownership only sticks when running as root, like unpacking the tarball
+                let _ = std::os::unix::fs::lchown(&target, Some(metadata.uid()), Some(metadata.gid()));
+                if file_type.is_dir() {
+                    apply_dir_entries(layer, &relative, rootfs)?;
+                    // After the children, so a read-only directory can still be filled
+                    std::fs::set_permissions(&target, metadata.permissions())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `relative` under `rootfs` with its parent resolved, or None when a symlink in a lower layer
+/// would lead outside the rootfs
+fn inside(rootfs: &Path, relative: &Path) -> Option<PathBuf> {
+    let name = relative.file_name()?;
+    let parent = rootfs.join(relative.parent().unwrap_or(Path::new(""))).canonicalize().ok()?;
+    parent.starts_with(rootfs).then(|| parent.join(name))
+}
+
+fn remove_existing(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The path without `.` components, or None when it is empty, absolute or climbs out with `..`
+fn clean_relative(path: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            match content {
+                Some(content) => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_size(content.len() as u64);
+                    header.set_mode(0o644);
+                    builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+                }
+                None => {
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_size(0);
+                    header.set_mode(0o755);
+                    builder.append_data(&mut header, path, std::io::empty()).unwrap();
+                }
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_classify_entries() {
+        let digest = "a".repeat(64);
+        assert_eq!(classify_entry(Path::new("./")), None);
+        assert_eq!(classify_entry(Path::new(&format!("{}/layer.tar", digest))), None);
+        assert_eq!(classify_entry(Path::new(&format!("{}.json", digest))), None);
+        assert_eq!(classify_entry(Path::new("blobs/sha256/abc")), None);
+        assert_eq!(classify_entry(Path::new("manifest.json")), Some(true));
+        assert_eq!(classify_entry(Path::new("./oci-layout")), Some(true));
+        assert_eq!(classify_entry(Path::new("./bin/sh")), Some(false));
+        assert_eq!(classify_entry(Path::new("etc/manifest.json")), Some(false));
+    }
+
+    #[test]
+    fn test_check_image() {
+        let dir = std::env::temp_dir().join(format!("quilt-check-image-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("rootfs.tar");
+        std::fs::write(&plain, layer(&[("bin/", None), ("bin/sh", Some("#!"))])).unwrap();
+        let gzipped = dir.join("rootfs.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &layer(&[("etc/motd", Some("hi"))])).unwrap();
+        std::fs::write(&gzipped, encoder.finish().unwrap()).unwrap();
+        let text = dir.join("notes.txt");
+        std::fs::write(&text, "not an image").unwrap();
+
+        assert!(check_image(plain.to_str().unwrap()).is_ok());
+        assert!(check_image(gzipped.to_str().unwrap()).is_ok());
+        assert!(check_image(text.to_str().unwrap()).unwrap_err().contains("not a tar"));
+        assert!(check_image(dir.to_str().unwrap()).unwrap_err().contains("not a regular file"));
+        assert!(check_image(dir.join("missing.tar").to_str().unwrap()).unwrap_err().starts_with("Image file not found"));
+        assert!(check_image("").is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_whiteouts() {
+        assert_eq!(whiteout(Path::new("etc/.wh.motd")), Some(Whiteout::Remove(PathBuf::from("etc/motd"))));
+        assert_eq!(whiteout(Path::new("var/cache/.wh..wh..opq")), Some(Whiteout::Opaque(PathBuf::from("var/cache"))));
+        assert_eq!(whiteout(Path::new("etc/motd")), None);
+        assert_eq!(clean_relative(Path::new("../etc/passwd")), None);
+        assert_eq!(clean_relative(Path::new("./usr/./bin")), Some(PathBuf::from("usr/bin")));
+    }
+
+    #[test]
+    fn test_import_applies_layers_in_order() {
+        let dir = std::env::temp_dir().join(format!("quilt-docker-archive-{}", std::process::id()));
+        let rootfs = dir.join("rootfs");
+        std::fs::create_dir_all(&rootfs).unwrap();
+
+        let base = layer(&[("etc/", None), ("etc/motd", Some("base")), ("etc/hostname", Some("old")),
+            ("var/", None), ("var/cache/", None), ("var/cache/stale", Some("x"))]);
+        let top = layer(&[("etc/.wh.hostname", Some("")), ("etc/motd", Some("top")),
+            ("var/cache/", None), ("var/cache/fresh", Some("y")), ("var/cache/.wh..wh..opq", Some(""))]);
+        // Classic layout: one directory per layer named by digest, manifest.json last
+        let (first, second) = (format!("{}/layer.tar", "1".repeat(64)), format!("{}/layer.tar", "2".repeat(64)));
+        let manifest = format!(r#"[{{"Config":"c.json","RepoTags":["demo:latest"],"Layers":["{}","{}"]}}]"#, first, second);
+        let config = r#"{"config":{"Env":["PATH=/usr/bin","LANG=C.UTF-8"],"WorkingDir":"/srv","User":"app"}}"#;
+
+        let mut outer = tar::Builder::new(Vec::new());
+        for (path, data) in [(first.as_str(), base.as_slice()), (second.as_str(), top.as_slice()), ("c.json", config.as_bytes()), ("manifest.json", manifest.as_bytes())] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            outer.append_data(&mut header, path, data).unwrap();
+        }
+        let image = dir.join("image.tar");
+        std::fs::write(&image, outer.into_inner().unwrap()).unwrap();
+
+        let image_path = image.to_str().unwrap();
+        assert!(is_docker_archive(image_path).unwrap());
+        let imported = import(image_path, rootfs.to_str().unwrap()).unwrap();
+        assert_eq!(imported.layers, 2);
+        assert_eq!(imported.repo_tags, vec!["demo:latest"]);
+        assert_eq!(imported.config.env, vec!["PATH=/usr/bin", "LANG=C.UTF-8"]);
+        assert_eq!((imported.config.working_dir.as_deref(), imported.config.user.as_deref()), (Some("/srv"), Some("app")));
+        assert_eq!(std::fs::read_to_string(rootfs.join("etc/motd")).unwrap(), "top");
+        assert!(!rootfs.join("etc/hostname").exists());
+        assert!(!rootfs.join("var/cache/stale").exists());
+        assert!(rootfs.join("var/cache/fresh").exists());
+        assert!(!dir.join("rootfs.import").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_import_oci_layout() {
+        let dir = std::env::temp_dir().join(format!("quilt-oci-layout-{}", std::process::id()));
+        let rootfs = dir.join("rootfs");
+        std::fs::create_dir_all(&rootfs).unwrap();
+
+        // index.json -> manifest -> config and layer blobs, without a manifest.json
+        let (layer_hex, config_hex, manifest_hex) = ("1".repeat(64), "2".repeat(64), "3".repeat(64));
+        let index = format!(r#"{{"schemaVersion":2,"manifests":[{{"digest":"sha256:{}","annotations":{{"org.opencontainers.image.ref.name":"registry.local/demo:1"}}}}]}}"#, manifest_hex);
+        let manifest = format!(r#"{{"schemaVersion":2,"config":{{"digest":"sha256:{}"}},"layers":[{{"digest":"sha256:{}"}}]}}"#, config_hex, layer_hex);
+        let config = r#"{"config":{"WorkingDir":"/app"}}"#;
+        let base = layer(&[("etc/", None), ("etc/motd", Some("oci"))]);
+
+        let mut outer = tar::Builder::new(Vec::new());
+        let entries = [
+            ("oci-layout".to_string(), br#"{"imageLayoutVersion":"1.0.0"}"#.to_vec()),
+            (format!("blobs/sha256/{}", layer_hex), base),
+            (format!("blobs/sha256/{}", config_hex), config.as_bytes().to_vec()),
+            (format!("blobs/sha256/{}", manifest_hex), manifest.into_bytes()),
+            ("index.json".to_string(), index.into_bytes()),
+        ];
+        for (path, data) in &entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            outer.append_data(&mut header, path, data.as_slice()).unwrap();
+        }
+        let image = dir.join("image.oci.tar");
+        std::fs::write(&image, outer.into_inner().unwrap()).unwrap();
+
+        let image_path = image.to_str().unwrap();
+        assert!(is_docker_archive(image_path).unwrap());
+        let imported = import(image_path, rootfs.to_str().unwrap()).unwrap();
+        assert_eq!(imported.repo_tags, vec!["registry.local/demo:1"]);
+        assert_eq!(imported.config.working_dir.as_deref(), Some("/app"));
+        assert_eq!(std::fs::read_to_string(rootfs.join("etc/motd")).unwrap(), "oci");
+        assert!(oci_blob_path("sha256:../etc").is_err());
+
+        // A multi-platform index is followed to the host's manifest, or refused without one
+        let host = platform::host_architecture();
+        let manifests: Vec<OciDescriptor> = serde_json::from_str(&format!(r#"[
+            {{"digest":"sha256:a","platform":{{"architecture":"s390x","os":"linux"}}}},
+            {{"digest":"sha256:b","platform":{{"architecture":"{}","os":"linux"}}}}]"#, host)).unwrap();
+        assert_eq!(host_manifest(&manifests).unwrap().map(|descriptor| descriptor.digest.as_str()), Some("sha256:b"));
+        assert!(host_manifest(&manifests[..1]).unwrap_err().contains("linux/s390x"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
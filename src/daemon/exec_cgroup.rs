@@ -0,0 +1,198 @@
+// Exec session accounting
+// Every exec session runs in a transient cgroup below its container's, so the command is held to
+// the container's limits while its CPU time and memory are counted on their own. A cgroup v2
+// cgroup with children cannot hold processes itself, so the first session moves the container's
+// own processes into a `workload` leaf; container metrics read that leaf, which keeps exec
+// sessions out of the container's figures. When a session ends, processes it left running join
+// the workload and the session cgroup is removed.
+
+use crate::utils::console::ConsoleLogger;
+
+use once_cell::sync::Lazy;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// Leaf holding a container's own processes once it has had an exec session
+pub const WORKLOAD_CGROUP: &str = "workload";
+const EXEC_CGROUP_PREFIX: &str = "exec-";
+const CONTROLLERS: &str = "+memory +cpu +pids";
+/// Processes the container forks while they are being moved need another pass
+const DELEGATE_ATTEMPTS: usize = 5;
+
+/// Two sessions starting in the same container must not both move its processes
+static DELEGATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Resources an exec session used
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExecUsage {
+    pub cpu_usage_usec: u64,
+    pub memory_peak_bytes: u64,
+}
+
+/// The transient cgroup of one exec session; removed by [`ExecCgroup::remove`] or when dropped
+#[derive(Debug)]
+pub struct ExecCgroup {
+    container_path: PathBuf,
+    path: PathBuf,
+    removed: bool,
+}
+
+impl ExecCgroup {
+    /// Create a session cgroup in `container_id`'s cgroup
+    pub fn create(container_id: &str) -> Result<Self, String> {
+        let session_id = uuid::Uuid::new_v4().simple().to_string();
+        Self::create_in(Path::new(CGROUP_ROOT), container_id, &session_id[..12])
+    }
+
+    fn create_in(cgroup_root: &Path, container_id: &str, session_id: &str) -> Result<Self, String> {
+        if !cgroup_root.join("cgroup.controllers").exists() {
+            return Err("Exec session accounting requires cgroup v2".to_string());
+        }
+        let container_path = cgroup_root.join("quilt").join(container_id);
+        if !container_path.exists() {
+            return Err(format!("Container {} has no cgroup", container_id));
+        }
+        delegate(&container_path)?;
+
+        let path = container_path.join(format!("{}{}", EXEC_CGROUP_PREFIX, session_id));
+        fs::create_dir(&path)
+            .map_err(|e| format!("Failed to create exec cgroup {}: {}", path.display(), e))?;
+        Ok(Self { container_path, path, removed: false })
+    }
+
+    /// The file a process writes itself into to join the session
+    pub fn procs_file(&self) -> PathBuf {
+        self.path.join("cgroup.procs")
+    }
+
+    /// CPU time and peak memory of the session so far
+    pub fn usage(&self) -> ExecUsage {
+        let read = |file: &str| fs::read_to_string(self.path.join(file)).unwrap_or_default();
+        ExecUsage {
+            cpu_usage_usec: parse_cpu_usage(&read("cpu.stat")),
+            // memory.peak needs Linux 5.19; older kernels only report what is still charged
+            memory_peak_bytes: read("memory.peak").trim().parse()
+                .or_else(|_| read("memory.current").trim().parse())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Final usage of the session; processes still running move to the container's workload
+    pub fn remove(mut self) -> ExecUsage {
+        let usage = self.usage();
+        self.release();
+        usage
+    }
+
+    fn release(&mut self) {
+        if self.removed {
+            return;
+        }
+        self.removed = true;
+        let workload_procs = self.container_path.join(WORKLOAD_CGROUP).join("cgroup.procs");
+        if let Err(e) = move_processes(&self.procs_file(), &workload_procs) {
+            ConsoleLogger::warning(&format!("Failed to move processes out of {}: {}", self.path.display(), e));
+        }
+        if let Err(e) = fs::remove_dir(&self.path) {
+            ConsoleLogger::warning(&format!("Failed to remove exec cgroup {}: {}", self.path.display(), e));
+        }
+    }
+}
+
+impl Drop for ExecCgroup {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Where a container's own usage is counted: its workload leaf once exec sessions split it,
+/// otherwise the container cgroup itself
+pub fn workload_path(container_path: &Path) -> PathBuf {
+    let workload = container_path.join(WORKLOAD_CGROUP);
+    if workload.exists() {
+        workload
+    } else {
+        container_path.to_path_buf()
+    }
+}
+
+/// Move the container's processes into its workload leaf and enable controllers for children
+fn delegate(container_path: &Path) -> Result<(), String> {
+    let _guard = DELEGATE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let subtree_control = container_path.join("cgroup.subtree_control");
+    if fs::read_to_string(&subtree_control).map_or(false, |enabled| enabled.contains("memory")) {
+        return Ok(());
+    }
+
+    let workload = container_path.join(WORKLOAD_CGROUP);
+    if let Err(e) = fs::create_dir(&workload) {
+        if e.kind() != ErrorKind::AlreadyExists {
+            return Err(format!("Failed to create workload cgroup {}: {}", workload.display(), e));
+        }
+    }
+    let mut last_error = String::new();
+    for _ in 0..DELEGATE_ATTEMPTS {
+        move_processes(&container_path.join("cgroup.procs"), &workload.join("cgroup.procs"))?;
+        // Refused with EBUSY while the container cgroup still holds a process
+        match fs::write(&subtree_control, CONTROLLERS) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(format!("Failed to enable controllers in {}: {}", container_path.display(), last_error))
+}
+
+/// Move every process listed in `from` to the cgroup of `to`; processes that exit meanwhile are skipped
+fn move_processes(from: &Path, to: &Path) -> Result<(), String> {
+    let pids = fs::read_to_string(from)
+        .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    for pid in pids.lines().filter(|pid| !pid.trim().is_empty()) {
+        if let Err(e) = fs::write(to, pid.trim()) {
+            // ESRCH: the process exited after it was listed
+            if e.raw_os_error() != Some(nix::libc::ESRCH) {
+                return Err(format!("Failed to move process {} to {}: {}", pid, to.display(), e));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_cpu_usage(cpu_stat: &str) -> u64 {
+    cpu_stat.lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_cgroup_layout() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(ExecCgroup::create_in(root.path(), "c1", "s1").is_err());
+
+        fs::write(root.path().join("cgroup.controllers"), "cpu memory pids").unwrap();
+        assert!(ExecCgroup::create_in(root.path(), "c1", "s1").unwrap_err().contains("no cgroup"));
+
+        let container = root.path().join("quilt").join("c1");
+        fs::create_dir_all(&container).unwrap();
+        fs::write(container.join("cgroup.procs"), "4242\n").unwrap();
+        fs::write(container.join("cgroup.subtree_control"), "").unwrap();
+        assert_eq!(workload_path(&container), container);
+
+        let session = ExecCgroup::create_in(root.path(), "c1", "s1").unwrap();
+        assert_eq!(session.procs_file(), container.join("exec-s1").join("cgroup.procs"));
+        assert_eq!(fs::read_to_string(container.join("cgroup.subtree_control")).unwrap(), CONTROLLERS);
+        assert_eq!(fs::read_to_string(container.join("workload/cgroup.procs")).unwrap(), "4242");
+        assert_eq!(workload_path(&container), container.join(WORKLOAD_CGROUP));
+
+        fs::write(container.join("exec-s1/cpu.stat"), "usage_usec 1500\nuser_usec 1000\nsystem_usec 500\n").unwrap();
+        fs::write(container.join("exec-s1/memory.peak"), "8192\n").unwrap();
+        assert_eq!(session.usage(), ExecUsage { cpu_usage_usec: 1500, memory_peak_bytes: 8192 });
+    }
+}
@@ -0,0 +1,207 @@
+// Immutable container profile
+// One opinionated hardening switch: read-only rootfs with tmpfs for the directories programs
+// expect to write, a reduced capability set, and no-new-privileges. Applied by the container's
+// init process right before it execs the workload, so setup commands still run against a
+// writable rootfs.
+
+use crate::utils::console::ConsoleLogger;
+use nix::libc;
+use nix::mount::{mount, MsFlags};
+use std::path::Path;
+
+/// Directories mounted as fresh tmpfs in an immutable container (relative to the container root)
+pub const IMMUTABLE_WRITABLE_DIRS: &[&str] = &["/tmp", "/run", "/var/tmp"];
+
+/// Size of each writable tmpfs
+const IMMUTABLE_TMPFS_SIZE: &str = "64m";
+
+/// Capabilities an immutable container keeps; everything else leaves the bounding set
+pub const IMMUTABLE_KEPT_CAPABILITIES: &[(u32, &str)] = &[
+    (0, "CAP_CHOWN"),
+    (1, "CAP_DAC_OVERRIDE"),
+    (3, "CAP_FOWNER"),
+    (5, "CAP_KILL"),
+    (6, "CAP_SETGID"),
+    (7, "CAP_SETUID"),
+    (10, "CAP_NET_BIND_SERVICE"),
+];
+
+/// Highest capability number assumed when /proc/sys/kernel/cap_last_cap is unreadable
+const FALLBACK_LAST_CAP: u32 = 40;
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// Apply the immutable profile to the calling process. Must run after chroot, inside the
+/// container's mount namespace, while the process still holds CAP_SYS_ADMIN. `mount_targets`
+/// are the container's configured mounts, which stay as they are.
+pub fn apply_immutable_profile(mount_targets: &[String]) -> Result<(), String> {
+    mount_writable_tmpfs(mount_targets)?;
+    remount_root_readonly()?;
+    drop_capabilities()?;
+    set_no_new_privs()?;
+    ConsoleLogger::debug("Immutable profile applied: read-only root, tmpfs scratch dirs, reduced capabilities, no-new-privileges");
+    Ok(())
+}
+
+/// Writable dirs that get a tmpfs: a dir with a configured mount at or below it is left alone,
+/// since a tmpfs on top would hide that mount
+fn tmpfs_dirs(mount_targets: &[String]) -> Vec<&'static str> {
+    IMMUTABLE_WRITABLE_DIRS.iter()
+        .copied()
+        .filter(|dir| !mount_targets.iter().any(|target| {
+            let target = format!("/{}", target.trim_matches('/'));
+            target == *dir || target.starts_with(&format!("{}/", dir))
+        }))
+        .collect()
+}
+
+fn mount_writable_tmpfs(mount_targets: &[String]) -> Result<(), String> {
+    let options = format!("mode=1777,size={}", IMMUTABLE_TMPFS_SIZE);
+    let dirs = tmpfs_dirs(mount_targets);
+    for dir in IMMUTABLE_WRITABLE_DIRS.iter().copied() {
+        if !dirs.contains(&dir) {
+            ConsoleLogger::warning(&format!("Immutable profile: leaving {} as is because a configured mount lives there", dir));
+            continue;
+        }
+        // Must happen before the root goes read-only
+        if !Path::new(dir).exists() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {} for tmpfs: {}", dir, e))?;
+        }
+        mount(
+            Some("tmpfs"),
+            dir,
+            Some("tmpfs"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+            Some(options.as_str()),
+        ).map_err(|e| format!("Failed to mount tmpfs at {}: {}", dir, e))?;
+    }
+    Ok(())
+}
+
+fn remount_root_readonly() -> Result<(), String> {
+    // The rootfs is a bind mount of itself (see setup_mount_namespace), so this only flips that
+    // mount; volumes, tmpfs and /proc mounted below it keep their own flags
+    mount(
+        None::<&str>,
+        "/",
+        None::<&str>,
+        MsFlags::MS_REMOUNT | MsFlags::MS_BIND | MsFlags::MS_RDONLY,
+        None::<&str>,
+    ).map_err(|e| format!("Failed to remount root read-only: {}", e))
+}
+
+/// Bitmask of the capabilities kept by the immutable profile
+fn kept_capability_mask() -> u64 {
+    IMMUTABLE_KEPT_CAPABILITIES.iter().fold(0u64, |mask, (cap, _)| mask | (1u64 << cap))
+}
+
+fn drop_capabilities() -> Result<(), String> {
+    let kept = kept_capability_mask();
+    let last_cap = std::fs::read_to_string("/proc/sys/kernel/cap_last_cap")
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .unwrap_or(FALLBACK_LAST_CAP);
+
+    // Bounding set first: it caps what any later exec can regain
+    for cap in 0..=last_cap.min(63) {
+        if kept & (1u64 << cap) != 0 {
+            continue;
+        }
+        let rc = unsafe { libc::prctl(libc::PR_CAPBSET_DROP, cap as libc::c_ulong, 0, 0, 0) };
+        if rc != 0 {
+            let err = std::io::Error::last_os_error();
+            // EINVAL: the kernel does not know this capability
+            if err.raw_os_error() != Some(libc::EINVAL) {
+                return Err(format!("Failed to drop capability {} from the bounding set: {}", cap, err));
+            }
+        }
+    }
+
+    // Ambient capabilities would survive exec regardless of the bounding set; older kernels lack them
+    unsafe { libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL as libc::c_ulong, 0, 0, 0) };
+
+    // Root's exec'd programs get inheritable | bounding, so trim the inheritable set as well
+    let mut header = CapUserHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+    let mut data = [CapUserData::default(); 2];
+    if unsafe { libc::syscall(libc::SYS_capget, &mut header as *mut CapUserHeader, data.as_mut_ptr()) } != 0 {
+        return Err(format!("capget failed: {}", std::io::Error::last_os_error()));
+    }
+    for (index, set) in data.iter_mut().enumerate() {
+        let mask = (kept >> (32 * index)) as u32;
+        set.effective &= mask;
+        set.permitted &= mask;
+        set.inheritable &= mask;
+    }
+    if unsafe { libc::syscall(libc::SYS_capset, &mut header as *mut CapUserHeader, data.as_ptr()) } != 0 {
+        return Err(format!("capset failed: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Seccomp mode of the calling process from /proc/self/status: 0 none, 1 strict, 2 filter.
+/// Containers inherit the daemon's filter and nothing can lift it, so a privileged container
+/// is only unconfined when this is 0.
+pub fn seccomp_mode() -> u32 {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| parse_seccomp_mode(&status))
+        .unwrap_or(0)
+}
+
+fn parse_seccomp_mode(status: &str) -> Option<u32> {
+    status.lines()
+        .find_map(|line| line.strip_prefix("Seccomp:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn set_no_new_privs() -> Result<(), String> {
+    let rc = unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, 0, 0, 0) };
+    if rc != 0 {
+        return Err(format!("Failed to set no_new_privs: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kept_capability_mask() {
+        let mask = kept_capability_mask();
+        assert_eq!(mask.count_ones() as usize, IMMUTABLE_KEPT_CAPABILITIES.len());
+        assert_ne!(mask & (1 << 10), 0); // CAP_NET_BIND_SERVICE
+        assert_eq!(mask & (1 << 21), 0); // CAP_SYS_ADMIN
+        assert_eq!(mask & (1 << 12), 0); // CAP_NET_ADMIN
+    }
+
+    #[test]
+    fn test_parse_seccomp_mode() {
+        assert_eq!(parse_seccomp_mode("Name:\tquilt\nSeccomp:\t2\nSeccomp_filters:\t1\n"), Some(2));
+        assert_eq!(parse_seccomp_mode("Seccomp:\t0\n"), Some(0));
+        assert_eq!(parse_seccomp_mode("Name:\tquilt\n"), None);
+    }
+
+    #[test]
+    fn test_tmpfs_dirs_skip_configured_mounts() {
+        assert_eq!(tmpfs_dirs(&[]), IMMUTABLE_WRITABLE_DIRS.to_vec());
+        let targets = vec!["/run/secrets".to_string(), "data".to_string(), "/tmpdata".to_string()];
+        assert_eq!(tmpfs_dirs(&targets), vec!["/tmp", "/var/tmp"]);
+        assert_eq!(tmpfs_dirs(&["/tmp/".to_string()]), vec!["/run", "/var/tmp"]);
+    }
+}
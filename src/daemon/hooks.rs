@@ -0,0 +1,269 @@
+// Container lifecycle hooks
+// Host-side commands or webhooks a container asks the daemon to run when it starts, stops, dies
+// or is removed, e.g. to register it with an external load balancer. Commands get the container's
+// metadata in their environment; webhooks get it as a JSON POST body. Hooks run with the daemon's
+// privileges, outside the container.
+
+use crate::sync::events::{ContainerEvent, EventType};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Time a hook may run when it does not set its own
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u32 = 10;
+/// Upper bound for a hook's timeout
+pub const MAX_HOOK_TIMEOUT_SECS: u32 = 300;
+/// Events hooks can fire on
+pub const LIFECYCLE_EVENTS: [EventType; 4] = [EventType::Started, EventType::Stopped, EventType::Died, EventType::Removed];
+
+// Hooks of every container that has some, keyed by container ID
+static HOOKS: once_cell::sync::Lazy<Mutex<HashMap<String, RegisteredHooks>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HookAction {
+    /// Run through `/bin/sh -c` on the host
+    Command(String),
+    /// POST the event as JSON to this http(s) URL
+    Webhook(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleHook {
+    /// Events the hook fires on; empty means every lifecycle event
+    pub events: Vec<EventType>,
+    pub action: HookAction,
+    pub timeout_secs: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisteredHooks {
+    pub container_name: String,
+    pub hooks: Vec<LifecycleHook>,
+}
+
+impl LifecycleHook {
+    /// Build a hook from API fields: exactly one of `command` and `url`, event names as in
+    /// [`parse_event`], and a timeout in seconds (0 = [`DEFAULT_HOOK_TIMEOUT_SECS`])
+    pub fn new(events: &[String], command: &str, url: &str, timeout_secs: u32) -> Result<Self, String> {
+        let action = match (command.trim(), url.trim()) {
+            ("", "") => return Err("Hook needs a command or a url".to_string()),
+            (_, "") => HookAction::Command(command.to_string()),
+            ("", url) => {
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return Err(format!("Hook url '{}' must be http:// or https://", url));
+                }
+                HookAction::Webhook(url.to_string())
+            }
+            _ => return Err("Hook takes either a command or a url, not both".to_string()),
+        };
+        if timeout_secs > MAX_HOOK_TIMEOUT_SECS {
+            return Err(format!("Hook timeout must be at most {}s", MAX_HOOK_TIMEOUT_SECS));
+        }
+        let mut parsed = Vec::new();
+        for event in events {
+            let event = parse_event(event)?;
+            if !parsed.contains(&event) {
+                parsed.push(event);
+            }
+        }
+        Ok(Self {
+            events: parsed,
+            action,
+            timeout_secs: if timeout_secs == 0 { DEFAULT_HOOK_TIMEOUT_SECS } else { timeout_secs },
+        })
+    }
+
+    pub fn fires_on(&self, event_type: EventType) -> bool {
+        LIFECYCLE_EVENTS.contains(&event_type) && (self.events.is_empty() || self.events.contains(&event_type))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs as u64)
+    }
+
+    /// Short description for logs: the webhook URL or the command
+    pub fn describe(&self) -> &str {
+        match &self.action {
+            HookAction::Command(command) => command,
+            HookAction::Webhook(url) => url,
+        }
+    }
+}
+
+/// Parse a lifecycle event name; "start", "stop", "die" and "remove" are accepted as well
+pub fn parse_event(name: &str) -> Result<EventType, String> {
+    let name = name.trim().to_ascii_lowercase();
+    let name = match name.as_str() {
+        "start" => "started",
+        "stop" => "stopped",
+        "die" => "died",
+        "remove" | "rm" => "removed",
+        other => other,
+    };
+    EventType::from_str(name)
+        .filter(|event| LIFECYCLE_EVENTS.contains(event))
+        .ok_or_else(|| format!("Unknown hook event '{}' (expected started, stopped, died or removed)", name))
+}
+
+/// Remember a container's hooks; an empty list forgets them
+pub fn register(container_id: &str, container_name: &str, hooks: Vec<LifecycleHook>) {
+    let Ok(mut registry) = HOOKS.lock() else { return };
+    if hooks.is_empty() {
+        registry.remove(container_id);
+    } else {
+        registry.insert(container_id.to_string(), RegisteredHooks {
+            container_name: container_name.to_string(),
+            hooks,
+        });
+    }
+}
+
+/// The container's name and the hooks that fire on `event_type`, if any do
+pub fn hooks_for(container_id: &str, event_type: EventType) -> Option<(String, Vec<LifecycleHook>)> {
+    let registry = HOOKS.lock().ok()?;
+    let registered = registry.get(container_id)?;
+    let hooks: Vec<_> = registered.hooks.iter().filter(|hook| hook.fires_on(event_type)).cloned().collect();
+    (!hooks.is_empty()).then(|| (registered.container_name.clone(), hooks))
+}
+
+pub fn forget(container_id: &str) {
+    if let Ok(mut registry) = HOOKS.lock() {
+        registry.remove(container_id);
+    }
+}
+
+/// Environment for a command hook: QUILT_EVENT, QUILT_CONTAINER_ID, QUILT_CONTAINER_NAME,
+/// QUILT_CONTAINER_IP (when known), QUILT_EVENT_TIME (ms since the epoch), QUILT_LABEL_<KEY> per
+/// label and QUILT_ATTR_<KEY> per event attribute (e.g. QUILT_ATTR_EXIT_CODE)
+pub fn hook_env(event: &ContainerEvent, container_name: &str, ip_address: Option<&str>) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("QUILT_EVENT".to_string(), event.event_type.as_str().to_string()),
+        ("QUILT_CONTAINER_ID".to_string(), event.container_id.clone()),
+        ("QUILT_CONTAINER_NAME".to_string(), container_name.to_string()),
+        ("QUILT_EVENT_TIME".to_string(), event.timestamp.to_string()),
+    ];
+    if let Some(ip) = ip_address {
+        env.push(("QUILT_CONTAINER_IP".to_string(), ip.to_string()));
+    }
+    let mut labels: Vec<_> = event.labels.iter().collect();
+    labels.sort();
+    env.extend(labels.into_iter().map(|(key, value)| (format!("QUILT_LABEL_{}", env_key(key)), value.clone())));
+    let mut attributes: Vec<_> = event.attributes.iter().collect();
+    attributes.sort();
+    env.extend(attributes.into_iter().map(|(key, value)| (format!("QUILT_ATTR_{}", env_key(key)), value.clone())));
+    env
+}
+
+/// JSON body for a webhook
+pub fn hook_payload(event: &ContainerEvent, container_name: &str, ip_address: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "event": event.event_type.as_str(),
+        "container_id": event.container_id,
+        "container_name": container_name,
+        "ip_address": ip_address,
+        "timestamp": event.timestamp,
+        "labels": event.labels,
+        "attributes": event.attributes,
+    })
+}
+
+/// Run one hook for `event` and wait for it, up to its timeout
+pub async fn run_hook(hook: &LifecycleHook, event: &ContainerEvent, container_name: &str, ip_address: Option<&str>) -> Result<(), String> {
+    let mut command = match &hook.action {
+        HookAction::Command(script) => {
+            let mut command = tokio::process::Command::new("/bin/sh");
+            command.arg("-c").arg(script)
+                .envs(hook_env(event, container_name, ip_address))
+                .stdin(Stdio::null());
+            command
+        }
+        HookAction::Webhook(url) => {
+            // The body goes in on stdin so nothing from the event reaches an argument list
+            let max_time = hook.timeout_secs.to_string();
+            let mut command = tokio::process::Command::new("curl");
+            command.args(["-fsS", "-o", "/dev/null", "-m", max_time.as_str(), "-X", "POST",
+                    "-H", "Content-Type: application/json", "--data-binary", "@-", "--"])
+                .arg(url)
+                .stdin(Stdio::piped());
+            command
+        }
+    };
+    command.stdout(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true);
+
+    let mut child = command.spawn().map_err(|e| format!("failed to run: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let body = hook_payload(event, container_name, ip_address).to_string();
+        stdin.write_all(body.as_bytes()).await.map_err(|e| format!("failed to send body: {}", e))?;
+    }
+    let output = tokio::time::timeout(hook.timeout(), child.wait_with_output()).await
+        .map_err(|_| format!("timed out after {}s", hook.timeout_secs))?
+        .map_err(|e| format!("failed to wait: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr.trim().lines().last().unwrap_or_default();
+    Err(match output.status.code() {
+        Some(code) if reason.is_empty() => format!("exited with code {}", code),
+        Some(code) => format!("exited with code {}: {}", code, reason),
+        None => "killed by a signal".to_string(),
+    })
+}
+
+fn env_key(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_parsing() {
+        let hook = LifecycleHook::new(&["start".to_string(), "died".to_string(), "started".to_string()], "lb-register", "", 0).unwrap();
+        assert_eq!(hook.events, vec![EventType::Started, EventType::Died]);
+        assert_eq!(hook.timeout_secs, DEFAULT_HOOK_TIMEOUT_SECS);
+        assert!(hook.fires_on(EventType::Died));
+        assert!(!hook.fires_on(EventType::Removed));
+
+        let all = LifecycleHook::new(&[], "", "https://lb.example/hooks", 30).unwrap();
+        assert_eq!(all.action, HookAction::Webhook("https://lb.example/hooks".to_string()));
+        assert!(all.fires_on(EventType::Removed));
+        assert!(!all.fires_on(EventType::Created));
+
+        assert!(LifecycleHook::new(&[], "", "", 0).is_err());
+        assert!(LifecycleHook::new(&[], "true", "http://x", 0).is_err());
+        assert!(LifecycleHook::new(&[], "", "file:///etc/passwd", 0).is_err());
+        assert!(LifecycleHook::new(&["created".to_string()], "true", "", 0).is_err());
+        assert!(LifecycleHook::new(&[], "true", "", MAX_HOOK_TIMEOUT_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn test_hook_env() {
+        let event = ContainerEvent {
+            event_type: EventType::Died,
+            container_id: "c1".to_string(),
+            timestamp: 1700000000000,
+            attributes: HashMap::from([("exit_code".to_string(), "137".to_string())]),
+            labels: HashMap::from([("app.tier".to_string(), "web".to_string())]),
+            volume_labels: HashMap::new(),
+        };
+        let env: HashMap<_, _> = hook_env(&event, "web-1", Some("10.42.0.5")).into_iter().collect();
+        assert_eq!(env["QUILT_EVENT"], "died");
+        assert_eq!(env["QUILT_CONTAINER_NAME"], "web-1");
+        assert_eq!(env["QUILT_CONTAINER_IP"], "10.42.0.5");
+        assert_eq!(env["QUILT_ATTR_EXIT_CODE"], "137");
+        assert_eq!(env["QUILT_LABEL_APP_TIER"], "web");
+
+        let payload = hook_payload(&event, "web-1", None);
+        assert_eq!(payload["event"], "died");
+        assert!(payload["ip_address"].is_null());
+    }
+}
@@ -0,0 +1,283 @@
+// Host prerequisite checks
+// Verifies the kernel features, tools, privileges and network layout the daemon depends on and
+// reports each as pass/warn/fail with a remediation hint, so a broken host is diagnosed up front
+// instead of through a failed container start
+
+use crate::icc::network::HostFirewall;
+use crate::utils::command::CommandExecutor;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but a feature is degraded or unavailable
+    Warn,
+    /// Containers will not work until this is fixed
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HostCheck {
+    pub name: String,
+    pub category: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warn/fail; empty on pass
+    pub remediation: String,
+}
+
+impl HostCheck {
+    fn pass(category: &'static str, name: &str, detail: impl Into<String>) -> Self {
+        Self { name: name.to_string(), category, status: CheckStatus::Pass, detail: detail.into(), remediation: String::new() }
+    }
+
+    fn problem(category: &'static str, name: &str, status: CheckStatus, detail: impl Into<String>, remediation: &str) -> Self {
+        Self { name: name.to_string(), category, status, detail: detail.into(), remediation: remediation.to_string() }
+    }
+}
+
+/// Namespaces the runtime creates, and whether a container start needs them
+const NAMESPACES: &[(&str, bool)] = &[
+    ("pid", true),
+    ("mnt", true),
+    ("net", true),
+    ("uts", true),
+    ("ipc", true),
+    ("user", false),
+    ("cgroup", false),
+];
+
+/// External tools the daemon shells out to, with the package that provides them
+const REQUIRED_TOOLS: &[(&str, &str)] = &[
+    ("nsenter", "util-linux"),
+    ("ip", "iproute2"),
+    ("chroot", "coreutils"),
+    ("tar", "tar"),
+];
+
+/// Run every check. Blocking: reads /proc and /sys and runs `ip route`.
+pub fn check_host_requirements(subnet: &str, bridge_name: &str) -> Vec<HostCheck> {
+    let mut checks = vec![check_privileges()];
+    checks.extend(check_namespaces());
+    checks.push(check_cgroups());
+    checks.push(check_overlayfs());
+    checks.push(check_br_netfilter());
+    checks.push(check_ip_forward());
+    checks.push(check_firewall_tooling());
+    checks.push(check_host_firewall(bridge_name));
+    checks.extend(check_tools());
+    checks.push(check_subnet(subnet, bridge_name));
+    checks
+}
+
+fn check_privileges() -> HostCheck {
+    let euid = nix::unistd::geteuid();
+    if euid.is_root() {
+        HostCheck::pass("privileges", "root", "running as root")
+    } else {
+        HostCheck::problem("privileges", "root", CheckStatus::Fail, format!("running as uid {}", euid),
+            "Run the daemon as root (namespaces, cgroups and bridge setup require CAP_SYS_ADMIN and CAP_NET_ADMIN)")
+    }
+}
+
+fn check_namespaces() -> Vec<HostCheck> {
+    NAMESPACES.iter().map(|(ns, required)| {
+        let name = format!("{} namespace", ns);
+        if Path::new("/proc/self/ns").join(ns).exists() {
+            HostCheck::pass("kernel", &name, "supported")
+        } else {
+            let status = if *required { CheckStatus::Fail } else { CheckStatus::Warn };
+            HostCheck::problem("kernel", &name, status, "not supported by this kernel",
+                &format!("Enable CONFIG_{}_NS in the kernel configuration", ns.to_uppercase()))
+        }
+    }).collect()
+}
+
+fn check_cgroups() -> HostCheck {
+    let root = Path::new("/sys/fs/cgroup");
+    if let Ok(controllers) = std::fs::read_to_string(root.join("cgroup.controllers")) {
+        let missing: Vec<&str> = ["memory", "cpu", "pids"].into_iter()
+            .filter(|controller| !controllers.split_whitespace().any(|c| c == *controller))
+            .collect();
+        if missing.is_empty() {
+            HostCheck::pass("kernel", "cgroups", "cgroup v2 with memory, cpu and pids controllers")
+        } else {
+            HostCheck::problem("kernel", "cgroups", CheckStatus::Warn,
+                format!("cgroup v2 without controllers: {}", missing.join(", ")),
+                "Enable the missing controllers (e.g. systemd.unified_cgroup_hierarchy=1 and cgroup_enable=memory on the kernel command line); limits for them are not enforced")
+        }
+    } else if root.join("memory").exists() && root.join("cpu").exists() {
+        HostCheck::problem("kernel", "cgroups", CheckStatus::Warn, "cgroup v1 (legacy hierarchy)",
+            "Boot with systemd.unified_cgroup_hierarchy=1 for cgroup v2; newer resource controls need it")
+    } else {
+        HostCheck::problem("kernel", "cgroups", CheckStatus::Fail, "no cgroup hierarchy mounted at /sys/fs/cgroup",
+            "Mount cgroup2 at /sys/fs/cgroup (mount -t cgroup2 none /sys/fs/cgroup)")
+    }
+}
+
+fn check_overlayfs() -> HostCheck {
+    let supported = std::fs::read_to_string("/proc/filesystems")
+        .map(|filesystems| filesystems.lines().any(|line| line.split_whitespace().last() == Some("overlay")))
+        .unwrap_or(false);
+    if supported || Path::new("/sys/module/overlay").exists() {
+        HostCheck::pass("kernel", "overlayfs", "available")
+    } else {
+        HostCheck::problem("kernel", "overlayfs", CheckStatus::Warn, "overlay filesystem not available",
+            "Load the module with `modprobe overlay`")
+    }
+}
+
+fn check_br_netfilter() -> HostCheck {
+    if Path::new("/proc/sys/net/bridge/bridge-nf-call-iptables").exists() {
+        HostCheck::pass("network", "br_netfilter", "loaded")
+    } else {
+        HostCheck::problem("network", "br_netfilter", CheckStatus::Warn, "module not loaded; bridged traffic bypasses iptables",
+            "Load the module with `modprobe br_netfilter` (and add it to /etc/modules-load.d)")
+    }
+}
+
+fn check_ip_forward() -> HostCheck {
+    match std::fs::read_to_string("/proc/sys/net/ipv4/ip_forward").map(|value| value.trim().to_string()) {
+        Ok(value) if value == "1" => HostCheck::pass("network", "ip_forward", "enabled"),
+        Ok(_) => HostCheck::problem("network", "ip_forward", CheckStatus::Warn, "disabled; containers cannot reach outside the bridge",
+            "Enable with `sysctl -w net.ipv4.ip_forward=1`"),
+        Err(e) => HostCheck::problem("network", "ip_forward", CheckStatus::Warn, format!("unreadable: {}", e),
+            "Check that /proc/sys is mounted"),
+    }
+}
+
+fn check_firewall_tooling() -> HostCheck {
+    let iptables = CommandExecutor::is_command_available("iptables");
+    let nft = CommandExecutor::is_command_available("nft");
+    match (iptables, nft) {
+        (true, true) => HostCheck::pass("tools", "firewall", "iptables and nft available"),
+        (true, false) => HostCheck::pass("tools", "firewall", "iptables available"),
+        (false, true) => HostCheck::problem("tools", "firewall", CheckStatus::Warn, "only nft available",
+            "Install iptables (or iptables-nft); port publishing and NAT rules are written with iptables"),
+        (false, false) => HostCheck::problem("tools", "firewall", CheckStatus::Fail, "neither iptables nor nft found",
+            "Install the iptables package"),
+    }
+}
+
+fn check_host_firewall(bridge_name: &str) -> HostCheck {
+    match HostFirewall::detect() {
+        None => HostCheck::pass("network", "host_firewall", "no firewalld or ufw running"),
+        Some(firewall) if firewall.bridge_registered(bridge_name) =>
+            HostCheck::pass("network", "host_firewall", format!("{} running; {} registered", firewall.as_str(), bridge_name)),
+        Some(firewall) => HostCheck::problem("network", "host_firewall", CheckStatus::Warn,
+            format!("{} running; {} not registered yet", firewall.as_str(), bridge_name),
+            "The daemon registers the bridge on start; set QUILT_HOST_FIREWALL=none to manage it yourself"),
+    }
+}
+
+fn check_tools() -> Vec<HostCheck> {
+    REQUIRED_TOOLS.iter().map(|(tool, package)| {
+        if CommandExecutor::is_command_available(tool) {
+            HostCheck::pass("tools", tool, "available")
+        } else {
+            HostCheck::problem("tools", tool, CheckStatus::Fail, "not found in PATH", &format!("Install {}", package))
+        }
+    }).collect()
+}
+
+fn check_subnet(subnet: &str, bridge_name: &str) -> HostCheck {
+    let Some(container_subnet) = parse_cidr(subnet) else {
+        return HostCheck::problem("network", "subnet", CheckStatus::Fail, format!("invalid subnet {}", subnet),
+            "Configure the container subnet in CIDR form, e.g. 10.42.0.0/16");
+    };
+    let routes = match CommandExecutor::run("ip", ["-4", "route", "show"]) {
+        Ok(result) if result.success => result.stdout,
+        _ => return HostCheck::problem("network", "subnet", CheckStatus::Warn, "could not read the routing table",
+            "Check that iproute2 is installed"),
+    };
+
+    let conflicts = route_conflicts(&routes, container_subnet, bridge_name);
+    if conflicts.is_empty() {
+        HostCheck::pass("network", "subnet", format!("{} does not overlap other routes", subnet))
+    } else {
+        HostCheck::problem("network", "subnet", CheckStatus::Fail,
+            format!("{} overlaps {}", subnet, conflicts.join(", ")),
+            "Pick a container subnet that no host interface or VPN route uses")
+    }
+}
+
+/// Parse `a.b.c.d/len` into the masked network address and prefix length
+fn parse_cidr(cidr: &str) -> Option<(u32, u32)> {
+    let (address, prefix) = cidr.split_once('/').unwrap_or((cidr, "32"));
+    let address: Ipv4Addr = address.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok()?;
+    if prefix > 32 {
+        return None;
+    }
+    let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+    Some((u32::from(address) & mask, prefix))
+}
+
+fn cidrs_overlap(a: (u32, u32), b: (u32, u32)) -> bool {
+    let prefix = a.1.min(b.1);
+    let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+    a.0 & mask == b.0 & mask
+}
+
+/// Routes from `ip -4 route show` output that overlap `subnet` on a device other than the bridge
+fn route_conflicts(routes: &str, subnet: (u32, u32), bridge_name: &str) -> Vec<String> {
+    routes.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        let destination = fields.next()?;
+        if destination == "default" {
+            return None;
+        }
+        let device = line.split_whitespace()
+            .skip_while(|field| *field != "dev")
+            .nth(1)
+            .unwrap_or("");
+        if device == bridge_name {
+            return None;
+        }
+        let route = parse_cidr(destination)?;
+        cidrs_overlap(route, subnet).then(|| format!("{} (dev {})", destination, device))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cidr_and_overlap() {
+        assert_eq!(parse_cidr("10.42.1.7/16"), Some((u32::from(Ipv4Addr::new(10, 42, 0, 0)), 16)));
+        assert_eq!(parse_cidr("10.42.0.0/33"), None);
+        assert!(cidrs_overlap(parse_cidr("10.0.0.0/8").unwrap(), parse_cidr("10.42.0.0/16").unwrap()));
+        assert!(!cidrs_overlap(parse_cidr("10.43.0.0/16").unwrap(), parse_cidr("10.42.0.0/16").unwrap()));
+    }
+
+    #[test]
+    fn test_route_conflicts_ignore_bridge_and_default() {
+        let routes = "default via 192.168.1.1 dev eth0 proto dhcp metric 100\n\
+                      10.42.0.0/16 dev quilt0 proto kernel scope link src 10.42.0.1\n\
+                      10.0.0.0/8 dev tun0 scope link\n\
+                      192.168.1.0/24 dev eth0 proto kernel scope link src 192.168.1.20\n";
+        let conflicts = route_conflicts(routes, parse_cidr("10.42.0.0/16").unwrap(), "quilt0");
+        assert_eq!(conflicts, vec!["10.0.0.0/8 (dev tun0)".to_string()]);
+    }
+
+    #[test]
+    fn test_check_host_requirements_reports_every_area() {
+        let checks = check_host_requirements("10.42.0.0/16", "quilt0");
+        for category in ["privileges", "kernel", "network", "tools"] {
+            assert!(checks.iter().any(|check| check.category == category), "missing {}", category);
+        }
+        assert!(checks.iter().all(|check| check.status == CheckStatus::Pass || !check.remediation.is_empty()));
+    }
+}
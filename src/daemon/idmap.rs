@@ -0,0 +1,242 @@
+// User namespaces and ID-mapped mounts
+// In a user namespace, root in the container is an unprivileged id range on the host
+// (100000-165535 unless QUILT_USERNS_RANGE says otherwise). Host files keep their ownership:
+// the rootfs, volumes and bind mounts are attached through ID-mapped mounts (mount_setattr with
+// MOUNT_ATTR_IDMAP, Linux 5.12+ and a filesystem that supports it), so a file owned by host root
+// shows up as owned by container root and nothing is chowned. The daemon clones and maps the
+// mounts before the container process forks, since that needs privilege over the host
+// filesystems; the process inherits the descriptors and attaches them in its own mount namespace.
+
+use crate::daemon::namespace::has_own_namespace;
+use crate::daemon::MountConfig;
+use nix::libc;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::waitpid;
+use nix::unistd::{fork, ForkResult};
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+/// Environment variable overriding the host id range as `<first host id>:<count>`
+pub const USERNS_RANGE_ENV: &str = "QUILT_USERNS_RANGE";
+
+const DEFAULT_HOST_ID: u32 = 100_000;
+const DEFAULT_ID_COUNT: u32 = 65_536;
+
+/// How long a process may take to enter its user namespace or to see its id maps
+const NAMESPACE_WAIT: Duration = Duration::from_secs(5);
+
+const OPEN_TREE_CLONE: libc::c_uint = 1;
+const MOVE_MOUNT_F_EMPTY_PATH: libc::c_uint = 0x4;
+const MOUNT_ATTR_RDONLY: u64 = 0x1;
+const MOUNT_ATTR_IDMAP: u64 = 0x0010_0000;
+
+/// struct mount_attr from linux/mount.h
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+/// Container ids 0..count map onto host ids host_id..host_id+count, for users and groups alike
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdMapping {
+    pub host_id: u32,
+    pub count: u32,
+}
+
+impl Default for IdMapping {
+    fn default() -> Self {
+        IdMapping { host_id: DEFAULT_HOST_ID, count: DEFAULT_ID_COUNT }
+    }
+}
+
+impl IdMapping {
+    /// The range from QUILT_USERNS_RANGE, or the default one
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(USERNS_RANGE_ENV) {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Parse `<first host id>:<count>`, e.g. `100000:65536`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid {} '{}': expected <first host id>:<count>", USERNS_RANGE_ENV, spec);
+        let (host_id, count) = spec.trim().split_once(':').ok_or_else(invalid)?;
+        let host_id: u32 = host_id.parse().map_err(|_| invalid())?;
+        let count: u32 = count.parse().map_err(|_| invalid())?;
+        if host_id == 0 {
+            return Err(format!("Invalid {} '{}': container root must not be host root", USERNS_RANGE_ENV, spec));
+        }
+        if count == 0 || host_id.checked_add(count).is_none() {
+            return Err(format!("Invalid {} '{}': range is empty or past the last id", USERNS_RANGE_ENV, spec));
+        }
+        Ok(IdMapping { host_id, count })
+    }
+
+    /// Line for /proc/<pid>/uid_map and gid_map
+    fn map_line(&self) -> String {
+        format!("0 {} {}\n", self.host_id, self.count)
+    }
+}
+
+fn wait_until(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + NAMESPACE_WAIT;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    true
+}
+
+/// Write the id maps of a process that is unsharing its user namespace. Waits for the unshare:
+/// the maps of the daemon's own namespace cannot be written.
+pub fn write_id_maps(pid: i32, mapping: &IdMapping) -> Result<(), String> {
+    if !wait_until(|| has_own_namespace(pid as i64, "user")) {
+        return Err(format!("Process {} did not enter its user namespace", pid));
+    }
+    // gid_map last: the process waits for it
+    for file in ["uid_map", "gid_map"] {
+        fs::write(format!("/proc/{}/{}", pid, file), mapping.map_line())
+            .map_err(|e| format!("Failed to write {} of process {}: {}", file, pid, e))?;
+    }
+    Ok(())
+}
+
+/// Whether the calling process runs in a user namespace other than the initial one
+pub fn in_user_namespace() -> bool {
+    fs::read_to_string("/proc/self/uid_map")
+        .map(|map| map.split_whitespace().collect::<Vec<_>>() != ["0", "0", "4294967295"])
+        .unwrap_or(false)
+}
+
+/// Become root of the user namespace the calling process has unshared, once the daemon has
+/// written its id maps. Until then the process has no uid in the namespace, so this has to
+/// come before anything that creates files.
+pub fn become_namespace_root() -> Result<(), String> {
+    let mapped = || fs::read_to_string("/proc/self/gid_map").map(|map| !map.trim().is_empty()).unwrap_or(false);
+    if !wait_until(mapped) {
+        return Err("Timed out waiting for the user namespace id maps".to_string());
+    }
+    // The daemon wrote gid_map with privilege, so setgroups is still allowed. Capabilities in
+    // the namespace survive: the process was never root of it before.
+    let failed = unsafe { libc::setgroups(0, std::ptr::null()) != 0 || libc::setresgid(0, 0, 0) != 0 || libc::setresuid(0, 0, 0) != 0 };
+    if failed {
+        return Err(format!("Failed to become root of the user namespace: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// A user namespace carrying `mapping`, used only to describe ID-mapped mounts. A helper
+/// process creates it and is killed once the namespace is held by the returned descriptor.
+pub fn mapping_namespace(mapping: &IdMapping) -> Result<OwnedFd, String> {
+    // SAFETY: the child only makes async-signal-safe calls before it is killed
+    let helper = match unsafe { fork() } {
+        Ok(ForkResult::Parent { child }) => child,
+        Ok(ForkResult::Child) => unsafe {
+            if libc::unshare(libc::CLONE_NEWUSER) == 0 {
+                loop {
+                    libc::pause();
+                }
+            }
+            libc::_exit(1)
+        },
+        Err(e) => return Err(format!("Failed to fork user namespace helper: {}", e)),
+    };
+
+    let namespace = write_id_maps(helper.as_raw(), mapping).and_then(|_| {
+        fs::File::open(format!("/proc/{}/ns/user", helper))
+            .map(OwnedFd::from)
+            .map_err(|e| format!("Failed to open user namespace of helper {}: {}", helper, e))
+    });
+    let _ = kill(helper, Signal::SIGKILL);
+    let _ = waitpid(helper, None);
+    namespace
+}
+
+/// Clone the mount at `source` into a detached tree whose ids are mapped through `userns`
+pub fn idmapped_tree(source: &str, userns: &OwnedFd, readonly: bool) -> Result<OwnedFd, String> {
+    let path = CString::new(source).map_err(|_| format!("Invalid mount source '{}'", source))?;
+    // SAFETY: plain syscall on a NUL-terminated path; the result is a new descriptor we own
+    let fd = unsafe {
+        libc::syscall(libc::SYS_open_tree, libc::AT_FDCWD, path.as_ptr(), OPEN_TREE_CLONE | libc::O_CLOEXEC as libc::c_uint)
+    };
+    if fd < 0 {
+        return Err(format!("Failed to clone mount {}: {}", source, std::io::Error::last_os_error()));
+    }
+    let tree = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+
+    let attr = MountAttr {
+        attr_set: MOUNT_ATTR_IDMAP | if readonly { MOUNT_ATTR_RDONLY } else { 0 },
+        attr_clr: 0,
+        propagation: 0,
+        userns_fd: userns.as_raw_fd() as u64,
+    };
+    let rc = unsafe {
+        libc::syscall(libc::SYS_mount_setattr, tree.as_raw_fd(), b"\0".as_ptr(), libc::AT_EMPTY_PATH,
+            &attr as *const MountAttr, std::mem::size_of::<MountAttr>())
+    };
+    if rc != 0 {
+        return Err(format!("Failed to ID-map {} (needs Linux 5.12+ and filesystem support): {}",
+            source, std::io::Error::last_os_error()));
+    }
+    Ok(tree)
+}
+
+/// Attach a tree from `idmapped_tree` at `target`, in the calling process's mount namespace
+pub fn attach_tree(tree: &OwnedFd, target: &str) -> Result<(), String> {
+    let path = CString::new(target).map_err(|_| format!("Invalid mount target '{}'", target))?;
+    let rc = unsafe {
+        libc::syscall(libc::SYS_move_mount, tree.as_raw_fd(), b"\0".as_ptr(), libc::AT_FDCWD, path.as_ptr(),
+            MOVE_MOUNT_F_EMPTY_PATH)
+    };
+    if rc != 0 {
+        return Err(format!("Failed to attach ID-mapped mount at {}: {}", target, std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// ID-mapped trees for a container's rootfs and for its bind and volume mounts, by target
+/// relative to the rootfs. Tmpfs mounts need none: the container creates them itself.
+pub struct IdmappedMounts {
+    pub rootfs: OwnedFd,
+    pub mounts: Vec<(String, OwnedFd)>,
+}
+
+/// Prepare the ID-mapped trees of a container about to start in a user namespace
+pub fn prepare_idmapped_mounts(rootfs_path: &str, mounts: &[MountConfig], mapping: &IdMapping) -> Result<IdmappedMounts, String> {
+    use crate::daemon::MountType;
+
+    let userns = mapping_namespace(mapping)?;
+    let rootfs = idmapped_tree(rootfs_path, &userns, false)?;
+    let mut trees = Vec::new();
+    for mount in mounts.iter().filter(|mount| !matches!(mount.mount_type, MountType::Tmpfs)) {
+        if !std::path::Path::new(&mount.source).exists() {
+            return Err(format!("Mount source '{}' does not exist", mount.source));
+        }
+        trees.push((mount.target.clone(), idmapped_tree(&mount.source, &userns, mount.readonly)?));
+    }
+    Ok(IdmappedMounts { rootfs, mounts: trees })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id_mapping() {
+        assert_eq!(IdMapping::parse("200000:1000"), Ok(IdMapping { host_id: 200_000, count: 1000 }));
+        assert_eq!(IdMapping::default().map_line(), "0 100000 65536\n");
+        assert!(IdMapping::parse("0:65536").is_err());
+        assert!(IdMapping::parse("100000:0").is_err());
+        assert!(IdMapping::parse("4294967295:2").is_err());
+        assert!(IdMapping::parse("100000").is_err());
+        assert!(IdMapping::parse("a:b").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+// Image configuration
+// A `docker save` archive carries the config its image was built with. Its ENV, WORKDIR and
+// USER are kept in a file at the root of the rootfs when the archive is imported, so they move
+// with the warm pool's renames and outlive restarts. The main process starts with them unless
+// the container says otherwise, as Docker does: a requested variable replaces the image's of the
+// same name, and a requested working directory or user replaces the image's. The architecture the
+// image was built for is kept too, for image inspect and the platform check on import.
+
+use crate::daemon::platform;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Config file name at the root of a container's rootfs
+pub const IMAGE_CONFIG_FILE: &str = ".quilt-image-config.json";
+
+/// The parts of an image's config the main process starts with
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageConfig {
+    /// `NAME=value` entries, as the image lists them
+    pub env: Vec<String>,
+    pub working_dir: Option<String>,
+    pub user: Option<String>,
+    /// Architecture the image was built for, e.g. `amd64`; unknown for plain rootfs tarballs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+}
+
+/// The image config document, e.g. `<digest>.json` in a docker archive
+#[derive(Debug, Deserialize)]
+struct ConfigDocument {
+    config: Option<RawConfig>,
+    architecture: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawConfig {
+    #[serde(rename = "Env")]
+    env: Option<Vec<String>>,
+    #[serde(rename = "WorkingDir")]
+    working_dir: Option<String>,
+    #[serde(rename = "User")]
+    user: Option<String>,
+}
+
+/// What the main process runs with once the image's defaults and the container's settings are
+/// merged
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessSettings {
+    pub environment: HashMap<String, String>,
+    pub working_directory: Option<String>,
+    pub user: Option<String>,
+}
+
+impl ImageConfig {
+    /// Read an image config document; fields it does not set stay empty
+    pub fn parse(document: &str) -> Result<Self, String> {
+        let document: ConfigDocument = serde_json::from_str(document)
+            .map_err(|e| format!("Invalid image config: {}", e))?;
+        let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+        let architecture = non_empty(document.architecture).map(|architecture| platform::normalize_architecture(&architecture));
+        let Some(config) = document.config else { return Ok(ImageConfig { architecture, ..Self::default() }) };
+        Ok(ImageConfig {
+            env: config.env.unwrap_or_default(),
+            working_dir: non_empty(config.working_dir),
+            user: non_empty(config.user),
+            architecture,
+        })
+    }
+
+    /// The image's variables by name; entries without `=` are skipped
+    pub fn environment(&self) -> HashMap<String, String> {
+        self.env.iter()
+            .filter_map(|entry| entry.split_once('='))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Set variable `name`, replacing the image's entry for it
+    pub fn set_env(&mut self, name: &str, value: &str) {
+        self.env.retain(|entry| entry.split_once('=').map_or(true, |(existing, _)| existing != name));
+        self.env.push(format!("{}={}", name, value));
+    }
+
+    /// An image config document carrying this config, as [`ImageConfig::parse`] reads it
+    pub fn to_document(&self) -> String {
+        let mut document = serde_json::json!({
+            "config": {
+                "Env": self.env,
+                "WorkingDir": self.working_dir.clone().unwrap_or_default(),
+                "User": self.user.clone().unwrap_or_default(),
+            }
+        });
+        if let Some(architecture) = &self.architecture {
+            document["architecture"] = serde_json::json!(architecture);
+        }
+        document.to_string()
+    }
+
+    /// Merge the container's own settings over the image's defaults
+    pub fn merged_with(&self, environment: &HashMap<String, String>, working_directory: Option<&str>, user: Option<&str>) -> ProcessSettings {
+        let mut merged = self.environment();
+        merged.extend(environment.iter().map(|(name, value)| (name.clone(), value.clone())));
+        ProcessSettings {
+            environment: merged,
+            working_directory: working_directory.map(str::to_string).or_else(|| self.working_dir.clone()),
+            user: user.map(str::to_string).or_else(|| self.user.clone()),
+        }
+    }
+}
+
+fn config_path(rootfs_path: &str) -> PathBuf {
+    Path::new(rootfs_path).join(IMAGE_CONFIG_FILE)
+}
+
+/// Keep `config` with the rootfs it was imported into
+pub fn save(rootfs_path: &str, config: &ImageConfig) -> Result<(), String> {
+    let json = serde_json::to_string(config).map_err(|e| format!("Failed to encode image config: {}", e))?;
+    fs::write(config_path(rootfs_path), json)
+        .map_err(|e| format!("Failed to save image config in {}: {}", rootfs_path, e))
+}
+
+/// The config saved with the rootfs; empty for images without one, such as plain rootfs tarballs
+pub fn load(rootfs_path: &str) -> ImageConfig {
+    fs::read_to_string(config_path(rootfs_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// The ids a user spec stands for in a container
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedUser {
+    pub uid: u32,
+    pub gid: u32,
+    /// Supplementary groups from /etc/group, the primary group first
+    pub groups: Vec<u32>,
+    /// Home directory from /etc/passwd
+    pub home: Option<String>,
+}
+
+/// Check a user spec: `user` or `user:group`, each a name or a numeric id
+pub fn validate_user(spec: &str) -> Result<(), String> {
+    let valid_part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+    let valid = match spec.split_once(':') {
+        Some((user, group)) => valid_part(user) && valid_part(group),
+        None => valid_part(spec),
+    };
+    if !valid {
+        return Err(format!("Invalid user '{}': expected user or user:group, each a name or numeric id", spec));
+    }
+    Ok(())
+}
+
+/// Look up `spec` in the passwd and group files under `root`. A numeric user needs no entry and
+/// then runs with group 0, as in Docker; a name must have one.
+pub fn resolve_user(spec: &str, root: &Path) -> Result<ResolvedUser, String> {
+    let (user, group) = match spec.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (spec, None),
+    };
+    let passwd = fs::read_to_string(root.join("etc/passwd")).unwrap_or_default();
+    let groups = fs::read_to_string(root.join("etc/group")).unwrap_or_default();
+
+    // name:password:uid:gid:gecos:home:shell
+    let entry = passwd.lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .filter(|fields| fields.len() >= 6)
+        .find(|fields| match user.parse::<u32>() {
+            Ok(uid) => fields[2].parse::<u32>() == Ok(uid),
+            Err(_) => fields[0] == user,
+        });
+    let (uid, mut gid, name, home) = match (entry, user.parse::<u32>()) {
+        (Some(fields), _) => (
+            fields[2].parse::<u32>().map_err(|_| format!("Invalid uid for user '{}' in /etc/passwd", user))?,
+            fields[3].parse::<u32>().map_err(|_| format!("Invalid gid for user '{}' in /etc/passwd", user))?,
+            Some(fields[0].to_string()),
+            Some(fields[5].to_string()).filter(|home| !home.is_empty()),
+        ),
+        (None, Ok(uid)) => (uid, 0, None, None),
+        (None, Err(_)) => return Err(format!("User '{}' is not in the container's /etc/passwd", user)),
+    };
+
+    // name:password:gid:member,member
+    let group_entries: Vec<Vec<&str>> = groups.lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .filter(|fields| fields.len() >= 3)
+        .collect();
+    if let Some(group) = group {
+        gid = match group.parse::<u32>() {
+            Ok(gid) => gid,
+            Err(_) => group_entries.iter()
+                .find(|fields| fields[0] == group)
+                .and_then(|fields| fields[2].parse::<u32>().ok())
+                .ok_or_else(|| format!("Group '{}' is not in the container's /etc/group", group))?,
+        };
+    }
+    let mut supplementary = vec![gid];
+    if let Some(name) = &name {
+        for fields in &group_entries {
+            let member = fields.get(3).map_or(false, |members| members.split(',').any(|member| member == name.as_str()));
+            if let (true, Ok(id)) = (member, fields[2].parse::<u32>()) {
+                if !supplementary.contains(&id) {
+                    supplementary.push(id);
+                }
+            }
+        }
+    }
+    Ok(ResolvedUser { uid, gid, groups: supplementary, home })
+}
+
+/// Drop the calling process to `user`. Needs CAP_SETUID and CAP_SETGID, which the immutable
+/// profile keeps.
+pub fn switch_user(user: &ResolvedUser) -> Result<(), String> {
+    use nix::libc;
+    // SAFETY: plain syscalls on owned values
+    let failed = unsafe {
+        libc::setgroups(user.groups.len(), user.groups.as_ptr()) != 0
+            || libc::setresgid(user.gid, user.gid, user.gid) != 0
+            || libc::setresuid(user.uid, user.uid, user.uid) != 0
+    };
+    if failed {
+        return Err(format!("Failed to switch to uid {} gid {}: {}", user.uid, user.gid, std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_image_config() {
+        let config = ImageConfig::parse(r#"{"architecture":"amd64","config":{"Env":["PATH=/usr/local/bin:/usr/bin","LANG=C.UTF-8","BROKEN"],"WorkingDir":"/app","User":""}}"#).unwrap();
+        assert_eq!(config.working_dir.as_deref(), Some("/app"));
+        assert_eq!(config.user, None);
+        assert_eq!(config.architecture.as_deref(), Some("amd64"));
+        assert_eq!(ImageConfig::parse(r#"{"architecture":"aarch64"}"#).unwrap().architecture.as_deref(), Some("arm64"));
+        assert_eq!(config.environment().len(), 2);
+        assert_eq!(ImageConfig::parse(r#"{"config":null}"#), Ok(ImageConfig::default()));
+        assert!(ImageConfig::parse("not json").is_err());
+
+        let requested = HashMap::from([("LANG".to_string(), "de_DE.UTF-8".to_string()), ("MODE".to_string(), "dev".to_string())]);
+        let settings = config.merged_with(&requested, None, Some("1000"));
+        assert_eq!(settings.environment["LANG"], "de_DE.UTF-8");
+        assert_eq!(settings.environment["PATH"], "/usr/local/bin:/usr/bin");
+        assert_eq!(settings.environment["MODE"], "dev");
+        assert_eq!(settings.working_directory.as_deref(), Some("/app"));
+        assert_eq!(settings.user.as_deref(), Some("1000"));
+        assert_eq!(config.merged_with(&HashMap::new(), Some("/srv"), None).working_directory.as_deref(), Some("/srv"));
+
+        let mut built = config.clone();
+        built.set_env("LANG", "C");
+        built.set_env("APP_ENV", "prod");
+        assert_eq!(built.env, vec!["PATH=/usr/local/bin:/usr/bin", "BROKEN", "LANG=C", "APP_ENV=prod"]);
+        assert_eq!(ImageConfig::parse(&built.to_document()).unwrap(), built);
+
+        let rootfs = tempfile::tempdir().unwrap();
+        let rootfs_path = rootfs.path().to_str().unwrap();
+        assert_eq!(load(rootfs_path), ImageConfig::default());
+        save(rootfs_path, &config).unwrap();
+        assert_eq!(load(rootfs_path), config);
+    }
+
+    #[test]
+    fn test_resolve_user() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("etc")).unwrap();
+        fs::write(root.path().join("etc/passwd"), "root:x:0:0:root:/root:/bin/sh\napp:x:1000:1000::/home/app:/bin/sh\n").unwrap();
+        fs::write(root.path().join("etc/group"), "root:x:0:\napp:x:1000:\ndocker:x:999:app,other\nstaff:x:50:\n").unwrap();
+
+        let app = resolve_user("app", root.path()).unwrap();
+        assert_eq!((app.uid, app.gid, app.groups.clone()), (1000, 1000, vec![1000, 999]));
+        assert_eq!(app.home.as_deref(), Some("/home/app"));
+        assert_eq!(resolve_user("1000:staff", root.path()).unwrap().gid, 50);
+        assert_eq!(resolve_user("4242", root.path()), Ok(ResolvedUser { uid: 4242, gid: 0, groups: vec![0], home: None }));
+        assert_eq!(resolve_user("4242:7", root.path()).unwrap().gid, 7);
+        assert!(resolve_user("nobody", root.path()).is_err());
+        assert!(resolve_user("app:wheel", root.path()).is_err());
+
+        assert!(validate_user("app:staff").is_ok());
+        assert!(validate_user("1000").is_ok());
+        assert!(validate_user("").is_err());
+        assert!(validate_user("app:").is_err());
+        assert!(validate_user("a b").is_err());
+        assert!(validate_user("a:b:c").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+// Image export
+// Writes a stored image back out as an archive Docker, Podman and other quilt hosts load: each
+// layer directory of the store is packed into a layer tarball again, whiteout files included,
+// and the image config gets the `rootfs.diff_ids` `docker load` checks the layers against. The
+// `docker` format is the classic `docker save` layout; the `oci` format is an OCI image layout
+// (index.json, blobs/) that also carries a manifest.json, as Docker 25 and later write it.
+
+use crate::daemon::image_config::ImageConfig;
+use crate::daemon::images::{self, StoredImage};
+use crate::daemon::platform;
+use crate::daemon::registry;
+use std::fs::{self, File};
+use std::path::Path;
+use std::str::FromStr;
+use tar::Builder;
+
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const OCI_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const OCI_LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+
+/// Archive layout to write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `docker save`: `<hex>/layer.tar` per layer, `<hex>.json` config, manifest.json
+    Docker,
+    /// OCI image layout with a manifest.json for `docker load`
+    Oci,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Docker => "docker",
+            ExportFormat::Oci => "oci",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "docker" | "docker-archive" => Ok(ExportFormat::Docker),
+            "oci" | "oci-archive" => Ok(ExportFormat::Oci),
+            other => Err(format!("Unknown image format '{}': expected docker or oci", other)),
+        }
+    }
+}
+
+/// A layer packed for export
+struct PackedLayer {
+    /// Path in the archive
+    path: String,
+    /// Digest of the uncompressed tarball, which is also its diff ID
+    digest: String,
+    size: u64,
+}
+
+/// Write `image` from the store at `root` to `output` as an archive tagged `repo_tags`. Returns
+/// the archive's size. Blocking.
+pub fn export(root: &Path, image: &StoredImage, repo_tags: &[String], format: ExportFormat, output: &Path) -> Result<u64, String> {
+    if !images::is_complete(root, image) {
+        return Err(format!("Image {} is missing layers in the store", image.digest));
+    }
+    let parent = output.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let staging = parent.join(format!(".export-{}", uuid::Uuid::new_v4()));
+    // Written beside the staging directory, which is archived whole
+    let partial = staging.with_extension("tar");
+    let written = fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create {}: {}", staging.display(), e))
+        .and_then(|_| write_layout(root, image, repo_tags, format, &staging, &partial))
+        .and_then(|_| fs::rename(&partial, output).map_err(|e| format!("Failed to move image to {}: {}", output.display(), e)))
+        .and_then(|_| fs::metadata(output).map(|metadata| metadata.len()).map_err(|e| e.to_string()));
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_file(&partial);
+    written
+}
+
+fn write_layout(root: &Path, image: &StoredImage, repo_tags: &[String], format: ExportFormat, staging: &Path, archive: &Path) -> Result<(), String> {
+    let mut layers = Vec::with_capacity(image.layers.len());
+    for (index, layer) in image.layers.iter().enumerate() {
+        let packed = staging.join(format!("layer-{}.tar", index));
+        pack_layer(&images::layer_dir(root, layer), &packed)?;
+        let digest = images::sha256_file(&packed)?;
+        let path = archive_path(format, &digest, "layer.tar");
+        let target = staging.join(&path);
+        fs::create_dir_all(target.parent().unwrap_or(staging))
+            .and_then(|_| fs::rename(&packed, &target))
+            .map_err(|e| format!("Failed to place layer {}: {}", layer, e))?;
+        let size = fs::metadata(&target).map(|metadata| metadata.len()).unwrap_or(0);
+        layers.push(PackedLayer { path, digest, size });
+    }
+
+    let config = config_document(&image.config, &layers);
+    let config_digest = images::sha256_bytes(config.clone())?;
+    let config_path = archive_path(format, &config_digest, "json");
+    write_file(staging, &config_path, config.as_bytes())?;
+
+    if format == ExportFormat::Oci {
+        let manifest = oci_manifest(&config_digest, config.len(), &layers);
+        let manifest_digest = images::sha256_bytes(manifest.clone())?;
+        write_file(staging, &archive_path(format, &manifest_digest, "json"), manifest.as_bytes())?;
+        write_file(staging, "index.json", oci_index(&manifest_digest, manifest.len(), repo_tags).as_bytes())?;
+        write_file(staging, "oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#)?;
+    }
+    let layer_paths: Vec<String> = layers.iter().map(|layer| layer.path.clone()).collect();
+    registry::write_archive(staging, &config_path, &layer_paths, repo_tags, archive)
+}
+
+/// Where a blob goes in the archive: `blobs/sha256/<hex>` in an OCI layout, else
+/// `<hex>/layer.tar` for layers and `<hex>.json` for the config
+fn archive_path(format: ExportFormat, digest: &str, kind: &str) -> String {
+    let hex = digest.trim_start_matches("sha256:");
+    match (format, kind) {
+        (ExportFormat::Oci, _) => format!("blobs/sha256/{}", hex),
+        (ExportFormat::Docker, "layer.tar") => format!("{}/layer.tar", hex),
+        (ExportFormat::Docker, _) => format!("{}.{}", hex, kind),
+    }
+}
+
+fn write_file(staging: &Path, path: &str, content: &[u8]) -> Result<(), String> {
+    let target = staging.join(path);
+    fs::create_dir_all(target.parent().unwrap_or(staging))
+        .and_then(|_| fs::write(&target, content))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// The image config document with the platform (the host's for images that do not name one) and
+/// the diff IDs of the layers
+fn config_document(config: &ImageConfig, layers: &[PackedLayer]) -> String {
+    let mut document: serde_json::Value = serde_json::from_str(&config.to_document()).unwrap_or_else(|_| serde_json::json!({}));
+    document["architecture"] = serde_json::json!(config.architecture.as_deref().unwrap_or(platform::host_architecture()));
+    document["os"] = serde_json::json!("linux");
+    document["rootfs"] = serde_json::json!({
+        "type": "layers",
+        "diff_ids": layers.iter().map(|layer| layer.digest.clone()).collect::<Vec<_>>(),
+    });
+    document.to_string()
+}
+
+fn oci_manifest(config_digest: &str, config_size: usize, layers: &[PackedLayer]) -> String {
+    serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+        "config": { "mediaType": OCI_CONFIG_MEDIA_TYPE, "digest": config_digest, "size": config_size },
+        "layers": layers.iter()
+            .map(|layer| serde_json::json!({ "mediaType": OCI_LAYER_MEDIA_TYPE, "digest": layer.digest, "size": layer.size }))
+            .collect::<Vec<_>>(),
+    }).to_string()
+}
+
+fn oci_index(manifest_digest: &str, manifest_size: usize, repo_tags: &[String]) -> String {
+    let mut descriptor = serde_json::json!({ "mediaType": OCI_MANIFEST_MEDIA_TYPE, "digest": manifest_digest, "size": manifest_size });
+    if let Some(tag) = repo_tags.first() {
+        descriptor["annotations"] = serde_json::json!({
+            "io.containerd.image.name": tag,
+            "org.opencontainers.image.ref.name": tag.rsplit_once(':').map_or("latest", |(_, tag)| tag),
+        });
+    }
+    serde_json::json!({ "schemaVersion": 2, "mediaType": "application/vnd.oci.image.index.v1+json", "manifests": [descriptor] }).to_string()
+}
+
+/// Pack a stored layer directory into a tarball at `output`, entries in name order
+fn pack_layer(layer: &Path, output: &Path) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut builder = Builder::new(file);
+    builder.follow_symlinks(false);
+    registry::append_tree(&mut builder, layer, &|_, _| registry::TreeEntry::Full)
+        .and_then(|_| builder.finish())
+        .map_err(|e| format!("Failed to pack layer {}: {}", layer.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::docker_archive;
+
+    #[test]
+    fn test_export_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("store");
+        let mut layer = tar::Builder::new(Vec::new());
+        for (path, content) in [("etc/motd", "exported"), ("etc/.wh.hostname", "")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            layer.append_data(&mut header, path, content.as_bytes()).unwrap();
+        }
+        let plain = dir.path().join("rootfs.tar");
+        fs::write(&plain, layer.into_inner().unwrap()).unwrap();
+        let mut image = images::import(&root, plain.to_str().unwrap()).unwrap();
+        image.config.working_dir = Some("/srv".to_string());
+        let tags = vec!["demo:2".to_string()];
+
+        for format in [ExportFormat::Docker, ExportFormat::Oci] {
+            let output = dir.path().join(format!("{}.tar", format.as_str()));
+            assert!(export(&root, &image, &tags, format, &output).unwrap() > 0);
+
+            let staging = dir.path().join(format!("{}-unpacked", format.as_str()));
+            let unpacked = docker_archive::unpack_archive(output.to_str().unwrap(), &staging).unwrap();
+            assert_eq!(unpacked.repo_tags, tags);
+            assert_eq!(unpacked.config.working_dir.as_deref(), Some("/srv"));
+            assert_eq!(unpacked.config.architecture.as_deref(), Some(platform::host_architecture()));
+            assert_eq!(unpacked.layers.len(), 1);
+            // The whiteout survives, and the diff ID matches the packed layer
+            let mut entries = tar::Archive::new(File::open(&unpacked.layers[0]).unwrap());
+            let names: Vec<String> = entries.entries().unwrap()
+                .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+                .collect();
+            assert!(names.contains(&"etc/.wh.hostname".to_string()));
+            let diff_id = images::sha256_file(&unpacked.layers[0]).unwrap();
+            assert!(fs::read_to_string(staging.join("manifest.json")).unwrap().contains(diff_id.trim_start_matches("sha256:")));
+        }
+        assert!(staging_left(dir.path()).is_empty());
+        assert_eq!("OCI-archive".parse::<ExportFormat>(), Ok(ExportFormat::Oci));
+        assert!("tgz".parse::<ExportFormat>().is_err());
+    }
+
+    fn staging_left(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with(".export-"))
+            .collect()
+    }
+}
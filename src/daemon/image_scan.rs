@@ -0,0 +1,313 @@
+// Image vulnerability scanning
+// When a scanner is configured, images are run through it the first time a container is created
+// from them: trivy or grype as a subprocess, on the `docker save` archive itself or on a plain
+// rootfs tarball unpacked to a scratch directory. Findings are stored per image file and reused
+// until the file changes. An optional severity threshold refuses containers from images with
+// findings at or above it.
+
+use crate::daemon::docker_archive;
+use crate::utils::command::{Argv, CommandExecutor};
+use crate::utils::console::ConsoleLogger;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tar::Archive;
+
+/// Scanner to run: `trivy` or `grype`, optionally with its path as `trivy=/opt/bin/trivy`
+pub const IMAGE_SCANNER_ENV: &str = "QUILT_IMAGE_SCANNER";
+/// Lowest severity that blocks container creation (`critical`, `high`, `medium`, `low`); unset
+/// only records findings
+pub const IMAGE_SCAN_BLOCK_ENV: &str = "QUILT_IMAGE_SCAN_BLOCK";
+/// Scanners fetch their vulnerability database on first use, which can take a while
+const SCAN_TIMEOUT: Duration = Duration::from_secs(600);
+/// Finding IDs named in a refusal
+const REFUSAL_EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "unknown" => Some(Severity::Unknown),
+            // grype's lowest rating
+            "low" | "negligible" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Unknown => "unknown",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VulnerabilityFinding {
+    /// CVE or advisory ID
+    pub id: String,
+    pub package: String,
+    pub installed_version: String,
+    /// Empty when no fixed version is known
+    pub fixed_version: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScannerKind {
+    Trivy,
+    Grype,
+}
+
+impl ScannerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScannerKind::Trivy => "trivy",
+            ScannerKind::Grype => "grype",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannerConfig {
+    pub kind: ScannerKind,
+    pub program: String,
+    pub block_at: Option<Severity>,
+}
+
+impl ScannerConfig {
+    /// The configured scanner, or `None` when scanning is off
+    pub fn from_env() -> Option<Self> {
+        let scanner = std::env::var(IMAGE_SCANNER_ENV).ok()?;
+        let block = std::env::var(IMAGE_SCAN_BLOCK_ENV).ok();
+        match Self::parse(&scanner, block.as_deref()) {
+            Ok(config) => config,
+            Err(e) => {
+                ConsoleLogger::warning(&format!("⚠️ [IMAGE-SCAN] Scanning disabled: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Parse the scanner and threshold settings; an empty scanner turns scanning off
+    pub fn parse(scanner: &str, block: Option<&str>) -> Result<Option<Self>, String> {
+        let scanner = scanner.trim();
+        if scanner.is_empty() {
+            return Ok(None);
+        }
+        let (name, program) = scanner.split_once('=').unwrap_or((scanner, scanner));
+        let kind = match name.trim() {
+            "trivy" => ScannerKind::Trivy,
+            "grype" => ScannerKind::Grype,
+            other => return Err(format!("Unknown image scanner '{}' in {}: expected trivy or grype", other, IMAGE_SCANNER_ENV)),
+        };
+        let block_at = match block.map(str::trim).filter(|block| !block.is_empty()) {
+            Some(block) => Some(Severity::parse(block)
+                .ok_or_else(|| format!("Invalid severity '{}' in {}", block, IMAGE_SCAN_BLOCK_ENV))?),
+            None => None,
+        };
+        Ok(Some(Self { kind, program: program.trim().to_string(), block_at }))
+    }
+
+    /// Run the scanner over `image_path`
+    pub async fn scan(&self, image_path: &str) -> Result<Vec<VulnerabilityFinding>, String> {
+        let path = image_path.to_string();
+        let docker_archive = tokio::task::spawn_blocking(move || docker_archive::is_docker_archive(&path)).await
+            .map_err(|e| format!("Image inspection failed: {}", e))??;
+
+        if docker_archive {
+            return self.run(&self.archive_argv(image_path)).await;
+        }
+        // A plain rootfs tarball is scanned as the directory it unpacks to
+        let scratch = std::env::temp_dir().join(format!("quilt-scan-{}", uuid::Uuid::new_v4()));
+        let result = match unpack_rootfs(image_path, scratch.clone()).await {
+            Ok(()) => self.run(&self.directory_argv(&scratch)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = std::fs::remove_dir_all(&scratch) {
+            ConsoleLogger::debug(&format!("Failed to remove scan directory {}: {}", scratch.display(), e));
+        }
+        result
+    }
+
+    fn archive_argv(&self, image_path: &str) -> Argv {
+        match self.kind {
+            ScannerKind::Trivy => Argv::new(&self.program, ["image", "--quiet", "--format", "json", "--input", image_path]),
+            ScannerKind::Grype => Argv::new(&self.program, ["--quiet".to_string(), "--output".to_string(), "json".to_string(), format!("docker-archive:{}", image_path)]),
+        }
+    }
+
+    fn directory_argv(&self, directory: &Path) -> Argv {
+        let directory = directory.display().to_string();
+        match self.kind {
+            ScannerKind::Trivy => Argv::new(&self.program, ["rootfs", "--quiet", "--format", "json", directory.as_str()]),
+            ScannerKind::Grype => Argv::new(&self.program, ["--quiet".to_string(), "--output".to_string(), "json".to_string(), format!("dir:{}", directory)]),
+        }
+    }
+
+    async fn run(&self, argv: &Argv) -> Result<Vec<VulnerabilityFinding>, String> {
+        let result = CommandExecutor::execute_with_timeout(argv, SCAN_TIMEOUT).await?;
+        if !result.success {
+            return Err(format!("{} failed: {}", self.kind.as_str(), result.stderr.trim()));
+        }
+        let report: Value = serde_json::from_str(&result.stdout)
+            .map_err(|e| format!("Unreadable {} report: {}", self.kind.as_str(), e))?;
+        Ok(match self.kind {
+            ScannerKind::Trivy => parse_trivy(&report),
+            ScannerKind::Grype => parse_grype(&report),
+        })
+    }
+
+    /// Refuse an image whose findings reach the block threshold
+    pub fn check(&self, image_path: &str, findings: &[VulnerabilityFinding]) -> Result<(), String> {
+        let Some(threshold) = self.block_at else { return Ok(()) };
+        let blocking: Vec<&VulnerabilityFinding> = findings.iter().filter(|finding| finding.severity >= threshold).collect();
+        if blocking.is_empty() {
+            return Ok(());
+        }
+        let examples: Vec<&str> = blocking.iter().take(REFUSAL_EXAMPLES).map(|finding| finding.id.as_str()).collect();
+        Err(format!("Image {} is not allowed: {} vulnerabilit{} at or above {} severity ({}{})",
+            image_path, blocking.len(), if blocking.len() == 1 { "y" } else { "ies" }, threshold.as_str(),
+            examples.join(", "), if blocking.len() > examples.len() { ", ..." } else { "" }))
+    }
+}
+
+async fn unpack_rootfs(image_path: &str, directory: PathBuf) -> Result<(), String> {
+    let image_path = image_path.to_string();
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| format!("Failed to create scan directory {}: {}", directory.display(), e))?;
+        Archive::new(docker_archive::open_image(&image_path)?).unpack(&directory)
+            .map_err(|e| format!("Failed to unpack {} for scanning: {}", image_path, e))
+    }).await.map_err(|e| format!("Image unpack failed: {}", e))?
+}
+
+/// Identity of an image file's contents as far as scanning is concerned: a rewritten file gets
+/// scanned again
+pub fn image_fingerprint(image_path: &str) -> Result<String, String> {
+    let metadata = std::fs::metadata(image_path).map_err(|_| format!("Image file not found: {}", image_path))?;
+    let modified = metadata.modified().ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+    Ok(format!("{}:{}", metadata.len(), modified))
+}
+
+/// Findings by severity, most severe first, for summaries
+pub fn severity_counts(findings: &[VulnerabilityFinding]) -> Vec<(Severity, usize)> {
+    [Severity::Critical, Severity::High, Severity::Medium, Severity::Low, Severity::Unknown].into_iter()
+        .map(|severity| (severity, findings.iter().filter(|finding| finding.severity == severity).count()))
+        .collect()
+}
+
+fn text(value: &Value, pointer: &str) -> String {
+    value.pointer(pointer).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn severity(value: &Value, pointer: &str) -> Severity {
+    Severity::parse(&text(value, pointer)).unwrap_or(Severity::Unknown)
+}
+
+/// `trivy --format json`: findings under Results[].Vulnerabilities[]
+fn parse_trivy(report: &Value) -> Vec<VulnerabilityFinding> {
+    report.get("Results").and_then(Value::as_array).into_iter().flatten()
+        .flat_map(|result| result.get("Vulnerabilities").and_then(Value::as_array).into_iter().flatten())
+        .map(|vulnerability| VulnerabilityFinding {
+            id: text(vulnerability, "/VulnerabilityID"),
+            package: text(vulnerability, "/PkgName"),
+            installed_version: text(vulnerability, "/InstalledVersion"),
+            fixed_version: text(vulnerability, "/FixedVersion"),
+            severity: severity(vulnerability, "/Severity"),
+        })
+        .collect()
+}
+
+/// `grype -o json`: findings under matches[], split into vulnerability and artifact
+fn parse_grype(report: &Value) -> Vec<VulnerabilityFinding> {
+    report.get("matches").and_then(Value::as_array).into_iter().flatten()
+        .map(|found| VulnerabilityFinding {
+            id: text(found, "/vulnerability/id"),
+            package: text(found, "/artifact/name"),
+            installed_version: text(found, "/artifact/version"),
+            fixed_version: found.pointer("/vulnerability/fix/versions").and_then(Value::as_array).into_iter().flatten()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+            severity: severity(found, "/vulnerability/severity"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanner_config() {
+        assert_eq!(ScannerConfig::parse(" ", Some("critical")).unwrap(), None);
+        let config = ScannerConfig::parse("grype=/opt/bin/grype", Some("High")).unwrap().unwrap();
+        assert_eq!((config.kind, config.program.as_str(), config.block_at), (ScannerKind::Grype, "/opt/bin/grype", Some(Severity::High)));
+        assert_eq!(ScannerConfig::parse("trivy", None).unwrap().unwrap().program, "trivy");
+        assert!(ScannerConfig::parse("clair", None).is_err());
+        assert!(ScannerConfig::parse("trivy", Some("severe")).is_err());
+    }
+
+    #[test]
+    fn test_parse_reports() {
+        let trivy: Value = serde_json::from_str(r#"{"Results": [
+            {"Target": "alpine", "Vulnerabilities": [
+                {"VulnerabilityID": "CVE-2024-0001", "PkgName": "openssl", "InstalledVersion": "3.1.0", "FixedVersion": "3.1.5", "Severity": "CRITICAL"},
+                {"VulnerabilityID": "CVE-2024-0002", "PkgName": "zlib", "InstalledVersion": "1.2", "Severity": "LOW"}
+            ]},
+            {"Target": "app"}
+        ]}"#).unwrap();
+        let findings = parse_trivy(&trivy);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!((findings[1].fixed_version.as_str(), findings[1].package.as_str()), ("", "zlib"));
+
+        let grype: Value = serde_json::from_str(r#"{"matches": [
+            {"vulnerability": {"id": "GHSA-xxxx", "severity": "Negligible", "fix": {"versions": ["2.0", "1.9.9"]}},
+             "artifact": {"name": "busybox", "version": "1.36"}}
+        ]}"#).unwrap();
+        let findings = parse_grype(&grype);
+        assert_eq!(findings[0].fixed_version, "2.0, 1.9.9");
+        assert_eq!(findings[0].severity, Severity::Low);
+    }
+
+    #[test]
+    fn test_block_threshold() {
+        let finding = |id: &str, severity| VulnerabilityFinding {
+            id: id.to_string(),
+            package: "openssl".to_string(),
+            installed_version: "1.0".to_string(),
+            fixed_version: String::new(),
+            severity,
+        };
+        let findings = vec![finding("CVE-1", Severity::High), finding("CVE-2", Severity::Critical), finding("CVE-3", Severity::Low)];
+
+        let block_critical = ScannerConfig::parse("trivy", Some("critical")).unwrap().unwrap();
+        let refusal = block_critical.check("web.tar", &findings).unwrap_err();
+        assert!(refusal.contains("not allowed") && refusal.contains("1 vulnerability") && refusal.contains("CVE-2"));
+        assert!(ScannerConfig::parse("trivy", Some("high")).unwrap().unwrap().check("web.tar", &findings).unwrap_err().contains("2 vulnerabilities"));
+        assert!(ScannerConfig::parse("trivy", None).unwrap().unwrap().check("web.tar", &findings).is_ok());
+
+        assert_eq!(severity_counts(&findings)[..2], [(Severity::Critical, 1), (Severity::High, 1)]);
+    }
+}
@@ -0,0 +1,247 @@
+// Local image store
+// Images are unpacked once and kept under QUILT_IMAGE_STORE (default /var/lib/quilt/store), one
+// directory per layer named by the sha256 digest of the layer tarball, so images built on the
+// same base share its layers. An image is identified by the digest of its ordered layer digests
+// and its config. A container's rootfs is copied together from the layer directories instead of
+// unpacking the image tarball again for every container; the index of which image files and tags
+// stand for which stored image lives in SQLite (sync/images.rs).
+
+use crate::daemon::docker_archive;
+use crate::daemon::image_config::{self, ImageConfig};
+use crate::utils::command::{Argv, CommandExecutor};
+use crate::utils::console::ConsoleLogger;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tar::Archive;
+
+/// Environment variable naming the store's root directory
+pub const IMAGE_STORE_ENV: &str = "QUILT_IMAGE_STORE";
+pub const DEFAULT_IMAGE_STORE: &str = "/var/lib/quilt/store";
+
+/// An image whose layers are unpacked in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredImage {
+    /// `sha256:<hex>` over the layer digests and the config
+    pub digest: String,
+    pub repo_tags: Vec<String>,
+    /// Layer digests, oldest first
+    pub layers: Vec<String>,
+    pub config: ImageConfig,
+    /// Size of the layer tarballs as imported
+    pub size_bytes: u64,
+}
+
+/// The store's root from QUILT_IMAGE_STORE, else [`DEFAULT_IMAGE_STORE`]
+pub fn image_store_root() -> PathBuf {
+    std::env::var(IMAGE_STORE_ENV)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_IMAGE_STORE))
+}
+
+/// Directory the layer with `digest` is kept unpacked in
+pub fn layer_dir(root: &Path, digest: &str) -> PathBuf {
+    root.join("layers").join(digest.trim_start_matches("sha256:"))
+}
+
+/// Whether every layer of `image` is still in the store
+pub fn is_complete(root: &Path, image: &StoredImage) -> bool {
+    image.layers.iter().all(|layer| layer_dir(root, layer).is_dir())
+}
+
+/// Unpack the image tarball at `image_path`, a `docker save` archive or a plain rootfs tarball,
+/// into the store. Layers the store already holds are not unpacked again. Blocking.
+pub fn import(root: &Path, image_path: &str) -> Result<StoredImage, String> {
+    let staging = root.join("tmp").join(uuid::Uuid::new_v4().to_string());
+    let result = import_via(root, image_path, &staging);
+    if staging.exists() {
+        if let Err(e) = fs::remove_dir_all(&staging) {
+            ConsoleLogger::debug(&format!("Failed to remove image staging directory {}: {}", staging.display(), e));
+        }
+    }
+    result
+}
+
+fn import_via(root: &Path, image_path: &str, staging: &Path) -> Result<StoredImage, String> {
+    // A plain rootfs tarball is an image of one layer
+    let (repo_tags, layer_files, config) = if docker_archive::is_docker_archive(image_path)? {
+        let archive = docker_archive::unpack_archive(image_path, staging)?;
+        (archive.repo_tags, archive.layers, archive.config)
+    } else {
+        (Vec::new(), vec![PathBuf::from(image_path)], ImageConfig::default())
+    };
+
+    let mut layers = Vec::with_capacity(layer_files.len());
+    let mut size_bytes = 0;
+    for file in &layer_files {
+        let digest = sha256_file(file)?;
+        size_bytes += fs::metadata(file).map(|metadata| metadata.len()).unwrap_or(0);
+        store_layer(root, &digest, file)?;
+        layers.push(digest);
+    }
+
+    let config_json = serde_json::to_string(&config).map_err(|e| format!("Failed to encode image config: {}", e))?;
+    let digest = sha256_bytes(format!("{}\n{}", layers.join("\n"), config_json))?;
+    ConsoleLogger::debug(&format!("Stored {} as {} ({} layers)", image_path, digest, layers.len()));
+    Ok(StoredImage { digest, repo_tags, layers, config, size_bytes })
+}
+
+/// Unpack a layer tarball into its directory unless the store has it already. It is unpacked
+/// beside the store's layers and renamed into place, so a crash or a concurrent import of the
+/// same layer never leaves a partial layer behind.
+fn store_layer(root: &Path, digest: &str, file: &Path) -> Result<(), String> {
+    let target = layer_dir(root, digest);
+    if target.is_dir() {
+        return Ok(());
+    }
+    let partial = root.join("tmp").join(format!("layer-{}", uuid::Uuid::new_v4()));
+    let unpacked = fs::create_dir_all(&partial)
+        .and_then(|_| docker_archive::open_maybe_gzip(File::open(file)?))
+        .and_then(|reader| {
+            let mut archive = Archive::new(reader);
+            archive.set_preserve_permissions(true);
+            archive.unpack(&partial)
+        })
+        .and_then(|_| fs::create_dir_all(root.join("layers")))
+        .and_then(|_| fs::rename(&partial, &target));
+    match unpacked {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&partial);
+            // Someone else stored the same layer first
+            if target.is_dir() {
+                return Ok(());
+            }
+            Err(format!("Failed to store layer {} of {}: {}", digest, file.display(), e))
+        }
+    }
+}
+
+/// Build a container rootfs in the existing directory `rootfs_path` from the stored layers of
+/// `image`, and keep the image's config with it. Blocking.
+pub fn materialize(root: &Path, image: &StoredImage, rootfs_path: &str) -> Result<(), String> {
+    for (index, layer) in image.layers.iter().enumerate() {
+        docker_archive::apply_layer_dir(&layer_dir(root, layer), Path::new(rootfs_path))
+            .map_err(|e| format!("Failed to apply stored layer {} ({}): {}", index + 1, layer, e))?;
+    }
+    // Plain rootfs tarballs have no config to keep
+    if image.config != ImageConfig::default() {
+        image_config::save(rootfs_path, &image.config)?;
+    }
+    Ok(())
+}
+
+/// Delete the layers of `image` that none of the `remaining` images uses. Returns the digests of
+/// the layers removed. Blocking.
+pub fn remove_layers(root: &Path, image: &StoredImage, remaining: &[StoredImage]) -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+    for layer in &image.layers {
+        let shared = remaining.iter().any(|other| other.digest != image.digest && other.layers.contains(layer));
+        let dir = layer_dir(root, layer);
+        if shared || !dir.is_dir() || removed.contains(layer) {
+            continue;
+        }
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove layer {}: {}", layer, e))?;
+        removed.push(layer.clone());
+    }
+    Ok(removed)
+}
+
+/// `sha256:<hex>` of a file's contents
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let path_arg = path.display().to_string();
+    sha256_output(&Argv::new("sha256sum", ["--", path_arg.as_str()]))
+}
+
+pub fn sha256_bytes(data: impl Into<Vec<u8>>) -> Result<String, String> {
+    sha256_output(&Argv::new("sha256sum", ["-"]).with_input(data))
+}
+
+fn sha256_output(argv: &Argv) -> Result<String, String> {
+    let result = CommandExecutor::execute(argv)?;
+    let hex = result.stdout.split_whitespace().next().unwrap_or_default();
+    if !result.success || hex.len() != 64 {
+        return Err(format!("Failed to compute digest: {}", result.stderr.trim()));
+    }
+    Ok(format!("sha256:{}", hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_store_and_materialize() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("store");
+        let base = layer(&[("etc/motd", "base"), ("etc/hostname", "old"), ("var/cache/stale", "x")]);
+        let top = layer(&[("etc/.wh.hostname", ""), ("etc/motd", "top"), ("var/cache/.wh..wh..opq", ""), ("var/cache/fresh", "y")]);
+        let (first, second) = (format!("{}/layer.tar", "1".repeat(64)), format!("{}/layer.tar", "2".repeat(64)));
+        let manifest = format!(r#"[{{"Config":"c.json","RepoTags":["demo:latest"],"Layers":["{}","{}"]}}]"#, first, second);
+        let config = r#"{"config":{"Env":["PATH=/usr/bin"],"WorkingDir":"/srv"}}"#;
+
+        let mut outer = tar::Builder::new(Vec::new());
+        for (path, data) in [(first.as_str(), base.as_slice()), (second.as_str(), top.as_slice()), ("c.json", config.as_bytes()), ("manifest.json", manifest.as_bytes())] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            outer.append_data(&mut header, path, data).unwrap();
+        }
+        let archive = dir.path().join("image.tar");
+        fs::write(&archive, outer.into_inner().unwrap()).unwrap();
+        let plain = dir.path().join("rootfs.tar");
+        fs::write(&plain, &base).unwrap();
+
+        let image = import(&root, archive.to_str().unwrap()).unwrap();
+        assert_eq!(image.repo_tags, vec!["demo:latest"]);
+        assert_eq!(image.layers.len(), 2);
+        assert!(image.digest.starts_with("sha256:"));
+        assert!(is_complete(&root, &image));
+        assert_eq!(import(&root, archive.to_str().unwrap()).unwrap(), image);
+        assert_eq!(fs::read_dir(root.join("tmp")).unwrap().count(), 0);
+
+        // The plain tarball is the archive's base layer, which the store already holds
+        let rootfs_image = import(&root, plain.to_str().unwrap()).unwrap();
+        assert_eq!(rootfs_image.layers, image.layers[..1]);
+        assert_ne!(rootfs_image.digest, image.digest);
+        assert_eq!(fs::read_dir(root.join("layers")).unwrap().count(), 2);
+
+        let rootfs = dir.path().join("rootfs");
+        fs::create_dir_all(&rootfs).unwrap();
+        materialize(&root, &image, rootfs.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read_to_string(rootfs.join("etc/motd")).unwrap(), "top");
+        assert!(!rootfs.join("etc/hostname").exists());
+        assert!(!rootfs.join("etc/.wh.hostname").exists());
+        assert!(!rootfs.join("var/cache/stale").exists());
+        assert!(rootfs.join("var/cache/fresh").exists());
+        assert_eq!(image_config::load(rootfs.to_str().unwrap()).working_dir.as_deref(), Some("/srv"));
+
+        // The container's copy is its own
+        fs::write(rootfs.join("etc/motd"), "changed").unwrap();
+        let stored_motd = layer_dir(&root, &image.layers[1]).join("etc/motd");
+        assert_eq!(fs::read_to_string(stored_motd).unwrap(), "top");
+
+        fs::remove_dir_all(layer_dir(&root, &image.layers[0])).unwrap();
+        assert!(!is_complete(&root, &image));
+
+        // The base layer stays for the plain image
+        fs::create_dir_all(layer_dir(&root, &image.layers[0])).unwrap();
+        assert_eq!(remove_layers(&root, &image, &[rootfs_image.clone()]).unwrap(), image.layers[1..]);
+        assert!(is_complete(&root, &rootfs_image));
+        assert_eq!(remove_layers(&root, &rootfs_image, &[]).unwrap(), rootfs_image.layers);
+        assert_eq!(fs::read_dir(root.join("layers")).unwrap().count(), 0);
+    }
+}
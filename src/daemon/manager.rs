@@ -1,6 +1,9 @@
-use std::process::Command;
+use std::process::{Command, Output};
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
 use crate::daemon::system::SystemRuntime;
+use crate::daemon::setup_report::SetupStepReport;
 use crate::utils::console::ConsoleLogger;
 use crate::utils::command::CommandExecutor;
 
@@ -48,12 +51,16 @@ impl Runtime {
 pub struct SetupCommand {
     pub runtime: Runtime,
     pub packages: Vec<String>,
+    /// The line it was parsed from, for reporting
+    pub spec: String,
 }
 
 pub struct RuntimeManager {
     system_runtime: SystemRuntime,
     installed_runtimes: HashMap<String, Runtime>,
     available_package_manager: Option<String>,
+    /// Output of the package manager commands run by the current setup step
+    step_output: Mutex<String>,
 }
 
 impl RuntimeManager {
@@ -62,6 +69,7 @@ impl RuntimeManager {
             system_runtime: SystemRuntime::new(),
             installed_runtimes: HashMap::new(),
             available_package_manager: None,
+            step_output: Mutex::new(String::new()),
         }
     }
 
@@ -116,14 +124,21 @@ impl RuntimeManager {
             if packages.is_empty() {
                 return Err(format!("No packages specified for runtime: {}", runtime_str));
             }
+            // Packages are passed to the package manager as arguments, so one that looks like an
+            // option would be read as one
+            if let Some(option) = packages.iter().find(|package| package.starts_with('-')) {
+                return Err(format!("Invalid package name '{}': package names cannot start with '-'", option));
+            }
             
-            Ok(SetupCommand { runtime, packages })
+            Ok(SetupCommand { runtime, packages, spec: line.to_string() })
         } else {
             Err(format!("Invalid setup command format: '{}'. Expected 'runtime: package1 package2'", line))
         }
     }
 
-    pub fn execute_setup_commands(&mut self, commands: &[SetupCommand]) -> Result<(), String> {
+    /// Run the setup commands in order, handing each finished step to `on_step`; stops at the
+    /// first failing step
+    pub fn execute_setup_commands(&mut self, commands: &[SetupCommand], on_step: &mut dyn FnMut(&SetupStepReport)) -> Result<(), String> {
         if commands.is_empty() {
             return Ok(());
         }
@@ -138,22 +153,54 @@ impl RuntimeManager {
             None => "none".to_string(),
         };
 
-        for command in commands {
+        for (index, command) in commands.iter().enumerate() {
             ConsoleLogger::progress(&format!("Executing setup command: Install {} packages: {}", 
                     command.runtime.get_name(), 
                     command.packages.join(", ")));
             
-            if matches!(command.runtime, Runtime::Nix) {
-                self.handle_nix_packages(&command.packages)?;
+            self.take_step_output();
+            let step_start = Instant::now();
+            let result = if matches!(command.runtime, Runtime::Nix) {
+                self.handle_nix_packages(&command.packages)
             } else {
-                self.ensure_runtime_available(&command.runtime, &package_manager)?;
-                self.install_packages(&command.runtime, &command.packages, &package_manager)?;
+                self.ensure_runtime_available(&command.runtime, &package_manager)
+                    .and_then(|()| self.install_packages(&command.runtime, &command.packages, &package_manager))
+            };
+            
+            // Install errors repeat the captured stderr, so only their first line is added to it
+            let mut output = self.take_step_output();
+            if let Err(e) = &result {
+                output.push_str(if output.is_empty() { e.as_str() } else { e.lines().next().unwrap_or_default() });
             }
+            on_step(&SetupStepReport {
+                index,
+                command: command.spec.clone(),
+                succeeded: result.is_ok(),
+                output,
+                duration_ms: step_start.elapsed().as_millis() as u64,
+            });
+            result.map_err(|e| format!("Setup step {} ({}) failed: {}", index + 1, command.spec, e))?;
         }
         
         Ok(())
     }
 
+    /// Keep a package manager's stdout/stderr for the current step's report
+    fn capture_output(&self, output: &Output) {
+        let mut captured = self.step_output.lock().unwrap();
+        for stream in [&output.stdout, &output.stderr] {
+            let text = String::from_utf8_lossy(stream);
+            if !text.trim().is_empty() {
+                captured.push_str(text.trim_end());
+                captured.push('\n');
+            }
+        }
+    }
+
+    fn take_step_output(&self) -> String {
+        std::mem::take(&mut *self.step_output.lock().unwrap())
+    }
+
     /// Handle Nix package specifications
     fn handle_nix_packages(&self, packages: &[String]) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Processing Nix packages: {:?}", packages));
@@ -319,6 +366,7 @@ impl RuntimeManager {
 
         match cmd.output() {
             Ok(output) => {
+                self.capture_output(&output);
                 if output.status.success() {
                     ConsoleLogger::package_installed(packages, "npm");
                     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -348,6 +396,7 @@ impl RuntimeManager {
 
         match cmd.output() {
             Ok(output) => {
+                self.capture_output(&output);
                 if output.status.success() {
                     ConsoleLogger::package_installed(packages, "pip");
                     Ok(())
@@ -373,6 +422,7 @@ impl RuntimeManager {
 
             match cmd.output() {
                 Ok(output) => {
+                    self.capture_output(&output);
                     if !output.status.success() {
                         let stderr = String::from_utf8_lossy(&output.stderr);
                         return Err(format!("Failed to install gem package {}: {}", package, stderr));
@@ -399,6 +449,7 @@ impl RuntimeManager {
 
             match cmd.output() {
                 Ok(output) => {
+                    self.capture_output(&output);
                     if !output.status.success() {
                         let stderr = String::from_utf8_lossy(&output.stderr);
                         return Err(format!("Failed to install Go package {}: {}", package, stderr));
@@ -431,6 +482,7 @@ impl RuntimeManager {
 
         match cmd.output() {
             Ok(output) => {
+                self.capture_output(&output);
                 if output.status.success() {
                     ConsoleLogger::package_installed(packages, "composer");
                     Ok(())
@@ -470,10 +522,13 @@ mod tests {
         let command = manager.parse_setup_line("nodejs: typescript ts-node").unwrap();
         assert!(matches!(command.runtime, Runtime::NodeJs));
         assert_eq!(command.packages, vec!["typescript", "ts-node"]);
+        assert_eq!(command.spec, "nodejs: typescript ts-node");
         
         let command = manager.parse_setup_line("python: requests flask").unwrap();
         assert!(matches!(command.runtime, Runtime::Python));
         assert_eq!(command.packages, vec!["requests", "flask"]);
+
+        assert!(manager.parse_setup_line("nodejs: typescript --prefix=/").is_err());
     }
 
     #[test]
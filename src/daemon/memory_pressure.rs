@@ -0,0 +1,258 @@
+// Host memory-pressure response
+// Reads the host's memory pressure stall information (PSI, /proc/pressure/memory) and freezes or
+// throttles low-priority containers through their cgroup v2 directory, so that under severe
+// pressure the daemon picks who gives way instead of the kernel OOM killer. Every container acted
+// on is remembered here until it is released, so stop and cleanup can undo it.
+
+use crate::utils::console::ConsoleLogger;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Environment variable enabling the responder, as comma-separated `<key>=<value>` pairs:
+/// `action=freeze|throttle`, `threshold=<full avg10 %>`, `recover=<full avg10 %>` and
+/// `selector=<label>=<value>`; `on` alone uses the defaults and `off` disables it
+pub const MEMORY_PRESSURE_ENV: &str = "QUILT_MEMORY_PRESSURE";
+
+const HOST_MEMORY_PRESSURE: &str = "/proc/pressure/memory";
+/// Label that marks containers which may be frozen or throttled by default
+pub const DEFAULT_PRESSURE_LABEL: (&str, &str) = ("quilt.priority", "low");
+
+static ACTIONS: once_cell::sync::Lazy<Mutex<HashMap<String, AppliedAction>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureAction {
+    /// Stop every process of the container (cgroup.freeze); its memory stays but stops growing
+    Freeze,
+    /// Cap memory.high at current usage so the kernel reclaims from this container first
+    Throttle,
+}
+
+impl PressureAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PressureAction::Freeze => "freeze",
+            PressureAction::Throttle => "throttle",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "freeze" => Some(PressureAction::Freeze),
+            "throttle" => Some(PressureAction::Throttle),
+            _ => None,
+        }
+    }
+}
+
+/// When to act and on which containers
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPressurePolicy {
+    pub action: PressureAction,
+    /// Host `full avg10` (share of time all non-idle tasks stalled on memory) that triggers action
+    pub threshold: f64,
+    /// Host `full avg10` below which containers are released again
+    pub recover_threshold: f64,
+    /// Label key and value selecting the containers that may be acted on
+    pub selector: (String, String),
+}
+
+impl Default for MemoryPressurePolicy {
+    fn default() -> Self {
+        Self {
+            action: PressureAction::Freeze,
+            threshold: 10.0,
+            recover_threshold: 2.0,
+            selector: (DEFAULT_PRESSURE_LABEL.0.to_string(), DEFAULT_PRESSURE_LABEL.1.to_string()),
+        }
+    }
+}
+
+impl MemoryPressurePolicy {
+    /// The policy from `QUILT_MEMORY_PRESSURE`; None (responder off) when unset or `off`
+    pub fn from_env() -> Option<Self> {
+        std::env::var(MEMORY_PRESSURE_ENV).ok().and_then(|value| Self::parse(&value))
+    }
+
+    /// Parse `action=throttle,threshold=20,selector=tier=batch`; malformed entries are skipped
+    /// with a warning
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut policy = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            if entry == "off" {
+                return None;
+            }
+            if entry == "on" {
+                continue;
+            }
+            let applied = entry.split_once('=').and_then(|(key, value)| {
+                let value = value.trim();
+                match key.trim() {
+                    "action" => policy.action = PressureAction::from_str(value)?,
+                    "threshold" => policy.threshold = value.parse().ok().filter(|v: &f64| *v > 0.0 && *v <= 100.0)?,
+                    "recover" => policy.recover_threshold = value.parse().ok().filter(|v: &f64| *v >= 0.0 && *v <= 100.0)?,
+                    "selector" => {
+                        let (label, label_value) = value.split_once('=')?;
+                        if label.trim().is_empty() {
+                            return None;
+                        }
+                        policy.selector = (label.trim().to_string(), label_value.trim().to_string());
+                    }
+                    _ => return None,
+                }
+                Some(())
+            });
+            if applied.is_none() {
+                ConsoleLogger::warning(&format!("Ignoring malformed {} entry: {}", MEMORY_PRESSURE_ENV, entry));
+            }
+        }
+        if policy.recover_threshold >= policy.threshold {
+            ConsoleLogger::warning(&format!("{}: recover must be below threshold; using {}", MEMORY_PRESSURE_ENV, policy.threshold / 2.0));
+            policy.recover_threshold = policy.threshold / 2.0;
+        }
+        Some(policy)
+    }
+
+    /// Whether a container with these labels may be frozen or throttled
+    pub fn selects(&self, labels: &HashMap<String, String>) -> bool {
+        labels.get(&self.selector.0) == Some(&self.selector.1)
+    }
+}
+
+/// The 10-second averages of one PSI file, in percent
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureStall {
+    /// Some task stalled on memory
+    pub some_avg10: f64,
+    /// All non-idle tasks stalled on memory at once
+    pub full_avg10: f64,
+}
+
+pub fn read_host_memory_pressure() -> Result<PressureStall, String> {
+    let content = std::fs::read_to_string(HOST_MEMORY_PRESSURE)
+        .map_err(|e| format!("Failed to read {} (kernel without PSI?): {}", HOST_MEMORY_PRESSURE, e))?;
+    parse_pressure(&content).ok_or_else(|| format!("Unexpected format in {}", HOST_MEMORY_PRESSURE))
+}
+
+/// Parse `some avg10=1.23 avg60=...` / `full avg10=...` lines
+fn parse_pressure(content: &str) -> Option<PressureStall> {
+    let avg10 = |kind: &str| content.lines()
+        .find(|line| line.split_whitespace().next() == Some(kind))
+        .and_then(|line| line.split_whitespace().find_map(|field| field.strip_prefix("avg10=")))
+        .and_then(|value| value.parse::<f64>().ok());
+    Some(PressureStall { some_avg10: avg10("some")?, full_avg10: avg10("full")? })
+}
+
+/// What was done to a container, with what is needed to undo it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppliedAction {
+    Frozen,
+    /// memory.high before throttling
+    Throttled { previous_high: String },
+}
+
+impl AppliedAction {
+    /// Event/log wording: "frozen" or "throttled"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppliedAction::Frozen => "frozen",
+            AppliedAction::Throttled { .. } => "throttled",
+        }
+    }
+}
+
+fn container_cgroup(container_id: &str) -> PathBuf {
+    PathBuf::from("/sys/fs/cgroup/quilt").join(container_id)
+}
+
+fn write_cgroup_file(container_id: &str, file: &str, value: &str) -> Result<(), String> {
+    let path = container_cgroup(container_id).join(file);
+    std::fs::write(&path, value).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Memory charged to the container's cgroup, used to act on the largest containers first
+pub fn container_memory_usage(container_id: &str) -> Option<u64> {
+    std::fs::read_to_string(container_cgroup(container_id).join("memory.current")).ok()?.trim().parse().ok()
+}
+
+/// Freeze or throttle a container; a container already acted on is left as it is
+pub fn apply(container_id: &str, action: PressureAction) -> Result<AppliedAction, String> {
+    let mut actions = ACTIONS.lock().map_err(|_| "Memory pressure state lock poisoned".to_string())?;
+    if let Some(applied) = actions.get(container_id) {
+        return Ok(applied.clone());
+    }
+
+    let applied = match action {
+        PressureAction::Freeze => {
+            write_cgroup_file(container_id, "cgroup.freeze", "1")?;
+            AppliedAction::Frozen
+        }
+        PressureAction::Throttle => {
+            let cgroup = container_cgroup(container_id);
+            let previous_high = std::fs::read_to_string(cgroup.join("memory.high"))
+                .map_err(|e| format!("Failed to read memory.high of {} (cgroup v2 required): {}", container_id, e))?
+                .trim().to_string();
+            let usage = container_memory_usage(container_id)
+                .ok_or_else(|| format!("Failed to read memory usage of {}", container_id))?;
+            write_cgroup_file(container_id, "memory.high", &usage.to_string())?;
+            AppliedAction::Throttled { previous_high }
+        }
+    };
+    actions.insert(container_id.to_string(), applied.clone());
+    Ok(applied)
+}
+
+/// Undo whatever was applied to the container; returns what was undone. Safe to call for
+/// containers that were never acted on.
+pub fn release(container_id: &str) -> Option<AppliedAction> {
+    let applied = ACTIONS.lock().ok()?.remove(container_id)?;
+    let result = match &applied {
+        AppliedAction::Frozen => write_cgroup_file(container_id, "cgroup.freeze", "0"),
+        AppliedAction::Throttled { previous_high } => write_cgroup_file(container_id, "memory.high", previous_high),
+    };
+    // The cgroup may already be gone with the container
+    if let Err(e) = result {
+        ConsoleLogger::debug(&format!("🧊 [MEM-PRESSURE] Release of {} incomplete: {}", container_id, e));
+    }
+    Some(applied)
+}
+
+/// Containers currently frozen or throttled
+pub fn acted_on() -> Vec<(String, AppliedAction)> {
+    ACTIONS.lock()
+        .map(|actions| actions.iter().map(|(id, applied)| (id.clone(), applied.clone())).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pressure() {
+        let content = "some avg10=12.50 avg60=3.10 avg300=0.80 total=123456\nfull avg10=4.25 avg60=1.00 avg300=0.20 total=65432\n";
+        assert_eq!(parse_pressure(content), Some(PressureStall { some_avg10: 12.5, full_avg10: 4.25 }));
+        // The memory file always has both lines; anything else is not PSI
+        assert_eq!(parse_pressure("some avg10=1.00 avg60=0.00 avg300=0.00 total=1\n"), None);
+    }
+
+    #[test]
+    fn test_policy_parsing() {
+        assert_eq!(MemoryPressurePolicy::parse("on"), Some(MemoryPressurePolicy::default()));
+        assert_eq!(MemoryPressurePolicy::parse("off"), None);
+
+        let policy = MemoryPressurePolicy::parse("action=throttle, threshold=20, recover=5, selector=tier=batch").unwrap();
+        assert_eq!(policy.action, PressureAction::Throttle);
+        assert_eq!(policy.threshold, 20.0);
+        assert_eq!(policy.recover_threshold, 5.0);
+        assert!(policy.selects(&HashMap::from([("tier".to_string(), "batch".to_string())])));
+        assert!(!policy.selects(&HashMap::from([("tier".to_string(), "web".to_string())])));
+
+        // Malformed entries keep the defaults; recover is kept below threshold
+        let policy = MemoryPressurePolicy::parse("action=pause,threshold=150,recover=50").unwrap();
+        assert_eq!(policy.action, PressureAction::Freeze);
+        assert_eq!(policy.threshold, 10.0);
+        assert_eq!(policy.recover_threshold, 5.0);
+    }
+}
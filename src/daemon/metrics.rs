@@ -1,6 +1,112 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 use serde::{Serialize, Deserialize};
+use crate::daemon::exec_cgroup::workload_path;
+use crate::utils::console::ConsoleLogger;
+
+/// CPU samples older than this are not used as the start of a cpu_percent window
+const CPU_SAMPLE_MAX_AGE_MS: u64 = 10 * 60 * 1000;
+
+/// Seconds between samples of a container without its own interval, and so the age of a stored
+/// sample GetMetrics serves by default
+pub const DEFAULT_METRICS_INTERVAL_SECS: u32 = 30;
+/// Longest per-container metrics interval
+pub const MAX_METRICS_INTERVAL_SECS: u32 = 3600;
+
+/// Environment variable configuring the background collector, as comma-separated
+/// `<key>=<value>` pairs: `interval=<seconds>` (default for containers without their own) and
+/// `jitter=<percent>` (0-50, spreads samples so containers are not all read at once); `off`
+/// disables it and leaves collection to GetMetrics
+pub const METRICS_COLLECTION_ENV: &str = "QUILT_METRICS_COLLECTION";
+
+/// How the background collector samples running containers
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsCollectionPolicy {
+    /// Interval of containers created without `metrics_interval_seconds`
+    pub interval: Duration,
+    /// Each delay until a container's next sample is moved by up to this share of its interval
+    pub jitter_percent: u32,
+}
+
+impl Default for MetricsCollectionPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(DEFAULT_METRICS_INTERVAL_SECS as u64),
+            jitter_percent: 10,
+        }
+    }
+}
+
+impl MetricsCollectionPolicy {
+    /// The policy from `QUILT_METRICS_COLLECTION`, the default when unset; None when `off`
+    pub fn from_env() -> Option<Self> {
+        match std::env::var(METRICS_COLLECTION_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Some(Self::default()),
+        }
+    }
+
+    /// Parse `interval=15,jitter=20`; malformed entries are skipped with a warning
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut policy = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            if entry == "off" {
+                return None;
+            }
+            if entry == "on" {
+                continue;
+            }
+            let applied = entry.split_once('=').and_then(|(key, value)| {
+                let value = value.trim();
+                match key.trim() {
+                    "interval" => policy.interval = value.parse().ok()
+                        .filter(|seconds: &u64| (1..=MAX_METRICS_INTERVAL_SECS as u64).contains(seconds))
+                        .map(Duration::from_secs)?,
+                    "jitter" => policy.jitter_percent = value.trim_end_matches('%').parse().ok().filter(|percent: &u32| *percent <= 50)?,
+                    _ => return None,
+                }
+                Some(())
+            });
+            if applied.is_none() {
+                ConsoleLogger::warning(&format!("Ignoring malformed {} entry: {}", METRICS_COLLECTION_ENV, entry));
+            }
+        }
+        Some(policy)
+    }
+
+    /// Interval of a container, its own when it was created with one
+    pub fn interval_for(&self, interval_seconds: Option<u32>) -> Duration {
+        interval_seconds.filter(|seconds| *seconds > 0)
+            .map(|seconds| Duration::from_secs(seconds as u64))
+            .unwrap_or(self.interval)
+    }
+
+    /// Delay until the next sample: `interval` moved by `unit` (-1.0 to 1.0) times the jitter
+    pub fn jittered(&self, interval: Duration, unit: f64) -> Duration {
+        let jitter = interval.as_secs_f64() * self.jitter_percent as f64 / 100.0 * unit.clamp(-1.0, 1.0);
+        Duration::from_secs_f64((interval.as_secs_f64() + jitter).max(0.0))
+    }
+}
+
+// Last (timestamp ms, usage_usec) per container, the start of the next cpu_percent window
+static LAST_CPU_SAMPLES: once_cell::sync::Lazy<Mutex<HashMap<String, (u64, u64)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Host per-CPU times and bridge counters at the previous system sample
+static LAST_HOST_CPU_TIMES: once_cell::sync::Lazy<Mutex<Vec<CpuTimes>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
+static LAST_BRIDGE_SAMPLES: once_cell::sync::Lazy<Mutex<HashMap<String, (u64, u64, u64)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerMetrics {
@@ -10,6 +116,12 @@ pub struct ContainerMetrics {
     pub memory: MemoryMetrics,
     pub network: NetworkMetrics,
     pub disk: DiskMetrics,
+    /// CPU use since the container's previous sample, 100 = one full core; 0 for a first sample
+    #[serde(default)]
+    pub cpu_percent: f64,
+    /// memory.current as a share of the memory limit, or of host memory when unlimited
+    #[serde(default)]
+    pub memory_percent: f64,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -39,6 +151,14 @@ pub struct NetworkMetrics {
     pub tx_packets: u64,
     pub rx_errors: u64,
     pub tx_errors: u64,
+    /// Drops on the host-side veth; filled in for live metrics only
+    #[serde(default)]
+    pub rx_dropped: u64,
+    #[serde(default)]
+    pub tx_dropped: u64,
+    /// "ebpf" or "procfs" for live metrics, empty for stored history
+    #[serde(default)]
+    pub source: String,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -70,9 +190,15 @@ impl MetricsCollector {
 
         let cpu = self.collect_cpu_metrics(container_id)?;
         let memory = self.collect_memory_metrics(container_id)?;
-        let network = self.collect_network_metrics(pid)?;
+        let network = self.collect_network_metrics(container_id, pid)?;
         let disk = self.collect_disk_metrics(pid)?;
 
+        let cpu_percent = Self::cpu_percent_since_last_sample(container_id, timestamp, cpu.usage_usec);
+        let host_total_bytes = SystemMetrics::get_memory_info()
+            .map(|(_, total_mb)| total_mb * 1024 * 1024)
+            .unwrap_or(0);
+        let memory_percent = memory_percent(memory.current_bytes, memory.limit_bytes, host_total_bytes);
+
         Ok(ContainerMetrics {
             container_id: container_id.to_string(),
             timestamp,
@@ -80,19 +206,33 @@ impl MetricsCollector {
             memory,
             network,
             disk,
+            cpu_percent,
+            memory_percent,
         })
     }
 
+    /// Record this sample and return CPU use over the window since the previous one
+    fn cpu_percent_since_last_sample(container_id: &str, timestamp: u64, usage_usec: u64) -> f64 {
+        let mut samples = lock(&LAST_CPU_SAMPLES);
+        let previous = samples.insert(container_id.to_string(), (timestamp, usage_usec));
+        samples.retain(|_, (sampled_at, _)| timestamp.saturating_sub(*sampled_at) <= CPU_SAMPLE_MAX_AGE_MS);
+
+        previous
+            .filter(|(sampled_at, _)| timestamp.saturating_sub(*sampled_at) <= CPU_SAMPLE_MAX_AGE_MS)
+            .map(|previous| cpu_percent(previous, (timestamp, usage_usec)))
+            .unwrap_or(0.0)
+    }
+
     fn collect_cpu_metrics(&self, container_id: &str) -> Result<CpuMetrics, String> {
         let mut metrics = CpuMetrics::default();
 
         // Try cgroup v2 first
         let cgroup_v2_path = Path::new(&self.cgroup_root).join("cgroup.controllers");
         if cgroup_v2_path.exists() {
-            let cpu_stat_path = Path::new(&self.cgroup_root)
+            let container_path = Path::new(&self.cgroup_root)
                 .join("quilt")
-                .join(container_id)
-                .join("cpu.stat");
+                .join(container_id);
+            let cpu_stat_path = container_path.join("cpu.stat");
 
             if let Ok(content) = fs::read_to_string(&cpu_stat_path) {
                 for line in content.lines() {
@@ -110,6 +250,24 @@ impl MetricsCollector {
                     }
                 }
             }
+
+            // Throttling applies to the whole container, usage only to its own processes
+            let workload = workload_path(&container_path);
+            if workload != container_path {
+                if let Ok(content) = fs::read_to_string(workload.join("cpu.stat")) {
+                    for line in content.lines() {
+                        let parts: Vec<&str> = line.split_whitespace().collect();
+                        if parts.len() == 2 {
+                            match parts[0] {
+                                "usage_usec" => metrics.usage_usec = parts[1].parse().unwrap_or(0),
+                                "user_usec" => metrics.user_usec = parts[1].parse().unwrap_or(0),
+                                "system_usec" => metrics.system_usec = parts[1].parse().unwrap_or(0),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
         } else {
             // Fallback to cgroup v1
             let cpu_acct_path = Path::new(&self.cgroup_root)
@@ -146,12 +304,14 @@ impl MetricsCollector {
             let memory_path = Path::new(&self.cgroup_root)
                 .join("quilt")
                 .join(container_id);
+            // The limit is the container's; usage leaves out exec sessions
+            let usage_path = workload_path(&memory_path);
 
-            if let Ok(current) = fs::read_to_string(memory_path.join("memory.current")) {
+            if let Ok(current) = fs::read_to_string(usage_path.join("memory.current")) {
                 metrics.current_bytes = current.trim().parse().unwrap_or(0);
             }
 
-            if let Ok(peak) = fs::read_to_string(memory_path.join("memory.peak")) {
+            if let Ok(peak) = fs::read_to_string(usage_path.join("memory.peak")) {
                 metrics.peak_bytes = peak.trim().parse().unwrap_or(0);
             }
 
@@ -162,7 +322,7 @@ impl MetricsCollector {
                 }
             }
 
-            if let Ok(stat) = fs::read_to_string(memory_path.join("memory.stat")) {
+            if let Ok(stat) = fs::read_to_string(usage_path.join("memory.stat")) {
                 for line in stat.lines() {
                     let parts: Vec<&str> = line.split_whitespace().collect();
                     if parts.len() == 2 {
@@ -205,9 +365,20 @@ impl MetricsCollector {
         Ok(metrics)
     }
 
-    fn collect_network_metrics(&self, pid: Option<i32>) -> Result<NetworkMetrics, String> {
+    fn collect_network_metrics(&self, container_id: &str, pid: Option<i32>) -> Result<NetworkMetrics, String> {
         let mut metrics = NetworkMetrics::default();
 
+        // eBPF counters cover every interface in the container's cgroup; no need to parse procfs
+        if let Some(counters) = crate::daemon::net_accounting::container_counters(container_id) {
+            metrics.rx_bytes = counters.rx_bytes;
+            metrics.tx_bytes = counters.tx_bytes;
+            metrics.rx_packets = counters.rx_packets;
+            metrics.tx_packets = counters.tx_packets;
+            metrics.source = "ebpf".to_string();
+            return Ok(metrics);
+        }
+        metrics.source = "procfs".to_string();
+
         if let Some(pid) = pid {
             // Read network stats from /proc/[pid]/net/dev
             let net_dev_path = Path::new(&self.proc_root)
@@ -267,6 +438,41 @@ impl MetricsCollector {
     }
 }
 
+/// CPU use between two (timestamp ms, usage_usec) samples, 100 = one full core.
+/// A counter that went backwards (cgroup recreated) or an empty window gives 0.
+pub fn cpu_percent(previous: (u64, u64), current: (u64, u64)) -> f64 {
+    let window_usec = current.0.saturating_sub(previous.0) * 1000;
+    if window_usec == 0 || current.1 < previous.1 {
+        return 0.0;
+    }
+    (current.1 - previous.1) as f64 / window_usec as f64 * 100.0
+}
+
+/// Memory use as a percentage of `limit_bytes`, or of `host_total_bytes` when there is no limit (0)
+pub fn memory_percent(current_bytes: u64, limit_bytes: u64, host_total_bytes: u64) -> f64 {
+    let capacity = if limit_bytes > 0 { limit_bytes } else { host_total_bytes };
+    if capacity == 0 {
+        return 0.0;
+    }
+    current_bytes as f64 / capacity as f64 * 100.0
+}
+
+/// Oldest stored sample, in seconds, a real-time GetMetrics may serve: the requested
+/// `max_age_seconds`, or the container's collection interval when that is 0. None (for a negative
+/// max age) means a sample is always collected.
+pub fn metrics_max_age(max_age_seconds: i32, interval_seconds: u64) -> Option<u64> {
+    match max_age_seconds {
+        age if age < 0 => None,
+        0 => Some(interval_seconds),
+        age => Some(age as u64),
+    }
+}
+
+/// Whether a sample taken at `sampled_at_ms` is at most `max_age_secs` old at `now_ms`
+pub fn sample_is_fresh(sampled_at_ms: u64, now_ms: u64, max_age_secs: u64) -> bool {
+    now_ms.saturating_sub(sampled_at_ms) <= max_age_secs * 1000
+}
+
 /// System-wide metrics for the Quilt runtime
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemMetrics {
@@ -279,6 +485,173 @@ pub struct SystemMetrics {
     pub cpu_count: u64,
     pub load_average: [f64; 3],
     pub uptime_seconds: u64,
+    /// Utilization of each CPU since the previous system sample (since boot for the first)
+    #[serde(default)]
+    pub cpu_percent: Vec<f64>,
+    /// Filled by caller with the directories quilt stores data in
+    #[serde(default)]
+    pub disk_usage: Vec<DirectoryUsage>,
+    /// Filled by caller with the container bridge
+    #[serde(default)]
+    pub bridge: Option<BridgeThroughput>,
+    #[serde(default)]
+    pub daemon: DaemonProcessStats,
+}
+
+/// Busy and total jiffies of one CPU from /proc/stat
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+/// Space on the filesystem holding one of quilt's data directories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectoryUsage {
+    pub name: String,
+    pub path: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl DirectoryUsage {
+    /// statvfs of `path`; fails when the directory does not exist (yet)
+    pub fn collect(name: &str, path: &str) -> Result<Self, String> {
+        let stat = nix::sys::statvfs::statvfs(path)
+            .map_err(|e| format!("Failed to stat filesystem of {}: {}", path, e))?;
+        let fragment = stat.fragment_size() as u64;
+        let total_bytes = stat.blocks() as u64 * fragment;
+        Ok(DirectoryUsage {
+            name: name.to_string(),
+            path: path.to_string(),
+            total_bytes,
+            used_bytes: total_bytes.saturating_sub(stat.blocks_free() as u64 * fragment),
+            available_bytes: stat.blocks_available() as u64 * fragment,
+        })
+    }
+}
+
+/// Traffic through the container bridge: counters since the bridge came up and rates since the
+/// previous system sample (0 for the first)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BridgeThroughput {
+    pub interface: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+}
+
+impl BridgeThroughput {
+    pub fn collect(interface: &str) -> Result<Self, String> {
+        let read_counter = |counter: &str| -> Result<u64, String> {
+            let path = format!("/sys/class/net/{}/statistics/{}", interface, counter);
+            fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))
+                .map(|value| value.trim().parse().unwrap_or(0))
+        };
+        let rx_bytes = read_counter("rx_bytes")?;
+        let tx_bytes = read_counter("tx_bytes")?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let previous = lock(&LAST_BRIDGE_SAMPLES).insert(interface.to_string(), (now, rx_bytes, tx_bytes));
+        let (rx_bytes_per_sec, tx_bytes_per_sec) = previous
+            .map(|(sampled_at, rx, tx)| (
+                bytes_per_sec((sampled_at, rx), (now, rx_bytes)),
+                bytes_per_sec((sampled_at, tx), (now, tx_bytes)),
+            ))
+            .unwrap_or((0.0, 0.0));
+        Ok(BridgeThroughput { interface: interface.to_string(), rx_bytes, tx_bytes, rx_bytes_per_sec, tx_bytes_per_sec })
+    }
+}
+
+/// Resource use of the daemon process itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonProcessStats {
+    pub pid: u32,
+    pub open_fds: u64,
+    /// Soft RLIMIT_NOFILE
+    pub fd_limit: u64,
+    pub threads: u64,
+    pub rss_bytes: u64,
+    /// Live direct children (runtime helpers, exec sessions, containers not yet reparented)
+    pub child_processes: u64,
+}
+
+impl DaemonProcessStats {
+    pub fn collect() -> Self {
+        let pid = std::process::id();
+        let open_fds = fs::read_dir("/proc/self/fd").map(|entries| entries.count() as u64).unwrap_or(0);
+        let fd_limit = nix::sys::resource::getrlimit(nix::sys::resource::Resource::RLIMIT_NOFILE)
+            .map(|(soft, _)| soft)
+            .unwrap_or(0);
+        let (threads, rss_bytes) = fs::read_to_string("/proc/self/status")
+            .map(|status| parse_process_status(&status))
+            .unwrap_or((0, 0));
+        let child_processes = fs::read_dir("/proc").map(|entries| {
+            entries.flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+                .filter_map(|entry| fs::read_to_string(entry.path().join("stat")).ok())
+                .filter(|stat| parent_pid(stat) == Some(pid))
+                .count() as u64
+        }).unwrap_or(0);
+
+        DaemonProcessStats { pid, open_fds, fd_limit, threads, rss_bytes, child_processes }
+    }
+}
+
+/// Per-CPU times from the `cpuN` lines of /proc/stat, in CPU order
+pub fn parse_cpu_times(proc_stat: &str) -> Vec<CpuTimes> {
+    proc_stat.lines()
+        .filter(|line| line.starts_with("cpu") && line.as_bytes().get(3).map_or(false, u8::is_ascii_digit))
+        .map(|line| {
+            let fields: Vec<u64> = line.split_whitespace().skip(1).map(|v| v.parse().unwrap_or(0)).collect();
+            // user nice system idle iowait irq softirq steal; guest time is already in user/nice
+            let total: u64 = fields.iter().take(8).sum();
+            let idle = fields.get(3).copied().unwrap_or(0) + fields.get(4).copied().unwrap_or(0);
+            CpuTimes { busy: total.saturating_sub(idle), total }
+        })
+        .collect()
+}
+
+/// Utilization of each CPU between two samples; CPUs missing from `previous` are measured since boot
+pub fn cpu_utilization(previous: &[CpuTimes], current: &[CpuTimes]) -> Vec<f64> {
+    current.iter().enumerate().map(|(i, now)| {
+        let before = previous.get(i).copied().filter(|before| before.total <= now.total).unwrap_or_default();
+        let total = now.total - before.total;
+        if total == 0 {
+            return 0.0;
+        }
+        now.busy.saturating_sub(before.busy) as f64 / total as f64 * 100.0
+    }).collect()
+}
+
+/// Rate between two (timestamp ms, counter) samples; 0 for an empty window or a reset counter
+pub fn bytes_per_sec(previous: (u64, u64), current: (u64, u64)) -> f64 {
+    let window_ms = current.0.saturating_sub(previous.0);
+    if window_ms == 0 || current.1 < previous.1 {
+        return 0.0;
+    }
+    (current.1 - previous.1) as f64 * 1000.0 / window_ms as f64
+}
+
+/// Threads and VmRSS (bytes) from /proc/<pid>/status
+fn parse_process_status(status: &str) -> (u64, u64) {
+    let field = |name: &str| status.lines()
+        .find_map(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(0);
+    (field("Threads:"), field("VmRSS:") * 1024)
+}
+
+/// Parent pid from /proc/<pid>/stat; the command name may contain spaces and parentheses
+fn parent_pid(stat: &str) -> Option<u32> {
+    stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse().ok()
 }
 
 impl SystemMetrics {
@@ -300,6 +673,15 @@ impl SystemMetrics {
         // Get uptime
         let uptime_seconds = Self::get_uptime()?;
 
+        // Per-CPU utilization over the window since the previous system sample
+        let cpu_times = fs::read_to_string("/proc/stat").map(|stat| parse_cpu_times(&stat)).unwrap_or_default();
+        let cpu_percent = {
+            let mut previous = lock(&LAST_HOST_CPU_TIMES);
+            let utilization = cpu_utilization(&previous, &cpu_times);
+            *previous = cpu_times;
+            utilization
+        };
+
         Ok(SystemMetrics {
             timestamp,
             containers_total: 0, // Will be filled by caller
@@ -310,6 +692,10 @@ impl SystemMetrics {
             cpu_count,
             load_average,
             uptime_seconds,
+            cpu_percent,
+            disk_usage: Vec::new(), // Will be filled by caller
+            bridge: None, // Will be filled by caller
+            daemon: DaemonProcessStats::collect(),
         })
     }
 
@@ -369,6 +755,91 @@ impl SystemMetrics {
     }
 }
 
+/// Connection tracking summary for one container, derived from nf_conntrack
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConntrackStats {
+    pub container_ip: String,
+    pub total_entries: u64,
+    pub tcp_entries: u64,
+    pub udp_entries: u64,
+    pub top_remote_endpoints: Vec<(String, u64)>,
+}
+
+impl ConntrackStats {
+    const CONNTRACK_PATH: &'static str = "/proc/net/nf_conntrack";
+    const PROTOCOLS: [&'static str; 7] = ["tcp", "udp", "icmp", "icmpv6", "sctp", "udplite", "gre"];
+
+    /// Read the host conntrack table once and summarize it for each container IP
+    pub fn collect_for_ips(ips: &[String], top_n: usize) -> Result<HashMap<String, ConntrackStats>, String> {
+        let table = match fs::read_to_string(Self::CONNTRACK_PATH) {
+            Ok(table) => table,
+            Err(_) => {
+                // nf_conntrack proc file is absent on some kernels; fall back to the conntrack tool
+                let output = std::process::Command::new("conntrack").arg("-L").output()
+                    .map_err(|e| format!("conntrack table unavailable: {}", e))?;
+                String::from_utf8_lossy(&output.stdout).to_string()
+            }
+        };
+        Ok(Self::parse_table(&table, ips, top_n))
+    }
+
+    /// Attribute each entry to a container when the container IP is either endpoint of the
+    /// original direction, or the reply source (inbound traffic DNAT'd via a published port)
+    fn parse_table(table: &str, ips: &[String], top_n: usize) -> HashMap<String, ConntrackStats> {
+        let mut endpoints: HashMap<&str, HashMap<String, u64>> = HashMap::new();
+        let mut stats: HashMap<String, ConntrackStats> = ips.iter()
+            .map(|ip| (ip.clone(), ConntrackStats { container_ip: ip.clone(), ..Default::default() }))
+            .collect();
+
+        for line in table.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let protocol = tokens.iter().find(|t| Self::PROTOCOLS.iter().any(|p| p == *t)).copied().unwrap_or("");
+            let field = |key: &str, nth: usize| tokens.iter()
+                .filter_map(|t| t.strip_prefix(key))
+                .nth(nth);
+
+            let (orig_src, orig_dst, orig_dport, reply_src) =
+                match (field("src=", 0), field("dst=", 0), field("dport=", 0), field("src=", 1)) {
+                    (Some(src), Some(dst), dport, reply_src) => (src, dst, dport, reply_src),
+                    _ => continue,
+                };
+
+            let (container_ip, remote) = if let Some(ip) = ips.iter().find(|ip| ip.as_str() == orig_src) {
+                let remote = match orig_dport {
+                    Some(port) => format!("{}:{}", orig_dst, port),
+                    None => orig_dst.to_string(),
+                };
+                (ip, remote)
+            } else if let Some(ip) = ips.iter().find(|ip| ip.as_str() == orig_dst || Some(ip.as_str()) == reply_src) {
+                (ip, orig_src.to_string())
+            } else {
+                continue;
+            };
+
+            if let Some(entry) = stats.get_mut(container_ip) {
+                entry.total_entries += 1;
+                match protocol {
+                    "tcp" => entry.tcp_entries += 1,
+                    "udp" => entry.udp_entries += 1,
+                    _ => {}
+                }
+            }
+            *endpoints.entry(container_ip.as_str()).or_default().entry(remote).or_insert(0) += 1;
+        }
+
+        for (ip, counts) in endpoints {
+            let mut top: Vec<(String, u64)> = counts.into_iter().collect();
+            top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top.truncate(top_n);
+            if let Some(entry) = stats.get_mut(ip) {
+                entry.top_remote_endpoints = top;
+            }
+        }
+
+        stats
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,4 +852,90 @@ mod tests {
         assert!(m.cpu_count > 0);
         assert!(m.memory_total_mb > 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_host_cpu_and_process_parsing() {
+        let stat = "cpu  300 0 100 600 0 0 0 0 0 0\n\
+cpu0 200 0 50 250 0 0 0 0 0 0\n\
+cpu1 100 0 50 300 50 0 0 0 0 0\n\
+intr 12345\n";
+        let times = parse_cpu_times(stat);
+        assert_eq!(times, vec![CpuTimes { busy: 250, total: 500 }, CpuTimes { busy: 150, total: 500 }]);
+        assert_eq!(cpu_utilization(&[], &times), vec![50.0, 30.0]);
+
+        let later = vec![CpuTimes { busy: 350, total: 600 }, CpuTimes { busy: 150, total: 600 }];
+        assert_eq!(cpu_utilization(&times, &later), vec![100.0, 0.0]);
+        assert_eq!(bytes_per_sec((1_000, 100), (3_000, 2_100)), 1000.0);
+        assert_eq!(bytes_per_sec((1_000, 100), (3_000, 50)), 0.0);
+
+        assert_eq!(parse_process_status("Name:\tquilt\nVmRSS:\t  2048 kB\nThreads:\t12\n"), (12, 2048 * 1024));
+        assert_eq!(parent_pid("4242 (weird) name) S 17 4242 4242 0"), Some(17));
+    }
+
+    #[test]
+    fn test_usage_percentages() {
+        // 500ms of CPU over a 1s window is half a core; two cores busy is 200%
+        assert_eq!(cpu_percent((1_000, 10_000), (2_000, 510_000)), 50.0);
+        assert_eq!(cpu_percent((1_000, 0), (2_000, 2_000_000)), 200.0);
+        assert_eq!(cpu_percent((1_000, 500), (1_000, 900)), 0.0);
+        assert_eq!(cpu_percent((1_000, 900), (2_000, 100)), 0.0);
+
+        assert_eq!(memory_percent(256, 1024, 4096), 25.0);
+        assert_eq!(memory_percent(1024, 0, 4096), 25.0);
+        assert_eq!(memory_percent(1024, 0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_sample_freshness() {
+        assert_eq!(metrics_max_age(0, 5), Some(5));
+        assert_eq!(metrics_max_age(120, 5), Some(120));
+        assert_eq!(metrics_max_age(-1, 5), None);
+
+        // Timestamps are milliseconds; a sample from the future (clock step) counts as fresh
+        assert!(sample_is_fresh(100_000, 130_000, 30));
+        assert!(!sample_is_fresh(100_000, 130_001, 30));
+        assert!(sample_is_fresh(100_000, 90_000, 30));
+    }
+
+    #[test]
+    fn test_metrics_collection_policy() {
+        let policy = MetricsCollectionPolicy::parse("interval=15,jitter=20%").unwrap();
+        assert_eq!((policy.interval, policy.jitter_percent), (Duration::from_secs(15), 20));
+        assert_eq!(MetricsCollectionPolicy::parse("on"), Some(MetricsCollectionPolicy::default()));
+        assert_eq!(MetricsCollectionPolicy::parse("interval=15,off"), None);
+        // Out-of-range values keep the default
+        let policy = MetricsCollectionPolicy::parse("interval=0,jitter=80").unwrap();
+        assert_eq!(policy, MetricsCollectionPolicy::default());
+
+        assert_eq!(policy.interval_for(Some(5)), Duration::from_secs(5));
+        assert_eq!(policy.interval_for(None), Duration::from_secs(DEFAULT_METRICS_INTERVAL_SECS as u64));
+        // 10% jitter keeps a 30s interval within 27-33s
+        assert_eq!(policy.jittered(Duration::from_secs(30), 1.0), Duration::from_secs(33));
+        assert_eq!(policy.jittered(Duration::from_secs(30), -5.0), Duration::from_secs(27));
+        assert_eq!(policy.jittered(Duration::from_secs(30), 0.0), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_conntrack_parsing() {
+        let table = "\
+ipv4     2 tcp      6 431999 ESTABLISHED src=10.42.0.5 dst=93.184.216.34 sport=40000 dport=443 src=93.184.216.34 dst=192.168.1.10 sport=443 dport=40000 [ASSURED] mark=0 use=2
+ipv4     2 tcp      6 431999 ESTABLISHED src=10.42.0.5 dst=93.184.216.34 sport=40001 dport=443 src=93.184.216.34 dst=192.168.1.10 sport=443 dport=40001 [ASSURED] mark=0 use=2
+ipv4     2 udp      17 29 src=10.42.0.5 dst=10.42.0.1 sport=5000 dport=53 src=10.42.0.1 dst=10.42.0.5 sport=53 dport=5000 mark=0 use=2
+ipv4     2 tcp      6 100 ESTABLISHED src=203.0.113.7 dst=192.168.1.10 sport=50000 dport=8080 src=10.42.0.6 dst=203.0.113.7 sport=80 dport=50000 [ASSURED] mark=0 use=2
+ipv4     2 tcp      6 100 ESTABLISHED src=192.168.1.2 dst=192.168.1.3 sport=1 dport=2 src=192.168.1.3 dst=192.168.1.2 sport=2 dport=1 mark=0 use=2
+";
+        let ips = vec!["10.42.0.5".to_string(), "10.42.0.6".to_string()];
+        let stats = ConntrackStats::parse_table(table, &ips, 1);
+
+        let web = &stats["10.42.0.5"];
+        assert_eq!(web.total_entries, 3);
+        assert_eq!(web.tcp_entries, 2);
+        assert_eq!(web.udp_entries, 1);
+        assert_eq!(web.top_remote_endpoints, vec![("93.184.216.34:443".to_string(), 2)]);
+
+        // Inbound connection through a published port is attributed via the reply source
+        let published = &stats["10.42.0.6"];
+        assert_eq!(published.total_entries, 1);
+        assert_eq!(published.top_remote_endpoints[0].0, "203.0.113.7");
+    }
+}
@@ -1,12 +1,44 @@
 // Daemon modules
 pub mod runtime;
 pub mod cgroup;
+pub mod exec_cgroup;
 pub mod namespace;
 pub mod readiness;
+pub mod readiness_gates;
 pub mod system;
 pub mod manager;
 pub mod resource;
 pub mod metrics;
+pub mod paths;
+pub mod docker_archive;
+pub mod image_scan;
+pub mod rootfs_pool;
+pub mod host_check;
+pub mod hardening;
+pub mod bpf;
+pub mod net_accounting;
+pub mod net_policy;
+pub mod device_policy;
+pub mod memory_pressure;
+pub mod hooks;
+pub mod prestart;
+pub mod pseudo_fs;
+pub mod priority;
+pub mod output;
+pub mod syscall_table;
+pub mod syscall_trace;
+pub mod setup_report;
+pub mod time_offset;
+pub mod timezone;
+pub mod idmap;
+pub mod workdir;
+pub mod image_config;
+pub mod platform;
+pub mod registry;
+pub mod images;
+pub mod image_export;
+pub mod overlay;
+pub mod build;
 
 // Re-export commonly used types
 pub use runtime::{ContainerConfig, MountConfig, MountType};
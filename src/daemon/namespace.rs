@@ -3,6 +3,9 @@ use nix::unistd::Pid;
 use nix::mount::{mount, MsFlags};
 use nix::sys::wait::{waitpid, WaitStatus, WaitPidFlag};
 use std::path::Path;
+use std::process::Command;
+use crate::daemon::exec_cgroup::workload_path;
+use crate::daemon::pseudo_fs;
 use crate::utils::console::ConsoleLogger;
 use crate::utils::process::ProcessUtils;
 use crate::utils::command::CommandExecutor;
@@ -14,6 +17,14 @@ pub struct NamespaceConfig {
     pub uts: bool,      // CLONE_NEWUTS - Hostname/domain isolation
     pub ipc: bool,      // CLONE_NEWIPC - IPC isolation
     pub network: bool,  // CLONE_NEWNET - Network isolation
+    pub time: bool,     // CLONE_NEWTIME - Clock offsets (unshared by the child, not passed to clone)
+    pub cgroup: bool,   // CLONE_NEWCGROUP - Own cgroup subtree only (unshared by the child once it is in its cgroup)
+    pub user: bool,     // CLONE_NEWUSER - Container root is an unprivileged host id range (see `idmap`)
+    /// Seconds added to CLOCK_MONOTONIC and CLOCK_BOOTTIME inside the time namespace
+    pub monotonic_offset: i64,
+    pub boottime_offset: i64,
+    /// Join this existing network namespace (e.g. /var/run/netns/<name>) instead of creating one
+    pub join_network_namespace: Option<String>,
 }
 
 impl Default for NamespaceConfig {
@@ -24,12 +35,82 @@ impl Default for NamespaceConfig {
             uts: false,     // UTS can cause issues in some environments
             ipc: false,     // IPC namespace disabled for compatibility
             network: true,  // Enable network namespace for ICC
+            time: false,    // Clocks follow the host unless offsets are asked for
+            cgroup: false,  // Opt-in, needs cgroup v2
+            user: false,    // Opt-in, needs ID-mapped mount support for the rootfs
+            monotonic_offset: 0,
+            boottime_offset: 0,
+            join_network_namespace: None,
         }
     }
 }
 
 pub struct NamespaceManager;
 
+/// Descriptor number a mount target is passed to nsenter under
+const TARGET_FD: nix::libc::c_int = 3;
+
+/// How a command run with the target descriptor refers to it
+fn target_descriptor_path() -> String {
+    format!("/proc/self/fd/{}", TARGET_FD)
+}
+
+/// One nsenter invocation of a mount attach, with the one that reverts it if a later step fails
+struct AttachStep {
+    args: Vec<String>,
+    undo: Option<Vec<String>>,
+}
+
+/// Open the directory `target` names below `root`, creating missing directories along the way.
+/// Every component is opened with O_NOFOLLOW, so a symlink anywhere on the path is refused
+/// rather than followed out of `root`; `root` itself (e.g. `/proc/<pid>/root`) is followed.
+pub fn open_directory_in_root(root: &Path, target: &str) -> Result<std::os::fd::OwnedFd, String> {
+    use nix::fcntl::{openat, OFlag};
+    use nix::sys::stat::{mkdirat, Mode};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    let flags = OFlag::O_PATH | OFlag::O_DIRECTORY | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC;
+    let open = |dir: nix::libc::c_int, name: &str| -> nix::Result<OwnedFd> {
+        openat(dir, name, flags, Mode::empty()).map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+    };
+
+    let mut current = openat(nix::libc::AT_FDCWD, root, flags - OFlag::O_NOFOLLOW, Mode::empty())
+        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+        .map_err(|e| format!("Failed to open {}: {}", root.display(), e))?;
+
+    for component in target.split('/').filter(|component| !component.is_empty() && *component != ".") {
+        // `..` from the root would step out into the host's directory above it
+        if component == ".." {
+            return Err(format!("Target '{}' must not contain '..'", target));
+        }
+        let next = match open(current.as_raw_fd(), component) {
+            Err(nix::errno::Errno::ENOENT) => {
+                mkdirat(current.as_raw_fd(), component, Mode::from_bits_truncate(0o755))
+                    .map_err(|e| format!("Failed to create '{}' in target '{}': {}", component, target, e))?;
+                open(current.as_raw_fd(), component)
+            }
+            other => other,
+        };
+        current = next.map_err(|e| match e {
+            nix::errno::Errno::ELOOP | nix::errno::Errno::ENOTDIR => {
+                format!("Target '{}' is not a directory ('{}' is a symlink or file)", target, component)
+            }
+            e => format!("Failed to open '{}' in target '{}': {}", component, target, e),
+        })?;
+    }
+
+    Ok(current)
+}
+
+/// Whether `pid` runs in a different `namespace` (as named under /proc/<pid>/ns) than the daemon.
+/// Namespaces a container only sometimes has are joined by exec only when this holds.
+pub fn has_own_namespace(pid: i64, namespace: &str) -> bool {
+    match (std::fs::read_link(format!("/proc/{}/ns/{}", pid, namespace)), std::fs::read_link(format!("/proc/self/ns/{}", namespace))) {
+        (Ok(container), Ok(daemon)) => container != daemon,
+        _ => false,
+    }
+}
+
 impl NamespaceManager {
     pub fn new() -> Self {
         NamespaceManager
@@ -48,14 +129,16 @@ impl NamespaceManager {
         
         ConsoleLogger::namespace_created(&format!("{:?}", clone_flags));
 
+        let join_netns = if config.network { config.join_network_namespace.clone() } else { None };
+
         // If no namespaces are requested, just use regular fork
-        if clone_flags.is_empty() {
+        if clone_flags.is_empty() && join_netns.is_none() {
             return self.create_simple_process(child_func);
         }
 
         // Try to create namespaces with unshare + fork approach
         // If that fails, fall back to simple fork
-        match self.try_create_with_namespaces(clone_flags, child_func) {
+        match self.try_create_with_namespaces(clone_flags, join_netns, child_func) {
             Ok(pid) => {
                 ConsoleLogger::success(&format!("Successfully created namespaced process with PID: {}", ProcessUtils::pid_to_i32(pid)));
                 Ok(pid)
@@ -75,6 +158,7 @@ impl NamespaceManager {
     fn try_create_with_namespaces<F>(
         &self,
         clone_flags: CloneFlags,
+        join_netns: Option<String>,
         child_func: F,
     ) -> Result<Pid, String>
     where
@@ -91,6 +175,12 @@ impl NamespaceManager {
             Ok(nix::unistd::ForkResult::Child) => {
                 // This runs in the child process - now create namespaces
                 // This approach ensures the server process is never affected
+                if let Some(netns_path) = &join_netns {
+                    if let Err(e) = Self::join_network_namespace(netns_path) {
+                        ConsoleLogger::error(&format!("Failed to join network namespace {}: {}", netns_path, e));
+                        std::process::exit(1);
+                    }
+                }
                 if let Err(e) = nix::sched::unshare(clone_flags) {
                     ConsoleLogger::error(&format!("Failed to unshare namespaces in child: {}", e));
                     std::process::exit(1);
@@ -145,10 +235,22 @@ impl NamespaceManager {
         }
     }
 
-    /// Build clone flags based on namespace configuration
+    /// Move the calling process into an existing network namespace
+    fn join_network_namespace(netns_path: &str) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+        let netns = std::fs::File::open(netns_path).map_err(|e| e.to_string())?;
+        nix::sched::setns(netns.as_raw_fd(), CloneFlags::CLONE_NEWNET).map_err(|e| e.to_string())
+    }
+
+    /// Build clone flags based on namespace configuration. Time and cgroup namespaces are not
+    /// among them: the child creates those itself, see `setup_cgroup_namespace`
     fn build_clone_flags(&self, config: &NamespaceConfig) -> CloneFlags {
         let mut flags = CloneFlags::empty();
 
+        // Unshared together with the rest, the user namespace comes first and owns the others
+        if config.user {
+            flags |= CloneFlags::CLONE_NEWUSER;
+        }
         if config.pid {
             flags |= CloneFlags::CLONE_NEWPID;
         }
@@ -161,15 +263,17 @@ impl NamespaceManager {
         if config.ipc {
             flags |= CloneFlags::CLONE_NEWIPC;
         }
-        if config.network {
+        // A joined namespace replaces a fresh one
+        if config.network && config.join_network_namespace.is_none() {
             flags |= CloneFlags::CLONE_NEWNET;
         }
 
         flags
     }
 
-    /// Setup the mount namespace for a container
-    pub fn setup_mount_namespace(&self, rootfs_path: &str) -> Result<(), String> {
+    /// Setup the mount namespace for a container. /proc and /sys are hardened unless `privileged`
+    /// (see `pseudo_fs`); a /proc that cannot be hardened fails the setup.
+    pub fn setup_mount_namespace(&self, rootfs_path: &str, privileged: bool) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Setting up mount namespace for rootfs: {}", rootfs_path));
 
         // Make the mount namespace private to prevent propagation to host
@@ -196,38 +300,14 @@ impl NamespaceManager {
             // Continue anyway - this might fail in restricted environments
         }
 
-        // Mount /proc inside the new namespace
-        let proc_path = format!("{}/proc", rootfs_path);
-        if Path::new(&proc_path).exists() {
-            if let Err(e) = mount(
-                Some("proc"),
-                proc_path.as_str(),
-                Some("proc"),
-                MsFlags::empty(),
-                None::<&str>,
-            ) {
-                // Non-fatal error - log and continue
-                ConsoleLogger::warning(&format!("Failed to mount /proc in container: {}", e));
-            } else {
-                ConsoleLogger::success("Successfully mounted /proc in container");
-            }
+        // Fresh /proc and /sys, whatever the image has in those directories
+        pseudo_fs::mount_proc(rootfs_path, privileged)?;
+        if let Err(e) = pseudo_fs::mount_sys(rootfs_path, privileged) {
+            // Non-fatal error - log and continue
+            ConsoleLogger::warning(&e);
         }
-
-        // Mount /sys inside the new namespace
-        let sys_path = format!("{}/sys", rootfs_path);
-        if Path::new(&sys_path).exists() {
-            if let Err(e) = mount(
-                Some("sysfs"),
-                sys_path.as_str(),
-                Some("sysfs"),
-                MsFlags::MS_RDONLY | MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC | MsFlags::MS_NODEV,
-                None::<&str>,
-            ) {
-                // Non-fatal error - log and continue
-                ConsoleLogger::warning(&format!("Failed to mount /sys in container: {}", e));
-            } else {
-                ConsoleLogger::success("Successfully mounted /sys in container");
-            }
+        if privileged {
+            pseudo_fs::mount_host_dev(rootfs_path)?;
         }
 
         // Mount /dev/pts for pseudo-terminals if it exists
@@ -250,6 +330,39 @@ impl NamespaceManager {
         Ok(())
     }
     
+    /// Move the calling process into its container's cgroup, make that cgroup the root of a new
+    /// cgroup namespace and mount it at /sys/fs/cgroup in the rootfs. Must run after
+    /// `setup_mount_namespace` and before chroot. Requires cgroup v2: a v1 hierarchy cannot be
+    /// mounted per namespace this way.
+    pub fn setup_cgroup_namespace(&self, container_id: &str, rootfs_path: &str) -> Result<(), String> {
+        let cgroup_root = Path::new("/sys/fs/cgroup");
+        if !cgroup_root.join("cgroup.controllers").exists() {
+            return Err("Cgroup namespace isolation requires cgroup v2".to_string());
+        }
+        // Joined here rather than by the parent, so the namespace is rooted at the container's cgroup
+        let container_cgroup = workload_path(&cgroup_root.join("quilt").join(container_id));
+        std::fs::write(container_cgroup.join("cgroup.procs"), "0")
+            .map_err(|e| format!("Failed to join cgroup {}: {}", container_cgroup.display(), e))?;
+        nix::sched::unshare(CloneFlags::CLONE_NEWCGROUP)
+            .map_err(|e| format!("Failed to create cgroup namespace: {}", e))?;
+
+        let target = format!("{}/sys/fs/cgroup", rootfs_path);
+        if !Path::new(&target).exists() {
+            // Without a /sys mount the directory has to come from the image
+            std::fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create {}: {}", target, e))?;
+        }
+        mount(
+            Some("cgroup2"),
+            target.as_str(),
+            Some("cgroup2"),
+            MsFlags::MS_RDONLY | MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC | MsFlags::MS_NODEV,
+            None::<&str>,
+        ).map_err(|e| format!("Failed to mount cgroup2 at {}: {}", target, e))?;
+        ConsoleLogger::success("Successfully mounted the container's cgroup at /sys/fs/cgroup");
+        Ok(())
+    }
+
     /// Setup container mounts (bind mounts, volumes, tmpfs)
     pub fn setup_container_mounts(&self, rootfs_path: &str, mounts: &[crate::daemon::MountConfig]) -> Result<(), String> {
         use crate::daemon::MountType;
@@ -365,6 +478,192 @@ impl NamespaceManager {
         Ok(())
     }
 
+    /// Attach a mount to an already running container by entering its mount namespace.
+    /// The container is chrooted (not pivoted), so host paths stay reachable from inside
+    /// its mount namespace. The target is resolved inside the container root and handed to
+    /// `mount` as an open descriptor, so a symlink planted by the container cannot redirect
+    /// the mount onto a host path.
+    pub fn attach_mount_to_process(&self, pid: Pid, mount_config: &crate::daemon::MountConfig) -> Result<(), String> {
+        use std::os::fd::AsRawFd;
+
+        ConsoleLogger::debug(&format!("Attaching mount {} -> {} to process {}",
+            mount_config.source, mount_config.target, ProcessUtils::pid_to_i32(pid)));
+
+        if mount_config.mount_type != crate::daemon::MountType::Tmpfs && !Path::new(&mount_config.source).exists() {
+            return Err(format!("Mount source '{}' does not exist", mount_config.source));
+        }
+
+        let target = open_directory_in_root(&Self::process_root(pid), &mount_config.target)?;
+
+        let mut undo = Vec::new();
+        for step in Self::build_attach_mount_commands(pid, mount_config) {
+            if let Err(e) = Self::run_nsenter_with_target(&step.args, target.as_raw_fd()) {
+                // Unmount what the earlier steps mounted, latest first
+                for args in undo.iter().rev() {
+                    if let Err(undo_error) = Self::run_nsenter_with_target(args, target.as_raw_fd()) {
+                        ConsoleLogger::warning(&format!("Failed to undo a partial mount attach: {}", undo_error));
+                    }
+                }
+                return Err(e);
+            }
+            undo.extend(step.undo);
+        }
+
+        ConsoleLogger::success(&format!("Attached {} to {} in process {}",
+            mount_config.source, mount_config.target, ProcessUtils::pid_to_i32(pid)));
+        Ok(())
+    }
+
+    /// The root directory of process `pid`, through which its view of the filesystem is reached
+    fn process_root(pid: Pid) -> std::path::PathBuf {
+        std::path::PathBuf::from(format!("/proc/{}/root", ProcessUtils::pid_to_i32(pid)))
+    }
+
+    /// Run nsenter with `args`, passing `target_fd` on as fd 3 of nsenter and the command it runs
+    fn run_nsenter_with_target(args: &[String], target_fd: std::os::fd::RawFd) -> Result<(), String> {
+        use std::os::unix::process::CommandExt;
+
+        let mut command = Command::new("nsenter");
+        command.args(args);
+        unsafe {
+            command.pre_exec(move || {
+                if target_fd == TARGET_FD {
+                    if nix::libc::fcntl(target_fd, nix::libc::F_SETFD, 0) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                } else if nix::libc::dup2(target_fd, TARGET_FD) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let output = command
+            .output()
+            .map_err(|e| format!("Failed to execute nsenter: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!("nsenter {} failed: {}", args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(())
+    }
+
+    /// Build the nsenter invocations needed to attach a mount inside a process' mount namespace.
+    /// The target is the descriptor `open_directory_in_root` returned, passed as fd 3; `mount` is
+    /// told not to canonicalize it so the kernel resolves the descriptor itself.
+    fn build_attach_mount_commands(pid: Pid, mount_config: &crate::daemon::MountConfig) -> Vec<AttachStep> {
+        use crate::daemon::MountType;
+
+        let descriptor_path = target_descriptor_path();
+        let target_path = descriptor_path.as_str();
+        let nsenter = |cmd: Vec<&str>| -> Vec<String> {
+            let mut args = vec!["-t".to_string(), ProcessUtils::pid_to_i32(pid).to_string(), "-m".to_string(), "--".to_string()];
+            args.extend(cmd.into_iter().map(str::to_string));
+            args
+        };
+        let step = |cmd: Vec<&str>| AttachStep { args: nsenter(cmd), undo: None };
+
+        let mut commands = Vec::new();
+
+        match mount_config.mount_type {
+            MountType::Bind | MountType::Volume => {
+                let source = mount_config.source.as_str();
+                if mount_config.readonly {
+                    // A remount through the descriptor would reach the directory under the new
+                    // mount, so the source is made read-only first (in the container's mount
+                    // namespace only) and the bind inherits that. The self-bind is undone when
+                    // a later step fails.
+                    commands.push(AttachStep {
+                        args: nsenter(vec!["mount", "--bind", source, source]),
+                        undo: Some(nsenter(vec!["umount", source])),
+                    });
+                    commands.push(step(vec!["mount", "-o", "remount,bind,ro", source]));
+                    commands.push(step(vec!["mount", "--no-canonicalize", "--bind", source, target_path]));
+                    commands.push(step(vec!["umount", source]));
+                } else {
+                    commands.push(step(vec!["mount", "--no-canonicalize", "--bind", source, target_path]));
+                }
+            }
+            MountType::Tmpfs => {
+                let mut mount_opts = vec![format!("size={}",
+                    mount_config.options.get("size").map(|s| s.as_str()).unwrap_or("64m"))];
+                if let Some(mode) = mount_config.options.get("mode") {
+                    mount_opts.push(format!("mode={}", mode));
+                }
+                mount_opts.push("nosuid".to_string());
+                mount_opts.push("nodev".to_string());
+                if mount_config.readonly {
+                    mount_opts.push("ro".to_string());
+                }
+                let mount_opts = mount_opts.join(",");
+                commands.push(step(vec![
+                    "mount", "--no-canonicalize", "-t", "tmpfs", "-o", mount_opts.as_str(), "tmpfs", target_path,
+                ]));
+            }
+        }
+
+        commands
+    }
+
+    /// Expose the host's /dev/fuse inside the container rootfs.
+    /// Runs in the child before chroot. The node is bind-mounted rather than created with
+    /// mknod so it works without CAP_MKNOD. FUSE mount(2) inside the container needs
+    /// CAP_SYS_ADMIN, which the immutable profile drops, so create rejects combining the two.
+    pub fn setup_fuse_device(&self, rootfs_path: &str) -> Result<(), String> {
+        if !Path::new("/dev/fuse").exists() {
+            return Err("Host has no /dev/fuse (is the fuse module loaded?)".to_string());
+        }
+
+        let dev_dir = format!("{}/dev", rootfs_path);
+        crate::utils::filesystem::FileSystemUtils::create_dir_all_with_logging(&dev_dir, "container /dev")
+            .map_err(|e| format!("Failed to create {}: {}", dev_dir, e))?;
+
+        let fuse_path = format!("{}/fuse", dev_dir);
+        if !Path::new(&fuse_path).exists() {
+            std::fs::File::create(&fuse_path)
+                .map_err(|e| format!("Failed to create FUSE device placeholder {}: {}", fuse_path, e))?;
+        }
+
+        mount(
+            Some("/dev/fuse"),
+            fuse_path.as_str(),
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        ).map_err(|e| format!("Failed to bind mount /dev/fuse into container: {}", e))?;
+
+        ConsoleLogger::success("Exposed /dev/fuse in container");
+        Ok(())
+    }
+
+    /// Run a host FUSE helper inside a running container's mount namespace so the
+    /// filesystem appears at the target path within the container rootfs. The target is
+    /// resolved the same way as for attached mounts and handed to the helper as fd 3.
+    pub fn mount_fuse_in_process(&self, pid: Pid, fuse_mount: &crate::utils::validation::FuseMount) -> Result<(), String> {
+        use std::os::fd::AsRawFd;
+
+        ConsoleLogger::debug(&format!("Mounting FUSE {} ({}) at {} in process {}",
+            fuse_mount.source, fuse_mount.helper, fuse_mount.target, ProcessUtils::pid_to_i32(pid)));
+
+        let target = open_directory_in_root(&Self::process_root(pid), &fuse_mount.target)?;
+        Self::run_nsenter_with_target(&Self::build_fuse_mount_command(pid, fuse_mount), target.as_raw_fd())?;
+
+        ConsoleLogger::success(&format!("Mounted FUSE {} at {} in process {}",
+            fuse_mount.source, fuse_mount.target, ProcessUtils::pid_to_i32(pid)));
+        Ok(())
+    }
+
+    /// Build the nsenter argument list that invokes the FUSE helper on the target descriptor
+    fn build_fuse_mount_command(pid: Pid, fuse_mount: &crate::utils::validation::FuseMount) -> Vec<String> {
+        let mut args = vec!["-t".to_string(), ProcessUtils::pid_to_i32(pid).to_string(), "-m".to_string(), "--".to_string()];
+        args.extend([fuse_mount.helper.clone(), fuse_mount.source.clone(), target_descriptor_path()]);
+        if !fuse_mount.options.is_empty() {
+            args.push("-o".to_string());
+            args.push(fuse_mount.options.join(","));
+        }
+        args
+    }
+
     /// Setup basic loopback networking in the network namespace
     pub fn setup_network_namespace(&self) -> Result<(), String> {
         ConsoleLogger::debug("Setting up basic loopback networking");
@@ -372,7 +671,7 @@ impl NamespaceManager {
         // Bring up the loopback interface
         // This is a simplified implementation - in production you'd want to use netlink
         // For now, we'll use the `ip` command if available
-        match CommandExecutor::execute_shell("ip link set lo up")
+        match CommandExecutor::run("ip", ["link", "set", "lo", "up"])
         {
             Ok(output) => {
                 if output.success {
@@ -461,6 +760,8 @@ mod tests {
         assert!(!config.uts);     // Updated to match actual default
         assert!(!config.ipc);     // Updated to match actual default
         assert!(config.network); // Updated to match actual default
+        assert!(!config.time);
+        assert!(!config.cgroup);
     }
 
     #[test]
@@ -496,4 +797,43 @@ mod tests {
         assert!(!flags.contains(CloneFlags::CLONE_NEWIPC));
         assert!(!flags.contains(CloneFlags::CLONE_NEWNET));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_attach_mount_commands() {
+        let mount_config = crate::daemon::MountConfig {
+            source: "/srv/data".to_string(),
+            target: "/data".to_string(),
+            mount_type: crate::daemon::MountType::Bind,
+            readonly: true,
+            options: std::collections::HashMap::new(),
+        };
+
+        let commands = NamespaceManager::build_attach_mount_commands(Pid::from_raw(42), &mount_config);
+
+        assert_eq!(commands.len(), 4);
+        assert_eq!(&commands[0].args[..4], &["-t", "42", "-m", "--"]);
+        assert_eq!(&commands[0].undo.as_ref().unwrap()[4..], &["umount", "/srv/data"]);
+        assert_eq!(&commands[1].args[4..], &["mount", "-o", "remount,bind,ro", "/srv/data"]);
+        assert_eq!(&commands[2].args[4..], &["mount", "--no-canonicalize", "--bind", "/srv/data", "/proc/self/fd/3"]);
+        assert_eq!(&commands[3].args[4..], &["umount", "/srv/data"]);
+        assert!(commands[1..].iter().all(|step| step.undo.is_none()));
+
+        let writable = crate::daemon::MountConfig { readonly: false, ..mount_config };
+        assert_eq!(NamespaceManager::build_attach_mount_commands(Pid::from_raw(42), &writable).len(), 1);
+    }
+
+    #[test]
+    fn test_fuse_mount_commands() {
+        let fuse_mount = crate::utils::validation::FuseMount {
+            helper: "sshfs".to_string(),
+            source: "user@host:/srv".to_string(),
+            target: "/mnt/remote".to_string(),
+            options: vec!["allow_other".to_string(), "reconnect".to_string()],
+        };
+
+        let command = NamespaceManager::build_fuse_mount_command(Pid::from_raw(7), &fuse_mount);
+
+        assert_eq!(&command[..4], &["-t", "7", "-m", "--"]);
+        assert_eq!(&command[4..], &["sshfs", "user@host:/srv", "/proc/self/fd/3", "-o", "allow_other,reconnect"]);
+    }
+}
@@ -0,0 +1,250 @@
+// eBPF network accounting
+// Optional per-container byte/packet counters kept by cgroup_skb programs attached to the
+// container's cgroup v2 directory, plus per-remote-address flow counters. Reading two BPF maps
+// replaces parsing /proc/<pid>/net/dev. The programs are assembled here and loaded through the raw
+// bpf(2) syscall, so there is no toolchain or object file to ship; when loading fails (old kernel,
+// cgroup v1, missing CAP_BPF) the container simply keeps the procfs path.
+
+use crate::daemon::bpf::*;
+use crate::utils::console::ConsoleLogger;
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Set to `1`/`true`/`on` to attach eBPF accounting to new containers
+pub const NET_ACCOUNTING_ENV: &str = "QUILT_EBPF_ACCOUNTING";
+
+/// Distinct (remote address, direction) pairs tracked per container; later flows are not recorded
+const MAX_FLOWS: u32 = 1024;
+
+/// Index into the totals map, also stored in flow keys
+const DIRECTION_INGRESS: u32 = 0;
+const DIRECTION_EGRESS: u32 = 1;
+
+static ACCOUNTING: once_cell::sync::Lazy<Mutex<HashMap<String, CgroupAccounting>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether new containers should get eBPF accounting
+pub fn enabled_from_env() -> bool {
+    std::env::var(NET_ACCOUNTING_ENV)
+        .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "on" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Totals seen by the cgroup programs, from the container's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrafficCounters {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+}
+
+/// Traffic between the container and one remote IPv4 address in one direction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowCounters {
+    pub remote_address: Ipv4Addr,
+    pub egress: bool,
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// Maps and programs owned by one container; dropping it closes every descriptor
+struct CgroupAccounting {
+    cgroup_dir: String,
+    totals_map: OwnedFd,
+    flows_map: OwnedFd,
+    ingress_prog: OwnedFd,
+    egress_prog: OwnedFd,
+}
+
+/// Attach accounting to the container's cgroup v2 directory. Counting starts now, so containers
+/// attached after a daemon restart report traffic since the restart.
+pub fn attach(container_id: &str) -> Result<(), String> {
+    let cgroup_dir = format!("/sys/fs/cgroup/quilt/{}", container_id);
+    if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        return Err("eBPF accounting needs the unified cgroup v2 hierarchy".to_string());
+    }
+
+    let totals_map = create_map(BPF_MAP_TYPE_ARRAY, 4, 16, 2, 0)?;
+    let flows_map = create_map(BPF_MAP_TYPE_HASH, 8, 16, MAX_FLOWS, 0)?;
+    let ingress_prog = load_cgroup_skb_program(&accounting_program(DIRECTION_INGRESS, totals_map.as_raw_fd(), flows_map.as_raw_fd()))?;
+    let egress_prog = load_cgroup_skb_program(&accounting_program(DIRECTION_EGRESS, totals_map.as_raw_fd(), flows_map.as_raw_fd()))?;
+
+    let cgroup = fs::File::open(&cgroup_dir)
+        .map_err(|e| format!("Failed to open cgroup {}: {}", cgroup_dir, e))?;
+    attach_to_cgroup(&cgroup, &ingress_prog, CgroupHook::Ingress)?;
+    if let Err(e) = attach_to_cgroup(&cgroup, &egress_prog, CgroupHook::Egress) {
+        let _ = detach_from_cgroup(&cgroup, &ingress_prog, CgroupHook::Ingress);
+        return Err(e);
+    }
+
+    ConsoleLogger::debug(&format!("📊 [NET-ACCT] eBPF accounting attached to {}", cgroup_dir));
+    ACCOUNTING.lock().unwrap().insert(container_id.to_string(), CgroupAccounting {
+        cgroup_dir,
+        totals_map,
+        flows_map,
+        ingress_prog,
+        egress_prog,
+    });
+    Ok(())
+}
+
+/// Detach the programs (if the cgroup still exists) and release the maps
+pub fn detach(container_id: &str) {
+    let Some(accounting) = ACCOUNTING.lock().unwrap().remove(container_id) else { return };
+    if let Ok(cgroup) = fs::File::open(&accounting.cgroup_dir) {
+        let _ = detach_from_cgroup(&cgroup, &accounting.ingress_prog, CgroupHook::Ingress);
+        let _ = detach_from_cgroup(&cgroup, &accounting.egress_prog, CgroupHook::Egress);
+    }
+}
+
+/// Totals for an attached container; None means the caller should fall back to procfs
+pub fn container_counters(container_id: &str) -> Option<TrafficCounters> {
+    let accounting = ACCOUNTING.lock().unwrap();
+    let map = &accounting.get(container_id)?.totals_map;
+    let ingress = lookup_counter(map, &DIRECTION_INGRESS.to_ne_bytes())?;
+    let egress = lookup_counter(map, &DIRECTION_EGRESS.to_ne_bytes())?;
+    Some(TrafficCounters {
+        rx_packets: ingress.0,
+        rx_bytes: ingress.1,
+        tx_packets: egress.0,
+        tx_bytes: egress.1,
+    })
+}
+
+/// Per-remote-address flows of an attached container, busiest (by bytes) first
+pub fn container_flows(container_id: &str, limit: usize) -> Vec<FlowCounters> {
+    let accounting = ACCOUNTING.lock().unwrap();
+    let Some(map) = accounting.get(container_id).map(|a| &a.flows_map) else { return Vec::new() };
+
+    let mut flows: Vec<FlowCounters> = map_keys::<8>(map).iter()
+        .filter_map(|key| lookup_counter(map, key).map(|(packets, bytes)| decode_flow(key, packets, bytes)))
+        .collect();
+    flows.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    flows.truncate(limit);
+    flows
+}
+
+/// Packets dropped on the container's link, read from the host-side veth's counters and turned
+/// around to the container's point of view: `(rx_dropped, tx_dropped)`
+pub fn veth_drop_counters(veth_host: &str) -> (u64, u64) {
+    let read = |name: &str| {
+        fs::read_to_string(format!("/sys/class/net/{}/statistics/{}", veth_host, name))
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    (read("tx_dropped"), read("rx_dropped"))
+}
+
+fn decode_flow(key: &[u8; 8], packets: u64, bytes: u64) -> FlowCounters {
+    // remote_ip4 is stored as it sits in the packet, i.e. network byte order
+    FlowCounters {
+        remote_address: Ipv4Addr::new(key[0], key[1], key[2], key[3]),
+        egress: u32::from_ne_bytes([key[4], key[5], key[6], key[7]]) == DIRECTION_EGRESS,
+        packets,
+        bytes,
+    }
+}
+
+/// cgroup_skb program for one direction. Adds the packet to the totals map slot `direction`,
+/// then, for IPv4 sockets, to the `{remote_ip4, direction}` flow entry (created on first sight).
+/// Values are `{packets: u64, bytes: u64}`. Always returns 1: accounting never drops traffic.
+fn accounting_program(direction: u32, totals_map: RawFd, flows_map: RawFd) -> Vec<BpfInsn> {
+    let direction = direction as i32;
+    [
+        &[
+            insn(MOV64_REG, R6, R1, 0, 0),                  // 0: r6 = skb
+            insn(LDX_W, R7, R6, SKB_LEN, 0),                // 1: r7 = skb->len
+            insn(ST_W, R10, 0, -4, direction),              // 2: totals key
+            insn(MOV64_REG, R2, R10, 0, 0),                 // 3
+            insn(ADD64_IMM, R2, 0, 0, -4),                  // 4: r2 = &key
+        ][..],
+        &ld_map_fd(R1, totals_map),                         // 5-6: r1 = totals map
+        &[
+            insn(CALL, 0, 0, 0, FN_MAP_LOOKUP_ELEM),        // 7
+            insn(JEQ_IMM, R0, 0, 3, 0),                     // 8: missing slot -> 12
+            insn(MOV64_IMM, R1, 0, 0, 1),                   // 9
+            insn(XADD_DW, R0, R1, 0, 0),                    // 10: packets += 1
+            insn(XADD_DW, R0, R7, 8, 0),                    // 11: bytes += len
+            insn(LDX_W, R1, R6, SKB_FAMILY, 0),             // 12
+            insn(JNE_IMM, R1, 0, 23, AF_INET),              // 13: not IPv4 -> 37
+            insn(LDX_W, R1, R6, SKB_REMOTE_IP4, 0),         // 14
+            insn(STX_W, R10, R1, -16, 0),                   // 15: flow key.remote_ip4
+            insn(ST_W, R10, 0, -12, direction),             // 16: flow key.direction
+            insn(MOV64_REG, R2, R10, 0, 0),                 // 17
+            insn(ADD64_IMM, R2, 0, 0, -16),                 // 18: r2 = &flow key
+        ],
+        &ld_map_fd(R1, flows_map),                          // 19-20: r1 = flows map
+        &[
+            insn(CALL, 0, 0, 0, FN_MAP_LOOKUP_ELEM),        // 21
+            insn(JEQ_IMM, R0, 0, 4, 0),                     // 22: new flow -> 27
+            insn(MOV64_IMM, R1, 0, 0, 1),                   // 23
+            insn(XADD_DW, R0, R1, 0, 0),                    // 24
+            insn(XADD_DW, R0, R7, 8, 0),                    // 25
+            insn(JA, 0, 0, 10, 0),                          // 26: -> 37
+            insn(ST_DW, R10, 0, -32, 1),                    // 27: value.packets = 1
+            insn(STX_DW, R10, R7, -24, 0),                  // 28: value.bytes = len
+            insn(MOV64_REG, R2, R10, 0, 0),                 // 29
+            insn(ADD64_IMM, R2, 0, 0, -16),                 // 30: r2 = &flow key
+            insn(MOV64_REG, R3, R10, 0, 0),                 // 31
+            insn(ADD64_IMM, R3, 0, 0, -32),                 // 32: r3 = &value
+        ],
+        &ld_map_fd(R1, flows_map),                          // 33-34
+        &[
+            insn(MOV64_IMM, R4, 0, 0, BPF_NOEXIST as i32),  // 35: a full map just stops adding flows
+            insn(CALL, 0, 0, 0, FN_MAP_UPDATE_ELEM),        // 36
+            insn(MOV64_IMM, R0, 0, 0, 1),                   // 37: allow the packet
+            insn(EXIT, 0, 0, 0, 0),                         // 38
+        ],
+    ].concat()
+}
+
+/// `(packets, bytes)` stored under `key`
+fn lookup_counter(map: &OwnedFd, key: &[u8]) -> Option<(u64, u64)> {
+    let mut value = [0u8; 16];
+    if !map_lookup(map, key, &mut value) {
+        return None;
+    }
+    let packets = u64::from_ne_bytes(value[..8].try_into().ok()?);
+    let bytes = u64::from_ne_bytes(value[8..].try_into().ok()?);
+    Some((packets, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounting_program_encoding() {
+        let program = accounting_program(DIRECTION_EGRESS, 7, 9);
+        assert_eq!(program.len(), 39);
+        assert_eq!(program[5].imm, 7);
+        assert_eq!(program[19].imm, 9);
+        assert_eq!(program[33].imm, 9);
+        assert_eq!(program[16].imm, DIRECTION_EGRESS as i32);
+        // Every jump lands on an instruction inside the program
+        for (index, instruction) in program.iter().enumerate() {
+            if matches!(instruction.code, JA | JEQ_IMM | JNE_IMM) {
+                let target = index as i64 + 1 + instruction.off as i64;
+                assert!(target > index as i64 && (target as usize) < program.len());
+            }
+        }
+        assert_eq!(program[13].off as usize + 14, 37);
+        assert_eq!(program.last().unwrap().code, EXIT);
+    }
+
+    #[test]
+    fn test_decode_flow() {
+        let mut key = [10, 42, 0, 7, 0, 0, 0, 0];
+        key[4..].copy_from_slice(&DIRECTION_EGRESS.to_ne_bytes());
+        let flow = decode_flow(&key, 3, 180);
+        assert_eq!(flow.remote_address, Ipv4Addr::new(10, 42, 0, 7));
+        assert!(flow.egress);
+        assert_eq!((flow.packets, flow.bytes), (3, 180));
+    }
+}
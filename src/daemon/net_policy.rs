@@ -0,0 +1,332 @@
+// eBPF network policy enforcement
+// Per-container allow/deny rules enforced by cgroup_skb programs on the container's cgroup v2
+// directory instead of iptables chains, so the host's global firewall configuration is never
+// touched. Each direction has an LPM trie of remote IPv4 prefixes → verdict and a default verdict;
+// the most specific matching prefix wins. Updating a policy only rewrites the maps.
+
+use crate::daemon::bpf::*;
+use crate::utils::console::ConsoleLogger;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Rules per direction a policy may hold
+pub const MAX_POLICY_RULES: usize = 256;
+
+static ENFORCED: once_cell::sync::Lazy<Mutex<HashMap<String, CgroupPolicy>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+}
+
+impl PolicyAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyAction::Allow => "allow",
+            PolicyAction::Deny => "deny",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(PolicyAction::Allow),
+            "deny" => Ok(PolicyAction::Deny),
+            other => Err(format!("Unknown policy action '{}' (expected allow or deny)", other)),
+        }
+    }
+
+    /// cgroup_skb verdict: 1 lets the packet through
+    fn verdict(&self) -> u32 {
+        match self {
+            PolicyAction::Allow => 1,
+            PolicyAction::Deny => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyDirection {
+    /// Traffic arriving at the container; the peer is the source
+    Ingress,
+    /// Traffic the container sends; the peer is the destination
+    Egress,
+}
+
+impl PolicyDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyDirection::Ingress => "ingress",
+            PolicyDirection::Egress => "egress",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ingress" => Ok(PolicyDirection::Ingress),
+            "egress" => Ok(PolicyDirection::Egress),
+            other => Err(format!("Unknown policy direction '{}' (expected ingress or egress)", other)),
+        }
+    }
+
+    fn hook(&self) -> CgroupHook {
+        match self {
+            PolicyDirection::Ingress => CgroupHook::Ingress,
+            PolicyDirection::Egress => CgroupHook::Egress,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicyRule {
+    pub direction: PolicyDirection,
+    /// Container name or ID, an IPv4 address, or an IPv4 CIDR
+    pub peer: String,
+    pub action: PolicyAction,
+}
+
+/// Stored policy of one container, as set through the API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    pub ingress_default: PolicyAction,
+    pub egress_default: PolicyAction,
+    #[serde(default)]
+    pub rules: Vec<NetworkPolicyRule>,
+}
+
+impl NetworkPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for direction in [PolicyDirection::Ingress, PolicyDirection::Egress] {
+            let count = self.rules.iter().filter(|rule| rule.direction == direction).count();
+            if count > MAX_POLICY_RULES {
+                return Err(format!("Too many {} rules ({} > {})", direction.as_str(), count, MAX_POLICY_RULES));
+            }
+        }
+        if let Some(rule) = self.rules.iter().find(|rule| rule.peer.trim().is_empty()) {
+            return Err(format!("{} {} rule has an empty peer", rule.direction.as_str(), rule.action.as_str()));
+        }
+        Ok(())
+    }
+
+    fn default_for(&self, direction: PolicyDirection) -> PolicyAction {
+        match direction {
+            PolicyDirection::Ingress => self.ingress_default,
+            PolicyDirection::Egress => self.egress_default,
+        }
+    }
+}
+
+/// A rule with its peer resolved to an IPv4 prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolicyEntry {
+    pub direction: PolicyDirection,
+    pub network: Ipv4Addr,
+    pub prefix_len: u8,
+    pub action: PolicyAction,
+}
+
+/// Parse `a.b.c.d` or `a.b.c.d/n`; host bits are cleared. None for anything else (e.g. a name).
+pub fn parse_prefix(peer: &str) -> Option<(Ipv4Addr, u8)> {
+    let (address, prefix_len) = match peer.trim().split_once('/') {
+        Some((address, len)) => (address, len.parse::<u8>().ok().filter(|len| *len <= 32)?),
+        None => (peer.trim(), 32),
+    };
+    let address: Ipv4Addr = address.parse().ok()?;
+    let mask = if prefix_len == 0 { 0 } else { u32::MAX << (32 - prefix_len) };
+    Some((Ipv4Addr::from(u32::from(address) & mask), prefix_len))
+}
+
+/// Maps and programs enforcing one container's policy; dropping it closes every descriptor
+struct CgroupPolicy {
+    cgroup_dir: String,
+    defaults_map: OwnedFd,
+    ingress_map: OwnedFd,
+    egress_map: OwnedFd,
+    ingress_prog: OwnedFd,
+    egress_prog: OwnedFd,
+}
+
+impl CgroupPolicy {
+    fn map_for(&self, direction: PolicyDirection) -> &OwnedFd {
+        match direction {
+            PolicyDirection::Ingress => &self.ingress_map,
+            PolicyDirection::Egress => &self.egress_map,
+        }
+    }
+}
+
+/// Enforce `policy` (with peers already resolved into `entries`) on a running container. The
+/// first call attaches the programs; later calls swap the map contents in place.
+pub fn enforce(container_id: &str, policy: &NetworkPolicy, entries: &[PolicyEntry]) -> Result<(), String> {
+    let mut enforced = ENFORCED.lock().unwrap();
+    if !enforced.contains_key(container_id) {
+        enforced.insert(container_id.to_string(), attach(container_id)?);
+    }
+    let state = &enforced[container_id];
+
+    for direction in [PolicyDirection::Ingress, PolicyDirection::Egress] {
+        let mut desired: HashMap<[u8; 8], u32> = HashMap::new();
+        for entry in entries.iter().filter(|entry| entry.direction == direction) {
+            desired.insert(lpm_key(entry.network, entry.prefix_len), entry.action.verdict());
+        }
+
+        // Install the new entries before dropping stale ones so no packet sees an empty table
+        let map = state.map_for(direction);
+        for (key, verdict) in &desired {
+            map_update(map, key, &verdict.to_ne_bytes(), BPF_ANY)?;
+        }
+        for key in map_keys::<8>(map) {
+            if !desired.contains_key(&key) {
+                map_delete(map, &key)?;
+            }
+        }
+        map_update(&state.defaults_map, &(direction as u32).to_ne_bytes(),
+            &policy.default_for(direction).verdict().to_ne_bytes(), BPF_ANY)?;
+    }
+
+    ConsoleLogger::debug(&format!("🛡️ [NET-POLICY] {} rules enforced on {} (ingress {}, egress {})",
+        entries.len(), container_id, policy.ingress_default.as_str(), policy.egress_default.as_str()));
+    Ok(())
+}
+
+/// Stop enforcing the container's policy; traffic is unrestricted afterwards
+pub fn remove(container_id: &str) {
+    let Some(state) = ENFORCED.lock().unwrap().remove(container_id) else { return };
+    if let Ok(cgroup) = fs::File::open(&state.cgroup_dir) {
+        let _ = detach_from_cgroup(&cgroup, &state.ingress_prog, CgroupHook::Ingress);
+        let _ = detach_from_cgroup(&cgroup, &state.egress_prog, CgroupHook::Egress);
+    }
+}
+
+/// Whether a policy is currently enforced on the container
+pub fn is_enforced(container_id: &str) -> bool {
+    ENFORCED.lock().unwrap().contains_key(container_id)
+}
+
+fn attach(container_id: &str) -> Result<CgroupPolicy, String> {
+    let cgroup_dir = format!("/sys/fs/cgroup/quilt/{}", container_id);
+    if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        return Err("eBPF network policies need the unified cgroup v2 hierarchy".to_string());
+    }
+    let cgroup = fs::File::open(&cgroup_dir)
+        .map_err(|e| format!("Failed to open cgroup {}: {}", cgroup_dir, e))?;
+
+    // Until the first update the defaults slots read 0 (deny); fill them before attaching
+    let defaults_map = create_map(BPF_MAP_TYPE_ARRAY, 4, 4, 2, 0)?;
+    for direction in [PolicyDirection::Ingress, PolicyDirection::Egress] {
+        map_update(&defaults_map, &(direction as u32).to_ne_bytes(), &PolicyAction::Allow.verdict().to_ne_bytes(), BPF_ANY)?;
+    }
+    let ingress_map = create_map(BPF_MAP_TYPE_LPM_TRIE, 8, 4, MAX_POLICY_RULES as u32, BPF_F_NO_PREALLOC)?;
+    let egress_map = create_map(BPF_MAP_TYPE_LPM_TRIE, 8, 4, MAX_POLICY_RULES as u32, BPF_F_NO_PREALLOC)?;
+    let ingress_prog = load_cgroup_skb_program(&policy_program(PolicyDirection::Ingress, ingress_map.as_raw_fd(), defaults_map.as_raw_fd()))?;
+    let egress_prog = load_cgroup_skb_program(&policy_program(PolicyDirection::Egress, egress_map.as_raw_fd(), defaults_map.as_raw_fd()))?;
+
+    attach_to_cgroup(&cgroup, &ingress_prog, PolicyDirection::Ingress.hook())?;
+    if let Err(e) = attach_to_cgroup(&cgroup, &egress_prog, PolicyDirection::Egress.hook()) {
+        let _ = detach_from_cgroup(&cgroup, &ingress_prog, PolicyDirection::Ingress.hook());
+        return Err(e);
+    }
+
+    Ok(CgroupPolicy { cgroup_dir, defaults_map, ingress_map, egress_map, ingress_prog, egress_prog })
+}
+
+/// `struct bpf_lpm_trie_key` for an IPv4 prefix: prefix length, then the address bytes in
+/// network order (how `remote_ip4` sits in memory)
+fn lpm_key(network: Ipv4Addr, prefix_len: u8) -> [u8; 8] {
+    let mut key = [0u8; 8];
+    key[..4].copy_from_slice(&(prefix_len as u32).to_ne_bytes());
+    key[4..].copy_from_slice(&network.octets());
+    key
+}
+
+/// cgroup_skb program for one direction: look the remote IPv4 address up in the direction's LPM
+/// trie and return the stored verdict, else the direction's default. Non-IPv4 traffic passes.
+fn policy_program(direction: PolicyDirection, rules_map: RawFd, defaults_map: RawFd) -> Vec<BpfInsn> {
+    [
+        &[
+            insn(MOV64_REG, R6, R1, 0, 0),                  // 0: r6 = skb
+            insn(LDX_W, R1, R6, SKB_FAMILY, 0),             // 1
+            insn(JNE_IMM, R1, 0, 22, AF_INET),              // 2: not IPv4 -> 25
+            insn(LDX_W, R1, R6, SKB_REMOTE_IP4, 0),         // 3
+            insn(STX_W, R10, R1, -4, 0),                    // 4: key.data = remote_ip4
+            insn(ST_W, R10, 0, -8, 32),                     // 5: key.prefixlen = 32
+            insn(MOV64_REG, R2, R10, 0, 0),                 // 6
+            insn(ADD64_IMM, R2, 0, 0, -8),                  // 7: r2 = &key
+        ][..],
+        &ld_map_fd(R1, rules_map),                          // 8-9: r1 = rules trie
+        &[
+            insn(CALL, 0, 0, 0, FN_MAP_LOOKUP_ELEM),        // 10
+            insn(JEQ_IMM, R0, 0, 3, 0),                     // 11: no rule -> 15
+            insn(LDX_W, R0, R0, 0, 0),                      // 12: r0 = verdict
+            insn(AND64_IMM, R0, 0, 0, 1),                   // 13: the verifier wants 0 or 1
+            insn(EXIT, 0, 0, 0, 0),                         // 14
+            insn(ST_W, R10, 0, -12, direction as i32),      // 15: defaults key
+            insn(MOV64_REG, R2, R10, 0, 0),                 // 16
+            insn(ADD64_IMM, R2, 0, 0, -12),                 // 17
+        ],
+        &ld_map_fd(R1, defaults_map),                       // 18-19: r1 = defaults
+        &[
+            insn(CALL, 0, 0, 0, FN_MAP_LOOKUP_ELEM),        // 20
+            insn(JEQ_IMM, R0, 0, 3, 0),                     // 21: -> 25
+            insn(LDX_W, R0, R0, 0, 0),                      // 22
+            insn(AND64_IMM, R0, 0, 0, 1),                   // 23
+            insn(EXIT, 0, 0, 0, 0),                         // 24
+            insn(MOV64_IMM, R0, 0, 0, 1),                   // 25: allow
+            insn(EXIT, 0, 0, 0, 0),                         // 26
+        ],
+    ].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(parse_prefix("10.42.0.7"), Some((Ipv4Addr::new(10, 42, 0, 7), 32)));
+        assert_eq!(parse_prefix("10.42.3.9/16"), Some((Ipv4Addr::new(10, 42, 0, 0), 16)));
+        assert_eq!(parse_prefix("0.0.0.0/0"), Some((Ipv4Addr::new(0, 0, 0, 0), 0)));
+        assert_eq!(parse_prefix("10.0.0.0/33"), None);
+        assert_eq!(parse_prefix("web"), None);
+    }
+
+    #[test]
+    fn test_policy_validation_and_storage_format() {
+        let policy = NetworkPolicy {
+            ingress_default: PolicyAction::Deny,
+            egress_default: PolicyAction::Allow,
+            rules: vec![NetworkPolicyRule { direction: PolicyDirection::Ingress, peer: "web".to_string(), action: PolicyAction::Allow }],
+        };
+        assert!(policy.validate().is_ok());
+        let json = serde_json::to_string(&policy).unwrap();
+        assert!(json.contains("\"ingress_default\":\"deny\""));
+        assert_eq!(serde_json::from_str::<NetworkPolicy>(&json).unwrap(), policy);
+
+        let mut empty_peer = policy.clone();
+        empty_peer.rules[0].peer = " ".to_string();
+        assert!(empty_peer.validate().is_err());
+    }
+
+    #[test]
+    fn test_policy_program() {
+        let program = policy_program(PolicyDirection::Egress, 5, 6);
+        assert_eq!(program.len(), 27);
+        assert_eq!((program[8].imm, program[18].imm), (5, 6));
+        assert_eq!(program[15].imm, PolicyDirection::Egress as i32);
+        for (index, instruction) in program.iter().enumerate() {
+            if matches!(instruction.code, JEQ_IMM | JNE_IMM) {
+                let target = index + 1 + instruction.off as usize;
+                assert!(target < program.len() && program[target].code != 0);
+            }
+        }
+        assert_eq!(lpm_key(Ipv4Addr::new(10, 42, 0, 0), 16)[4..], [10, 42, 0, 0]);
+    }
+}
@@ -0,0 +1,191 @@
+// Container output capture
+// A container's stdout and stderr are pipes read by one thread each. Every line is stamped when it
+// is read, with both the wall clock (for display and time filters) and CLOCK_MONOTONIC (for
+// ordering that survives clock steps), and tagged with the stream it came from. Lines the runtime
+// itself records about the container use the same type with the `System` stream.
+
+use nix::fcntl::OFlag;
+use nix::time::{clock_gettime, ClockId};
+use nix::unistd::{dup2, pipe2};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lines kept per container; the oldest are dropped first
+pub const MAX_OUTPUT_LINES: usize = 10_000;
+/// Longer lines are split so a stream without newlines cannot grow without bound
+const MAX_LINE_BYTES: u64 = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// Recorded by the runtime (start, stop, exit)
+    System,
+}
+
+impl LogStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+            LogStream::System => "system",
+        }
+    }
+}
+
+/// One line of container output, stamped at ingestion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// Unix time in milliseconds
+    pub timestamp_ms: u64,
+    /// CLOCK_MONOTONIC in nanoseconds; orders lines within one boot
+    pub monotonic_ns: u64,
+    pub stream: LogStream,
+    pub message: String,
+}
+
+impl LogLine {
+    pub fn now(stream: LogStream, message: String) -> Self {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let monotonic_ns = clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .map(|ts| ts.tv_sec() as u64 * 1_000_000_000 + ts.tv_nsec() as u64)
+            .unwrap_or(0);
+        Self { timestamp_ms, monotonic_ns, stream, message }
+    }
+}
+
+/// Bounded per-container line buffer
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer {
+    lines: VecDeque<LogLine>,
+    dropped: u64,
+}
+
+impl OutputBuffer {
+    pub fn push(&mut self, line: LogLine) {
+        if self.lines.len() >= MAX_OUTPUT_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Lines in ingestion order
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.iter().cloned().collect()
+    }
+
+    /// Lines dropped because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// The pipes a container's stdout and stderr are connected to. Created close-on-exec, so other
+/// processes the daemon forks meanwhile do not keep them open.
+pub struct OutputPipes {
+    stdout: (OwnedFd, OwnedFd),
+    stderr: (OwnedFd, OwnedFd),
+}
+
+impl OutputPipes {
+    pub fn new() -> Result<Self, String> {
+        let pipe = || pipe2(OFlag::O_CLOEXEC)
+            .map(|(read, write)| unsafe { (OwnedFd::from_raw_fd(read), OwnedFd::from_raw_fd(write)) })
+            .map_err(|e| format!("Failed to create output pipe: {}", e));
+        Ok(Self { stdout: pipe()?, stderr: pipe()? })
+    }
+
+    /// Write ends, for [`redirect_output`] in the forked child
+    pub fn child_fds(&self) -> (RawFd, RawFd) {
+        (self.stdout.1.as_raw_fd(), self.stderr.1.as_raw_fd())
+    }
+
+    /// Close the parent's write ends and read both streams on background threads until every
+    /// process holding the write ends has exited
+    pub fn spawn_readers<F>(self, name: &str, sink: F) -> Result<(), String>
+    where
+        F: Fn(LogLine) + Clone + Send + 'static,
+    {
+        let (stdout_read, stdout_write) = self.stdout;
+        let (stderr_read, stderr_write) = self.stderr;
+        drop(stdout_write);
+        drop(stderr_write);
+        for (read, stream) in [(stdout_read, LogStream::Stdout), (stderr_read, LogStream::Stderr)] {
+            let sink = sink.clone();
+            std::thread::Builder::new()
+                .name(format!("quilt-{}-{}", stream.as_str(), name))
+                .spawn(move || read_lines(std::fs::File::from(read), stream, sink))
+                .map_err(|e| format!("Failed to start {} reader: {}", stream.as_str(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// In the child: point stdout and stderr at the capture pipes. The originals are close-on-exec.
+pub fn redirect_output(stdout_fd: RawFd, stderr_fd: RawFd) -> Result<(), String> {
+    dup2(stdout_fd, 1).map_err(|e| format!("Failed to redirect stdout: {}", e))?;
+    dup2(stderr_fd, 2).map_err(|e| format!("Failed to redirect stderr: {}", e))?;
+    Ok(())
+}
+
+fn read_lines<R: Read>(source: R, stream: LogStream, sink: impl Fn(LogLine)) {
+    let mut reader = BufReader::new(source);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match (&mut reader).take(MAX_LINE_BYTES).read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                // Stamp before any further work so the time is when the line arrived
+                let mut line = LogLine::now(stream, String::new());
+                if buf.last() == Some(&b'\n') {
+                    buf.pop();
+                }
+                if buf.last() == Some(&b'\r') {
+                    buf.pop();
+                }
+                line.message = String::from_utf8_lossy(&buf).into_owned();
+                sink(line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_read_lines_stamps_each_line() {
+        let long = "x".repeat(MAX_LINE_BYTES as usize + 10);
+        let input = format!("first\r\nsecond\n{}", long);
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink_lines = lines.clone();
+        read_lines(input.as_bytes(), LogStream::Stderr, move |line| sink_lines.lock().unwrap().push(line));
+
+        let lines = lines.lock().unwrap();
+        let messages: Vec<&str> = lines.iter().map(|line| line.message.as_str()).collect();
+        assert_eq!(messages[..2], ["first", "second"]);
+        // An over-long line is split rather than buffered whole
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[3].message.len(), 10);
+        assert!(lines.iter().all(|line| line.stream == LogStream::Stderr && line.timestamp_ms > 0));
+        assert!(lines.windows(2).all(|pair| pair[0].monotonic_ns <= pair[1].monotonic_ns));
+    }
+
+    #[test]
+    fn test_output_buffer_is_bounded() {
+        let mut buffer = OutputBuffer::default();
+        for i in 0..MAX_OUTPUT_LINES + 5 {
+            buffer.push(LogLine::now(LogStream::Stdout, i.to_string()));
+        }
+        assert_eq!(buffer.dropped(), 5);
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), MAX_OUTPUT_LINES);
+        assert_eq!(lines[0].message, "5");
+    }
+}
@@ -0,0 +1,197 @@
+// Copy-on-write container rootfs
+// A container started from an image in the local store (daemon/images.rs) gets an overlayfs
+// rootfs instead of its own copy of the image. The image's layers are flattened once into a
+// read-only lower directory in the store, `rootfs/<digest hex>`, with the binaries fixes applied;
+// every container of that image mounts it under an upper and a work directory of its own,
+// `<rootfs base>/.overlay/<id>/{upper,work}`, kept beside the container's rootfs so both are on
+// the same filesystem. Creating a container is then a mount, and only what a container changes
+// takes disk space. The lower directory is recorded with the container, so the rootfs can be
+// mounted again after a stop unmounted it or the host rebooted. QUILT_OVERLAY_ROOTFS=0, a kernel
+// without overlayfs and user-namespaced containers (whose rootfs is attached ID-mapped) keep the
+// per-container copy.
+
+use crate::daemon::images::{self, StoredImage};
+use crate::utils::console::ConsoleLogger;
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set to `0` or `false` to copy the image into every container instead
+pub const OVERLAY_ROOTFS_ENV: &str = "QUILT_OVERLAY_ROOTFS";
+
+/// File in a container's overlay directory naming its lower directory
+const LOWER_RECORD: &str = "lower";
+
+/// Whether new containers from stored images get an overlayfs rootfs
+pub fn enabled() -> bool {
+    let opted_out = std::env::var(OVERLAY_ROOTFS_ENV)
+        .map_or(false, |value| matches!(value.trim(), "0" | "false" | "no" | "off"));
+    !opted_out && kernel_supports_overlay()
+}
+
+fn kernel_supports_overlay() -> bool {
+    fs::read_to_string("/proc/filesystems")
+        .map(|filesystems| filesystems.lines().any(|line| line.split_whitespace().last() == Some("overlay")))
+        .unwrap_or(false)
+}
+
+/// The overlay directory of the container whose rootfs is `rootfs_path`
+pub fn overlay_dir(rootfs_path: &str) -> PathBuf {
+    let rootfs = Path::new(rootfs_path);
+    let parent = rootfs.parent().unwrap_or_else(|| Path::new("/"));
+    let name = rootfs.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    parent.join(".overlay").join(name)
+}
+
+/// Whether the container's rootfs is an overlay, mounted or not
+pub fn is_overlay_rootfs(rootfs_path: &str) -> bool {
+    overlay_dir(rootfs_path).join(LOWER_RECORD).is_file()
+}
+
+/// Flattened, read-only lower directory of a stored image
+pub fn lower_dir(store_root: &Path, image: &StoredImage) -> PathBuf {
+    store_root.join("rootfs").join(image.digest.trim_start_matches("sha256:"))
+}
+
+/// The image's lower directory, built first if this is the image's first overlay container.
+/// `prepare` runs on the flattened image before it is put in place, e.g. to fix its binaries.
+/// Blocking.
+pub fn prepare_lower(store_root: &Path, image: &StoredImage, prepare: impl FnOnce(&str) -> Result<(), String>) -> Result<PathBuf, String> {
+    let target = lower_dir(store_root, image);
+    if target.is_dir() {
+        return Ok(target);
+    }
+    let partial = store_root.join("tmp").join(format!("rootfs-{}", uuid::Uuid::new_v4()));
+    let built = fs::create_dir_all(&partial)
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))
+        .and_then(|_| images::materialize(store_root, image, &partial.display().to_string()))
+        .and_then(|_| prepare(&partial.display().to_string()))
+        .and_then(|_| fs::create_dir_all(store_root.join("rootfs")).map_err(|e| e.to_string()))
+        .and_then(|_| fs::rename(&partial, &target).map_err(|e| e.to_string()));
+    if let Err(e) = built {
+        let _ = fs::remove_dir_all(&partial);
+        // Another container of the same image built it first
+        if target.is_dir() {
+            return Ok(target);
+        }
+        return Err(format!("Failed to build the overlay lower directory of {}: {}", image.digest, e));
+    }
+    ConsoleLogger::debug(&format!("Flattened {} into {}", image.digest, target.display()));
+    Ok(target)
+}
+
+fn mount_options(lower: &Path, upper: &Path, work: &Path) -> String {
+    format!("lowerdir={},upperdir={},workdir={}", lower.display(), upper.display(), work.display())
+}
+
+/// Whether `/proc/self/mounts` content lists a mount at `path`
+fn mounted_at(mounts: &str, path: &str) -> bool {
+    mounts.lines().any(|line| line.split_whitespace().nth(1) == Some(path))
+}
+
+/// Give the container at `rootfs_path` an overlay rootfs over `lower` and mount it
+pub fn create_rootfs(lower: &Path, rootfs_path: &str) -> Result<(), String> {
+    let dir = overlay_dir(rootfs_path);
+    for path in [dir.join("upper"), dir.join("work"), PathBuf::from(rootfs_path)] {
+        fs::create_dir_all(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    }
+    fs::write(dir.join(LOWER_RECORD), lower.display().to_string())
+        .map_err(|e| format!("Failed to record the lower directory of {}: {}", rootfs_path, e))?;
+    ensure_mounted(rootfs_path)
+}
+
+/// Mount the overlay rootfs at `rootfs_path` unless it is mounted already; a rootfs that is no
+/// overlay is left alone
+pub fn ensure_mounted(rootfs_path: &str) -> Result<(), String> {
+    if !is_overlay_rootfs(rootfs_path) {
+        return Ok(());
+    }
+    if mounted_at(&fs::read_to_string("/proc/self/mounts").unwrap_or_default(), rootfs_path) {
+        return Ok(());
+    }
+    let dir = overlay_dir(rootfs_path);
+    let lower = fs::read_to_string(dir.join(LOWER_RECORD))
+        .map_err(|e| format!("Failed to read the lower directory of {}: {}", rootfs_path, e))?;
+    let lower = PathBuf::from(lower.trim());
+    if !lower.is_dir() {
+        return Err(format!("Image layers of {} are gone from the store ({})", rootfs_path, lower.display()));
+    }
+    let options = mount_options(&lower, &dir.join("upper"), &dir.join("work"));
+    mount(Some("overlay"), rootfs_path, Some("overlay"), MsFlags::empty(), Some(options.as_str()))
+        .map_err(|e| format!("Failed to mount overlay rootfs at {}: {}", rootfs_path, e))?;
+    ConsoleLogger::debug(&format!("Mounted overlay rootfs at {} over {}", rootfs_path, lower.display()));
+    Ok(())
+}
+
+/// Unmount an overlay rootfs and delete what the container wrote to it. The rootfs directory
+/// itself is left to the caller. Does nothing for a rootfs that is no overlay.
+pub fn remove_rootfs(rootfs_path: &str) -> Result<(), String> {
+    if !is_overlay_rootfs(rootfs_path) {
+        return Ok(());
+    }
+    if mounted_at(&fs::read_to_string("/proc/self/mounts").unwrap_or_default(), rootfs_path) {
+        umount2(rootfs_path, MntFlags::MNT_DETACH)
+            .map_err(|e| format!("Failed to unmount overlay rootfs at {}: {}", rootfs_path, e))?;
+    }
+    let dir = overlay_dir(rootfs_path);
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_layout() {
+        assert_eq!(overlay_dir("/tmp/quilt-containers/abc"), PathBuf::from("/tmp/quilt-containers/.overlay/abc"));
+        let image = StoredImage {
+            digest: format!("sha256:{}", "a".repeat(64)),
+            repo_tags: Vec::new(),
+            layers: Vec::new(),
+            config: Default::default(),
+            size_bytes: 0,
+        };
+        assert_eq!(lower_dir(Path::new("/store"), &image), PathBuf::from(format!("/store/rootfs/{}", "a".repeat(64))));
+        assert_eq!(mount_options(Path::new("/l"), Path::new("/u"), Path::new("/w")), "lowerdir=/l,upperdir=/u,workdir=/w");
+
+        let mounts = "overlay /srv/c/abc overlay rw,lowerdir=/l 0 0\n/dev/sda1 /srv ext4 rw 0 0\n";
+        assert!(mounted_at(mounts, "/srv/c/abc"));
+        assert!(!mounted_at(mounts, "/srv/c"));
+
+        // Only containers created with an overlay have the record
+        let base = tempfile::tempdir().unwrap();
+        let rootfs = base.path().join("abc").display().to_string();
+        assert!(!is_overlay_rootfs(&rootfs));
+        assert!(remove_rootfs(&rootfs).is_ok());
+        fs::create_dir_all(overlay_dir(&rootfs)).unwrap();
+        fs::write(overlay_dir(&rootfs).join(LOWER_RECORD), "/missing").unwrap();
+        assert!(is_overlay_rootfs(&rootfs));
+        assert!(ensure_mounted(&rootfs).unwrap_err().contains("gone from the store"));
+    }
+
+    #[test]
+    fn test_prepare_lower() {
+        let store = tempfile::tempdir().unwrap();
+        let layer_digest = format!("sha256:{}", "b".repeat(64));
+        let layer = images::layer_dir(store.path(), &layer_digest);
+        fs::create_dir_all(layer.join("etc")).unwrap();
+        fs::write(layer.join("etc/motd"), "hello").unwrap();
+        let image = StoredImage {
+            digest: format!("sha256:{}", "c".repeat(64)),
+            repo_tags: Vec::new(),
+            layers: vec![layer_digest],
+            config: Default::default(),
+            size_bytes: 0,
+        };
+
+        let lower = prepare_lower(store.path(), &image, |dir| fs::write(Path::new(dir).join("fixed"), "").map_err(|e| e.to_string())).unwrap();
+        assert_eq!(fs::read_to_string(lower.join("etc/motd")).unwrap(), "hello");
+        assert!(lower.join("fixed").exists());
+        // Built once; later containers reuse it as it is
+        let again = prepare_lower(store.path(), &image, |_| Err("not called".to_string())).unwrap();
+        assert_eq!(again, lower);
+        assert!(prepare_lower(store.path(), &StoredImage { digest: "sha256:d".to_string(), ..image.clone() }, |_| Err("broken".to_string()))
+            .unwrap_err().contains("broken"));
+        assert_eq!(fs::read_dir(store.path().join("tmp")).unwrap().count(), 0);
+    }
+}
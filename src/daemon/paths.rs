@@ -0,0 +1,86 @@
+// Container rootfs location
+// Every container rootfs lives in one base directory, `/tmp/quilt-containers` unless
+// `QUILT_ROOTFS_DIR` or the engine configuration says otherwise. /tmp is often small, noexec or
+// cleaned by tmpfiles, so hosts running long-lived containers should point this elsewhere. The
+// path of an existing container is whatever the sync engine recorded for it; the helpers here
+// only name the directory of a container being created.
+
+use once_cell::sync::OnceCell;
+use std::path::Path;
+
+/// Environment variable overriding the rootfs base directory
+pub const ROOTFS_BASE_ENV: &str = "QUILT_ROOTFS_DIR";
+pub const DEFAULT_ROOTFS_BASE_DIR: &str = "/tmp/quilt-containers";
+
+static ROOTFS_BASE_DIR: OnceCell<String> = OnceCell::new();
+
+/// The base directory from `QUILT_ROOTFS_DIR`, falling back to the default
+pub fn rootfs_base_dir_from_env() -> String {
+    std::env::var(ROOTFS_BASE_ENV).ok()
+        .map(|dir| normalize(&dir))
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| DEFAULT_ROOTFS_BASE_DIR.to_string())
+}
+
+/// Fix the base directory for this process. Must happen before the first container is created;
+/// setting the same directory again is fine, a different one is an error.
+pub fn set_rootfs_base_dir(dir: &str) -> Result<(), String> {
+    let dir = normalize(dir);
+    // "/" itself normalizes to "" and is rejected with relative paths
+    if !Path::new(&dir).is_absolute() {
+        return Err(format!("Rootfs base directory must be an absolute path: {}", dir));
+    }
+    let current = ROOTFS_BASE_DIR.get_or_init(|| dir.clone());
+    if *current != dir {
+        return Err(format!("Rootfs base directory already set to {}", current));
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create rootfs base directory {}: {}", dir, e))
+}
+
+/// Where container rootfs directories are created
+pub fn rootfs_base_dir() -> &'static str {
+    ROOTFS_BASE_DIR.get_or_init(rootfs_base_dir_from_env)
+}
+
+/// Rootfs directory for a container that is being created
+pub fn default_rootfs_path(container_id: &str) -> String {
+    format!("{}/{}", rootfs_base_dir(), container_id)
+}
+
+/// Staging area for pre-extracted images; on the same filesystem as the rootfs directories so a
+/// claim is a rename
+pub fn rootfs_pool_dir() -> String {
+    format!("{}/.pool", rootfs_base_dir())
+}
+
+/// Whether `path` is a container directory below the base directory
+pub fn is_container_rootfs(path: &str) -> bool {
+    is_below(rootfs_base_dir(), path)
+}
+
+fn is_below(base: &str, path: &str) -> bool {
+    path.strip_prefix(base)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map_or(false, |name| !name.is_empty() && !name.split('/').any(|part| part == ".."))
+}
+
+fn normalize(dir: &str) -> String {
+    dir.trim().trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rootfs_paths() {
+        assert_eq!(normalize(" /srv/quilt/ "), "/srv/quilt");
+        assert!(is_below("/srv/quilt", "/srv/quilt/abc"));
+        assert!(is_below("/srv/quilt", "/srv/quilt/abc/tmp"));
+        assert!(!is_below("/srv/quilt", "/srv/quilt/"));
+        assert!(!is_below("/srv/quilt", "/srv/quilted/abc"));
+        assert!(!is_below("/srv/quilt", "/srv/quilt/../etc"));
+        assert!(set_rootfs_base_dir("relative/dir").is_err());
+        assert!(set_rootfs_base_dir("/").is_err());
+    }
+}
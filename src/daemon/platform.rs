@@ -0,0 +1,105 @@
+// Image platforms
+// An image is built for one CPU architecture; a multi-arch image is an index of per-platform
+// manifests. Pulls and OCI layout imports pick the index entry for linux on the host's
+// architecture, and an image whose config names another architecture is refused when it is
+// pulled or imported, instead of failing with "exec format error" once a container runs it.
+// Architectures go by their OCI (GOARCH) names: amd64, arm64, ...
+
+use serde::Deserialize;
+
+/// Platform of an index entry
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Platform {
+    pub architecture: String,
+    pub os: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// Whether an image for this platform runs on linux/`architecture`; for arm64 an entry
+    /// without variant or with v8
+    pub fn runs_on(&self, architecture: &str) -> bool {
+        self.os == "linux"
+            && normalize_architecture(&self.architecture) == architecture
+            && (architecture != "arm64" || self.variant.as_deref().map_or(true, |variant| variant == "v8"))
+    }
+}
+
+/// The host architecture under its OCI name
+pub fn host_architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "powerpc64" => "ppc64le",
+        other => other,
+    }
+}
+
+/// The OCI name of an architecture also spelled the kernel's way (`x86_64`, `aarch64`)
+pub fn normalize_architecture(architecture: &str) -> String {
+    match architecture.trim().to_ascii_lowercase().as_str() {
+        "x86_64" | "x86-64" => "amd64".to_string(),
+        "aarch64" => "arm64".to_string(),
+        "i386" | "i686" => "386".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// The first of `entries` whose platform runs on `architecture`
+pub fn select<'a, T>(entries: &'a [T], architecture: &str, platform: impl Fn(&T) -> Option<&Platform>) -> Option<&'a T> {
+    entries.iter().find(|entry| platform(entry).map_or(false, |platform| platform.runs_on(architecture)))
+}
+
+/// The platforms `entries` offer, e.g. `linux/amd64, linux/arm/v7`, for errors
+pub fn describe<T>(entries: &[T], platform: impl Fn(&T) -> Option<&Platform>) -> String {
+    let platforms: Vec<String> = entries.iter()
+        .filter_map(|entry| platform(entry))
+        .map(|platform| match &platform.variant {
+            Some(variant) => format!("{}/{}/{}", platform.os, platform.architecture, variant),
+            None => format!("{}/{}", platform.os, platform.architecture),
+        })
+        .collect();
+    if platforms.is_empty() { "none".to_string() } else { platforms.join(", ") }
+}
+
+/// Refuse image `name` when its config names an architecture other than the host's; images that
+/// do not say are accepted
+pub fn check_architecture(name: &str, architecture: Option<&str>) -> Result<(), String> {
+    let Some(architecture) = architecture.map(normalize_architecture).filter(|architecture| !architecture.is_empty()) else {
+        return Ok(());
+    };
+    let host = host_architecture();
+    if architecture != host {
+        return Err(format!("Image {} is built for linux/{} but this host is linux/{}; use an image with a linux/{} variant",
+            name, architecture, host, host));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_selection() {
+        let entries: Vec<Platform> = serde_json::from_str(r#"[
+            {"architecture":"arm","os":"linux","variant":"v7"},
+            {"architecture":"arm64","os":"linux","variant":"v8"},
+            {"architecture":"amd64","os":"windows"},
+            {"architecture":"amd64","os":"linux"}]"#).unwrap();
+        let select_for = |architecture: &str| select(&entries, architecture, |platform| Some(platform));
+        assert_eq!(select_for("amd64").map(|platform| platform.os.as_str()), Some("linux"));
+        assert_eq!(select_for("arm64").and_then(|platform| platform.variant.as_deref()), Some("v8"));
+        assert_eq!(select_for("s390x"), None);
+        assert_eq!(describe(&entries, |platform| Some(platform)), "linux/arm/v7, linux/arm64/v8, windows/amd64, linux/amd64");
+
+        assert_eq!(normalize_architecture("x86_64"), "amd64");
+        assert!(check_architecture("app", None).is_ok());
+        assert!(check_architecture("app", Some(host_architecture())).is_ok());
+        let other = if host_architecture() == "amd64" { "aarch64" } else { "x86_64" };
+        let error = check_architecture("app:1", Some(other)).unwrap_err();
+        assert!(error.contains("app:1") && error.contains(host_architecture()));
+    }
+}
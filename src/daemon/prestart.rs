@@ -0,0 +1,280 @@
+// Pre-start hooks
+// Commands run after a container's namespaces, mounts, root and cgroups are in place but before
+// its command execs, like OCI createRuntime/createContainer hooks: either in the host namespaces
+// or inside the container's namespaces and root. The container process waits at a gate (two
+// pipes) until every hook succeeded; a failing hook fails the start. Hooks get the OCI state
+// document on stdin and QUILT_CONTAINER_ID, QUILT_CONTAINER_PID and QUILT_ROOTFS in their
+// environment.
+
+use crate::daemon::hooks::{DEFAULT_HOOK_TIMEOUT_SECS, MAX_HOOK_TIMEOUT_SECS};
+use crate::utils::process::ProcessUtils;
+
+use nix::fcntl::OFlag;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::unistd::{close, pipe2, read, write, Pid};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Time the container process may take to reach the gate
+const GATE_ARRIVAL_TIMEOUT: Duration = Duration::from_secs(30);
+/// OCI runtime spec version of the state document hooks receive
+const OCI_VERSION: &str = "1.0.2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookNamespace {
+    /// The daemon's namespaces; for plumbing done from the host (veths, routes, devices)
+    Host,
+    /// The container's mount, UTS, IPC and network namespaces and its root directory
+    Container,
+}
+
+impl HookNamespace {
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "" | "host" => Ok(HookNamespace::Host),
+            "container" => Ok(HookNamespace::Container),
+            other => Err(format!("Unknown pre-start hook namespace '{}' (expected host or container)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookNamespace::Host => "host",
+            HookNamespace::Container => "container",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrestartHook {
+    /// Run with `/bin/sh -c`; inside the container's root for [`HookNamespace::Container`]
+    pub command: String,
+    pub namespace: HookNamespace,
+    pub timeout_secs: u32,
+}
+
+impl PrestartHook {
+    /// Build a hook from API fields; a timeout of 0 means [`DEFAULT_HOOK_TIMEOUT_SECS`]
+    pub fn new(command: &str, namespace: &str, timeout_secs: u32) -> Result<Self, String> {
+        if command.trim().is_empty() {
+            return Err("Pre-start hook command must not be empty".to_string());
+        }
+        if timeout_secs > MAX_HOOK_TIMEOUT_SECS {
+            return Err(format!("Pre-start hook timeout must be at most {}s", MAX_HOOK_TIMEOUT_SECS));
+        }
+        Ok(Self {
+            command: command.to_string(),
+            namespace: HookNamespace::from_str(namespace.trim())?,
+            timeout_secs: if timeout_secs == 0 { DEFAULT_HOOK_TIMEOUT_SECS } else { timeout_secs },
+        })
+    }
+}
+
+/// The OCI state document of a container whose process exists but has not exec'd yet
+pub fn hook_state(container_id: &str, pid: Pid, rootfs_path: &str) -> serde_json::Value {
+    serde_json::json!({
+        "ociVersion": OCI_VERSION,
+        "id": container_id,
+        "status": "creating",
+        "pid": ProcessUtils::pid_to_i32(pid),
+        "bundle": rootfs_path,
+        "annotations": {},
+    })
+}
+
+/// Pipes the container process waits on before it execs. Created close-on-exec.
+pub struct StartGate {
+    arrived: (OwnedFd, OwnedFd),
+    release: (OwnedFd, OwnedFd),
+}
+
+/// The gate's file descriptors as seen by the forked container process
+#[derive(Debug, Clone, Copy)]
+pub struct GateFds {
+    arrived: RawFd,
+    release: RawFd,
+    // The parent's ends, closed in the child so either side sees the other exit
+    parent_ends: (RawFd, RawFd),
+}
+
+/// The daemon's side of a gate once the container process is forked. Dropping it without
+/// [`ParentGate::release`] stops the container process at the gate.
+pub struct ParentGate {
+    arrived: OwnedFd,
+    release: OwnedFd,
+}
+
+impl StartGate {
+    pub fn new() -> Result<Self, String> {
+        let pipe = || pipe2(OFlag::O_CLOEXEC)
+            .map(|(read, write)| unsafe { (OwnedFd::from_raw_fd(read), OwnedFd::from_raw_fd(write)) })
+            .map_err(|e| format!("Failed to create start gate: {}", e));
+        Ok(Self { arrived: pipe()?, release: pipe()? })
+    }
+
+    pub fn child_fds(&self) -> GateFds {
+        GateFds {
+            arrived: self.arrived.1.as_raw_fd(),
+            release: self.release.0.as_raw_fd(),
+            parent_ends: (self.arrived.0.as_raw_fd(), self.release.1.as_raw_fd()),
+        }
+    }
+
+    /// Keep the daemon's ends after the fork and close the child's
+    pub fn into_parent(self) -> ParentGate {
+        ParentGate { arrived: self.arrived.0, release: self.release.1 }
+    }
+}
+
+impl ParentGate {
+    /// Wait until the container process reached the gate
+    pub fn wait_for_child(&self, timeout: Duration) -> Result<(), String> {
+        let mut fds = [PollFd::new(self.arrived.as_raw_fd(), PollFlags::POLLIN)];
+        let ready = poll(&mut fds, timeout.as_millis() as i32)
+            .map_err(|e| format!("Failed to wait for the container process: {}", e))?;
+        if ready == 0 {
+            return Err(format!("Container process did not reach its pre-start hooks within {:?}", timeout));
+        }
+        let mut byte = [0u8; 1];
+        match read(self.arrived.as_raw_fd(), &mut byte) {
+            Ok(1) => Ok(()),
+            Ok(_) => Err("Container process exited before its pre-start hooks ran".to_string()),
+            Err(e) => Err(format!("Failed to wait for the container process: {}", e)),
+        }
+    }
+
+    /// Let the container process continue to its command
+    pub fn release(self) -> Result<(), String> {
+        write(self.release.as_raw_fd(), &[1])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to release the container process: {}", e))
+    }
+}
+
+/// In the container process: report arrival and block until the daemon releases the gate.
+/// Fails when the daemon gave up, e.g. because a hook failed.
+pub fn wait_at_gate(fds: GateFds) -> Result<(), String> {
+    let _ = close(fds.parent_ends.0);
+    let _ = close(fds.parent_ends.1);
+    write(fds.arrived, &[1]).map_err(|e| format!("Failed to signal the daemon: {}", e))?;
+    let _ = close(fds.arrived);
+
+    let mut byte = [0u8; 1];
+    let released = loop {
+        match read(fds.release, &mut byte) {
+            Ok(n) => break n == 1,
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(format!("Failed to wait for pre-start hooks: {}", e)),
+        }
+    };
+    let _ = close(fds.release);
+    if released {
+        Ok(())
+    } else {
+        Err("Pre-start hooks did not complete; not starting the container command".to_string())
+    }
+}
+
+/// Wait for the container process at the gate, run `hooks` in order and release it. Returns the
+/// description of each completed hook for the container's log.
+pub fn run_prestart_hooks(gate: ParentGate, hooks: &[PrestartHook], container_id: &str, pid: Pid, rootfs_path: &str) -> Result<Vec<String>, String> {
+    gate.wait_for_child(GATE_ARRIVAL_TIMEOUT)?;
+    let mut completed = Vec::with_capacity(hooks.len());
+    for hook in hooks {
+        run_prestart_hook(hook, container_id, pid, rootfs_path)
+            .map_err(|e| format!("{} pre-start hook '{}' {}", hook.namespace.as_str(), hook.command, e))?;
+        completed.push(format!("{} pre-start hook '{}' completed", hook.namespace.as_str(), hook.command));
+    }
+    gate.release()?;
+    Ok(completed)
+}
+
+/// Run one hook to completion, killing it at its timeout
+pub fn run_prestart_hook(hook: &PrestartHook, container_id: &str, pid: Pid, rootfs_path: &str) -> Result<(), String> {
+    let mut command = match hook.namespace {
+        HookNamespace::Host => Command::new("/bin/sh"),
+        HookNamespace::Container => {
+            // -r and -w take the container process's root and working directory
+            let mut command = Command::new("nsenter");
+            command.arg("-t").arg(ProcessUtils::pid_to_i32(pid).to_string())
+                .args(["-m", "-u", "-i", "-n", "-r", "-w", "--", "/bin/sh"]);
+            command
+        }
+    };
+    command.arg("-c").arg(&hook.command)
+        .env("QUILT_CONTAINER_ID", container_id)
+        .env("QUILT_CONTAINER_PID", ProcessUtils::pid_to_i32(pid).to_string())
+        .env("QUILT_ROOTFS", rootfs_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| format!("failed to run: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that does not read its state closes stdin early; that is not an error
+        let _ = stdin.write_all(hook_state(container_id, pid, rootfs_path).to_string().as_bytes());
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(hook.timeout_secs as u64);
+    let status = loop {
+        match child.try_wait().map_err(|e| format!("failed to wait: {}", e))? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", hook.timeout_secs));
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+    let reason = stderr.trim().lines().last().unwrap_or_default();
+    Err(match status.code() {
+        Some(code) if reason.is_empty() => format!("exited with code {}", code),
+        Some(code) => format!("exited with code {}: {}", code, reason),
+        None => "was killed by a signal".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prestart_hook_parsing() {
+        let hook = PrestartHook::new("ip link add dummy0 type dummy", "", 0).unwrap();
+        assert_eq!(hook.namespace, HookNamespace::Host);
+        assert_eq!(hook.timeout_secs, DEFAULT_HOOK_TIMEOUT_SECS);
+        assert_eq!(PrestartHook::new("license-setup", "container", 5).unwrap().namespace, HookNamespace::Container);
+        assert!(PrestartHook::new(" ", "host", 0).is_err());
+        assert!(PrestartHook::new("true", "vm", 0).is_err());
+        assert!(PrestartHook::new("true", "host", MAX_HOOK_TIMEOUT_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn test_start_gate() {
+        let gate = StartGate::new().unwrap();
+        let fds = gate.child_fds();
+        // Stand-in for the forked container process
+        let child = std::thread::spawn(move || {
+            write(fds.arrived, &[1]).unwrap();
+            let mut byte = [0u8; 1];
+            read(fds.release, &mut byte).unwrap()
+        });
+        let StartGate { arrived, release } = gate;
+        let parent = ParentGate { arrived: arrived.0, release: release.1 };
+        parent.wait_for_child(Duration::from_secs(5)).unwrap();
+        parent.release().unwrap();
+        assert_eq!(child.join().unwrap(), 1);
+        drop((arrived.1, release.0));
+    }
+}
@@ -0,0 +1,170 @@
+// Container priority classes
+// Every container is critical, normal (the default) or best-effort. The class decides who waits
+// and who gives way: queued starts are served highest class first, a start that would exceed the
+// admission limits evicts running containers of a lower class (best-effort first), and a drain
+// stops containers lowest class first.
+
+use crate::utils::console::ConsoleLogger;
+use serde::{Deserialize, Serialize};
+
+/// Environment variable setting admission limits, as comma-separated `<key>=<value>` pairs:
+/// `max_running=<containers>` and `min_available_mb=<host MemAvailable floor>`. Unset means no
+/// limits, so starts never evict.
+pub const ADMISSION_ENV: &str = "QUILT_ADMISSION";
+
+/// Declared lowest to highest so the derived ordering ranks classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
+pub enum PriorityClass {
+    /// First to be evicted or drained; never evicts anything itself
+    BestEffort,
+    #[default]
+    Normal,
+    /// Evicted by nothing; drained last
+    Critical,
+}
+
+impl PriorityClass {
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "" | "normal" => Ok(PriorityClass::Normal),
+            "critical" => Ok(PriorityClass::Critical),
+            "best-effort" | "besteffort" => Ok(PriorityClass::BestEffort),
+            other => Err(format!("Unknown priority class '{}' (expected critical, normal or best-effort)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriorityClass::BestEffort => "best-effort",
+            PriorityClass::Normal => "normal",
+            PriorityClass::Critical => "critical",
+        }
+    }
+}
+
+/// Limits a start must fit in; a start that does not evicts lower classes first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdmissionPolicy {
+    /// Running (and starting) containers allowed at once
+    pub max_running: Option<usize>,
+    /// Host MemAvailable, in MiB, that must remain when a container starts
+    pub min_available_mb: Option<u64>,
+}
+
+impl AdmissionPolicy {
+    /// The policy from `QUILT_ADMISSION`; None when unset or empty
+    pub fn from_env() -> Option<Self> {
+        std::env::var(ADMISSION_ENV).ok().and_then(|value| Self::parse(&value))
+    }
+
+    /// Parse `max_running=40,min_available_mb=512`; malformed entries are skipped with a warning
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut policy = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let applied = entry.split_once('=').and_then(|(key, value)| {
+                let value = value.trim();
+                match key.trim() {
+                    "max_running" => policy.max_running = Some(value.parse().ok().filter(|v: &usize| *v > 0)?),
+                    "min_available_mb" => policy.min_available_mb = Some(value.parse().ok()?),
+                    _ => return None,
+                }
+                Some(())
+            });
+            if applied.is_none() {
+                ConsoleLogger::warning(&format!("Ignoring malformed {} entry: {}", ADMISSION_ENV, entry));
+            }
+        }
+        (policy != Self::default()).then_some(policy)
+    }
+
+    /// Why a start does not fit with `running` other containers and `available_mb` host memory
+    /// available, or None when it does
+    pub fn pressure(&self, running: usize, available_mb: Option<u64>) -> Option<String> {
+        if let Some(max) = self.max_running {
+            if running >= max {
+                return Some(format!("{} containers running (limit {})", running, max));
+            }
+        }
+        match (self.min_available_mb, available_mb) {
+            (Some(floor), Some(available)) if available < floor => {
+                Some(format!("{} MiB host memory available (floor {} MiB)", available, floor))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A running container that may be evicted or drained
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningContainer {
+    pub id: String,
+    pub class: PriorityClass,
+    pub started_at: i64,
+}
+
+/// Containers a start of class `requester` may evict, in eviction order: lowest class first and,
+/// within a class, the most recently started first (the least work lost)
+pub fn eviction_order(requester: PriorityClass, running: &[RunningContainer]) -> Vec<String> {
+    drain_order(running.iter().filter(|container| container.class < requester).cloned().collect())
+}
+
+/// Order in which `containers` are stopped by a drain: lowest class first, most recently started
+/// first within a class
+pub fn drain_order(mut containers: Vec<RunningContainer>) -> Vec<String> {
+    containers.sort_by(|a, b| a.class.cmp(&b.class).then(b.started_at.cmp(&a.started_at)));
+    containers.into_iter().map(|container| container.id).collect()
+}
+
+/// Host MemAvailable in MiB from /proc/meminfo
+pub fn host_available_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb / 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running(id: &str, class: PriorityClass, started_at: i64) -> RunningContainer {
+        RunningContainer { id: id.to_string(), class, started_at }
+    }
+
+    #[test]
+    fn test_priority_class_parsing() {
+        assert_eq!(PriorityClass::from_str("").unwrap(), PriorityClass::Normal);
+        assert_eq!(PriorityClass::from_str("Best_Effort").unwrap(), PriorityClass::BestEffort);
+        assert_eq!(PriorityClass::from_str("critical").unwrap().as_str(), "critical");
+        assert!(PriorityClass::from_str("urgent").is_err());
+        assert!(PriorityClass::Critical > PriorityClass::Normal && PriorityClass::Normal > PriorityClass::BestEffort);
+    }
+
+    #[test]
+    fn test_admission_policy() {
+        assert_eq!(AdmissionPolicy::parse(""), None);
+        let policy = AdmissionPolicy::parse("max_running=2, min_available_mb=512, bogus=1").unwrap();
+        assert_eq!(policy.max_running, Some(2));
+        assert!(policy.pressure(1, Some(1024)).is_none());
+        assert!(policy.pressure(2, Some(1024)).is_some());
+        assert!(policy.pressure(0, Some(100)).is_some());
+        // Unknown available memory does not block starts
+        assert!(policy.pressure(0, None).is_none());
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        let containers = vec![
+            running("web", PriorityClass::Normal, 100),
+            running("batch-old", PriorityClass::BestEffort, 50),
+            running("db", PriorityClass::Critical, 10),
+            running("batch-new", PriorityClass::BestEffort, 200),
+        ];
+        assert_eq!(eviction_order(PriorityClass::Critical, &containers), vec!["batch-new", "batch-old", "web"]);
+        assert_eq!(eviction_order(PriorityClass::Normal, &containers), vec!["batch-new", "batch-old"]);
+        assert!(eviction_order(PriorityClass::BestEffort, &containers).is_empty());
+        assert_eq!(drain_order(containers), vec!["batch-new", "batch-old", "web", "db"]);
+    }
+}
@@ -0,0 +1,126 @@
+// Kernel filesystem mounts
+// Every container gets a fresh /proc and /sys from its own mount namespace, whatever the image
+// shipped in those directories. By default /proc hides processes of other users (hidepid) and
+// has its kernel tunables read-only and its most sensitive files masked, and /sys is read-only.
+// Privileged containers get both as the host would mount them, plus the host's /dev.
+
+use crate::daemon::idmap;
+use crate::utils::console::ConsoleLogger;
+use nix::mount::{mount, MsFlags};
+use std::path::Path;
+
+/// Kernel interfaces a container may read but not change
+pub const PROC_READONLY_PATHS: &[&str] = &["/proc/sys", "/proc/sysrq-trigger", "/proc/irq", "/proc/bus", "/proc/fs"];
+
+/// Kernel interfaces hidden from a container: files are covered with /dev/null, directories with
+/// an empty read-only tmpfs
+pub const PROC_MASKED_PATHS: &[&str] = &[
+    "/proc/kcore",
+    "/proc/keys",
+    "/proc/timer_list",
+    "/proc/sched_debug",
+    "/proc/acpi",
+    "/proc/scsi",
+];
+
+const PSEUDO_FS_FLAGS: MsFlags = MsFlags::MS_NOSUID.union(MsFlags::MS_NOEXEC).union(MsFlags::MS_NODEV);
+
+/// Mount data for /proc: processes of other users are invisible unless privileged
+fn proc_mount_data(privileged: bool) -> Option<&'static str> {
+    if privileged { None } else { Some("hidepid=2") }
+}
+
+fn sys_mount_flags(privileged: bool) -> MsFlags {
+    if privileged { PSEUDO_FS_FLAGS } else { PSEUDO_FS_FLAGS | MsFlags::MS_RDONLY }
+}
+
+/// Mount a fresh procfs at `<rootfs>/proc`, hardened unless `privileged`. Must run inside the
+/// container's mount namespace, before chroot.
+pub fn mount_proc(rootfs_path: &str, privileged: bool) -> Result<(), String> {
+    let target = format!("{}/proc", rootfs_path);
+    std::fs::create_dir_all(&target).map_err(|e| format!("Failed to create {}: {}", target, e))?;
+
+    let data = proc_mount_data(privileged);
+    let mounted = mount(Some("proc"), target.as_str(), Some("proc"), PSEUDO_FS_FLAGS, data).or_else(|e| {
+        let Some(data) = data else { return Err(e) };
+        // Kernels that reject hidepid for this mount still get the masked, read-only layout
+        ConsoleLogger::warning(&format!("Failed to mount /proc with {}: {}; mounting without it", data, e));
+        mount(Some("proc"), target.as_str(), Some("proc"), PSEUDO_FS_FLAGS, None::<&str>)
+    });
+    if let Err(e) = mounted {
+        // In a user namespace a fresh procfs needs a PID namespace that namespace owns, which
+        // unshare does not give the calling process; the daemon's /proc is bound instead
+        if !idmap::in_user_namespace() {
+            return Err(format!("Failed to mount /proc in container: {}", e));
+        }
+        ConsoleLogger::warning(&format!("Failed to mount a fresh /proc in the user namespace: {}; binding the host's", e));
+        mount(Some("/proc"), target.as_str(), None::<&str>, MsFlags::MS_BIND | MsFlags::MS_REC, None::<&str>)
+            .map_err(|e| format!("Failed to mount /proc in container: {}", e))?;
+    }
+    if privileged {
+        return Ok(());
+    }
+
+    for path in PROC_READONLY_PATHS {
+        let path = format!("{}{}", rootfs_path, path);
+        if !Path::new(&path).exists() {
+            continue;
+        }
+        // A bind mount only takes MS_RDONLY on a remount of itself
+        mount(Some(path.as_str()), path.as_str(), None::<&str>, MsFlags::MS_BIND | MsFlags::MS_REC, None::<&str>)
+            .and_then(|_| mount(None::<&str>, path.as_str(), None::<&str>,
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | PSEUDO_FS_FLAGS, None::<&str>))
+            .map_err(|e| format!("Failed to make {} read-only: {}", path, e))?;
+    }
+    for path in PROC_MASKED_PATHS {
+        let path = format!("{}{}", rootfs_path, path);
+        let masked = if Path::new(&path).is_dir() {
+            mount(Some("tmpfs"), path.as_str(), Some("tmpfs"), PSEUDO_FS_FLAGS | MsFlags::MS_RDONLY, Some("size=0"))
+        } else if Path::new(&path).exists() {
+            mount(Some("/dev/null"), path.as_str(), None::<&str>, MsFlags::MS_BIND, None::<&str>)
+        } else {
+            continue;
+        };
+        masked.map_err(|e| format!("Failed to mask {}: {}", path, e))?;
+    }
+    ConsoleLogger::debug("Mounted /proc with hidepid, read-only kernel tunables and masked kernel interfaces");
+    Ok(())
+}
+
+/// Mount a fresh sysfs at `<rootfs>/sys`, read-only unless `privileged`
+pub fn mount_sys(rootfs_path: &str, privileged: bool) -> Result<(), String> {
+    let target = format!("{}/sys", rootfs_path);
+    std::fs::create_dir_all(&target).map_err(|e| format!("Failed to create {}: {}", target, e))?;
+    mount(Some("sysfs"), target.as_str(), Some("sysfs"), sys_mount_flags(privileged), None::<&str>)
+        .map_err(|e| format!("Failed to mount /sys in container: {}", e))
+}
+
+/// Bind the host's /dev, with every device node it holds, over `<rootfs>/dev`. Only for
+/// privileged containers; must run before /dev/pts gets its own instance.
+pub fn mount_host_dev(rootfs_path: &str) -> Result<(), String> {
+    let target = format!("{}/dev", rootfs_path);
+    std::fs::create_dir_all(&target).map_err(|e| format!("Failed to create {}: {}", target, e))?;
+    mount(Some("/dev"), target.as_str(), None::<&str>, MsFlags::MS_BIND | MsFlags::MS_REC, None::<&str>)
+        .map_err(|e| format!("Failed to bind host /dev into container: {}", e))?;
+    // Host mounts under /dev (e.g. /dev/shm) came along with the recursive bind; keep them from
+    // propagating back out
+    mount(None::<&str>, target.as_str(), None::<&str>, MsFlags::MS_REC | MsFlags::MS_SLAVE, None::<&str>)
+        .map_err(|e| format!("Failed to make container /dev a slave mount: {}", e))?;
+    ConsoleLogger::debug("Mounted host /dev in privileged container");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hardened_defaults() {
+        assert_eq!(proc_mount_data(false), Some("hidepid=2"));
+        assert_eq!(proc_mount_data(true), None);
+        assert!(sys_mount_flags(false).contains(MsFlags::MS_RDONLY));
+        assert!(!sys_mount_flags(true).contains(MsFlags::MS_RDONLY));
+        assert!(sys_mount_flags(true).contains(MsFlags::MS_NOSUID | MsFlags::MS_NODEV));
+        assert!(PROC_READONLY_PATHS.iter().chain(PROC_MASKED_PATHS).all(|path| path.starts_with("/proc/")));
+    }
+}
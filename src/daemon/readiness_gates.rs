@@ -0,0 +1,177 @@
+// Readiness gates
+// Conditions a container must meet after its process started before it is reported Running: a
+// TCP port accepting connections, an HTTP endpoint answering 200, a file existing in its root, or
+// a line of its output matching a regex. Gates are checked until all have passed or the gate
+// timeout runs out, in which case the start fails.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Time gates get when the container does not set one
+pub const DEFAULT_READINESS_TIMEOUT_SECS: u32 = 60;
+pub const MAX_READINESS_TIMEOUT_SECS: u32 = 3600;
+/// Bound on a single TCP or HTTP attempt, so a hung peer does not eat the whole gate timeout
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReadinessGate {
+    /// A connection to the port on the container's address succeeds
+    Tcp { port: u16 },
+    /// GET of the path on the container's address answers 200
+    Http { port: u16, path: String },
+    /// The path exists inside the container's root
+    File { path: String },
+    /// A line of the container's stdout or stderr matches the regex
+    LogLine { pattern: String },
+}
+
+impl ReadinessGate {
+    /// Build a gate from API fields: kind tcp, http, file or log and its target
+    /// (`8080`, `8080/healthz`, `/run/app.ready`, `listening on .*`)
+    pub fn new(kind: &str, target: &str) -> Result<Self, String> {
+        let target = target.trim();
+        match kind.trim() {
+            "tcp" => Ok(ReadinessGate::Tcp { port: parse_port(target)? }),
+            "http" => {
+                let (port, path) = match target.split_once('/') {
+                    Some((port, path)) => (port, format!("/{}", path)),
+                    None => (target, "/".to_string()),
+                };
+                if path.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                    return Err(format!("Invalid HTTP readiness path '{}'", path));
+                }
+                Ok(ReadinessGate::Http { port: parse_port(port)?, path })
+            }
+            "file" => {
+                let path = Path::new(target);
+                if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                    return Err(format!("File readiness path must be absolute without '..': '{}'", target));
+                }
+                Ok(ReadinessGate::File { path: target.to_string() })
+            }
+            "log" => {
+                if target.is_empty() {
+                    return Err("Log readiness pattern must not be empty".to_string());
+                }
+                Regex::new(target).map_err(|e| format!("Invalid log readiness pattern '{}': {}", target, e))?;
+                Ok(ReadinessGate::LogLine { pattern: target.to_string() })
+            }
+            other => Err(format!("Unknown readiness gate '{}' (expected tcp, http, file or log)", other)),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReadinessGate::Tcp { .. } => "tcp",
+            ReadinessGate::Http { .. } => "http",
+            ReadinessGate::File { .. } => "file",
+            ReadinessGate::LogLine { .. } => "log",
+        }
+    }
+
+    pub fn target(&self) -> String {
+        match self {
+            ReadinessGate::Tcp { port } => port.to_string(),
+            ReadinessGate::Http { port, path } => format!("{}{}", port, path),
+            ReadinessGate::File { path } => path.clone(),
+            ReadinessGate::LogLine { pattern } => pattern.clone(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{}:{}", self.kind(), self.target())
+    }
+}
+
+/// The gates of a container and how long they may take together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessGates {
+    pub gates: Vec<ReadinessGate>,
+    pub timeout_secs: u32,
+}
+
+impl ReadinessGates {
+    /// A timeout of 0 means [`DEFAULT_READINESS_TIMEOUT_SECS`]
+    pub fn new(gates: Vec<ReadinessGate>, timeout_secs: u32) -> Result<Self, String> {
+        if timeout_secs > MAX_READINESS_TIMEOUT_SECS {
+            return Err(format!("Readiness timeout must be at most {}s", MAX_READINESS_TIMEOUT_SECS));
+        }
+        let timeout_secs = if timeout_secs == 0 { DEFAULT_READINESS_TIMEOUT_SECS } else { timeout_secs };
+        Ok(Self { gates, timeout_secs })
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs as u64)
+    }
+}
+
+fn parse_port(s: &str) -> Result<u16, String> {
+    s.parse::<u16>().ok()
+        .filter(|port| *port > 0)
+        .ok_or_else(|| format!("Invalid readiness port '{}'", s))
+}
+
+/// Whether something accepts connections on `address:port`
+pub async fn tcp_open(address: &str, port: u16) -> bool {
+    matches!(tokio::time::timeout(ATTEMPT_TIMEOUT, TcpStream::connect((address, port))).await, Ok(Ok(_)))
+}
+
+/// Whether `GET path` on `address:port` answers 200
+pub async fn http_ok(address: &str, port: u16, path: &str) -> bool {
+    let attempt = async {
+        let mut stream = TcpStream::connect((address, port)).await.ok()?;
+        let request = format!("GET {} HTTP/1.0\r\nHost: {}:{}\r\nUser-Agent: quilt-readiness\r\nConnection: close\r\n\r\n", path, address, port);
+        stream.write_all(request.as_bytes()).await.ok()?;
+        // The status line fits in the first read of any real server's answer
+        let mut head = vec![0u8; 512];
+        let read = stream.read(&mut head).await.ok()?;
+        http_status(&String::from_utf8_lossy(&head[..read]))
+    };
+    matches!(tokio::time::timeout(ATTEMPT_TIMEOUT, attempt).await, Ok(Some(200)))
+}
+
+/// Status code from the status line of an HTTP response
+pub fn http_status(response: &str) -> Option<u16> {
+    let mut parts = response.lines().next()?.split_whitespace();
+    parts.next().filter(|version| version.starts_with("HTTP/"))?;
+    parts.next()?.parse().ok()
+}
+
+/// Whether `path` exists below the container root `rootfs_path`
+pub fn file_exists(rootfs_path: &str, path: &str) -> bool {
+    Path::new(rootfs_path).join(path.trim_start_matches('/')).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate_parsing() {
+        assert_eq!(ReadinessGate::new("tcp", "5432").unwrap(), ReadinessGate::Tcp { port: 5432 });
+        assert_eq!(ReadinessGate::new("http", "8080/healthz").unwrap(),
+            ReadinessGate::Http { port: 8080, path: "/healthz".to_string() });
+        assert_eq!(ReadinessGate::new("http", "80").unwrap().describe(), "http:80/");
+        assert_eq!(ReadinessGate::new("log", "listening on \\d+").unwrap().kind(), "log");
+        assert!(ReadinessGate::new("tcp", "0").is_err());
+        assert!(ReadinessGate::new("file", "run/app.ready").is_err());
+        assert!(ReadinessGate::new("file", "/run/../../etc/passwd").is_err());
+        assert!(ReadinessGate::new("log", "(unclosed").is_err());
+        assert!(ReadinessGate::new("exec", "true").is_err());
+
+        assert_eq!(ReadinessGates::new(vec![], 0).unwrap().timeout_secs, DEFAULT_READINESS_TIMEOUT_SECS);
+        assert!(ReadinessGates::new(vec![], MAX_READINESS_TIMEOUT_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn test_http_status() {
+        assert_eq!(http_status("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"), Some(200));
+        assert_eq!(http_status("HTTP/1.0 503 Service Unavailable\r\n"), Some(503));
+        assert_eq!(http_status("SSH-2.0-OpenSSH_9.6\r\n"), None);
+        assert_eq!(http_status(""), None);
+    }
+}
@@ -0,0 +1,714 @@
+// Registry image pulls
+// Images can be pulled straight from an OCI or Docker registry by reference (`alpine:3.19`,
+// `ghcr.io/org/app:v2`, `repo@sha256:...`). The manifest is resolved for the host's platform,
+// an image whose config names another architecture is refused before its layers are fetched,
+// every blob is downloaded with curl and checked against its sha256 digest, and the result is
+// written as a `docker save` archive in the image store. Containers are then created from that
+// archive like from any other: layers, whiteouts and the image config go through
+// `docker_archive::import`, and the warm pool and image scans key on its path. Registries are
+// used anonymously, with a bearer token fetched whenever one asks for it; localhost registries
+// are spoken to over plain HTTP.
+
+use crate::daemon::image_config::ImageConfig;
+use crate::daemon::platform::{self, Platform};
+use crate::utils::command::{Argv, CommandExecutor};
+use crate::utils::console::ConsoleLogger;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Environment variable overriding the image store directory
+pub const IMAGE_STORE_ENV: &str = "QUILT_IMAGE_DIR";
+pub const DEFAULT_IMAGE_STORE_DIR: &str = "/var/lib/quilt/images";
+
+/// Registry host behind `docker.io` references
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+const DOCKER_HUB_NAME: &str = "docker.io";
+const DEFAULT_TAG: &str = "latest";
+
+/// How long a manifest or token request may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long one blob download may take
+const BLOB_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Manifest types we can read, image indexes first
+const MANIFEST_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+
+/// A parsed image reference, normalized the way Docker does: Docker Hub when no registry is
+/// named, `library/` for its official images and `latest` when neither tag nor digest is given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    /// Registry host, with port if any
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+    /// `sha256:<hex>`; takes precedence over the tag
+    pub digest: Option<String>,
+}
+
+impl ImageReference {
+    pub fn parse(reference: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid image reference '{}': {}", reference, reason);
+        if reference.is_empty() || reference.chars().any(|c| c.is_whitespace()) {
+            return Err(invalid("must be non-empty and without spaces"));
+        }
+
+        let (name, digest) = match reference.rsplit_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_string())),
+            None => (reference, None),
+        };
+        if let Some(digest) = &digest {
+            validate_digest(digest).map_err(|e| invalid(&e))?;
+        }
+        // A tag follows the last colon, unless that colon belongs to a registry port
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag.to_string())),
+            _ => (name, None),
+        };
+        if let Some(tag) = &tag {
+            let valid = !tag.is_empty() && tag.len() <= 128
+                && tag.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+                && !tag.starts_with(['.', '-']);
+            if !valid {
+                return Err(invalid("tags are 1-128 letters, digits, '_', '.' or '-'"));
+            }
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, rest)) if host.contains(['.', ':']) || host == "localhost" => (host.to_string(), rest.to_string()),
+            _ => (DOCKER_HUB_REGISTRY.to_string(), name.to_string()),
+        };
+        let registry = if registry == DOCKER_HUB_NAME || registry == "index.docker.io" {
+            DOCKER_HUB_REGISTRY.to_string()
+        } else {
+            registry
+        };
+        // The registry names a directory of the image store, so it must be a plain host[:port]
+        if !valid_registry(&registry) {
+            return Err(invalid("registries are a host name or address with an optional port"));
+        }
+        let repository = if registry == DOCKER_HUB_REGISTRY && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+        let valid_component = |component: &str| !component.is_empty()
+            && component.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c))
+            && component.starts_with(|c: char| c.is_ascii_alphanumeric());
+        if !repository.split('/').all(valid_component) {
+            return Err(invalid("repository names are lowercase letters, digits, '.', '_', '-' and '/'"));
+        }
+
+        let tag = if tag.is_none() && digest.is_none() { Some(DEFAULT_TAG.to_string()) } else { tag };
+        Ok(ImageReference { registry, repository, tag, digest })
+    }
+
+    /// What to ask the registry for: the digest when pinned, else the tag
+    pub fn manifest_reference(&self) -> &str {
+        self.digest.as_deref().or(self.tag.as_deref()).unwrap_or(DEFAULT_TAG)
+    }
+
+    fn base_url(&self) -> String {
+        let host = self.registry.split(':').next().unwrap_or_default();
+        let scheme = if host == "localhost" || host.starts_with("127.") { "http" } else { "https" };
+        format!("{}://{}/v2/{}", scheme, self.registry, self.repository)
+    }
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registry = if self.registry == DOCKER_HUB_REGISTRY { DOCKER_HUB_NAME } else { self.registry.as_str() };
+        write!(f, "{}/{}", registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// `host[:port]` with dot-separated host labels of letters, digits and inner '-'
+fn valid_registry(registry: &str) -> bool {
+    let (host, port) = match registry.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (registry, None),
+    };
+    let valid_label = |label: &str| !label.is_empty() && label.len() <= 63
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !label.starts_with('-') && !label.ends_with('-');
+    host.split('.').all(valid_label)
+        && port.map_or(true, |port| !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn validate_digest(digest: &str) -> Result<(), String> {
+    match digest.strip_prefix("sha256:") {
+        Some(hex) if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()) => Ok(()),
+        Some(_) => Err(format!("invalid sha256 digest '{}'", digest)),
+        None => Err(format!("unsupported digest '{}': only sha256 is supported", digest)),
+    }
+}
+
+/// Whether `image` names a tarball rather than a registry reference: an existing file, an
+/// absolute or relative path, or a tar file name
+pub fn is_image_file(image: &str) -> bool {
+    Path::new(image).exists()
+        || image.starts_with(['/', '.'])
+        || [".tar", ".tar.gz", ".tgz"].iter().any(|suffix| image.ends_with(suffix))
+}
+
+/// The image store from `QUILT_IMAGE_DIR`, falling back to the default
+pub fn image_store_dir() -> PathBuf {
+    std::env::var(IMAGE_STORE_ENV).ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_IMAGE_STORE_DIR))
+}
+
+/// Where the archive of `reference` lives in `store`: one file per registry, repository and tag
+/// or digest
+pub fn archive_path(store: &Path, reference: &ImageReference) -> PathBuf {
+    let file = reference.digest.as_deref().or(reference.tag.as_deref()).unwrap_or(DEFAULT_TAG).replace(':', "-");
+    store.join(&reference.registry).join(&reference.repository).join(format!("{}.tar", file))
+}
+
+fn record_path(archive: &Path) -> PathBuf {
+    archive.with_extension("json")
+}
+
+/// An image in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PulledImage {
+    /// Normalized reference, e.g. `docker.io/library/alpine:3.19`
+    pub reference: String,
+    /// Digest of the platform manifest that was pulled
+    pub digest: String,
+    /// The `docker save` archive containers are created from
+    pub image_path: String,
+    pub layers: usize,
+    /// Compressed layer bytes
+    pub size_bytes: u64,
+    /// Unix seconds
+    pub pulled_at: i64,
+    /// Already in the store; nothing was downloaded
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+/// The stored image of `reference`, if it was pulled before and its archive is still there
+pub fn find_pulled(store: &Path, reference: &ImageReference) -> Option<PulledImage> {
+    let archive = archive_path(store, reference);
+    if !archive.is_file() {
+        return None;
+    }
+    let record = fs::read_to_string(record_path(&archive)).ok()?;
+    let mut image: PulledImage = serde_json::from_str(&record).ok()?;
+    image.cached = true;
+    Some(image)
+}
+
+/// Pull `reference` into `store` unless it is there already (or `force` is set)
+pub async fn pull(store: &Path, reference: &ImageReference, force: bool) -> Result<PulledImage, String> {
+    if !force {
+        if let Some(image) = find_pulled(store, reference) {
+            return Ok(image);
+        }
+    }
+    let archive = archive_path(store, reference);
+    let parent = archive.parent().ok_or_else(|| format!("Invalid image store path {}", archive.display()))?;
+    fs::create_dir_all(parent).map_err(|e| format!("Failed to create image store directory {}: {}", parent.display(), e))?;
+
+    // Concurrent pulls of one reference each work in their own directory; the last rename wins
+    let staging = parent.join(format!(".pull-{}", uuid::Uuid::new_v4()));
+    let result = pull_into(reference, &staging, &archive).await;
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        ConsoleLogger::debug(&format!("Failed to remove pull staging directory {}: {}", staging.display(), e));
+    }
+    result
+}
+
+async fn pull_into(reference: &ImageReference, staging: &Path, archive: &Path) -> Result<PulledImage, String> {
+    let content = staging.join("content");
+    let scratch = staging.join("scratch");
+    for dir in [&content, &scratch] {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let mut client = RegistryClient { reference: reference.clone(), token: None, scratch };
+
+    ConsoleLogger::info(&format!("📥 [PULL] Resolving {}", reference));
+    let (manifest, digest) = client.platform_manifest().await?;
+    let config = manifest.config.ok_or_else(|| format!("Manifest of {} has no image config", reference))?;
+    if manifest.layers.is_empty() {
+        return Err(format!("Manifest of {} lists no layers", reference));
+    }
+
+    let config_file = format!("{}.json", digest_hex(&config.digest)?);
+    client.fetch_blob(&config.digest, &content.join(&config_file)).await?;
+    // A single-platform manifest may still be for another architecture
+    let document = fs::read_to_string(content.join(&config_file))
+        .map_err(|e| format!("Failed to read image config of {}: {}", reference, e))?;
+    if let Ok(image_config) = ImageConfig::parse(&document) {
+        platform::check_architecture(&reference.to_string(), image_config.architecture.as_deref())?;
+    }
+    let mut layer_files = Vec::with_capacity(manifest.layers.len());
+    let mut size_bytes = 0;
+    for (index, layer) in manifest.layers.iter().enumerate() {
+        if layer.media_type.contains("zstd") {
+            return Err(format!("Layer {} of {} is zstd-compressed, which is not supported", layer.digest, reference));
+        }
+        ConsoleLogger::info(&format!("📥 [PULL] {} layer {}/{} ({} bytes)", reference, index + 1, manifest.layers.len(), layer.size));
+        let layer_dir = digest_hex(&layer.digest)?;
+        fs::create_dir_all(content.join(layer_dir))
+            .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+        let layer_file = format!("{}/layer.tar", layer_dir);
+        let path = content.join(&layer_file);
+        client.fetch_blob(&layer.digest, &path).await?;
+        size_bytes += fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        layer_files.push(layer_file);
+    }
+
+    let name = reference.to_string();
+    let partial = staging.join("image.tar");
+    let (content_dir, partial_path) = (content.clone(), partial.clone());
+    let tags = vec![name.clone()];
+    tokio::task::spawn_blocking(move || write_archive(&content_dir, &config_file, &layer_files, &tags, &partial_path))
+        .await
+        .map_err(|e| format!("Archive task failed: {}", e))??;
+    fs::rename(&partial, archive)
+        .map_err(|e| format!("Failed to move pulled image to {}: {}", archive.display(), e))?;
+
+    let image = PulledImage {
+        reference: name,
+        digest,
+        image_path: archive.display().to_string(),
+        layers: manifest.layers.len(),
+        size_bytes,
+        pulled_at: chrono::Utc::now().timestamp(),
+        cached: false,
+    };
+    let record = serde_json::to_string(&image).map_err(|e| format!("Failed to encode image record: {}", e))?;
+    fs::write(record_path(archive), record)
+        .map_err(|e| format!("Failed to record pulled image {}: {}", archive.display(), e))?;
+    ConsoleLogger::success(&format!("📥 [PULL] Pulled {} ({}, {} layers)", image.reference, image.digest, image.layers));
+    Ok(image)
+}
+
+/// Write `content` as a `docker save` archive listing `config` and `layers`, paths relative to it
+pub fn write_archive(content: &Path, config: &str, layers: &[String], repo_tags: &[String], output: &Path) -> Result<(), String> {
+    let manifest = serde_json::json!([{ "Config": config, "RepoTags": repo_tags, "Layers": layers }]);
+    fs::write(content.join("manifest.json"), manifest.to_string())
+        .map_err(|e| format!("Failed to write manifest.json: {}", e))?;
+    let file = fs::File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut builder = tar::Builder::new(file);
+    builder.append_dir_all("", content)
+        .and_then(|_| builder.into_inner())
+        .map_err(|e| format!("Failed to write image archive {}: {}", output.display(), e))?;
+    Ok(())
+}
+
+/// What `append_tree` does with an entry it comes across
+pub enum TreeEntry {
+    Skip,
+    /// The entry itself, without what a directory holds
+    Shallow,
+    Full,
+}
+
+/// Append everything below `root` to `builder` in name order, paths relative to `root`. `select`
+/// sees each entry's relative path and type and decides whether (and how deep) it goes in.
+pub fn append_tree<W: std::io::Write>(builder: &mut tar::Builder<W>, root: &Path,
+    select: &dyn Fn(&Path, &fs::FileType) -> TreeEntry) -> std::io::Result<()> {
+    append_directory(builder, root, Path::new(""), select)
+}
+
+fn append_directory<W: std::io::Write>(builder: &mut tar::Builder<W>, root: &Path, relative: &Path,
+    select: &dyn Fn(&Path, &fs::FileType) -> TreeEntry) -> std::io::Result<()> {
+    let mut names: Vec<_> = fs::read_dir(root.join(relative))?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    for name in names {
+        let entry = relative.join(&name);
+        let path = root.join(&entry);
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        let descend = match select(&entry, &file_type) {
+            TreeEntry::Skip => continue,
+            TreeEntry::Shallow => false,
+            TreeEntry::Full => file_type.is_dir(),
+        };
+        builder.append_path_with_name(&path, &entry)?;
+        if descend {
+            append_directory(builder, root, &entry, select)?;
+        }
+    }
+    Ok(())
+}
+
+fn digest_hex(digest: &str) -> Result<&str, String> {
+    validate_digest(digest)?;
+    Ok(&digest["sha256:".len()..])
+}
+
+/// Check that the file at `path` hashes to `digest`
+pub async fn verify_digest(path: &Path, digest: &str) -> Result<(), String> {
+    let expected = digest_hex(digest)?;
+    let path_arg = path.display().to_string();
+    let result = CommandExecutor::execute_with_timeout(&Argv::new("sha256sum", ["--", path_arg.as_str()]), BLOB_TIMEOUT).await?;
+    if !result.success {
+        return Err(format!("Failed to hash {}: {}", path.display(), result.stderr.trim()));
+    }
+    let actual = result.stdout.split_whitespace().next().unwrap_or_default();
+    if actual != expected {
+        return Err(format!("Digest mismatch: expected {}, downloaded content is sha256:{}", digest, actual));
+    }
+    Ok(())
+}
+
+/// A manifest document; an image index carries `manifests`, an image manifest `config` and `layers`
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    #[serde(default)]
+    manifests: Vec<IndexEntry>,
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Descriptor {
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    digest: String,
+    platform: Option<Platform>,
+}
+
+impl Manifest {
+    fn is_index(&self) -> bool {
+        self.media_type.contains("index") || self.media_type.contains("manifest.list") || !self.manifests.is_empty()
+    }
+}
+
+/// The index entry for linux on `architecture`
+fn select_platform<'a>(entries: &'a [IndexEntry], architecture: &str) -> Option<&'a IndexEntry> {
+    platform::select(entries, architecture, |entry| entry.platform.as_ref())
+}
+
+/// The parameters of a `WWW-Authenticate: Bearer realm="...",service="...",scope="..."` challenge
+fn parse_bearer_challenge(header: &str) -> Option<HashMap<String, String>> {
+    let (scheme, parameters) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let mut values = HashMap::new();
+    let mut rest = parameters.trim();
+    while let Some((key, tail)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => tail.split_once(',').unwrap_or((tail, "")),
+        };
+        values.insert(key, value.to_string());
+        rest = tail.trim_start_matches(',').trim();
+    }
+    values.contains_key("realm").then_some(values)
+}
+
+/// Whether `token` is an RFC 6750 token68 (`[A-Za-z0-9-._~+/]+=*`), the only form that may go
+/// into an Authorization header, and so into curl's config, as is
+fn is_token68(token: &str) -> bool {
+    let body = token.trim_end_matches('=');
+    !body.is_empty() && body.bytes().all(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b))
+}
+
+/// Percent-encode a query parameter value
+fn query_escape(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b':' | b'/' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// Status code and headers of one curl request; headers of every redirect hop are included
+struct HttpResponse {
+    status: u16,
+    headers: String,
+}
+
+impl HttpResponse {
+    /// The last value of header `name`, i.e. the one from the final hop
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers.lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+            .last()
+    }
+}
+
+struct RegistryClient {
+    reference: ImageReference,
+    token: Option<String>,
+    /// Manifests, headers and tokens downloaded along the way
+    scratch: PathBuf,
+}
+
+impl RegistryClient {
+    /// One GET with curl into `output`. The token goes in through curl's config on stdin, so it
+    /// never shows up in an argument list; curl does not send it on to redirect targets on other
+    /// hosts, such as the blob storage registries redirect to.
+    async fn request(&self, url: &str, accept: &[&str], output: &Path, timeout: Duration) -> Result<HttpResponse, String> {
+        let mut config = String::new();
+        if let Some(token) = &self.token {
+            config.push_str(&format!("header = \"Authorization: Bearer {}\"\n", token));
+        }
+        if !accept.is_empty() {
+            config.push_str(&format!("header = \"Accept: {}\"\n", accept.join(", ")));
+        }
+        let headers = self.scratch.join("headers");
+        let argv = Argv::new("curl", [
+            "-sS".to_string(), "-L".to_string(),
+            "--proto".to_string(), "=http,https".to_string(),
+            "--proto-redir".to_string(), "=http,https".to_string(),
+            "--connect-timeout".to_string(), "30".to_string(),
+            "-m".to_string(), timeout.as_secs().to_string(),
+            "-D".to_string(), headers.display().to_string(),
+            "-o".to_string(), output.display().to_string(),
+            "-w".to_string(), "%{http_code}".to_string(),
+            "--config".to_string(), "-".to_string(),
+            "--".to_string(), url.to_string(),
+        ]).with_input(config);
+        // curl gets the timeout itself; the executor's only bounds a hung process
+        let result = CommandExecutor::execute_with_timeout(&argv, timeout + Duration::from_secs(10)).await?;
+        if !result.success {
+            return Err(format!("Request to {} failed: {}", url, result.stderr.trim()));
+        }
+        let status = result.stdout.trim().parse::<u16>()
+            .map_err(|_| format!("Request to {} returned no status", url))?;
+        Ok(HttpResponse { status, headers: fs::read_to_string(&headers).unwrap_or_default() })
+    }
+
+    /// GET `url`, fetching a token and retrying once when the registry asks for one
+    async fn get(&mut self, url: &str, accept: &[&str], output: &Path, timeout: Duration) -> Result<HttpResponse, String> {
+        let response = self.request(url, accept, output, timeout).await?;
+        if response.status != 401 || self.token.is_some() {
+            return Ok(response);
+        }
+        let challenge = response.header("www-authenticate")
+            .and_then(|header| parse_bearer_challenge(&header))
+            .ok_or_else(|| format!("Registry {} requires authentication, which is not supported", self.reference.registry))?;
+        self.token = Some(self.fetch_token(&challenge).await?);
+        self.request(url, accept, output, timeout).await
+    }
+
+    async fn fetch_token(&self, challenge: &HashMap<String, String>) -> Result<String, String> {
+        let realm = &challenge["realm"];
+        if !(realm.starts_with("https://") || realm.starts_with("http://")) {
+            return Err(format!("Registry {} named a token realm that is not an http(s) URL: {}", self.reference.registry, realm));
+        }
+        let default_scope = format!("repository:{}:pull", self.reference.repository);
+        let mut url = format!("{}?scope={}", realm, query_escape(challenge.get("scope").unwrap_or(&default_scope)));
+        if let Some(service) = challenge.get("service") {
+            url.push_str(&format!("&service={}", query_escape(service)));
+        }
+        let output = self.scratch.join("token.json");
+        let response = self.request(&url, &[], &output, REQUEST_TIMEOUT).await?;
+        if response.status != 200 {
+            return Err(format!("Token request for {} was refused (HTTP {})", self.reference, response.status));
+        }
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+        let document = fs::read_to_string(&output).map_err(|e| format!("Failed to read token response: {}", e))?;
+        let token: TokenResponse = serde_json::from_str(&document).map_err(|e| format!("Invalid token response: {}", e))?;
+        let token = token.token.or(token.access_token).ok_or_else(|| "Token response carries no token".to_string())?;
+        if !is_token68(&token) {
+            return Err(format!("Token response for {} carries a malformed token", self.reference));
+        }
+        Ok(token)
+    }
+
+    /// Fetch the manifest `reference` names, verified against `expected_digest` or, failing that,
+    /// the digest the registry reports. Returns it with its digest.
+    async fn fetch_manifest(&mut self, reference: &str, expected_digest: Option<&str>) -> Result<(Manifest, String), String> {
+        let url = format!("{}/manifests/{}", self.reference.base_url(), reference);
+        let output = self.scratch.join("manifest.json");
+        let response = self.get(&url, MANIFEST_MEDIA_TYPES, &output, REQUEST_TIMEOUT).await?;
+        match response.status {
+            200 => {}
+            404 => return Err(format!("Image {} not found in registry {}", self.reference, self.reference.registry)),
+            status => return Err(format!("Registry {} answered HTTP {} for the manifest of {}", self.reference.registry, status, self.reference)),
+        }
+        let digest = expected_digest.map(str::to_string)
+            .or_else(|| response.header("docker-content-digest"))
+            .ok_or_else(|| format!("Registry {} did not report the manifest digest of {}", self.reference.registry, self.reference))?;
+        verify_digest(&output, &digest).await
+            .map_err(|e| format!("Manifest of {} failed verification: {}", self.reference, e))?;
+        let document = fs::read_to_string(&output).map_err(|e| format!("Failed to read manifest: {}", e))?;
+        let manifest = serde_json::from_str(&document)
+            .map_err(|e| format!("Invalid manifest for {}: {}", self.reference, e))?;
+        Ok((manifest, digest))
+    }
+
+    /// The image manifest for this host, resolving an image index to its linux entry
+    async fn platform_manifest(&mut self) -> Result<(Manifest, String), String> {
+        let reference = self.reference.manifest_reference().to_string();
+        let pinned = self.reference.digest.clone();
+        let (manifest, digest) = self.fetch_manifest(&reference, pinned.as_deref()).await?;
+        if !manifest.is_index() {
+            return Ok((manifest, digest));
+        }
+        let architecture = platform::host_architecture();
+        let entry = select_platform(&manifest.manifests, architecture)
+            .ok_or_else(|| format!("Image {} has no linux/{} variant (available: {})", self.reference, architecture,
+                platform::describe(&manifest.manifests, |entry| entry.platform.as_ref())))?;
+        let entry_digest = entry.digest.clone();
+        let (manifest, digest) = self.fetch_manifest(&entry_digest, Some(&entry_digest)).await?;
+        if manifest.is_index() {
+            return Err(format!("Image {} has nested image indexes, which are not supported", self.reference));
+        }
+        Ok((manifest, digest))
+    }
+
+    /// Download blob `digest` to `path` and verify it
+    async fn fetch_blob(&mut self, digest: &str, path: &Path) -> Result<(), String> {
+        let url = format!("{}/blobs/{}", self.reference.base_url(), digest);
+        let response = self.get(&url, &[], path, BLOB_TIMEOUT).await?;
+        if response.status != 200 {
+            return Err(format!("Registry {} answered HTTP {} for blob {} of {}", self.reference.registry, response.status, digest, self.reference));
+        }
+        verify_digest(path, digest).await
+            .map_err(|e| format!("Blob {} of {} failed verification: {}", digest, self.reference, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_image_reference() {
+        let alpine = ImageReference::parse("alpine:3.19").unwrap();
+        assert_eq!((alpine.registry.as_str(), alpine.repository.as_str(), alpine.tag.as_deref()), (DOCKER_HUB_REGISTRY, "library/alpine", Some("3.19")));
+        assert_eq!(alpine.to_string(), "docker.io/library/alpine:3.19");
+        assert_eq!(ImageReference::parse("docker.io/library/alpine:3.19").unwrap(), alpine);
+        assert_eq!(ImageReference::parse("nginx").unwrap().manifest_reference(), "latest");
+
+        let local = ImageReference::parse("localhost:5000/team/app").unwrap();
+        assert_eq!((local.registry.as_str(), local.repository.as_str()), ("localhost:5000", "team/app"));
+        assert_eq!(local.base_url(), "http://localhost:5000/v2/team/app");
+        assert_eq!(ImageReference::parse("ghcr.io/org/app:v2").unwrap().base_url(), "https://ghcr.io/v2/org/app");
+
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let pinned = ImageReference::parse(&format!("quay.io/org/app:v1@{}", digest)).unwrap();
+        assert_eq!(pinned.manifest_reference(), digest);
+        assert_eq!(archive_path(Path::new("/store"), &pinned), PathBuf::from(format!("/store/quay.io/org/app/sha256-{}.tar", "a".repeat(64))));
+        assert_eq!(archive_path(Path::new("/store"), &alpine), PathBuf::from("/store/registry-1.docker.io/library/alpine/3.19.tar"));
+
+        for invalid in ["", "Alpine", "alpine:", "alpine:-x", "alpine@sha256:abc", "alpine@md5:abc", "a b", "org//app",
+            "../x", "../../etc/app", "registry..io/app", "host:port/app", "-bad.io/app"] {
+            assert!(ImageReference::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert!(is_image_file("./image.tar.gz") && is_image_file("/images/alpine") && is_image_file("alpine.tar"));
+        assert!(!is_image_file("alpine:3.19"));
+    }
+
+    #[test]
+    fn test_registry_protocol_helpers() {
+        let challenge = parse_bearer_challenge(r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull""#).unwrap();
+        assert_eq!(challenge["realm"], "https://auth.docker.io/token");
+        assert_eq!(challenge["scope"], "repository:library/alpine:pull");
+        assert_eq!(parse_bearer_challenge(r#"Bearer realm=https://ghcr.io/token"#).unwrap()["realm"], "https://ghcr.io/token");
+        assert!(parse_bearer_challenge(r#"Basic realm="registry""#).is_none());
+        assert_eq!(query_escape("repository:a/b:pull,push"), "repository:a/b:pull%2Cpush");
+        assert!(is_token68("eyJhbGciOi.J9-_~+/abc==") && !is_token68("") && !is_token68("=="));
+        assert!(!is_token68("abc\"\noutput = /etc/passwd") && !is_token68("a b"));
+
+        let index: Manifest = serde_json::from_str(r#"{"mediaType":"application/vnd.oci.image.index.v1+json","manifests":[
+            {"digest":"sha256:1","platform":{"architecture":"arm","os":"linux","variant":"v7"}},
+            {"digest":"sha256:2","platform":{"architecture":"arm64","os":"linux","variant":"v8"}},
+            {"digest":"sha256:3","platform":{"architecture":"amd64","os":"windows"}},
+            {"digest":"sha256:4","platform":{"architecture":"amd64","os":"linux"}}]}"#).unwrap();
+        assert!(index.is_index());
+        assert_eq!(select_platform(&index.manifests, "amd64").map(|entry| entry.digest.as_str()), Some("sha256:4"));
+        assert_eq!(select_platform(&index.manifests, "arm64").map(|entry| entry.digest.as_str()), Some("sha256:2"));
+        assert!(select_platform(&index.manifests, "s390x").is_none());
+
+        let response = HttpResponse { status: 200, headers: "HTTP/1.1 307\r\nDocker-Content-Digest: sha256:old\r\n\r\nHTTP/1.1 200\r\ndocker-content-digest: sha256:new\r\n".to_string() };
+        assert_eq!(response.header("Docker-Content-Digest").as_deref(), Some("sha256:new"));
+    }
+
+    #[tokio::test]
+    async fn test_pulled_archive_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = format!("sha256:{}", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        fs::write(dir.path().join("empty"), "").unwrap();
+        verify_digest(&dir.path().join("empty"), &empty).await.unwrap();
+        assert!(verify_digest(&dir.path().join("empty"), &format!("sha256:{}", "0".repeat(64))).await.is_err());
+
+        // A layer and a config as a pull leaves them in the content directory
+        let (layer_hex, config_hex) = ("1".repeat(64), "2".repeat(64));
+        let content = dir.path().join("content");
+        fs::create_dir_all(content.join(&layer_hex)).unwrap();
+        let mut layer = tar::Builder::new(fs::File::create(content.join(format!("{}/layer.tar", layer_hex))).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        layer.append_data(&mut header, "etc/motd", &b"hello"[..]).unwrap();
+        layer.finish().unwrap();
+        let config_file = format!("{}.json", config_hex);
+        fs::write(content.join(&config_file), r#"{"config":{"Env":["PATH=/bin"],"WorkingDir":"/srv"}}"#).unwrap();
+
+        let archive = dir.path().join("alpine.tar");
+        let layers = vec![format!("{}/layer.tar", layer_hex)];
+        write_archive(&content, &config_file, &layers, &["docker.io/library/alpine:3.19".to_string()], &archive).unwrap();
+        assert!(crate::daemon::docker_archive::is_docker_archive(archive.to_str().unwrap()).unwrap());
+        let rootfs = dir.path().join("rootfs");
+        fs::create_dir_all(&rootfs).unwrap();
+        let imported = crate::daemon::docker_archive::import(archive.to_str().unwrap(), rootfs.to_str().unwrap()).unwrap();
+        assert_eq!(imported.repo_tags, vec!["docker.io/library/alpine:3.19"]);
+        assert_eq!(imported.config.working_dir.as_deref(), Some("/srv"));
+        assert_eq!(fs::read_to_string(rootfs.join("etc/motd")).unwrap(), "hello");
+
+        let store = dir.path().join("store");
+        let reference = ImageReference::parse("alpine:3.19").unwrap();
+        assert!(find_pulled(&store, &reference).is_none());
+        let stored = archive_path(&store, &reference);
+        fs::create_dir_all(stored.parent().unwrap()).unwrap();
+        fs::rename(&archive, &stored).unwrap();
+        let image = PulledImage {
+            reference: reference.to_string(),
+            digest: empty.clone(),
+            image_path: stored.display().to_string(),
+            layers: 1,
+            size_bytes: 10,
+            pulled_at: 0,
+            cached: false,
+        };
+        fs::write(record_path(&stored), serde_json::to_string(&image).unwrap()).unwrap();
+        let found = find_pulled(&store, &reference).unwrap();
+        assert!(found.cached);
+        assert_eq!(found.digest, empty);
+        assert_eq!(pull(&store, &reference, false).await.unwrap().image_path, image.image_path);
+    }
+}
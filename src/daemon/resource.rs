@@ -5,6 +5,7 @@ use nix::unistd::Pid;
 use crate::utils::console::ConsoleLogger;
 use crate::utils::command::CommandExecutor;
 use crate::daemon::cgroup::CgroupManager;
+use crate::daemon::paths;
 use crate::icc::network::ContainerNetworkConfig;
 use crate::utils::filesystem::FileSystemUtils;
 use crate::daemon::MountConfig;
@@ -128,10 +129,9 @@ impl ResourceManager {
                     network_config.container_id, rootfs_path));
                 
                 // Validate correlation consistency
-                let expected_rootfs = format!("/tmp/quilt-containers/{}", container_id);
-                if rootfs_path != &expected_rootfs {
-                    ConsoleLogger::warning(&format!("[RESOURCE] Rootfs path mismatch: expected {}, found {}", 
-                        expected_rootfs, rootfs_path));
+                if !paths::is_container_rootfs(rootfs_path) {
+                    ConsoleLogger::warning(&format!("[RESOURCE] Rootfs path outside {}: {}",
+                        paths::rootfs_base_dir(), rootfs_path));
                 }
             }
         }
@@ -160,6 +160,11 @@ impl ResourceManager {
         } else {
             None
         };
+
+        // The network configuration carries the rootfs the container was started from
+        let rootfs_path = network_config.as_ref()
+            .and_then(|config| config.rootfs_path.clone())
+            .unwrap_or_else(|| paths::default_rootfs_path(container_id));
         
         if let Some(network_config) = network_config {
             if let Err(e) = self.cleanup_network_resources(&network_config, container_pid) {
@@ -175,7 +180,7 @@ impl ResourceManager {
         };
         
         if let Some(mounts) = mounts {
-            if let Err(e) = self.cleanup_mount_resources(container_id, &mounts, container_pid) {
+            if let Err(e) = self.cleanup_mount_resources(container_id, &rootfs_path, &mounts, container_pid) {
                 cleanup_errors.push(format!("Mount cleanup failed: {}", e));
             }
         }
@@ -185,19 +190,7 @@ impl ResourceManager {
             cleanup_errors.push(format!("Cgroup cleanup failed: {}", e));
         }
 
-        // 4. Final rootfs cleanup with network correlation validation
-        let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
-        
-        // Check if rootfs path matches network configuration correlation
-        if let Some(network_rootfs) = self.get_network_rootfs_correlation(container_id) {
-            if network_rootfs != rootfs_path {
-                ConsoleLogger::warning(&format!("[RESOURCE] Rootfs cleanup path mismatch: cleanup={}, network={}", 
-                    rootfs_path, network_rootfs));
-            } else {
-                ConsoleLogger::debug(&format!("[RESOURCE] Rootfs cleanup path correlation verified: {}", rootfs_path));
-            }
-        }
-        
+        // 4. Final rootfs cleanup
         if let Err(e) = self.cleanup_rootfs_resources_safe(&rootfs_path) {
             cleanup_errors.push(format!("Rootfs cleanup failed: {}", e));
         }
@@ -231,8 +224,7 @@ impl ResourceManager {
         }
 
         // Clean up veth pair - delete the host side, container side will be cleaned up automatically
-        let cleanup_host_veth = format!("ip link delete {} 2>/dev/null || true", network_config.veth_host_name);
-        if let Err(e) = CommandExecutor::execute_shell(&cleanup_host_veth) {
+        if let Err(e) = CommandExecutor::run("ip", ["link", "delete", &network_config.veth_host_name]) {
             ConsoleLogger::warning(&format!("Failed to delete host veth {}: {}", network_config.veth_host_name, e));
         } else {
             ConsoleLogger::debug(&format!("Deleted host veth interface: {}", network_config.veth_host_name));
@@ -240,9 +232,9 @@ impl ResourceManager {
 
         // Clean up container side veth if container is still running
         if let Some(pid) = container_pid {
-            // SECURITY NOTE: Safe cleanup operation - only deletes interface, with || true fallback
-            let cleanup_container_veth = format!("nsenter -t {} -n ip link delete {} 2>/dev/null || true", crate::utils::process::ProcessUtils::pid_to_i32(pid), network_config.veth_container_name);
-            if let Err(e) = CommandExecutor::execute_shell(&cleanup_container_veth) {
+            // SECURITY NOTE: Safe cleanup operation - only deletes interface; a failure is expected once it is gone
+            let pid = crate::utils::process::ProcessUtils::pid_to_i32(pid).to_string();
+            if let Err(e) = CommandExecutor::run("nsenter", ["-t", &pid, "-n", "ip", "link", "delete", &network_config.veth_container_name]) {
                 ConsoleLogger::debug(&format!("Container veth cleanup attempt failed (expected if container exited): {}", e));
             }
         }
@@ -250,8 +242,8 @@ impl ResourceManager {
         // Clean up any custom interface names
         let interface_name = format!("qnet{}", &network_config.container_id[..8]);
         if let Some(pid) = container_pid {
-            let cleanup_custom_interface = format!("nsenter -t {} -n ip link delete {} 2>/dev/null || true", crate::utils::process::ProcessUtils::pid_to_i32(pid), interface_name);
-            if let Err(e) = CommandExecutor::execute_shell(&cleanup_custom_interface) {
+            let pid = crate::utils::process::ProcessUtils::pid_to_i32(pid).to_string();
+            if let Err(e) = CommandExecutor::run("nsenter", ["-t", &pid, "-n", "ip", "link", "delete", &interface_name]) {
                 ConsoleLogger::debug(&format!("Custom interface cleanup attempt failed (expected if container exited): {}", e));
             }
         }
@@ -261,7 +253,7 @@ impl ResourceManager {
     }
 
     /// Cleanup mount namespaces
-    fn cleanup_mount_resources(&self, container_id: &str, mounts: &[MountConfig], container_pid: Option<Pid>) -> Result<(), String> {
+    fn cleanup_mount_resources(&self, container_id: &str, rootfs_path: &str, mounts: &[MountConfig], container_pid: Option<Pid>) -> Result<(), String> {
         ConsoleLogger::debug(&format!("📁 Cleaning up {} mount points for container {}", mounts.len(), container_id));
 
         // Log readonly mount cleanup for security audit
@@ -275,8 +267,7 @@ impl ResourceManager {
         if let Some(pid) = container_pid {
             for mount_config in mounts.iter().rev() { // Reverse order for proper unmounting
                 let mount_point = &mount_config.target;
-                let unmount_cmd = format!("nsenter -t {} -m umount -l {} 2>/dev/null || true", pid.as_raw(), mount_point);
-                if let Err(e) = CommandExecutor::execute_shell(&unmount_cmd) {
+                if let Err(e) = CommandExecutor::run("nsenter", ["-t", &pid.as_raw().to_string(), "-m", "umount", "-l", mount_point]) {
                     ConsoleLogger::debug(&format!("Namespace unmount failed for {}: {} (may be expected)", mount_point, e));
                 }
                 
@@ -289,23 +280,20 @@ impl ResourceManager {
         }
 
         // Force unmount from host side with lazy unmount
-        let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
         let common_mounts = vec![
             format!("{}/proc", rootfs_path),
             format!("{}/sys", rootfs_path),
             format!("{}/dev/pts", rootfs_path),
-            rootfs_path.clone(), // The rootfs bind mount itself
+            rootfs_path.to_string(), // The rootfs bind mount itself
         ];
 
         for mount_point in common_mounts.iter().rev() {
             if Path::new(mount_point).exists() {
                 // Try regular unmount first
-                let unmount_cmd = format!("umount {} 2>/dev/null || true", mount_point);
-                let _ = CommandExecutor::execute_shell(&unmount_cmd);
+                let _ = CommandExecutor::run("umount", [mount_point]);
 
                 // Force lazy unmount as fallback
-                let lazy_unmount_cmd = format!("umount -l {} 2>/dev/null || true", mount_point);
-                if let Err(e) = CommandExecutor::execute_shell(&lazy_unmount_cmd) {
+                if let Err(e) = CommandExecutor::run("umount", ["-l", mount_point]) {
                     ConsoleLogger::debug(&format!("Lazy unmount failed for {}: {}", mount_point, e));
                 }
             }
@@ -315,8 +303,9 @@ impl ResourceManager {
         for mount_config in mounts.iter().rev() {
             let mount_point = &mount_config.target;
             if Path::new(mount_point).exists() {
-                let unmount_cmd = format!("umount {} 2>/dev/null || umount -l {} 2>/dev/null || true", mount_point, mount_point);
-                if let Err(e) = CommandExecutor::execute_shell(&unmount_cmd) {
+                let unmounted = CommandExecutor::run("umount", [mount_point]).map_or(false, |result| result.success);
+                let result = if unmounted { Ok(()) } else { CommandExecutor::run("umount", ["-l", mount_point]).map(|_| ()) };
+                if let Err(e) = result {
                     ConsoleLogger::debug(&format!("User mount cleanup failed for {}: {}", mount_point, e));
                 }
                 
@@ -337,6 +326,10 @@ impl ResourceManager {
     fn cleanup_cgroup_resources(&self, container_id: &str) -> Result<(), String> {
         ConsoleLogger::debug(&format!("⚙️ Cleaning up cgroup resources for container {}", container_id));
         
+        crate::daemon::net_accounting::detach(container_id);
+        crate::daemon::net_policy::remove(container_id);
+        crate::daemon::device_policy::remove(container_id);
+        crate::daemon::memory_pressure::release(container_id);
         let cgroup_manager = CgroupManager::new(container_id.to_string());
         cgroup_manager.cleanup()
     }
@@ -360,8 +353,7 @@ impl ResourceManager {
 
         for mount_point in nested_mounts {
             if Path::new(&mount_point).exists() {
-                let umount_cmd = format!("umount -l '{}' 2>/dev/null || true", mount_point);
-                let _ = CommandExecutor::execute_shell(&umount_cmd);
+                let _ = CommandExecutor::run("umount", ["-l", &mount_point]);
                 
                 // Give kernel time to process unmount
                 std::thread::sleep(std::time::Duration::from_millis(100));
@@ -380,23 +372,22 @@ impl ResourceManager {
         }
 
         // Step 3: Force cleanup with more aggressive unmounting
-        let force_umount_cmd = format!("umount -f -l '{}' 2>/dev/null || true", rootfs_path);
-        let _ = CommandExecutor::execute_shell(&force_umount_cmd);
+        let _ = CommandExecutor::run("umount", ["-f", "-l", rootfs_path]);
         
         // Wait a bit longer for force unmount to complete
         std::thread::sleep(std::time::Duration::from_millis(200));
 
         // Step 4: Force remove directory
-        let force_remove_cmd = format!("rm -rf '{}'", rootfs_path);
-        match CommandExecutor::execute_shell(&force_remove_cmd) {
+        match CommandExecutor::run("rm", ["-rf", "--", rootfs_path]) {
             Ok(_) => {
                 ConsoleLogger::success(&format!("✅ Force rootfs cleanup successful: {}", rootfs_path));
                 Ok(())
             }
             Err(e) => {
                 // Last resort - try with sudo if available
-                let sudo_remove_cmd = format!("sudo rm -rf '{}' 2>/dev/null || rm -rf '{}'", rootfs_path, rootfs_path);
-                match CommandExecutor::execute_shell(&sudo_remove_cmd) {
+                let sudo_removed = CommandExecutor::run("sudo", ["rm", "-rf", "--", rootfs_path]).map_or(false, |result| result.success);
+                let result = if sudo_removed { Ok(()) } else { CommandExecutor::run("rm", ["-rf", "--", rootfs_path]).map(|_| ()) };
+                match result {
                     Ok(_) => {
                         ConsoleLogger::success(&format!("✅ Emergency rootfs cleanup successful: {}", rootfs_path));
                         Ok(())
@@ -414,8 +405,7 @@ impl ResourceManager {
         ConsoleLogger::warning(&format!("🚨 Emergency cleanup for container: {}", container_id));
 
         // Kill any remaining processes
-        let kill_cmd = format!("pkill -9 -f 'quilt.*{}' 2>/dev/null || true", container_id);
-        let _ = CommandExecutor::execute_shell(&kill_cmd);
+        let _ = CommandExecutor::run("pkill", ["-9", "-f", &format!("quilt.*{}", container_id)]);
 
         // Force cleanup all resources
         self.cleanup_container_resources(container_id, None)?;
@@ -0,0 +1,178 @@
+// Pre-warmed rootfs pool
+// Keeps already-extracted rootfs directories for frequently used images, so creating a container
+// from one of them is a directory rename instead of a full image extraction
+
+use crate::daemon::paths;
+use crate::utils::console::ConsoleLogger;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Environment variable listing pooled images as `<image_path>=<count>` pairs separated by commas
+pub const ROOTFS_POOL_ENV: &str = "QUILT_ROOTFS_POOL";
+const MAX_PER_IMAGE: usize = 64;
+
+pub struct RootfsPool {
+    targets: HashMap<String, usize>, // image path -> number of warm rootfs to keep
+    ready: Mutex<HashMap<String, VecDeque<String>>>, // image path -> staged rootfs directories
+    next_slot: AtomicU64,
+    refilling: AtomicBool,
+}
+
+static ROOTFS_POOL: once_cell::sync::Lazy<RootfsPool> =
+    once_cell::sync::Lazy::new(|| RootfsPool::new(RootfsPool::targets_from_env()));
+
+impl RootfsPool {
+    pub fn new(targets: HashMap<String, usize>) -> Self {
+        let targets = targets.into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(image, count)| (image, count.min(MAX_PER_IMAGE)))
+            .collect();
+        Self {
+            targets,
+            ready: Mutex::new(HashMap::new()),
+            next_slot: AtomicU64::new(0),
+            refilling: AtomicBool::new(false),
+        }
+    }
+
+    /// Pool configured from the environment, shared by every container creation
+    pub fn global() -> &'static RootfsPool {
+        &ROOTFS_POOL
+    }
+
+    /// Pool targets requested via the environment
+    pub fn targets_from_env() -> HashMap<String, usize> {
+        std::env::var(ROOTFS_POOL_ENV).ok()
+            .map(|value| Self::parse_targets(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parse `/images/a.tar.gz=4,/images/b.tar.gz=2`; malformed entries are skipped with a warning
+    pub fn parse_targets(value: &str) -> HashMap<String, usize> {
+        let mut targets = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.rsplit_once('=').map(|(image, count)| (image.trim(), count.trim().parse::<usize>())) {
+                Some((image, Ok(count))) if !image.is_empty() => {
+                    targets.insert(image.to_string(), count);
+                }
+                _ => ConsoleLogger::warning(&format!("⚠️ [ROOTFS-POOL] Ignoring malformed {} entry: {}", ROOTFS_POOL_ENV, entry)),
+            }
+        }
+        targets
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.targets.is_empty()
+    }
+
+    pub fn is_pooled(&self, image_path: &str) -> bool {
+        self.targets.contains_key(image_path)
+    }
+
+    /// Remove staged directories left behind by a previous daemon run
+    pub fn remove_stale(&self) {
+        let pool_dir = paths::rootfs_pool_dir();
+        if std::path::Path::new(&pool_dir).exists() {
+            ConsoleLogger::debug(&format!("🧹 [ROOTFS-POOL] Removing stale staging area {}", pool_dir));
+            let _ = std::fs::remove_dir_all(&pool_dir);
+        }
+    }
+
+    /// Prepare rootfs directories until every pooled image holds its target count.
+    /// `prepare` extracts `image_path` into the given empty directory. Blocking; only one refill runs at a time.
+    pub fn refill<F>(&self, prepare: F)
+    where
+        F: Fn(&str, &str) -> Result<(), String>,
+    {
+        if !self.enabled() || self.refilling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        for (image_path, target) in &self.targets {
+            let mut created = 0;
+            while self.ready_count(image_path) < *target {
+                match self.create_slot(image_path, &prepare) {
+                    Ok(dir) => {
+                        self.ready.lock().unwrap().entry(image_path.clone()).or_default().push_back(dir);
+                        created += 1;
+                    }
+                    Err(e) => {
+                        ConsoleLogger::warning(&format!("⚠️ [ROOTFS-POOL] Failed to pre-extract {}: {}", image_path, e));
+                        break;
+                    }
+                }
+            }
+
+            if created > 0 {
+                ConsoleLogger::debug(&format!("🗂️ [ROOTFS-POOL] Pre-extracted {} rootfs for {} ({} ready)",
+                    created, image_path, self.ready_count(image_path)));
+            }
+        }
+        self.refilling.store(false, Ordering::Release);
+    }
+
+    pub fn ready_count(&self, image_path: &str) -> usize {
+        self.ready.lock().unwrap().get(image_path).map_or(0, VecDeque::len)
+    }
+
+    /// Move a warm rootfs for `image_path` to `rootfs_path`. Returns false when none is ready,
+    /// in which case the caller extracts the image itself.
+    pub fn claim(&self, image_path: &str, rootfs_path: &str) -> bool {
+        loop {
+            let staged = self.ready.lock().unwrap().get_mut(image_path).and_then(VecDeque::pop_front);
+            let Some(staged) = staged else { return false };
+
+            match std::fs::rename(&staged, rootfs_path) {
+                Ok(()) => {
+                    ConsoleLogger::debug(&format!("🗂️ [ROOTFS-POOL] Claimed {} for {}", staged, rootfs_path));
+                    return true;
+                }
+                Err(e) => {
+                    ConsoleLogger::warning(&format!("⚠️ [ROOTFS-POOL] Discarding {}: {}", staged, e));
+                    let _ = std::fs::remove_dir_all(&staged);
+                }
+            }
+        }
+    }
+
+    fn create_slot<F>(&self, image_path: &str, prepare: &F) -> Result<String, String>
+    where
+        F: Fn(&str, &str) -> Result<(), String>,
+    {
+        let index = self.next_slot.fetch_add(1, Ordering::Relaxed);
+        let dir = format!("{}/{:x}-{}", paths::rootfs_pool_dir(), std::process::id(), index);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create staging directory {}: {}", dir, e))?;
+
+        if let Err(e) = prepare(image_path, &dir) {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+        Ok(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_targets() {
+        let targets = RootfsPool::parse_targets(" /images/a.tar.gz=4, /images/b=c.tar.gz=2,bogus,/images/c.tar.gz=x,");
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets.get("/images/a.tar.gz"), Some(&4));
+        assert_eq!(targets.get("/images/b=c.tar.gz"), Some(&2));
+    }
+
+    #[test]
+    fn test_disabled_pool_hands_out_nothing() {
+        let mut targets = HashMap::new();
+        targets.insert("/images/a.tar.gz".to_string(), 0);
+        let pool = RootfsPool::new(targets);
+        assert!(!pool.enabled());
+        assert!(!pool.is_pooled("/images/a.tar.gz"));
+        pool.refill(|_, _| panic!("disabled pool must not prepare"));
+        assert!(!pool.claim("/images/a.tar.gz", "/tmp/quilt-containers/unused"));
+    }
+}
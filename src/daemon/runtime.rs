@@ -1,11 +1,21 @@
 use crate::daemon::namespace::{NamespaceManager, NamespaceConfig};
-use crate::daemon::cgroup::{CgroupManager, CgroupLimits};
+use crate::daemon::cgroup::{CgroupManager, CgroupLimits, DeviceRule};
 use crate::daemon::manager::RuntimeManager;
 use crate::daemon::readiness::{ContainerReadinessManager, ReadinessConfig, cleanup_readiness_signal};
+use crate::daemon::docker_archive;
+use crate::daemon::image_config;
+use crate::daemon::images::{self, StoredImage};
+use crate::daemon::overlay;
+use crate::daemon::paths;
+use crate::daemon::prestart::{self, PrestartHook, StartGate};
+use crate::daemon::rootfs_pool::RootfsPool;
+use crate::daemon::time_offset;
+use crate::daemon::idmap::{self, IdMapping};
+use crate::daemon::output::{LogLine, LogStream, OutputBuffer, OutputPipes};
 use crate::utils::console::ConsoleLogger;
 use crate::utils::process::ProcessUtils;
 use crate::utils::filesystem::FileSystemUtils;
-use crate::utils::command::CommandExecutor;
+use crate::utils::command::{Argv, CommandExecutor};
 use crate::icc::network::{ContainerNetworkConfig, NetworkManager};
 use crate::icc::network::security::NetworkSecurity;
 use crate::sync::ContainerState;
@@ -14,7 +24,6 @@ use std::sync::Arc;
 use std::process::Command;
 use std::fs;
 use std::path::Path;
-use flate2::read::GzDecoder;
 use tar::Archive;
 use nix::unistd::{chroot, chdir, Pid, execv};
 use std::os::unix::fs::PermissionsExt;
@@ -30,9 +39,16 @@ pub struct ContainerConfig {
     pub setup_commands: Vec<String>,  // Setup commands specification
     pub resource_limits: Option<CgroupLimits>,
     pub namespace_config: Option<NamespaceConfig>,
-    #[allow(dead_code)]
-    pub working_directory: Option<String>,
+    pub working_directory: Option<String>, // Main process start directory; defaults to the image's WORKDIR
+    pub user: Option<String>,              // user[:group] the main process runs as; defaults to the image's USER
     pub mounts: Vec<MountConfig>,
+    pub allow_fuse: bool,             // Expose /dev/fuse and allow the FUSE character device
+    pub immutable: bool,              // Read-only rootfs, tmpfs scratch dirs, reduced capabilities, no-new-privileges
+    pub privileged: bool,             // Host-like /proc, writable /sys, host /dev and access to all devices
+    pub prestart_hooks: Vec<PrestartHook>, // Run while the process waits between root setup and exec
+    pub time_offset: Option<i64>,     // Seconds the container's clocks run ahead (or behind, if negative) of the host
+    pub timezone: Option<String>,     // Zone whose host zoneinfo file is mounted at /etc/localtime
+    pub stored_image: Option<StoredImage>, // The image's layers in the local store; the rootfs is copied from them instead of unpacking image_path
 }
 
 #[derive(Debug, Clone)]
@@ -61,7 +77,15 @@ impl Default for ContainerConfig {
             resource_limits: Some(CgroupLimits::default()),
             namespace_config: Some(NamespaceConfig::default()),
             working_directory: None,
+            user: None,
             mounts: vec![],
+            allow_fuse: false,
+            immutable: false,
+            privileged: false,
+            prestart_hooks: vec![],
+            time_offset: None,
+            timezone: None,
+            stored_image: None,
         }
     }
 }
@@ -72,7 +96,8 @@ pub struct Container {
     pub id: String,
     pub config: ContainerConfig,
     pub state: ContainerState,
-    pub logs: Vec<String>,
+    /// Runtime events and captured stdout/stderr, stamped as they arrive
+    pub logs: OutputBuffer,
     pub pid: Option<Pid>,
     pub rootfs_path: String,
     pub created_at: u64,
@@ -106,9 +131,9 @@ impl Container {
             id: id.clone(),
             config,
             state: ContainerState::Created,
-            logs: Vec::new(),
+            logs: OutputBuffer::default(),
             pid: None,
-            rootfs_path: format!("/tmp/quilt-containers/{}", id),
+            rootfs_path: paths::default_rootfs_path(&id),
             created_at: timestamp,
             network_config: None,
             monitoring_task: None,
@@ -116,14 +141,12 @@ impl Container {
     }
 
     pub fn add_log(&mut self, message: String) {
-        let _timestamp = ProcessUtils::get_timestamp();
-
-        self.logs.push(message);
+        self.logs.push(LogLine::now(LogStream::System, message));
     }
 }
 
 pub struct ContainerRuntime {
-    containers: Arc<tokio::sync::Mutex<HashMap<String, Container>>>,
+    containers: Arc<std::sync::Mutex<HashMap<String, Container>>>,
     namespace_manager: NamespaceManager,
     runtime_manager: RuntimeManager,
     readiness_manager: ContainerReadinessManager,
@@ -132,7 +155,7 @@ pub struct ContainerRuntime {
 impl ContainerRuntime {
     pub fn new() -> Self {
         ContainerRuntime {
-            containers: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            containers: Arc::new(std::sync::Mutex::new(HashMap::new())),
             namespace_manager: NamespaceManager::new(),
             runtime_manager: RuntimeManager::new(),
             readiness_manager: ContainerReadinessManager::new(ReadinessConfig::default()),
@@ -145,8 +168,8 @@ impl ContainerRuntime {
         let container = Container::new(id.clone(), config);
         let rootfs_path = container.rootfs_path.clone();
 
-        // Lock-free container insertion
-        if let Ok(mut containers) = self.containers.try_lock() {
+        // Short critical section: insert and release the containers lock
+        if let Ok(mut containers) = self.containers.lock() {
             containers.insert(id.clone(), container);
         } else {
             return Err(format!("Failed to lock containers for insertion of {}", id));
@@ -157,7 +180,7 @@ impl ContainerRuntime {
         if let Err(e) = self.setup_rootfs(&id) {
             ConsoleLogger::error(&format!("[CREATE] Rootfs setup failed for {}: {}", id, e));
             // Rollback: remove container from map
-            if let Ok(mut containers) = self.containers.try_lock() {
+            if let Ok(mut containers) = self.containers.lock() {
                 containers.remove(&id);
             }
             return Err(e);
@@ -166,7 +189,7 @@ impl ContainerRuntime {
         // Verify rootfs was actually created and has content
         if !std::path::Path::new(&rootfs_path).exists() {
             ConsoleLogger::error(&format!("Rootfs path {} was not created after setup", rootfs_path));
-            if let Ok(mut containers) = self.containers.try_lock() {
+            if let Ok(mut containers) = self.containers.lock() {
                 containers.remove(&id);
             }
             return Err(format!("Rootfs creation failed for container {}", id));
@@ -177,7 +200,7 @@ impl ContainerRuntime {
             .map_err(|e| format!("Failed to read rootfs directory: {}", e))?;
         if entries.is_empty() {
             ConsoleLogger::error(&format!("Rootfs {} is empty after extraction", rootfs_path));
-            if let Ok(mut containers) = self.containers.try_lock() {
+            if let Ok(mut containers) = self.containers.lock() {
                 containers.remove(&id);
             }
             return Err(format!("Rootfs extraction failed - directory is empty"));
@@ -201,8 +224,8 @@ impl ContainerRuntime {
         let mut container = Container::new(id.clone(), config);
         container.rootfs_path = rootfs_path.clone();
         
-        // Lock-free container insertion
-        if let Ok(mut containers) = self.containers.try_lock() {
+        // Short critical section: insert and release the containers lock
+        if let Ok(mut containers) = self.containers.lock() {
             containers.insert(id.clone(), container);
         } else {
             return Err(format!("Failed to lock containers for insertion of {}", id));
@@ -217,8 +240,8 @@ impl ContainerRuntime {
     pub fn start_container(&self, id: &str, network_config: Option<ContainerNetworkConfig>) -> Result<(), String> {
         ConsoleLogger::progress(&format!("[START] Starting container: {}", id));
 
-        // Get container configuration (lock-free read)
-        let (config, rootfs_path) = if let Ok(containers) = self.containers.try_lock() {
+        // Get container configuration (cloned out under the containers lock)
+        let (config, rootfs_path) = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(id) {
                 (container.config.clone(), container.rootfs_path.clone())
             } else {
@@ -238,6 +261,9 @@ impl ContainerRuntime {
         let resource_manager = ResourceManager::global();
         resource_manager.register_mounts(id, mount_points);
 
+        // Stopping unmounts an overlay rootfs, and a reboot loses it
+        overlay::ensure_mounted(&rootfs_path)?;
+
         // Register network config with ResourceManager if available
         if let Some(ref net_config) = network_config {
             resource_manager.register_network(id, net_config.clone());
@@ -272,7 +298,7 @@ impl ContainerRuntime {
         let command_for_logs = format!("{:?}", config.command);
         
         // Add log entry (per-container lock)
-        if let Ok(mut containers) = self.containers.try_lock() {
+        if let Ok(mut containers) = self.containers.lock() {
             if let Some(container) = containers.get_mut(id) {
                 container.add_log(format!("Starting container execution with command: {}", command_for_logs));
             }
@@ -282,24 +308,126 @@ impl ContainerRuntime {
         // ENHANCED: Inject readiness check into command
         let enhanced_command = self.readiness_manager.inject_readiness_into_command(id, config.command.clone());
         let command_clone = enhanced_command;
-        let environment_clone = config.environment.clone();
+        // The image's ENV, WORKDIR and USER, overridden by the container's own settings
+        let settings = image_config::load(&rootfs_path)
+            .merged_with(&config.environment, config.working_directory.as_deref(), config.user.as_deref());
+        let mut environment_clone = settings.environment;
+        let working_directory = settings.working_directory;
+        let user = settings.user;
+        let time_namespace = namespace_config.time
+            .then(|| (namespace_config.monotonic_offset, namespace_config.boottime_offset));
+        if let Some(offset) = config.time_offset {
+            match time_offset::install_faketime(&rootfs_path) {
+                Ok(()) => time_offset::add_faketime_environment(&mut environment_clone, offset),
+                Err(e) => ConsoleLogger::warning(&format!("Container {}: {}", id, e)),
+            }
+        }
         let rootfs_path_clone = rootfs_path.clone();
         let setup_commands_clone = setup_commands.clone();
         let network_enabled = namespace_config.network; // Capture network flag for child process
-        let mounts_clone = config.mounts.clone();
+        let cgroup_namespace = namespace_config.cgroup;
+        // In a user namespace the rootfs, volumes and bind mounts are attached ID-mapped; only
+        // tmpfs mounts are left for `setup_container_mounts`
+        let id_mapping = if namespace_config.user { Some(IdMapping::from_env()?) } else { None };
+        let idmapped_mounts = match &id_mapping {
+            Some(mapping) => Some(idmap::prepare_idmapped_mounts(&rootfs_path, &config.mounts, mapping)?),
+            None => None,
+        };
+        let mounts_clone: Vec<MountConfig> = config.mounts.iter()
+            .filter(|m| idmapped_mounts.is_none() || matches!(m.mount_type, MountType::Tmpfs))
+            .cloned()
+            .collect();
+        let allow_fuse = config.allow_fuse;
+        let timezone = config.timezone.clone();
+        let immutable = config.immutable;
+        let privileged = config.privileged;
+        if privileged && crate::daemon::hardening::seccomp_mode() != 0 {
+            ConsoleLogger::warning(&format!(
+                "Container {} is privileged but inherits the daemon's seccomp filter, which cannot be lifted", id));
+        }
+        let mount_targets: Vec<String> = config.mounts.iter().map(|m| m.target.clone()).collect();
+
+        // Capture stdout/stderr; without pipes the container writes to the daemon's own output
+        let output_pipes = match OutputPipes::new() {
+            Ok(pipes) => Some(pipes),
+            Err(e) => {
+                ConsoleLogger::warning(&format!("Container {} output will not be captured: {}", id, e));
+                None
+            }
+        };
+        let output_fds = output_pipes.as_ref().map(|pipes| pipes.child_fds());
+
+        // With pre-start hooks the process stops at a gate once its root is set up
+        let start_gate = if config.prestart_hooks.is_empty() {
+            None
+        } else {
+            Some(StartGate::new()?)
+        };
+        let gate_fds = start_gate.as_ref().map(|gate| gate.child_fds());
 
         // Create new lightweight runtime manager for child (not clone of existing)
         let child_func = move || -> i32 {
             // This runs in the child process with new namespaces
             // Keep memory allocation to minimum in child process
             
+            if let Some((stdout_fd, stderr_fd)) = output_fds {
+                if let Err(e) = crate::daemon::output::redirect_output(stdout_fd, stderr_fd) {
+                    eprintln!("{}", e);
+                }
+            }
+
+            // Nothing may create files before the process has ids in its user namespace
+            if id_mapping.is_some() {
+                if let Err(e) = idmap::become_namespace_root() {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            }
+
+            // Shift the monotonic clocks before anything is forked; the command enters the
+            // namespace when it execs
+            if let Some((monotonic_offset, boottime_offset)) = time_namespace {
+                if let Err(e) = time_offset::enter_time_namespace(monotonic_offset, boottime_offset) {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            }
+
+            // The ID-mapped rootfs goes under everything else mounted into it
+            if let Some(trees) = &idmapped_mounts {
+                if let Err(e) = idmap::attach_tree(&trees.rootfs, &rootfs_path_clone) {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            }
+
             // Setup mount namespace
             let namespace_manager = NamespaceManager::new();
-            if let Err(e) = namespace_manager.setup_mount_namespace(&rootfs_path_clone) {
+            if let Err(e) = namespace_manager.setup_mount_namespace(&rootfs_path_clone, privileged) {
                 eprintln!("Failed to setup mount namespace: {}", e);
                 return 1;
             }
             
+            // Show the container only its own cgroup subtree
+            if cgroup_namespace {
+                if let Err(e) = namespace_manager.setup_cgroup_namespace(&id_for_logs, &rootfs_path_clone) {
+                    eprintln!("Failed to setup cgroup namespace: {}", e);
+                    return 1;
+                }
+            }
+            
+            // Volumes and bind mounts of a user-namespaced container, prepared by the daemon
+            if let Some(trees) = &idmapped_mounts {
+                for (target, tree) in &trees.mounts {
+                    let target_path = format!("{}/{}", rootfs_path_clone, target.trim_start_matches('/'));
+                    if let Err(e) = FileSystemUtils::create_dir_all_with_logging(&target_path, "mount target")
+                        .and_then(|_| idmap::attach_tree(tree, &target_path)) {
+                        eprintln!("Failed to setup ID-mapped mount: {}", e);
+                        // Non-fatal, continue - as for the other container mounts
+                    }
+                }
+            }
+
             // Setup container mounts (volumes, bind mounts, tmpfs)
             if !mounts_clone.is_empty() {
                 println!("DEBUG: Setting up {} mounts before chroot", mounts_clone.len());
@@ -311,6 +439,22 @@ impl ContainerRuntime {
                 }
             }
 
+            // Expose /dev/fuse for containers that opted into FUSE
+            if allow_fuse {
+                if let Err(e) = namespace_manager.setup_fuse_device(&rootfs_path_clone) {
+                    eprintln!("Failed to setup FUSE device: {}", e);
+                    // Non-fatal, continue - FUSE mounts will fail inside the container
+                }
+            }
+
+            // The zone named by TZ, for programs that read /etc/localtime
+            if let Some(zone) = &timezone {
+                if let Err(e) = crate::daemon::timezone::mount_timezone(&rootfs_path_clone, zone) {
+                    eprintln!("Failed to setup timezone {}: {}", zone, e);
+                    // Non-fatal, continue - the container keeps the image's local time
+                }
+            }
+
             // Setup basic network namespace ONLY if networking is enabled
             if network_enabled {
                 if let Err(e) = namespace_manager.setup_network_namespace() {
@@ -340,6 +484,14 @@ impl ContainerRuntime {
                 return 1;
             }
             
+            // Pre-start hooks run now, with namespaces, mounts and root in place
+            if let Some(gate_fds) = gate_fds {
+                if let Err(e) = prestart::wait_at_gate(gate_fds) {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            }
+            
             // NOW wait for network configuration AFTER chroot if networking is enabled
             if network_enabled {
                 // Ensure /tmp exists in container
@@ -395,17 +547,61 @@ impl ContainerRuntime {
             // Execute setup commands inside the container
             if !setup_commands_clone.is_empty() {
                 println!("Executing {} setup commands in container {}", setup_commands_clone.len(), id_for_logs);
-                if let Err(e) = runtime_manager.execute_setup_commands(&setup_commands_clone) {
+                // Steps are reported from inside the rootfs, where the daemon follows them
+                let mut report_step = |report: &crate::daemon::setup_report::SetupStepReport| {
+                    if let Err(e) = crate::daemon::setup_report::append("/", report) {
+                        eprintln!("{}", e);
+                    }
+                };
+                if let Err(e) = runtime_manager.execute_setup_commands(&setup_commands_clone, &mut report_step) {
                     eprintln!("Setup commands failed: {}", e);
                     return 1;
                 }
             }
 
             // Set environment variables
+            let home_set = environment_clone.contains_key("HOME");
             for (key, value) in environment_clone {
                 std::env::set_var(key, value);
             }
 
+            // Created like Docker's WORKDIR, before an immutable root would refuse it
+            if let Some(dir) = &working_directory {
+                let entered = std::fs::create_dir_all(dir).map_err(|e| e.to_string())
+                    .and_then(|_| chdir(dir.as_str()).map_err(|e| e.to_string()));
+                if let Err(e) = entered {
+                    eprintln!("Failed to enter working directory {}: {}", dir, e);
+                    return 1;
+                }
+            }
+
+            // Lock the container down last so setup commands can still write to the rootfs
+            if immutable {
+                if let Err(e) = crate::daemon::hardening::apply_immutable_profile(&mount_targets) {
+                    eprintln!("Failed to apply immutable profile: {}", e);
+                    return 1;
+                }
+            }
+
+            // Looked up in the container's own passwd and group files, which setup commands may
+            // have changed
+            if let Some(user) = &user {
+                let resolved = match image_config::resolve_user(user, std::path::Path::new("/")) {
+                    Ok(resolved) => resolved,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return 1;
+                    }
+                };
+                if let Err(e) = image_config::switch_user(&resolved) {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+                if let (false, Some(home)) = (home_set, &resolved.home) {
+                    std::env::set_var("HOME", home);
+                }
+            }
+
             // Execute the main command with reduced memory overhead
             println!("Executing main command in container: {:?}", command_clone);
             
@@ -531,12 +727,68 @@ impl ContainerRuntime {
         match self.namespace_manager.create_namespaced_process(&namespace_config, child_func) {
             Ok(pid) => {
                 ConsoleLogger::debug(&format!("🚀 Container process created, PID: {} - verifying readiness...", ProcessUtils::pid_to_i32(pid)));
+
+                // Start reading before anything can block on a full pipe
+                if let Some(pipes) = output_pipes {
+                    let containers_ref = self.containers.clone();
+                    let container_id = id.to_string();
+                    let sink = move |line: LogLine| {
+                        if let Ok(mut containers) = containers_ref.lock() {
+                            if let Some(container) = containers.get_mut(&container_id) {
+                                container.logs.push(line);
+                            }
+                        }
+                    };
+                    if let Err(e) = pipes.spawn_readers(id, sink) {
+                        ConsoleLogger::warning(&format!("Container {} output will not be captured: {}", id, e));
+                    }
+                }
+
+                // The process waits for its id maps before it does anything else
+                if let Some(mapping) = &id_mapping {
+                    if let Err(e) = idmap::write_id_maps(ProcessUtils::pid_to_i32(pid), mapping) {
+                        ConsoleLogger::error(&format!("Container {} user namespace setup failed: {}", id, e));
+                        let _ = ProcessUtils::terminate_process(pid, 2);
+                        self.update_container_state(id, ContainerState::Error);
+                        return Err(format!("Container {} failed to start: {}", id, e));
+                    }
+                }
                 
                 // Add process to cgroups
                 if let Err(e) = cgroup_manager.add_process(pid) {
                     ConsoleLogger::warning(&format!("Failed to add process to cgroups: {}", e));
                 }
 
+                // Optional eBPF traffic accounting; metrics fall back to procfs without it
+                if crate::daemon::net_accounting::enabled_from_env() {
+                    if let Err(e) = crate::daemon::net_accounting::attach(id) {
+                        ConsoleLogger::debug(&format!("eBPF network accounting unavailable for {}: {}", id, e));
+                    }
+                }
+
+                // Devices are denied by default; allow_fuse and privileged open them up below
+                if let Err(e) = cgroup_manager.install_device_policy(pid) {
+                    ConsoleLogger::warning(&format!("Default device policy not applied for {}: {}", id, e));
+                }
+
+                // Allow the FUSE character device (c 10:229) through the devices controller
+                if config.allow_fuse {
+                    if let Ok(rule) = DeviceRule::parse("c 10:229 rwm") {
+                        if let Err(e) = cgroup_manager.grant_device(pid, &rule) {
+                            ConsoleLogger::debug(&format!("FUSE device rule not applied for {}: {}", id, e));
+                        }
+                    }
+                }
+
+                // Privileged containers may use every device they can see
+                if config.privileged {
+                    if let Ok(rule) = DeviceRule::parse("a") {
+                        if let Err(e) = cgroup_manager.grant_device(pid, &rule) {
+                            ConsoleLogger::debug(&format!("Privileged device rule not applied for {}: {}", id, e));
+                        }
+                    }
+                }
+
                 // Finalize cgroup limits after process is started
                 if let Some(limits) = &config.resource_limits {
                     if let Err(e) = cgroup_manager.finalize_limits(limits) {
@@ -544,6 +796,27 @@ impl ContainerRuntime {
                     }
                 }
 
+                // Run pre-start hooks while the process waits at its gate
+                if let Some(gate) = start_gate {
+                    match prestart::run_prestart_hooks(gate.into_parent(), &config.prestart_hooks, id, pid, &rootfs_path) {
+                        Ok(completed) => {
+                            if let Ok(mut containers) = self.containers.lock() {
+                                if let Some(container) = containers.get_mut(id) {
+                                    for message in completed {
+                                        container.add_log(message);
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            ConsoleLogger::error(&format!("Container {} pre-start hooks failed: {}", id, e));
+                            let _ = ProcessUtils::terminate_process(pid, 2);
+                            self.update_container_state(id, ContainerState::Error);
+                            return Err(format!("Container {} failed to start: {}", id, e));
+                        }
+                    }
+                }
+
                 // First verify the process actually started
                 // EVENT-DRIVEN: Just check immediately, no sleep
                 if !ProcessUtils::is_process_running(pid) {
@@ -566,8 +839,8 @@ impl ContainerRuntime {
                         ConsoleLogger::container_started(id, Some(ProcessUtils::pid_to_i32(pid)));
                         
                         ConsoleLogger::debug(&format!("[START] Locking containers map to update state for {}", id));
-                        // Update container state using lock-free concurrent operations
-                        if let Ok(mut containers) = self.containers.try_lock() {
+                        // Update container state under the containers lock
+                        if let Ok(mut containers) = self.containers.lock() {
                             if let Some(container) = containers.get_mut(id) {
                                 container.pid = Some(pid);
                                 container.state = ContainerState::Running;
@@ -615,7 +888,7 @@ impl ContainerRuntime {
                     };
 
                     // Update container state to EXITED
-                    if let Ok(mut containers) = containers_ref.try_lock() {
+                    if let Ok(mut containers) = containers_ref.lock() {
                         if let Some(container) = containers.get_mut(&id_clone) {
                             if let Some(_code) = exit_code {
                                 container.state = ContainerState::Exited;
@@ -637,7 +910,7 @@ impl ContainerRuntime {
                 // For now, we'll let it run to completion since it cleans up after itself
                 
                 // Update container state to store the monitoring task
-                if let Ok(mut containers) = self.containers.try_lock() {
+                if let Ok(mut containers) = self.containers.lock() {
                     if let Some(container) = containers.get_mut(id) {
                         container.monitoring_task = Some(wait_task);
                     }
@@ -653,10 +926,11 @@ impl ContainerRuntime {
     }
 
     fn setup_rootfs(&self, container_id: &str) -> Result<(), String> {
-        // Lock-free read of container configuration
-        let image_path = if let Ok(containers) = self.containers.try_lock() {
+        // Read container configuration under the containers lock
+        let (image_path, rootfs_path, stored_image, user_namespace) = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
-                container.config.image_path.clone()
+                let user_namespace = container.config.namespace_config.as_ref().map_or(false, |namespaces| namespaces.user);
+                (container.config.image_path.clone(), container.rootfs_path.clone(), container.config.stored_image.clone(), user_namespace)
             } else {
                 return Err(format!("Container {} not found", container_id));
             }
@@ -666,19 +940,39 @@ impl ContainerRuntime {
 
         // Extract image to simple rootfs directory
         if std::path::Path::new(&image_path).is_file() {
-            let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
+            // Take a pre-extracted rootfs when the image is pooled and one is ready
+            if RootfsPool::global().claim(&image_path, &rootfs_path) {
+                ConsoleLogger::success(&format!("Rootfs for container {} taken from warm pool", container_id));
+                return Ok(());
+            }
             
             // Create the directory first using FileSystemUtils
             FileSystemUtils::create_dir_all_with_logging(&rootfs_path, "container rootfs")?;
             
-            // Extract the image
-            if let Err(e) = self.extract_image(&image_path, &rootfs_path) {
-                return Err(format!("Failed to extract container image: {}", e));
+            match stored_image {
+                // ID-mapped rootfs mounts need a rootfs of the container's own
+                Some(image) if !user_namespace && overlay::enabled() => {
+                    let store_root = images::image_store_root();
+                    let mounted = overlay::prepare_lower(&store_root, &image, |lower| self.fix_container_binaries(lower))
+                        .and_then(|lower| overlay::create_rootfs(&lower, &rootfs_path));
+                    match mounted {
+                        Ok(()) => ConsoleLogger::debug(&format!("Mounted {} as overlay rootfs at {}", image.digest, rootfs_path)),
+                        Err(e) => {
+                            ConsoleLogger::warning(&format!("Overlay rootfs unavailable for container {}, copying the image: {}", container_id, e));
+                            overlay::remove_rootfs(&rootfs_path)?;
+                            images::materialize(&store_root, &image, &rootfs_path)?;
+                            self.fix_container_binaries(&rootfs_path)?;
+                        }
+                    }
+                }
+                Some(image) => {
+                    images::materialize(&images::image_store_root(), &image, &rootfs_path)?;
+                    ConsoleLogger::debug(&format!("Copied {} stored layers of {} to {}", image.layers.len(), image.digest, rootfs_path));
+                    self.fix_container_binaries(&rootfs_path)?;
+                }
+                None => self.prepare_rootfs(&image_path, &rootfs_path)?,
             }
             
-            // Fix broken symlinks and ensure working binaries
-            self.fix_container_binaries(&rootfs_path)?;
-            
             ConsoleLogger::success(&format!("Rootfs setup completed for container {}", container_id));
             Ok(())
         } else {
@@ -686,6 +980,22 @@ impl ContainerRuntime {
         }
     }
 
+    /// Extract an image into an existing empty directory and make its essential binaries usable
+    fn prepare_rootfs(&self, image_path: &str, rootfs_path: &str) -> Result<(), String> {
+        // Extract the image
+        if let Err(e) = self.extract_image(image_path, rootfs_path) {
+            return Err(format!("Failed to extract container image: {}", e));
+        }
+
+        // Fix broken symlinks and ensure working binaries
+        self.fix_container_binaries(rootfs_path)
+    }
+
+    /// Top up the warm rootfs pool configured via QUILT_ROOTFS_POOL. Blocking.
+    pub fn refill_rootfs_pool(&self) {
+        RootfsPool::global().refill(|image_path, rootfs_path| self.prepare_rootfs(image_path, rootfs_path));
+    }
+
     /// Fix broken symlinks in Nix-generated containers and ensure working binaries
     fn fix_container_binaries(&self, rootfs_path: &str) -> Result<(), String> {
         ConsoleLogger::debug("Fixing container binaries and symlinks...");
@@ -1372,13 +1682,15 @@ done
                 ConsoleLogger::warning("Busybox not found, downloading...");
                 // Download busybox if not found
                 let download_path = "/tmp/quilt-busybox";
-                let download_cmd = format!(
-                    "curl -L -o {} https://busybox.net/downloads/binaries/1.35.0-x86_64-linux-musl/busybox && chmod +x {}",
-                    download_path, download_path
-                );
-                
-                CommandExecutor::execute_shell(&download_cmd)
-                    .map_err(|e| format!("Failed to download busybox: {}", e))?;
+                let download = CommandExecutor::run("curl", [
+                    "-L", "-o", download_path,
+                    "https://busybox.net/downloads/binaries/1.35.0-x86_64-linux-musl/busybox",
+                ]).map_err(|e| format!("Failed to download busybox: {}", e))?;
+                if !download.success {
+                    return Err(format!("Failed to download busybox: {}", download.stderr.trim()));
+                }
+                fs::set_permissions(download_path, fs::Permissions::from_mode(0o755))
+                    .map_err(|e| format!("Failed to make busybox executable: {}", e))?;
                 
                 download_path
             }
@@ -1395,11 +1707,12 @@ done
         FileSystemUtils::copy_file(busybox_source, &busybox_target)?;
         
         // Make it executable
-        CommandExecutor::execute_shell(&format!("chmod +x {}", busybox_target))?;
+        fs::set_permissions(&busybox_target, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make busybox executable: {}", e))?;
         ConsoleLogger::success(&format!("Busybox installed to {}", busybox_target));
         
         // Get list of all busybox applets
-        let applets_output = CommandExecutor::execute_shell(&format!("{} --list", busybox_target))?;
+        let applets_output = CommandExecutor::run(&busybox_target, ["--list"])?;
         let applets: Vec<&str> = applets_output.stdout.lines().collect();
         
         // Create symlinks for essential networking and system utilities
@@ -1466,12 +1779,18 @@ done
         let security = NetworkSecurity::new("192.168.100.1".to_string()); // Bridge IP placeholder
         security.validate_rootfs_path(rootfs_path)?;
         
-        // Open and decompress the tar file
-        let tar_file = std::fs::File::open(image_path)
-            .map_err(|e| format!("Failed to open image file: {}", e))?;
+        // `docker save` archives carry layers to flatten rather than the rootfs itself
+        if docker_archive::is_docker_archive(image_path)? {
+            let imported = docker_archive::import(image_path, rootfs_path)?;
+            image_config::save(rootfs_path, &imported.config)?;
+            ConsoleLogger::success(&format!("Imported docker archive {} ({} layers) to {}",
+                if imported.repo_tags.is_empty() { image_path.to_string() } else { imported.repo_tags.join(", ") },
+                imported.layers, rootfs_path));
+            return Ok(());
+        }
 
-        let tar = GzDecoder::new(tar_file);
-        let mut archive = Archive::new(tar);
+        // Open and decompress the tar file
+        let mut archive = Archive::new(docker_archive::open_image(image_path)?);
 
         // Extract to rootfs directory
         archive.unpack(rootfs_path)
@@ -1483,7 +1802,7 @@ done
 
     fn update_container_state(&self, container_id: &str, new_state: ContainerState) {
         // Per-container lock for state update
-        if let Ok(mut containers) = self.containers.try_lock() {
+        if let Ok(mut containers) = self.containers.lock() {
             if let Some(container) = containers.get_mut(container_id) {
                 container.state = new_state;
             }
@@ -1492,15 +1811,15 @@ done
 
     #[allow(dead_code)]
     pub fn get_container_state(&self, container_id: &str) -> Option<ContainerState> {
-        if let Ok(containers) = self.containers.try_lock() {
+        if let Ok(containers) = self.containers.lock() {
             containers.get(container_id).map(|c| c.state.clone())
         } else {
             None
         }
     }
 
-    pub fn get_container_logs(&self, container_id: &str) -> Option<Vec<String>> {
-        if let Ok(containers) = self.containers.try_lock() {
+    pub fn get_container_logs(&self, container_id: &str) -> Option<OutputBuffer> {
+        if let Ok(containers) = self.containers.lock() {
             containers.get(container_id).map(|c| c.logs.clone())
         } else {
             None
@@ -1508,7 +1827,7 @@ done
     }
 
     pub fn get_container_info(&self, container_id: &str) -> Option<Container> {
-        if let Ok(containers) = self.containers.try_lock() {
+        if let Ok(containers) = self.containers.lock() {
             containers.get(container_id).cloned()
         } else {
             None
@@ -1535,6 +1854,10 @@ done
             ContainerState::Running => stats.insert("state".to_string(), "running".to_string()),
             ContainerState::Exited => stats.insert("state".to_string(), "exited".to_string()),
             ContainerState::Error => stats.insert("state".to_string(), "error".to_string()),
+            ContainerState::Stopping => stats.insert("state".to_string(), "stopping".to_string()),
+            ContainerState::Removing => stats.insert("state".to_string(), "removing".to_string()),
+            ContainerState::Restarting => stats.insert("state".to_string(), "restarting".to_string()),
+            ContainerState::Paused => stats.insert("state".to_string(), "paused".to_string()),
         };
 
         // Get PID if available
@@ -1546,7 +1869,7 @@ done
     }
 
     pub fn get_container_stats(&self, container_id: &str) -> Result<HashMap<String, String>, String> {
-        if let Ok(containers) = self.containers.try_lock() {
+        if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
                 self.get_container_stats_for_container(container, container_id)
             } else {
@@ -1558,7 +1881,7 @@ done
     }
 
     pub fn get_container_info_and_stats(&self, container_id: &str) -> (Option<Container>, Result<HashMap<String, String>, String>) {
-        let container_info = if let Ok(containers) = self.containers.try_lock() {
+        let container_info = if let Ok(containers) = self.containers.lock() {
             containers.get(container_id).cloned()
         } else {
             None
@@ -1571,7 +1894,7 @@ done
         ConsoleLogger::progress(&format!("Stopping container: {}", container_id));
 
         // Get container PID and monitoring task
-        let (pid, monitoring_task) = if let Ok(containers) = self.containers.try_lock() {
+        let (pid, monitoring_task) = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
                 (container.pid, container.monitoring_task.as_ref().map(|t| t.abort_handle()))
             } else {
@@ -1583,6 +1906,9 @@ done
 
         let pid = pid.ok_or_else(|| format!("Container {} is not running", container_id))?;
 
+        // A frozen container cannot handle SIGTERM; thaw it (or lift its throttle) first
+        crate::daemon::memory_pressure::release(container_id);
+
         // Abort the monitoring task to prevent resource leaks
         if let Some(abort_handle) = monitoring_task {
             abort_handle.abort();
@@ -1592,7 +1918,7 @@ done
         match ProcessUtils::terminate_process(pid, 10) {
             Ok(()) => {
                 // Update container state
-                if let Ok(mut containers) = self.containers.try_lock() {
+                if let Ok(mut containers) = self.containers.lock() {
                     if let Some(container) = containers.get_mut(container_id) {
                         container.state = ContainerState::Exited;
                         container.pid = None;
@@ -1617,9 +1943,9 @@ done
         ConsoleLogger::progress(&format!("Removing container: {}", container_id));
 
         // Get container PID before stopping if it's running
-        let container_pid = if let Ok(containers) = self.containers.try_lock() {
+        let (container_pid, rootfs_path) = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
-                container.pid
+                (container.pid, container.rootfs_path.clone())
             } else {
                 return Err(format!("Container {} not found", container_id));
             }
@@ -1633,7 +1959,7 @@ done
         }
 
         // Remove container from registry
-        let removed = if let Ok(mut containers) = self.containers.try_lock() {
+        let removed = if let Ok(mut containers) = self.containers.lock() {
             containers.remove(container_id).is_some()
         } else {
             return Err(format!("Failed to lock containers for removal of {}", container_id));
@@ -1657,7 +1983,9 @@ done
         }
 
         // Clean up rootfs directory using FileSystemUtils
-        let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
+        if let Err(e) = overlay::remove_rootfs(&rootfs_path) {
+            ConsoleLogger::warning(&format!("Failed to remove overlay rootfs: {}", e));
+        }
         if let Err(e) = FileSystemUtils::remove_path(&rootfs_path) {
             ConsoleLogger::warning(&format!("Failed to remove rootfs directory: {}", e));
         }
@@ -1668,7 +1996,7 @@ done
 
     #[allow(dead_code)]
     pub fn list_containers(&self) -> Vec<String> {
-        if let Ok(containers) = self.containers.try_lock() {
+        if let Ok(containers) = self.containers.lock() {
             containers.keys().cloned().collect()
         } else {
             Vec::new()
@@ -1677,7 +2005,7 @@ done
 
     /// Set the network configuration for a container
     pub fn set_container_network(&self, container_id: &str, network_config: ContainerNetworkConfig) -> Result<(), String> {
-        if let Ok(mut containers) = self.containers.try_lock() {
+        if let Ok(mut containers) = self.containers.lock() {
             if let Some(container) = containers.get_mut(container_id) {
                 container.network_config = Some(network_config);
                 Ok(())
@@ -1691,7 +2019,7 @@ done
 
     /// Get the network configuration for a container
     pub fn get_container_network(&self, container_id: &str) -> Option<ContainerNetworkConfig> {
-        if let Ok(containers) = self.containers.try_lock() {
+        if let Ok(containers) = self.containers.lock() {
             containers.get(container_id).and_then(|c| c.network_config.clone())
         } else {
             None
@@ -1700,7 +2028,7 @@ done
 
     /// Configure network for a running container
     pub fn setup_container_network_post_start(&self, container_id: &str, network_manager: &NetworkManager) -> Result<(), String> {
-        let (network_config, pid) = if let Ok(containers) = self.containers.try_lock() {
+        let (network_config, pid) = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
                 let network_config = container.network_config
                     .as_ref()
@@ -1735,7 +2063,7 @@ done
         ConsoleLogger::debug(&format!("🔍 [EXEC] Working dir: {:?}, Env vars: {}, Capture output: {}", 
                                      working_directory, environment.len(), capture_output));
 
-        let pid = if let Ok(containers) = self.containers.try_lock() {
+        let pid = if let Ok(containers) = self.containers.lock() {
             if let Some(container) = containers.get(container_id) {
                 match container.state {
                     ContainerState::Running => {
@@ -1749,6 +2077,10 @@ done
                             ContainerState::Running => "RUNNING",
                             ContainerState::Exited => "EXITED",
                             ContainerState::Error => "ERROR",
+                            ContainerState::Stopping => "STOPPING",
+                            ContainerState::Removing => "REMOVING",
+                            ContainerState::Restarting => "RESTARTING",
+                            ContainerState::Paused => "PAUSED",
                         };
                         ConsoleLogger::debug(&format!("❌ [EXEC] Container {} is not running, state: {}", container_id, state_msg));
                         Err(format!("Container {} is not running", container_id))
@@ -1762,49 +2094,36 @@ done
         }?;
         ConsoleLogger::debug(&format!("🔓 [EXEC] Released containers lock, got PID: {}", ProcessUtils::pid_to_i32(pid)));
 
-        // Prepare the command to execute
-        let cmd_str = if command.len() == 1 {
-            command[0].clone()
-        } else {
-            command.join(" ")
-        };
-        ConsoleLogger::debug(&format!("📝 [EXEC] Prepared command string: '{}'", cmd_str));
-
-        // Build nsenter command to enter container's namespaces
-        let mut nsenter_cmd = vec![
-            "nsenter".to_string(),
+        // nsenter runs the command directly with exactly these variables; no shell line is built
+        let mut nsenter_args = vec![
             "-t".to_string(), pid.as_raw().to_string(),
             "-p".to_string(), "-m".to_string(), "-n".to_string(), "-u".to_string(), "-i".to_string(),
         ];
 
-        // Add working directory if specified
-        if let Some(workdir) = working_directory {
+        // Add working directory if specified; it must exist in the container, and is reached
+        // through the container's root because nsenter opens it before joining the namespaces
+        if let Some(workdir) = working_directory.filter(|workdir| !workdir.is_empty()) {
+            let workdir = crate::daemon::workdir::prepare_working_directory(pid.as_raw() as i64, &workdir, false)?;
             ConsoleLogger::debug(&format!("📁 [EXEC] Setting working directory: {}", workdir));
-            nsenter_cmd.extend(vec!["--wd".to_string(), workdir]);
-        }
-
-        // Add environment variables
-        for (key, value) in environment {
-            ConsoleLogger::debug(&format!("🌍 [EXEC] Setting env var: {}={}", key, value));
-            nsenter_cmd.extend(vec!["-E".to_string(), format!("{}={}", key, value)]);
+            nsenter_args.push(format!("--wd={}{}", crate::daemon::workdir::container_root(pid.as_raw() as i64).display(), workdir));
         }
 
         // Add the actual command
-        nsenter_cmd.extend(vec!["--".to_string(), "/bin/sh".to_string(), "-c".to_string(), cmd_str.clone()]);
+        nsenter_args.push("--".to_string());
+        nsenter_args.extend(CommandExecutor::exec_form(&command));
+        let argv = Argv::new("nsenter", nsenter_args)
+            .with_environment(CommandExecutor::process_environment(&environment));
         
-        ConsoleLogger::debug(&format!("🚀 [EXEC] Full nsenter command: {:?}", nsenter_cmd));
+        ConsoleLogger::debug(&format!("🚀 [EXEC] Full nsenter command: {}", argv));
         let exec_start = std::time::SystemTime::now();
 
         // Execute the command using nsenter
-        let output = Command::new("nsenter")
-            .args(&nsenter_cmd[1..]) // Skip the "nsenter" part since we're calling it directly
-            .output()
-            .map_err(|e| format!("Failed to execute nsenter: {}", e))?;
+        let result = CommandExecutor::execute(&argv)?;
 
         let elapsed = exec_start.elapsed().unwrap_or_default();
-        let exit_code = output.status.code().unwrap_or(-1);
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let exit_code = result.exit_code.unwrap_or(-1);
+        let stdout = result.stdout;
+        let stderr = result.stderr;
         
         ConsoleLogger::debug(&format!("⏱️ [EXEC] Command completed in {:?}, exit code: {}", elapsed, exit_code));
         if !stdout.is_empty() {
@@ -0,0 +1,118 @@
+// Setup step reports
+// Setup commands run inside the container after it has chrooted into its rootfs, out of reach of
+// the daemon's database. Each finished step is appended as one JSON line to a file at the root
+// of the rootfs, which the daemon follows from the host while the container starts.
+
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Report file name at the root of a container's rootfs
+pub const REPORT_FILE: &str = ".quilt-setup-steps.jsonl";
+/// Captured output kept per step; the tail is kept since that is where the error usually is
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupStepReport {
+    pub index: usize,
+    /// The setup line, e.g. `python: requests flask`
+    pub command: String,
+    pub succeeded: bool,
+    /// Captured stdout/stderr, followed by the error when the step failed
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+/// Report file of the rootfs at `root` (`/` from inside the container)
+pub fn report_path(root: &str) -> PathBuf {
+    Path::new(root).join(REPORT_FILE)
+}
+
+/// Append a finished step
+pub fn append(root: &str, report: &SetupStepReport) -> Result<(), String> {
+    let mut report = report.clone();
+    report.output = truncate_output(&report.output);
+    let mut line = serde_json::to_string(&report)
+        .map_err(|e| format!("Failed to encode setup step report: {}", e))?;
+    line.push('\n');
+
+    let path = report_path(root);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| format!("Failed to write setup step report {}: {}", path.display(), e))
+}
+
+/// Steps reported so far; a line still being written is skipped until it is complete
+pub fn read(root: &str) -> Vec<SetupStepReport> {
+    let content = std::fs::read_to_string(report_path(root)).unwrap_or_default();
+    content.split_inclusive('\n')
+        .filter(|line| line.ends_with('\n'))
+        .filter_map(|line| serde_json::from_str(line.trim()).ok())
+        .collect()
+}
+
+/// Forget the reports of an earlier start
+pub fn clear(root: &str) {
+    let _ = std::fs::remove_file(report_path(root));
+}
+
+fn truncate_output(output: &str) -> String {
+    if output.len() <= MAX_OUTPUT_BYTES {
+        return output.to_string();
+    }
+    let mut start = output.len() - MAX_OUTPUT_BYTES;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[output truncated]\n{}", &output[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(index: usize, succeeded: bool, output: &str) -> SetupStepReport {
+        SetupStepReport {
+            index,
+            command: format!("pip: package{}", index),
+            succeeded,
+            output: output.to_string(),
+            duration_ms: 10,
+        }
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        assert!(read(root).is_empty());
+
+        append(root, &report(0, true, "Successfully installed package0")).unwrap();
+        append(root, &report(1, false, "ERROR: No matching distribution found")).unwrap();
+        // A line the container is still writing is not reported yet
+        OpenOptions::new().append(true).open(report_path(root)).unwrap()
+            .write_all(b"{\"index\":2,\"comm").unwrap();
+
+        let reports = read(root);
+        assert_eq!(reports, vec![
+            report(0, true, "Successfully installed package0"),
+            report(1, false, "ERROR: No matching distribution found"),
+        ]);
+
+        clear(root);
+        assert!(read(root).is_empty());
+    }
+
+    #[test]
+    fn test_output_truncated_to_tail() {
+        let output = format!("{}the error", "x".repeat(MAX_OUTPUT_BYTES));
+        let truncated = truncate_output(&output);
+        assert!(truncated.starts_with("[output truncated]\n"));
+        assert!(truncated.ends_with("the error"));
+        assert!(truncated.len() < output.len() + 20);
+    }
+}
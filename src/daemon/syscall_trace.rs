@@ -0,0 +1,368 @@
+// Syscall tracing
+// Debugging aid: attaches to every process of a running container with PTRACE_SEIZE, follows
+// forks and clones, and counts the syscalls they make (PTRACE_GET_SYSCALL_INFO, so no per-arch
+// register decoding). Summaries stream out periodically; the set of syscalls seen is what a tight
+// seccomp allow-list needs. A seccomp user-notification listener would have to be installed by
+// the container init before exec, so it cannot attach to containers that are already running;
+// ptrace can, at the cost of two stops per syscall while the trace lasts.
+
+use crate::daemon::syscall_table::syscall_name;
+use crate::utils::console::ConsoleLogger;
+use nix::libc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+pub const DEFAULT_TRACE_SECONDS: u32 = 10;
+pub const MAX_TRACE_SECONDS: u32 = 300;
+const DEFAULT_SUMMARY_INTERVAL_MS: u32 = 1000;
+const MIN_SUMMARY_INTERVAL_MS: u32 = 100;
+/// Threads traced at once; forks beyond this run untraced
+const MAX_TRACED_THREADS: usize = 4096;
+
+const PTRACE_GET_SYSCALL_INFO: libc::c_uint = 0x420e;
+const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+const PTRACE_SYSCALL_INFO_EXIT: u8 = 2;
+const PTRACE_EVENT_STOP: libc::c_int = 128;
+
+/// Limits for one trace; zero values fall back to defaults
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    pub duration_seconds: u32,
+    pub summary_interval_ms: u32,
+}
+
+impl TraceOptions {
+    /// Apply defaults and reject limits above the hard caps
+    pub fn normalized(mut self) -> Result<Self, String> {
+        if self.duration_seconds == 0 {
+            self.duration_seconds = DEFAULT_TRACE_SECONDS;
+        }
+        if self.summary_interval_ms == 0 {
+            self.summary_interval_ms = DEFAULT_SUMMARY_INTERVAL_MS;
+        }
+        if self.duration_seconds > MAX_TRACE_SECONDS {
+            return Err(format!("Trace duration cannot exceed {} seconds", MAX_TRACE_SECONDS));
+        }
+        self.summary_interval_ms = self.summary_interval_ms.max(MIN_SUMMARY_INTERVAL_MS);
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallCount {
+    pub name: String,
+    pub count: u64,
+    /// Calls that returned an error
+    pub errors: u64,
+}
+
+/// Cumulative state of a trace
+#[derive(Debug, Clone, Default)]
+pub struct TraceSummary {
+    pub elapsed_ms: u64,
+    /// Threads being traced right now
+    pub threads_traced: u64,
+    pub total_syscalls: u64,
+    /// Most frequent first
+    pub syscalls: Vec<SyscallCount>,
+    /// Syscalls first seen since the previous summary
+    pub new_syscalls: Vec<String>,
+}
+
+/// Output of a running trace
+pub enum TraceEvent {
+    Summary(TraceSummary),
+    Finished(TraceSummary),
+    Failed(String),
+}
+
+pub struct SyscallTracer;
+
+impl SyscallTracer {
+    /// Trace `root_pid` and all its descendants. The trace ends after the duration, when every
+    /// traced process has exited, or as soon as the receiver is dropped; tracees are detached
+    /// and keep running either way.
+    pub fn start(root_pid: i32, options: TraceOptions) -> Result<mpsc::Receiver<TraceEvent>, String> {
+        let options = options.normalized()?;
+        let pids = process_tree(root_pid);
+        if pids.is_empty() {
+            return Err(format!("Process {} is not running", root_pid));
+        }
+
+        let (tx, rx) = mpsc::channel(16);
+        // ptrace requests are only accepted from the thread that attached
+        std::thread::Builder::new()
+            .name(format!("quilt-trace-{}", root_pid))
+            .spawn(move || {
+                let mut trace = Trace::new(options);
+                if let Err(e) = trace.attach_all(&pids) {
+                    trace.detach_all();
+                    let _ = tx.blocking_send(TraceEvent::Failed(e));
+                    return;
+                }
+                ConsoleLogger::debug(&format!("🔎 [TRACE] Tracing {} threads under PID {}", trace.threads.len(), root_pid));
+                let summary = trace.run(&tx);
+                trace.detach_all();
+                let _ = tx.blocking_send(TraceEvent::Finished(summary));
+            })
+            .map_err(|e| format!("Failed to start trace thread: {}", e))?;
+        Ok(rx)
+    }
+}
+
+/// `struct ptrace_syscall_info`; the union is read as raw words
+#[repr(C)]
+#[derive(Default)]
+struct PtraceSyscallInfo {
+    op: u8,
+    _pad: [u8; 3],
+    arch: u32,
+    instruction_pointer: u64,
+    stack_pointer: u64,
+    /// entry: nr, args[6]; exit: rval, is_error
+    data: [u64; 8],
+}
+
+struct Trace {
+    options: TraceOptions,
+    started: Instant,
+    /// Traced thread → syscall it is inside of (set at entry, cleared at exit)
+    threads: HashMap<i32, Option<u64>>,
+    counts: HashMap<u64, (u64, u64)>,
+    reported: HashSet<u64>,
+    total: u64,
+}
+
+impl Trace {
+    fn new(options: TraceOptions) -> Self {
+        Self {
+            options,
+            started: Instant::now(),
+            threads: HashMap::new(),
+            counts: HashMap::new(),
+            reported: HashSet::new(),
+            total: 0,
+        }
+    }
+
+    fn attach_all(&mut self, pids: &[i32]) -> Result<(), String> {
+        let options = libc::PTRACE_O_TRACESYSGOOD | libc::PTRACE_O_TRACECLONE
+            | libc::PTRACE_O_TRACEFORK | libc::PTRACE_O_TRACEVFORK;
+        for pid in pids {
+            for tid in threads_of(*pid) {
+                if self.threads.contains_key(&tid) {
+                    continue;
+                }
+                if ptrace(libc::PTRACE_SEIZE, tid, 0, options as usize) != 0 {
+                    let err = std::io::Error::last_os_error();
+                    // Threads exit while we walk /proc; only the first process is mandatory
+                    if self.threads.is_empty() {
+                        return Err(format!("Failed to attach to {}: {}", tid, err));
+                    }
+                    continue;
+                }
+                // The interrupt stop is where syscall tracing gets switched on
+                ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0);
+                self.threads.insert(tid, None);
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, tx: &mpsc::Sender<TraceEvent>) -> TraceSummary {
+        let deadline = self.started + Duration::from_secs(self.options.duration_seconds as u64);
+        let interval = Duration::from_millis(self.options.summary_interval_ms as u64);
+        let mut next_summary = Instant::now() + interval;
+
+        while Instant::now() < deadline && !self.threads.is_empty() && !tx.is_closed() {
+            // waitpid(-1) would also reap the daemon's own children, so poll each tracee
+            let tids: Vec<i32> = self.threads.keys().copied().collect();
+            let mut stopped_any = false;
+            for tid in tids {
+                let mut status = 0;
+                let rc = unsafe { libc::waitpid(tid, &mut status, libc::WNOHANG | libc::__WALL) };
+                if rc == tid {
+                    stopped_any = true;
+                    self.handle_stop(tid, status);
+                } else if rc < 0 {
+                    self.threads.remove(&tid);
+                }
+            }
+            if !stopped_any {
+                std::thread::sleep(Duration::from_micros(200));
+            }
+            if Instant::now() >= next_summary {
+                next_summary = Instant::now() + interval;
+                if tx.blocking_send(TraceEvent::Summary(self.summary())).is_err() {
+                    break;
+                }
+            }
+        }
+        self.summary()
+    }
+
+    fn handle_stop(&mut self, tid: i32, status: libc::c_int) {
+        if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+            self.threads.remove(&tid);
+            return;
+        }
+        if !libc::WIFSTOPPED(status) {
+            return;
+        }
+
+        let signal = libc::WSTOPSIG(status);
+        let event = status >> 16;
+        let mut inject = 0;
+        if signal == (libc::SIGTRAP | 0x80) {
+            self.record_syscall(tid);
+        } else if event == libc::PTRACE_EVENT_CLONE || event == libc::PTRACE_EVENT_FORK || event == libc::PTRACE_EVENT_VFORK {
+            // New tracees are attached automatically and start with their own stop
+            let mut child: libc::c_ulong = 0;
+            if ptrace(libc::PTRACE_GETEVENTMSG, tid, 0, &mut child as *mut libc::c_ulong as usize) == 0
+                && self.threads.len() < MAX_TRACED_THREADS {
+                self.threads.insert(child as i32, None);
+            }
+        } else if event != PTRACE_EVENT_STOP && signal != libc::SIGTRAP {
+            // Signal-delivery stop: pass the signal on
+            inject = signal;
+        }
+        ptrace(libc::PTRACE_SYSCALL, tid, 0, inject as usize);
+    }
+
+    fn record_syscall(&mut self, tid: i32) {
+        let mut info = PtraceSyscallInfo::default();
+        let size = std::mem::size_of::<PtraceSyscallInfo>();
+        if ptrace(PTRACE_GET_SYSCALL_INFO, tid, size, &mut info as *mut PtraceSyscallInfo as usize) <= 0 {
+            return;
+        }
+        match info.op {
+            PTRACE_SYSCALL_INFO_ENTRY => {
+                let nr = info.data[0];
+                // Counted at entry so exit/execve, which never return, show up too
+                self.counts.entry(nr).or_default().0 += 1;
+                self.total += 1;
+                self.threads.insert(tid, Some(nr));
+            }
+            PTRACE_SYSCALL_INFO_EXIT => {
+                let is_error = info.data[1] & 0xff != 0;
+                if let Some(nr) = self.threads.insert(tid, None).flatten() {
+                    if is_error {
+                        self.counts.entry(nr).or_default().1 += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn summary(&mut self) -> TraceSummary {
+        let mut new_syscalls = BTreeSet::new();
+        for nr in self.counts.keys() {
+            if self.reported.insert(*nr) {
+                new_syscalls.insert(display_name(*nr));
+            }
+        }
+        TraceSummary {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            threads_traced: self.threads.len() as u64,
+            total_syscalls: self.total,
+            syscalls: sorted_counts(&self.counts),
+            new_syscalls: new_syscalls.into_iter().collect(),
+        }
+    }
+
+    /// Stop every tracee once more and let it go
+    fn detach_all(&mut self) {
+        for (tid, _) in self.threads.drain() {
+            ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0);
+            let mut status = 0;
+            if unsafe { libc::waitpid(tid, &mut status, libc::__WALL) } != tid || !libc::WIFSTOPPED(status) {
+                continue;
+            }
+            // The stop collected may be a pending signal rather than the interrupt; don't lose it
+            let stop_signal = libc::WSTOPSIG(status);
+            let is_trace_stop = status >> 16 == PTRACE_EVENT_STOP || stop_signal == (libc::SIGTRAP | 0x80) || stop_signal == libc::SIGTRAP;
+            let signal = if is_trace_stop { 0 } else { stop_signal };
+            ptrace(libc::PTRACE_DETACH, tid, 0, signal as usize);
+        }
+    }
+}
+
+/// ptrace(2) with addr and data passed as full pointer-width words
+fn ptrace(request: libc::c_uint, tid: i32, addr: usize, data: usize) -> libc::c_long {
+    unsafe { libc::ptrace(request, tid as libc::pid_t, addr as *mut libc::c_void, data as *mut libc::c_void) }
+}
+
+fn display_name(nr: u64) -> String {
+    syscall_name(nr).map(str::to_string).unwrap_or_else(|| format!("syscall_{}", nr))
+}
+
+fn sorted_counts(counts: &HashMap<u64, (u64, u64)>) -> Vec<SyscallCount> {
+    let mut sorted: Vec<SyscallCount> = counts.iter()
+        .map(|(nr, (count, errors))| SyscallCount { name: display_name(*nr), count: *count, errors: *errors })
+        .collect();
+    sorted.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    sorted
+}
+
+/// `root` and every descendant, from the PPid fields in /proc
+fn process_tree(root: i32) -> Vec<i32> {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for entry in std::fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>() else { continue };
+        if let Some(ppid) = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok().and_then(|stat| parent_pid(&stat)) {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    if !std::path::Path::new(&format!("/proc/{}", root)).exists() {
+        return Vec::new();
+    }
+    let mut tree = vec![root];
+    let mut index = 0;
+    while index < tree.len() {
+        if let Some(kids) = children.get(&tree[index]) {
+            tree.extend(kids);
+        }
+        index += 1;
+    }
+    tree
+}
+
+/// PPid from /proc/<pid>/stat; the command name may contain spaces and parentheses
+fn parent_pid(stat: &str) -> Option<i32> {
+    stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn threads_of(pid: i32) -> Vec<i32> {
+    std::fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|entries| entries.flatten()
+            .filter_map(|entry| entry.file_name().to_string_lossy().parse().ok())
+            .collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_pid() {
+        assert_eq!(parent_pid("42 (sh) S 7 42 42 0 -1"), Some(7));
+        assert_eq!(parent_pid("43 (my (odd) name) R 42 43 1 0"), Some(42));
+        assert_eq!(parent_pid("garbage"), None);
+    }
+
+    #[test]
+    fn test_trace_options_and_counts() {
+        let options = TraceOptions { duration_seconds: 0, summary_interval_ms: 10 }.normalized().unwrap();
+        assert_eq!(options.duration_seconds, DEFAULT_TRACE_SECONDS);
+        assert_eq!(options.summary_interval_ms, MIN_SUMMARY_INTERVAL_MS);
+        assert!(TraceOptions { duration_seconds: MAX_TRACE_SECONDS + 1, summary_interval_ms: 0 }.normalized().is_err());
+
+        let counts = HashMap::from([(100_000, (3, 0)), (435, (5, 1))]);
+        let sorted = sorted_counts(&counts);
+        assert_eq!(sorted[0], SyscallCount { name: "clone3".to_string(), count: 5, errors: 1 });
+        assert_eq!(sorted[1].name, "syscall_100000");
+    }
+}
@@ -159,7 +159,7 @@ impl SystemRuntime {
         }
 
         // Check if binaries are from Nix store
-        if let Ok(result) = CommandExecutor::execute_shell("ls -la /bin/* 2>/dev/null | head -5") {
+        if let Ok(result) = CommandExecutor::run("ls", ["-la", "/bin/"]) {
             if result.stdout.contains("/nix/store") {
                 return true;
             }
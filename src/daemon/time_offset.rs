@@ -0,0 +1,271 @@
+// Per-container clock offsets
+// A container can run with its clocks moved by a fixed offset, so time-sensitive code (expiry,
+// renewals, scheduled jobs) can be tested without touching the host clock. The wall clock cannot
+// be namespaced, so it is shifted by libfaketime when the host has it: the library is copied into
+// the rootfs and preloaded into the container's command and exec sessions. That only works for
+// images built on a glibc for the library's architecture; others keep the real wall clock.
+// The monotonic and boot-time clocks have their own offsets, applied with a Linux time
+// namespace (5.6+).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the host's libfaketime.so.1 when it is not in a standard location
+pub const LIBFAKETIME_ENV: &str = "QUILT_LIBFAKETIME";
+
+const LIBFAKETIME_CANDIDATES: &[&str] = &[
+    "/usr/lib/x86_64-linux-gnu/faketime/libfaketime.so.1",
+    "/usr/lib/aarch64-linux-gnu/faketime/libfaketime.so.1",
+    "/usr/lib64/faketime/libfaketime.so.1",
+    "/usr/lib/faketime/libfaketime.so.1",
+    "/usr/local/lib/faketime/libfaketime.so.1",
+];
+
+/// Where the library is placed inside the rootfs
+pub const CONTAINER_LIBFAKETIME: &str = "/usr/lib/quilt/libfaketime.so.1";
+
+/// Offsets beyond a century are almost certainly a typo
+const MAX_OFFSET_SECONDS: i64 = 100 * 365 * 86_400;
+
+/// Parse an offset such as `+7d`, `-2h30m`, `90s` or a bare number of seconds
+pub fn parse_time_offset(spec: &str) -> Result<i64, String> {
+    let spec = spec.trim();
+    let (sign, body) = match spec.strip_prefix('-') {
+        Some(body) => (-1, body),
+        None => (1, spec.strip_prefix('+').unwrap_or(spec)),
+    };
+    if body.is_empty() {
+        return Err(format!("Invalid time offset '{}': expected e.g. +7d, -2h30m or 3600", spec));
+    }
+    if let Ok(seconds) = body.parse::<i64>() {
+        return checked_offset(spec, sign * seconds);
+    }
+
+    let mut total: i64 = 0;
+    let mut digits = String::new();
+    for c in body.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 7 * 86_400,
+            _ => return Err(format!("Invalid time offset '{}': unknown unit '{}' (use s, m, h, d or w)", spec, c)),
+        };
+        let value: i64 = digits.parse()
+            .map_err(|_| format!("Invalid time offset '{}': missing number before '{}'", spec, c))?;
+        total = value.checked_mul(unit).and_then(|value| total.checked_add(value))
+            .ok_or_else(|| format!("Time offset '{}' is too large", spec))?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!("Invalid time offset '{}': '{}' has no unit", spec, digits));
+    }
+    checked_offset(spec, sign * total)
+}
+
+fn checked_offset(spec: &str, seconds: i64) -> Result<i64, String> {
+    if seconds.unsigned_abs() > MAX_OFFSET_SECONDS as u64 {
+        return Err(format!("Time offset '{}' is too large (at most 100 years)", spec));
+    }
+    Ok(seconds)
+}
+
+/// Check the offsets requested for a container's time namespace
+pub fn validate_namespace_offsets(monotonic_seconds: i64, boottime_seconds: i64) -> Result<(), String> {
+    for (clock, seconds) in [("monotonic", monotonic_seconds), ("boottime", boottime_seconds)] {
+        if seconds.unsigned_abs() > MAX_OFFSET_SECONDS as u64 {
+            return Err(format!("{} offset of {}s is too large (at most 100 years)", clock, seconds));
+        }
+    }
+    Ok(())
+}
+
+/// Render an offset the way it is accepted, e.g. `+3600s`
+pub fn format_time_offset(seconds: i64) -> String {
+    format!("{:+}s", seconds)
+}
+
+/// The host's libfaketime, if any
+pub fn libfaketime() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(LIBFAKETIME_ENV) {
+        return Some(PathBuf::from(path)).filter(|path| path.is_file());
+    }
+    LIBFAKETIME_CANDIDATES.iter().map(PathBuf::from).find(|path| path.is_file())
+}
+
+/// Add the variables that make a process inside the container see the wall clock moved by
+/// `seconds`; libfaketime goes after anything already in LD_PRELOAD
+pub fn add_faketime_environment(environment: &mut HashMap<String, String>, seconds: i64) {
+    let preload = match environment.get("LD_PRELOAD").map(|preload| preload.trim()) {
+        Some(preload) if preload.split([':', ' ']).any(|library| library == CONTAINER_LIBFAKETIME) => preload.to_string(),
+        Some(preload) if !preload.is_empty() => format!("{}:{}", preload, CONTAINER_LIBFAKETIME),
+        _ => CONTAINER_LIBFAKETIME.to_string(),
+    };
+    environment.insert("LD_PRELOAD".to_string(), preload);
+    // Relative offset from the real clock; libfaketime accepts plain seconds with a sign
+    environment.insert("FAKETIME".to_string(), format!("{:+}", seconds));
+    // Monotonic clocks are the time namespace's job
+    environment.insert("DONT_FAKE_MONOTONIC".to_string(), "1".to_string());
+}
+
+/// The ELF machine of `path`, if it is an ELF file
+fn elf_machine(path: &Path) -> Option<u16> {
+    use std::io::Read;
+
+    let mut header = [0u8; 20];
+    fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[..4] != b"\x7fELF" {
+        return None;
+    }
+    let machine = [header[18], header[19]];
+    // EI_DATA: 1 little-endian, 2 big-endian
+    Some(if header[5] == 2 { u16::from_be_bytes(machine) } else { u16::from_le_bytes(machine) })
+}
+
+/// Follow symlinks the way the container would, keeping absolute targets inside `rootfs`
+fn resolve_in_rootfs(rootfs: &Path, path: PathBuf) -> PathBuf {
+    let mut path = path;
+    for _ in 0..8 {
+        let Ok(target) = fs::read_link(&path) else { break };
+        path = match target.strip_prefix("/") {
+            Ok(relative) => rootfs.join(relative),
+            Err(_) => path.parent().map_or(target.clone(), |parent| parent.join(&target)),
+        };
+    }
+    path
+}
+
+/// The ELF machines of the glibc dynamic loaders (`ld-linux*.so.*`) in `rootfs`; an image with
+/// none (musl, static, distroless without libc) cannot load a glibc libfaketime
+fn glibc_loader_machines(rootfs: &Path) -> Vec<u16> {
+    ["lib", "lib64", "usr/lib", "usr/lib64"].iter()
+        .filter_map(|dir| fs::read_dir(rootfs.join(dir)).ok())
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("ld-linux"))
+        .filter_map(|entry| elf_machine(&resolve_in_rootfs(rootfs, entry.path())))
+        .collect()
+}
+
+/// Copy libfaketime into `rootfs`, after checking that the image's dynamic loader can load it.
+/// An error means the wall clock stays unshifted; the caller decides whether that is fatal.
+pub fn install_faketime(rootfs: &str) -> Result<(), String> {
+    let library = libfaketime().ok_or_else(|| format!(
+        "libfaketime not found (install it or set {}); the wall clock is not shifted", LIBFAKETIME_ENV))?;
+    let machine = elf_machine(&library)
+        .ok_or_else(|| format!("{} is not an ELF library; the wall clock is not shifted", library.display()))?;
+    let loaders = glibc_loader_machines(Path::new(rootfs));
+    if loaders.is_empty() {
+        return Err("the image has no glibc dynamic loader (musl or static image) and cannot load libfaketime; \
+            the wall clock is not shifted".to_string());
+    }
+    if !loaders.contains(&machine) {
+        return Err(format!("{} is built for another architecture than the image; the wall clock is not shifted",
+            library.display()));
+    }
+    copy_into_rootfs(&library, Path::new(rootfs), CONTAINER_LIBFAKETIME)
+        .map_err(|e| format!("Failed to copy {} into the container: {}", library.display(), e))
+}
+
+/// Copy a host file to `target` inside the rootfs without following symlinks the image may have
+/// planted on the way (e.g. usr/lib/quilt pointing back at a host directory)
+fn copy_into_rootfs(source: &Path, rootfs: &Path, target: &str) -> Result<(), String> {
+    use nix::fcntl::{openat, OFlag};
+    use nix::sys::stat::Mode;
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let target = Path::new(target);
+    let (parent, name) = match (target.parent(), target.file_name()) {
+        (Some(parent), Some(name)) => (parent, name),
+        _ => return Err(format!("'{}' does not name a file", target.display())),
+    };
+    let directory = crate::daemon::namespace::open_directory_in_root(rootfs, &parent.to_string_lossy())?;
+    let fd = openat(
+        directory.as_raw_fd(),
+        name,
+        OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC,
+        Mode::from_bits_truncate(0o755),
+    ).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    // SAFETY: openat returned a fresh descriptor that nothing else owns
+    let mut destination = unsafe { fs::File::from_raw_fd(fd) };
+    let mut source = fs::File::open(source).map_err(|e| e.to_string())?;
+    std::io::copy(&mut source, &mut destination).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Contents of /proc/self/timens_offsets for the monotonic and boot-time clocks
+fn timens_offsets(monotonic_seconds: i64, boottime_seconds: i64) -> String {
+    format!("monotonic {} 0\nboottime {} 0\n", monotonic_seconds, boottime_seconds)
+}
+
+/// Create a time namespace with the given offsets for the calling process's children and its
+/// next exec (Linux 6.0+; on 5.6-5.19 only children enter it). Must run before the process
+/// forks anything that should see the offsets.
+pub fn enter_time_namespace(monotonic_seconds: i64, boottime_seconds: i64) -> Result<(), String> {
+    // SAFETY: unshare only changes the namespaces of the calling process
+    if unsafe { nix::libc::unshare(nix::libc::CLONE_NEWTIME) } != 0 {
+        return Err(format!("Failed to create time namespace (Linux 5.6+ required): {}", std::io::Error::last_os_error()));
+    }
+    // Offsets may only be written while the namespace has no members yet
+    fs::write("/proc/self/timens_offsets", timens_offsets(monotonic_seconds, boottime_seconds))
+        .map_err(|e| format!("Failed to set time namespace offsets: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_offset() {
+        assert_eq!(parse_time_offset("3600"), Ok(3600));
+        assert_eq!(parse_time_offset("+7d"), Ok(7 * 86_400));
+        assert_eq!(parse_time_offset("-2h30m"), Ok(-(2 * 3_600 + 30 * 60)));
+        assert_eq!(parse_time_offset(" 1w1s "), Ok(7 * 86_400 + 1));
+        assert!(parse_time_offset("").is_err());
+        assert!(parse_time_offset("+").is_err());
+        assert!(parse_time_offset("5x").is_err());
+        assert!(parse_time_offset("3h15").is_err());
+        assert!(parse_time_offset("200000d").is_err());
+        assert_eq!(format_time_offset(-90), "-90s");
+        assert_eq!(parse_time_offset(&format_time_offset(-90)), Ok(-90));
+        assert!(validate_namespace_offsets(86_400, -60).is_ok());
+        assert!(validate_namespace_offsets(0, MAX_OFFSET_SECONDS + 1).is_err());
+    }
+
+    #[test]
+    fn test_faketime_setup() {
+        let mut environment = HashMap::new();
+        add_faketime_environment(&mut environment, -86_400);
+        assert_eq!(environment["FAKETIME"], "-86400");
+        assert_eq!(environment["LD_PRELOAD"], CONTAINER_LIBFAKETIME);
+
+        let mut environment = HashMap::from([("LD_PRELOAD".to_string(), "/usr/lib/libjemalloc.so".to_string())]);
+        add_faketime_environment(&mut environment, 60);
+        add_faketime_environment(&mut environment, 60);
+        assert_eq!(environment["LD_PRELOAD"], format!("/usr/lib/libjemalloc.so:{}", CONTAINER_LIBFAKETIME));
+
+        let rootfs = std::env::temp_dir().join(format!("quilt-faketime-test-{}", std::process::id()));
+        fs::create_dir_all(rootfs.join("lib")).unwrap();
+        assert!(glibc_loader_machines(&rootfs).is_empty());
+        let mut loader = b"\x7fELF\x02\x01\x01".to_vec();
+        loader.resize(18, 0);
+        loader.extend_from_slice(&62u16.to_le_bytes());
+        fs::write(rootfs.join("lib/ld-linux-x86-64.so.2"), &loader).unwrap();
+        assert_eq!(glibc_loader_machines(&rootfs), vec![62]);
+
+        let loader_path = rootfs.join("lib/ld-linux-x86-64.so.2");
+        copy_into_rootfs(&loader_path, &rootfs, CONTAINER_LIBFAKETIME).unwrap();
+        assert_eq!(fs::read(rootfs.join("usr/lib/quilt/libfaketime.so.1")).unwrap(), loader);
+        // A symlinked directory must not carry the copy out of the rootfs
+        std::os::unix::fs::symlink(rootfs.join("lib"), rootfs.join("opt")).unwrap();
+        assert!(copy_into_rootfs(&loader_path, &rootfs, "/opt/libfaketime.so.1").is_err());
+        fs::remove_dir_all(&rootfs).unwrap();
+        assert_eq!(timens_offsets(60, -5), "monotonic 60 0\nboottime -5 0\n");
+    }
+}
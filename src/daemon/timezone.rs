@@ -0,0 +1,180 @@
+// Container timezone and locale
+// Images disagree about local time: many ship no tzdata and run in UTC, others carry an
+// /etc/localtime symlink into a zoneinfo tree that is not there. A container can be given a zone
+// instead: the host's /usr/share/zoneinfo entry is bind-mounted read-only over /etc/localtime
+// and, when the image lacks it, at its own zoneinfo path, and TZ names it. A locale sets LANG.
+// Both fall back to daemon-wide defaults from QUILT_DEFAULT_TIMEZONE and QUILT_DEFAULT_LOCALE;
+// variables the container sets itself win over either.
+
+use crate::utils::console::ConsoleLogger;
+use nix::mount::{mount, MsFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Zone of containers created without one, e.g. `Europe/Berlin` or `host`
+pub const DEFAULT_TIMEZONE_ENV: &str = "QUILT_DEFAULT_TIMEZONE";
+/// LANG of containers created without a locale, e.g. `C.UTF-8`
+pub const DEFAULT_LOCALE_ENV: &str = "QUILT_DEFAULT_LOCALE";
+/// Where zone files live, on the host and in images
+pub const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+/// Check a zone name such as `Europe/Berlin` or `UTC` without touching the filesystem
+fn validate_zone_name(zone: &str) -> Result<(), String> {
+    let valid_chars = zone.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'));
+    let valid_parts = zone.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+    if zone.is_empty() || !valid_chars || !valid_parts {
+        return Err(format!("Invalid timezone '{}': expected a zone name such as Europe/Berlin or UTC", zone));
+    }
+    Ok(())
+}
+
+/// Zone name of a path into a zoneinfo tree, e.g. `/usr/share/zoneinfo/Europe/Berlin`
+fn zone_from_path(path: &Path) -> Option<String> {
+    let path = path.to_string_lossy();
+    let (_, zone) = path.split_once("zoneinfo/")?;
+    // Some distributions link into the posix/ copy of the tree
+    let zone = zone.strip_prefix("posix/").unwrap_or(zone);
+    validate_zone_name(zone).ok().map(|_| zone.to_string())
+}
+
+/// The host's zone, from /etc/timezone or where /etc/localtime links to
+fn host_timezone() -> Result<String, String> {
+    let named = fs::read_to_string("/etc/timezone").ok()
+        .map(|zone| zone.trim().to_string())
+        .filter(|zone| validate_zone_name(zone).is_ok());
+    named
+        .or_else(|| fs::read_link("/etc/localtime").ok().and_then(|link| zone_from_path(&link)))
+        .ok_or_else(|| "Cannot determine the host's timezone: /etc/localtime is not a link into a zoneinfo tree".to_string())
+}
+
+/// Resolve a requested zone (`host` for the host's own) to a zone name the host has a file for
+pub fn resolve_timezone(spec: &str) -> Result<String, String> {
+    let spec = spec.trim();
+    let zone = if spec.eq_ignore_ascii_case("host") { host_timezone()? } else { spec.to_string() };
+    validate_zone_name(&zone)?;
+    if !zone_file(&zone).is_file() {
+        return Err(format!("Unknown timezone '{}': {} has no such zone", zone, ZONEINFO_DIR));
+    }
+    Ok(zone)
+}
+
+/// Check a locale such as `en_US.UTF-8` or `C.UTF-8`
+pub fn validate_locale(locale: &str) -> Result<(), String> {
+    let valid = locale.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '@'));
+    if locale.is_empty() || !valid {
+        return Err(format!("Invalid locale '{}': expected e.g. en_US.UTF-8 or C.UTF-8", locale));
+    }
+    Ok(())
+}
+
+/// Host file of a validated zone
+pub fn zone_file(zone: &str) -> PathBuf {
+    Path::new(ZONEINFO_DIR).join(zone)
+}
+
+/// The daemon-wide zone and locale; invalid settings are ignored with a warning
+pub fn defaults() -> (Option<String>, Option<String>) {
+    let setting = |name: &str| std::env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+    let timezone = setting(DEFAULT_TIMEZONE_ENV).and_then(|spec| resolve_timezone(&spec)
+        .map_err(|e| ConsoleLogger::warning(&format!("Ignoring {}: {}", DEFAULT_TIMEZONE_ENV, e)))
+        .ok());
+    let locale = setting(DEFAULT_LOCALE_ENV).filter(|locale| validate_locale(locale)
+        .map_err(|e| ConsoleLogger::warning(&format!("Ignoring {}: {}", DEFAULT_LOCALE_ENV, e)))
+        .is_ok());
+    (timezone, locale)
+}
+
+/// Variables naming the zone and locale
+pub fn environment(timezone: Option<&str>, locale: Option<&str>) -> Vec<(String, String)> {
+    let mut environment = Vec::new();
+    if let Some(zone) = timezone {
+        environment.push(("TZ".to_string(), zone.to_string()));
+    }
+    if let Some(locale) = locale {
+        environment.push(("LANG".to_string(), locale.to_string()));
+    }
+    environment
+}
+
+/// Rootfs paths the zone file is mounted at: /etc/localtime always, the zone's own path only
+/// when the image does not carry it
+fn mount_targets(rootfs_path: &str, zone: &str) -> Vec<PathBuf> {
+    let rootfs = Path::new(rootfs_path);
+    let mut targets = vec![rootfs.join("etc/localtime")];
+    let own = rootfs.join(ZONEINFO_DIR.trim_start_matches('/')).join(zone);
+    if !own.is_file() {
+        targets.push(own);
+    }
+    targets
+}
+
+/// A regular file to mount over; a symlink (usually into a zoneinfo tree) is replaced, since
+/// the mount would follow it
+fn prepare_target(target: &Path) -> Result<(), String> {
+    match fs::symlink_metadata(target) {
+        Ok(metadata) if metadata.is_file() => return Ok(()),
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(target).map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+        }
+        Ok(_) => return Err(format!("{} is not a file", target.display())),
+        Err(_) => {}
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::File::create(target).map(|_| ()).map_err(|e| format!("Failed to create {}: {}", target.display(), e))
+}
+
+/// Bind the host's zone file read-only into the rootfs. Runs in the child before chroot.
+pub fn mount_timezone(rootfs_path: &str, zone: &str) -> Result<(), String> {
+    let source = zone_file(zone);
+    if !source.is_file() {
+        return Err(format!("Host has no zone file {}", source.display()));
+    }
+    for target in mount_targets(rootfs_path, zone) {
+        prepare_target(&target)?;
+        mount(Some(source.as_path()), target.as_path(), None::<&str>, MsFlags::MS_BIND, None::<&str>)
+            .and_then(|_| mount(None::<&str>, target.as_path(), None::<&str>, MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY, None::<&str>))
+            .map_err(|e| format!("Failed to mount {} at {}: {}", source.display(), target.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timezone_settings() {
+        for zone in ["UTC", "Europe/Berlin", "America/Argentina/Buenos_Aires", "Etc/GMT+5"] {
+            assert!(validate_zone_name(zone).is_ok(), "{}", zone);
+        }
+        for zone in ["", "/etc/passwd", "../../etc/shadow", "Europe//Berlin", "Europe/Berlin\n"] {
+            assert!(validate_zone_name(zone).is_err(), "{:?}", zone);
+        }
+        assert_eq!(zone_from_path(Path::new("../usr/share/zoneinfo/Asia/Tokyo")).as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(zone_from_path(Path::new("/usr/share/zoneinfo/posix/Europe/Paris")).as_deref(), Some("Europe/Paris"));
+        assert_eq!(zone_from_path(Path::new("/etc/alternatives/localtime")), None);
+
+        assert!(validate_locale("en_US.UTF-8").is_ok());
+        assert!(validate_locale("de_DE@euro").is_ok());
+        assert!(validate_locale("C UTF-8").is_err());
+        assert_eq!(environment(Some("Asia/Tokyo"), Some("C.UTF-8")),
+            vec![("TZ".to_string(), "Asia/Tokyo".to_string()), ("LANG".to_string(), "C.UTF-8".to_string())]);
+
+        // The image's own zone file is left alone; a missing one is mounted too
+        let rootfs = tempfile::tempdir().unwrap();
+        let rootfs_path = rootfs.path().to_str().unwrap();
+        assert_eq!(mount_targets(rootfs_path, "Asia/Tokyo").len(), 2);
+        fs::create_dir_all(rootfs.path().join("usr/share/zoneinfo/Asia")).unwrap();
+        fs::write(rootfs.path().join("usr/share/zoneinfo/Asia/Tokyo"), "TZif").unwrap();
+        assert_eq!(mount_targets(rootfs_path, "Asia/Tokyo"), vec![rootfs.path().join("etc/localtime")]);
+
+        // A localtime symlink is replaced by a file to mount over
+        fs::create_dir_all(rootfs.path().join("etc")).unwrap();
+        let localtime = rootfs.path().join("etc/localtime");
+        std::os::unix::fs::symlink("/usr/share/zoneinfo/Etc/UTC", &localtime).unwrap();
+        prepare_target(&localtime).unwrap();
+        assert!(fs::symlink_metadata(&localtime).unwrap().is_file());
+    }
+}
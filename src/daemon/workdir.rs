@@ -0,0 +1,129 @@
+// Exec working directories
+// An exec may name the directory its command starts in. The directory is looked up in the
+// container's own view of its filesystem, through /proc/<pid>/root, so volumes and other mounts
+// made inside the container count. Symlinks are resolved the way the container would resolve
+// them: `..` stops at its root and absolute targets start over from it, never from the host's.
+// A missing directory is an error unless the exec asks for it to be created.
+
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+/// Symlinks followed while resolving one path, as the kernel allows
+const MAX_SYMLINKS: usize = 40;
+
+fn path_components(path: &Path) -> impl Iterator<Item = OsString> + '_ {
+    path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_os_string()),
+        Component::ParentDir => Some(OsString::from("..")),
+        _ => None,
+    })
+}
+
+/// Resolve the absolute `path` under `root` as a process chrooted there would. Returns the
+/// resolved path as seen from inside the root, free of symlinks, and whether it exists.
+pub fn resolve_in_root(root: &Path, path: &str) -> Result<(PathBuf, bool), String> {
+    if !path.starts_with('/') {
+        return Err(format!("Working directory '{}' must be an absolute path", path));
+    }
+    let mut pending: VecDeque<OsString> = path_components(Path::new(path)).collect();
+    let mut resolved = PathBuf::from("/");
+    let mut exists = true;
+    let mut links = 0;
+
+    while let Some(component) = pending.pop_front() {
+        if component == ".." {
+            resolved.pop();
+            continue;
+        }
+        let candidate = resolved.join(&component);
+        // Below a missing directory nothing can be a symlink
+        if !exists {
+            resolved = candidate;
+            continue;
+        }
+        let host_path = root.join(candidate.strip_prefix("/").unwrap_or(&candidate));
+        match fs::symlink_metadata(&host_path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                links += 1;
+                if links > MAX_SYMLINKS {
+                    return Err(format!("Working directory '{}' has too many levels of symlinks", path));
+                }
+                let target = fs::read_link(&host_path)
+                    .map_err(|e| format!("Failed to read symlink {} in the container: {}", candidate.display(), e))?;
+                if target.is_absolute() {
+                    resolved = PathBuf::from("/");
+                }
+                for component in path_components(&target).collect::<Vec<_>>().into_iter().rev() {
+                    pending.push_front(component);
+                }
+            }
+            Ok(_) => resolved = candidate,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                exists = false;
+                resolved = candidate;
+            }
+            Err(e) => return Err(format!("Failed to look up {} in the container: {}", candidate.display(), e)),
+        }
+    }
+    Ok((resolved, exists))
+}
+
+/// Check that `working_directory` is a directory under `root`, creating it when `create` is
+/// set, and return it with symlinks resolved
+pub fn prepare_in_root(root: &Path, working_directory: &str, create: bool) -> Result<String, String> {
+    let (resolved, exists) = resolve_in_root(root, working_directory)?;
+    let host_path = root.join(resolved.strip_prefix("/").unwrap_or(&resolved));
+    if !exists {
+        if !create {
+            return Err(format!("Working directory {} does not exist in the container", working_directory));
+        }
+        fs::create_dir_all(&host_path)
+            .map_err(|e| format!("Failed to create working directory {} in the container: {}", working_directory, e))?;
+    } else if !host_path.is_dir() {
+        return Err(format!("Working directory {} is not a directory in the container", working_directory));
+    }
+    Ok(resolved.display().to_string())
+}
+
+/// [`prepare_in_root`] for the running container whose init process is `pid`
+pub fn prepare_working_directory(pid: i64, working_directory: &str, create: bool) -> Result<String, String> {
+    prepare_in_root(&container_root(pid), working_directory, create)
+}
+
+/// The container's root as the host can reach it, mounts included
+pub fn container_root(pid: i64) -> PathBuf {
+    PathBuf::from(format!("/proc/{}/root", pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_resolve_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("app/data")).unwrap();
+        fs::write(root.path().join("app/file"), "").unwrap();
+        symlink("/app", root.path().join("absolute")).unwrap();
+        symlink("../../../app", root.path().join("escaping")).unwrap();
+        symlink("/loop", root.path().join("loop")).unwrap();
+
+        let resolve = |path| resolve_in_root(root.path(), path);
+        assert_eq!(resolve("/absolute/data"), Ok((PathBuf::from("/app/data"), true)));
+        assert_eq!(resolve("/escaping"), Ok((PathBuf::from("/app"), true)));
+        assert_eq!(resolve("/../../app/./data/.."), Ok((PathBuf::from("/app"), true)));
+        assert_eq!(resolve("/absolute/missing/../new"), Ok((PathBuf::from("/app/new"), false)));
+        assert!(resolve("app").is_err());
+        assert!(resolve("/loop").is_err());
+
+        assert_eq!(prepare_in_root(root.path(), "/absolute/data", false), Ok("/app/data".to_string()));
+        assert!(prepare_in_root(root.path(), "/app/file", true).is_err());
+        assert!(prepare_in_root(root.path(), "/absolute/work", false).is_err());
+        assert_eq!(prepare_in_root(root.path(), "/absolute/work", true), Ok("/app/work".to_string()));
+        assert!(root.path().join("app/work").is_dir());
+    }
+}
@@ -0,0 +1,649 @@
+// quilt-docker: docker CLI compatibility shim
+// Accepts the docker verbs and flags scripts use most (run, ps, exec, logs, start, stop, rm,
+// volume) and translates them to quilt RPCs. Images are tarball paths rather than registry
+// references; flags quilt has no equivalent for are rejected instead of silently ignored.
+
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::transport::Channel;
+
+use quilt_core::utils::security::SecurityValidator;
+use quilt_core::utils::validation::{InputValidator, MountType, PortMapping, VolumeMount};
+
+pub mod quilt {
+    tonic::include_proto!("quilt");
+}
+
+use quilt::quilt_service_client::QuiltServiceClient;
+
+/// Time between status and log polls while following a container
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Parser, Debug)]
+#[clap(name = "quilt-docker", version, about = "docker-compatible front end for quilt")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Commands,
+    #[clap(short = 'H', long = "host", default_value = "http://127.0.0.1:50051", help = "quilt daemon address")]
+    host: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Create and start a container
+    Run(RunArgs),
+    /// List containers
+    Ps {
+        #[clap(short = 'a', long, help = "Show all containers (default shows just running)")]
+        all: bool,
+        #[clap(short = 'q', long, help = "Only display container IDs")]
+        quiet: bool,
+        #[clap(long, help = "Don't truncate output")]
+        no_trunc: bool,
+        #[clap(short = 'f', long = "filter", help = "Filter output (only label=<key>=<value> is supported)", value_parser = parse_label_filter)]
+        filters: Vec<(String, String)>,
+    },
+    /// Execute a command in a running container
+    Exec {
+        #[clap(flatten)]
+        terminal: TerminalFlags,
+        #[clap(short = 'e', long = "env", value_parser = parse_env)]
+        env: Vec<(String, String)>,
+        #[clap(short = 'w', long)]
+        workdir: Option<String>,
+        container: String,
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Fetch the logs of a container
+    Logs {
+        #[clap(short = 'f', long, help = "Follow log output until the container exits")]
+        follow: bool,
+        #[clap(short = 'n', long, help = "Number of lines to show from the end of the logs")]
+        tail: Option<usize>,
+        #[clap(short = 't', long, help = "Show timestamps")]
+        timestamps: bool,
+        container: String,
+    },
+    /// Start one or more stopped containers
+    Start {
+        #[clap(required = true)]
+        containers: Vec<String>,
+    },
+    /// Stop one or more running containers
+    Stop {
+        #[clap(short = 't', long = "time", help = "Seconds to wait before killing the container")]
+        time: Option<i32>,
+        #[clap(required = true)]
+        containers: Vec<String>,
+    },
+    /// Remove one or more containers
+    Rm {
+        #[clap(short = 'f', long, help = "Force the removal of a running container")]
+        force: bool,
+        #[clap(required = true)]
+        containers: Vec<String>,
+    },
+    /// Manage volumes
+    Volume {
+        #[clap(subcommand)]
+        command: VolumeCommands,
+    },
+}
+
+/// `-i` and `-t` are accepted so existing scripts run unchanged; stdin is not forwarded and no
+/// TTY is allocated
+#[derive(clap::Args, Debug)]
+struct TerminalFlags {
+    #[clap(short = 'i', long)]
+    interactive: bool,
+    #[allow(dead_code)]
+    #[clap(short = 't', long)]
+    tty: bool,
+}
+
+impl TerminalFlags {
+    fn warn_unsupported(&self) {
+        if self.interactive && !std::io::stdin().is_terminal() {
+            eprintln!("WARNING: quilt-docker does not forward stdin; piped input is ignored");
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    #[clap(short = 'd', long, help = "Run container in background and print container ID")]
+    detach: bool,
+    #[clap(long, conflicts_with = "detach", help = "Remove the container when it exits")]
+    rm: bool,
+    #[clap(flatten)]
+    terminal: TerminalFlags,
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(short = 'e', long = "env", value_parser = parse_env)]
+    env: Vec<(String, String)>,
+    #[clap(short = 'v', long = "volume", value_parser = InputValidator::parse_volume)]
+    volumes: Vec<VolumeMount>,
+    #[clap(short = 'p', long = "publish", value_parser = InputValidator::parse_port_mapping)]
+    ports: Vec<PortMapping>,
+    #[clap(short = 'l', long = "label", value_parser = InputValidator::parse_key_val)]
+    labels: Vec<(String, String)>,
+    #[clap(short = 'w', long)]
+    workdir: Option<String>,
+    #[clap(short = 'u', long, help = "user[:group] to run as")]
+    user: Option<String>,
+    #[clap(short = 'm', long, help = "Memory limit (e.g. 512m, 2g)", value_parser = parse_memory_mb)]
+    memory: Option<i32>,
+    #[clap(long, help = "Number of CPUs (e.g. 0.5)")]
+    cpus: Option<f32>,
+    #[clap(long, help = "Read-only rootfs (quilt's immutable mode)")]
+    read_only: bool,
+    #[clap(long, default_value = "bridge", value_parser = ["bridge", "default", "host"])]
+    network: String,
+    #[clap(help = "Image tarball path")]
+    image: String,
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum VolumeCommands {
+    /// Create a volume
+    Create {
+        #[clap(short = 'd', long, default_value = "local")]
+        driver: String,
+        #[clap(long = "label", value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        name: String,
+    },
+    /// List volumes
+    #[clap(alias = "list")]
+    Ls {
+        #[clap(short = 'q', long, help = "Only display volume names")]
+        quiet: bool,
+    },
+    /// Remove one or more volumes
+    #[clap(alias = "remove")]
+    Rm {
+        #[clap(short = 'f', long)]
+        force: bool,
+        #[clap(required = true)]
+        names: Vec<String>,
+    },
+    /// Display detailed information on one or more volumes
+    Inspect {
+        #[clap(required = true)]
+        names: Vec<String>,
+    },
+}
+
+/// `-e KEY=VALUE`, or `-e KEY` to pass the variable through from this environment
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    if s.contains('=') {
+        return InputValidator::parse_key_val(s);
+    }
+    std::env::var(s).map(|value| (s.to_string(), value))
+        .map_err(|_| format!("Environment variable '{}' is not set", s))
+}
+
+/// docker's `--memory` units: b, k, m, g (binary); quilt takes whole megabytes
+fn parse_memory_mb(s: &str) -> Result<i32, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (number, unit) = match lower.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(pos) => lower.split_at(pos),
+        None => (lower.as_str(), "b"),
+    };
+    let value: f64 = number.parse().map_err(|_| format!("Invalid memory limit '{}'", s))?;
+    let bytes = match unit.trim_end_matches('b') {
+        "" => value,
+        "k" => value * 1024.0,
+        "m" => value * 1024.0 * 1024.0,
+        "g" => value * 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("Invalid memory unit in '{}' (expected b, k, m or g)", s)),
+    };
+    let mb = (bytes / (1024.0 * 1024.0)).ceil();
+    if mb < 1.0 || mb > i32::MAX as f64 {
+        return Err(format!("Memory limit '{}' out of range", s));
+    }
+    Ok(mb as i32)
+}
+
+/// `--filter label=<key>=<value>`
+fn parse_label_filter(s: &str) -> Result<(String, String), String> {
+    s.strip_prefix("label=")
+        .ok_or_else(|| format!("Unsupported filter '{}': only label=<key>=<value> is supported", s))
+        .and_then(InputValidator::parse_key_val)
+}
+
+/// The daemon runs commands through `/bin/sh -c`; quote each argument so it stays one argument
+fn shell_join(args: &[String]) -> String {
+    args.iter().map(|arg| {
+        if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c)) {
+            arg.clone()
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    }).collect::<Vec<_>>().join(" ")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// docker-style relative time: "5 minutes", "About an hour", "3 days"
+fn human_duration(secs: u64) -> String {
+    match secs {
+        0 => "Less than a second".to_string(),
+        1 => "1 second".to_string(),
+        2..=59 => format!("{} seconds", secs),
+        60..=119 => "About a minute".to_string(),
+        120..=3599 => format!("{} minutes", secs / 60),
+        3600..=7199 => "About an hour".to_string(),
+        7200..=172_799 => format!("{} hours", secs / 3600),
+        _ => format!("{} days", secs / 86_400),
+    }
+}
+
+fn status_text(container: &quilt::ContainerSummary, now: u64) -> String {
+    match quilt::ContainerStatus::from_i32(container.status) {
+        Some(quilt::ContainerStatus::Running) | Some(quilt::ContainerStatus::Stopping) =>
+            format!("Up {}", human_duration(now.saturating_sub(container.started_at))),
+        Some(quilt::ContainerStatus::Paused) => format!("Up {} (Paused)", human_duration(now.saturating_sub(container.started_at))),
+        Some(quilt::ContainerStatus::Restarting) => format!("Restarting ({})", container.exit_code),
+        Some(quilt::ContainerStatus::Removing) => "Removal In Progress".to_string(),
+        Some(quilt::ContainerStatus::Exited) | Some(quilt::ContainerStatus::Failed) if container.exited_at > 0 =>
+            format!("Exited ({}) {} ago", container.exit_code, human_duration(now.saturating_sub(container.exited_at))),
+        Some(quilt::ContainerStatus::Exited) | Some(quilt::ContainerStatus::Failed) => format!("Exited ({})", container.exit_code),
+        _ => "Created".to_string(),
+    }
+}
+
+/// Runtime log lines are stored as `[STDOUT] message`; give back the stream and the bare message
+fn output_line(entry: &quilt::LogEntry) -> Option<(bool, &str)> {
+    let is_stderr = match entry.stream.as_str() {
+        "stdout" => false,
+        "stderr" => true,
+        _ => return None,
+    };
+    let prefix = format!("[{}] ", entry.stream.to_uppercase());
+    Some((is_stderr, entry.message.strip_prefix(prefix.as_str()).unwrap_or(&entry.message)))
+}
+
+/// Container name, full ID or unique ID prefix to container ID, as docker accepts them
+async fn resolve(client: &mut QuiltServiceClient<Channel>, reference: &str) -> Result<String, String> {
+    let by_name = client.get_container_by_name(quilt::GetContainerByNameRequest { name: reference.to_string() }).await
+        .map_err(|e| e.message().to_string())?
+        .into_inner();
+    if by_name.found {
+        return Ok(by_name.container_id);
+    }
+    let containers = client.list_containers(quilt::ListContainersRequest::default()).await
+        .map_err(|e| e.message().to_string())?
+        .into_inner()
+        .containers;
+    let matches: Vec<&quilt::ContainerSummary> = containers.iter()
+        .filter(|container| container.container_id.starts_with(reference))
+        .collect();
+    match matches.as_slice() {
+        [container] => Ok(container.container_id.clone()),
+        [] => Err(format!("No such container: {}", reference)),
+        _ => Err(format!("Multiple IDs found with provided prefix: {}", reference)),
+    }
+}
+
+/// Print runtime output newer than `after_ns`; returns the newest line printed
+async fn print_output(client: &mut QuiltServiceClient<Channel>, container_id: &str, after_ns: u64, tail: Option<usize>, timestamps: bool) -> Result<u64, String> {
+    let logs = client.get_container_logs(quilt::GetContainerLogsRequest {
+        container_id: container_id.to_string(),
+        container_name: String::new(),
+    }).await.map_err(|e| e.message().to_string())?.into_inner().logs;
+
+    let lines: Vec<(&quilt::LogEntry, bool, &str)> = logs.iter()
+        .filter(|entry| entry.monotonic_ns > after_ns)
+        .filter_map(|entry| output_line(entry).map(|(is_stderr, message)| (entry, is_stderr, message)))
+        .collect();
+    let skip = tail.map_or(0, |tail| lines.len().saturating_sub(tail));
+
+    let mut newest = after_ns;
+    let (mut stdout, mut stderr) = (std::io::stdout().lock(), std::io::stderr().lock());
+    for (entry, is_stderr, message) in lines.into_iter().skip(skip) {
+        let out: &mut dyn Write = if is_stderr { &mut stderr } else { &mut stdout };
+        let _ = if timestamps {
+            let stamp = humantime::format_rfc3339_millis(UNIX_EPOCH + Duration::from_millis(entry.timestamp_ms));
+            writeln!(out, "{} {}", stamp, message)
+        } else {
+            writeln!(out, "{}", message)
+        };
+        newest = newest.max(entry.monotonic_ns);
+    }
+    Ok(newest)
+}
+
+/// Poll until the container is no longer running, printing its output; returns its exit code
+async fn follow(client: &mut QuiltServiceClient<Channel>, container_id: &str, mut after_ns: u64, timestamps: bool) -> Result<i32, String> {
+    loop {
+        let status = client.get_container_status(quilt::GetContainerStatusRequest {
+            container_id: container_id.to_string(),
+            container_name: String::new(),
+        }).await.map_err(|e| e.message().to_string())?.into_inner();
+        after_ns = print_output(client, container_id, after_ns, None, timestamps).await?;
+        match quilt::ContainerStatus::from_i32(status.status) {
+            Some(quilt::ContainerStatus::Exited) => return Ok(status.exit_code),
+            Some(quilt::ContainerStatus::Failed) => return Ok(if status.exit_code != 0 { status.exit_code } else { 125 }),
+            _ => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+async fn run(client: &mut QuiltServiceClient<Channel>, args: RunArgs) -> Result<i32, String> {
+    args.terminal.warn_unsupported();
+    let mut mounts = Vec::with_capacity(args.volumes.len());
+    for volume in args.volumes {
+        SecurityValidator::validate_mount(&volume)?;
+        mounts.push(quilt::Mount {
+            r#type: match volume.mount_type {
+                MountType::Bind => quilt::MountType::Bind as i32,
+                MountType::Volume => quilt::MountType::Volume as i32,
+                MountType::Tmpfs => quilt::MountType::Tmpfs as i32,
+            },
+            source: volume.source,
+            target: volume.target,
+            readonly: volume.readonly,
+            options: volume.options,
+        });
+    }
+
+    let network = args.network != "host";
+    let response = client.create_container(quilt::CreateContainerRequest {
+        image_path: args.image,
+        // Without a command the daemon keeps the container alive with its default one
+        async_mode: args.command.is_empty(),
+        command: if args.command.is_empty() { Vec::new() } else { vec![shell_join(&args.command)] },
+        environment: args.env.into_iter().collect(),
+        working_directory: args.workdir.unwrap_or_default(),
+        user: args.user.unwrap_or_default(),
+        memory_limit_mb: args.memory.unwrap_or(0),
+        cpu_limit_percent: args.cpus.map_or(0.0, |cpus| cpus * 100.0),
+        enable_pid_namespace: true,
+        enable_mount_namespace: true,
+        enable_uts_namespace: true,
+        enable_ipc_namespace: true,
+        enable_network_namespace: network,
+        name: args.name.unwrap_or_default(),
+        mounts,
+        ports: args.ports.into_iter().map(|port| quilt::PortMapping {
+            host_ip: port.host_ip,
+            host_port: port.host_port as u32,
+            container_port: port.container_port as u32,
+            protocol: port.protocol,
+        }).collect(),
+        labels: args.labels.into_iter().collect(),
+        immutable: args.read_only,
+        ..Default::default()
+    }).await.map_err(|e| e.message().to_string())?.into_inner();
+    if !response.success {
+        return Err(response.error_message);
+    }
+
+    if args.detach {
+        println!("{}", response.container_id);
+        return Ok(0);
+    }
+    let exit_code = follow(client, &response.container_id, 0, false).await?;
+    if args.rm {
+        remove(client, &response.container_id, true).await?;
+    }
+    Ok(exit_code)
+}
+
+async fn remove(client: &mut QuiltServiceClient<Channel>, container_id: &str, force: bool) -> Result<(), String> {
+    let response = client.remove_container(quilt::RemoveContainerRequest {
+        container_id: container_id.to_string(),
+        force,
+        container_name: String::new(),
+    }).await.map_err(|e| e.message().to_string())?.into_inner();
+    if response.success { Ok(()) } else { Err(response.error_message) }
+}
+
+async fn ps(client: &mut QuiltServiceClient<Channel>, all: bool, quiet: bool, no_trunc: bool, filters: Vec<(String, String)>) -> Result<i32, String> {
+    let containers = client.list_containers(quilt::ListContainersRequest {
+        running_only: !all,
+        labels: filters.into_iter().collect(),
+    }).await.map_err(|e| e.message().to_string())?.into_inner().containers;
+
+    let short = |id: &str| if no_trunc { id.to_string() } else { id.chars().take(12).collect() };
+    if quiet {
+        for container in &containers {
+            println!("{}", short(&container.container_id));
+        }
+        return Ok(0);
+    }
+    let now = now_secs();
+    let id_width = if no_trunc { 36 } else { 12 };
+    let row = |id: &str, created: &str, ip: &str, status: &str, names: &str| {
+        format!("{:<id_width$}   {:<24}   {:<16}   {:<28}   {}", id, created, ip, status, names)
+    };
+    println!("{}", row("CONTAINER ID", "CREATED", "IP", "STATUS", "NAMES"));
+    for container in &containers {
+        let created = format!("{} ago", human_duration(now.saturating_sub(container.created_at)));
+        println!("{}", row(&short(&container.container_id), &created, &container.ip_address,
+            &status_text(container, now), &container.name));
+    }
+    Ok(0)
+}
+
+async fn exec(client: &mut QuiltServiceClient<Channel>, container: &str, env: Vec<(String, String)>, workdir: Option<String>, command: Vec<String>) -> Result<i32, String> {
+    let container_id = resolve(client, container).await?;
+    let response = client.exec_container(quilt::ExecContainerRequest {
+        container_id,
+        command: vec![shell_join(&command)],
+        working_directory: workdir.unwrap_or_default(),
+        environment: env.into_iter().collect(),
+        capture_output: true,
+        ..Default::default()
+    }).await.map_err(|e| e.message().to_string())?.into_inner();
+    if !response.success && !response.error_message.is_empty() && response.exit_code < 0 {
+        return Err(response.error_message);
+    }
+    print!("{}", response.stdout);
+    eprint!("{}", response.stderr);
+    Ok(response.exit_code)
+}
+
+async fn volume(client: &mut QuiltServiceClient<Channel>, command: VolumeCommands) -> Result<i32, String> {
+    match command {
+        VolumeCommands::Create { driver, labels, name } => {
+            let response = client.create_volume(quilt::CreateVolumeRequest {
+                name: name.clone(),
+                driver,
+                labels: labels.into_iter().collect(),
+                options: HashMap::new(),
+            }).await.map_err(|e| e.message().to_string())?.into_inner();
+            if !response.success {
+                return Err(response.error_message);
+            }
+            println!("{}", name);
+        }
+        VolumeCommands::Ls { quiet } => {
+            let volumes = client.list_volumes(quilt::ListVolumesRequest { filters: HashMap::new() }).await
+                .map_err(|e| e.message().to_string())?.into_inner().volumes;
+            if !quiet {
+                println!("DRIVER          VOLUME NAME");
+            }
+            for volume in volumes {
+                if quiet {
+                    println!("{}", volume.name);
+                } else {
+                    println!("{:<16}{}", volume.driver, volume.name);
+                }
+            }
+        }
+        VolumeCommands::Rm { force, names } => {
+            let mut failed = false;
+            for name in names {
+                let response = client.remove_volume(quilt::RemoveVolumeRequest { name: name.clone(), force }).await
+                    .map_err(|e| e.message().to_string())?.into_inner();
+                if response.success {
+                    println!("{}", name);
+                } else {
+                    eprintln!("Error response from daemon: {}", response.error_message);
+                    failed = true;
+                }
+            }
+            return Ok(if failed { 1 } else { 0 });
+        }
+        VolumeCommands::Inspect { names } => {
+            let mut found = Vec::new();
+            for name in names {
+                let response = client.inspect_volume(quilt::InspectVolumeRequest { name: name.clone() }).await
+                    .map_err(|e| e.message().to_string())?.into_inner();
+                match response.volume.filter(|_| response.found) {
+                    Some(volume) => found.push(serde_json::json!({
+                        "Name": volume.name,
+                        "Driver": volume.driver,
+                        "Mountpoint": volume.mount_point,
+                        "Labels": volume.labels,
+                        "Options": volume.options,
+                        "CreatedAt": humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(volume.created_at)).to_string(),
+                    })),
+                    None => return Err(format!("get {}: no such volume", name)),
+                }
+            }
+            println!("{}", serde_json::to_string_pretty(&found).unwrap_or_default());
+        }
+    }
+    Ok(0)
+}
+
+async fn dispatch(client: &mut QuiltServiceClient<Channel>, command: Commands) -> Result<i32, String> {
+    match command {
+        Commands::Run(args) => run(client, args).await,
+        Commands::Ps { all, quiet, no_trunc, filters } => ps(client, all, quiet, no_trunc, filters).await,
+        Commands::Exec { terminal, env, workdir, container, command } => {
+            terminal.warn_unsupported();
+            exec(client, &container, env, workdir, command).await
+        }
+        Commands::Logs { follow: keep_following, tail, timestamps, container } => {
+            let container_id = resolve(client, &container).await?;
+            let newest = print_output(client, &container_id, 0, tail, timestamps).await?;
+            if keep_following {
+                follow(client, &container_id, newest, timestamps).await?;
+            }
+            Ok(0)
+        }
+        Commands::Start { containers } => {
+            for container in containers {
+                let container_id = resolve(client, &container).await?;
+                let response = client.start_container(quilt::StartContainerRequest { container_id, container_name: String::new(), wait_ready: false }).await
+                    .map_err(|e| e.message().to_string())?.into_inner();
+                if !response.success {
+                    return Err(response.error_message);
+                }
+                println!("{}", container);
+            }
+            Ok(0)
+        }
+        Commands::Stop { time, containers } => {
+            for container in containers {
+                let container_id = resolve(client, &container).await?;
+                let response = client.stop_container(quilt::StopContainerRequest {
+                    container_id,
+                    timeout_seconds: time.unwrap_or(0),
+                    container_name: String::new(),
+                }).await.map_err(|e| e.message().to_string())?.into_inner();
+                if !response.success {
+                    return Err(response.error_message);
+                }
+                println!("{}", container);
+            }
+            Ok(0)
+        }
+        Commands::Rm { force, containers } => {
+            for container in containers {
+                let container_id = resolve(client, &container).await?;
+                remove(client, &container_id, force).await?;
+                println!("{}", container);
+            }
+            Ok(0)
+        }
+        Commands::Volume { command } => volume(client, command).await,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    // Same override the quilt CLI honours inside nested containers
+    let host = std::env::var("QUILT_SERVER").map(|server| format!("http://{}", server)).unwrap_or(cli.host);
+    // docker exits 125 when the daemon itself fails a `run`
+    let failure_code = if matches!(cli.command, Commands::Run(_)) { 125 } else { 1 };
+
+    let channel = match Channel::from_shared(host.clone()) {
+        Ok(endpoint) => endpoint.connect_timeout(Duration::from_secs(10)).connect().await,
+        Err(e) => {
+            eprintln!("Invalid daemon address {}: {}", host, e);
+            std::process::exit(failure_code);
+        }
+    };
+    let mut client = match channel {
+        Ok(channel) => QuiltServiceClient::new(channel),
+        Err(e) => {
+            eprintln!("Cannot connect to the quilt daemon at {}. Is quilt running? ({})", host, e);
+            std::process::exit(failure_code);
+        }
+    };
+
+    let code = match dispatch(&mut client, cli.command).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error response from daemon: {}", e);
+            failure_code
+        }
+    };
+    // exit() skips destructors, so flush output printed without a trailing newline
+    let _ = std::io::stdout().flush();
+    std::process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_parsing() {
+        let cli = Cli::parse_from(["quilt-docker", "run", "-d", "--name", "web", "-e", "A=1", "-p", "8080:80",
+            "-v", "data:/data:ro", "-m", "512m", "--cpus", "1.5", "./alpine.tar", "sh", "-c", "echo hi"]);
+        match cli.command {
+            Commands::Run(args) => {
+                assert!(args.detach);
+                assert_eq!(args.name.as_deref(), Some("web"));
+                assert_eq!(args.env, vec![("A".to_string(), "1".to_string())]);
+                assert_eq!(args.ports[0].host_port, 8080);
+                assert!(args.volumes[0].readonly);
+                assert_eq!(args.memory, Some(512));
+                assert_eq!(args.image, "./alpine.tar");
+                assert_eq!(args.command, vec!["sh", "-c", "echo hi"]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        // Unsupported networks and --rm with -d are refused rather than ignored
+        assert!(Cli::try_parse_from(["quilt-docker", "run", "--network", "mynet", "img.tar"]).is_err());
+        assert!(Cli::try_parse_from(["quilt-docker", "run", "-d", "--rm", "img.tar"]).is_err());
+    }
+
+    #[test]
+    fn test_translations() {
+        assert_eq!(parse_memory_mb("512m"), Ok(512));
+        assert_eq!(parse_memory_mb("2g"), Ok(2048));
+        assert_eq!(parse_memory_mb("1536k"), Ok(2));
+        assert!(parse_memory_mb("10x").is_err());
+        assert_eq!(parse_label_filter("label=app=web"), Ok(("app".to_string(), "web".to_string())));
+        assert!(parse_label_filter("status=running").is_err());
+        assert_eq!(shell_join(&["sh".to_string(), "-c".to_string(), "echo 'hi' $HOME".to_string()]),
+            "sh -c 'echo '\\''hi'\\'' $HOME'");
+        assert_eq!(human_duration(90), "About a minute");
+        assert_eq!(human_duration(7300), "2 hours");
+    }
+}
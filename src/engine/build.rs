@@ -0,0 +1,186 @@
+// Image builds from a Quiltfile
+// The base image is resolved like any container's image and started as a temporary build
+// container. RUN steps are exec'd in it with the environment and working directory the steps so
+// far set, COPY steps unpack the context's files into it, and ENV and WORKDIR also go into the
+// new image's config. Once every step passed, the container is stopped, its rootfs written as an
+// image tagged as requested and imported into the local image store, so containers (and later
+// builds) can name the tag as their image. The build container is removed whatever the outcome.
+
+use super::images;
+use super::Engine;
+use crate::daemon::build::{self, BuildStep, Quiltfile};
+use crate::daemon::image_config::ImageConfig;
+use crate::daemon::overlay;
+use crate::daemon::registry::{self, ImageReference};
+use crate::sync::containers::ContainerConfig;
+use crate::utils::command::CommandExecutor;
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Longest a single RUN or COPY step may take
+pub const BUILD_STEP_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// Label of build containers, set to the tag being built
+pub const BUILD_LABEL: &str = "quilt.build";
+/// Keeps the build container running between steps
+const BUILD_CONTAINER_COMMAND: &str = "tail -f /dev/null || while true; do sleep 3600; done";
+
+/// A finished build
+#[derive(Debug, Clone)]
+pub struct BuildReport {
+    pub tag: String,
+    /// Digest of the image in the local image store
+    pub digest: String,
+    /// The `docker save` archive the image was written to
+    pub image_path: String,
+    pub steps: usize,
+    /// Step headers and the output of the steps
+    pub log: Vec<String>,
+}
+
+/// Build `quiltfile` with COPY sources taken from `context_dir` and store the result as `tag`
+pub async fn build_image(engine: &Engine, quiltfile: &str, context_dir: &str, tag: &str, actor: &str) -> Result<BuildReport, String> {
+    let quiltfile = Quiltfile::parse(quiltfile)?;
+    ImageReference::parse(tag).map_err(|e| format!("Invalid tag {}: {}", tag, e))?;
+    let context = std::fs::canonicalize(context_dir).map_err(|e| format!("Build context {}: {}", context_dir, e))?;
+    if !context.is_dir() {
+        return Err(format!("Build context {} is not a directory", context_dir));
+    }
+
+    let base_path = engine.resolve_image(&quiltfile.base).await?;
+    engine.admit_image(&base_path).await?;
+    let base = images::ensure_stored(engine.sync_engine(), &base_path).await?;
+
+    let build_id = uuid::Uuid::new_v4().to_string();
+    let config = ContainerConfig {
+        id: build_id.clone(),
+        name: Some(format!("build-{}", &build_id[..8])),
+        image_path: base_path,
+        command: BUILD_CONTAINER_COMMAND.to_string(),
+        enable_network_namespace: true,
+        enable_pid_namespace: true,
+        enable_mount_namespace: true,
+        enable_uts_namespace: true,
+        enable_ipc_namespace: true,
+        labels: HashMap::from([(BUILD_LABEL.to_string(), tag.to_string())]),
+        ..Default::default()
+    };
+    engine.create_container(config, actor).await?;
+    ConsoleLogger::info(&format!("🏗️ [BUILD] Building {} from {} in {}", tag, quiltfile.base, build_id));
+
+    let result = run_build(engine, &build_id, &quiltfile, &context, base.config, tag, actor).await;
+    if let Err(e) = engine.remove_container(&build_id, true, actor).await {
+        ConsoleLogger::warning(&format!("🏗️ [BUILD] Failed to remove build container {}: {}", build_id, e));
+    }
+    match &result {
+        Ok(report) => ConsoleLogger::success(&format!("🏗️ [BUILD] Built {} as {}", tag, report.digest)),
+        Err(e) => ConsoleLogger::warning(&format!("🏗️ [BUILD] Build of {} failed: {}", tag, e)),
+    }
+    result
+}
+
+async fn run_build(
+    engine: &Engine,
+    build_id: &str,
+    quiltfile: &Quiltfile,
+    context: &Path,
+    mut image_config: ImageConfig,
+    tag: &str,
+    actor: &str,
+) -> Result<BuildReport, String> {
+    engine.start_container(build_id, None).await?;
+    let status = engine.container_status(build_id).await?;
+    let pid = status.pid.ok_or_else(|| format!("Build container {} has no PID", build_id))?;
+    let rootfs_path = status.rootfs_path.ok_or_else(|| format!("Build container {} has no rootfs", build_id))?;
+
+    let mut log = Vec::new();
+    let mut environment = HashMap::new();
+    let total = quiltfile.steps.len();
+    for (index, step) in quiltfile.steps.iter().enumerate() {
+        let header = format!("Step {}/{}: {}", index + 1, total, step);
+        ConsoleLogger::info(&format!("🏗️ [BUILD] {}", header));
+        log.push(header);
+        let failed = |e: String| format!("Step {}/{} ({}) failed: {}", index + 1, total, step, e);
+        match step {
+            BuildStep::Run(command) => {
+                let output = exec_step(engine, build_id, pid, &rootfs_path, &[command.clone()], image_config.working_dir.as_deref(), &environment, None)
+                    .await
+                    .map_err(failed)?;
+                log.extend(output.lines().map(str::to_string));
+            }
+            BuildStep::Copy { source, destination } => {
+                let destination = build::container_path(image_config.working_dir.as_deref(), destination);
+                let (context, source_name) = (context.to_path_buf(), source.clone());
+                let archive = tokio::task::spawn_blocking(move || build::copy_archive(&context, &source_name, &destination))
+                    .await
+                    .map_err(|e| failed(format!("Copy task failed: {}", e)))?
+                    .map_err(failed)?;
+                let unpack = ["tar", "-xf", "-", "-C", "/"].map(str::to_string);
+                exec_step(engine, build_id, pid, &rootfs_path, &unpack, None, &environment, Some(archive))
+                    .await
+                    .map_err(|e| failed(format!("the image needs tar to unpack COPY sources: {}", e)))?;
+            }
+            BuildStep::Env(variables) => {
+                for (name, value) in variables {
+                    environment.insert(name.clone(), value.clone());
+                    image_config.set_env(name, value);
+                }
+            }
+            BuildStep::Workdir(path) => {
+                let path = build::container_path(image_config.working_dir.as_deref(), path);
+                Engine::exec_working_directory(pid, &path, true).map_err(failed)?;
+                image_config.working_dir = Some(path);
+            }
+        }
+    }
+
+    // Stopping unmounts an overlay rootfs, which the image is read from afterwards
+    engine.stop_container(build_id, actor).await?;
+    let output = registry::image_store_dir().join("builds").join(build::archive_file_name(tag));
+    let (rootfs, archive, tag_name) = (PathBuf::from(&rootfs_path), output.clone(), tag.to_string());
+    tokio::task::spawn_blocking(move || {
+        overlay::ensure_mounted(&rootfs.display().to_string())?;
+        std::fs::create_dir_all(archive.parent().unwrap_or(Path::new("/")))
+            .map_err(|e| format!("Failed to create {}: {}", archive.display(), e))?;
+        build::write_image(&rootfs, &image_config, &tag_name, &archive)
+    })
+        .await
+        .map_err(|e| format!("Image write task failed: {}", e))??;
+
+    let image_path = output.display().to_string();
+    let image = images::ensure_stored(engine.sync_engine(), &image_path).await?;
+    Ok(BuildReport { tag: tag.to_string(), digest: image.digest, image_path, steps: total, log })
+}
+
+/// Run `command` in the build container and return its output; a non-zero exit is an error
+#[allow(clippy::too_many_arguments)]
+async fn exec_step(
+    engine: &Engine,
+    build_id: &str,
+    pid: i64,
+    rootfs_path: &str,
+    command: &[String],
+    working_directory: Option<&str>,
+    environment: &HashMap<String, String>,
+    input: Option<Vec<u8>>,
+) -> Result<String, String> {
+    let environment = engine.exec_environment(build_id, environment).await?;
+    let working_directory = match working_directory {
+        Some(directory) => Engine::exec_working_directory(pid, directory, true)?,
+        None => None,
+    };
+    let argv = Engine::exec_argv_in(pid, rootfs_path, working_directory.as_deref(), command, &environment);
+    let argv = match input {
+        Some(input) => argv.with_input(input),
+        None => argv,
+    };
+    let result = CommandExecutor::execute_with_timeout(&argv, BUILD_STEP_TIMEOUT).await?;
+    let output = format!("{}{}", result.stdout, result.stderr);
+    if !result.success {
+        let exit = result.exit_code.map_or("a signal".to_string(), |code| format!("exit code {}", code));
+        return Err(format!("{}: {}", exit, output.trim()));
+    }
+    Ok(output)
+}
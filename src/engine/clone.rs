@@ -0,0 +1,169 @@
+// Container cloning
+// Creates a new container from an existing one's stored settings, optionally starting from a
+// copy of its rootfs and named volumes, so identical sandboxes can be fanned out from one source
+
+use crate::daemon::paths;
+use crate::sync::events::{global_event_buffer, EventType};
+use crate::sync::volumes::Mount;
+use crate::sync::{MountType, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::PortMapping;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a clone takes from its source besides the stored settings
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Name for the clone; generated when `None`
+    pub name: Option<String>,
+    /// Start from a copy of the source's rootfs instead of a fresh image extraction
+    pub snapshot_rootfs: bool,
+    /// Give the clone copies of the source's named volumes instead of sharing them
+    pub snapshot_volumes: bool,
+    /// Labels merged over the source's labels
+    pub labels: HashMap<String, String>,
+    /// Host ports to publish for the clone; the source's own are never copied
+    pub ports: Vec<PortMapping>,
+    /// Image to extract instead of the source's
+    pub image_path: Option<String>,
+    /// Command to run instead of the source's
+    pub command: Option<String>,
+    /// Environment variables merged over the source's
+    pub environment: HashMap<String, String>,
+}
+
+/// Record a clone of `source_id` and prepare its rootfs and mounts; returns the clone's ID.
+/// Published host ports are not copied, since a host port can only be bound once; the clone
+/// publishes `options.ports` instead. Snapshots of a running source are taken without pausing it.
+pub async fn clone_container(sync_engine: &SyncEngine, source_id: &str, options: CloneOptions, actor: &str) -> Result<String, String> {
+    if options.snapshot_rootfs && options.image_path.is_some() {
+        return Err("A rootfs snapshot cannot be combined with a different image".to_string());
+    }
+    let mut config = sync_engine.get_container_config(source_id).await
+        .map_err(|e| format!("Container {} not found: {}", source_id, e))?;
+    let source_rootfs = sync_engine.get_container_status(source_id).await
+        .map_err(|e| format!("Container {} not found: {}", source_id, e))?
+        .rootfs_path;
+    let mounts = sync_engine.get_container_mounts(source_id).await
+        .map_err(|e| format!("Failed to read mounts of {}: {}", source_id, e))?;
+
+    let clone_id = uuid::Uuid::new_v4().to_string();
+    config.id = clone_id.clone();
+    config.name = options.name.clone();
+    config.labels.extend(options.labels.clone());
+    config.ports = options.ports.clone();
+    if let Some(image_path) = &options.image_path {
+        config.image_path = image_path.clone();
+    }
+    if let Some(command) = &options.command {
+        config.command = command.clone();
+    }
+    config.environment.extend(options.environment.clone());
+
+    sync_engine.create_container(config).await
+        .map_err(|e| format!("Failed to create clone of {}: {}", source_id, e))?;
+    // A clone runs on the same clock as its source
+    if let Ok(Some(offset)) = sync_engine.get_time_offset(source_id).await {
+        let _ = sync_engine.set_time_offset(&clone_id, Some(offset)).await;
+    }
+
+    if let Err(e) = populate_clone(sync_engine, &clone_id, source_rootfs.as_deref(), &mounts, &options).await {
+        ConsoleLogger::warning(&format!("↩️ [CLONE] Clone {} of {} failed; removing it: {}", clone_id, source_id, e));
+        let _ = sync_engine.remove_container_mounts(&clone_id).await;
+        let _ = sync_engine.delete_container(&clone_id).await;
+        let _ = tokio::fs::remove_dir_all(paths::default_rootfs_path(&clone_id)).await;
+        global_event_buffer().forget_container_labels(&clone_id);
+        return Err(e);
+    }
+
+    let attributes = HashMap::from([("cloned_from".to_string(), source_id.to_string())]);
+    global_event_buffer().emit_as(EventType::Created, &clone_id, actor, Some(attributes));
+    let _ = sync_engine.store_container_log_as(&clone_id, "info", &format!("Container cloned from {}", source_id), actor).await;
+    ConsoleLogger::success(&format!("🐑 [CLONE] Cloned {} into {} (rootfs snapshot: {}, volume snapshots: {})",
+        source_id, clone_id, options.snapshot_rootfs, options.snapshot_volumes));
+    Ok(clone_id)
+}
+
+/// Name of the volume copy a clone gets for `volume`
+fn snapshot_volume_name(volume: &str, clone_id: &str) -> String {
+    format!("{}-{}", volume, &clone_id[..8.min(clone_id.len())])
+}
+
+async fn populate_clone(
+    sync_engine: &SyncEngine,
+    clone_id: &str,
+    source_rootfs: Option<&str>,
+    mounts: &[Mount],
+    options: &CloneOptions,
+) -> Result<(), String> {
+    if options.snapshot_rootfs {
+        let source_rootfs = source_rootfs
+            .filter(|path| Path::new(path).exists())
+            .ok_or_else(|| "Source container has no rootfs to snapshot (it has never been started)".to_string())?;
+        let target = paths::default_rootfs_path(clone_id);
+        copy_tree(source_rootfs, &target, false).await?;
+        // Startup registers an existing rootfs instead of extracting the image again
+        sync_engine.set_rootfs_path(clone_id, &target).await
+            .map_err(|e| format!("Failed to record rootfs of {}: {}", clone_id, e))?;
+    }
+
+    for mount in mounts {
+        let source = if options.snapshot_volumes && mount.mount_type == MountType::Volume {
+            snapshot_volume(sync_engine, &mount.source, clone_id).await?
+        } else {
+            mount.source.clone()
+        };
+        sync_engine.add_container_mount(clone_id, &source, &mount.target, mount.mount_type.clone(), mount.readonly, mount.options.clone()).await
+            .map_err(|e| format!("Failed to add mount {} to {}: {}", mount.target, clone_id, e))?;
+    }
+    Ok(())
+}
+
+/// Create a copy of `volume` for the clone and return its name
+async fn snapshot_volume(sync_engine: &SyncEngine, volume: &str, clone_id: &str) -> Result<String, String> {
+    let (labels, options) = match sync_engine.get_volume(volume).await {
+        Ok(Some(existing)) => (existing.labels, existing.options),
+        _ => (HashMap::new(), HashMap::new()),
+    };
+    let name = snapshot_volume_name(volume, clone_id);
+    sync_engine.create_volume(&name, None, labels, options).await
+        .map_err(|e| format!("Failed to create volume {}: {}", name, e))?;
+
+    let source_path = sync_engine.get_volume_path(volume);
+    if source_path.exists() {
+        let target_path = sync_engine.get_volume_path(&name);
+        if let Err(e) = copy_tree(&source_path.to_string_lossy(), &target_path.to_string_lossy(), true).await {
+            let _ = sync_engine.remove_volume(&name, true).await;
+            return Err(e);
+        }
+    }
+    Ok(name)
+}
+
+/// `cp -a` a directory tree; with `into_existing` the contents of `source` go into the existing `target`
+async fn copy_tree(source: &str, target: &str, into_existing: bool) -> Result<(), String> {
+    let source_arg = if into_existing { format!("{}/.", source.trim_end_matches('/')) } else { source.to_string() };
+    let output = tokio::process::Command::new("cp")
+        .arg("-a")
+        .arg(&source_arg)
+        .arg(target)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run cp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to copy {} to {}: {}", source, target, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_volume_name() {
+        assert_eq!(snapshot_volume_name("data", "0123456789ab-cdef"), "data-01234567");
+        assert_eq!(snapshot_volume_name("data", "abc"), "data-abc");
+    }
+}
@@ -0,0 +1,107 @@
+// Container replacement (blue/green deploys)
+// The replacement is cloned from the running container with the new image, command or
+// environment, started next to it and only takes over once it is running and its readiness gates
+// passed: the name and DNS aliases move over in one step, the old container is stopped and
+// removed, and its published host ports are handed to the replacement. Virtual services follow
+// the labels, so the replacement serves them as soon as it runs. A replacement that fails to
+// start is removed and the old container keeps serving untouched.
+
+use super::clone::CloneOptions;
+use super::virtual_services;
+use super::Engine;
+use crate::sync::ContainerState;
+use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::PortMapping;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// What changes between a container and its replacement; everything else is copied
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    pub image_path: Option<String>,
+    pub command: Option<String>,
+    /// Environment variables merged over the old container's
+    pub environment: HashMap<String, String>,
+    /// Labels merged over the old container's
+    pub labels: HashMap<String, String>,
+    /// Limit for the replacement to start and pass its readiness gates
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeployReport {
+    pub old_container_id: String,
+    pub container_id: String,
+    pub name: String,
+}
+
+/// Replace `old_id` with an updated copy once the copy is healthy
+pub async fn replace_container(engine: &Engine, old_id: &str, options: DeployOptions, actor: &str) -> Result<DeployReport, String> {
+    let sync_engine = engine.sync_engine();
+    let old = sync_engine.get_container_status(old_id).await
+        .map_err(|e| format!("Container {} not found: {}", old_id, e))?;
+    let name = old.name.clone().unwrap_or_else(|| old_id.to_string());
+    let ports: Vec<PortMapping> = sync_engine.get_port_bindings(old_id).await.map_err(|e| e.to_string())?
+        .iter()
+        .map(|binding| binding.to_mapping())
+        .collect();
+
+    // The replacement runs next to the old container under a generated name and without host
+    // ports, which can only be bound once
+    let clone_options = CloneOptions {
+        image_path: options.image_path,
+        command: options.command,
+        environment: options.environment,
+        labels: options.labels,
+        ..Default::default()
+    };
+    let new_id = engine.clone_container(old_id, clone_options, actor).await?;
+    // Healthy means the same readiness gates the old container had to pass
+    if let Ok(Some(readiness)) = sync_engine.get_readiness_gates(old_id).await {
+        let _ = sync_engine.set_readiness_gates(&new_id, Some(&readiness)).await;
+    }
+
+    ConsoleLogger::info(&format!("🚢 [DEPLOY] Starting replacement {} for {}", new_id, name));
+    if let Err(e) = engine.start_container(&new_id, options.timeout).await {
+        if let Err(cleanup) = engine.remove_container(&new_id, true, actor).await {
+            ConsoleLogger::warning(&format!("🚢 [DEPLOY] Failed to remove replacement {}: {}", new_id, cleanup));
+        }
+        return Err(format!("Replacement for {} did not become healthy; {} is unchanged: {}", name, old_id, e));
+    }
+
+    // Switch over: the name first, then DNS, so lookups and the records never disagree for long
+    sync_engine.swap_container_names(old_id, &new_id).await
+        .map_err(|e| format!("Failed to hand the name {} to {}: {}", name, new_id, e))?;
+    if let Err(e) = engine.network_manager().transfer_container_dns(old_id, &new_id, &name) {
+        // Host-network containers have no DNS entry to move
+        ConsoleLogger::debug(&format!("🚢 [DEPLOY] DNS not switched to {}: {}", new_id, e));
+    }
+
+    if matches!(old.state, ContainerState::Running) {
+        if let Err(e) = engine.stop_container(old_id, actor).await {
+            ConsoleLogger::warning(&format!("🚢 [DEPLOY] Graceful stop of {} failed; removing it anyway: {}", old_id, e));
+        }
+    }
+    engine.remove_container(old_id, true, actor).await
+        .map_err(|e| format!("{} now serves {}, but removing the old container {} failed: {}", new_id, name, old_id, e))?;
+    if let Err(e) = virtual_services::refresh_virtual_services(sync_engine, engine.network_manager()).await {
+        ConsoleLogger::debug(&format!("⚖️ [VSERVICE] Refresh after deploy skipped: {}", e));
+    }
+
+    if !ports.is_empty() {
+        publish_ports(engine, &new_id, &ports).await
+            .map_err(|e| format!("{} now serves {}, but its host ports were not published: {}", new_id, name, e))?;
+    }
+
+    ConsoleLogger::success(&format!("🚢 [DEPLOY] {} replaced {} as {}", new_id, old_id, name));
+    Ok(DeployReport { old_container_id: old_id.to_string(), container_id: new_id, name })
+}
+
+/// Bind the old container's host ports, released with it, to the running replacement
+async fn publish_ports(engine: &Engine, container_id: &str, ports: &[PortMapping]) -> Result<(), String> {
+    let sync_engine = engine.sync_engine();
+    sync_engine.add_port_bindings(container_id, ports).await.map_err(|e| e.to_string())?;
+    let allocation = sync_engine.get_network_allocation(container_id).await.map_err(|e| e.to_string())?;
+    engine.network_manager().publish_container_ports(container_id, &allocation.ip_address, ports)
+}
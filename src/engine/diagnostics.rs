@@ -0,0 +1,187 @@
+// Diagnostic bundle collection
+// Gathers container state, stored logs, network allocations, firewall rules, cgroup files and
+// recent daemon output into a single tar.gz that can be attached to a bug report
+
+use crate::sync::SyncEngine;
+use crate::utils::command::CommandExecutor;
+use crate::utils::console::ConsoleLogger;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Stored log rows included per container when the caller does not ask for a count
+pub const DEFAULT_DIAGNOSTIC_LOG_LINES: u32 = 500;
+
+/// Largest cgroup file copied into the bundle; larger files are truncated
+const MAX_CGROUP_FILE_BYTES: u64 = 64 * 1024;
+
+/// Host commands whose output goes into `host/`; failures are recorded rather than fatal
+const HOST_COMMANDS: &[(&str, &str)] = &[
+    ("uname.txt", "uname -a"),
+    ("iptables.txt", "iptables-save"),
+    ("iptables-nat.txt", "iptables -t nat -L -n -v"),
+    ("nft-ruleset.txt", "nft list ruleset"),
+    ("ip-addr.txt", "ip addr show"),
+    ("ip-route.txt", "ip route show"),
+    ("bridges.txt", "ip -d link show type bridge"),
+    ("mounts.txt", "cat /proc/mounts"),
+];
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Files accumulated for the archive, relative to its top-level directory
+struct Bundle {
+    root: String,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl Bundle {
+    fn add(&mut self, path: &str, contents: impl Into<Vec<u8>>) {
+        self.files.push((format!("{}/{}", self.root, path), contents.into()));
+    }
+
+    fn into_tar_gz(self) -> Result<Vec<u8>, String> {
+        let mtime = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in &self.files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_slice())
+                .map_err(|e| format!("Failed to add {} to diagnostic bundle: {}", path, e))?;
+        }
+        builder.into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(|e| format!("Failed to finish diagnostic bundle: {}", e))
+    }
+}
+
+/// Build a tar.gz bundle for `container_id` (or host-wide state only when `None`)
+pub async fn collect_diagnostics(sync_engine: &SyncEngine, container_id: Option<&str>, log_lines: u32) -> Result<Vec<u8>, String> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut bundle = Bundle { root: format!("quilt-diagnostics-{}", timestamp), files: Vec::new() };
+    let log_lines = if log_lines == 0 { DEFAULT_DIAGNOSTIC_LOG_LINES } else { log_lines };
+
+    bundle.add("daemon/version.txt", format!("quilt {}\n", env!("CARGO_PKG_VERSION")));
+    bundle.add("daemon/recent.log", ConsoleLogger::recent_lines().join("\n"));
+
+    if let Some(container_id) = container_id {
+        collect_container(&mut bundle, sync_engine, container_id, log_lines).await?;
+    }
+
+    match sync_engine.list_containers(None).await {
+        Ok(containers) => bundle.add("containers.txt", format!("{:#?}\n", containers)),
+        Err(e) => bundle.add("containers.txt", format!("error: {}\n", e)),
+    }
+    match sync_engine.list_network_allocations().await {
+        Ok(allocations) => bundle.add("network/allocations.txt", format!("{:#?}\n", allocations)),
+        Err(e) => bundle.add("network/allocations.txt", format!("error: {}\n", e)),
+    }
+
+    for (file, command) in HOST_COMMANDS {
+        let output = match CommandExecutor::execute_shell_with_timeout(command, COMMAND_TIMEOUT).await {
+            Ok(result) if result.success => result.stdout,
+            Ok(result) => format!("$ {}\nexit {:?}\n{}{}", command, result.exit_code, result.stdout, result.stderr),
+            Err(e) => format!("$ {}\n{}\n", command, e),
+        };
+        bundle.add(&format!("host/{}", file), output);
+    }
+
+    let file_count = bundle.files.len();
+    let archive = tokio::task::spawn_blocking(move || bundle.into_tar_gz()).await
+        .map_err(|e| format!("Diagnostic bundle task failed: {}", e))??;
+    ConsoleLogger::info(&format!("🩺 [DIAGNOSE] Collected {} files ({} bytes){}", file_count, archive.len(),
+        container_id.map(|id| format!(" for container {}", id)).unwrap_or_default()));
+    Ok(archive)
+}
+
+async fn collect_container(bundle: &mut Bundle, sync_engine: &SyncEngine, container_id: &str, log_lines: u32) -> Result<(), String> {
+    let dir = format!("container-{}", container_id);
+
+    let status = sync_engine.get_container_status(container_id).await
+        .map_err(|e| format!("Container {} not found: {}", container_id, e))?;
+    bundle.add(&format!("{}/status.txt", dir), format!("{:#?}\n", status));
+
+    let logs = match sync_engine.get_container_logs(container_id, Some(log_lines)).await {
+        Ok(mut logs) => {
+            logs.reverse();
+            logs.iter()
+                .map(|log| format!("[{}] [{}] [{}] {}", log.timestamp_formatted(), log.level.to_uppercase(), log.actor, log.message))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Err(e) => format!("error: {}", e),
+    };
+    bundle.add(&format!("{}/logs.txt", dir), logs);
+
+    let network = match sync_engine.get_network_allocation(container_id).await {
+        Ok(allocation) => format!("{:#?}\n", allocation),
+        Err(e) => format!("no network allocation: {}\n", e),
+    };
+    bundle.add(&format!("{}/network.txt", dir), network);
+
+    let ports = sync_engine.get_port_bindings(container_id).await.unwrap_or_default();
+    bundle.add(&format!("{}/ports.txt", dir), format!("{:#?}\n", ports));
+
+    // cgroup v2 keeps everything in one directory; v1 splits it by controller
+    let cgroup_root = Path::new("/sys/fs/cgroup");
+    let cgroup_dirs: Vec<(String, std::path::PathBuf)> = if cgroup_root.join("cgroup.controllers").exists() {
+        vec![("cgroup".to_string(), cgroup_root.join("quilt").join(container_id))]
+    } else {
+        ["memory", "cpu", "cpuacct", "pids", "devices"].iter()
+            .map(|controller| (format!("cgroup/{}", controller), cgroup_root.join(controller).join("quilt").join(container_id)))
+            .collect()
+    };
+    for (name, path) in cgroup_dirs {
+        for (file, contents) in read_cgroup_dir(&path) {
+            bundle.add(&format!("{}/{}/{}", dir, name, file), contents);
+        }
+    }
+
+    Ok(())
+}
+
+/// Readable files in a cgroup directory; write-only control files are skipped
+fn read_cgroup_dir(path: &Path) -> Vec<(String, Vec<u8>)> {
+    use std::io::Read;
+
+    let Ok(entries) = std::fs::read_dir(path) else { return Vec::new() };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let mut contents = Vec::new();
+        let read = std::fs::File::open(entry.path())
+            .and_then(|file| file.take(MAX_CGROUP_FILE_BYTES).read_to_end(&mut contents));
+        if read.is_ok() {
+            files.push((entry.file_name().to_string_lossy().to_string(), contents));
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn test_bundle_round_trips_through_tar_gz() {
+        let mut bundle = Bundle { root: "quilt-diagnostics-1".to_string(), files: Vec::new() };
+        bundle.add("daemon/recent.log", "line one\nline two");
+        bundle.add("host/empty.txt", Vec::new());
+
+        let archive = bundle.into_tar_gz().unwrap();
+        let mut reader = tar::Archive::new(GzDecoder::new(archive.as_slice()));
+        let paths: Vec<String> = reader.entries().unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(paths, vec!["quilt-diagnostics-1/daemon/recent.log", "quilt-diagnostics-1/host/empty.txt"]);
+    }
+}
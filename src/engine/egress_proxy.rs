@@ -0,0 +1,80 @@
+// Egress proxy of a container
+// Resolves a container's proxy (see `icc::network::egress_proxy`) each time it starts: the proxy
+// variables go into its environment, and in transparent mode the redirection is installed once
+// its network is up. The proxy container has to be running with its network set up by then. The
+// network reconciler re-checks the redirection, so it follows a proxy that restarted on a new
+// address and comes back after a firewall reload.
+
+use crate::icc::dns::DnsDomainConfig;
+use crate::icc::network::{EgressProxy, NetworkManager};
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+use std::sync::Arc;
+
+/// Bridge address of the running proxy container
+async fn proxy_address(sync_engine: &SyncEngine, proxy: &EgressProxy) -> Result<String, String> {
+    let status = sync_engine.get_container_status(&proxy.container_id).await
+        .map_err(|_| format!("Egress proxy container {} not found", proxy.container_id))?;
+    if status.state != ContainerState::Running {
+        return Err(format!("Egress proxy container {} is not running (state: {})", proxy.container_id, status.state.to_string()));
+    }
+    let allocation = sync_engine.get_network_allocation(&proxy.container_id).await
+        .map_err(|_| format!("Egress proxy container {} has no network", proxy.container_id))?;
+    if !allocation.setup_completed {
+        return Err(format!("Network setup of egress proxy container {} has not completed", proxy.container_id));
+    }
+    Ok(allocation.ip_address)
+}
+
+/// Proxy variables for a container that starts now
+pub async fn environment(sync_engine: &SyncEngine, network_manager: &NetworkManager, proxy: &EgressProxy) -> Result<Vec<(String, String)>, String> {
+    let proxy_ip = proxy_address(sync_engine, proxy).await?;
+    Ok(proxy.environment(&proxy_ip, &network_manager.config.subnet_cidr, &DnsDomainConfig::from_env().cluster_domain))
+}
+
+/// Install the transparent redirection of a container whose network just came up; a failure is
+/// logged to the container
+pub async fn redirect(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, container_id: &str, container_ip: &str, proxy: &EgressProxy) {
+    let result = match proxy_address(sync_engine, proxy).await {
+        Ok(proxy_ip) => {
+            let manager = Arc::clone(network_manager);
+            let (id, ip, port) = (container_id.to_string(), container_ip.to_string(), proxy.port);
+            tokio::task::spawn_blocking(move || manager.redirect_egress_to_proxy(&id, &ip, &proxy_ip, port).map(|_| proxy_ip))
+                .await
+                .map_err(|e| format!("Egress redirect task failed: {}", e))
+                .and_then(|result| result)
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(proxy_ip) => {
+            let _ = sync_engine.store_container_log(container_id, "info",
+                &format!("Web traffic redirected to egress proxy {}:{}", proxy_ip, proxy.port)).await;
+        }
+        Err(e) => {
+            ConsoleLogger::warning(&format!("Egress proxy of {}: {}", container_id, e));
+            let _ = sync_engine.store_container_log(container_id, "warn", &format!("Egress redirect failed: {}", e)).await;
+        }
+    }
+}
+
+/// Reinstall a running container's redirection when rules are missing or point at an old proxy
+/// address. Returns the proxy address when it was reinstalled.
+pub async fn reconcile(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, container_id: &str, container_ip: &str)
+    -> Result<Option<String>, String> {
+    let proxy = match sync_engine.get_egress_proxy(container_id).await {
+        Ok(Some(proxy)) if proxy.transparent => proxy,
+        _ => return Ok(None),
+    };
+    let proxy_ip = proxy_address(sync_engine, &proxy).await?;
+    let manager = Arc::clone(network_manager);
+    let (id, ip) = (container_id.to_string(), container_ip.to_string());
+    tokio::task::spawn_blocking(move || {
+        if manager.egress_redirect_installed(&id, &ip, &proxy_ip, proxy.port) {
+            return Ok(None);
+        }
+        manager.redirect_egress_to_proxy(&id, &ip, &proxy_ip, proxy.port).map(|_| Some(proxy_ip))
+    })
+        .await
+        .map_err(|e| format!("Egress redirect task failed: {}", e))?
+}
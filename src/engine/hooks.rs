@@ -0,0 +1,84 @@
+// Lifecycle hook runner
+// Follows the event buffer and runs the hooks containers registered for started, stopped, died and
+// removed events. Each container's hooks run one event at a time, in event order, so a
+// deregistration never overtakes the registration before it; containers do not wait on each other.
+// A failing hook is logged on the console and in the container's log and does not affect the
+// container.
+
+use crate::daemon::hooks::{self, LIFECYCLE_EVENTS};
+use crate::sync::events::{global_event_buffer, ContainerEvent, EventType};
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+/// Run registered hooks for every lifecycle event until the runtime shuts down
+pub fn spawn_hook_runner(sync_engine: Arc<SyncEngine>) {
+    let mut events = global_event_buffer().subscribe();
+
+    tokio::spawn(async move {
+        let mut queues: HashMap<String, mpsc::UnboundedSender<ContainerEvent>> = HashMap::new();
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    ConsoleLogger::warning(&format!("🪝 [HOOKS] Fell behind the event stream; {} events were not checked for hooks", missed));
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if !LIFECYCLE_EVENTS.contains(&event.event_type) {
+                continue;
+            }
+            queues.retain(|_, queue| !queue.is_closed());
+            // A container with hooks in flight gets its removal queued behind them
+            if !queues.contains_key(&event.container_id)
+                && hooks::hooks_for(&event.container_id, event.event_type).is_none() {
+                if event.event_type == EventType::Removed {
+                    hooks::forget(&event.container_id);
+                }
+                continue;
+            }
+
+            let queue = queues.entry(event.container_id.clone()).or_insert_with(|| {
+                let (queue, pending) = mpsc::unbounded_channel();
+                tokio::spawn(run_container_hooks(sync_engine.clone(), pending));
+                queue
+            });
+            let _ = queue.send(event);
+        }
+    });
+}
+
+/// Work through one container's events in order; ends after its removal
+async fn run_container_hooks(sync_engine: Arc<SyncEngine>, mut pending: mpsc::UnboundedReceiver<ContainerEvent>) {
+    while let Some(event) = pending.recv().await {
+        let removed = event.event_type == EventType::Removed;
+        if let Some((container_name, container_hooks)) = hooks::hooks_for(&event.container_id, event.event_type) {
+            let ip_address = match event.attributes.get("ip_address") {
+                Some(ip) => Some(ip.clone()),
+                None => sync_engine.get_network_allocation(&event.container_id).await.ok().map(|alloc| alloc.ip_address),
+            };
+            for hook in &container_hooks {
+                match hooks::run_hook(hook, &event, &container_name, ip_address.as_deref()).await {
+                    Ok(()) => ConsoleLogger::debug(&format!("🪝 [HOOKS] {} hook '{}' for {} succeeded",
+                        event.event_type.as_str(), hook.describe(), event.container_id)),
+                    Err(e) => {
+                        let message = format!("{} hook '{}' failed: {}", event.event_type.as_str(), hook.describe(), e);
+                        ConsoleLogger::warning(&format!("🪝 [HOOKS] {}: {}", event.container_id, message));
+                        if !removed {
+                            let _ = sync_engine.store_container_log(&event.container_id, "warn", &message).await;
+                        }
+                    }
+                }
+            }
+        }
+        if removed {
+            hooks::forget(&event.container_id);
+            return;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Host port inventory
+// Everything the daemon has opened on the machine: host ports published to containers through
+// NAT, the embedded DNS listener with its port 53 redirect, and the mDNS responder. Published
+// ports of running containers are checked against the installed iptables rules, so an entry
+// reports whether traffic actually reaches its target.
+
+use crate::icc::mdns::MDNS_PORT;
+use crate::icc::network::NetworkManager;
+use crate::sync::containers::ContainerStatus;
+use crate::sync::ports::PortBinding;
+use crate::sync::{ContainerState, SyncEngine};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HostPortKind {
+    /// A container port published with DNAT rules
+    Published,
+    /// The embedded DNS server's UDP listener on the bridge
+    Dns,
+    /// The DNAT rule sending containers' port 53 traffic to the DNS listener
+    DnsRedirect,
+    /// The LAN mDNS responder
+    Mdns,
+    /// The daemon's gRPC API
+    Grpc,
+}
+
+impl HostPortKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HostPortKind::Published => "published",
+            HostPortKind::Dns => "dns",
+            HostPortKind::DnsRedirect => "dns_redirect",
+            HostPortKind::Mdns => "mdns",
+            HostPortKind::Grpc => "grpc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostPort {
+    pub kind: HostPortKind,
+    pub protocol: String,
+    pub host_ip: String,
+    pub host_port: u16,
+    /// Owning container, for published ports
+    pub container_id: Option<String>,
+    pub container_name: Option<String>,
+    /// Where the traffic goes: a container address, or the listener a redirect points at
+    pub target: String,
+    /// Whether the port is live: rules installed, listener serving
+    pub active: bool,
+}
+
+impl HostPort {
+    fn daemon(kind: HostPortKind, protocol: &str, host_ip: &str, host_port: u16, target: String, active: bool) -> Self {
+        Self {
+            kind,
+            protocol: protocol.to_string(),
+            host_ip: host_ip.to_string(),
+            host_port,
+            container_id: None,
+            container_name: None,
+            target,
+            active,
+        }
+    }
+}
+
+/// Every host port allocated by quilt except the gRPC listener, which the server reports itself
+pub async fn list_host_ports(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>) -> Result<Vec<HostPort>, String> {
+    let bindings = sync_engine.list_port_bindings().await.map_err(|e| e.to_string())?;
+    let containers: HashMap<String, ContainerStatus> = sync_engine.list_containers(None).await.map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|container| (container.id.clone(), container))
+        .collect();
+
+    // iptables checks block, so the whole rule audit runs off the runtime
+    let network_manager = Arc::clone(network_manager);
+    tokio::task::spawn_blocking(move || {
+        let mut ports: Vec<HostPort> = group_by_container(&bindings).into_iter()
+            .flat_map(|(container_id, bindings)| {
+                let container = containers.get(container_id);
+                let missing = match container {
+                    Some(ContainerStatus { state: ContainerState::Running, ip_address: Some(ip), .. }) => {
+                        let mappings: Vec<_> = bindings.iter().map(|binding| binding.to_mapping()).collect();
+                        Some(network_manager.missing_port_mappings(container_id, ip, &mappings))
+                    }
+                    // Not running: allocated, but nothing is forwarded
+                    _ => None,
+                };
+                bindings.into_iter().map(move |binding| {
+                    let installed = missing.as_ref().map_or(false, |missing| {
+                        !missing.iter().any(|mapping| mapping.host_port == binding.host_port && mapping.protocol == binding.protocol)
+                    });
+                    published_port(binding, container, installed)
+                }).collect::<Vec<_>>()
+            })
+            .collect();
+
+        if let Some((address, listening)) = network_manager.dns_listener() {
+            let ip = address.ip().to_string();
+            ports.push(HostPort::daemon(HostPortKind::Dns, "udp", &ip, address.port(), "embedded DNS server".to_string(), listening));
+            let redirected = network_manager.dns_redirect_installed();
+            for protocol in ["udp", "tcp"] {
+                ports.push(HostPort::daemon(HostPortKind::DnsRedirect, protocol, &ip, 53, address.to_string(), redirected));
+            }
+        }
+        if network_manager.mdns_enabled() {
+            ports.push(HostPort::daemon(HostPortKind::Mdns, "udp", "0.0.0.0", MDNS_PORT, "mDNS responder".to_string(), true));
+        }
+        ports
+    }).await.map_err(|e| format!("Port audit task failed: {}", e))
+}
+
+/// Bindings grouped by owning container, in host port order within each group
+fn group_by_container(bindings: &[PortBinding]) -> Vec<(&str, Vec<&PortBinding>)> {
+    let mut groups: Vec<(&str, Vec<&PortBinding>)> = Vec::new();
+    for binding in bindings {
+        match groups.iter_mut().find(|(container_id, _)| *container_id == binding.container_id) {
+            Some((_, group)) => group.push(binding),
+            None => groups.push((&binding.container_id, vec![binding])),
+        }
+    }
+    groups
+}
+
+fn published_port(binding: &PortBinding, container: Option<&ContainerStatus>, installed: bool) -> HostPort {
+    let container_ip = container.and_then(|container| container.ip_address.as_deref()).unwrap_or("-");
+    HostPort {
+        kind: HostPortKind::Published,
+        protocol: binding.protocol.clone(),
+        host_ip: binding.host_ip.clone(),
+        host_port: binding.host_port,
+        container_id: Some(binding.container_id.clone()),
+        container_name: container.and_then(|container| container.name.clone()),
+        target: format!("{}:{}", container_ip, binding.container_port),
+        active: installed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(container_id: &str, host_port: u16) -> PortBinding {
+        PortBinding {
+            container_id: container_id.to_string(),
+            host_ip: "0.0.0.0".to_string(),
+            host_port,
+            container_port: 80,
+            protocol: "tcp".to_string(),
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_published_ports() {
+        let bindings = vec![binding("web", 8080), binding("db", 8081), binding("web", 8082)];
+        let groups = group_by_container(&bindings);
+        let summary: Vec<(&str, Vec<u16>)> = groups.iter()
+            .map(|(id, group)| (*id, group.iter().map(|binding| binding.host_port).collect()))
+            .collect();
+        assert_eq!(summary, vec![("web", vec![8080, 8082]), ("db", vec![8081])]);
+
+        let port = published_port(&bindings[1], None, false);
+        assert_eq!(port.kind.as_str(), "published");
+        assert_eq!((port.target.as_str(), port.active), ("-:80", false));
+        assert_eq!(port.container_id.as_deref(), Some("db"));
+    }
+}
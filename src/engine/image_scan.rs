@@ -0,0 +1,62 @@
+// Image scan policy
+// Connects the configured vulnerability scanner to container creation: an image is scanned the
+// first time a container is created from it and again after its file changes, the findings are
+// stored per image, and the block threshold decides whether the container may be created.
+
+use crate::daemon::image_scan::{image_fingerprint, severity_counts, ScannerConfig};
+use crate::sync::image_scans::ImageScanRecord;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+
+use once_cell::sync::Lazy;
+
+static SCANNER: Lazy<Option<ScannerConfig>> = Lazy::new(ScannerConfig::from_env);
+
+/// The scanner set up through QUILT_IMAGE_SCANNER, read once at startup
+pub fn configured_scanner() -> Option<&'static ScannerConfig> {
+    SCANNER.as_ref()
+}
+
+/// The stored scan of `image_path` when it still matches the file, otherwise a fresh one
+pub async fn scan_image(sync_engine: &SyncEngine, scanner: &ScannerConfig, image_path: &str, force: bool) -> Result<ImageScanRecord, String> {
+    // One record per file, however the path was spelled
+    let image_path = std::fs::canonicalize(image_path)
+        .map_err(|_| format!("Image file not found: {}", image_path))?
+        .display()
+        .to_string();
+    let fingerprint = image_fingerprint(&image_path)?;
+    if !force {
+        if let Ok(Some(scan)) = sync_engine.get_image_scan(&image_path).await {
+            if scan.fingerprint == fingerprint && scan.scanner == scanner.kind.as_str() {
+                return Ok(scan);
+            }
+        }
+    }
+
+    ConsoleLogger::info(&format!("🛡️ [IMAGE-SCAN] Scanning {} with {}", image_path, scanner.kind.as_str()));
+    let findings = scanner.scan(&image_path).await
+        .map_err(|e| format!("Scan of {} failed: {}", image_path, e))?;
+    let summary: Vec<String> = severity_counts(&findings).into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(severity, count)| format!("{} {}", count, severity.as_str()))
+        .collect();
+    ConsoleLogger::info(&format!("🛡️ [IMAGE-SCAN] {}: {}", image_path,
+        if summary.is_empty() { "no vulnerabilities".to_string() } else { summary.join(", ") }));
+    sync_engine.store_image_scan(&image_path, &fingerprint, scanner.kind.as_str(), &findings).await
+        .map_err(|e| e.to_string())
+}
+
+/// Decide whether a container may be created from `image_path`. Without a scanner everything is
+/// admitted. A scan that fails is only logged, unless a block threshold is set: then the image's
+/// findings are unknown and it is refused.
+pub async fn admit_image(sync_engine: &SyncEngine, image_path: &str) -> Result<(), String> {
+    let Some(scanner) = configured_scanner() else { return Ok(()) };
+    match scan_image(sync_engine, scanner, image_path, false).await {
+        Ok(scan) => scanner.check(image_path, &scan.findings),
+        Err(e) if scanner.block_at.is_some() => Err(format!("Image {} is not allowed: {}", image_path, e)),
+        Err(e) => {
+            ConsoleLogger::warning(&format!("🛡️ [IMAGE-SCAN] {}; creating the container anyway", e));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Local image store index
+// Connects the image store to container starts: the first start from an image file imports it
+// into the store and indexes it by path, size and modification time; later starts from the
+// unchanged file, and tags of images in the store, find the stored layers through the index.
+// Stored images can be listed, inspected and removed by digest, digest prefix or tag; an image
+// containers were created from stays until they are removed. Archives imported from elsewhere
+// are copied into the daemon's image directory first, so their tags keep resolving after the
+// original file is gone, and stored images export as docker-save or OCI archives.
+
+use crate::daemon::docker_archive;
+use crate::daemon::image_export::{self, ExportFormat};
+use crate::daemon::image_scan::image_fingerprint;
+use crate::daemon::images::{self, StoredImage};
+use crate::daemon::{overlay, registry};
+use crate::sync::images::ImageRecord;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A stored image with the containers created from its files
+#[derive(Debug, Clone)]
+pub struct ImageInfo {
+    pub record: ImageRecord,
+    pub containers: Vec<String>,
+    /// Whether every layer is still in the store
+    pub complete: bool,
+}
+
+/// What removing an image deleted
+#[derive(Debug, Clone)]
+pub struct RemovedImage {
+    pub digest: String,
+    /// Layers no other image shares
+    pub layers: Vec<String>,
+    /// Pulled, built or imported archives of the image in the daemon's image directory
+    pub files: Vec<String>,
+}
+
+/// An image written out as an archive
+#[derive(Debug, Clone)]
+pub struct ExportedImage {
+    pub digest: String,
+    pub path: String,
+    pub format: ExportFormat,
+    pub size_bytes: u64,
+}
+
+/// The stored image of the file at `image_path`, imported into the store first when the index
+/// has no entry for the file as it is now or the store lost some of its layers
+pub async fn ensure_stored(sync_engine: &SyncEngine, image_path: &str) -> Result<StoredImage, String> {
+    // One index entry per file, however the path was spelled
+    let image_path = std::fs::canonicalize(image_path)
+        .map_err(|_| format!("Image file not found: {}", image_path))?
+        .display()
+        .to_string();
+    let fingerprint = image_fingerprint(&image_path)?;
+    let root = images::image_store_root();
+    if let Ok(Some(image)) = sync_engine.find_image_by_source(&image_path, &fingerprint).await {
+        if images::is_complete(&root, &image) {
+            let _ = sync_engine.mark_image_source_used(&image_path).await;
+            return Ok(image);
+        }
+    }
+
+    let import_path = image_path.clone();
+    let image = tokio::task::spawn_blocking(move || images::import(&root, &import_path))
+        .await
+        .map_err(|e| format!("Image import task failed: {}", e))??;
+    sync_engine.record_image(&image_path, &fingerprint, &image).await.map_err(|e| e.to_string())?;
+    let _ = sync_engine.mark_image_source_used(&image_path).await;
+    ConsoleLogger::info(&format!("📦 [IMAGES] Stored {} as {} ({} layers)", image_path, image.digest, image.layers.len()));
+    Ok(image)
+}
+
+/// An image file holding the image tagged `tag`, if the store has one and the file is still there
+pub async fn find_tagged(sync_engine: &SyncEngine, tag: &str) -> Result<Option<String>, String> {
+    let sources = sync_engine.find_image_sources_by_tag(tag).await.map_err(|e| e.to_string())?;
+    Ok(sources.into_iter().find(|source| std::path::Path::new(source).is_file()))
+}
+
+/// Every stored image, most recently stored first
+pub async fn list(sync_engine: &SyncEngine) -> Result<Vec<ImageInfo>, String> {
+    let records = sync_engine.list_images().await.map_err(|e| e.to_string())?;
+    let users = image_users(sync_engine).await?;
+    Ok(records.into_iter().map(|record| image_info(record, &users)).collect())
+}
+
+/// The stored image `reference` names
+pub async fn inspect(sync_engine: &SyncEngine, reference: &str) -> Result<ImageInfo, String> {
+    let records = sync_engine.list_images().await.map_err(|e| e.to_string())?;
+    let record = find_image(&records, reference)?.clone();
+    Ok(image_info(record, &image_users(sync_engine).await?))
+}
+
+/// Remove the stored image `reference` names with the layers and archives only it used. Refused
+/// while containers created from it exist.
+pub async fn remove(sync_engine: &SyncEngine, reference: &str) -> Result<RemovedImage, String> {
+    let records = sync_engine.list_images().await.map_err(|e| e.to_string())?;
+    let info = image_info(find_image(&records, reference)?.clone(), &image_users(sync_engine).await?);
+    if !info.containers.is_empty() {
+        return Err(format!("Image {} is used by container(s) {}; remove them first",
+            info.record.image.digest, info.containers.join(", ")));
+    }
+    let image = info.record.image.clone();
+    sync_engine.remove_image(&image.digest).await.map_err(|e| e.to_string())?;
+
+    let remaining: Vec<StoredImage> = records.into_iter()
+        .map(|record| record.image)
+        .filter(|other| other.digest != image.digest)
+        .collect();
+    let archives: Vec<String> = info.record.sources.iter()
+        .map(|source| source.path.clone())
+        .filter(|path| Path::new(path).starts_with(registry::image_store_dir()))
+        .collect();
+    let root = images::image_store_root();
+    let removed_image = image.clone();
+    let (layers, files) = tokio::task::spawn_blocking(move || -> Result<(Vec<String>, Vec<String>), String> {
+        let lower = overlay::lower_dir(&root, &removed_image);
+        if lower.is_dir() {
+            std::fs::remove_dir_all(&lower).map_err(|e| format!("Failed to remove {}: {}", lower.display(), e))?;
+        }
+        let layers = images::remove_layers(&root, &removed_image, &remaining)?;
+        let files = archives.into_iter().filter(|path| std::fs::remove_file(path).is_ok()).collect();
+        Ok((layers, files))
+    })
+        .await
+        .map_err(|e| format!("Image removal task failed: {}", e))??;
+
+    ConsoleLogger::info(&format!("📦 [IMAGES] Removed {} ({} layer(s), {} archive(s))", image.digest, layers.len(), files.len()));
+    Ok(RemovedImage { digest: image.digest, layers, files })
+}
+
+/// Import a docker-save, OCI layout or plain rootfs archive from `path` on the daemon host
+pub async fn import(sync_engine: &SyncEngine, path: &str) -> Result<ImageInfo, String> {
+    docker_archive::check_image(path)?;
+    let source = PathBuf::from(path);
+    let imports = registry::image_store_dir().join("imports");
+    let archive = tokio::task::spawn_blocking(move || copy_into(&source, &imports))
+        .await
+        .map_err(|e| format!("Image copy task failed: {}", e))??;
+    let image = ensure_stored(sync_engine, &archive.display().to_string()).await?;
+    ConsoleLogger::info(&format!("📦 [IMAGES] Imported {} as {}", path, image.digest));
+    inspect(sync_engine, &image.digest).await
+}
+
+/// Copy `source` into `dir` under the digest of its contents; an identical copy is reused
+fn copy_into(source: &Path, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let partial = dir.join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let copied = std::fs::copy(source, &partial)
+        .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))
+        .and_then(|_| images::sha256_file(&partial))
+        .and_then(|digest| {
+            let target = dir.join(format!("{}.tar", digest.trim_start_matches("sha256:")));
+            if target.is_file() {
+                let _ = std::fs::remove_file(&partial);
+            } else {
+                std::fs::rename(&partial, &target).map_err(|e| format!("Failed to store {}: {}", target.display(), e))?;
+            }
+            Ok(target)
+        });
+    if copied.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    copied
+}
+
+/// Write the stored image `reference` names to `output` on the daemon host. `tag` replaces the
+/// image's own tags in the archive.
+pub async fn export(sync_engine: &SyncEngine, reference: &str, output: &str, format: ExportFormat, tag: Option<&str>) -> Result<ExportedImage, String> {
+    let output_path = PathBuf::from(output);
+    if !output_path.is_absolute() {
+        return Err(format!("Export path {} must be absolute", output));
+    }
+    if output_path.is_dir() {
+        return Err(format!("Export path {} is a directory", output));
+    }
+    let records = sync_engine.list_images().await.map_err(|e| e.to_string())?;
+    let image = find_image(&records, reference)?.image.clone();
+    let repo_tags = match tag {
+        Some(tag) => vec![tag.to_string()],
+        None => image.repo_tags.clone(),
+    };
+
+    let (exported, root, target) = (image.clone(), images::image_store_root(), output_path.clone());
+    let size_bytes = tokio::task::spawn_blocking(move || image_export::export(&root, &exported, &repo_tags, format, &target))
+        .await
+        .map_err(|e| format!("Image export task failed: {}", e))??;
+    ConsoleLogger::info(&format!("📦 [IMAGES] Exported {} to {} ({} format)", image.digest, output, format.as_str()));
+    Ok(ExportedImage { digest: image.digest, path: output.to_string(), format, size_bytes })
+}
+
+fn image_info(record: ImageRecord, users: &HashMap<String, Vec<String>>) -> ImageInfo {
+    let mut containers: Vec<String> = record.sources.iter()
+        .flat_map(|source| users.get(&source.path).cloned().unwrap_or_default())
+        .collect();
+    containers.sort();
+    containers.dedup();
+    let complete = images::is_complete(&images::image_store_root(), &record.image);
+    ImageInfo { record, containers, complete }
+}
+
+/// Container IDs by the image file they were created from, with paths spelled as in the index
+async fn image_users(sync_engine: &SyncEngine) -> Result<HashMap<String, Vec<String>>, String> {
+    let containers = sync_engine.list_containers(None).await.map_err(|e| e.to_string())?;
+    let mut users: HashMap<String, Vec<String>> = HashMap::new();
+    for container in containers {
+        let Ok(config) = sync_engine.get_container_config(&container.id).await else { continue };
+        let path = std::fs::canonicalize(&config.image_path)
+            .map(|path| path.display().to_string())
+            .unwrap_or(config.image_path);
+        users.entry(path).or_default().push(container.id);
+    }
+    Ok(users)
+}
+
+/// The image `reference` names: a tag, a digest, or a digest prefix with or without `sha256:`
+fn find_image<'a>(records: &'a [ImageRecord], reference: &str) -> Result<&'a ImageRecord, String> {
+    if let Some(record) = records.iter().find(|record| record.image.repo_tags.iter().any(|tag| tag == reference)) {
+        return Ok(record);
+    }
+    let prefix = reference.trim_start_matches("sha256:");
+    if prefix.is_empty() {
+        return Err("An image tag or digest is required".to_string());
+    }
+    let matches: Vec<&ImageRecord> = records.iter()
+        .filter(|record| record.image.digest.trim_start_matches("sha256:").starts_with(prefix))
+        .collect();
+    match matches.as_slice() {
+        [record] => Ok(record),
+        [] => Err(format!("Image {} not found", reference)),
+        _ => Err(format!("Image reference {} is ambiguous ({} images match)", reference, matches.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::image_config::ImageConfig;
+
+    fn record(hex: &str, tags: &[&str]) -> ImageRecord {
+        ImageRecord {
+            image: StoredImage {
+                digest: format!("sha256:{}", hex),
+                repo_tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                layers: Vec::new(),
+                config: ImageConfig::default(),
+                size_bytes: 0,
+            },
+            created_at: 0,
+            sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_image() {
+        let records = vec![record("abc123", &["web:latest"]), record("abd456", &[]), record("ff00", &["abc"])];
+        assert_eq!(find_image(&records, "web:latest").unwrap().image.digest, "sha256:abc123");
+        assert_eq!(find_image(&records, "sha256:abd").unwrap().image.digest, "sha256:abd456");
+        // Tags win over digest prefixes
+        assert_eq!(find_image(&records, "abc").unwrap().image.digest, "sha256:ff00");
+        assert!(find_image(&records, "ab").unwrap_err().contains("ambiguous"));
+        assert!(find_image(&records, "web").unwrap_err().contains("not found"));
+        assert!(find_image(&records, "sha256:").is_err());
+    }
+}
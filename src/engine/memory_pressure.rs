@@ -0,0 +1,127 @@
+// Memory-pressure responder
+// Watches host memory pressure and, while it stays above the policy threshold, freezes or
+// throttles one more selected container per pass, largest memory user first. Once pressure drops
+// below the recover threshold every container acted on is released. Each step is recorded as a
+// ResourceLimit event and in the container's log.
+
+use crate::daemon::memory_pressure::{self, AppliedAction, MemoryPressurePolicy, PressureStall};
+use crate::sync::events::{global_event_buffer, EventType};
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Time between pressure checks; PSI averages over 10s, so this reacts within a few seconds
+pub const MEMORY_PRESSURE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Minimum time between two actions, so the previous one can show in the 10s average
+const ACTION_SPACING: Duration = Duration::from_secs(10);
+
+/// Run [`respond_to_memory_pressure`] until the runtime shuts down. Stops quietly on kernels
+/// without PSI.
+pub fn spawn_memory_pressure_responder(sync_engine: Arc<SyncEngine>, policy: MemoryPressurePolicy) {
+    if let Err(e) = memory_pressure::read_host_memory_pressure() {
+        ConsoleLogger::warning(&format!("🧊 [MEM-PRESSURE] Responder disabled: {}", e));
+        return;
+    }
+    ConsoleLogger::info(&format!("🧊 [MEM-PRESSURE] Will {} containers labelled {}={} above {}% full memory pressure",
+        policy.action.as_str(), policy.selector.0, policy.selector.1, policy.threshold));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MEMORY_PRESSURE_CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_action: Option<Instant> = None;
+
+        loop {
+            ticker.tick().await;
+            let pressure = match memory_pressure::read_host_memory_pressure() {
+                Ok(pressure) => pressure,
+                Err(e) => {
+                    ConsoleLogger::debug(&format!("🧊 [MEM-PRESSURE] {}", e));
+                    continue;
+                }
+            };
+            if last_action.map_or(true, |at| at.elapsed() >= ACTION_SPACING)
+                && respond_to_memory_pressure(&sync_engine, &policy, pressure).await {
+                last_action = Some(Instant::now());
+            }
+        }
+    });
+}
+
+/// One pass: act on the next container above the threshold, release everyone below the recover
+/// threshold. Returns whether anything was frozen, throttled or released.
+pub async fn respond_to_memory_pressure(sync_engine: &SyncEngine, policy: &MemoryPressurePolicy, pressure: PressureStall) -> bool {
+    if pressure.full_avg10 < policy.recover_threshold {
+        let acted_on = memory_pressure::acted_on();
+        for (container_id, _) in &acted_on {
+            if let Some(applied) = memory_pressure::release(container_id) {
+                record(sync_engine, container_id, "released", &applied, pressure).await;
+            }
+        }
+        return !acted_on.is_empty();
+    }
+    if pressure.full_avg10 < policy.threshold {
+        return false;
+    }
+
+    let running = match sync_engine.list_containers(Some(ContainerState::Running)).await {
+        Ok(containers) => containers,
+        Err(e) => {
+            ConsoleLogger::warning(&format!("🧊 [MEM-PRESSURE] Failed to list running containers: {}", e));
+            return false;
+        }
+    };
+    let labels = sync_engine.list_container_labels().await.unwrap_or_default();
+    let already: HashMap<String, AppliedAction> = memory_pressure::acted_on().into_iter().collect();
+
+    let mut candidates: Vec<(String, u64)> = running.into_iter()
+        .filter(|container| !already.contains_key(&container.id))
+        .filter(|container| labels.get(&container.id).map_or(false, |labels| policy.selects(labels)))
+        .map(|container| {
+            let usage = memory_pressure::container_memory_usage(&container.id).unwrap_or(0);
+            (container.id, usage)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1));
+
+    for (container_id, _) in candidates {
+        match memory_pressure::apply(&container_id, policy.action) {
+            Ok(applied) => {
+                record(sync_engine, &container_id, applied.as_str(), &applied, pressure).await;
+                return true;
+            }
+            Err(e) => ConsoleLogger::warning(&format!("🧊 [MEM-PRESSURE] Could not {} {}: {}", policy.action.as_str(), container_id, e)),
+        }
+    }
+    if already.is_empty() {
+        ConsoleLogger::debug(&format!("🧊 [MEM-PRESSURE] Host memory pressure at {:.1}% but no container labelled {}={} to {}",
+            pressure.full_avg10, policy.selector.0, policy.selector.1, policy.action.as_str()));
+    }
+    false
+}
+
+async fn record(sync_engine: &SyncEngine, container_id: &str, outcome: &str, applied: &AppliedAction, pressure: PressureStall) {
+    let message = if outcome == "released" {
+        format!("Container no longer {}: host memory pressure eased (full {:.1}%)", applied.as_str(), pressure.full_avg10)
+    } else {
+        format!("Container {} under host memory pressure (full {:.1}%, some {:.1}%)", outcome, pressure.full_avg10, pressure.some_avg10)
+    };
+    ConsoleLogger::warning(&format!("🧊 [MEM-PRESSURE] {}: {}", container_id, message));
+    // A frozen container is paused until it is thawed
+    if matches!(applied, AppliedAction::Frozen) {
+        let state = if outcome == "released" { ContainerState::Running } else { ContainerState::Paused };
+        let _ = sync_engine.update_container_state(container_id, state).await;
+    }
+    let _ = sync_engine.store_container_log(container_id, "warn", &message).await;
+
+    let attributes = HashMap::from([
+        ("resource".to_string(), "memory".to_string()),
+        ("action".to_string(), outcome.to_string()),
+        ("mechanism".to_string(), applied.as_str().to_string()),
+        ("pressure_full_avg10".to_string(), format!("{:.2}", pressure.full_avg10)),
+        ("pressure_some_avg10".to_string(), format!("{:.2}", pressure.some_avg10)),
+    ]);
+    global_event_buffer().emit(EventType::ResourceLimit, container_id, Some(attributes));
+}
@@ -0,0 +1,87 @@
+// Background metrics collection
+// Samples every running container on its own interval and stores the samples, so the metrics
+// history in SQLite has no gaps while nobody calls GetMetrics. Each container's next sample is
+// moved by a random share of its interval (the policy's jitter), so containers started together
+// are not all read in the same tick. Containers can opt out, or get their own interval, through
+// SetMetricsCollection.
+
+use crate::daemon::metrics::{MetricsCollectionPolicy, MetricsCollector, DEFAULT_METRICS_INTERVAL_SECS};
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often the collector looks for containers that are due; the finest interval it honours
+pub const METRICS_COLLECTOR_TICK: Duration = Duration::from_secs(1);
+
+static DEFAULT_INTERVAL_SECS: AtomicU32 = AtomicU32::new(DEFAULT_METRICS_INTERVAL_SECS);
+
+/// Interval of containers without their own, as configured for the running collector
+pub fn default_interval_secs() -> u32 {
+    DEFAULT_INTERVAL_SECS.load(Ordering::Relaxed)
+}
+
+/// Run [`collect_due_metrics`] every tick until the runtime shuts down
+pub fn spawn_metrics_collector(sync_engine: Arc<SyncEngine>, policy: MetricsCollectionPolicy) {
+    DEFAULT_INTERVAL_SECS.store(policy.interval.as_secs() as u32, Ordering::Relaxed);
+    ConsoleLogger::info(&format!("📊 [METRICS] Sampling running containers every {}s (±{}%)",
+        policy.interval.as_secs(), policy.jitter_percent));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(METRICS_COLLECTOR_TICK);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut next_due = HashMap::new();
+
+        loop {
+            ticker.tick().await;
+            collect_due_metrics(&sync_engine, &policy, &mut next_due).await;
+        }
+    });
+}
+
+/// One pass: sample and store every running container whose next sample is due. `next_due`
+/// carries the schedule between passes. Returns how many samples were stored.
+pub async fn collect_due_metrics(sync_engine: &SyncEngine, policy: &MetricsCollectionPolicy, next_due: &mut HashMap<String, Instant>) -> usize {
+    let schedules = match sync_engine.list_metrics_schedules().await {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            ConsoleLogger::warning(&format!("📊 [METRICS] Failed to list running containers: {}", e));
+            return 0;
+        }
+    };
+    // Stopped and opted-out containers start over with a new offset if they come back
+    next_due.retain(|container_id, _| schedules.iter().any(|s| s.enabled && s.container_id == *container_id));
+
+    let now = Instant::now();
+    let collector = MetricsCollector::new();
+    let mut stored = 0;
+    for schedule in schedules.iter().filter(|schedule| schedule.enabled) {
+        let interval = policy.interval_for(schedule.interval_seconds);
+        // A new container gets its first sample within the jitter window instead of all at once
+        let due = *next_due.entry(schedule.container_id.clone())
+            .or_insert_with(|| now + interval.mul_f64(policy.jitter_percent as f64 / 100.0 * (random_unit() + 1.0) / 2.0));
+        if due > now {
+            continue;
+        }
+        next_due.insert(schedule.container_id.clone(), now + policy.jittered(interval, random_unit()));
+
+        match collector.collect_container_metrics(&schedule.container_id, schedule.pid.map(|pid| pid as i32)) {
+            Ok(metrics) => match sync_engine.store_metrics(&metrics).await {
+                Ok(()) => stored += 1,
+                Err(e) => ConsoleLogger::warning(&format!("📊 [METRICS] Failed to store sample of {}: {}", schedule.container_id, e)),
+            },
+            Err(e) => ConsoleLogger::debug(&format!("📊 [METRICS] Failed to sample {}: {}", schedule.container_id, e)),
+        }
+    }
+    stored
+}
+
+/// A value spread evenly over -1.0..1.0; the std hasher keys are random per instance
+fn random_unit() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64 * 2.0 - 1.0
+}
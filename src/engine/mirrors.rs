@@ -0,0 +1,86 @@
+// Traffic mirroring between containers
+// Copies a running container's veth traffic to an observer container (see
+// `icc::network::mirror`), e.g. an IDS or capture sidecar, and records the pair. A mirror ends
+// when either container stops or is removed, since the veths it joins go with their namespaces.
+
+use crate::icc::network::{MirrorDirection, NetworkManager};
+use crate::sync::traffic_mirrors::TrafficMirror;
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+use std::sync::Arc;
+
+/// Host-side veth of a running container whose network is set up
+async fn running_veth(sync_engine: &SyncEngine, container_id: &str) -> Result<String, String> {
+    let status = sync_engine.get_container_status(container_id).await.map_err(|e| e.to_string())?;
+    if status.state != ContainerState::Running {
+        return Err(format!("Container {} is not running (state: {})", container_id, status.state.to_string()));
+    }
+    let allocation = sync_engine.get_network_allocation(container_id).await
+        .map_err(|_| format!("Container {} has no network", container_id))?;
+    match (allocation.setup_completed, allocation.veth_host) {
+        (true, Some(veth)) => Ok(veth),
+        _ => Err(format!("Network setup for {} has not completed", container_id)),
+    }
+}
+
+/// Mirror `source_id`'s traffic to `observer_id`, replacing the source's previous mirror
+pub async fn start(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, source_id: &str, observer_id: &str, direction: MirrorDirection, actor: &str)
+    -> Result<TrafficMirror, String> {
+    if source_id == observer_id {
+        return Err("A container cannot observe its own traffic".to_string());
+    }
+    let source_veth = running_veth(sync_engine, source_id).await?;
+    let observer_veth = running_veth(sync_engine, observer_id).await?;
+
+    let manager = Arc::clone(network_manager);
+    let id = source_id.to_string();
+    tokio::task::spawn_blocking(move || manager.mirror_container_traffic(&id, &source_veth, &observer_veth, direction))
+        .await
+        .map_err(|e| format!("Mirror task failed: {}", e))??;
+    let mirror = sync_engine.set_traffic_mirror(source_id, observer_id, direction.as_str()).await.map_err(|e| e.to_string())?;
+
+    let _ = sync_engine.store_container_log_as(source_id, "info",
+        &format!("Mirroring {} traffic to {}", direction.as_str(), observer_id), actor).await;
+    Ok(mirror)
+}
+
+/// Stop mirroring `source_id`'s traffic
+pub async fn stop(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, source_id: &str, actor: &str) -> Result<TrafficMirror, String> {
+    let mirror = sync_engine.get_traffic_mirror(source_id).await.map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Container {} has no traffic mirror", source_id))?;
+    if let Ok(allocation) = sync_engine.get_network_allocation(source_id).await {
+        if let Some(veth) = allocation.veth_host {
+            let manager = Arc::clone(network_manager);
+            let id = source_id.to_string();
+            tokio::task::spawn_blocking(move || manager.stop_traffic_mirror(&id, &veth))
+                .await
+                .map_err(|e| format!("Mirror task failed: {}", e))??;
+        }
+    }
+    sync_engine.remove_traffic_mirror(source_id).await.map_err(|e| e.to_string())?;
+    let _ = sync_engine.store_container_log_as(source_id, "info",
+        &format!("Stopped mirroring traffic to {}", mirror.observer_container_id), actor).await;
+    Ok(mirror)
+}
+
+/// End the mirrors of a container that stopped or is being removed, as source or observer.
+/// A source that keeps running stops copying into a veth that is gone.
+pub async fn release_all(sync_engine: &SyncEngine, network_manager: &NetworkManager, container_id: &str) {
+    let mirrors = sync_engine.get_container_traffic_mirrors(container_id).await.unwrap_or_default();
+    for mirror in &mirrors {
+        if mirror.source_container_id != container_id {
+            let veth = sync_engine.get_network_allocation(&mirror.source_container_id).await.ok().and_then(|allocation| allocation.veth_host);
+            if let Some(veth) = veth {
+                if let Err(e) = network_manager.stop_traffic_mirror(&mirror.source_container_id, &veth) {
+                    ConsoleLogger::warning(&e);
+                }
+            }
+        }
+        if let Err(e) = sync_engine.remove_traffic_mirror(&mirror.source_container_id).await {
+            ConsoleLogger::warning(&format!("Failed to forget the traffic mirror of {}: {}", mirror.source_container_id, e));
+        }
+    }
+    if !mirrors.is_empty() {
+        ConsoleLogger::debug(&format!("Container {} left {} traffic mirror(s)", container_id, mirrors.len()));
+    }
+}
@@ -0,0 +1,1027 @@
+// Embeddable container engine
+// Wires the sync engine, container runtime and network manager together so an application can
+// create and manage containers in-process; the gRPC daemon is a thin wrapper around this
+
+pub mod build;
+pub mod clone;
+pub mod deploy;
+pub mod diagnostics;
+pub mod egress_proxy;
+pub mod hooks;
+pub mod host_ports;
+pub mod image_scan;
+pub mod images;
+pub mod memory_pressure;
+pub mod metrics_collection;
+pub mod mirrors;
+pub mod network_health;
+pub mod network_policy;
+pub mod networks;
+pub mod plugins;
+pub mod priority;
+pub mod readiness;
+pub mod scale;
+pub mod setup_steps;
+pub mod startup;
+pub mod startup_pipeline;
+pub mod virtual_services;
+
+pub use build::BuildReport;
+pub use images::{ExportedImage, ImageInfo, RemovedImage};
+pub use crate::daemon::image_export::ExportFormat;
+pub use clone::CloneOptions;
+pub use deploy::{DeployOptions, DeployReport};
+pub use host_ports::{HostPort, HostPortKind};
+pub use network_health::DEFAULT_NETWORK_RECONCILE_INTERVAL;
+pub use scale::{ScaleReport, ScaleTemplate};
+pub use startup::{run_container_startup, DEFAULT_STARTUP_TIMEOUT};
+pub use virtual_services::ServiceBackend;
+
+use crate::daemon::host_check::check_host_requirements;
+pub use crate::daemon::hooks::LifecycleHook;
+pub use crate::daemon::prestart::{HookNamespace, PrestartHook};
+pub use crate::daemon::readiness_gates::{ReadinessGate, ReadinessGates};
+pub use crate::daemon::priority::PriorityClass;
+use crate::daemon::memory_pressure::MemoryPressurePolicy;
+use crate::daemon::metrics::MetricsCollectionPolicy;
+use crate::daemon::namespace::has_own_namespace;
+use crate::daemon::net_policy::NetworkPolicy;
+use crate::daemon::paths;
+pub use crate::daemon::host_check::{CheckStatus, HostCheck};
+pub use crate::daemon::exec_cgroup::{ExecCgroup, ExecUsage};
+pub use crate::daemon::image_scan::{Severity, VulnerabilityFinding, IMAGE_SCANNER_ENV};
+use crate::daemon::resource::ResourceManager;
+use crate::daemon::rootfs_pool::RootfsPool;
+use crate::daemon::runtime::ContainerRuntime;
+use crate::daemon::image_config;
+use crate::daemon::registry::{self, ImageReference};
+pub use crate::daemon::registry::PulledImage;
+use crate::daemon::time_offset;
+use crate::daemon::workdir;
+use crate::icc::network::security::NetworkSecurity;
+use crate::icc::network::NetworkManager;
+use crate::sync::containers::{ContainerConfig, ContainerStatus};
+use crate::sync::events::{global_event_buffer, EventType};
+pub use crate::sync::image_scans::ImageScanRecord;
+pub use crate::sync::events::DAEMON_ACTOR;
+use crate::sync::plugins::PluginRecord;
+use crate::sync::virtual_services::VirtualServiceRecord;
+pub use crate::sync::dns_records::DnsRecord;
+pub use crate::sync::network_attachments::NetworkAttachment;
+pub use crate::sync::traffic_mirrors::TrafficMirror;
+pub use crate::icc::network::MirrorDirection;
+pub use crate::icc::network::EgressProxy;
+use crate::icc::dns::{DnsDomainConfig, DnsView};
+use crate::sync::volumes::Volume;
+use crate::sync::{ContainerState, MountType, SyncEngine};
+use crate::utils::command::{Argv, CommandExecutor, CommandResult};
+use crate::utils::console::ConsoleLogger;
+use crate::utils::container_log::ContainerLogLevel;
+use crate::utils::filesystem::FileSystemUtils;
+use crate::utils::process::ProcessUtils;
+use crate::utils::validation::InputValidator;
+
+use nix::sys::signal::Signal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Settings for an in-process engine; the defaults match the quilt daemon
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// SQLite database holding container, network and volume state
+    pub database_path: String,
+    pub bridge_name: String,
+    /// Subnet containers are allocated from
+    pub subnet: String,
+    /// Serve container name resolution on the bridge
+    pub enable_dns: bool,
+    /// How often the bridge, veth attachments and published-port rules are re-checked and
+    /// repaired in the background; zero disables the reconciler
+    pub network_reconcile_interval: Duration,
+    /// Freeze or throttle selected containers under host memory pressure; None disables the
+    /// responder. Defaults to `QUILT_MEMORY_PRESSURE`.
+    pub memory_pressure: Option<MemoryPressurePolicy>,
+    /// Sample running containers in the background and store the samples; None leaves
+    /// collection to GetMetrics. Defaults to `QUILT_METRICS_COLLECTION`, else on every 30s.
+    pub metrics_collection: Option<MetricsCollectionPolicy>,
+    /// Directory container rootfs directories are created in; fixed for the process once the
+    /// first engine starts. Defaults to `QUILT_ROOTFS_DIR`, else `/tmp/quilt-containers`.
+    pub rootfs_base_dir: String,
+    /// Directory scanned for plugin sockets and spec files. Defaults to `QUILT_PLUGIN_DIR`, else
+    /// `/run/quilt/plugins`.
+    pub plugin_dir: String,
+    /// Domain container names are published under and the search list written to their
+    /// resolv.conf. Defaults to `QUILT_DNS_DOMAIN` (else `quilt.local`) and `QUILT_DNS_SEARCH`.
+    pub dns_domain: DnsDomainConfig,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            database_path: "quilt.db".to_string(),
+            bridge_name: "quilt0".to_string(),
+            subnet: "10.42.0.0/16".to_string(),
+            enable_dns: true,
+            network_reconcile_interval: DEFAULT_NETWORK_RECONCILE_INTERVAL,
+            memory_pressure: MemoryPressurePolicy::from_env(),
+            metrics_collection: MetricsCollectionPolicy::from_env(),
+            rootfs_base_dir: paths::rootfs_base_dir_from_env(),
+            plugin_dir: plugins::plugin_dir_from_env(),
+            dns_domain: DnsDomainConfig::from_env(),
+        }
+    }
+}
+
+/// Container engine: owns the state store, the runtime and host networking
+pub struct Engine {
+    sync_engine: Arc<SyncEngine>,
+    network_manager: Arc<NetworkManager>,
+    runtime: Arc<ContainerRuntime>,
+}
+
+impl Engine {
+    /// Bring up the bridge, DNS, state store and runtime. Requires root, like the daemon.
+    pub async fn new(config: EngineConfig) -> Result<Self, String> {
+        // Before anything creates, restores or validates a rootfs
+        paths::set_rootfs_base_dir(&config.rootfs_base_dir)?;
+
+        // Initialize ICC network manager first
+        let mut network_manager = NetworkManager::new(&config.bridge_name, &config.subnet)
+            .map_err(|e| format!("Failed to create network manager: {}", e))?;
+
+        // CRITICAL: Ensure bridge is ready before any other network operations
+        network_manager.ensure_bridge_ready()
+            .map_err(|e| format!("Failed to setup network bridge: {}", e))?;
+
+        // SECURITY: Verify bridge isolation after setup
+        let security = NetworkSecurity::new("192.168.100.1".to_string());
+        if let Err(e) = security.verify_bridge_isolation(&config.bridge_name) {
+            ConsoleLogger::warning(&format!("Bridge isolation verification failed: {}", e));
+        }
+
+        ConsoleLogger::success("Bridge network initialized - containers can now communicate");
+
+        // Before the DNS redirect goes in: creating the firewalld zone reloads firewalld
+        if let Some(firewall) = network_manager.host_firewall {
+            match network_manager.reconcile_host_firewall() {
+                Ok(_) => ConsoleLogger::success(&format!("Bridge {} registered with {}", config.bridge_name, firewall.as_str())),
+                Err(e) => ConsoleLogger::warning(&format!("Could not register bridge {} with {} (bridged traffic may be filtered): {}",
+                    config.bridge_name, firewall.as_str(), e)),
+            }
+        }
+
+        // Before the DNS server starts and any container gets a resolv.conf
+        network_manager.set_dns_domain(config.dns_domain.clone());
+
+        // Pre-create warm network namespaces when QUILT_NETNS_POOL_SIZE is set
+        network_manager.start_netns_pool();
+
+        // Start DNS server (non-critical - bridge networking works without DNS)
+        if config.enable_dns {
+            match network_manager.start_dns_server().await {
+                Ok(()) => {
+                    ConsoleLogger::success("DNS server started - containers can resolve names");
+                }
+                Err(e) => {
+                    ConsoleLogger::warning(&format!("DNS server startup failed (non-critical): {}", e));
+                    ConsoleLogger::info("Bridge networking is fully functional - containers can communicate via IP addresses");
+                }
+            }
+        }
+
+        ConsoleLogger::success("Network manager initialized with bridge networking");
+
+        // Initialize sync engine with ICC network manager integration
+        let network_manager = Arc::new(network_manager);
+        let sync_engine = Arc::new(SyncEngine::new_with_network_config(
+            &config.database_path,
+            Some(config.subnet.clone()),
+            Some(network_manager.clone())
+        ).await.map_err(|e| format!("Failed to initialize sync engine: {}", e))?);
+
+        // Start background services for monitoring and cleanup with ICC integration
+        sync_engine.start_background_services().await
+            .map_err(|e| format!("Failed to start sync engine services: {}", e))?;
+
+        ConsoleLogger::success("✅ Sync engine initialized with ICC network manager integration - enhanced cleanup enabled");
+
+        // Containers and volumes created before a restart keep matching event label selectors
+        if let Err(e) = sync_engine.load_event_labels().await {
+            ConsoleLogger::warning(&format!("Failed to load labels for event filtering: {}", e));
+        }
+
+        // Hooks of containers created before a restart keep firing
+        if let Err(e) = sync_engine.load_container_hooks().await {
+            ConsoleLogger::warning(&format!("Failed to load container lifecycle hooks: {}", e));
+        }
+        hooks::spawn_hook_runner(sync_engine.clone());
+
+        // Plugins registered before a restart come back; new ones are picked up from the plugin dir
+        plugins::spawn_plugin_discovery(sync_engine.clone(), config.plugin_dir.clone());
+
+        // Firewall reloads and manual `ip link` changes happen behind our back; repair them
+        if !config.network_reconcile_interval.is_zero() {
+            network_health::spawn_network_reconciler(sync_engine.clone(), network_manager.clone(), config.network_reconcile_interval);
+        }
+
+        // Virtual service names follow their label-selected backends as containers come and go
+        virtual_services::spawn_virtual_service_refresher(sync_engine.clone(), network_manager.clone(),
+            virtual_services::VIRTUAL_SERVICE_REFRESH_INTERVAL);
+
+        // Records set before a restart answer again in their views
+        if let Err(e) = restore_dns_records(&sync_engine, &network_manager).await {
+            ConsoleLogger::warning(&format!("Failed to restore DNS records: {}", e));
+        }
+        if let Err(e) = networks::restore_attachments(&sync_engine, &network_manager).await {
+            ConsoleLogger::warning(&format!("Failed to restore additional networks: {}", e));
+        }
+
+        // Choose who gives way under memory pressure instead of leaving it to the OOM killer
+        if let Some(policy) = config.memory_pressure.clone() {
+            memory_pressure::spawn_memory_pressure_responder(sync_engine.clone(), policy);
+        }
+
+        // Metrics history keeps filling while nobody polls GetMetrics
+        if let Some(policy) = config.metrics_collection.clone() {
+            metrics_collection::spawn_metrics_collector(sync_engine.clone(), policy);
+        }
+
+        // Initialize container runtime
+        let runtime = Arc::new(ContainerRuntime::new());
+
+        // Pre-extract rootfs for images listed in QUILT_ROOTFS_POOL
+        let rootfs_pool = RootfsPool::global();
+        if rootfs_pool.enabled() {
+            rootfs_pool.remove_stale();
+            let pool_runtime = runtime.clone();
+            tokio::task::spawn_blocking(move || pool_runtime.refill_rootfs_pool());
+        }
+
+        Ok(Self { sync_engine, network_manager, runtime })
+    }
+
+    pub fn sync_engine(&self) -> &Arc<SyncEngine> {
+        &self.sync_engine
+    }
+
+    pub fn network_manager(&self) -> &Arc<NetworkManager> {
+        &self.network_manager
+    }
+
+    pub fn runtime(&self) -> &Arc<ContainerRuntime> {
+        &self.runtime
+    }
+
+    /// Record a container and allocate its network; returns its ID. An empty `config.id` gets a fresh UUID.
+    /// `actor` (e.g. [`DAEMON_ACTOR`]) is recorded on the event and log row for this and the other lifecycle calls.
+    pub async fn create_container(&self, mut config: ContainerConfig, actor: &str) -> Result<String, String> {
+        if config.id.is_empty() {
+            config.id = uuid::Uuid::new_v4().to_string();
+        }
+        let container_id = config.id.clone();
+
+        self.sync_engine.create_container(config).await
+            .map_err(|e| format!("Failed to create container: {}", e))?;
+        global_event_buffer().emit_as(EventType::Created, &container_id, actor, None);
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info", "Container created and configured", actor).await;
+        Ok(container_id)
+    }
+
+    /// Pull `reference` from its registry into the image store; an image already there is returned
+    /// as is unless `force` is set
+    pub async fn pull_image(&self, reference: &str, force: bool) -> Result<PulledImage, String> {
+        let reference = ImageReference::parse(reference)?;
+        registry::pull(&registry::image_store_dir(), &reference, force).await
+    }
+
+    /// The tarball a container is created from: `image` itself when it names a file, else the
+    /// file of an image with that tag in the local image store, else the stored archive of the
+    /// registry reference, pulled first if it is not in the store
+    pub async fn resolve_image(&self, image: &str) -> Result<String, String> {
+        if image.is_empty() || registry::is_image_file(image) {
+            return Ok(image.to_string());
+        }
+        if let Some(source) = images::find_tagged(&self.sync_engine, image).await? {
+            return Ok(source);
+        }
+        Ok(self.pull_image(image, false).await?.image_path)
+    }
+
+    /// Run a Quiltfile in a temporary container and store the result in the image store as `tag`
+    pub async fn build_image(&self, quiltfile: &str, context_dir: &str, tag: &str, actor: &str) -> Result<BuildReport, String> {
+        build::build_image(self, quiltfile, context_dir, tag, actor).await
+    }
+
+    /// Images in the local store with the containers created from them
+    pub async fn list_images(&self) -> Result<Vec<ImageInfo>, String> {
+        images::list(&self.sync_engine).await
+    }
+
+    /// A stored image by tag, digest or digest prefix
+    pub async fn inspect_image(&self, reference: &str) -> Result<ImageInfo, String> {
+        images::inspect(&self.sync_engine, reference).await
+    }
+
+    /// Import a docker-save or OCI archive on the daemon host into the image store
+    pub async fn import_image(&self, path: &str, actor: &str) -> Result<ImageInfo, String> {
+        let image = images::import(&self.sync_engine, path).await?;
+        ConsoleLogger::info(&format!("📦 [IMAGES] {} imported {}", actor, image.record.image.digest));
+        Ok(image)
+    }
+
+    /// Write a stored image to an archive on the daemon host
+    pub async fn export_image(&self, reference: &str, output: &str, format: ExportFormat, tag: Option<&str>) -> Result<ExportedImage, String> {
+        images::export(&self.sync_engine, reference, output, format, tag).await
+    }
+
+    /// Remove a stored image no container was created from
+    pub async fn remove_image(&self, reference: &str, actor: &str) -> Result<RemovedImage, String> {
+        let removed = images::remove(&self.sync_engine, reference).await?;
+        ConsoleLogger::info(&format!("📦 [IMAGES] {} removed image {}", actor, removed.digest));
+        Ok(removed)
+    }
+
+    /// Refuse an image the configured vulnerability scan policy blocks; scans it on first use
+    pub async fn admit_image(&self, image_path: &str) -> Result<(), String> {
+        image_scan::admit_image(&self.sync_engine, image_path).await
+    }
+
+    /// Scan an image now, or return its stored scan while the file is unchanged and `force` is
+    /// not set
+    pub async fn scan_image(&self, image_path: &str, force: bool) -> Result<ImageScanRecord, String> {
+        let scanner = image_scan::configured_scanner()
+            .ok_or_else(|| format!("No image scanner configured: set {} to trivy or grype", IMAGE_SCANNER_ENV))?;
+        image_scan::scan_image(&self.sync_engine, scanner, image_path, force).await
+    }
+
+    pub async fn list_image_scans(&self) -> Result<Vec<ImageScanRecord>, String> {
+        self.sync_engine.list_image_scans().await.map_err(|e| e.to_string())
+    }
+
+    /// Record a new container with `source_id`'s settings, optionally seeded with copies of its
+    /// rootfs and named volumes; returns the clone's ID. The clone is not started.
+    pub async fn clone_container(&self, source_id: &str, options: CloneOptions, actor: &str) -> Result<String, String> {
+        clone::clone_container(&self.sync_engine, source_id, options, actor).await
+    }
+
+    /// Replace a container with an updated copy: the copy starts next to it and takes over its
+    /// name, DNS aliases and host ports once healthy, then the old container is removed
+    pub async fn replace_container(&self, container_id: &str, options: DeployOptions, actor: &str) -> Result<DeployReport, String> {
+        deploy::replace_container(self, container_id, options, actor).await
+    }
+
+    /// The template container behind a scale target: a container, a replica, or a virtual service
+    pub async fn resolve_scale_template(&self, target: &str) -> Result<ScaleTemplate, String> {
+        scale::resolve_template(&self.sync_engine, target).await
+    }
+
+    /// Converge `template`'s replica set to `replicas` members; new replicas are not started
+    pub async fn scale(&self, template: &ScaleTemplate, replicas: u32, actor: &str) -> Result<ScaleReport, String> {
+        scale::scale(self, template, replicas, actor).await
+    }
+
+    /// Start a created or exited container and wait until it is running. A start that fails or
+    /// exceeds `timeout` (default [`DEFAULT_STARTUP_TIMEOUT`]) is rolled back.
+    pub async fn start_container(&self, container_id: &str, timeout: Option<Duration>) -> Result<(), String> {
+        run_container_startup(
+            &self.sync_engine,
+            container_id,
+            self.network_manager.clone(),
+            self.runtime.clone(),
+            timeout.unwrap_or(DEFAULT_STARTUP_TIMEOUT),
+        ).await
+    }
+
+    /// Stop a container's process; its rootfs and network are kept for a restart
+    pub async fn stop_container(&self, container_id: &str, actor: &str) -> Result<(), String> {
+        let _guard = self.sync_engine.lock_container(container_id, "stop").await.map_err(|e| e.to_string())?;
+        let state = self.sync_engine.get_container_status(container_id).await.map_err(|e| e.to_string())?.state;
+        if matches!(state, ContainerState::Created | ContainerState::Exited) {
+            return Err(format!("Container {} is not running (state: {})", container_id, state.to_string()));
+        }
+
+        // Mark the stop as in progress so clients do not race it; containers that are not
+        // running have no intermediate state to show
+        let stopping = self.sync_engine.update_container_state(container_id, ContainerState::Stopping).await.is_ok();
+
+        // Use the comprehensive runtime stop_container method
+        if let Err(e) = self.runtime.stop_container(container_id) {
+            if stopping {
+                let _ = self.sync_engine.update_container_state(container_id, ContainerState::Running).await;
+            }
+            let _ = self.sync_engine.store_container_log(container_id, "error", &format!("Failed to stop container: {}", e)).await;
+            ConsoleLogger::error(&format!("Failed to stop container {}: {}", container_id, e));
+            return Err(e);
+        }
+
+        // Update sync engine state
+        if let Err(e) = self.sync_engine.update_container_state(container_id, ContainerState::Exited).await {
+            ConsoleLogger::warning(&format!("Failed to update container state in sync engine: {}", e));
+        }
+
+        // Stop monitoring in sync engine
+        let _ = self.sync_engine.stop_monitoring(container_id).await;
+
+        // The additional networks' veths went with the namespace, and so did mirrors through its veth
+        networks::release_all(&self.sync_engine, &self.network_manager, container_id).await;
+        mirrors::release_all(&self.sync_engine, &self.network_manager, container_id).await;
+        // The address may go to another container before this one starts again
+        if self.sync_engine.get_egress_proxy(container_id).await.ok().flatten().map_or(false, |proxy| proxy.transparent) {
+            self.network_manager.remove_egress_redirect(container_id);
+        }
+
+        // Store stop log
+        let _ = self.sync_engine.store_container_log_as(container_id, "info", "Container stopped successfully", actor).await;
+
+        global_event_buffer().emit_as(EventType::Stopped, container_id, actor, None);
+        Ok(())
+    }
+
+    /// Release everything a container holds: process, rootfs, ports, DNS and state. A container
+    /// that is still running is refused unless `force`, which kills its process first.
+    pub async fn remove_container(&self, container_id: &str, force: bool, actor: &str) -> Result<(), String> {
+        // Waits for stops, starts and exec preparation in progress; later operations find the
+        // container gone
+        let _guard = self.sync_engine.lock_container(container_id, "remove").await.map_err(|e| e.to_string())?;
+        let status = self.sync_engine.get_container_status(container_id).await.map_err(|e| e.to_string())?;
+        let active = matches!(status.state, ContainerState::Starting | ContainerState::Running | ContainerState::Paused
+            | ContainerState::Stopping | ContainerState::Restarting);
+        if active && !force {
+            return Err(format!("Container {} is {}; stop it first or remove it with force", container_id, status.state.to_string()));
+        }
+        if active {
+            // SIGKILL instead of the runtime's graceful stop; it only has to reap the process now
+            let pid = self.runtime.get_container_info(container_id)
+                .and_then(|info| info.pid)
+                .or_else(|| status.pid.map(|pid| ProcessUtils::i32_to_pid(pid as i32)));
+            crate::daemon::memory_pressure::release(container_id);
+            if let Some(pid) = pid {
+                if let Err(e) = ProcessUtils::send_signal(pid, Signal::SIGKILL) {
+                    ConsoleLogger::debug(&format!("SIGKILL to {} for forced removal of {}: {}", pid, container_id, e));
+                }
+            }
+            let _ = self.sync_engine.stop_monitoring(container_id).await;
+            let _ = self.sync_engine.store_container_log_as(container_id, "warn",
+                &format!("Container force-removed while {}", status.state.to_string()), actor).await;
+        }
+
+        // Visible until the record is deleted below
+        let _ = self.sync_engine.update_container_state(container_id, ContainerState::Removing).await;
+
+        // Use both runtime cleanup and sync engine cleanup for comprehensive removal
+        // Capture the PID before the runtime forgets the container
+        let container_pid = self.runtime.get_container_info(container_id)
+            .and_then(|info| info.pid);
+
+        // First, attempt runtime removal (handles process stopping and resource cleanup)
+        let runtime_result = self.runtime.remove_container(container_id);
+
+        // Capture published ports before the allocation rows disappear with the container
+        let port_bindings = self.sync_engine.get_port_bindings(container_id).await.unwrap_or_default();
+        let allocation = self.sync_engine.get_network_allocation(container_id).await.ok();
+        let container_ip = allocation.as_ref().map(|alloc| alloc.ip_address.clone()).unwrap_or_default();
+        let recorded_interfaces: Vec<String> = allocation.into_iter()
+            .flat_map(|alloc| [alloc.veth_host, alloc.veth_container])
+            .flatten()
+            .collect();
+        let network_plugin = self.sync_engine.get_network_plugin(container_id).await.ok().flatten();
+        let plugin_volumes = self.plugin_volume_mounts(container_id).await;
+        networks::release_all(&self.sync_engine, &self.network_manager, container_id).await;
+        mirrors::release_all(&self.sync_engine, &self.network_manager, container_id).await;
+
+        // Then, remove from sync engine (handles database cleanup)
+        if let Err(e) = self.sync_engine.delete_container(container_id).await {
+            ConsoleLogger::error(&format!("Failed to remove container {}: {}", container_id, e));
+            let _ = self.sync_engine.update_container_state(container_id, ContainerState::Error).await;
+            return Err(e.to_string());
+        }
+
+        // Withdraw published ports and release the host port allocations
+        if !port_bindings.is_empty() {
+            let mappings: Vec<_> = port_bindings.iter().map(|binding| binding.to_mapping()).collect();
+            self.network_manager.unpublish_container_ports(container_id, &container_ip, &mappings);
+            if let Err(e) = self.sync_engine.remove_port_bindings(container_id).await {
+                ConsoleLogger::warning(&format!("Failed to release port bindings for {}: {}", container_id, e));
+            }
+        }
+
+        // Remove container mounts
+        if let Err(e) = self.sync_engine.remove_container_mounts(container_id).await {
+            ConsoleLogger::warning(&format!("Failed to remove mounts for {}: {}", container_id, e));
+        }
+
+        // Let plugins release what they set up for the container
+        if let Some(plugin) = &network_plugin {
+            if let Err(e) = plugins::detach_network(plugin, container_id).await {
+                ConsoleLogger::warning(&format!("🔌 [PLUGINS] {}", e));
+            }
+        }
+        for (driver, volume) in &plugin_volumes {
+            if let Err(e) = plugins::unmount_volume(driver, volume, container_id).await {
+                ConsoleLogger::warning(&format!("🔌 [PLUGINS] {}", e));
+            }
+        }
+
+        // Cleanup container logs (keep last 10 for debugging)
+        if let Ok(cleaned_count) = self.sync_engine.cleanup_container_logs(container_id, 10).await {
+            ConsoleLogger::debug(&format!("Cleaned up {} log entries for {}", cleaned_count, container_id));
+        }
+
+        // Unregister from DNS
+        let _ = self.network_manager.unregister_container_dns(container_id);
+
+        // Destroy the warm namespace (and its veth pair) if the container held one
+        self.network_manager.release_pooled_netns(container_id);
+
+        // Veth pair, leftover tagged iptables rules and the bridge's neighbor entry
+        if let Err(e) = self.network_manager.teardown_container_network(container_id,
+            Some(container_ip.as_str()).filter(|ip| !ip.is_empty()), &recorded_interfaces) {
+            ConsoleLogger::warning(&e);
+        }
+
+        // Enhanced resource cleanup with correlation
+        let resource_manager = ResourceManager::new();
+        if let Err(e) = resource_manager.cleanup_container_with_correlation(container_id, container_pid) {
+            ConsoleLogger::warning(&format!("Resource correlation cleanup issues for {}: {}", container_id, e));
+        } else {
+            ConsoleLogger::debug(&format!("✅ Resource correlation cleanup completed for {}", container_id));
+        }
+
+        // Log runtime result for debugging
+        if let Err(e) = runtime_result {
+            ConsoleLogger::warning(&format!("Runtime cleanup issues for {}: {}", container_id, e));
+            // A runtime that no longer knew the container (e.g. after a daemon restart) left its rootfs
+            if let Some(rootfs_path) = status.rootfs_path.as_deref().filter(|path| FileSystemUtils::exists(path)) {
+                if let Err(e) = crate::daemon::overlay::remove_rootfs(rootfs_path) {
+                    ConsoleLogger::warning(&format!("Failed to remove overlay rootfs of {}: {}", container_id, e));
+                }
+                if let Err(e) = FileSystemUtils::remove_path(rootfs_path) {
+                    ConsoleLogger::warning(&format!("Failed to remove rootfs of {}: {}", container_id, e));
+                }
+            }
+        }
+
+        ConsoleLogger::success(&format!("Container {} removed with comprehensive cleanup", container_id));
+
+        // Store removal log
+        let _ = self.sync_engine.store_container_log_as(container_id, "info", "Container removed successfully", actor).await;
+
+        // The allocation is gone by now; hooks deregistering the container still need its address
+        let attributes = (!container_ip.is_empty())
+            .then(|| HashMap::from([("ip_address".to_string(), container_ip.clone())]));
+        global_event_buffer().emit_as(EventType::Removed, container_id, actor, attributes);
+        global_event_buffer().forget_container_labels(container_id);
+        ConsoleLogger::set_container_log_level(container_id, ContainerLogLevel::Info);
+        Ok(())
+    }
+
+    /// Store a container's lifecycle hooks (an empty list clears them); they fire from the next event on
+    pub async fn set_container_hooks(&self, container_id: &str, hooks: Vec<LifecycleHook>) -> Result<(), String> {
+        self.sync_engine.set_container_hooks(container_id, &hooks).await
+            .map_err(|e| format!("Failed to store lifecycle hooks: {}", e))?;
+        let name = self.sync_engine.get_container_status(container_id).await
+            .ok()
+            .and_then(|status| status.name)
+            .unwrap_or_default();
+        crate::daemon::hooks::register(container_id, &name, hooks);
+        Ok(())
+    }
+
+    /// Store the hooks run between a container's root setup and its command on every start
+    /// (an empty list clears them); they apply from the next start
+    pub async fn set_prestart_hooks(&self, container_id: &str, hooks: Vec<PrestartHook>) -> Result<(), String> {
+        self.sync_engine.set_prestart_hooks(container_id, &hooks).await
+            .map_err(|e| format!("Failed to store pre-start hooks: {}", e))
+    }
+
+    /// Store the setup commands (`runtime: package ...` lines) run inside a container before its
+    /// command on every start; an empty list clears them
+    pub async fn set_setup_commands(&self, container_id: &str, commands: Vec<String>) -> Result<(), String> {
+        self.sync_engine.set_setup_commands(container_id, &commands).await
+            .map_err(|e| format!("Failed to store setup commands: {}", e))
+    }
+
+    /// Store the gates a container must pass before it is reported Running (`None` clears
+    /// them); they apply from the next start
+    pub async fn set_readiness_gates(&self, container_id: &str, readiness: Option<&ReadinessGates>) -> Result<(), String> {
+        self.sync_engine.set_readiness_gates(container_id, readiness).await
+            .map_err(|e| format!("Failed to store readiness gates: {}", e))
+    }
+
+    /// Send a container's web traffic through a proxy container from its next start; `None` stops it
+    pub async fn set_egress_proxy(&self, container_id: &str, proxy: Option<&EgressProxy>) -> Result<(), String> {
+        self.sync_engine.set_egress_proxy(container_id, proxy).await
+            .map_err(|e| format!("Failed to store egress proxy: {}", e))
+    }
+
+    /// Shift a container's clocks by `offset` seconds from its next start; `None` restores host time
+    pub async fn set_time_offset(&self, container_id: &str, offset: Option<i64>) -> Result<(), String> {
+        self.sync_engine.set_time_offset(container_id, offset).await
+            .map_err(|e| format!("Failed to store time offset: {}", e))
+    }
+
+    /// Turn background metrics collection of a container on or off, optionally with a new
+    /// interval; takes effect on the collector's next pass
+    pub async fn set_metrics_collection(&self, container_id: &str, enabled: bool, interval_seconds: Option<u32>) -> Result<(), String> {
+        self.sync_engine.set_metrics_collection(container_id, enabled, interval_seconds).await
+            .map_err(|e| format!("Failed to store metrics collection settings: {}", e))
+    }
+
+    /// Set the class deciding the container's place in start queues and who is evicted first;
+    /// applies from its next start
+    pub async fn set_priority_class(&self, container_id: &str, class: PriorityClass) -> Result<(), String> {
+        self.sync_engine.set_priority_class(container_id, class).await
+            .map_err(|e| format!("Failed to store priority class: {}", e))
+    }
+
+    /// Stop every running container of class `up_to` or lower, best-effort first, e.g. before
+    /// host maintenance. Returns each container and its class with the outcome of stopping it.
+    pub async fn drain_containers(&self, up_to: PriorityClass, actor: &str) -> Result<Vec<priority::DrainOutcome>, String> {
+        priority::drain(&self.sync_engine, &self.runtime, up_to, actor).await
+    }
+
+    /// Name the network driver plugin that plumbs the container after quilt's bridge setup on
+    /// every start (`None` clears it); the plugin must be registered
+    pub async fn set_network_plugin(&self, container_id: &str, plugin: Option<&str>) -> Result<(), String> {
+        if let Some(plugin) = plugin {
+            if !plugins::is_active(plugin) {
+                return Err(format!("Network plugin '{}' is not registered", plugin));
+            }
+        }
+        self.sync_engine.set_network_plugin(container_id, plugin).await
+            .map_err(|e| format!("Failed to store network plugin: {}", e))
+    }
+
+    /// Create a volume with the built-in `local` driver, or on the volume driver plugin named by
+    /// `driver`
+    pub async fn create_volume(
+        &self,
+        name: &str,
+        driver: Option<&str>,
+        labels: HashMap<String, String>,
+        options: HashMap<String, String>,
+    ) -> Result<Volume, String> {
+        let driver = driver.filter(|driver| !driver.is_empty() && *driver != "local");
+        let Some(driver) = driver else {
+            return self.sync_engine.create_volume(name, None, labels, options).await.map_err(|e| e.to_string());
+        };
+        if !plugins::is_volume_driver(driver) {
+            return Err(format!("Unknown volume driver '{}': register a volume driver plugin with that name first", driver));
+        }
+        // Checked here too so a name clash does not leave storage behind on the plugin
+        if self.sync_engine.get_volume(name).await.map_err(|e| e.to_string())?.is_some() {
+            return Err(format!("Volume '{}' already exists", name));
+        }
+        let mount_point = plugins::create_volume(driver, name, options.clone()).await?;
+        match self.sync_engine.create_driver_volume(name, driver, mount_point, labels, options).await {
+            Ok(volume) => Ok(volume),
+            Err(e) => {
+                let _ = plugins::remove_volume(driver, name).await;
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Remove a volume; a plugin driver is asked to release its storage afterwards
+    pub async fn remove_volume(&self, name: &str, force: bool) -> Result<(), String> {
+        let driver = self.sync_engine.get_volume(name).await
+            .map_err(|e| e.to_string())?
+            .map(|volume| volume.driver)
+            .filter(|driver| driver != "local");
+        self.sync_engine.remove_volume(name, force).await.map_err(|e| e.to_string())?;
+        if let Some(driver) = driver {
+            if let Err(e) = plugins::remove_volume(&driver, name).await {
+                ConsoleLogger::warning(&format!("🔌 [PLUGINS] Volume {} removed but its storage may remain: {}", name, e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Register a plugin after checking it answers the activation handshake
+    pub async fn register_plugin(&self, name: &str, endpoint: &str) -> Result<PluginRecord, String> {
+        plugins::register(&self.sync_engine, name, endpoint).await
+    }
+
+    /// Returns whether the plugin was registered. Volumes and containers using it stay as they are.
+    pub async fn unregister_plugin(&self, name: &str) -> Result<bool, String> {
+        let removed = self.sync_engine.unregister_plugin(name).await.map_err(|e| e.to_string())?;
+        plugins::forget(name);
+        Ok(removed)
+    }
+
+    pub async fn list_plugins(&self) -> Result<Vec<PluginRecord>, String> {
+        self.sync_engine.list_plugins().await.map_err(|e| e.to_string())
+    }
+
+    /// Create a virtual service and answer for it right away. Names are case-insensitive and may
+    /// not shadow a container name.
+    pub async fn create_virtual_service(&self, name: &str, selector: &HashMap<String, String>)
+        -> Result<(VirtualServiceRecord, Vec<ServiceBackend>), String> {
+        InputValidator::validate_virtual_service_name(name)?;
+        let name = name.to_ascii_lowercase();
+        if let Ok(container_id) = self.sync_engine.get_container_by_name(&name).await {
+            return Err(format!("DNS name '{}' is already in use by container {}", name, container_id));
+        }
+
+        let service = self.sync_engine.create_virtual_service(&name, selector).await.map_err(|e| e.to_string())?;
+        if let Err(e) = virtual_services::refresh_virtual_services(&self.sync_engine, &self.network_manager).await {
+            ConsoleLogger::warning(&format!("⚖️ [VSERVICE] {} created but not yet served: {}", name, e));
+        }
+        let backends = virtual_services::backends(&self.sync_engine, &service.selector).await.map_err(|e| e.to_string())?;
+        Ok((service, backends))
+    }
+
+    pub async fn delete_virtual_service(&self, name: &str) -> Result<(), String> {
+        let name = name.to_ascii_lowercase();
+        if !self.sync_engine.delete_virtual_service(&name).await.map_err(|e| e.to_string())? {
+            return Err(format!("Virtual service '{}' not found", name));
+        }
+        self.network_manager.remove_virtual_service(&name)
+    }
+
+    /// Every virtual service with the running containers currently backing it
+    pub async fn list_virtual_services(&self) -> Result<Vec<(VirtualServiceRecord, Vec<ServiceBackend>)>, String> {
+        let services = self.sync_engine.list_virtual_services().await.map_err(|e| e.to_string())?;
+        let mut listed = Vec::with_capacity(services.len());
+        for service in services {
+            let backends = virtual_services::backends(&self.sync_engine, &service.selector).await.map_err(|e| e.to_string())?;
+            listed.push((service, backends));
+        }
+        Ok(listed)
+    }
+
+    /// Answer `name` with `addresses` for queries in `view` (all, containers or host), replacing
+    /// its earlier record there. A record may override a container or virtual service name for
+    /// one view only.
+    pub async fn set_dns_record(&self, name: &str, view: &str, addresses: &[String]) -> Result<DnsRecord, String> {
+        InputValidator::validate_dns_record_name(name)?;
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let view = DnsView::parse(view)?;
+        let ips = addresses.iter()
+            .map(|address| match address.parse::<std::net::IpAddr>() {
+                Ok(ip) if ip.is_ipv4() => Ok(ip),
+                _ => Err(format!("Invalid address '{}' for DNS record '{}': expected an IPv4 address", address, name)),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let record = self.sync_engine.set_dns_record(&name, view.as_str(), addresses).await.map_err(|e| e.to_string())?;
+        if let Err(e) = self.network_manager.set_dns_record(&name, view, ips) {
+            ConsoleLogger::warning(&format!("DNS record {} ({}) stored but not yet served: {}", name, view.as_str(), e));
+        }
+        Ok(record)
+    }
+
+    pub async fn remove_dns_record(&self, name: &str, view: &str) -> Result<(), String> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let view = DnsView::parse(view)?;
+        if !self.sync_engine.delete_dns_record(&name, view.as_str()).await.map_err(|e| e.to_string())? {
+            return Err(format!("DNS record '{}' not found in view {}", name, view.as_str()));
+        }
+        self.network_manager.remove_dns_record(&name, view)
+    }
+
+    pub async fn list_dns_records(&self) -> Result<Vec<DnsRecord>, String> {
+        self.sync_engine.list_dns_records().await.map_err(|e| e.to_string())
+    }
+
+    /// Join a running container to an additional network, creating the network on first use
+    pub async fn connect_container_to_network(&self, container_id: &str, network: &str, actor: &str) -> Result<NetworkAttachment, String> {
+        let _guard = self.sync_engine.lock_container(container_id, "network-connect").await.map_err(|e| e.to_string())?;
+        networks::connect(&self.sync_engine, &self.network_manager, container_id, network, actor).await
+    }
+
+    pub async fn disconnect_container_from_network(&self, container_id: &str, network: &str, actor: &str) -> Result<(), String> {
+        let _guard = self.sync_engine.lock_container(container_id, "network-disconnect").await.map_err(|e| e.to_string())?;
+        networks::disconnect(&self.sync_engine, &self.network_manager, container_id, network, actor).await
+    }
+
+    /// Copy a running container's veth traffic to a running observer container
+    pub async fn mirror_container_traffic(&self, container_id: &str, observer_id: &str, direction: MirrorDirection, actor: &str) -> Result<TrafficMirror, String> {
+        let _guard = self.sync_engine.lock_container(container_id, "traffic-mirror").await.map_err(|e| e.to_string())?;
+        mirrors::start(&self.sync_engine, &self.network_manager, container_id, observer_id, direction, actor).await
+    }
+
+    pub async fn stop_traffic_mirror(&self, container_id: &str, actor: &str) -> Result<TrafficMirror, String> {
+        let _guard = self.sync_engine.lock_container(container_id, "traffic-mirror").await.map_err(|e| e.to_string())?;
+        mirrors::stop(&self.sync_engine, &self.network_manager, container_id, actor).await
+    }
+
+    pub async fn list_traffic_mirrors(&self) -> Result<Vec<TrafficMirror>, String> {
+        self.sync_engine.list_traffic_mirrors().await.map_err(|e| e.to_string())
+    }
+
+    /// (driver, volume) for each of the container's volume mounts backed by a plugin
+    async fn plugin_volume_mounts(&self, container_id: &str) -> Vec<(String, String)> {
+        let mounts = self.sync_engine.get_container_mounts(container_id).await.unwrap_or_default();
+        let mut plugin_volumes = Vec::new();
+        for mount in mounts.into_iter().filter(|mount| mount.mount_type == MountType::Volume) {
+            if let Ok(Some(volume)) = self.sync_engine.get_volume(&mount.source).await {
+                if volume.driver != "local" {
+                    plugin_volumes.push((volume.driver, volume.name));
+                }
+            }
+        }
+        plugin_volumes
+    }
+
+    /// Store a container's network policy (`None` clears it) and enforce it right away when the
+    /// container is running. Returns whether it is enforced; a policy that cannot be enforced yet
+    /// stays stored and is applied on the next start.
+    pub async fn set_network_policy(&self, container_id: &str, policy: Option<NetworkPolicy>, actor: &str) -> Result<bool, String> {
+        if let Some(policy) = &policy {
+            policy.validate()?;
+        }
+        self.sync_engine.set_network_policy(container_id, policy.as_ref()).await
+            .map_err(|e| format!("Failed to store network policy: {}", e))?;
+        let message = if policy.is_some() { "Network policy updated" } else { "Network policy cleared" };
+        let _ = self.sync_engine.store_container_log_as(container_id, "info", message, actor).await;
+
+        match network_policy::apply_network_policy(&self.sync_engine, &self.network_manager.config.bridge_ip, container_id).await {
+            Ok(enforced) => Ok(enforced),
+            Err(e) => {
+                ConsoleLogger::warning(&format!("🛡️ [NET-POLICY] Policy for {} stored but not enforced: {}", container_id, e));
+                let _ = self.sync_engine.store_container_log(container_id, "warn", &format!("Network policy not enforced: {}", e)).await;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Collect a tar.gz diagnostic bundle for a container, or for the host when `container_id` is `None`
+    pub async fn collect_diagnostics(&self, container_id: Option<&str>, log_lines: u32) -> Result<Vec<u8>, String> {
+        diagnostics::collect_diagnostics(&self.sync_engine, container_id, log_lines).await
+    }
+
+    /// Host ports the daemon has opened, with whether each one is live
+    pub async fn list_host_ports(&self) -> Result<Vec<HostPort>, String> {
+        host_ports::list_host_ports(&self.sync_engine, &self.network_manager).await
+    }
+
+    /// Verify kernel features, tools, privileges and subnet layout against this engine's network config
+    pub async fn check_host_requirements(&self) -> Result<Vec<HostCheck>, String> {
+        let subnet = self.network_manager.config.subnet_cidr.clone();
+        let bridge_name = self.network_manager.config.bridge_name.clone();
+        tokio::task::spawn_blocking(move || check_host_requirements(&subnet, &bridge_name)).await
+            .map_err(|e| format!("Host check task failed: {}", e))
+    }
+
+    pub async fn container_status(&self, container_id: &str) -> Result<ContainerStatus, String> {
+        self.sync_engine.get_container_status(container_id).await
+            .map_err(|e| format!("Container {} not found: {}", container_id, e))
+    }
+
+    /// Run a shell command inside a running container and collect its output
+    pub async fn exec(&self, container_id: &str, command: &str, timeout: Duration) -> Result<CommandResult, String> {
+        let status = self.container_status(container_id).await?;
+        if status.state != ContainerState::Running {
+            return Err(format!("Container {} is not running (state: {:?})", container_id, status.state));
+        }
+        let pid = status.pid.ok_or_else(|| format!("Container {} has no PID", container_id))?;
+        let rootfs_path = status.rootfs_path.ok_or_else(|| format!("Container {} has no rootfs", container_id))?;
+
+        let environment = self.exec_environment(container_id, &HashMap::new()).await?;
+        let argv = Self::exec_argv(pid, &rootfs_path, &[command.to_string()], &environment);
+        let session = Self::exec_session_cgroup(container_id);
+        let argv = match &session {
+            Some(session) => argv.with_cgroup(session.procs_file()),
+            None => argv,
+        };
+        CommandExecutor::execute_with_timeout(&argv, timeout).await
+    }
+
+    /// A transient cgroup accounting one exec session in `container_id`. `None` when the
+    /// container's cgroup cannot be split, as on cgroup v1 hosts; the command then runs without
+    /// separate accounting.
+    pub fn exec_session_cgroup(container_id: &str) -> Option<ExecCgroup> {
+        ExecCgroup::create(container_id)
+            .map_err(|e| ConsoleLogger::debug(&format!("Exec in {} is not accounted separately: {}", container_id, e)))
+            .ok()
+    }
+
+    /// Variables an exec'd command gets: the image's ENV overlaid with the container's configured
+    /// environment (with its zone and locale), the libfaketime settings of a container with a
+    /// time offset, overlaid with `requested`. Requested names a shell cannot export are refused; such names in the stored
+    /// environment are dropped when the command is run.
+    pub async fn exec_environment(&self, container_id: &str, requested: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+        for name in requested.keys() {
+            InputValidator::validate_env_name(name)?;
+        }
+        let mut configured = HashMap::new();
+        if let Ok(config) = self.sync_engine.get_container_config(container_id).await {
+            let (default_timezone, default_locale) = crate::daemon::timezone::defaults();
+            let timezone = config.timezone.or(default_timezone);
+            let locale = config.locale.or(default_locale);
+            configured = config.environment;
+            for (name, value) in crate::daemon::timezone::environment(timezone.as_deref(), locale.as_deref()) {
+                configured.entry(name).or_insert(value);
+            }
+        }
+        let rootfs_path = self.sync_engine.get_container_status(container_id).await.ok().and_then(|status| status.rootfs_path);
+        let mut environment = match &rootfs_path {
+            Some(rootfs) => image_config::load(rootfs).merged_with(&configured, None, None).environment,
+            None => configured,
+        };
+        if let Ok(Some(offset)) = self.sync_engine.get_time_offset(container_id).await {
+            // Only when startup managed to install the library, or every command would complain
+            let installed = rootfs_path.map_or(false, |rootfs| {
+                std::path::Path::new(&rootfs).join(time_offset::CONTAINER_LIBFAKETIME.trim_start_matches('/')).exists()
+            });
+            if installed {
+                time_offset::add_faketime_environment(&mut environment, offset);
+            }
+        }
+        environment.extend(requested.iter().map(|(name, value)| (name.clone(), value.clone())));
+        Ok(environment)
+    }
+
+    /// The directory an exec's command should start in: `None` for an empty request, otherwise
+    /// `working_directory` checked (and with `create`, created) inside the container of `pid`
+    /// and resolved as the container sees it
+    pub fn exec_working_directory(pid: i64, working_directory: &str, create: bool) -> Result<Option<String>, String> {
+        if working_directory.is_empty() {
+            return Ok(None);
+        }
+        workdir::prepare_working_directory(pid, working_directory, create).map(Some)
+    }
+
+    /// The nsenter + chroot invocation that runs `command` inside a container with exactly
+    /// `environment` (see `CommandExecutor::exec_form` for how `command` is read). No shell runs
+    /// on the host, so nothing in the command or the values is interpreted before it gets there.
+    pub fn exec_argv(pid: i64, rootfs_path: &str, command: &[String], environment: &HashMap<String, String>) -> Argv {
+        Self::exec_argv_in(pid, rootfs_path, None, command, environment)
+    }
+
+    /// [`Engine::exec_argv`] starting the command in `working_directory`, a path from
+    /// [`Engine::exec_working_directory`]. chroot(1) always starts in `/`, so for a working
+    /// directory nsenter enters the container's root itself and changes into the directory after.
+    pub fn exec_argv_in(pid: i64, rootfs_path: &str, working_directory: Option<&str>, command: &[String], environment: &HashMap<String, String>) -> Argv {
+        // Note: We're not using IPC namespace (-i) by default as it's disabled in NamespaceConfig::default()
+        let mut args = vec![
+            "-t".to_string(), pid.to_string(),
+            "-p".to_string(), "-m".to_string(), "-n".to_string(), "-u".to_string(),
+        ];
+        match working_directory {
+            // Both are opened before nsenter joins the namespaces; /proc/<pid>/root is the
+            // container's root with its mounts
+            Some(working_directory) => args.extend([
+                "--root".to_string(),
+                format!("--wd={}{}", workdir::container_root(pid).display(), working_directory),
+                "--".to_string(),
+            ]),
+            // Found on the daemon's PATH: the command's own PATH may not have it
+            None => args.extend(["--".to_string(), CommandExecutor::program_path("chroot"), rootfs_path.to_string()]),
+        }
+        // Time, cgroup and user namespaces are optional, and --time needs a newer nsenter than
+        // the other flags. Entering the user namespace makes the command its root.
+        if has_own_namespace(pid, "user") {
+            args.insert(2, "--user".to_string());
+        }
+        if has_own_namespace(pid, "time") {
+            args.insert(2, "--time".to_string());
+        }
+        if has_own_namespace(pid, "cgroup") {
+            args.insert(2, "--cgroup".to_string());
+        }
+        args.extend(CommandExecutor::exec_form(command));
+        Argv::new("nsenter", args).with_environment(CommandExecutor::process_environment(environment))
+    }
+
+    /// Stop background services and close the state store
+    pub async fn shutdown(&self) {
+        self.sync_engine.close().await;
+    }
+}
+
+/// Hand every stored DNS record to the DNS server
+async fn restore_dns_records(sync_engine: &SyncEngine, network_manager: &NetworkManager) -> Result<(), String> {
+    for record in sync_engine.list_dns_records().await.map_err(|e| e.to_string())? {
+        let view = DnsView::parse(&record.view)?;
+        let addresses = record.addresses.iter().filter_map(|address| address.parse().ok()).collect();
+        network_manager.set_dns_record(&record.name, view, addresses)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_argv_passes_command_without_host_shell() {
+        let argv = Engine::exec_argv(42, "/tmp/quilt-containers/abc", &["echo \"$HOME\" `id`".to_string()], &HashMap::new());
+        assert_eq!(argv.program, "nsenter");
+        assert_eq!(&argv.args[..7], ["-t", "42", "-p", "-m", "-n", "-u", "--"]);
+        assert!(argv.args[7].ends_with("chroot"));
+        assert_eq!(&argv.args[8..], ["/tmp/quilt-containers/abc", "/bin/sh", "-c", "echo \"$HOME\" `id`"]);
+
+        let environment = HashMap::from([("MODE".to_string(), "it's $prod".to_string())]);
+        let argv = Engine::exec_argv(42, "/tmp/quilt-containers/abc", &["ls".to_string(), "a; rm -rf /".to_string()], &environment);
+        assert_eq!(&argv.args[9..], ["ls", "a; rm -rf /"]);
+        let process_environment = argv.environment.unwrap();
+        assert_eq!(process_environment["MODE"], "it's $prod");
+        assert!(process_environment.contains_key("PATH"));
+
+        let argv = Engine::exec_argv_in(42, "/tmp/quilt-containers/abc", Some("/srv/app"), &["ls".to_string(), "-l".to_string()], &HashMap::new());
+        assert_eq!(&argv.args[6..], ["--root", "--wd=/proc/42/root/srv/app", "--", "ls", "-l"]);
+    }
+}
@@ -0,0 +1,159 @@
+// Background network reconciliation
+// Periodically re-runs the bridge verification that otherwise only happens at startup and
+// container setup, and repairs drift caused outside the daemon: a deleted or downed bridge, a
+// lost bridge address, veths orphaned by a recreated bridge, the bridge's firewalld zone or ufw
+// rules gone, published-port NAT rules wiped by a firewall reload, and egress proxy redirections
+// that were wiped or point at a proxy that moved. Every repair is recorded as a NetworkRepaired
+// event.
+
+use crate::icc::network::NetworkManager;
+use crate::sync::events::{global_event_buffer, EventType};
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time between reconciliation passes
+pub const DEFAULT_NETWORK_RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Run [`reconcile_network`] every `interval` until the runtime shuts down
+pub fn spawn_network_reconciler(sync_engine: Arc<SyncEngine>, network_manager: Arc<NetworkManager>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // Startup has just verified everything; skip the immediate first tick
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let repairs = reconcile_network(&sync_engine, &network_manager).await;
+            if repairs > 0 {
+                ConsoleLogger::success(&format!("🔧 [NET-RECONCILE] Repaired {} network issue(s)", repairs));
+            }
+        }
+    });
+}
+
+/// One reconciliation pass; returns the number of repairs made
+pub async fn reconcile_network(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>) -> usize {
+    let mut repairs = 0;
+    let bridge_name = network_manager.config.bridge_name.clone();
+
+    let manager = network_manager.clone();
+    let bridge_repairs = match tokio::task::spawn_blocking(move || manager.reconcile_bridge()).await {
+        Ok(Ok(repaired)) => repaired,
+        Ok(Err(e)) => {
+            ConsoleLogger::error(&format!("🔧 [NET-RECONCILE] Bridge {} repair failed, retrying next pass: {}", bridge_name, e));
+            return 0;
+        }
+        Err(e) => {
+            ConsoleLogger::error(&format!("🔧 [NET-RECONCILE] Bridge check task failed: {}", e));
+            return 0;
+        }
+    };
+    if !bridge_repairs.is_empty() {
+        repairs += 1;
+        emit_repaired("", &bridge_name, &bridge_repairs.join(","), None);
+    }
+    let bridge_recreated = bridge_repairs.contains(&"bridge");
+
+    if let Some(firewall) = network_manager.host_firewall {
+        let manager = network_manager.clone();
+        match tokio::task::spawn_blocking(move || manager.reconcile_host_firewall()).await {
+            Ok(Ok(true)) => {
+                repairs += 1;
+                emit_repaired("", &bridge_name, "host_firewall", Some(("firewall", firewall.as_str().to_string())));
+            }
+            Ok(Ok(false)) => {}
+            Ok(Err(e)) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Could not restore {} rules for {}: {}", firewall.as_str(), bridge_name, e)),
+            Err(e) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Host firewall task failed: {}", e)),
+        }
+    }
+
+    let running = match sync_engine.list_containers(Some(ContainerState::Running)).await {
+        Ok(containers) => containers,
+        Err(e) => {
+            ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Failed to list running containers: {}", e));
+            return repairs;
+        }
+    };
+
+    for container in running {
+        let Some(container_ip) = container.ip_address.clone() else { continue };
+
+        // A recreated bridge has no ports; put every running container's veth back on it
+        if bridge_recreated {
+            let veth = sync_engine.get_network_allocation(&container.id).await.ok().and_then(|allocation| allocation.veth_host);
+            if let Some(veth) = veth {
+                let manager = network_manager.clone();
+                let veth_name = veth.clone();
+                match tokio::task::spawn_blocking(move || manager.reattach_veth(&veth_name)).await {
+                    Ok(Ok(())) => {
+                        repairs += 1;
+                        emit_repaired(&container.id, &bridge_name, "veth", Some(("veth", veth)));
+                    }
+                    Ok(Err(e)) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Could not reattach {} of {}: {}", veth, container.id, e)),
+                    Err(e) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Reattach task failed: {}", e)),
+                }
+            }
+        }
+
+        match super::egress_proxy::reconcile(sync_engine, network_manager, &container.id, &container_ip).await {
+            Ok(Some(proxy_ip)) => {
+                repairs += 1;
+                emit_repaired(&container.id, &bridge_name, "egress_proxy", Some(("proxy", proxy_ip)));
+            }
+            Ok(None) => {}
+            Err(e) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Could not restore the egress redirect of {}: {}", container.id, e)),
+        }
+
+        let mappings: Vec<_> = match sync_engine.get_port_bindings(&container.id).await {
+            Ok(bindings) => bindings.iter().map(|binding| binding.to_mapping()).collect(),
+            Err(_) => continue,
+        };
+        if mappings.is_empty() {
+            continue;
+        }
+
+        let manager = network_manager.clone();
+        let (container_id, ip) = (container.id.clone(), container_ip.clone());
+        let missing = tokio::task::spawn_blocking(move || manager.missing_port_mappings(&container_id, &ip, &mappings))
+            .await
+            .unwrap_or_default();
+        if missing.is_empty() {
+            continue;
+        }
+
+        // Drop whatever half of the rule set survived, then install the mapping again
+        network_manager.unpublish_container_ports(&container.id, &container_ip, &missing);
+        match network_manager.publish_container_ports(&container.id, &container_ip, &missing) {
+            Ok(()) => {
+                repairs += 1;
+                let ports = missing.iter()
+                    .map(|mapping| format!("{}/{}", mapping.host_port, mapping.protocol))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                emit_repaired(&container.id, &bridge_name, "port_rules", Some(("ports", ports)));
+            }
+            Err(e) => ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Could not restore published ports of {}: {}", container.id, e)),
+        }
+    }
+
+    repairs
+}
+
+/// Record a NetworkRepaired event; bridge-wide repairs carry an empty container ID
+fn emit_repaired(container_id: &str, bridge_name: &str, repaired: &str, extra: Option<(&str, String)>) {
+    let mut attributes = HashMap::from([
+        ("bridge".to_string(), bridge_name.to_string()),
+        ("repaired".to_string(), repaired.to_string()),
+    ]);
+    if let Some((key, value)) = extra {
+        attributes.insert(key.to_string(), value);
+    }
+    ConsoleLogger::warning(&format!("🔧 [NET-RECONCILE] Repaired {} on {}{}", repaired, bridge_name,
+        if container_id.is_empty() { String::new() } else { format!(" for container {}", container_id) }));
+    global_event_buffer().emit(EventType::NetworkRepaired, container_id, Some(attributes));
+}
@@ -0,0 +1,87 @@
+// Network policy resolution
+// Turns a container's stored policy into IPv4 prefixes (peers named by container name or ID are
+// looked up in the network allocations) and hands them to the eBPF enforcement in
+// `daemon::net_policy`. Peer addresses change when containers restart, so every start re-resolves
+// the policies of all running containers.
+
+use crate::daemon::net_policy::{self, NetworkPolicy, PolicyAction, PolicyDirection, PolicyEntry};
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+
+/// Enforce the container's stored policy, or lift enforcement when it has none. Returns whether
+/// a policy is now enforced. A container that is not running is left alone.
+pub async fn apply_network_policy(sync_engine: &SyncEngine, gateway_ip: &str, container_id: &str) -> Result<bool, String> {
+    let policy = sync_engine.get_network_policy(container_id).await
+        .map_err(|e| format!("Failed to load network policy: {}", e))?;
+    let Some(policy) = policy else {
+        net_policy::remove(container_id);
+        return Ok(false);
+    };
+
+    let state = sync_engine.get_container_status(container_id).await
+        .map_err(|e| format!("Container {} not found: {}", container_id, e))?
+        .state;
+    if !matches!(state, ContainerState::Running | ContainerState::Starting) {
+        net_policy::remove(container_id);
+        return Ok(false);
+    }
+
+    let entries = resolve_policy(sync_engine, gateway_ip, &policy).await;
+    net_policy::enforce(container_id, &policy, &entries)?;
+    Ok(true)
+}
+
+/// Called once a container's network is up: drop enforcement left over from its previous run
+/// (the cgroup was recreated), apply its own policy, and re-resolve everyone else's, since their
+/// rules may name this container.
+pub async fn reapply_after_start(sync_engine: &SyncEngine, gateway_ip: &str, container_id: &str) {
+    net_policy::remove(container_id);
+
+    let policies = match sync_engine.list_network_policies().await {
+        Ok(policies) => policies,
+        Err(e) => {
+            ConsoleLogger::warning(&format!("🛡️ [NET-POLICY] Failed to list network policies: {}", e));
+            return;
+        }
+    };
+    for policy_owner in policies.keys() {
+        if let Err(e) = apply_network_policy(sync_engine, gateway_ip, policy_owner).await {
+            ConsoleLogger::warning(&format!("🛡️ [NET-POLICY] Could not enforce network policy of {}: {}", policy_owner, e));
+            let _ = sync_engine.store_container_log(policy_owner, "warn",
+                &format!("Network policy not enforced: {}", e)).await;
+        }
+    }
+}
+
+/// Resolve rule peers to prefixes. Peers that are neither addresses nor containers with a network
+/// allocation are skipped (a deny rule for a stopped container has nothing to match yet). The
+/// gateway always stays reachable so DNS keeps working.
+async fn resolve_policy(sync_engine: &SyncEngine, gateway_ip: &str, policy: &NetworkPolicy) -> Vec<PolicyEntry> {
+    let mut entries = Vec::new();
+    for rule in &policy.rules {
+        let prefix = match net_policy::parse_prefix(&rule.peer) {
+            Some(prefix) => Some(prefix),
+            None => resolve_container_ip(sync_engine, &rule.peer).await
+                .and_then(|ip| net_policy::parse_prefix(&ip)),
+        };
+        match prefix {
+            Some((network, prefix_len)) => entries.push(PolicyEntry { direction: rule.direction, network, prefix_len, action: rule.action }),
+            None => ConsoleLogger::debug(&format!("🛡️ [NET-POLICY] Peer '{}' has no address yet; {} rule skipped", rule.peer, rule.direction.as_str())),
+        }
+    }
+
+    if let Some((network, prefix_len)) = net_policy::parse_prefix(gateway_ip) {
+        for direction in [PolicyDirection::Ingress, PolicyDirection::Egress] {
+            entries.push(PolicyEntry { direction, network, prefix_len, action: PolicyAction::Allow });
+        }
+    }
+    entries
+}
+
+async fn resolve_container_ip(sync_engine: &SyncEngine, peer: &str) -> Option<String> {
+    let container_id = match sync_engine.get_container_by_name(peer).await {
+        Ok(id) => id,
+        Err(_) => peer.to_string(),
+    };
+    sync_engine.get_network_allocation(&container_id).await.ok().map(|allocation| allocation.ip_address)
+}
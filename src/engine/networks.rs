@@ -0,0 +1,151 @@
+// Runtime network connect and disconnect
+// Joins a running container to additional networks (see `icc::network::attachments`) and takes it
+// off them again. The sync engine allocates the address; the container's name on the network,
+// `<name>.<network>`, is served as a DNS record for all views. A container leaves all of its
+// additional networks when it stops, since the veths went with its namespace.
+
+use crate::icc::dns::DnsView;
+use crate::icc::network::attachments::{network_interface_name, remove_veth};
+use crate::icc::network::{NetworkAttachmentPlan, NetworkManager};
+use crate::sync::events::{global_event_buffer, EventType};
+use crate::sync::network_attachments::NetworkAttachment;
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::InputValidator;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// DNS name of a container on an additional network
+fn attachment_dns_name(container_name: &str, network: &str) -> String {
+    format!("{}.{}", container_name, network)
+}
+
+async fn container_name(sync_engine: &SyncEngine, container_id: &str) -> Option<String> {
+    sync_engine.get_container_status(container_id).await.ok().and_then(|status| status.name)
+}
+
+fn veth_present(attachment: &NetworkAttachment) -> bool {
+    Path::new(&format!("/sys/class/net/{}", attachment.veth_host)).exists()
+}
+
+fn attachment_event_attributes(attachment: &NetworkAttachment) -> Option<HashMap<String, String>> {
+    Some(HashMap::from([
+        ("network".to_string(), attachment.network.clone()),
+        ("ip_address".to_string(), attachment.ip_address.clone()),
+    ]))
+}
+
+/// Plumb the running container into `network`, allocating it an address there
+pub async fn connect(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, container_id: &str, network: &str, actor: &str)
+    -> Result<NetworkAttachment, String> {
+    InputValidator::validate_network_name(network)?;
+    let status = sync_engine.get_container_status(container_id).await.map_err(|e| e.to_string())?;
+    let pid = match (&status.state, status.pid) {
+        (ContainerState::Running, Some(pid)) => pid as i32,
+        _ => return Err(format!("Container {} is not running (state: {})", container_id, status.state.to_string())),
+    };
+
+    // A container that died on its own never went through stop and still holds the address
+    if let Some(stale) = sync_engine.get_network_attachments(container_id).await.map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|attachment| attachment.network == network && !veth_present(attachment)) {
+        release(sync_engine, network_manager, &stale, status.name.as_deref()).await;
+    }
+
+    let attachment = sync_engine.attach_network(container_id, network).await.map_err(|e| e.to_string())?;
+    let plan = NetworkAttachmentPlan {
+        container_id: container_id.to_string(),
+        network: network.to_string(),
+        subnet_index: attachment.subnet_index,
+        ip_address: attachment.ip_address.clone(),
+    };
+    let manager = Arc::clone(network_manager);
+    let connected = tokio::task::spawn_blocking(move || manager.connect_container_to_network(&plan, pid))
+        .await
+        .map_err(|e| format!("Network connect task failed: {}", e))
+        .and_then(|result| result);
+    if let Err(e) = connected {
+        let _ = sync_engine.detach_network(container_id, network).await;
+        return Err(e);
+    }
+
+    if let Some(name) = &status.name {
+        let address = attachment.ip_address.parse::<IpAddr>().map_err(|e| format!("Invalid address {}: {}", attachment.ip_address, e))?;
+        if let Err(e) = network_manager.set_dns_record(&attachment_dns_name(name, network), DnsView::All, vec![address]) {
+            ConsoleLogger::warning(&format!("Container {} joined {} but is not yet resolvable there: {}", container_id, network, e));
+        }
+    }
+    let _ = sync_engine.store_container_log_as(container_id, "info",
+        &format!("Connected to network {} at {}", network, attachment.ip_address), actor).await;
+    global_event_buffer().emit_as(EventType::NetworkConnect, container_id, actor, attachment_event_attributes(&attachment));
+    Ok(attachment)
+}
+
+/// Take the container off `network` and release its address there
+pub async fn disconnect(sync_engine: &SyncEngine, network_manager: &Arc<NetworkManager>, container_id: &str, network: &str, actor: &str)
+    -> Result<(), String> {
+    let attachment = sync_engine.get_network_attachments(container_id).await.map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|attachment| attachment.network == network)
+        .ok_or_else(|| format!("Container {} is not connected to network '{}'", container_id, network))?;
+
+    let manager = Arc::clone(network_manager);
+    let (id, name, veth_host) = (container_id.to_string(), network.to_string(), attachment.veth_host.clone());
+    tokio::task::spawn_blocking(move || manager.disconnect_container_from_network(&id, &name, &veth_host))
+        .await
+        .map_err(|e| format!("Network disconnect task failed: {}", e))??;
+    release(sync_engine, network_manager, &attachment, container_name(sync_engine, container_id).await.as_deref()).await;
+
+    let _ = sync_engine.store_container_log_as(container_id, "info", &format!("Disconnected from network {}", network), actor).await;
+    global_event_buffer().emit_as(EventType::NetworkDisconnect, container_id, actor, attachment_event_attributes(&attachment));
+    Ok(())
+}
+
+/// Forget an attachment whose veth is gone: its address and its DNS name
+async fn release(sync_engine: &SyncEngine, network_manager: &NetworkManager, attachment: &NetworkAttachment, container_name: Option<&str>) {
+    if let Err(e) = sync_engine.detach_network(&attachment.container_id, &attachment.network).await {
+        ConsoleLogger::warning(&format!("Failed to release {} on network {}: {}", attachment.ip_address, attachment.network, e));
+    }
+    if let Some(name) = container_name {
+        let _ = network_manager.remove_dns_record(&attachment_dns_name(name, &attachment.network), DnsView::All);
+    }
+}
+
+/// Leave every additional network; for containers that stopped or are being removed
+pub async fn release_all(sync_engine: &SyncEngine, network_manager: &NetworkManager, container_id: &str) {
+    let attachments = sync_engine.get_network_attachments(container_id).await.unwrap_or_default();
+    if attachments.is_empty() {
+        return;
+    }
+    let name = container_name(sync_engine, container_id).await;
+    for attachment in &attachments {
+        if let Err(e) = remove_veth(&attachment.veth_host) {
+            ConsoleLogger::warning(&e);
+        }
+        release(sync_engine, network_manager, attachment, name.as_deref()).await;
+    }
+    ConsoleLogger::debug(&format!("Container {} left {} additional network(s)", container_id, attachments.len()));
+}
+
+/// After a daemon restart: serve the DNS names of attachments that are still plumbed and drop
+/// the ones whose container is gone
+pub async fn restore_attachments(sync_engine: &SyncEngine, network_manager: &NetworkManager) -> Result<(), String> {
+    for attachment in sync_engine.list_network_attachments().await.map_err(|e| e.to_string())? {
+        let name = container_name(sync_engine, &attachment.container_id).await;
+        if !veth_present(&attachment) {
+            release(sync_engine, network_manager, &attachment, name.as_deref()).await;
+            continue;
+        }
+        if let (Some(name), Ok(address)) = (name, attachment.ip_address.parse::<IpAddr>()) {
+            network_manager.set_dns_record(&attachment_dns_name(&name, &attachment.network), DnsView::All, vec![address])?;
+        }
+    }
+    Ok(())
+}
+
+/// The bridge, and interface in the container, of an attachment
+pub fn attachment_interface(attachment: &NetworkAttachment) -> String {
+    network_interface_name(&attachment.network)
+}
@@ -0,0 +1,408 @@
+// Plugins
+// Out-of-process gRPC servers (proto/plugin.proto) that extend the daemon without forking it:
+// volume drivers back volumes created with their name as driver, network drivers plumb
+// containers that name them after quilt's own bridge setup, and auth providers decide every API
+// call. Plugins are registered through the API or discovered as `<name>.sock` (a unix socket) or
+// `<name>.spec` (a file holding the endpoint URL) in QUILT_PLUGIN_DIR; either way the daemon
+// activates them to learn what they implement. Registrations are stored and re-activated on start.
+
+use crate::sync::plugins::PluginRecord;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint, Uri};
+
+pub mod proto {
+    tonic::include_proto!("quilt.plugin");
+}
+
+use proto::auth_provider_client::AuthProviderClient;
+use proto::network_driver_client::NetworkDriverClient;
+use proto::plugin_client::PluginClient;
+use proto::volume_driver_client::VolumeDriverClient;
+
+/// Directory scanned for plugin sockets and spec files when QUILT_PLUGIN_DIR is unset
+pub const DEFAULT_PLUGIN_DIR: &str = "/run/quilt/plugins";
+/// How often the plugin directory is rescanned
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(30);
+/// Upper bound on any single call into a plugin
+const PLUGIN_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Activated plugins keyed by name
+static PLUGINS: once_cell::sync::Lazy<RwLock<HashMap<String, ActivePlugin>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginCapability {
+    VolumeDriver,
+    NetworkDriver,
+    AuthProvider,
+}
+
+impl PluginCapability {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "volume_driver" => Some(PluginCapability::VolumeDriver),
+            "network_driver" => Some(PluginCapability::NetworkDriver),
+            "auth_provider" => Some(PluginCapability::AuthProvider),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginCapability::VolumeDriver => "volume_driver",
+            PluginCapability::NetworkDriver => "network_driver",
+            PluginCapability::AuthProvider => "auth_provider",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ActivePlugin {
+    capabilities: Vec<PluginCapability>,
+    channel: Channel,
+}
+
+/// Outcome of asking the auth providers about an API call
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthDecision {
+    /// No provider objected; `actor` is the caller name the last provider reported, if any
+    Allowed { actor: Option<String> },
+    Denied { plugin: String, reason: String },
+}
+
+/// Plugin names become volume drivers and appear in logs; keep them to a DNS-label-like shape
+pub fn validate_plugin_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty() && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric());
+    if !valid {
+        return Err(format!("Invalid plugin name '{}': use letters, digits, '-', '_' and '.'", name));
+    }
+    if name == "local" {
+        return Err("Plugin name 'local' is reserved for the built-in volume driver".to_string());
+    }
+    Ok(())
+}
+
+/// Check an endpoint is `unix:///absolute/path`, `http://` or `https://`
+pub fn validate_endpoint(endpoint: &str) -> Result<(), String> {
+    if let Some(path) = endpoint.strip_prefix("unix://") {
+        if !path.starts_with('/') {
+            return Err(format!("Plugin socket path in '{}' must be absolute", endpoint));
+        }
+        return Ok(());
+    }
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        return endpoint.parse::<Uri>()
+            .map(|_| ())
+            .map_err(|e| format!("Invalid plugin endpoint '{}': {}", endpoint, e));
+    }
+    Err(format!("Plugin endpoint '{}' must be unix://, http:// or https://", endpoint))
+}
+
+/// Lazily connecting channel to a plugin; calls time out after [`PLUGIN_CALL_TIMEOUT`]
+fn connect(endpoint: &str) -> Result<Channel, String> {
+    validate_endpoint(endpoint)?;
+    if let Some(path) = endpoint.strip_prefix("unix://") {
+        let path = path.to_string();
+        // The URI is required by the transport but never dialed
+        return Ok(Endpoint::from_static("http://quilt-plugin")
+            .timeout(PLUGIN_CALL_TIMEOUT)
+            .connect_with_connector_lazy(tower::service_fn(move |_: Uri| {
+                tokio::net::UnixStream::connect(path.clone())
+            })));
+    }
+    Endpoint::from_shared(endpoint.to_string())
+        .map(|endpoint| endpoint.timeout(PLUGIN_CALL_TIMEOUT).connect_timeout(PLUGIN_CALL_TIMEOUT).connect_lazy())
+        .map_err(|e| format!("Invalid plugin endpoint '{}': {}", endpoint, e))
+}
+
+/// Capabilities named in an activation response; names this daemon does not know are ignored
+pub fn parse_capabilities(implements: &[String]) -> Result<Vec<PluginCapability>, String> {
+    let mut capabilities = Vec::new();
+    for capability in implements.iter().filter_map(|name| PluginCapability::from_str(name.trim())) {
+        if !capabilities.contains(&capability) {
+            capabilities.push(capability);
+        }
+    }
+    if capabilities.is_empty() {
+        return Err(format!("Plugin implements nothing this daemon supports ({})",
+            if implements.is_empty() { "empty list".to_string() } else { implements.join(", ") }));
+    }
+    Ok(capabilities)
+}
+
+/// Handshake with a plugin and make it available under `name`; returns what it implements
+pub async fn activate(name: &str, endpoint: &str) -> Result<Vec<PluginCapability>, String> {
+    validate_plugin_name(name)?;
+    let channel = connect(endpoint)?;
+    let response = PluginClient::new(channel.clone())
+        .activate(proto::ActivateRequest { daemon_version: env!("CARGO_PKG_VERSION").to_string() })
+        .await
+        .map_err(|e| format!("Plugin '{}' at {} did not activate: {}", name, endpoint, e.message()))?
+        .into_inner();
+    let capabilities = parse_capabilities(&response.implements)
+        .map_err(|e| format!("Plugin '{}': {}", name, e))?;
+
+    if let Ok(mut plugins) = PLUGINS.write() {
+        plugins.insert(name.to_string(), ActivePlugin { capabilities: capabilities.clone(), channel });
+    }
+    ConsoleLogger::success(&format!("🔌 [PLUGINS] Activated {} at {} ({})", name, endpoint,
+        capabilities.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ")));
+    Ok(capabilities)
+}
+
+pub fn forget(name: &str) {
+    if let Ok(mut plugins) = PLUGINS.write() {
+        plugins.remove(name);
+    }
+}
+
+pub fn is_active(name: &str) -> bool {
+    PLUGINS.read().map_or(false, |plugins| plugins.contains_key(name))
+}
+
+fn channel_for(name: &str, capability: PluginCapability) -> Result<Channel, String> {
+    let plugins = PLUGINS.read().map_err(|_| "Plugin registry is unavailable".to_string())?;
+    match plugins.get(name) {
+        Some(plugin) if plugin.capabilities.contains(&capability) => Ok(plugin.channel.clone()),
+        Some(_) => Err(format!("Plugin '{}' is not a {}", name, capability.as_str())),
+        None => Err(format!("Plugin '{}' is not registered or not active", name)),
+    }
+}
+
+/// Whether `driver` names an active volume driver plugin
+pub fn is_volume_driver(driver: &str) -> bool {
+    channel_for(driver, PluginCapability::VolumeDriver).is_ok()
+}
+
+/// A plugin reports failures in-band; an empty error means success
+fn check_error(plugin: &str, call: &str, error: String) -> Result<(), String> {
+    if error.is_empty() { Ok(()) } else { Err(format!("Plugin '{}' {} failed: {}", plugin, call, error)) }
+}
+
+fn rpc_error(plugin: &str, call: &str, status: tonic::Status) -> String {
+    format!("Plugin '{}' {} failed: {}", plugin, call, status.message())
+}
+
+/// Create a volume on a driver plugin; returns the host path it reported
+pub async fn create_volume(driver: &str, name: &str, options: HashMap<String, String>) -> Result<String, String> {
+    let mut client = VolumeDriverClient::new(channel_for(driver, PluginCapability::VolumeDriver)?);
+    let response = client.create(proto::VolumeCreateRequest { name: name.to_string(), options })
+        .await
+        .map_err(|e| rpc_error(driver, "Create", e))?
+        .into_inner();
+    check_error(driver, "Create", response.error)?;
+    Ok(response.mount_point)
+}
+
+pub async fn remove_volume(driver: &str, name: &str) -> Result<(), String> {
+    let mut client = VolumeDriverClient::new(channel_for(driver, PluginCapability::VolumeDriver)?);
+    let response = client.remove(proto::VolumeNameRequest { name: name.to_string() })
+        .await
+        .map_err(|e| rpc_error(driver, "Remove", e))?
+        .into_inner();
+    check_error(driver, "Remove", response.error)
+}
+
+/// Ask a driver plugin to make a volume available for a container; returns the host path to bind
+pub async fn mount_volume(driver: &str, name: &str, container_id: &str) -> Result<String, String> {
+    let mut client = VolumeDriverClient::new(channel_for(driver, PluginCapability::VolumeDriver)?);
+    let response = client.mount(proto::VolumeMountRequest { name: name.to_string(), container_id: container_id.to_string() })
+        .await
+        .map_err(|e| rpc_error(driver, "Mount", e))?
+        .into_inner();
+    check_error(driver, "Mount", response.error)?;
+    if response.mount_point.is_empty() {
+        return Err(format!("Plugin '{}' Mount returned no mount point for volume {}", driver, name));
+    }
+    Ok(response.mount_point)
+}
+
+pub async fn unmount_volume(driver: &str, name: &str, container_id: &str) -> Result<(), String> {
+    let mut client = VolumeDriverClient::new(channel_for(driver, PluginCapability::VolumeDriver)?);
+    let response = client.unmount(proto::VolumeMountRequest { name: name.to_string(), container_id: container_id.to_string() })
+        .await
+        .map_err(|e| rpc_error(driver, "Unmount", e))?
+        .into_inner();
+    check_error(driver, "Unmount", response.error)
+}
+
+/// Hand a started container to a network driver plugin
+pub async fn attach_network(plugin: &str, container_id: &str, pid: i32, ip_address: &str, labels: HashMap<String, String>) -> Result<(), String> {
+    let mut client = NetworkDriverClient::new(channel_for(plugin, PluginCapability::NetworkDriver)?);
+    let response = client.attach(proto::NetworkAttachRequest {
+        container_id: container_id.to_string(),
+        pid,
+        netns_path: format!("/proc/{}/ns/net", pid),
+        ip_address: ip_address.to_string(),
+        labels,
+    })
+        .await
+        .map_err(|e| rpc_error(plugin, "Attach", e))?
+        .into_inner();
+    check_error(plugin, "Attach", response.error)
+}
+
+pub async fn detach_network(plugin: &str, container_id: &str) -> Result<(), String> {
+    let mut client = NetworkDriverClient::new(channel_for(plugin, PluginCapability::NetworkDriver)?);
+    let response = client.detach(proto::NetworkDetachRequest { container_id: container_id.to_string() })
+        .await
+        .map_err(|e| rpc_error(plugin, "Detach", e))?
+        .into_inner();
+    check_error(plugin, "Detach", response.error)
+}
+
+/// Whether any auth provider is active; when none is, API calls skip authorization
+pub fn has_auth_providers() -> bool {
+    PLUGINS.read().map_or(false, |plugins| {
+        plugins.values().any(|plugin| plugin.capabilities.contains(&PluginCapability::AuthProvider))
+    })
+}
+
+/// Ask every auth provider, in name order, about an API call. The first denial wins; an
+/// unreachable provider is an error so calls fail closed.
+pub async fn authorize(method: &str, metadata: HashMap<String, String>, peer: &str) -> Result<AuthDecision, String> {
+    let mut providers: Vec<(String, Channel)> = PLUGINS.read()
+        .map_err(|_| "Plugin registry is unavailable".to_string())?
+        .iter()
+        .filter(|(_, plugin)| plugin.capabilities.contains(&PluginCapability::AuthProvider))
+        .map(|(name, plugin)| (name.clone(), plugin.channel.clone()))
+        .collect();
+    providers.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut actor = None;
+    for (name, channel) in providers {
+        let response = AuthProviderClient::new(channel)
+            .authorize(proto::AuthorizeRequest {
+                method: method.to_string(),
+                metadata: metadata.clone(),
+                peer: peer.to_string(),
+            })
+            .await
+            .map_err(|e| rpc_error(&name, "Authorize", e))?
+            .into_inner();
+        if !response.allowed {
+            let reason = if response.reason.is_empty() { "denied".to_string() } else { response.reason };
+            return Ok(AuthDecision::Denied { plugin: name, reason });
+        }
+        if !response.actor.is_empty() {
+            actor = Some(response.actor);
+        }
+    }
+    Ok(AuthDecision::Allowed { actor })
+}
+
+/// Plugins offered in `dir`: `<name>.sock` is served on that socket, `<name>.spec` holds the
+/// endpoint URL on its first line. Sorted by name; unreadable entries are skipped.
+pub fn discover_endpoints(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut found: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+            let endpoint = match path.extension()?.to_str()? {
+                "sock" => format!("unix://{}", path.to_str()?),
+                "spec" => std::fs::read_to_string(&path).ok()?.lines().next()?.trim().to_string(),
+                _ => return None,
+            };
+            validate_plugin_name(&name).ok()?;
+            Some((name, endpoint))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// The plugin directory from QUILT_PLUGIN_DIR, else [`DEFAULT_PLUGIN_DIR`]
+pub fn plugin_dir_from_env() -> String {
+    std::env::var("QUILT_PLUGIN_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| DEFAULT_PLUGIN_DIR.to_string())
+}
+
+/// Activate and store a plugin; fails without storing anything when the handshake fails
+pub async fn register(sync_engine: &SyncEngine, name: &str, endpoint: &str) -> Result<PluginRecord, String> {
+    let capabilities = activate(name, endpoint).await?;
+    let capabilities: Vec<String> = capabilities.iter().map(|c| c.as_str().to_string()).collect();
+    sync_engine.register_plugin(name, endpoint, &capabilities).await
+        .map_err(|e| {
+            forget(name);
+            format!("Failed to store plugin {}: {}", name, e)
+        })
+}
+
+/// Re-activate stored plugins, then keep picking up new ones from the plugin directory
+pub fn spawn_plugin_discovery(sync_engine: Arc<SyncEngine>, plugin_dir: String) {
+    tokio::spawn(async move {
+        match sync_engine.list_plugins().await {
+            Ok(stored) => {
+                for plugin in stored {
+                    if let Err(e) = activate(&plugin.name, &plugin.endpoint).await {
+                        ConsoleLogger::warning(&format!("🔌 [PLUGINS] {}", e));
+                    }
+                }
+            }
+            Err(e) => ConsoleLogger::warning(&format!("🔌 [PLUGINS] Failed to load registered plugins: {}", e)),
+        }
+
+        let dir = Path::new(&plugin_dir);
+        let mut interval = tokio::time::interval(DISCOVERY_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (name, endpoint) in discover_endpoints(dir) {
+                if is_active(&name) {
+                    continue;
+                }
+                if let Err(e) = register(&sync_engine, &name, &endpoint).await {
+                    ConsoleLogger::debug(&format!("🔌 [PLUGINS] Discovered {} but could not register it: {}", name, e));
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_validation() {
+        assert!(validate_plugin_name("nfs-driver").is_ok());
+        assert!(validate_plugin_name("local").is_err());
+        assert!(validate_plugin_name("-x").is_err());
+        assert!(validate_plugin_name("a/b").is_err());
+
+        assert!(validate_endpoint("unix:///run/quilt/plugins/nfs.sock").is_ok());
+        assert!(validate_endpoint("http://127.0.0.1:7000").is_ok());
+        assert!(validate_endpoint("unix://relative.sock").is_err());
+        assert!(validate_endpoint("ftp://host").is_err());
+
+        let capabilities = parse_capabilities(&["volume_driver".to_string(), "gpu".to_string(), "volume_driver".to_string()]).unwrap();
+        assert_eq!(capabilities, vec![PluginCapability::VolumeDriver]);
+        assert!(parse_capabilities(&[]).is_err());
+    }
+
+    #[test]
+    fn test_discover_endpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("auth.spec"), "http://127.0.0.1:7100\n").unwrap();
+        std::fs::write(dir.path().join("nfs.sock"), "").unwrap();
+        std::fs::write(dir.path().join("README"), "not a plugin").unwrap();
+
+        let found = discover_endpoints(dir.path());
+        assert_eq!(found, vec![
+            ("auth".to_string(), "http://127.0.0.1:7100".to_string()),
+            ("nfs".to_string(), format!("unix://{}", dir.path().join("nfs.sock").display())),
+        ]);
+        assert!(discover_endpoints(&dir.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+// Priority-based admission, eviction and drain
+// A start that does not fit within the admission limits (QUILT_ADMISSION) stops running
+// containers of a lower priority class, best-effort first, until it does; when that is not
+// possible the start is rejected. A drain stops running containers lowest class first. Evicted
+// containers are stopped, not removed, and get a Stopped event with reason=evicted.
+
+use crate::daemon::priority::{self, AdmissionPolicy, PriorityClass, RunningContainer};
+use crate::daemon::runtime::ContainerRuntime;
+use crate::sync::events::{global_event_buffer, EventType, DAEMON_ACTOR};
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A drained container, its class and the outcome of stopping it
+pub type DrainOutcome = (String, PriorityClass, Result<(), String>);
+
+static ADMISSION: once_cell::sync::Lazy<Option<AdmissionPolicy>> = once_cell::sync::Lazy::new(AdmissionPolicy::from_env);
+// One admission decision at a time, so two starts cannot both claim the last slot
+static ADMISSION_LOCK: once_cell::sync::Lazy<tokio::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Running and starting containers other than `container_id`; only the running ones can be
+/// evicted, but starting ones count against the limits
+async fn active_containers(sync_engine: &SyncEngine, container_id: &str) -> Result<(usize, Vec<RunningContainer>), String> {
+    let containers = sync_engine.list_containers(None).await
+        .map_err(|e| format!("Failed to list containers: {}", e))?;
+    let classes = sync_engine.list_priority_classes().await
+        .map_err(|e| format!("Failed to load priority classes: {}", e))?;
+
+    let active: Vec<_> = containers.into_iter()
+        .filter(|container| container.id != container_id)
+        .filter(|container| matches!(container.state, ContainerState::Running | ContainerState::Starting))
+        .collect();
+    let count = active.len();
+    let running = active.into_iter()
+        .filter(|container| container.state == ContainerState::Running)
+        .map(|container| RunningContainer {
+            class: classes.get(&container.id).copied().unwrap_or_default(),
+            started_at: container.started_at.unwrap_or(container.created_at),
+            id: container.id,
+        })
+        .collect();
+    Ok((count, running))
+}
+
+/// Make room for `container_id` under the admission limits, evicting lower classes if needed.
+/// Without QUILT_ADMISSION every start is admitted.
+pub async fn admit(sync_engine: &SyncEngine, runtime: &Arc<ContainerRuntime>, container_id: &str, class: PriorityClass) -> Result<(), String> {
+    let Some(policy) = ADMISSION.as_ref() else { return Ok(()) };
+    let _decision = ADMISSION_LOCK.lock().await;
+
+    let (mut active, running) = active_containers(sync_engine, container_id).await?;
+    let Some(reason) = policy.pressure(active, priority::host_available_memory_mb()) else { return Ok(()) };
+
+    let victims = priority::eviction_order(class, &running);
+    let needed = policy.max_running.map_or(0, |max| (active + 1).saturating_sub(max));
+    if victims.is_empty() || victims.len() < needed {
+        return Err(format!("Admission rejected for {} container: {} and no lower-priority container to evict",
+            class.as_str(), reason));
+    }
+
+    ConsoleLogger::warning(&format!("⚖️ [ADMISSION] {} ({}) does not fit: {}", container_id, class.as_str(), reason));
+    for victim in victims {
+        let detail = format!("evicted to admit {} ({}): {}", container_id, class.as_str(), reason);
+        if let Err(e) = evict(sync_engine, runtime, &victim, &detail, DAEMON_ACTOR).await {
+            ConsoleLogger::warning(&format!("⚖️ [ADMISSION] Failed to evict {}: {}", victim, e));
+            continue;
+        }
+        active -= 1;
+        if policy.pressure(active, priority::host_available_memory_mb()).is_none() {
+            return Ok(());
+        }
+    }
+    Err(format!("Admission rejected for {} container: still under pressure after evicting every lower-priority container",
+        class.as_str()))
+}
+
+/// Stop a running container to free its resources, recording why
+pub async fn evict(sync_engine: &SyncEngine, runtime: &Arc<ContainerRuntime>, container_id: &str, detail: &str, actor: &str) -> Result<(), String> {
+    let _guard = sync_engine.lock_container(container_id, "evict").await.map_err(|e| e.to_string())?;
+    let stopping = sync_engine.update_container_state(container_id, ContainerState::Stopping).await.is_ok();
+    let stop_runtime = Arc::clone(runtime);
+    let stop_id = container_id.to_string();
+    let stopped = tokio::task::spawn_blocking(move || stop_runtime.stop_container(&stop_id))
+        .await
+        .map_err(|e| format!("Stop task failed: {}", e))
+        .and_then(|result| result);
+    if let Err(e) = stopped {
+        if stopping {
+            let _ = sync_engine.update_container_state(container_id, ContainerState::Running).await;
+        }
+        return Err(e);
+    }
+
+    if let Err(e) = sync_engine.update_container_state(container_id, ContainerState::Exited).await {
+        ConsoleLogger::warning(&format!("Failed to update container state in sync engine: {}", e));
+    }
+    let _ = sync_engine.stop_monitoring(container_id).await;
+
+    ConsoleLogger::warning(&format!("⚖️ [EVICT] {} {}", container_id, detail));
+    let _ = sync_engine.store_container_log_as(container_id, "warn", &format!("Container {}", detail), actor).await;
+    let attributes = HashMap::from([
+        ("reason".to_string(), "evicted".to_string()),
+        ("detail".to_string(), detail.to_string()),
+    ]);
+    global_event_buffer().emit_as(EventType::Stopped, container_id, actor, Some(attributes));
+    Ok(())
+}
+
+/// Stop every running container of class `up_to` or lower, lowest class first
+pub async fn drain(sync_engine: &SyncEngine, runtime: &Arc<ContainerRuntime>, up_to: PriorityClass, actor: &str) -> Result<Vec<DrainOutcome>, String> {
+    let (_, running) = active_containers(sync_engine, "").await?;
+    let running: Vec<_> = running.into_iter().filter(|container| container.class <= up_to).collect();
+    let classes: HashMap<String, PriorityClass> = running.iter().map(|container| (container.id.clone(), container.class)).collect();
+
+    let mut outcomes = Vec::with_capacity(running.len());
+    for container_id in priority::drain_order(running) {
+        let result = evict(sync_engine, runtime, &container_id, "evicted by drain", actor).await;
+        let class = classes.get(&container_id).copied().unwrap_or_default();
+        outcomes.push((container_id, class, result));
+    }
+    Ok(outcomes)
+}
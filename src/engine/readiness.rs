@@ -0,0 +1,92 @@
+// Readiness gate evaluation during startup
+// Runs after the container process is up and before it is reported Running. Gates are polled
+// together; each one that passes is logged, and the start fails when the process exits or the
+// gates have not all passed within their timeout.
+
+use crate::daemon::readiness_gates::{self, ReadinessGate, ReadinessGates};
+use crate::daemon::runtime::ContainerRuntime;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+use crate::utils::process::ProcessUtils;
+
+use nix::unistd::Pid;
+use regex::Regex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Wait until every gate of the container passed. `host_network` containers are probed on
+/// 127.0.0.1; the others on their bridge address once network setup assigned one.
+pub async fn wait_for_gates(
+    sync_engine: &SyncEngine,
+    runtime: &Arc<ContainerRuntime>,
+    container_id: &str,
+    pid: Pid,
+    rootfs_path: &str,
+    host_network: bool,
+    readiness: &ReadinessGates,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let deadline = started + readiness.timeout();
+    // Patterns were validated when the gates were stored
+    let patterns: Vec<Option<Regex>> = readiness.gates.iter()
+        .map(|gate| match gate {
+            ReadinessGate::LogLine { pattern } => Regex::new(pattern).ok(),
+            _ => None,
+        })
+        .collect();
+    let mut pending: Vec<usize> = (0..readiness.gates.len()).collect();
+
+    ConsoleLogger::info(&format!("🚦 [READINESS] Waiting up to {}s for {} gate(s) of {}",
+        readiness.timeout_secs, pending.len(), container_id));
+    loop {
+        let address = if host_network {
+            Some("127.0.0.1".to_string())
+        } else {
+            sync_engine.get_network_allocation(container_id).await.ok()
+                .map(|allocation| allocation.ip_address)
+                .filter(|ip| !ip.is_empty())
+        };
+
+        let mut still_pending = Vec::with_capacity(pending.len());
+        for index in pending {
+            let gate = &readiness.gates[index];
+            let passed = match gate {
+                ReadinessGate::Tcp { port } => match &address {
+                    Some(address) => readiness_gates::tcp_open(address, *port).await,
+                    None => false,
+                },
+                ReadinessGate::Http { port, path } => match &address {
+                    Some(address) => readiness_gates::http_ok(address, *port, path).await,
+                    None => false,
+                },
+                ReadinessGate::File { path } => readiness_gates::file_exists(rootfs_path, path),
+                ReadinessGate::LogLine { .. } => match (&patterns[index], runtime.get_container_logs(container_id)) {
+                    (Some(pattern), Some(output)) => output.lines().iter().any(|line| pattern.is_match(&line.message)),
+                    _ => false,
+                },
+            };
+            if passed {
+                let message = format!("Readiness gate {} passed after {:.1}s", gate.describe(), started.elapsed().as_secs_f64());
+                ConsoleLogger::debug(&format!("🚦 [READINESS] {}: {}", container_id, message));
+                let _ = sync_engine.store_container_log(container_id, "info", &message).await;
+            } else {
+                still_pending.push(index);
+            }
+        }
+        pending = still_pending;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if !ProcessUtils::is_process_running(pid) {
+            return Err("Container process exited before it became ready".to_string());
+        }
+        if Instant::now() >= deadline {
+            let waiting: Vec<String> = pending.iter().map(|index| readiness.gates[*index].describe()).collect();
+            return Err(format!("Readiness gates not passed within {}s: {}", readiness.timeout_secs, waiting.join(", ")));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
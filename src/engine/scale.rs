@@ -0,0 +1,205 @@
+// Replica scaling
+// A replica set is a template container plus clones of it labelled with the template's ID and an
+// ordinal. Scaling clones the template until the set has the requested size, naming each clone
+// `<template>-<ordinal>` and shifting every published host port by the ordinal, or removes the
+// highest ordinals first. The template is replica zero and is never removed by scaling.
+
+use super::clone::CloneOptions;
+use super::Engine;
+use crate::sync::events::matches_selector;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::PortMapping;
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+/// Label naming the template a replica was cloned from
+pub const REPLICA_OF_LABEL: &str = "quilt.replica-of";
+/// Label carrying a replica's ordinal (1, 2, ...)
+pub const REPLICA_ORDINAL_LABEL: &str = "quilt.replica-ordinal";
+/// Largest replica set, template included
+pub const MAX_REPLICAS: u32 = 64;
+
+/// Scaling reads the set and then creates or removes containers; one scale at a time keeps two
+/// calls from picking the same ordinals
+static SCALE_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// The container a replica set is cloned from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScaleTemplate {
+    pub container_id: String,
+    /// Prefix of replica names: the template's name, or its short ID when unnamed
+    pub base_name: String,
+}
+
+impl ScaleTemplate {
+    pub fn replica_name(&self, ordinal: u32) -> String {
+        format!("{}-{}", self.base_name, ordinal)
+    }
+}
+
+/// What a scale changed and the resulting set, template first
+#[derive(Debug, Clone, Default)]
+pub struct ScaleReport {
+    pub created: Vec<String>,
+    pub removed: Vec<String>,
+    pub replicas: Vec<String>,
+}
+
+/// Find the template behind `target`: a container (a replica's own template when `target` is a
+/// replica), or for a virtual service the oldest non-replica container matching its selector
+pub async fn resolve_template(sync_engine: &SyncEngine, target: &str) -> Result<ScaleTemplate, String> {
+    let labels = sync_engine.list_container_labels().await.map_err(|e| e.to_string())?;
+
+    let container_id = match sync_engine.get_container_by_name(target).await {
+        Ok(id) => Some(id),
+        Err(_) => sync_engine.get_container_status(target).await.ok().map(|status| status.id),
+    };
+    let container_id = match container_id {
+        Some(id) => labels.get(&id)
+            .and_then(|container_labels| container_labels.get(REPLICA_OF_LABEL).cloned())
+            .unwrap_or(id),
+        None => {
+            let service = sync_engine.list_virtual_services().await.map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|service| service.name == target.to_ascii_lowercase())
+                .ok_or_else(|| format!("Container or virtual service '{}' not found", target))?;
+            let mut candidates = sync_engine.list_containers(None).await.map_err(|e| e.to_string())?;
+            candidates.retain(|container| labels.get(&container.id).map_or(false, |container_labels|
+                matches_selector(container_labels, &service.selector) && !container_labels.contains_key(REPLICA_OF_LABEL)));
+            candidates.into_iter()
+                .min_by_key(|container| container.created_at)
+                .map(|container| container.id)
+                .ok_or_else(|| format!("Virtual service '{}' has no container to scale from", service.name))?
+        }
+    };
+
+    let status = sync_engine.get_container_status(&container_id).await
+        .map_err(|e| format!("Template container {} not found: {}", container_id, e))?;
+    let base_name = status.name.unwrap_or_else(|| container_id[..8.min(container_id.len())].to_string());
+    Ok(ScaleTemplate { container_id, base_name })
+}
+
+/// Create or remove replicas of `template` until the set (template included) has `replicas`
+/// members. New replicas are created but not started.
+pub async fn scale(engine: &Engine, template: &ScaleTemplate, replicas: u32, actor: &str) -> Result<ScaleReport, String> {
+    if replicas == 0 || replicas > MAX_REPLICAS {
+        return Err(format!("Invalid replica count {}: must be between 1 and {}", replicas, MAX_REPLICAS));
+    }
+    let _guard = SCALE_LOCK.lock().await;
+    let sync_engine = engine.sync_engine();
+
+    let labels = sync_engine.list_container_labels().await.map_err(|e| e.to_string())?;
+    let mut current = replica_ordinals(&labels, &template.container_id);
+    let wanted = (replicas - 1) as usize;
+    let mut report = ScaleReport::default();
+
+    if current.len() > wanted {
+        // Highest ordinals go first so the survivors stay a contiguous 1..N
+        for (ordinal, replica_id) in current.split_off(wanted).into_iter().rev() {
+            engine.remove_container(&replica_id, true, actor).await
+                .map_err(|e| format!("Scaling stopped after removing {} replica(s): replica {} ({}): {}",
+                    report.removed.len(), ordinal, replica_id, e))?;
+            report.removed.push(replica_id);
+        }
+    } else if current.len() < wanted {
+        let bindings = sync_engine.get_port_bindings(&template.container_id).await.map_err(|e| e.to_string())?;
+        let template_ports: Vec<PortMapping> = bindings.iter().map(|binding| binding.to_mapping()).collect();
+
+        for ordinal in free_ordinals(&current, wanted - current.len()) {
+            let name = template.replica_name(ordinal);
+            let options = shifted_ports(&template_ports, ordinal).map(|ports| CloneOptions {
+                name: Some(name.clone()),
+                labels: HashMap::from([
+                    (REPLICA_OF_LABEL.to_string(), template.container_id.clone()),
+                    (REPLICA_ORDINAL_LABEL.to_string(), ordinal.to_string()),
+                ]),
+                ports,
+                ..Default::default()
+            });
+            let created = match options {
+                Ok(options) => engine.clone_container(&template.container_id, options, actor).await,
+                Err(e) => Err(e),
+            };
+            let replica_id = created
+                .map_err(|e| format!("Scaling stopped after creating {} replica(s): {}: {}", report.created.len(), name, e))?;
+            current.push((ordinal, replica_id.clone()));
+            report.created.push(replica_id);
+        }
+        current.sort();
+    }
+
+    ConsoleLogger::info(&format!("📈 [SCALE] {} scaled {} to {} replica(s) (+{} -{})", actor, template.base_name,
+        replicas, report.created.len(), report.removed.len()));
+    report.replicas = std::iter::once(template.container_id.clone())
+        .chain(current.into_iter().map(|(_, replica_id)| replica_id))
+        .collect();
+    Ok(report)
+}
+
+/// (ordinal, container ID) of the template's replicas, lowest ordinal first
+fn replica_ordinals(labels: &HashMap<String, HashMap<String, String>>, template_id: &str) -> Vec<(u32, String)> {
+    let mut replicas: Vec<(u32, String)> = labels.iter()
+        .filter(|(_, container_labels)| container_labels.get(REPLICA_OF_LABEL).map(String::as_str) == Some(template_id))
+        .map(|(id, container_labels)| {
+            let ordinal = container_labels.get(REPLICA_ORDINAL_LABEL).and_then(|value| value.parse().ok()).unwrap_or(u32::MAX);
+            (ordinal, id.clone())
+        })
+        .collect();
+    replicas.sort();
+    replicas
+}
+
+/// The `count` lowest ordinals not taken by `current`, filling gaps left by removed replicas
+fn free_ordinals(current: &[(u32, String)], count: usize) -> Vec<u32> {
+    (1..).filter(|ordinal| !current.iter().any(|(taken, _)| taken == ordinal)).take(count).collect()
+}
+
+/// The template's published ports moved up by `ordinal`, so every replica gets its own host ports
+fn shifted_ports(ports: &[PortMapping], ordinal: u32) -> Result<Vec<PortMapping>, String> {
+    ports.iter().map(|mapping| {
+        let host_port = u16::try_from(mapping.host_port as u32 + ordinal).ok()
+            .ok_or_else(|| format!("Host port {} + {} is out of range", mapping.host_port, ordinal))?;
+        Ok(PortMapping { host_port, ..mapping.clone() })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_ordinals() {
+        let replica = |template: &str, ordinal: &str| HashMap::from([
+            (REPLICA_OF_LABEL.to_string(), template.to_string()),
+            (REPLICA_ORDINAL_LABEL.to_string(), ordinal.to_string()),
+        ]);
+        let labels = HashMap::from([
+            ("r3".to_string(), replica("web", "3")),
+            ("r1".to_string(), replica("web", "1")),
+            ("other".to_string(), replica("db", "2")),
+            ("plain".to_string(), HashMap::from([("app".to_string(), "web".to_string())])),
+        ]);
+
+        let current = replica_ordinals(&labels, "web");
+        assert_eq!(current, vec![(1, "r1".to_string()), (3, "r3".to_string())]);
+        assert_eq!(free_ordinals(&current, 3), vec![2, 4, 5]);
+
+        let template = ScaleTemplate { container_id: "web".to_string(), base_name: "web".to_string() };
+        assert_eq!(template.replica_name(2), "web-2");
+    }
+
+    #[test]
+    fn test_shifted_ports() {
+        let mapping = |host_port| PortMapping {
+            host_ip: "0.0.0.0".to_string(),
+            host_port,
+            container_port: 80,
+            protocol: "tcp".to_string(),
+        };
+        let shifted = shifted_ports(&[mapping(8080)], 2).unwrap();
+        assert_eq!((shifted[0].host_port, shifted[0].container_port), (8082, 80));
+        assert!(shifted_ports(&[mapping(65535)], 1).is_err());
+    }
+}
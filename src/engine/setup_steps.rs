@@ -0,0 +1,64 @@
+// Setup step tracking
+// A container's setup commands run inside it before its command. While they run, the steps
+// the container reports in its rootfs are copied into the setup_steps table and the step's
+// output into the container log, so a failed install shows which step broke and why.
+
+use crate::daemon::setup_report;
+use crate::sync::SyncEngine;
+use crate::utils::console::ConsoleLogger;
+use crate::utils::process::ProcessUtils;
+
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Start a container's steps over before it starts: every command pending, no stale report
+pub async fn prepare(sync_engine: &SyncEngine, container_id: &str, rootfs_path: &str, commands: &[String]) -> Result<(), String> {
+    setup_report::clear(rootfs_path);
+    sync_engine.reset_setup_steps(container_id, commands).await
+        .map_err(|e| format!("Failed to reset setup steps: {}", e))
+}
+
+/// Follow the steps of a started container until all have finished, one failed or the process
+/// is gone; meant to run in the background
+pub async fn follow(sync_engine: SyncEngine, container_id: String, rootfs_path: String, pid: i32, total: usize) {
+    let mut recorded = 0;
+    loop {
+        // Checked before reading so the last report of an exited process is not missed
+        let running = ProcessUtils::is_process_running(ProcessUtils::i32_to_pid(pid));
+        let reports = setup_report::read(&rootfs_path);
+        for report in reports.iter().skip(recorded) {
+            record(&sync_engine, &container_id, report, total).await;
+        }
+        recorded = recorded.max(reports.len());
+
+        if reports.iter().any(|report| !report.succeeded) || recorded >= total {
+            return;
+        }
+        if !running {
+            let message = format!("Container exited after {} of {} setup steps", recorded, total);
+            ConsoleLogger::warning(&format!("⚠️ [SETUP] {}: {}", container_id, message));
+            let _ = sync_engine.store_container_log(&container_id, "error", &message).await;
+            return;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn record(sync_engine: &SyncEngine, container_id: &str, report: &setup_report::SetupStepReport, total: usize) {
+    let (level, outcome) = if report.succeeded { ("info", "succeeded") } else { ("error", "failed") };
+    let mut message = format!("Setup step {}/{} ({}) {} in {}ms",
+        report.index + 1, total, report.command, outcome, report.duration_ms);
+    if !report.output.trim().is_empty() {
+        message.push_str(":\n");
+        message.push_str(report.output.trim_end());
+    }
+    if !report.succeeded {
+        ConsoleLogger::error(&format!("❌ [SETUP] {}: setup step {} ({}) failed", container_id, report.index + 1, report.command));
+    }
+
+    let _ = sync_engine.store_container_log(container_id, level, &message).await;
+    if let Err(e) = sync_engine.finish_setup_step(container_id, report.index as i64, report.succeeded, &report.output, report.duration_ms as i64).await {
+        ConsoleLogger::warning(&format!("⚠️ [SETUP] Failed to record setup step for {}: {}", container_id, e));
+    }
+}
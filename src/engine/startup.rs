@@ -1,29 +1,155 @@
 use crate::daemon::{ContainerConfig, CgroupLimits, NamespaceConfig};
+use crate::daemon::paths;
+use crate::daemon::runtime::ContainerRuntime;
+use crate::daemon::rootfs_pool::RootfsPool;
+use super::startup_pipeline::{StartupPipeline, StartupStage};
 use crate::utils::console::ConsoleLogger;
 use crate::utils::filesystem::FileSystemUtils;
 use crate::sync::{SyncEngine, ContainerState, MountType};
+use crate::sync::events::{global_event_buffer, EventType};
+use crate::sync::operations::global_operations;
 use crate::icc;
 
 use std::sync::Arc;
 use std::collections::HashMap;
 use sqlx::Row;
 
+/// Upper bound on a container start when the caller set no deadline
+pub const DEFAULT_STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Run a container start bounded by `timeout` (the deadline of a caller waiting for it, else the
+/// default startup limit)
+/// plus the container's readiness gate timeout. A start that fails or runs out of time is rolled
+/// back instead of being left in Starting.
+pub async fn run_container_startup(
+    sync_engine: &SyncEngine,
+    container_id: &str,
+    network_manager: Arc<icc::network::NetworkManager>,
+    runtime: Arc<ContainerRuntime>,
+    timeout: std::time::Duration,
+) -> Result<(), String> {
+    let task_start = std::time::Instant::now();
+
+    // Held until the start finished or was rolled back. A start that lost the race to another
+    // operation is refused here, before there is anything to roll back.
+    let _guard = sync_engine.lock_container(container_id, "start").await.map_err(|e| e.to_string())?;
+    let state = sync_engine.get_container_status(container_id).await.map_err(|e| e.to_string())?.state;
+    if !state.can_transition_to(&ContainerState::Starting) {
+        return Err(format!("Cannot start container in state: {}", state.to_string()));
+    }
+
+    // A restart reuses the rootfs on disk; rollback must keep it
+    let restarting = sync_engine.get_container_status(container_id).await.ok()
+        .and_then(|status| status.rootfs_path)
+        .map_or(false, |rootfs| FileSystemUtils::exists(&rootfs));
+
+    // Readiness gates get their own time on top of the startup limit
+    let timeout = timeout + sync_engine.get_readiness_gates(container_id).await.ok().flatten()
+        .map_or(std::time::Duration::ZERO, |readiness| readiness.timeout());
+
+    ConsoleLogger::info(&format!("⏰ [TASK-SPAWN] Starting container {} with {:?} timeout", container_id, timeout));
+
+    let startup_result = tokio::time::timeout(
+        timeout,
+        start_container_process(sync_engine, container_id, Arc::clone(&network_manager), Arc::clone(&runtime)),
+    ).await;
+
+    let reason = match startup_result {
+        Ok(Ok(())) => {
+            ConsoleLogger::success(&format!("🎯 [TASK-COMPLETE] Container {} startup completed successfully in {:?}",
+                container_id, task_start.elapsed()));
+            return Ok(());
+        }
+        Ok(Err(e)) => {
+            ConsoleLogger::error(&format!("💥 [TASK-ERROR] Failed to start container process {} after {:?}: {}",
+                container_id, task_start.elapsed(), e));
+            e
+        }
+        Err(_) => {
+            ConsoleLogger::error(&format!("⏰ [TASK-TIMEOUT] Container {} startup timed out after {:?} (limit: {:?})",
+                container_id, task_start.elapsed(), timeout));
+            format!("startup did not finish within {:?}", timeout)
+        }
+    };
+
+    rollback_container_start(sync_engine, container_id, &network_manager, runtime, restarting, &reason).await;
+    Err(reason)
+}
+
+/// Undo the partial state of an aborted start: stop the process, drop the runtime entry and any
+/// freshly extracted rootfs, withdraw published ports, DNS and the pooled namespace, and mark the
+/// container Error so it can be started again
+async fn rollback_container_start(
+    sync_engine: &SyncEngine,
+    container_id: &str,
+    network_manager: &icc::network::NetworkManager,
+    runtime: Arc<ContainerRuntime>,
+    restarting: bool,
+    reason: &str,
+) {
+    ConsoleLogger::warning(&format!("↩️ [ROLLBACK] Rolling back start of {}: {}", container_id, reason));
+    global_operations().report(container_id, 0, "rolling back");
+
+    let rollback_id = container_id.to_string();
+    let rollback_runtime = Arc::clone(&runtime);
+    let runtime_result = tokio::task::spawn_blocking(move || {
+        if rollback_runtime.get_container_info(&rollback_id).is_none() {
+            // Creation never registered; only a partially extracted rootfs may be left
+            if restarting {
+                return Ok(());
+            }
+            return FileSystemUtils::remove_path(paths::default_rootfs_path(&rollback_id));
+        }
+        if restarting {
+            rollback_runtime.stop_container(&rollback_id)
+        } else {
+            rollback_runtime.remove_container(&rollback_id)
+        }
+    }).await;
+    match runtime_result {
+        Ok(Err(e)) => ConsoleLogger::warning(&format!("⚠️ [ROLLBACK] Runtime cleanup for {} incomplete: {}", container_id, e)),
+        Err(e) => ConsoleLogger::warning(&format!("⚠️ [ROLLBACK] Runtime cleanup task for {} failed: {}", container_id, e)),
+        Ok(Ok(())) => {}
+    }
+
+    // Port bindings stay allocated so a later start republishes them; only the forwarding is withdrawn
+    let port_bindings = sync_engine.get_port_bindings(container_id).await.unwrap_or_default();
+    if !port_bindings.is_empty() {
+        if let Ok(allocation) = sync_engine.get_network_allocation(container_id).await {
+            let mappings: Vec<_> = port_bindings.iter().map(|binding| binding.to_mapping()).collect();
+            network_manager.unpublish_container_ports(container_id, &allocation.ip_address, &mappings);
+        }
+    }
+    if sync_engine.get_egress_proxy(container_id).await.ok().flatten().map_or(false, |proxy| proxy.transparent) {
+        network_manager.remove_egress_redirect(container_id);
+    }
+    let _ = network_manager.unregister_container_dns(container_id);
+    network_manager.release_pooled_netns(container_id);
+
+    let _ = sync_engine.store_container_log(container_id, "error",
+        &format!("Container startup rolled back: {}", reason)).await;
+    let _ = sync_engine.update_container_state(container_id, ContainerState::Error).await;
+}
+
 /// Background container process startup
 /// This function handles the actual container creation and startup process
 pub async fn start_container_process(
     sync_engine: &SyncEngine, 
     container_id: &str,
-    network_manager: Arc<icc::network::NetworkManager>
+    network_manager: Arc<icc::network::NetworkManager>,
+    runtime: Arc<ContainerRuntime>,
 ) -> Result<(), String> {
-    use crate::daemon::runtime::ContainerRuntime;
-    
     let start_time = std::time::Instant::now();
     ConsoleLogger::info(&format!("🚀 [STARTUP] Starting container process for {} at {:?}", container_id, std::time::SystemTime::now()));
     
     // Store startup log
     let _ = sync_engine.store_container_log(container_id, "info", "Container startup initiated").await;
     
+    // Decides the place in each stage queue and what admission may evict
+    let priority_class = sync_engine.get_priority_class(container_id).await.unwrap_or_default();
+    
     // Step 1: Configuration retrieval
+    global_operations().report(container_id, 5, "loading configuration");
     let config_start = std::time::Instant::now();
     ConsoleLogger::debug(&format!("📋 [STARTUP-CONFIG] Retrieving configuration for {}", container_id));
     
@@ -36,7 +162,7 @@ pub async fn start_container_process(
     
     // Get full container config from database to get image_path and command
     ConsoleLogger::debug(&format!("🔍 [STARTUP-CONFIG] Querying database for container details {}", container_id));
-    let container_record = sqlx::query("SELECT image_path, command, rootfs_path FROM containers WHERE id = ?")
+    let container_record = sqlx::query("SELECT image_path, command, environment, working_directory, run_as_user, timezone, locale, rootfs_path, allow_fuse, fuse_mounts, immutable, privileged, prestart_hooks, network_plugin, labels, readiness_gates, setup_commands, enable_network_namespace, time_offset, enable_time_namespace, monotonic_offset, boottime_offset, enable_cgroup_namespace, enable_user_namespace, memory_high_mb, memory_swap_mb, memory_swappiness, cpu_limit_percent, cpu_burst_percent FROM containers WHERE id = ?")
         .bind(container_id)
         .fetch_one(sync_engine.pool())
         .await
@@ -47,7 +173,65 @@ pub async fn start_container_process(
     
     let image_path: String = container_record.get("image_path");
     let command: String = container_record.get("command");
+    let mut environment: HashMap<String, String> = container_record
+        .get::<Option<String>, _>("environment")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let working_directory: Option<String> = container_record.get("working_directory");
+    let user: Option<String> = container_record.get("run_as_user");
+    let (default_timezone, default_locale) = crate::daemon::timezone::defaults();
+    let timezone = container_record.get::<Option<String>, _>("timezone").or(default_timezone);
+    let locale = container_record.get::<Option<String>, _>("locale").or(default_locale);
+    for (name, value) in crate::daemon::timezone::environment(timezone.as_deref(), locale.as_deref()) {
+        environment.entry(name).or_insert(value);
+    }
     let rootfs_path: Option<String> = container_record.get("rootfs_path");
+    let allow_fuse: bool = container_record.get("allow_fuse");
+    let immutable: bool = container_record.get("immutable");
+    let privileged: bool = container_record.get("privileged");
+    let fuse_mounts: Vec<crate::utils::validation::FuseMount> = container_record
+        .get::<Option<String>, _>("fuse_mounts")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let prestart_hooks: Vec<crate::daemon::prestart::PrestartHook> = container_record
+        .get::<Option<String>, _>("prestart_hooks")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let network_plugin: Option<String> = container_record.get("network_plugin");
+    // Variables the container sets itself win over the proxy's
+    let egress_proxy = sync_engine.get_egress_proxy(container_id).await.ok().flatten();
+    if let Some(proxy) = &egress_proxy {
+        for (name, value) in super::egress_proxy::environment(sync_engine, &network_manager, proxy).await? {
+            environment.entry(name).or_insert(value);
+        }
+    }
+    let labels: HashMap<String, String> = container_record
+        .get::<Option<String>, _>("labels")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let readiness: Option<crate::daemon::readiness_gates::ReadinessGates> = container_record
+        .get::<Option<String>, _>("readiness_gates")
+        .and_then(|json| serde_json::from_str(&json).ok());
+    let setup_commands: Vec<String> = container_record
+        .get::<Option<String>, _>("setup_commands")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let host_network = !container_record.get::<bool, _>("enable_network_namespace");
+    let time_offset: Option<i64> = container_record.get("time_offset");
+    let enable_time_namespace: bool = container_record.get("enable_time_namespace");
+    let monotonic_offset: i64 = container_record.get("monotonic_offset");
+    let boottime_offset: i64 = container_record.get("boottime_offset");
+    let enable_cgroup_namespace: bool = container_record.get("enable_cgroup_namespace");
+    let enable_user_namespace: bool = container_record.get("enable_user_namespace");
+    let megabytes = |column: &str| container_record.get::<Option<i64>, _>(column).map(|mb| mb as u64 * 1024 * 1024);
+    let memory_high_bytes = megabytes("memory_high_mb");
+    let memory_swap_bytes = megabytes("memory_swap_mb");
+    let swappiness = container_record.get::<Option<i64>, _>("memory_swappiness").map(|value| value as u64);
+    let cpu_period = CgroupLimits::default().cpu_period.unwrap_or(100_000);
+    let cpu_quota = container_record.get::<Option<f64>, _>("cpu_limit_percent")
+        .map(|percent| CgroupLimits::quota_for_percent(percent, cpu_period));
+    let cpu_burst = container_record.get::<Option<f64>, _>("cpu_burst_percent")
+        .map(|percent| CgroupLimits::quota_for_percent(percent, cpu_period) as u64);
     
     ConsoleLogger::debug(&format!("📄 [STARTUP-CONFIG] Container {} details: image={}, command={}, rootfs={:?}", 
         container_id, image_path, command, rootfs_path));
@@ -64,6 +248,7 @@ pub async fn start_container_process(
         container_id, config_start.elapsed()));
     
     // Step 2: Mount preparation
+    global_operations().report(container_id, 10, "preparing mounts");
     let mount_start = std::time::Instant::now();
     ConsoleLogger::debug(&format!("💾 [STARTUP-MOUNTS] Converting {} mounts for container {}", sync_mounts.len(), container_id));
     
@@ -75,8 +260,18 @@ pub async fn start_container_process(
             
         let source = match m.mount_type {
             MountType::Volume => {
-                // For volumes, convert volume name to actual path
-                let volume_path = sync_engine.get_volume_path(&m.source).to_string_lossy().to_string();
+                // For volumes, convert volume name to actual path; a plugin driver reports its own
+                let driver = sync_engine.get_volume(&m.source).await.ok().flatten()
+                    .map(|volume| volume.driver)
+                    .filter(|driver| driver != "local");
+                let volume_path = match driver {
+                    Some(driver) => super::plugins::mount_volume(&driver, &m.source, container_id).await
+                        .map_err(|e| {
+                            ConsoleLogger::error(&format!("❌ [STARTUP-MOUNTS] {}", e));
+                            format!("Failed to mount volume {}: {}", m.source, e)
+                        })?,
+                    None => sync_engine.get_volume_path(&m.source).to_string_lossy().to_string(),
+                };
                 ConsoleLogger::debug(&format!("📦 [STARTUP-MOUNTS] Volume {} resolved to path: {}", m.source, volume_path));
                 volume_path
             }
@@ -100,6 +295,7 @@ pub async fn start_container_process(
         container_id, mount_start.elapsed()));
     
     // Step 2.5: Mount Security Validation
+    global_operations().report(container_id, 15, "validating mounts");
     let validation_start = std::time::Instant::now();
     ConsoleLogger::debug(&format!("🔒 [STARTUP-SECURITY] Validating mount security for {} ({} mounts)", container_id, daemon_mounts.len()));
     
@@ -135,24 +331,48 @@ pub async fn start_container_process(
         vec!["/bin/sh".to_string(), "-c".to_string(), command.clone()]
     };
     
-    let legacy_config = ContainerConfig {
+    // Check if network setup is needed BEFORE starting container; with a warm pool the process
+    // joins a pre-plumbed namespace instead of creating its own
+    let needs_network_setup = sync_engine.should_setup_network(container_id).await.unwrap_or(false);
+    let pooled_netns = if needs_network_setup { network_manager.claim_pooled_netns(container_id) } else { None };
+    
+    let mut legacy_config = ContainerConfig {
         image_path: image_path.clone(),
         command: command_vec.clone(),
-        environment: HashMap::new(), // TODO: Get from sync engine
-        setup_commands: vec![],
-        resource_limits: Some(CgroupLimits::default()),
-        namespace_config: Some(NamespaceConfig::default()),
-        working_directory: None,
+        environment,
+        setup_commands: setup_commands.clone(),
+        resource_limits: Some(CgroupLimits {
+            cpu_quota,
+            cpu_burst,
+            memory_high_bytes,
+            memory_swap_bytes,
+            swappiness,
+            ..CgroupLimits::default()
+        }),
+        namespace_config: Some(NamespaceConfig {
+            join_network_namespace: pooled_netns.map(|slot| slot.netns_path),
+            time: enable_time_namespace,
+            monotonic_offset,
+            boottime_offset,
+            cgroup: enable_cgroup_namespace,
+            user: enable_user_namespace,
+            ..NamespaceConfig::default()
+        }),
+        working_directory,
+        user,
         mounts: daemon_mounts,
+        allow_fuse,
+        immutable,
+        privileged,
+        prestart_hooks,
+        time_offset,
+        timezone,
+        stored_image: None,
     };
 
     ConsoleLogger::debug(&format!("📝 [STARTUP-LEGACY] Legacy config created for {}: image={}, command={:?}", 
         container_id, image_path, command_vec));
 
-    // Create legacy runtime for actual process management (temporary)
-    ConsoleLogger::debug(&format!("🏗️ [STARTUP-RUNTIME] Creating container runtime for {}", container_id));
-    let runtime = ContainerRuntime::new();
-    
     ConsoleLogger::debug(&format!("⏱️ [STARTUP-LEGACY] Legacy conversion completed for {} in {:?}", 
         container_id, legacy_start.elapsed()));
     
@@ -171,6 +391,7 @@ pub async fn start_container_process(
         container_id, state_start.elapsed()));
 
     // Step 5: Container creation/restart logic
+    global_operations().report(container_id, 25, "creating rootfs");
     let creation_start = std::time::Instant::now();
     
     // Check if this is a restart (container already has rootfs)
@@ -183,16 +404,39 @@ pub async fn start_container_process(
         true
     };
 
+    let pipeline = StartupPipeline::global();
+    let rootfs_permit = pipeline.enter_with_priority(StartupStage::Rootfs, container_id, priority_class).await?;
+    
     if needs_creation {
         // First time starting - create container in legacy runtime
         ConsoleLogger::info(&format!("🏗️ [STARTUP-CREATE] Creating NEW container runtime for {} (first time start)", container_id));
-        runtime.create_container(container_id.to_string(), legacy_config)
+        // The image is unpacked into the local store once; this and later containers copy its
+        // layers from there. Pooled images come pre-extracted from the warm pool instead.
+        if !RootfsPool::global().is_pooled(&image_path) {
+            match super::images::ensure_stored(sync_engine, &image_path).await {
+                Ok(image) => legacy_config.stored_image = Some(image),
+                Err(e) => ConsoleLogger::warning(&format!("⚠️ [STARTUP-CREATE] Image store unavailable for {} ({}); extracting {} directly", container_id, e, image_path)),
+            }
+        }
+        // Image extraction is blocking disk I/O; run it on the blocking pool so other starts keep moving
+        let create_runtime = Arc::clone(&runtime);
+        let create_id = container_id.to_string();
+        tokio::task::spawn_blocking(move || create_runtime.create_container(create_id, legacy_config))
+            .await
+            .map_err(|e| format!("Rootfs preparation task failed: {}", e))
+            .and_then(|result| result)
             .map_err(|e| {
                 ConsoleLogger::error(&format!("❌ [STARTUP-CREATE] Failed to create legacy container {}: {}", container_id, e));
                 format!("Failed to create legacy container: {}", e)
             })?;
         
         ConsoleLogger::debug(&format!("✅ [STARTUP-CREATE] Container runtime created successfully for {}", container_id));
+
+        // Replace a claimed warm rootfs in the background
+        if RootfsPool::global().is_pooled(&image_path) {
+            let pool_runtime = Arc::clone(&runtime);
+            tokio::task::spawn_blocking(move || pool_runtime.refill_rootfs_pool());
+        }
             
         // Save the rootfs path back to sync engine
         if let Some(container) = runtime.get_container_info(container_id) {
@@ -219,11 +463,13 @@ pub async fn start_container_process(
         
         ConsoleLogger::debug(&format!("✅ [STARTUP-CREATE] Existing container {} registered successfully", container_id));
     }
+    drop(rootfs_permit);
     
     ConsoleLogger::debug(&format!("⏱️ [STARTUP-CREATE] Container creation/restart phase completed for {} in {:?}", 
         container_id, creation_start.elapsed()));
 
     // Step 6: Rootfs validation
+    global_operations().report(container_id, 60, "validating rootfs");
     let rootfs_start = std::time::Instant::now();
     ConsoleLogger::debug(&format!("🔍 [STARTUP-ROOTFS] Retrieving rootfs path for {}", container_id));
     
@@ -240,12 +486,10 @@ pub async fn start_container_process(
         container_id, rootfs_start.elapsed()));
     
     // Step 7: Network setup preparation
+    global_operations().report(container_id, 65, "preparing network");
     let network_prep_start = std::time::Instant::now();
     ConsoleLogger::debug(&format!("🌐 [STARTUP-NETWORK] Checking network requirements for {}", container_id));
     
-    // Check if network setup is needed BEFORE starting container
-    let needs_network_setup = sync_engine.should_setup_network(container_id).await.unwrap_or(false);
-    
     // Network ready signal should be written to container's filesystem
     let network_ready_path = format!("{}/tmp/quilt-network-ready-{}", actual_rootfs_path, container_id);
     
@@ -280,12 +524,26 @@ pub async fn start_container_process(
     ConsoleLogger::debug(&format!("⏱️ [STARTUP-NETWORK] Network preparation completed for {} in {:?}", 
         container_id, network_prep_start.elapsed()));
     
+    if !setup_commands.is_empty() {
+        super::setup_steps::prepare(sync_engine, container_id, &actual_rootfs_path, &setup_commands).await?;
+    }
+    
     // Step 8: Start the container process
+    global_operations().report(container_id, 75, "starting process");
     let start_process_time = std::time::Instant::now();
     ConsoleLogger::info(&format!("🚀 [STARTUP-START] Starting container process for {}", container_id));
     
-    // Start the container
-    match runtime.start_container(container_id, None) {
+    // Start the container: namespace/process spawn plus readiness verification, bounded by the spawn stage
+    let start_result = {
+        let _spawn_permit = pipeline.enter_with_priority(StartupStage::Spawn, container_id, priority_class).await?;
+        super::priority::admit(sync_engine, &runtime, container_id, priority_class).await?;
+        let start_runtime = Arc::clone(&runtime);
+        let start_id = container_id.to_string();
+        tokio::task::spawn_blocking(move || start_runtime.start_container(&start_id, None))
+            .await
+            .unwrap_or_else(|e| Err(format!("Container start task failed: {}", e)))
+    };
+    match start_result {
         Ok(()) => {
             ConsoleLogger::success(&format!("✅ [STARTUP-START] Container process started successfully for {} in {:?}", 
                 container_id, start_process_time.elapsed()));
@@ -313,6 +571,36 @@ pub async fn start_container_process(
                     ConsoleLogger::debug(&format!("⏱️ [STARTUP-PID] PID handling completed for {} in {:?}", 
                         container_id, pid_start.elapsed()));
                     
+                    // Step 9.2: Follow the setup commands the container runs before its command
+                    if !setup_commands.is_empty() {
+                        tokio::spawn(super::setup_steps::follow(
+                            sync_engine.clone(),
+                            container_id.to_string(),
+                            actual_rootfs_path.clone(),
+                            pid.as_raw(),
+                            setup_commands.len(),
+                        ));
+                    }
+                    
+                    // Step 9.5: Pre-mount declared FUSE filesystems inside the container
+                    if allow_fuse {
+                        let namespace_manager = crate::daemon::namespace::NamespaceManager::new();
+                        for fuse_mount in &fuse_mounts {
+                            match namespace_manager.mount_fuse_in_process(pid, fuse_mount) {
+                                Ok(()) => {
+                                    let _ = sync_engine.store_container_log(container_id, "info",
+                                        &format!("FUSE filesystem {} mounted at {}", fuse_mount.source, fuse_mount.target)).await;
+                                }
+                                Err(e) => {
+                                    ConsoleLogger::warning(&format!("⚠️ [STARTUP-FUSE] Failed to mount {} at {} for {}: {}",
+                                        fuse_mount.source, fuse_mount.target, container_id, e));
+                                    let _ = sync_engine.store_container_log(container_id, "warn",
+                                        &format!("FUSE mount of {} at {} failed: {}", fuse_mount.source, fuse_mount.target, e)).await;
+                                }
+                            }
+                        }
+                    }
+                    
                     // Step 10: Schedule background network setup (if needed) - NON-BLOCKING
                     if needs_network_setup {
                         ConsoleLogger::info(&format!("🌐 [STARTUP-NET] Scheduling background network setup for container {} (PID: {})", 
@@ -324,6 +612,9 @@ pub async fn start_container_process(
                         let bg_network_manager = Arc::clone(&network_manager);
                         let bg_pid = pid.as_raw();
                         let bg_actual_rootfs_path = actual_rootfs_path.clone();
+                        let bg_network_plugin = network_plugin.clone();
+                        let bg_egress_proxy = egress_proxy.clone().filter(|proxy| proxy.transparent);
+                        let bg_labels = labels.clone();
                         
                         // Emit network setup started event
                         
@@ -334,7 +625,15 @@ pub async fn start_container_process(
                             ConsoleLogger::info(&format!("📡 [BACKGROUND-NET] Starting background network setup for {} (PID: {})", 
                                 bg_container_id, bg_pid));
                             
-                            // Background network setup - this runs in parallel with other containers
+                            // Background network setup - this runs in parallel with other containers,
+                            // up to the network stage limit
+                            let _network_permit = match StartupPipeline::global().enter_with_priority(StartupStage::Network, &bg_container_id, priority_class).await {
+                                Ok(permit) => permit,
+                                Err(e) => {
+                                    ConsoleLogger::error(&format!("❌ [BACKGROUND-NET] {}", e));
+                                    return;
+                                }
+                            };
                             let setup_result: Result<crate::sync::network::NetworkAllocation, String> = setup_container_network_async(
                                 &bg_sync_engine,
                                 &bg_network_manager, 
@@ -352,6 +651,18 @@ pub async fn start_container_process(
                                     let _ = bg_sync_engine.store_container_log(&bg_container_id, "info", 
                                         &format!("Network setup completed with IP {}", network_alloc.ip_address)).await;
                                     
+                                    // The new address may be a peer in other containers' policies
+                                    crate::engine::network_policy::reapply_after_start(
+                                        &bg_sync_engine, &bg_network_manager.config.bridge_ip, &bg_container_id).await;
+                                    
+                                    if let Some(plugin) = &bg_network_plugin {
+                                        attach_network_plugin(&bg_sync_engine, plugin, &bg_container_id, bg_pid, &network_alloc.ip_address, bg_labels).await;
+                                    }
+                                    
+                                    if let Some(proxy) = &bg_egress_proxy {
+                                        super::egress_proxy::redirect(&bg_sync_engine, &bg_network_manager, &bg_container_id, &network_alloc.ip_address, proxy).await;
+                                    }
+                                    
                                     // Emit network setup completed event
                                 }
                                 Err(e) => {
@@ -367,6 +678,9 @@ pub async fn start_container_process(
                         });
                         
                         ConsoleLogger::success(&format!("✅ [STARTUP-NET] Background network setup scheduled for {} - container startup continues", container_id));
+                    } else if let Some(plugin) = &network_plugin {
+                        // No bridge address; the plugin provides all of the container's networking
+                        attach_network_plugin(sync_engine, plugin, container_id, pid.as_raw(), "", labels).await;
                     }
                 } else {
                     ConsoleLogger::error(&format!("❌ [STARTUP-PID] Container {} started but has no PID!", container_id));
@@ -375,6 +689,19 @@ pub async fn start_container_process(
                 ConsoleLogger::error(&format!("❌ [STARTUP-PID] Container {} has no info after starting", container_id));
             }
             
+            // Step 10.5: Readiness gates - the container is only Running once all of them passed;
+            // a failure rolls the start back
+            if let Some(readiness) = readiness.as_ref().filter(|readiness| !readiness.gates.is_empty()) {
+                global_operations().report(container_id, 90, "waiting for readiness gates");
+                let pid = runtime.get_container_info(container_id).and_then(|container| container.pid)
+                    .ok_or_else(|| format!("Container {} has no PID to check readiness against", container_id))?;
+                super::readiness::wait_for_gates(sync_engine, &runtime, container_id, pid, &actual_rootfs_path, host_network, readiness).await
+                    .map_err(|e| {
+                        ConsoleLogger::error(&format!("❌ [READINESS] Container {} not ready: {}", container_id, e));
+                        format!("Container not ready: {}", e)
+                    })?;
+            }
+            
             // Step 11: Final state transition to Running
             let final_state_start = std::time::Instant::now();
             ConsoleLogger::info(&format!("🏁 [STARTUP-FINAL] Transitioning container {} to Running state", container_id));
@@ -399,7 +726,9 @@ pub async fn start_container_process(
                 &format!("Container startup completed successfully in {:.2}s", total_time.as_secs_f64())).await;
             
             // Emit container ready event with timing
-            let _startup_time_ms = total_time.as_millis() as u64;
+            let mut attributes = HashMap::new();
+            attributes.insert("startup_ms".to_string(), total_time.as_millis().to_string());
+            global_event_buffer().emit(EventType::Started, container_id, Some(attributes));
             
             ConsoleLogger::debug(&format!("📡 [STARTUP-SUCCESS] Container ready event emitted for {}", container_id));
             
@@ -425,6 +754,20 @@ pub async fn start_container_process(
     }
 }
 
+/// Hand the container to its network driver plugin; a failure is logged and leaves the container
+/// with quilt's own networking
+async fn attach_network_plugin(sync_engine: &SyncEngine, plugin: &str, container_id: &str, pid: i32, ip_address: &str, labels: HashMap<String, String>) {
+    match super::plugins::attach_network(plugin, container_id, pid, ip_address, labels).await {
+        Ok(()) => {
+            let _ = sync_engine.store_container_log(container_id, "info", &format!("Network plugin {} attached", plugin)).await;
+        }
+        Err(e) => {
+            ConsoleLogger::warning(&format!("🔌 [PLUGINS] {}", e));
+            let _ = sync_engine.store_container_log(container_id, "warn", &format!("Network plugin attach failed: {}", e)).await;
+        }
+    }
+}
+
 /// Background async network setup function for parallel container networking
 /// This function handles all network setup operations in the background without blocking container startup
 async fn setup_container_network_async(
@@ -456,9 +799,11 @@ async fn setup_container_network_async(
         None
     };
     
-    // Create ContainerNetworkConfig for ICC network manager
-    let veth_host_name = format!("veth-{}", &container_id[..8]);
-    let veth_container_name = format!("vethc-{}", &container_id[..8]);
+    // Create ContainerNetworkConfig for ICC network manager; warm namespaces come with their own veth pair
+    let (veth_host_name, veth_container_name) = match network_manager.pooled_netns(container_id) {
+        Some(slot) => (slot.veth_host_name, slot.veth_container_name),
+        None => (format!("veth-{}", &container_id[..8]), format!("vethc-{}", &container_id[..8])),
+    };
     
     ConsoleLogger::debug(&format!("🔗 [ASYNC-NET] Creating network config for {}: veth_host={}, veth_container={}", 
         container_id, veth_host_name, veth_container_name));
@@ -492,12 +837,25 @@ async fn setup_container_network_async(
     // Setup container network using ICC network manager (lock-free, parallel-safe)
     ConsoleLogger::debug(&format!("🔧 [ASYNC-NET] Setting up container network for {} (PID: {})", 
         container_id, container_pid));
-    network_manager.setup_container_network(&icc_network_config, container_pid)
+    // Interface plumbing is a short sequence of synchronous netlink/ip calls; keep it off the async workers
+    let setup_manager = Arc::clone(network_manager);
+    let setup_config = icc_network_config.clone();
+    tokio::task::spawn_blocking(move || setup_manager.setup_container_network(&setup_config, container_pid))
+        .await
+        .map_err(|e| format!("Network setup task failed: {}", e))
+        .and_then(|result| result)
         .map_err(|e| {
             ConsoleLogger::error(&format!("❌ [ASYNC-NET] Network setup failed for {}: {}", container_id, e));
             e
         })?;
     
+    // Connectivity diagnostics and readiness probes run on the async command executor
+    network_manager.verify_container_network(&icc_network_config, container_pid).await
+        .map_err(|e| {
+            ConsoleLogger::error(&format!("❌ [ASYNC-NET] Network verification failed for {}: {}", container_id, e));
+            e
+        })?;
+    
     ConsoleLogger::success(&format!("✅ [ASYNC-NET] Container network setup succeeded for {}", container_id));
     
     // Mark network setup complete in sync engine
@@ -523,12 +881,28 @@ async fn setup_container_network_async(
     
     ConsoleLogger::debug(&format!("🌐 [ASYNC-NET] DNS name for {}: {}", container_id, container_name));
     
-    network_manager.register_container_dns(container_id, &container_name, &network_alloc.ip_address)
+    let dns_wildcard = sync_engine.get_container_config(container_id).await
+        .map(|config| config.dns_wildcard)
+        .unwrap_or(false);
+    network_manager.register_container_dns(container_id, &container_name, &network_alloc.ip_address, dns_wildcard)
         .map_err(|e| {
             ConsoleLogger::error(&format!("❌ [ASYNC-NET] DNS registration failed for {}: {}", container_id, e));
             e
         })?;
     
+    // Publish host ports allocated at create time
+    let port_bindings = sync_engine.get_port_bindings(container_id).await
+        .map_err(|e| format!("Failed to load port bindings: {}", e))?;
+    if !port_bindings.is_empty() {
+        ConsoleLogger::debug(&format!("🔌 [ASYNC-NET] Publishing {} ports for {}", port_bindings.len(), container_id));
+        let mappings: Vec<_> = port_bindings.iter().map(|binding| binding.to_mapping()).collect();
+        network_manager.publish_container_ports(container_id, &network_alloc.ip_address, &mappings)
+            .map_err(|e| {
+                ConsoleLogger::error(&format!("❌ [ASYNC-NET] Port publishing failed for {}: {}", container_id, e));
+                e
+            })?;
+    }
+    
     ConsoleLogger::success(&format!("🎉 [ASYNC-NET] All network setup operations complete for {} with IP {}", 
         container_id, network_alloc.ip_address));
     
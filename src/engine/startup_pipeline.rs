@@ -0,0 +1,251 @@
+// Startup pipeline for container creation
+// Splits the start path into stages with independent parallelism limits so a burst of starts
+// overlaps rootfs extraction, process spawn and network attach instead of contending on one.
+// Starts queued for a stage are let in highest priority class first, then in arrival order.
+// Netlink work is batched per container only: the in-namespace rename, address and link-up run
+// as one `ip -batch` (VethManager::container_interface_batch). Batching across containers, or
+// the host-side veth creation and bridge attach, is out of scope: each start reaches the network
+// stage on its own schedule, and the bridge attach is verified and retried per veth, so a shared
+// batch would have to wait for stragglers and could not retry a single failed attach.
+
+use crate::daemon::priority::PriorityClass;
+use crate::utils::console::ConsoleLogger;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Stages of the container start path, each with its own concurrency budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupStage {
+    /// Image extraction or registration of an existing rootfs (disk bound)
+    Rootfs,
+    /// Namespace/process creation and readiness verification (CPU bound)
+    Spawn,
+    /// veth, bridge, DNS and port publishing (netlink/iptables bound)
+    Network,
+}
+
+impl StartupStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StartupStage::Rootfs => "rootfs",
+            StartupStage::Spawn => "spawn",
+            StartupStage::Network => "network",
+        }
+    }
+}
+
+pub struct StartupPipeline {
+    rootfs: StageGate,
+    spawn: StageGate,
+    network: StageGate,
+}
+
+static STARTUP_PIPELINE: once_cell::sync::Lazy<StartupPipeline> =
+    once_cell::sync::Lazy::new(StartupPipeline::with_host_defaults);
+
+/// Counting semaphore whose waiters are served by priority class, then first come first served
+struct StageGate {
+    state: Mutex<GateState>,
+}
+
+struct GateState {
+    available: usize,
+    waiters: BinaryHeap<Waiter>,
+    arrivals: u64,
+}
+
+struct Waiter {
+    class: PriorityClass,
+    arrival: u64,
+    slot: oneshot::Sender<()>,
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap pops the greatest: the higher class, then the earlier arrival
+        self.class.cmp(&other.class).then(other.arrival.cmp(&self.arrival))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+/// A slot in a stage, handed to the next waiter when dropped
+pub struct StagePermit<'a> {
+    gate: &'a StageGate,
+}
+
+impl Drop for StagePermit<'_> {
+    fn drop(&mut self) {
+        self.gate.release();
+    }
+}
+
+/// A queued entry; a start that gives up after being handed a slot passes it on
+struct PendingSlot<'a> {
+    gate: &'a StageGate,
+    slot: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PendingSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(mut slot) = self.slot.take() {
+            slot.close();
+            if slot.try_recv().is_ok() {
+                self.gate.release();
+            }
+        }
+    }
+}
+
+impl StageGate {
+    fn new(slots: usize) -> Self {
+        Self { state: Mutex::new(GateState { available: slots.max(1), waiters: BinaryHeap::new(), arrivals: 0 }) }
+    }
+
+    fn available_permits(&self) -> usize {
+        self.state.lock().map(|state| state.available).unwrap_or(0)
+    }
+
+    async fn acquire(&self, class: PriorityClass) -> Result<StagePermit<'_>, String> {
+        let slot = {
+            let mut state = self.state.lock().map_err(|_| "Startup pipeline lock poisoned".to_string())?;
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return Ok(StagePermit { gate: self });
+            }
+            let (sender, slot) = oneshot::channel();
+            state.arrivals += 1;
+            let arrival = state.arrivals;
+            state.waiters.push(Waiter { class, arrival, slot: sender });
+            slot
+        };
+        let mut pending = PendingSlot { gate: self, slot: Some(slot) };
+        let granted = match pending.slot.as_mut() {
+            Some(slot) => slot.await.is_ok(),
+            None => false,
+        };
+        pending.slot = None;
+        if granted { Ok(StagePermit { gate: self }) } else { Err("Startup pipeline closed".to_string()) }
+    }
+
+    /// Hand the slot to the best waiter still waiting, else return it to the pool
+    fn release(&self) {
+        let Ok(mut state) = self.state.lock() else { return };
+        while let Some(waiter) = state.waiters.pop() {
+            if waiter.slot.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+}
+
+impl StartupPipeline {
+    pub fn new(rootfs: usize, spawn: usize, network: usize) -> Self {
+        Self {
+            rootfs: StageGate::new(rootfs),
+            spawn: StageGate::new(spawn),
+            network: StageGate::new(network),
+        }
+    }
+
+    /// Shared pipeline used by every start request
+    pub fn global() -> &'static StartupPipeline {
+        &STARTUP_PIPELINE
+    }
+
+    /// Size stages from the host CPU count: extraction competes for disk, spawning for CPU,
+    /// and network attach is mostly waiting on short netlink calls so it tolerates more overlap
+    fn with_host_defaults() -> Self {
+        let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        Self::new((cpus / 2).clamp(2, 8), cpus.clamp(2, 16), (cpus * 2).clamp(4, 32))
+    }
+
+    fn gate(&self, stage: StartupStage) -> &StageGate {
+        match stage {
+            StartupStage::Rootfs => &self.rootfs,
+            StartupStage::Spawn => &self.spawn,
+            StartupStage::Network => &self.network,
+        }
+    }
+
+    /// Wait for a slot in `stage` as a normal-priority start
+    pub async fn enter(&self, stage: StartupStage, container_id: &str) -> Result<StagePermit<'_>, String> {
+        self.enter_with_priority(stage, container_id, PriorityClass::Normal).await
+    }
+
+    /// Wait for a slot in `stage`, ahead of queued starts of lower classes; the slot is released
+    /// when the permit is dropped
+    pub async fn enter_with_priority(&self, stage: StartupStage, container_id: &str, class: PriorityClass) -> Result<StagePermit<'_>, String> {
+        let gate = self.gate(stage);
+        if gate.available_permits() == 0 {
+            ConsoleLogger::debug(&format!("⏳ [PIPELINE] {} ({}) queued for {} stage", container_id, class.as_str(), stage.as_str()));
+        }
+        let waited = std::time::Instant::now();
+        let permit = gate.acquire(class).await?;
+        ConsoleLogger::debug(&format!("▶️ [PIPELINE] {} entered {} stage after {:?}", container_id, stage.as_str(), waited.elapsed()));
+        Ok(permit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_stage_limits_are_independent() {
+        let pipeline = StartupPipeline::new(1, 1, 1);
+        let _rootfs = pipeline.enter(StartupStage::Rootfs, "a").await.unwrap();
+
+        // A second rootfs entry waits, but other stages are unaffected
+        let blocked = tokio::time::timeout(Duration::from_millis(50), pipeline.enter(StartupStage::Rootfs, "b")).await;
+        assert!(blocked.is_err());
+        assert!(pipeline.enter(StartupStage::Spawn, "b").await.is_ok());
+        assert!(pipeline.enter(StartupStage::Network, "b").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_queued_starts_by_priority() {
+        let pipeline = std::sync::Arc::new(StartupPipeline::new(1, 1, 1));
+        let first = pipeline.enter(StartupStage::Spawn, "a").await.unwrap();
+        let (order, mut entered) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut queued = Vec::new();
+        for (id, class) in [("batch", PriorityClass::BestEffort), ("web", PriorityClass::Normal), ("db", PriorityClass::Critical)] {
+            let (pipeline, order) = (pipeline.clone(), order.clone());
+            queued.push(tokio::spawn(async move {
+                let _permit = pipeline.enter_with_priority(StartupStage::Spawn, id, class).await.unwrap();
+                order.send(id).unwrap();
+            }));
+            // Let each one queue before the next arrives
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // A start that gives up does not hold on to its place
+        let abandoned = tokio::time::timeout(Duration::from_millis(20),
+            pipeline.enter_with_priority(StartupStage::Spawn, "impatient", PriorityClass::Critical)).await;
+        assert!(abandoned.is_err());
+
+        drop(first);
+        for task in queued {
+            task.await.unwrap();
+        }
+        let order: Vec<_> = std::iter::from_fn(|| entered.try_recv().ok()).collect();
+        assert_eq!(order, vec!["db", "web", "batch"]);
+        assert_eq!(pipeline.spawn.available_permits(), 1);
+    }
+}
@@ -0,0 +1,123 @@
+// Virtual service load balancing
+// A virtual service is a DNS name backed by every running container that carries its selector
+// labels. The embedded DNS server answers with all backend addresses, rotating their order on
+// each query; this loop keeps the backend sets current so stopped or failed containers drop out
+// and new replicas join without any registration step.
+
+use crate::icc::network::NetworkManager;
+use crate::sync::events::matches_selector;
+use crate::sync::error::SyncResult;
+use crate::sync::{ContainerState, SyncEngine};
+use crate::utils::console::ConsoleLogger;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time between backend refreshes; with the 5s answer TTL, a dead backend stops receiving new
+/// clients within about ten seconds
+pub const VIRTUAL_SERVICE_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A running container currently serving a virtual service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceBackend {
+    pub container_id: String,
+    pub ip_address: String,
+}
+
+/// Run [`refresh_virtual_services`] every `interval` until the runtime shuts down
+pub fn spawn_virtual_service_refresher(sync_engine: Arc<SyncEngine>, network_manager: Arc<NetworkManager>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(e) = refresh_virtual_services(&sync_engine, &network_manager).await {
+                ConsoleLogger::debug(&format!("⚖️ [VSERVICE] Refresh skipped: {}", e));
+            }
+        }
+    });
+}
+
+/// Push the current backends of every virtual service to the DNS server
+pub async fn refresh_virtual_services(sync_engine: &SyncEngine, network_manager: &NetworkManager) -> Result<(), String> {
+    let services = sync_engine.list_virtual_services().await.map_err(|e| e.to_string())?;
+    if services.is_empty() {
+        return Ok(());
+    }
+    let labels = sync_engine.list_container_labels().await.map_err(|e| e.to_string())?;
+    let running = running_addresses(sync_engine).await.map_err(|e| e.to_string())?;
+
+    for service in services {
+        let addresses = select_backends(&service.selector, &labels, &running).into_iter()
+            .filter_map(|backend| backend.ip_address.parse().ok())
+            .collect();
+        network_manager.set_virtual_service_backends(&service.name, addresses)?;
+    }
+    Ok(())
+}
+
+/// Running containers carrying every label of `selector`
+pub async fn backends(sync_engine: &SyncEngine, selector: &HashMap<String, String>) -> SyncResult<Vec<ServiceBackend>> {
+    let labels = sync_engine.list_container_labels().await?;
+    let running = running_addresses(sync_engine).await?;
+    Ok(select_backends(selector, &labels, &running))
+}
+
+/// Container ID to bridge address of every running container with a network
+async fn running_addresses(sync_engine: &SyncEngine) -> SyncResult<HashMap<String, String>> {
+    Ok(sync_engine.list_containers(Some(ContainerState::Running)).await?
+        .into_iter()
+        .filter_map(|container| Some((container.id, container.ip_address?)))
+        .collect())
+}
+
+/// Backends ordered by container ID, so rotation on the DNS side is the only reordering
+fn select_backends(
+    selector: &HashMap<String, String>,
+    labels: &HashMap<String, HashMap<String, String>>,
+    running: &HashMap<String, String>,
+) -> Vec<ServiceBackend> {
+    let mut backends: Vec<ServiceBackend> = running.iter()
+        .filter(|(id, _)| labels.get(*id).map_or(false, |container_labels| matches_selector(container_labels, selector)))
+        .map(|(id, ip)| ServiceBackend { container_id: id.clone(), ip_address: ip.clone() })
+        .collect();
+    backends.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+    backends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_backends() {
+        let label = |app: &str, tier: &str| HashMap::from([
+            ("app".to_string(), app.to_string()),
+            ("tier".to_string(), tier.to_string()),
+        ]);
+        let labels = HashMap::from([
+            ("c1".to_string(), label("web", "frontend")),
+            ("c2".to_string(), label("web", "canary")),
+            ("c3".to_string(), label("web", "frontend")),
+            ("c4".to_string(), label("db", "backend")),
+        ]);
+        // c3 is stopped, so it has no running address
+        let running = HashMap::from([
+            ("c2".to_string(), "10.42.0.6".to_string()),
+            ("c1".to_string(), "10.42.0.5".to_string()),
+            ("c4".to_string(), "10.42.0.8".to_string()),
+        ]);
+
+        let web = HashMap::from([("app".to_string(), "web".to_string())]);
+        let ids: Vec<String> = select_backends(&web, &labels, &running).into_iter().map(|b| b.container_id).collect();
+        assert_eq!(ids, vec!["c1", "c2"]);
+
+        let frontend = label("web", "frontend");
+        assert_eq!(select_backends(&frontend, &labels, &running), vec![ServiceBackend {
+            container_id: "c1".to_string(),
+            ip_address: "10.42.0.5".to_string(),
+        }]);
+    }
+}
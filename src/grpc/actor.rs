@@ -0,0 +1,74 @@
+// Caller attribution for API actions
+// Identifies who issued a request so the events and container log rows it produces can be
+// traced back to a client instead of just "something stopped the container"
+
+use crate::utils::name_policy::TOKEN_HEADER;
+use tonic::Request;
+
+/// Optional header naming the caller (user, service account or token name)
+pub const ACTOR_HEADER: &str = "x-quilt-actor";
+
+/// Longest caller name kept from the header
+const MAX_ACTOR_LEN: usize = 64;
+
+/// Actor for a request: `<name>@<peer>` when the client names itself, otherwise the peer
+/// address, or `api` when neither is known (e.g. in-process calls)
+pub fn request_actor<T>(request: &Request<T>) -> String {
+    let name = request.metadata().get(ACTOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(sanitize_actor)
+        .filter(|name| !name.is_empty());
+    let peer = request.remote_addr().map(|addr| addr.to_string());
+
+    match (name, peer) {
+        (Some(name), Some(peer)) => format!("{}@{}", name, peer),
+        (Some(name), None) => name,
+        (None, Some(peer)) => peer,
+        (None, None) => "api".to_string(),
+    }
+}
+
+/// Token the caller presented, if any
+pub fn request_token<T>(request: &Request<T>) -> Option<String> {
+    request.metadata().get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Keep printable, non-space characters so a caller cannot forge log formatting
+fn sanitize_actor(value: &str) -> String {
+    value.trim()
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(MAX_ACTOR_LEN)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_actor() {
+        let request = Request::new(());
+        assert_eq!(request_actor(&request), "api");
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(ACTOR_HEADER, " ci-bot ".parse().unwrap());
+        assert_eq!(request_actor(&request), "ci-bot");
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(ACTOR_HEADER, "alice\tadmin".parse().unwrap());
+        assert_eq!(request_actor(&request), "aliceadmin");
+    }
+
+    #[test]
+    fn test_request_token() {
+        assert_eq!(request_token(&Request::new(())), None);
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(TOKEN_HEADER, " s3cret ".parse().unwrap());
+        assert_eq!(request_token(&request).as_deref(), Some("s3cret"));
+    }
+}
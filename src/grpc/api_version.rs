@@ -0,0 +1,106 @@
+// API version handshake for the gRPC server
+// Rejects clients older than the minimum supported API version and stamps every response
+// with the daemon's version so newer clients can degrade against an older daemon
+
+use crate::utils::api_version::{API_VERSION, API_VERSION_HEADER};
+use crate::utils::console::ConsoleLogger;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Oldest client API version the daemon still serves
+pub const MIN_SUPPORTED_API_VERSION: u32 = 1;
+
+/// Parse a version header value; anything but a plain number is treated as absent
+pub fn parse_api_version(value: &str) -> Option<u32> {
+    value.trim().parse().ok()
+}
+
+/// Whether a client speaking `client_version` can be served. Newer clients are accepted and
+/// are expected to degrade based on the daemon's advertised version.
+pub fn is_supported(client_version: u32) -> bool {
+    client_version >= MIN_SUPPORTED_API_VERSION
+}
+
+/// Tower layer performing the `x-quilt-api-version` handshake. Requests without the header
+/// (clients that predate versioning, grpcurl, health probes) are always served.
+#[derive(Clone, Default)]
+pub struct ApiVersionLayer;
+
+impl<S> Layer<S> for ApiVersionLayer {
+    type Service = ApiVersion<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiVersion { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiVersion<S> {
+    inner: S,
+}
+
+/// Client version declared on a request, if any
+fn client_api_version<B>(request: &http::Request<B>) -> Option<u32> {
+    request.headers().get(API_VERSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_api_version)
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for ApiVersion<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let mut response = match client_api_version(&request) {
+                Some(version) if !is_supported(version) => {
+                    ConsoleLogger::warning(&format!("🔢 [API-VERSION] Rejecting {} from client API version {} (minimum {})",
+                        request.uri().path(), version, MIN_SUPPORTED_API_VERSION));
+                    Status::failed_precondition(format!(
+                        "Client API version {} is no longer supported; this daemon requires {} or newer (current {})",
+                        version, MIN_SUPPORTED_API_VERSION, API_VERSION)).to_http()
+                }
+                _ => inner.call(request).await?,
+            };
+            response.headers_mut().insert(API_VERSION_HEADER, http::HeaderValue::from(API_VERSION));
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_api_version_header() {
+        let request = http::Request::builder().body(()).unwrap();
+        assert_eq!(client_api_version(&request), None);
+
+        let request = http::Request::builder().header(API_VERSION_HEADER, "1").body(()).unwrap();
+        assert_eq!(client_api_version(&request), Some(1));
+
+        let request = http::Request::builder().header(API_VERSION_HEADER, "v1").body(()).unwrap();
+        assert_eq!(client_api_version(&request), None);
+        assert!(!is_supported(MIN_SUPPORTED_API_VERSION - 1));
+        assert!(is_supported(API_VERSION + 1));
+    }
+}
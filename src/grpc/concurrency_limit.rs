@@ -0,0 +1,280 @@
+// Per-method concurrency limits for the gRPC server
+// A tower layer that caps in-flight calls per RPC and bounds how many may wait for a slot;
+// calls beyond the queue depth are rejected with RESOURCE_EXHAUSTED instead of piling onto
+// the sync engine and network setup
+
+use crate::utils::console::ConsoleLogger;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Environment variable overriding limits as `<Method>=<max_in_flight>:<max_queued>` pairs separated
+/// by commas; `*` gives every method without its own entry a limit of that size of its own
+pub const GRPC_LIMITS_ENV: &str = "QUILT_GRPC_LIMITS";
+
+/// Built-in limits for the calls that fan out into extraction, namespace and network work
+const DEFAULT_LIMITS: &[(&str, usize, usize)] = &[
+    ("CreateContainer", 32, 128),
+    ("StartContainer", 32, 128),
+    ("ExecContainer", 64, 256),
+    ("ExecContainerStream", 64, 256),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodLimit {
+    pub max_in_flight: usize,
+    pub max_queued: usize,
+}
+
+struct MethodGate {
+    limit: MethodLimit,
+    slots: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl MethodGate {
+    fn new(limit: MethodLimit) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(limit.max_in_flight.max(1))),
+            queued: AtomicUsize::new(0),
+            limit,
+        }
+    }
+
+    /// No call holds or waits for a slot
+    fn is_idle(&self) -> bool {
+        self.queued.load(Ordering::Acquire) == 0 && self.slots.available_permits() == self.limit.max_in_flight.max(1)
+    }
+}
+
+/// A place in a gate's queue, given back when the wait ends or the waiting call is dropped
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Limits keyed by RPC method name (the last segment of the request path)
+pub struct MethodLimits {
+    gates: HashMap<String, Arc<MethodGate>>,
+    /// The `*` limit, applied to each unlisted method separately
+    fallback: Option<MethodLimit>,
+    fallback_gates: Mutex<HashMap<String, Arc<MethodGate>>>,
+}
+
+impl MethodLimits {
+    pub fn new(limits: HashMap<String, MethodLimit>) -> Self {
+        let mut gates = HashMap::new();
+        let mut fallback = None;
+        for (method, limit) in limits {
+            if method == "*" {
+                fallback = Some(limit);
+            } else {
+                gates.insert(method, Arc::new(MethodGate::new(limit)));
+            }
+        }
+        Self { gates, fallback, fallback_gates: Mutex::new(HashMap::new()) }
+    }
+
+    /// Built-in defaults with any `QUILT_GRPC_LIMITS` entries applied on top
+    pub fn from_env() -> Self {
+        let mut limits: HashMap<String, MethodLimit> = DEFAULT_LIMITS.iter()
+            .map(|(method, max_in_flight, max_queued)| {
+                (method.to_string(), MethodLimit { max_in_flight: *max_in_flight, max_queued: *max_queued })
+            })
+            .collect();
+        if let Ok(value) = std::env::var(GRPC_LIMITS_ENV) {
+            limits.extend(Self::parse(&value));
+        }
+        Self::new(limits)
+    }
+
+    /// Parse `CreateContainer=16:64,*=128:512`; malformed entries are skipped with a warning
+    pub fn parse(value: &str) -> HashMap<String, MethodLimit> {
+        let mut limits = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(method, spec)| {
+                let (in_flight, queued) = spec.split_once(':')?;
+                let limit = MethodLimit {
+                    max_in_flight: in_flight.trim().parse().ok()?,
+                    max_queued: queued.trim().parse().ok()?,
+                };
+                Some((method.trim().to_string(), limit))
+            });
+            match parsed {
+                Some((method, limit)) if !method.is_empty() && limit.max_in_flight > 0 => {
+                    limits.insert(method, limit);
+                }
+                _ => ConsoleLogger::warning(&format!("⚠️ [GRPC-LIMIT] Ignoring malformed {} entry: {}", GRPC_LIMITS_ENV, entry)),
+            }
+        }
+        limits
+    }
+
+    fn gate(&self, method: &str) -> Option<Arc<MethodGate>> {
+        if let Some(gate) = self.gates.get(method) {
+            return Some(gate.clone());
+        }
+        let limit = self.fallback?;
+        let mut fallback_gates = self.fallback_gates.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !fallback_gates.contains_key(method) {
+            // Request paths come from clients, so gates nobody uses are dropped rather than kept
+            // for every path ever seen
+            fallback_gates.retain(|_, gate| Arc::strong_count(gate) > 1 || !gate.is_idle());
+        }
+        Some(fallback_gates.entry(method.to_string())
+            .or_insert_with(|| Arc::new(MethodGate::new(limit)))
+            .clone())
+    }
+
+    /// Wait for an in-flight slot for `method`. Unlimited methods get `None`; a full queue is
+    /// reported as RESOURCE_EXHAUSTED.
+    pub async fn admit(&self, method: &str) -> Result<Option<OwnedSemaphorePermit>, Status> {
+        let Some(gate) = self.gate(method) else { return Ok(None) };
+
+        if let Ok(permit) = gate.slots.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+
+        let queued = gate.queued.fetch_add(1, Ordering::AcqRel);
+        let _queue_slot = QueueSlot(&gate.queued);
+        if queued >= gate.limit.max_queued {
+            ConsoleLogger::warning(&format!("🚦 [GRPC-LIMIT] Rejecting {}: {} in flight and {} queued",
+                method, gate.limit.max_in_flight, queued));
+            return Err(Status::resource_exhausted(format!(
+                "{} is at capacity ({} in flight, {} queued); retry later",
+                method, gate.limit.max_in_flight, queued)));
+        }
+
+        let permit = gate.slots.clone().acquire_owned().await;
+        permit.map(Some).map_err(|_| Status::unavailable("Server is shutting down"))
+    }
+}
+
+/// Tower layer applying [`MethodLimits`] in front of the gRPC routes. A slot is held until the
+/// handler returns its response, so for streaming RPCs it covers setup rather than the whole stream.
+#[derive(Clone)]
+pub struct ConcurrencyLimitLayer {
+    limits: Arc<MethodLimits>,
+}
+
+impl ConcurrencyLimitLayer {
+    pub fn new(limits: MethodLimits) -> Self {
+        Self { limits: Arc::new(limits) }
+    }
+}
+
+impl<S> Layer<S> for ConcurrencyLimitLayer {
+    type Service = ConcurrencyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConcurrencyLimit { inner, limits: self.limits.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    limits: Arc<MethodLimits>,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for ConcurrencyLimit<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        // The clone has not been driven to readiness; keep the ready service for this call
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = self.limits.clone();
+
+        Box::pin(async move {
+            let method = request.uri().path().rsplit('/').next().unwrap_or_default().to_string();
+            let _permit = match limits.admit(&method).await {
+                Ok(permit) => permit,
+                Err(status) => return Ok(status.to_http()),
+            };
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = MethodLimits::parse("CreateContainer=4:8, *=16:32,ExecContainer=0:1,bogus,Stop=1");
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits.get("CreateContainer"), Some(&MethodLimit { max_in_flight: 4, max_queued: 8 }));
+        assert_eq!(limits.get("*"), Some(&MethodLimit { max_in_flight: 16, max_queued: 32 }));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_is_resource_exhausted() {
+        let mut config = HashMap::new();
+        config.insert("CreateContainer".to_string(), MethodLimit { max_in_flight: 1, max_queued: 0 });
+        let limits = MethodLimits::new(config);
+
+        let held = limits.admit("CreateContainer").await.unwrap();
+        assert!(held.is_some());
+        let rejected = limits.admit("CreateContainer").await.unwrap_err();
+        assert_eq!(rejected.code(), tonic::Code::ResourceExhausted);
+
+        // Unlisted methods are not limited when there is no fallback
+        assert!(limits.admit("ListContainers").await.unwrap().is_none());
+
+        drop(held);
+        assert!(limits.admit("CreateContainer").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_waiter_frees_its_queue_slot() {
+        let mut config = HashMap::new();
+        config.insert("StartContainer".to_string(), MethodLimit { max_in_flight: 1, max_queued: 1 });
+        let limits = MethodLimits::new(config);
+
+        let held = limits.admit("StartContainer").await.unwrap();
+        // A caller that gives up while queued must not keep its place
+        let waiting = tokio::time::timeout(std::time::Duration::from_millis(20), limits.admit("StartContainer")).await;
+        assert!(waiting.is_err());
+        assert_eq!(limits.gates["StartContainer"].queued.load(Ordering::Acquire), 0);
+
+        drop(held);
+        assert!(limits.admit("StartContainer").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_limits_each_method() {
+        let mut config = HashMap::new();
+        config.insert("*".to_string(), MethodLimit { max_in_flight: 1, max_queued: 0 });
+        let limits = MethodLimits::new(config);
+
+        let listing = limits.admit("ListContainers").await.unwrap();
+        assert!(listing.is_some());
+        // Another method has its own slots under the `*` limit
+        assert!(limits.admit("GetContainerStatus").await.unwrap().is_some());
+        assert_eq!(limits.admit("ListContainers").await.unwrap_err().code(), tonic::Code::ResourceExhausted);
+    }
+}
@@ -0,0 +1,68 @@
+// Client deadline handling
+// Reads the gRPC deadline a caller attached to a request so long-running work started on its
+// behalf can be bounded by it and rolled back once the caller has given up
+
+use std::time::Duration;
+use tonic::Request;
+
+/// Header carrying the caller's remaining deadline, e.g. `30S` or `1500m`
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Deadline the client attached to `request`, if any
+pub fn request_deadline<T>(request: &Request<T>) -> Option<Duration> {
+    request.metadata().get(GRPC_TIMEOUT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_grpc_timeout)
+}
+
+/// Parse the grpc-timeout wire format: up to 8 digits followed by a unit (H, M, S, m, u, n)
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// `timeout`, shortened to the caller's deadline when that is sooner
+pub fn bounded_by_deadline(timeout: Duration, deadline: Option<Duration>) -> Duration {
+    deadline.map_or(timeout, |deadline| timeout.min(deadline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::DEFAULT_STARTUP_TIMEOUT;
+
+    #[test]
+    fn test_parse_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("30S"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("1500m"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_grpc_timeout("S"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+    }
+
+    #[test]
+    fn test_request_deadline_bounds_timeout() {
+        let mut request = Request::new(());
+        assert_eq!(request_deadline(&request), None);
+        assert_eq!(bounded_by_deadline(DEFAULT_STARTUP_TIMEOUT, None), DEFAULT_STARTUP_TIMEOUT);
+
+        request.metadata_mut().insert(GRPC_TIMEOUT_HEADER, "5S".parse().unwrap());
+        let deadline = request_deadline(&request);
+        assert_eq!(deadline, Some(Duration::from_secs(5)));
+        assert_eq!(bounded_by_deadline(DEFAULT_STARTUP_TIMEOUT, deadline), Duration::from_secs(5));
+    }
+}
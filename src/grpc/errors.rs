@@ -0,0 +1,123 @@
+// Structured API errors
+// Responses carry an ErrorCode next to their free-form error_message so clients can branch on
+// the reason; RPCs that fail with a Status use the matching gRPC code and name the ErrorCode in
+// a trailer header
+
+use quilt_core::sync::error::SyncError;
+use tonic::{Code, Status};
+
+use crate::quilt::ErrorCode;
+
+/// Header naming the ErrorCode on failed RPCs, e.g. `ERROR_CODE_NOT_FOUND`
+pub const ERROR_CODE_HEADER: &str = "x-quilt-error-code";
+
+/// Code for a storage-layer error
+pub fn sync_error_code(error: &SyncError) -> ErrorCode {
+    match error {
+        SyncError::NotFound { .. } => ErrorCode::NotFound,
+        SyncError::InvalidStateTransition { .. } => ErrorCode::InvalidState,
+        SyncError::NoAvailableIp => ErrorCode::ResourceExhausted,
+        SyncError::Busy { .. } => ErrorCode::Busy,
+        SyncError::ValidationFailed { .. } => ErrorCode::InvalidArgument,
+        SyncError::Conflict { .. } => ErrorCode::Conflict,
+        SyncError::Database(_) | SyncError::CleanupFailed { .. } | SyncError::Serialization(_) | SyncError::SystemTime(_) => ErrorCode::Internal,
+    }
+}
+
+/// Code for an engine error, which reaches the API as a message; state and busy checks come
+/// first since their messages also mention names and arguments
+pub fn classify_error(message: &str) -> ErrorCode {
+    let message = message.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+
+    if message.is_empty() {
+        ErrorCode::Unspecified
+    } else if mentions(&["is busy", "in progress"]) {
+        ErrorCode::Busy
+    } else if mentions(&["state transition invalid", "not running", "already running", "in state", "stop it first", "has not completed", "has no pid", "no rootfs"]) {
+        ErrorCode::InvalidState
+    } else if mentions(&["not found", "no such", "does not exist", "not registered"]) {
+        ErrorCode::NotFound
+    } else if mentions(&["already exists", "already in use", "already registered"]) {
+        ErrorCode::Conflict
+    } else if mentions(&["timed out", "timeout", "not passed within"]) {
+        ErrorCode::Timeout
+    } else if mentions(&["no available ip", "exhausted", "admission rejected", "quota", "limit reached", "too many"]) {
+        ErrorCode::ResourceExhausted
+    } else if mentions(&["permission denied", "not allowed", "security validation"]) {
+        ErrorCode::PermissionDenied
+    } else if mentions(&["invalid", "required", "must ", "unknown", "cannot exceed", "validation failed"]) {
+        ErrorCode::InvalidArgument
+    } else if mentions(&["network", "veth", "bridge", "iptables", "dns"]) {
+        ErrorCode::NetworkSetupFailed
+    } else {
+        ErrorCode::Internal
+    }
+}
+
+/// Wire value for a response's `error_code`: unspecified when there is no error
+pub fn error_code(message: &str) -> i32 {
+    classify_error(message) as i32
+}
+
+/// gRPC status code matching an ErrorCode
+pub fn grpc_code(code: ErrorCode) -> Code {
+    match code {
+        ErrorCode::Unspecified => Code::Ok,
+        ErrorCode::NotFound => Code::NotFound,
+        ErrorCode::Conflict => Code::AlreadyExists,
+        ErrorCode::InvalidState => Code::FailedPrecondition,
+        ErrorCode::InvalidArgument => Code::InvalidArgument,
+        ErrorCode::ResourceExhausted => Code::ResourceExhausted,
+        ErrorCode::NetworkSetupFailed => Code::Unavailable,
+        ErrorCode::Busy => Code::Aborted,
+        ErrorCode::Timeout => Code::DeadlineExceeded,
+        ErrorCode::PermissionDenied => Code::PermissionDenied,
+        ErrorCode::Internal => Code::Internal,
+    }
+}
+
+/// Failed-RPC status for `code`, carrying the ErrorCode header
+pub fn error_status(code: ErrorCode, message: impl Into<String>) -> Status {
+    let mut status = Status::new(grpc_code(code), message);
+    if let Ok(value) = code.as_str_name().parse() {
+        status.metadata_mut().insert(ERROR_CODE_HEADER, value);
+    }
+    status
+}
+
+/// Failed-RPC status for an engine error message
+pub fn engine_status(message: impl Into<String>) -> Status {
+    let message = message.into();
+    error_status(classify_error(&message), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(classify_error(""), ErrorCode::Unspecified);
+        assert_eq!(classify_error("Container not found: abc"), ErrorCode::NotFound);
+        assert_eq!(classify_error("Container abc is running; stop it first or remove it with force"), ErrorCode::InvalidState);
+        assert_eq!(classify_error("Container abc is not running (state: Exited)"), ErrorCode::InvalidState);
+        assert_eq!(classify_error("Container abc is busy: stop in progress"), ErrorCode::Busy);
+        assert_eq!(classify_error("Volume 'data' already exists"), ErrorCode::Conflict);
+        assert_eq!(classify_error("No available IP addresses in range"), ErrorCode::ResourceExhausted);
+        assert_eq!(classify_error("Readiness gates not passed within 30s: tcp:80"), ErrorCode::Timeout);
+        assert_eq!(classify_error("Invalid port mapping: 99999"), ErrorCode::InvalidArgument);
+        assert_eq!(classify_error("Failed to attach veth to bridge"), ErrorCode::NetworkSetupFailed);
+        assert_eq!(classify_error("Failed to write cgroup file"), ErrorCode::Internal);
+    }
+
+    #[test]
+    fn test_error_status() {
+        let status = error_status(ErrorCode::Busy, "Container abc is busy: stop in progress");
+        assert_eq!(status.code(), Code::Aborted);
+        assert_eq!(status.metadata().get(ERROR_CODE_HEADER).unwrap(), "ERROR_CODE_BUSY");
+        assert_eq!(engine_status("Container not found: abc").code(), Code::NotFound);
+        assert_eq!(sync_error_code(&SyncError::NoAvailableIp), ErrorCode::ResourceExhausted);
+        assert_eq!(sync_error_code(&SyncError::Conflict { message: "Container with name 'web' already exists".to_string() }), ErrorCode::Conflict);
+    }
+}
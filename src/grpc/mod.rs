@@ -1,8 +1,14 @@
-pub mod container_ops;
 pub mod volume_ops;
+pub mod concurrency_limit;
+pub mod rate_limit;
+pub mod deadline;
+pub mod api_version;
+pub mod actor;
+pub mod name_policy;
+pub mod plugin_auth;
+pub mod errors;
 // monitoring_ops and helpers removed - were empty placeholder files
+// container startup orchestration lives in the library's engine module
 
 #[cfg(test)]
 pub mod tests;
-
-pub use container_ops::start_container_process;
\ No newline at end of file
@@ -0,0 +1,99 @@
+// Reserved container name prefixes
+// Operators can set aside name prefixes (e.g. `system-`) for their own containers: a name starting
+// with one is only accepted from callers that present a privileged token in the `x-quilt-token`
+// header. Prefixes and tokens come from the daemon's environment; with no prefixes configured
+// every valid name is open to everyone.
+
+use once_cell::sync::Lazy;
+
+/// Environment variable listing reserved prefixes, comma-separated
+pub const RESERVED_PREFIXES_ENV: &str = "QUILT_RESERVED_NAME_PREFIXES";
+
+/// Environment variable listing the tokens allowed to use reserved prefixes, comma-separated
+pub const PRIVILEGED_TOKENS_ENV: &str = "QUILT_PRIVILEGED_TOKENS";
+
+static GLOBAL_POLICY: Lazy<NamePolicy> = Lazy::new(NamePolicy::from_env);
+
+#[derive(Debug, Clone, Default)]
+pub struct NamePolicy {
+    /// Lowercased; names are matched against them case-insensitively
+    pub reserved_prefixes: Vec<String>,
+    privileged_tokens: Vec<String>,
+}
+
+impl NamePolicy {
+    /// Policy from comma-separated prefix and token lists; blank entries are skipped
+    pub fn new(prefixes: &str, tokens: &str) -> Self {
+        let split = |list: &str| list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        Self {
+            reserved_prefixes: split(prefixes).into_iter().map(|prefix| prefix.to_lowercase()).collect(),
+            privileged_tokens: split(tokens),
+        }
+    }
+
+    /// The policy from `QUILT_RESERVED_NAME_PREFIXES` and `QUILT_PRIVILEGED_TOKENS`
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        Self::new(&var(RESERVED_PREFIXES_ENV), &var(PRIVILEGED_TOKENS_ENV))
+    }
+
+    /// The daemon's policy, read from the environment once
+    pub fn global() -> &'static NamePolicy {
+        &GLOBAL_POLICY
+    }
+
+    /// The reserved prefix `name` starts with, if any
+    pub fn reserved_prefix(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.reserved_prefixes.iter()
+            .find(|prefix| name.starts_with(prefix.as_str()))
+            .map(String::as_str)
+    }
+
+    /// Whether `token` is one of the privileged tokens
+    pub fn is_privileged(&self, token: Option<&str>) -> bool {
+        token.map_or(false, |token| self.privileged_tokens.iter().any(|privileged| constant_time_eq(privileged, token)))
+    }
+
+    /// Refuse a reserved name unless the caller is privileged
+    pub fn check(&self, name: &str, token: Option<&str>) -> Result<(), String> {
+        match self.reserved_prefix(name) {
+            Some(prefix) if !self.is_privileged(token) => Err(format!(
+                "Permission denied: container name '{}' uses the reserved prefix '{}', which requires a privileged token",
+                name, prefix)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Compare without stopping at the first differing byte, so response timing does not reveal
+/// how much of a token was right
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_prefixes() {
+        let policy = NamePolicy::new(" System-, infra-,,", "s3cret, other");
+        assert_eq!(policy.reserved_prefixes, vec!["system-", "infra-"]);
+        assert_eq!(policy.reserved_prefix("SYSTEM-dns"), Some("system-"));
+        assert_eq!(policy.reserved_prefix("web"), None);
+
+        assert!(policy.check("web", None).is_ok());
+        assert!(policy.check("system-dns", None).is_err());
+        assert!(policy.check("infra-proxy", Some("s3cre")).is_err());
+        assert!(policy.check("infra-proxy", Some("other")).is_ok());
+
+        let open = NamePolicy::new("", "");
+        assert!(open.check("system-dns", None).is_ok());
+        assert!(!open.is_privileged(Some("")));
+    }
+}
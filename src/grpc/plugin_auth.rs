@@ -0,0 +1,117 @@
+// Auth provider plugins for the gRPC server
+// While an auth provider plugin is registered, every API call is put to it before it reaches a
+// handler. A denial ends the call with PERMISSION_DENIED; an unreachable provider with
+// UNAVAILABLE, so the API fails closed. Health checks and reflection are never gated.
+
+use crate::engine::plugins::{self, AuthDecision};
+use crate::grpc::actor::ACTOR_HEADER;
+use crate::utils::console::ConsoleLogger;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Services that stay reachable for probes and tooling whatever the providers decide
+const UNGATED_PREFIXES: [&str; 2] = ["/grpc.health.v1.", "/grpc.reflection."];
+
+#[derive(Clone, Default)]
+pub struct PluginAuthLayer;
+
+impl<S> Layer<S> for PluginAuthLayer {
+    type Service = PluginAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PluginAuth { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct PluginAuth<S> {
+    inner: S,
+}
+
+fn is_gated(path: &str) -> bool {
+    !UNGATED_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+}
+
+/// Request headers with ASCII values, as sent to providers; binary (`-bin`) headers are left out
+fn request_metadata<B>(request: &http::Request<B>) -> HashMap<String, String> {
+    request.headers().iter()
+        .filter(|(name, _)| !name.as_str().ends_with("-bin"))
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for PluginAuth<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let path = request.uri().path().to_string();
+            if !is_gated(&path) || !plugins::has_auth_providers() {
+                return inner.call(request).await;
+            }
+
+            let peer = request.extensions().get::<TcpConnectInfo>()
+                .and_then(|info| info.remote_addr())
+                .map(|addr| addr.to_string())
+                .unwrap_or_default();
+            match plugins::authorize(&path, request_metadata(&request), &peer).await {
+                Ok(AuthDecision::Allowed { actor }) => {
+                    // The provider knows the caller better than a self-declared header does
+                    if let Some(value) = actor.and_then(|actor| http::HeaderValue::from_str(&actor).ok()) {
+                        request.headers_mut().insert(ACTOR_HEADER, value);
+                    }
+                    inner.call(request).await
+                }
+                Ok(AuthDecision::Denied { plugin, reason }) => {
+                    ConsoleLogger::warning(&format!("🔌 [PLUGIN-AUTH] {} denied {} from {}: {}", plugin, path, peer, reason));
+                    Ok(Status::permission_denied(reason).to_http())
+                }
+                Err(e) => {
+                    ConsoleLogger::error(&format!("🔌 [PLUGIN-AUTH] Could not authorize {}: {}", path, e));
+                    Ok(Status::unavailable(format!("Authorization unavailable: {}", e)).to_http())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_metadata() {
+        assert!(is_gated("/quilt.QuiltService/CreateContainer"));
+        assert!(!is_gated("/grpc.health.v1.Health/Check"));
+
+        let request = http::Request::builder()
+            .header("authorization", "Bearer t0ken")
+            .header("trace-bin", "AAE=")
+            .body(())
+            .unwrap();
+        let metadata = request_metadata(&request);
+        assert_eq!(metadata.get("authorization").map(String::as_str), Some("Bearer t0ken"));
+        assert!(!metadata.contains_key("trace-bin"));
+    }
+}
@@ -0,0 +1,300 @@
+// Per-client rate limits for the gRPC server
+// Exec and log calls are cheap to issue and expensive to serve, so a client polling them in a
+// tight loop can starve everyone else. Each (method, client) pair gets a token bucket: a call
+// spends a token, tokens refill at a steady rate up to the burst size, and a call that finds the
+// bucket empty is rejected with RESOURCE_EXHAUSTED. Clients holding one of the configured
+// privileged tokens are told apart by it; everyone else by address, since a token the daemon
+// does not know could be made up afresh for every call.
+
+use crate::grpc::name_policy::NamePolicy;
+use crate::utils::console::ConsoleLogger;
+use crate::utils::name_policy::TOKEN_HEADER;
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Environment variable overriding limits as `<Method>=<calls_per_second>:<burst>` pairs
+/// separated by commas; a rate of 0 lifts the limit for that method
+pub const RATE_LIMITS_ENV: &str = "QUILT_RATE_LIMITS";
+
+/// Built-in (calls per second, burst) per client for the calls agents tend to poll
+const DEFAULT_RATE_LIMITS: &[(&str, f64, u32)] = &[
+    ("ExecContainer", 10.0, 50),
+    ("ExecContainerStream", 5.0, 20),
+    ("GetContainerLogs", 10.0, 50),
+    ("SearchContainerLogs", 5.0, 20),
+];
+
+/// Past this many buckets, those that have refilled completely are forgotten
+const MAX_TRACKED_BUCKETS: usize = 4096;
+
+/// Allowed and rejected calls per method since the daemon started
+static STATS: Lazy<Mutex<HashMap<String, RateLimitStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MethodRate {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimitStats {
+    pub allowed: u64,
+    pub rejected: u64,
+}
+
+/// Counters of every rate-limited method that has been called, by method name
+pub fn rate_limit_stats() -> Vec<(String, RateLimitStats)> {
+    let mut stats: Vec<_> = lock(&STATS).iter().map(|(method, stats)| (method.clone(), *stats)).collect();
+    stats.sort_by(|a, b| a.0.cmp(&b.0));
+    stats
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: MethodRate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.refilled_at = now;
+    }
+
+    /// Spend a token, or report how long until one is available
+    fn take(&mut self, limit: MethodRate, now: Instant) -> Result<(), Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / limit.per_second))
+        }
+    }
+}
+
+/// Limits keyed by RPC method name (the last segment of the request path)
+pub struct RateLimits {
+    limits: HashMap<String, MethodRate>,
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+impl RateLimits {
+    pub fn new(limits: HashMap<String, MethodRate>) -> Self {
+        Self { limits, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Built-in defaults with any `QUILT_RATE_LIMITS` entries applied on top
+    pub fn from_env() -> Self {
+        let mut limits: HashMap<String, MethodRate> = DEFAULT_RATE_LIMITS.iter()
+            .map(|(method, per_second, burst)| (method.to_string(), MethodRate { per_second: *per_second, burst: *burst }))
+            .collect();
+        if let Ok(value) = std::env::var(RATE_LIMITS_ENV) {
+            limits.extend(Self::parse(&value));
+        }
+        limits.retain(|_, limit| limit.per_second > 0.0);
+        Self::new(limits)
+    }
+
+    /// Parse `ExecContainer=20:100,GetContainerLogs=0:0`; malformed entries are skipped with a warning
+    pub fn parse(value: &str) -> HashMap<String, MethodRate> {
+        let mut limits = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(method, spec)| {
+                let (per_second, burst) = spec.split_once(':')?;
+                let limit = MethodRate {
+                    per_second: per_second.trim().parse().ok()?,
+                    burst: burst.trim().parse().ok()?,
+                };
+                Some((method.trim().to_string(), limit))
+            });
+            match parsed {
+                Some((method, limit)) if !method.is_empty() && limit.per_second == 0.0 => {
+                    limits.insert(method, limit);
+                }
+                Some((method, limit)) if !method.is_empty() && limit.per_second.is_finite() && limit.per_second > 0.0 && limit.burst > 0 => {
+                    limits.insert(method, limit);
+                }
+                _ => ConsoleLogger::warning(&format!("⚠️ [RATE-LIMIT] Ignoring malformed {} entry: {}", RATE_LIMITS_ENV, entry)),
+            }
+        }
+        limits
+    }
+
+    /// Spend one of `client`'s tokens for `method`; an empty bucket is reported as RESOURCE_EXHAUSTED
+    pub fn check(&self, method: &str, client: &str, now: Instant) -> Result<(), Status> {
+        let Some(limit) = self.limits.get(method).copied() else { return Ok(()) };
+
+        let outcome = {
+            let mut buckets = lock(&self.buckets);
+            if buckets.len() >= MAX_TRACKED_BUCKETS {
+                buckets.retain(|(method, _), bucket| match self.limits.get(method) {
+                    Some(limit) => {
+                        bucket.refill(*limit, now);
+                        bucket.tokens < limit.burst as f64
+                    }
+                    None => false,
+                });
+            }
+            buckets.entry((method.to_string(), client.to_string()))
+                .or_insert(Bucket { tokens: limit.burst as f64, refilled_at: now })
+                .take(limit, now)
+        };
+
+        let mut stats = lock(&STATS);
+        let stats = stats.entry(method.to_string()).or_default();
+        match outcome {
+            Ok(()) => {
+                stats.allowed += 1;
+                Ok(())
+            }
+            Err(retry_after) => {
+                stats.rejected += 1;
+                // Debug only: a client over its limit would otherwise flood the log as well
+                ConsoleLogger::debug(&format!("🚦 [RATE-LIMIT] Rejecting {} from {}: over {}/s (burst {})",
+                    method, client, limit.per_second, limit.burst));
+                Err(Status::resource_exhausted(format!(
+                    "Rate limit for {} exceeded ({}/s, burst {}); retry in {}ms",
+                    method, limit.per_second, limit.burst, retry_after.as_millis().max(1))))
+            }
+        }
+    }
+}
+
+/// The bucket key of a request's caller: a digest of its token when `policy` knows it, else its
+/// IP address
+fn client_identity<B>(request: &http::Request<B>, policy: &NamePolicy) -> String {
+    let token = request.headers().get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|token| policy.is_privileged(Some(token)));
+    if let Some(token) = token {
+        // Only a digest is kept, so the bucket table never holds a credential
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        return format!("token:{:016x}", hasher.finish());
+    }
+    request.extensions().get::<TcpConnectInfo>()
+        .and_then(|info| info.remote_addr())
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Tower layer applying [`RateLimits`] in front of the gRPC routes
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limits: Arc<RateLimits>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits: Arc::new(limits) }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit { inner, limits: self.limits.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limits: Arc<RateLimits>,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for RateLimit<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = self.limits.clone();
+
+        Box::pin(async move {
+            let method = request.uri().path().rsplit('/').next().unwrap_or_default();
+            if let Err(status) = limits.check(method, &client_identity(&request, NamePolicy::global()), Instant::now()) {
+                return Ok(status.to_http());
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limits() {
+        let limits = RateLimits::parse("ExecContainer=2.5:10, GetContainerLogs=0:0,Stop=1:0,bogus,Exec=x:1");
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits.get("ExecContainer"), Some(&MethodRate { per_second: 2.5, burst: 10 }));
+        assert_eq!(limits.get("GetContainerLogs"), Some(&MethodRate { per_second: 0.0, burst: 0 }));
+    }
+
+    #[test]
+    fn test_token_bucket_per_client() {
+        let limits = RateLimits::new(HashMap::from([
+            ("ExecContainerStream".to_string(), MethodRate { per_second: 1.0, burst: 2 }),
+        ]));
+        let start = Instant::now();
+        assert!(limits.check("ExecContainerStream", "10.0.0.1", start).is_ok());
+        assert!(limits.check("ExecContainerStream", "10.0.0.1", start).is_ok());
+        let rejected = limits.check("ExecContainerStream", "10.0.0.1", start).unwrap_err();
+        assert_eq!(rejected.code(), tonic::Code::ResourceExhausted);
+
+        // Other clients and unlimited methods are unaffected; tokens come back over time
+        assert!(limits.check("ExecContainerStream", "10.0.0.2", start).is_ok());
+        assert!(limits.check("ListContainers", "10.0.0.1", start).is_ok());
+        assert!(limits.check("ExecContainerStream", "10.0.0.1", start + Duration::from_secs(1)).is_ok());
+
+        let stats = rate_limit_stats().into_iter().find(|(method, _)| method == "ExecContainerStream").unwrap().1;
+        assert!(stats.allowed >= 4 && stats.rejected >= 1);
+    }
+
+    #[test]
+    fn test_client_identity() {
+        let policy = NamePolicy::new("", "s3cret");
+        let request = http::Request::builder().body(()).unwrap();
+        assert_eq!(client_identity(&request, &policy), "unknown");
+
+        let request = http::Request::builder().header(TOKEN_HEADER, " s3cret ").body(()).unwrap();
+        let identity = client_identity(&request, &policy);
+        assert!(identity.starts_with("token:") && !identity.contains("s3cret"));
+
+        // Unknown tokens would let a client mint a fresh bucket per call
+        let request = http::Request::builder().header(TOKEN_HEADER, "made-up").body(()).unwrap();
+        assert_eq!(client_identity(&request, &policy), "unknown");
+    }
+}
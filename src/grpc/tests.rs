@@ -174,6 +174,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         sync_engine.create_container(config).await.unwrap();
@@ -5,11 +5,14 @@
 
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
-use trust_dns_proto::op::{Message, MessageType, OpCode, ResponseCode};
-use trust_dns_proto::rr::{DNSClass, RData, Record, RecordType};
+use trust_dns_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns_proto::rr::{DNSClass, Name, RData, Record, RecordType};
 use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::InputValidator;
 
 #[derive(Debug, Clone)]
 pub struct DnsEntry {
@@ -17,21 +20,387 @@ pub struct DnsEntry {
     pub container_name: String,
     pub ip_address: IpAddr,
     pub ttl: u32,
+    /// Extra names resolving to this container, without the domain suffix
+    pub aliases: Vec<String>,
+    /// Whether every name below `<name>.<domain suffix>` resolves to this container too, for the
+    /// container name and each alias
+    pub wildcard: bool,
+}
+
+/// Cluster domain when `QUILT_DNS_DOMAIN` is unset
+pub const DEFAULT_CLUSTER_DOMAIN: &str = "quilt.local";
+/// Domain container names, aliases and virtual services are published under
+pub const CLUSTER_DOMAIN_ENV: &str = "QUILT_DNS_DOMAIN";
+/// Extra search domains for containers, separated by commas or spaces
+pub const SEARCH_DOMAINS_ENV: &str = "QUILT_DNS_SEARCH";
+/// Search domains a resolv.conf may list; older resolvers ignore the rest
+const MAX_SEARCH_DOMAINS: usize = 6;
+
+/// The domain the DNS server is authoritative for and the search list containers resolve short
+/// names with
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsDomainConfig {
+    pub cluster_domain: String,
+    /// Searched after the cluster domain
+    pub search_domains: Vec<String>,
+}
+
+impl Default for DnsDomainConfig {
+    fn default() -> Self {
+        Self { cluster_domain: DEFAULT_CLUSTER_DOMAIN.to_string(), search_domains: Vec::new() }
+    }
+}
+
+impl DnsDomainConfig {
+    /// Validate and normalise the domains: lowercase, no trailing dot, no duplicates
+    pub fn new(cluster_domain: &str, search_domains: &[String]) -> Result<Self, String> {
+        let normalize = |domain: &str| domain.trim().trim_end_matches('.').to_ascii_lowercase();
+        let cluster_domain = normalize(cluster_domain);
+        InputValidator::validate_dns_domain(&cluster_domain)?;
+
+        let mut search = Vec::new();
+        for domain in search_domains.iter().map(|domain| normalize(domain)).filter(|domain| !domain.is_empty()) {
+            InputValidator::validate_dns_domain(&domain)?;
+            if domain != cluster_domain && !search.contains(&domain) {
+                search.push(domain);
+            }
+        }
+        if search.len() >= MAX_SEARCH_DOMAINS {
+            return Err(format!("At most {} search domains besides the cluster domain are allowed", MAX_SEARCH_DOMAINS - 1));
+        }
+        Ok(Self { cluster_domain, search_domains: search })
+    }
+
+    /// The domains from `QUILT_DNS_DOMAIN` and `QUILT_DNS_SEARCH`; invalid settings fall back to
+    /// the defaults with a warning
+    pub fn from_env() -> Self {
+        let cluster_domain = std::env::var(CLUSTER_DOMAIN_ENV).ok()
+            .filter(|domain| !domain.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_CLUSTER_DOMAIN.to_string());
+        let search_domains: Vec<String> = std::env::var(SEARCH_DOMAINS_ENV).unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(str::to_string)
+            .collect();
+        Self::new(&cluster_domain, &search_domains).unwrap_or_else(|e| {
+            ConsoleLogger::warning(&format!("Ignoring {} / {}: {}; using {}", CLUSTER_DOMAIN_ENV, SEARCH_DOMAINS_ENV, e, DEFAULT_CLUSTER_DOMAIN));
+            Self::default()
+        })
+    }
+
+    /// The cluster domain, then the extra search domains
+    pub fn search_list(&self) -> Vec<&str> {
+        std::iter::once(self.cluster_domain.as_str())
+            .chain(self.search_domains.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// resolv.conf content pointing a container at `nameserver`
+    pub fn resolv_conf(&self, nameserver: &str) -> String {
+        format!("nameserver {}\nsearch {}\n", nameserver, self.search_list().join(" "))
+    }
+}
+
+/// Default number of answers kept in the DNS cache
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+/// Upper bound on how long any answer is cached, regardless of record TTL
+const MAX_CACHE_TTL_SECS: u32 = 300;
+/// How long NXDOMAIN / empty answers are cached
+const NEGATIVE_CACHE_TTL_SECS: u32 = 30;
+/// Timeout for a single forwarded query
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+/// Consecutive receive errors after which the listener gives up, so a supervisor can rebind it
+const MAX_CONSECUTIVE_RECV_ERRORS: u32 = 32;
+/// Label queried by health probes; it is never registered, so the answer is a local NXDOMAIN
+const PROBE_LABEL: &str = "quilt-dns-probe";
+/// TTL of virtual service answers; short so clients re-resolve and notice failover quickly
+const VIRTUAL_SERVICE_TTL: u32 = 5;
+/// Zone of IPv4 reverse lookups
+const REVERSE_ZONE_V4: &str = "in-addr.arpa";
+/// TTL of answers from operator-defined records
+const RECORD_TTL: u32 = 30;
+/// Queries resolved at once; past this, new queries are answered SERVFAIL straight away so a
+/// flood (or a stalled upstream) cannot pile up tasks and forwarding sockets
+const MAX_CONCURRENT_QUERIES: usize = 256;
+
+/// Which clients a DNS record answers: queries from containers, from the host, or both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DnsView {
+    All,
+    Containers,
+    Host,
+}
+
+impl DnsView {
+    pub fn parse(view: &str) -> Result<Self, String> {
+        match view {
+            "" | "all" => Ok(DnsView::All),
+            "containers" => Ok(DnsView::Containers),
+            "host" => Ok(DnsView::Host),
+            other => Err(format!("Invalid DNS view '{}': expected all, containers or host", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsView::All => "all",
+            DnsView::Containers => "containers",
+            DnsView::Host => "host",
+        }
+    }
+}
+
+/// Snapshot of DNS cache counters
+#[derive(Debug, Clone, Default)]
+pub struct DnsCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub forwarded_queries: u64,
+    pub entries: u64,
+    pub capacity: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedAnswer {
+    answers: Vec<Record>,
+    response_code: ResponseCode,
+    authoritative: bool,
+    inserted_at: Instant,
+    ttl: u32,
+    last_used: u64,
+}
+
+/// LRU cache of DNS answers keyed by (name, record type).
+/// Holds both container-name answers and answers forwarded from upstream resolvers.
+pub struct DnsCache {
+    entries: Mutex<HashMap<(String, RecordType), CachedAnswer>>,
+    capacity: usize,
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    forwarded: AtomicU64,
+}
+
+impl DnsCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            forwarded: AtomicU64::new(0),
+        }
+    }
+
+    fn key(name: &str, record_type: RecordType) -> (String, RecordType) {
+        (name.trim_end_matches('.').to_ascii_lowercase(), record_type)
+    }
+
+    /// Look up a cached answer; TTLs on returned records reflect the remaining lifetime
+    fn get(&self, name: &str, record_type: RecordType) -> Option<CachedAnswer> {
+        let key = Self::key(name, record_type);
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return None,
+        };
+
+        let expired = match entries.get(&key) {
+            Some(cached) => cached.inserted_at.elapsed().as_secs() >= cached.ttl as u64,
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        };
+
+        if expired {
+            entries.remove(&key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        let cached = entries.get_mut(&key)?;
+        cached.last_used = tick;
+
+        let remaining = cached.ttl.saturating_sub(cached.inserted_at.elapsed().as_secs() as u32).max(1);
+        let mut answer = cached.clone();
+        for record in answer.answers.iter_mut() {
+            record.set_ttl(remaining.min(record.ttl()));
+        }
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(answer)
+    }
+
+    /// Store an answer, evicting the least recently used entry when full
+    fn insert(&self, name: &str, record_type: RecordType, answers: Vec<Record>, response_code: ResponseCode, authoritative: bool) {
+        let ttl = if answers.is_empty() {
+            NEGATIVE_CACHE_TTL_SECS
+        } else {
+            answers.iter().map(|r| r.ttl()).min().unwrap_or(0).min(MAX_CACHE_TTL_SECS)
+        };
+        if ttl == 0 {
+            return;
+        }
+
+        let key = Self::key(name, record_type);
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            if let Some(lru_key) = entries.iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone()) {
+                entries.remove(&lru_key);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        entries.insert(key, CachedAnswer {
+            answers,
+            response_code,
+            authoritative,
+            inserted_at: Instant::now(),
+            ttl,
+            last_used: self.clock.fetch_add(1, Ordering::Relaxed),
+        });
+    }
+
+    /// Drop every cached answer, returning how many were removed
+    pub fn flush(&self) -> usize {
+        match self.entries.lock() {
+            Ok(mut entries) => {
+                let flushed = entries.len();
+                entries.clear();
+                flushed
+            }
+            Err(_) => 0,
+        }
+    }
+
+    pub fn stats(&self) -> DnsCacheStats {
+        DnsCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            forwarded_queries: self.forwarded.load(Ordering::Relaxed),
+            entries: self.entries.lock().map(|e| e.len() as u64).unwrap_or(0),
+            capacity: self.capacity as u64,
+        }
+    }
+}
+
+/// Names that resolve to every address of a changing backend set, rotated on each query so
+/// clients spread across the backends
+#[derive(Default)]
+struct VirtualServices {
+    backends: RwLock<HashMap<String, Vec<IpAddr>>>,
+    rotation: AtomicU64,
+}
+
+impl VirtualServices {
+    /// The backends of a service name (bare or under the domain suffix), first one rotating
+    fn rotated(&self, name: &str, domain_suffix: &str) -> Option<Vec<IpAddr>> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let short_name = name.strip_suffix(&format!(".{}", domain_suffix)).unwrap_or(&name);
+        let backends = self.backends.read().ok()?;
+        let mut addresses = backends.get(short_name)?.clone();
+        if !addresses.is_empty() {
+            let start = self.rotation.fetch_add(1, Ordering::Relaxed) as usize % addresses.len();
+            addresses.rotate_left(start);
+        }
+        Some(addresses)
+    }
+}
+
+/// Operator-defined names, each answered per view. A query gets the record of its own view, or
+/// else the one for all views; without either it resolves as usual.
+#[derive(Default)]
+struct ViewRecords {
+    records: RwLock<HashMap<(String, DnsView), Vec<IpAddr>>>,
+}
+
+impl ViewRecords {
+    /// The addresses `name` (bare, fully qualified or under the domain suffix) has in `view`
+    fn lookup(&self, name: &str, view: DnsView, domain_suffix: &str) -> Option<Vec<IpAddr>> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let short_name = name.strip_suffix(&format!(".{}", domain_suffix));
+        let candidates: Vec<(String, DnsView)> = [view, DnsView::All].iter()
+            .flat_map(|view| [Some(name.as_str()), short_name].into_iter().flatten().map(move |name| (name.to_string(), *view)))
+            .collect();
+        let records = self.records.read().ok()?;
+        candidates.iter().find_map(|key| records.get(key).cloned())
+    }
 }
 
 pub struct DnsServer {
     entries: Arc<RwLock<HashMap<String, DnsEntry>>>,
-    bind_address: SocketAddr,
+    services: Arc<VirtualServices>,
+    records: Arc<ViewRecords>,
+    cache: Arc<DnsCache>,
+    upstream_servers: Arc<Vec<SocketAddr>>,
+    bind_address: Mutex<SocketAddr>,
     domain_suffix: String,
+    listener: Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 impl DnsServer {
     pub fn new(bind_address: SocketAddr) -> Self {
         Self {
             entries: Arc::new(RwLock::new(HashMap::new())),
-            bind_address,
-            domain_suffix: "quilt.local".to_string(),
+            services: Arc::new(VirtualServices::default()),
+            records: Arc::new(ViewRecords::default()),
+            cache: Arc::new(DnsCache::new(DEFAULT_CACHE_CAPACITY)),
+            upstream_servers: Arc::new(Self::detect_upstream_servers(bind_address.ip())),
+            bind_address: Mutex::new(bind_address),
+            domain_suffix: DEFAULT_CLUSTER_DOMAIN.to_string(),
+            listener: Mutex::new(None),
+        }
+    }
+    
+    /// Publish names under `domain` instead of the default cluster domain
+    pub fn with_domain(mut self, domain: &str) -> Self {
+        self.domain_suffix = domain.to_string();
+        self
+    }
+    
+    /// Domain names are published under
+    pub fn domain_suffix(&self) -> &str {
+        &self.domain_suffix
+    }
+    
+    /// Read upstream resolvers from the host's resolv.conf, skipping loopback stubs and ourselves
+    fn detect_upstream_servers(own_ip: IpAddr) -> Vec<SocketAddr> {
+        let mut servers: Vec<SocketAddr> = std::fs::read_to_string("/etc/resolv.conf")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|addr| addr.trim().parse::<IpAddr>().ok())
+            .filter(|ip| !ip.is_loopback() && *ip != own_ip)
+            .map(|ip| SocketAddr::new(ip, 53))
+            .collect();
+        
+        if servers.is_empty() {
+            servers.push(SocketAddr::from(([8, 8, 8, 8], 53)));
         }
+        servers
+    }
+    
+    /// Current cache counters
+    pub fn cache_stats(&self) -> DnsCacheStats {
+        self.cache.stats()
+    }
+    
+    /// Flush all cached answers
+    pub fn flush_cache(&self) -> usize {
+        let flushed = self.cache.flush();
+        ConsoleLogger::info(&format!("DNS: Flushed {} cached answers", flushed));
+        flushed
     }
     
     /// Register a container with DNS
@@ -41,17 +410,24 @@ impl DnsServer {
         
         ConsoleLogger::debug(&format!("🔧 [DNS-REG] Registering container DNS: id={}, name={}, ip={}", container_id, container_name, ip_address));
         
+        let mut entries = self.entries.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        
+        // Aliases outlive a restart's re-registration; only unregistering drops them
+        let aliases = entries.get(container_id)
+            .map(|existing| existing.aliases.clone())
+            .unwrap_or_default();
+        
         let entry = DnsEntry {
             container_id: container_id.to_string(),
             container_name: container_name.to_string(),
             ip_address: ip,
             ttl: 300, // 5 minutes
+            aliases,
+            wildcard: false,
         };
         
         // Register both by name and by ID
-        let mut entries = self.entries.write()
-            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
-        
         entries.insert(container_name.to_string(), entry.clone());
         entries.insert(container_id.to_string(), entry.clone());
         
@@ -59,6 +435,14 @@ impl DnsServer {
         let fqdn = format!("{}.{}", container_name, self.domain_suffix);
         entries.insert(fqdn, entry.clone());
         
+        for alias in &entry.aliases {
+            entries.insert(alias.clone(), entry.clone());
+            entries.insert(format!("{}.{}", alias, self.domain_suffix), entry.clone());
+        }
+        
+        // Registrations change authoritative answers, so cached container lookups are stale
+        self.cache.flush();
+        
         ConsoleLogger::success(&format!("✅ [DNS-REG] DNS registration complete: {} entries now registered", entries.len()));
         
         // Debug: List all registered entries
@@ -88,25 +472,256 @@ impl DnsServer {
             ConsoleLogger::debug(&format!("DNS: Unregistered {}", key));
         }
         
+        self.cache.flush();
+        
         Ok(())
     }
     
+    /// Hand `from_id`'s aliases and the name `container_name` to the registered `to_id` in one
+    /// step, so a replacement never leaves the name unanswered. `from_id` is unregistered; it
+    /// need not be registered at all.
+    pub fn take_over(&self, from_id: &str, to_id: &str, container_name: &str) -> Result<(), String> {
+        let mut entries = self.entries.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        
+        let mut entry = entries.get(to_id).cloned()
+            .ok_or_else(|| format!("Container {} is not registered with DNS", to_id))?;
+        let inherited = entries.get(from_id).map(|old| old.aliases.clone()).unwrap_or_default();
+        entries.retain(|_, existing| existing.container_id != from_id && existing.container_id != to_id);
+        
+        entry.container_name = container_name.to_string();
+        for alias in inherited {
+            if !entry.aliases.contains(&alias) {
+                entry.aliases.push(alias);
+            }
+        }
+        let names: Vec<String> = std::iter::once(container_name.to_string()).chain(entry.aliases.iter().cloned()).collect();
+        for name in names {
+            entries.insert(format!("{}.{}", name, self.domain_suffix), entry.clone());
+            entries.insert(name, entry.clone());
+        }
+        entries.insert(to_id.to_string(), entry);
+        
+        self.cache.flush();
+        ConsoleLogger::info(&format!("DNS: {} took over {} from {}", to_id, container_name, from_id));
+        Ok(())
+    }
+    
+    /// Add an extra name for a registered container; a container may have any number of aliases
+    pub fn add_alias(&self, container_id: &str, alias: &str) -> Result<(), String> {
+        let mut entries = self.entries.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        
+        if !entries.contains_key(container_id) {
+            return Err(format!("Container {} is not registered with DNS", container_id));
+        }
+        let fqdn = format!("{}.{}", alias, self.domain_suffix);
+        if let Some(owner) = entries.get(alias).or_else(|| entries.get(&fqdn)) {
+            return Err(format!("DNS name '{}' is already in use by container {}", alias, owner.container_id));
+        }
+        
+        // Every key holds its own copy of the entry, so all of them learn the alias
+        for entry in entries.values_mut().filter(|entry| entry.container_id == container_id) {
+            entry.aliases.push(alias.to_string());
+        }
+        let entry = entries[container_id].clone();
+        entries.insert(alias.to_string(), entry.clone());
+        entries.insert(fqdn, entry);
+        
+        self.cache.flush();
+        ConsoleLogger::info(&format!("DNS: Added alias {} for {}", alias, container_id));
+        Ok(())
+    }
+    
+    /// Remove an alias added with `add_alias`, returning the container it pointed at
+    pub fn remove_alias(&self, alias: &str) -> Result<String, String> {
+        let mut entries = self.entries.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        
+        let container_id = match entries.get(alias) {
+            Some(entry) if entry.aliases.iter().any(|existing| existing == alias) => entry.container_id.clone(),
+            _ => return Err(format!("DNS alias '{}' not found", alias)),
+        };
+        
+        entries.remove(alias);
+        entries.remove(&format!("{}.{}", alias, self.domain_suffix));
+        for entry in entries.values_mut().filter(|entry| entry.container_id == container_id) {
+            entry.aliases.retain(|existing| existing != alias);
+        }
+        
+        self.cache.flush();
+        ConsoleLogger::info(&format!("DNS: Removed alias {} from {}", alias, container_id));
+        Ok(container_id)
+    }
+    
+    /// Answer any name below the container's names (`*.<name>.<domain suffix>`) with its address
+    pub fn set_wildcard(&self, container_id: &str, enabled: bool) -> Result<(), String> {
+        let mut entries = self.entries.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        
+        if !entries.contains_key(container_id) {
+            return Err(format!("Container {} is not registered with DNS", container_id));
+        }
+        for entry in entries.values_mut().filter(|entry| entry.container_id == container_id) {
+            entry.wildcard = enabled;
+        }
+        
+        self.cache.flush();
+        ConsoleLogger::info(&format!("DNS: Wildcard names {} for {}", if enabled { "enabled" } else { "disabled" }, container_id));
+        Ok(())
+    }
+    
+    /// Point a virtual service name at `backends`; answers are never cached, so changes apply to
+    /// the next query
+    pub fn set_service_backends(&self, name: &str, backends: Vec<IpAddr>) -> Result<(), String> {
+        let name = name.to_ascii_lowercase();
+        let mut services = self.services.backends.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        if services.get(&name) == Some(&backends) {
+            return Ok(());
+        }
+        // The name may have a cached NXDOMAIN from before the service existed
+        if services.insert(name.clone(), backends).is_none() {
+            self.cache.flush();
+        }
+        Ok(())
+    }
+    
+    /// Stop answering for a virtual service
+    pub fn remove_service(&self, name: &str) -> Result<(), String> {
+        self.services.backends.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+            .remove(&name.to_ascii_lowercase());
+        Ok(())
+    }
+    
+    /// Answer `name` with `addresses` for queries in `view`, replacing its earlier record there.
+    /// Records are consulted before anything else and never cached.
+    pub fn set_record(&self, name: &str, view: DnsView, addresses: Vec<IpAddr>) -> Result<(), String> {
+        self.records.records.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+            .insert((name.to_ascii_lowercase(), view), addresses);
+        Ok(())
+    }
+    
+    /// Drop the record of `name` in `view`; the name resolves as before it was set
+    pub fn remove_record(&self, name: &str, view: DnsView) -> Result<(), String> {
+        self.records.records.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+            .remove(&(name.to_ascii_lowercase(), view));
+        Ok(())
+    }
+    
+    /// The view a query from `source` is answered in: containers are recognised by their
+    /// registered addresses, anything else is the host
+    fn view_of(entries: &RwLock<HashMap<String, DnsEntry>>, source: IpAddr) -> DnsView {
+        let from_container = entries.read()
+            .map(|entries| entries.values().any(|entry| entry.ip_address == source))
+            .unwrap_or(false);
+        if from_container { DnsView::Containers } else { DnsView::Host }
+    }
+    
+    /// Address the listener is (or was last) bound to
+    pub fn bind_address(&self) -> SocketAddr {
+        *self.bind_address.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Whether the listener task is still running
+    pub fn is_listening(&self) -> bool {
+        self.listener.lock().unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|task| !task.is_finished())
+            .unwrap_or(false)
+    }
+    
+    /// Stop the listener; registrations and the cache are kept
+    pub fn stop(&self) {
+        if let Some(task) = self.listener.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
+    }
+    
     /// Start the DNS server
     pub async fn start(&self) -> Result<(), String> {
-        let socket = UdpSocket::bind(&self.bind_address).await
-            .map_err(|e| format!("Failed to bind DNS server to {}: {}", self.bind_address, e))?;
+        self.listen_on(self.bind_address()).await
+    }
+    
+    /// Start on the first of `ports` that can be bound (on the current bind IP); returns that port
+    pub async fn start_with_fallback(&self, ports: &[u16]) -> Result<u16, String> {
+        let ip = self.bind_address().ip();
+        let mut last_error = "no ports to try".to_string();
+        for port in ports {
+            match self.listen_on(SocketAddr::new(ip, *port)).await {
+                Ok(()) => return Ok(self.bind_address().port()),
+                Err(e) => {
+                    ConsoleLogger::warning(&format!("DNS: {}", e));
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+    
+    /// Stop the current listener (if any) and start again on the first bindable port
+    pub async fn restart(&self, ports: &[u16]) -> Result<u16, String> {
+        self.stop();
+        self.start_with_fallback(ports).await
+    }
+    
+    /// Query the listener for a name only it can answer; Ok means it is receiving and replying
+    pub async fn probe(&self, timeout: Duration) -> Result<(), String> {
+        let target = self.bind_address();
+        let socket = UdpSocket::bind(SocketAddr::new(target.ip(), 0)).await
+            .map_err(|e| format!("cannot bind probe socket on {}: {}", target.ip(), e))?;
         
-        ConsoleLogger::info(&format!("DNS server listening on {}", self.bind_address));
+        let name = Name::from_ascii(format!("{}.{}.", PROBE_LABEL, self.domain_suffix))
+            .map_err(|e| format!("invalid probe name: {}", e))?;
+        let id = uuid::Uuid::new_v4().as_u128() as u16;
+        let mut query = Message::new();
+        query.set_id(id);
+        query.set_message_type(MessageType::Query);
+        query.set_op_code(OpCode::Query);
+        query.add_query(Query::query(name, RecordType::A));
+        let query_bytes = query.to_vec().map_err(|e| format!("failed to encode probe: {}", e))?;
+        
+        socket.send_to(&query_bytes, target).await
+            .map_err(|e| format!("probe send to {} failed: {}", target, e))?;
+        let mut buf = vec![0u8; 512];
+        match tokio::time::timeout(timeout, socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, _))) => match Message::from_vec(&buf[..len]) {
+                Ok(response) if response.id() == id => Ok(()),
+                Ok(_) => Err("probe answered with a mismatched id".to_string()),
+                Err(e) => Err(format!("unparseable probe answer: {}", e)),
+            },
+            Ok(Err(e)) => Err(format!("probe receive failed: {}", e)),
+            Err(_) => Err(format!("no answer from {} within {:?}", target, timeout)),
+        }
+    }
+    
+    async fn listen_on(&self, address: SocketAddr) -> Result<(), String> {
+        let socket = UdpSocket::bind(address).await
+            .map_err(|e| format!("Failed to bind DNS server to {}: {}", address, e))?;
+        let bound = socket.local_addr().unwrap_or(address);
+        let socket = Arc::new(socket);
+        
+        ConsoleLogger::info(&format!("DNS server listening on {} (upstreams: {:?})", bound, self.upstream_servers));
         
         let entries = self.entries.clone();
+        let services = self.services.clone();
+        let records = self.records.clone();
+        let cache = self.cache.clone();
+        let upstream_servers = self.upstream_servers.clone();
         let domain_suffix = self.domain_suffix.clone();
+        let in_flight = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_QUERIES));
         
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             let mut buf = vec![0u8; 512];
+            let mut consecutive_errors = 0u32;
             
             loop {
                 match socket.recv_from(&mut buf).await {
                     Ok((len, src)) => {
+                        consecutive_errors = 0;
                         match Message::from_vec(&buf[..len]) {
                             Ok(query) => {
                                 ConsoleLogger::debug(&format!("🔍 [DNS-QUERY] Received DNS query from {}: {} queries", src, query.query_count()));
@@ -114,14 +729,37 @@ impl DnsServer {
                                     ConsoleLogger::debug(&format!("🔍 [DNS-QUERY] Query: {} (type: {:?})", q.name(), q.query_type()));
                                 }
                                 
-                                if let Ok(response) = Self::handle_query(query, &entries, &domain_suffix) {
-                                    ConsoleLogger::debug(&format!("📤 [DNS-RESPONSE] Sending response with {} answers", response.answer_count()));
-                                    if let Ok(response_bytes) = response.to_vec() {
+                                let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+                                    ConsoleLogger::debug(&format!("🚦 [DNS-QUERY] {} queries in flight, refusing query from {}", MAX_CONCURRENT_QUERIES, src));
+                                    if let Ok(response_bytes) = Self::build_servfail_response(&query).to_vec() {
                                         let _ = socket.send_to(&response_bytes, src).await;
                                     }
-                                } else {
-                                    ConsoleLogger::warning(&format!("❌ [DNS-QUERY] Failed to handle query from {}", src));
-                                }
+                                    continue;
+                                };
+                                
+                                // Resolve off the receive loop so slow upstream lookups don't block other queries
+                                let socket = socket.clone();
+                                let entries = entries.clone();
+                                let services = services.clone();
+                                let records = records.clone();
+                                let cache = cache.clone();
+                                let upstream_servers = upstream_servers.clone();
+                                let domain_suffix = domain_suffix.clone();
+                                tokio::spawn(async move {
+                                    let _permit = permit;
+                                    let view = Self::view_of(&entries, src.ip());
+                                    match Self::resolve(query, view, &entries, &services, &records, &cache, &upstream_servers, &domain_suffix).await {
+                                        Ok(response) => {
+                                            ConsoleLogger::debug(&format!("📤 [DNS-RESPONSE] Sending response with {} answers", response.answer_count()));
+                                            if let Ok(response_bytes) = response.to_vec() {
+                                                let _ = socket.send_to(&response_bytes, src).await;
+                                            }
+                                        }
+                                        Err(e) => {
+                                            ConsoleLogger::warning(&format!("❌ [DNS-QUERY] Failed to handle query from {}: {}", src, e));
+                                        }
+                                    }
+                                });
                             }
                             Err(e) => {
                                 ConsoleLogger::debug(&format!("❌ [DNS-PARSE] Failed to parse query: {}", e));
@@ -130,14 +768,187 @@ impl DnsServer {
                     }
                     Err(e) => {
                         ConsoleLogger::warning(&format!("DNS: Failed to receive packet: {}", e));
+                        consecutive_errors += 1;
+                        if consecutive_errors >= MAX_CONSECUTIVE_RECV_ERRORS {
+                            ConsoleLogger::error(&format!("DNS: Listener on {} stopping after {} consecutive receive errors", bound, consecutive_errors));
+                            break;
+                        }
                     }
                 }
             }
         });
         
+        *self.bind_address.lock().unwrap_or_else(|e| e.into_inner()) = bound;
+        if let Some(previous) = self.listener.lock().unwrap_or_else(|e| e.into_inner()).replace(task) {
+            previous.abort();
+        }
         Ok(())
     }
     
+    /// Resolve a query in `view` through operator records, virtual services, the cache, container
+    /// entries, then upstream resolvers
+    #[allow(clippy::too_many_arguments)]
+    async fn resolve(
+        query: Message,
+        view: DnsView,
+        entries: &Arc<RwLock<HashMap<String, DnsEntry>>>,
+        services: &VirtualServices,
+        records: &ViewRecords,
+        cache: &DnsCache,
+        upstream_servers: &[SocketAddr],
+        domain_suffix: &str,
+    ) -> Result<Message, String> {
+        // Only single-question standard queries are cached or forwarded
+        if query.op_code() != OpCode::Query || query.queries().len() != 1 {
+            return Self::handle_query(query, entries, domain_suffix);
+        }
+        let question = query.queries()[0].clone();
+        let name = question.name().to_string();
+        
+        // Records differ between views, and the cache is shared by all of them
+        if let Some(addresses) = records.lookup(&name, view, domain_suffix) {
+            return Ok(Self::build_address_response(&query, &question, &addresses, RECORD_TTL));
+        }
+        
+        // Virtual services bypass the cache so every query gets the next rotation
+        if let Some(backends) = services.rotated(&name, domain_suffix) {
+            return Ok(Self::build_address_response(&query, &question, &backends, VIRTUAL_SERVICE_TTL));
+        }
+        
+        if let Some(cached) = cache.get(&name, question.query_type()) {
+            ConsoleLogger::debug(&format!("⚡ [DNS-CACHE] Hit for {} ({:?})", name, question.query_type()));
+            return Ok(Self::build_cached_response(&query, cached));
+        }
+        
+        let response = Self::handle_query(query.clone(), entries, domain_suffix)?;
+        if response.answer_count() > 0 || Self::is_local_name(&name, domain_suffix) || upstream_servers.is_empty() {
+            cache.insert(&name, question.query_type(), response.answers().to_vec(), response.response_code(), true);
+            return Ok(response);
+        }
+        
+        // Not a container name - forward to the host's resolvers
+        cache.forwarded.fetch_add(1, Ordering::Relaxed);
+        match Self::forward_query(&query, upstream_servers).await {
+            Ok(upstream_response) => {
+                if matches!(upstream_response.response_code(), ResponseCode::NoError | ResponseCode::NXDomain) {
+                    cache.insert(&name, question.query_type(), upstream_response.answers().to_vec(),
+                        upstream_response.response_code(), false);
+                }
+                Ok(upstream_response)
+            }
+            Err(e) => {
+                ConsoleLogger::debug(&format!("❌ [DNS-FORWARD] Upstream lookup failed for {}: {}", name, e));
+                let mut failure = response;
+                failure.set_response_code(ResponseCode::ServFail);
+                Ok(failure)
+            }
+        }
+    }
+    
+    /// Names we are authoritative for: bare container names and anything under the domain suffix
+    fn is_local_name(name: &str, domain_suffix: &str) -> bool {
+        let name = name.trim_end_matches('.');
+        !name.contains('.') || name == domain_suffix || name.ends_with(&format!(".{}", domain_suffix))
+    }
+    
+    /// Forward the raw query to each upstream in turn until one answers
+    async fn forward_query(query: &Message, upstream_servers: &[SocketAddr]) -> Result<Message, String> {
+        let query_bytes = query.to_vec().map_err(|e| format!("Failed to encode query: {}", e))?;
+        let mut last_error = "no upstream servers configured".to_string();
+        
+        for upstream in upstream_servers {
+            let bind_addr: SocketAddr = if upstream.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }
+                .parse()
+                .map_err(|e| format!("Invalid bind address: {}", e))?;
+            let socket = UdpSocket::bind(bind_addr).await
+                .map_err(|e| format!("Failed to bind forwarding socket: {}", e))?;
+            
+            if let Err(e) = socket.send_to(&query_bytes, upstream).await {
+                last_error = format!("send to {} failed: {}", upstream, e);
+                continue;
+            }
+            
+            let mut buf = vec![0u8; 4096];
+            match tokio::time::timeout(UPSTREAM_TIMEOUT, socket.recv_from(&mut buf)).await {
+                Ok(Ok((len, _))) => {
+                    let response = Message::from_vec(&buf[..len])
+                        .map_err(|e| format!("Invalid response from {}: {}", upstream, e))?;
+                    if response.id() == query.id() {
+                        return Ok(response);
+                    }
+                    last_error = format!("mismatched response id from {}", upstream);
+                }
+                Ok(Err(e)) => last_error = format!("receive from {} failed: {}", upstream, e),
+                Err(_) => last_error = format!("timeout waiting for {}", upstream),
+            }
+        }
+        
+        Err(last_error)
+    }
+    
+    /// SERVFAIL for a query the server is too busy to resolve
+    fn build_servfail_response(query: &Message) -> Message {
+        let mut response = Message::new();
+        response.set_id(query.id());
+        response.set_message_type(MessageType::Response);
+        response.set_op_code(query.op_code());
+        response.set_recursion_desired(query.recursion_desired());
+        response.set_recursion_available(true);
+        for q in query.queries() {
+            response.add_query(q.clone());
+        }
+        response.set_response_code(ResponseCode::ServFail);
+        response
+    }
+    
+    /// Authoritative answer for a virtual service or record: every IPv4 address for A queries, and
+    /// NXDOMAIN while there is none
+    fn build_address_response(query: &Message, question: &Query, backends: &[IpAddr], ttl: u32) -> Message {
+        let mut response = Message::new();
+        response.set_id(query.id());
+        response.set_message_type(MessageType::Response);
+        response.set_op_code(OpCode::Query);
+        response.set_authoritative(true);
+        response.set_recursion_desired(query.recursion_desired());
+        response.set_recursion_available(true);
+        response.add_query(question.clone());
+        
+        if question.query_type() == RecordType::A && question.query_class() == DNSClass::IN {
+            for backend in backends {
+                if let IpAddr::V4(ipv4) = backend {
+                    response.add_answer(Record::new()
+                        .set_name(question.name().clone())
+                        .set_ttl(ttl)
+                        .set_rr_type(RecordType::A)
+                        .set_dns_class(DNSClass::IN)
+                        .set_data(Some(RData::A(trust_dns_proto::rr::rdata::A::from(*ipv4))))
+                        .clone());
+                }
+            }
+        }
+        response.set_response_code(if backends.is_empty() { ResponseCode::NXDomain } else { ResponseCode::NoError });
+        response
+    }
+    
+    /// Build a response to `query` from a cached answer
+    fn build_cached_response(query: &Message, cached: CachedAnswer) -> Message {
+        let mut response = Message::new();
+        response.set_id(query.id());
+        response.set_message_type(MessageType::Response);
+        response.set_op_code(OpCode::Query);
+        response.set_authoritative(cached.authoritative);
+        response.set_recursion_desired(query.recursion_desired());
+        response.set_recursion_available(true);
+        for q in query.queries() {
+            response.add_query(q.clone());
+        }
+        for record in cached.answers {
+            response.add_answer(record);
+        }
+        response.set_response_code(cached.response_code);
+        response
+    }
+    
     /// Handle a DNS query
     fn handle_query(
         query: Message, 
@@ -150,7 +961,7 @@ impl DnsServer {
         response.set_op_code(OpCode::Query);
         response.set_authoritative(true);
         response.set_recursion_desired(query.recursion_desired());
-        response.set_recursion_available(false);
+        response.set_recursion_available(true);
         
         // Copy the query to the response
         for q in query.queries() {
@@ -223,6 +1034,17 @@ impl DnsServer {
                             ConsoleLogger::debug(&format!("✅ [DNS-ADD-FALLBACK] Added answer to response. Total answers: {}", response.answer_count()));
                             ConsoleLogger::debug(&format!("DNS: Resolved {} -> {}", short_name, ipv4));
                         }
+                    } else if let Some(entry) = Self::wildcard_owner(&entries, &name, domain_suffix) {
+                        if let IpAddr::V4(ipv4) = entry.ip_address {
+                            response.add_answer(Record::new()
+                                .set_name(query.name().clone())
+                                .set_ttl(entry.ttl)
+                                .set_rr_type(RecordType::A)
+                                .set_dns_class(DNSClass::IN)
+                                .set_data(Some(RData::A(trust_dns_proto::rr::rdata::A::from(ipv4))))
+                                .clone());
+                            ConsoleLogger::debug(&format!("DNS: Resolved {} -> {} (wildcard of {})", name, ipv4, entry.container_name));
+                        }
                     } else {
                         ConsoleLogger::debug(&format!("❌ [DNS-NOTFOUND] Name not found: '{}' (short: '{}')", name, short_name));
                         
@@ -230,6 +1052,22 @@ impl DnsServer {
                         ConsoleLogger::debug(&format!("🔧 [DNS-DEBUG] Available entries: {:?}", entries.keys().collect::<Vec<_>>()));
                     }
                 }
+            } else if query.query_type() == RecordType::PTR && query.query_class() == DNSClass::IN {
+                let entries = entries.read().map_err(|e| format!("Failed to read entries: {}", e))?;
+                let owner = Self::reverse_address(&query.name().to_string())
+                    .and_then(|ip| entries.values().find(|entry| entry.ip_address == ip));
+                if let Some(entry) = owner {
+                    let target = Name::from_ascii(format!("{}.{}.", entry.container_name, domain_suffix))
+                        .map_err(|e| format!("Invalid DNS name for {}: {}", entry.container_name, e))?;
+                    response.add_answer(Record::new()
+                        .set_name(query.name().clone())
+                        .set_ttl(entry.ttl)
+                        .set_rr_type(RecordType::PTR)
+                        .set_dns_class(DNSClass::IN)
+                        .set_data(Some(RData::PTR(trust_dns_proto::rr::rdata::PTR(target))))
+                        .clone());
+                    ConsoleLogger::debug(&format!("DNS: Reverse {} -> {}.{}", entry.ip_address, entry.container_name, domain_suffix));
+                }
             }
         }
         
@@ -243,6 +1081,33 @@ impl DnsServer {
         Ok(response)
     }
     
+    /// The container with wildcard names that owns `name`: the nearest parent of `name` under
+    /// the domain suffix that is a registered name of such a container
+    fn wildcard_owner<'a>(entries: &'a HashMap<String, DnsEntry>, name: &str, domain_suffix: &str) -> Option<&'a DnsEntry> {
+        let name = name.trim_end_matches('.');
+        let zone = format!(".{}", domain_suffix);
+        let mut parent = name.strip_suffix(&zone)?;
+        while let Some((_, rest)) = parent.split_once('.') {
+            if let Some(entry) = entries.get(&format!("{}{}", rest, zone)).filter(|entry| entry.wildcard) {
+                return Some(entry);
+            }
+            parent = rest;
+        }
+        None
+    }
+    
+    /// The IPv4 address a reverse lookup name such as `5.0.42.10.in-addr.arpa.` stands for
+    fn reverse_address(name: &str) -> Option<IpAddr> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let octets = name.strip_suffix(&format!(".{}", REVERSE_ZONE_V4))?;
+        let mut octets: Vec<u8> = octets.split('.').map(|octet| octet.parse().ok()).collect::<Option<_>>()?;
+        if octets.len() != 4 {
+            return None;
+        }
+        octets.reverse();
+        Some(IpAddr::from([octets[0], octets[1], octets[2], octets[3]]))
+    }
+    
     /// Get all registered containers
     pub fn list_entries(&self) -> Result<Vec<DnsEntry>, String> {
         let entries = self.entries.read()
@@ -281,4 +1146,227 @@ mod tests {
         let entries = dns.list_entries().unwrap();
         assert_eq!(entries.len(), 0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_dns_aliases() {
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap());
+        let entries = dns.entries.clone();
+        let resolves = |name: &str| entries.read().unwrap().get(name).map(|entry| entry.container_id.clone());
+        
+        dns.register_container("container-123", "web-server", "10.42.0.5").unwrap();
+        dns.register_container("container-456", "db", "10.42.0.6").unwrap();
+        assert!(dns.add_alias("missing", "www").is_err());
+        
+        dns.add_alias("container-123", "www").unwrap();
+        dns.add_alias("container-123", "frontend").unwrap();
+        assert!(dns.add_alias("container-456", "www").is_err());
+        assert!(dns.add_alias("container-456", "web-server").is_err());
+        assert_eq!(resolves("frontend.quilt.local").as_deref(), Some("container-123"));
+        
+        // A restart re-registers the container at a new address and keeps its aliases
+        dns.register_container("container-123", "web-server", "10.42.0.7").unwrap();
+        let web = dns.list_entries().unwrap().into_iter().find(|entry| entry.container_id == "container-123").unwrap();
+        assert_eq!(web.aliases, vec!["www", "frontend"]);
+        assert_eq!(entries.read().unwrap()["www"].ip_address, IpAddr::from_str("10.42.0.7").unwrap());
+        
+        assert!(dns.remove_alias("web-server").is_err());
+        assert_eq!(dns.remove_alias("www").unwrap(), "container-123");
+        assert_eq!(resolves("www"), None);
+        assert_eq!(resolves("www.quilt.local"), None);
+        assert_eq!(entries.read().unwrap()["container-123"].aliases, vec!["frontend"]);
+        
+        dns.unregister_container("container-123").unwrap();
+        assert_eq!(resolves("frontend"), None);
+    }
+    
+    #[test]
+    fn test_dns_take_over() {
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap());
+        let entries = dns.entries.clone();
+        let resolves = |name: &str| entries.read().unwrap().get(name).map(|entry| entry.container_id.clone());
+        
+        dns.register_container("blue", "web", "10.42.0.5").unwrap();
+        dns.add_alias("blue", "www").unwrap();
+        dns.register_container("green", "brave_otter", "10.42.0.6").unwrap();
+        assert!(dns.take_over("blue", "missing", "web").is_err());
+        
+        dns.take_over("blue", "green", "web").unwrap();
+        for name in ["web", "web.quilt.local", "www", "www.quilt.local", "green"] {
+            assert_eq!(resolves(name).as_deref(), Some("green"), "{}", name);
+        }
+        assert_eq!(resolves("blue"), None);
+        assert_eq!(resolves("brave_otter"), None);
+        assert_eq!(entries.read().unwrap()["web"].ip_address, IpAddr::from_str("10.42.0.6").unwrap());
+        assert_eq!(entries.read().unwrap()["green"].aliases, vec!["www"]);
+    }
+    
+    #[test]
+    fn test_wildcard_and_reverse_lookups() {
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap());
+        dns.register_container("container-123", "app1", "10.42.0.5").unwrap();
+        dns.add_alias("container-123", "www").unwrap();
+        dns.register_container("container-456", "db", "10.42.0.6").unwrap();
+        let answer = |name: &str, record_type: RecordType| {
+            let mut query = Message::new();
+            query.add_query(Query::query(Name::from_str(name).unwrap(), record_type));
+            let response = DnsServer::handle_query(query, &dns.entries, "quilt.local").unwrap();
+            response.answers().first().and_then(|record| record.data().cloned())
+        };
+        let app1 = Some(RData::A(trust_dns_proto::rr::rdata::A::new(10, 42, 0, 5)));
+        
+        assert_eq!(answer("api.app1.quilt.local.", RecordType::A), None);
+        assert!(dns.set_wildcard("missing", true).is_err());
+        dns.set_wildcard("container-123", true).unwrap();
+        assert_eq!(answer("api.app1.quilt.local.", RecordType::A), app1);
+        assert_eq!(answer("v2.api.www.quilt.local.", RecordType::A), app1);
+        assert_eq!(answer("app1.quilt.local.", RecordType::A), app1);
+        assert_eq!(answer("api.db.quilt.local.", RecordType::A), None);
+        assert_eq!(answer("api.app1.example.com.", RecordType::A), None);
+        
+        // Re-registering after a restart starts without wildcards until they are set again
+        dns.register_container("container-123", "app1", "10.42.0.5").unwrap();
+        assert_eq!(answer("api.app1.quilt.local.", RecordType::A), None);
+        
+        let ptr = |name: &str| Some(RData::PTR(trust_dns_proto::rr::rdata::PTR(Name::from_str(name).unwrap())));
+        assert_eq!(answer("5.0.42.10.in-addr.arpa.", RecordType::PTR), ptr("app1.quilt.local."));
+        assert_eq!(answer("6.0.42.10.in-addr.arpa.", RecordType::PTR), ptr("db.quilt.local."));
+        assert_eq!(answer("7.0.42.10.in-addr.arpa.", RecordType::PTR), None);
+        assert_eq!(DnsServer::reverse_address("5.0.42.in-addr.arpa"), None);
+        assert_eq!(DnsServer::reverse_address("5.0.42.300.in-addr.arpa."), None);
+        
+        dns.unregister_container("container-456").unwrap();
+        assert_eq!(answer("6.0.42.10.in-addr.arpa.", RecordType::PTR), None);
+    }
+    
+    #[test]
+    fn test_virtual_service_rotation() {
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap());
+        let web: Vec<IpAddr> = ["10.42.0.5", "10.42.0.6", "10.42.0.7"].iter().map(|ip| ip.parse().unwrap()).collect();
+        dns.set_service_backends("Web", web.clone()).unwrap();
+        
+        let first = dns.services.rotated("web.quilt.local.", "quilt.local").unwrap();
+        let second = dns.services.rotated("web", "quilt.local").unwrap();
+        assert_eq!(first.len(), 3);
+        assert_ne!(first[0], second[0]);
+        assert_eq!(second[0], first[1]);
+        
+        dns.set_service_backends("web", vec![]).unwrap();
+        assert_eq!(dns.services.rotated("web", "quilt.local"), Some(vec![]));
+        dns.remove_service("web").unwrap();
+        assert_eq!(dns.services.rotated("web", "quilt.local"), None);
+    }
+    
+    #[test]
+    fn test_view_records() {
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap());
+        dns.register_container("container-123", "web", "10.42.0.5").unwrap();
+        let vip: IpAddr = "10.42.255.10".parse().unwrap();
+        let public: IpAddr = "203.0.113.7".parse().unwrap();
+        dns.set_record("API", DnsView::Containers, vec![vip]).unwrap();
+        dns.set_record("api", DnsView::All, vec![public]).unwrap();
+        dns.set_record("db.example.com", DnsView::Host, vec![public]).unwrap();
+        
+        assert_eq!(DnsServer::view_of(&dns.entries, "10.42.0.5".parse().unwrap()), DnsView::Containers);
+        assert_eq!(DnsServer::view_of(&dns.entries, "10.42.0.1".parse().unwrap()), DnsView::Host);
+        
+        let records = &dns.records;
+        assert_eq!(records.lookup("api.quilt.local.", DnsView::Containers, "quilt.local"), Some(vec![vip]));
+        assert_eq!(records.lookup("api", DnsView::Host, "quilt.local"), Some(vec![public]));
+        assert_eq!(records.lookup("DB.example.com.", DnsView::Host, "quilt.local"), Some(vec![public]));
+        assert_eq!(records.lookup("db.example.com.", DnsView::Containers, "quilt.local"), None);
+        
+        dns.remove_record("api", DnsView::Containers).unwrap();
+        assert_eq!(records.lookup("api", DnsView::Containers, "quilt.local"), Some(vec![public]));
+        
+        assert_eq!(DnsView::parse(""), Ok(DnsView::All));
+        assert_eq!(DnsView::parse(DnsView::Host.as_str()), Ok(DnsView::Host));
+        assert!(DnsView::parse("guests").is_err());
+    }
+    
+    #[test]
+    fn test_cluster_domain() {
+        let search = vec!["Corp.Example.com.".to_string(), "cluster.internal".to_string(), "corp.example.com".to_string()];
+        let domain = DnsDomainConfig::new("Cluster.Internal.", &search).unwrap();
+        assert_eq!(domain.search_list(), vec!["cluster.internal", "corp.example.com"]);
+        assert_eq!(domain.resolv_conf("10.42.0.1"), "nameserver 10.42.0.1\nsearch cluster.internal corp.example.com\n");
+        assert_eq!(DnsDomainConfig::default().resolv_conf("10.42.0.1"), "nameserver 10.42.0.1\nsearch quilt.local\n");
+        assert!(DnsDomainConfig::new("bad domain", &[]).is_err());
+        assert!(DnsDomainConfig::new("quilt.local", &["a..b".to_string()]).is_err());
+        let many: Vec<String> = (0..MAX_SEARCH_DOMAINS).map(|i| format!("d{}.example", i)).collect();
+        assert!(DnsDomainConfig::new("quilt.local", &many).is_err());
+        
+        let dns = DnsServer::new("10.42.0.1:1053".parse().unwrap()).with_domain(&domain.cluster_domain);
+        dns.register_container("container-123", "web", "10.42.0.5").unwrap();
+        assert!(dns.entries.read().unwrap().contains_key("web.cluster.internal"));
+        assert!(DnsServer::is_local_name("web.cluster.internal.", dns.domain_suffix()));
+    }
+    
+    #[test]
+    fn test_dns_cache_lru_eviction() {
+        let cache = DnsCache::new(2);
+        let record = |name: &str| Record::new()
+            .set_name(trust_dns_proto::rr::Name::from_str(name).unwrap())
+            .set_ttl(60)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_data(Some(RData::A(trust_dns_proto::rr::rdata::A::new(10, 42, 0, 5))))
+            .clone();
+        
+        cache.insert("a.quilt.local.", RecordType::A, vec![record("a.quilt.local.")], ResponseCode::NoError, true);
+        cache.insert("b.quilt.local.", RecordType::A, vec![record("b.quilt.local.")], ResponseCode::NoError, true);
+        
+        // Touch "a" so "b" becomes least recently used
+        assert!(cache.get("A.quilt.local", RecordType::A).is_some());
+        cache.insert("c.quilt.local.", RecordType::A, vec![record("c.quilt.local.")], ResponseCode::NoError, true);
+        
+        assert!(cache.get("b.quilt.local.", RecordType::A).is_none());
+        assert!(cache.get("c.quilt.local.", RecordType::A).is_some());
+        
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entries, 2);
+        
+        assert_eq!(cache.flush(), 2);
+        assert_eq!(cache.stats().entries, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_probe_and_restart() {
+        let dns = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        let port = dns.start_with_fallback(&[0]).await.unwrap();
+        assert_ne!(port, 0);
+        assert!(dns.is_listening());
+        dns.probe(Duration::from_secs(2)).await.unwrap();
+        
+        dns.stop();
+        assert!(!dns.is_listening());
+        
+        dns.restart(&[0]).await.unwrap();
+        assert!(dns.is_listening());
+        dns.probe(Duration::from_secs(2)).await.unwrap();
+        dns.stop();
+    }
+    
+    #[test]
+    fn test_local_name_detection() {
+        assert!(DnsServer::is_local_name("web", "quilt.local"));
+        assert!(DnsServer::is_local_name("web.quilt.local.", "quilt.local"));
+        assert!(!DnsServer::is_local_name("example.com.", "quilt.local"));
+    }
+    
+    #[test]
+    fn test_servfail_response() {
+        let mut query = Message::new();
+        query.set_id(7);
+        query.set_message_type(MessageType::Query);
+        query.add_query(Query::query(Name::from_ascii("example.com.").unwrap(), RecordType::A));
+        
+        let response = DnsServer::build_servfail_response(&query);
+        assert_eq!(response.id(), 7);
+        assert_eq!(response.message_type(), MessageType::Response);
+        assert_eq!(response.response_code(), ResponseCode::ServFail);
+        assert_eq!(response.queries(), query.queries());
+    }
+}
@@ -0,0 +1,259 @@
+// src/icc/mdns.rs
+// Optional mDNS responder advertising containers as <name>.local on the host LAN
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::unix::io::FromRawFd;
+use std::sync::{Arc, RwLock};
+use tokio::net::UdpSocket;
+use trust_dns_proto::op::{Message, MessageType, OpCode};
+use trust_dns_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use crate::utils::console::ConsoleLogger;
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_PORT: u16 = 5353;
+const MDNS_TTL: u32 = 120;
+
+/// Environment variable that enables the responder
+pub const MDNS_ENABLE_ENV: &str = "QUILT_MDNS";
+/// Environment variable overriding the advertised address (defaults to the host's LAN address)
+pub const MDNS_ADDRESS_ENV: &str = "QUILT_MDNS_ADDRESS";
+
+/// Answers mDNS queries for `<container-name>.local`.
+/// Container bridge addresses are not routable from the LAN, so names resolve to the
+/// host's LAN address where published ports are reachable.
+pub struct MdnsResponder {
+    names: Arc<RwLock<HashMap<String, String>>>, // hostname label -> container id
+    advertise_ip: Ipv4Addr,
+    socket: Arc<UdpSocket>,
+}
+
+impl MdnsResponder {
+    /// Returns true when mDNS advertisement was requested via the environment
+    pub fn enabled_from_env() -> bool {
+        matches!(std::env::var(MDNS_ENABLE_ENV).as_deref(), Ok("1") | Ok("true") | Ok("yes"))
+    }
+
+    /// Bind the mDNS socket and start answering queries
+    pub async fn start() -> Result<Self, String> {
+        let advertise_ip = match std::env::var(MDNS_ADDRESS_ENV) {
+            Ok(addr) => addr.parse::<Ipv4Addr>()
+                .map_err(|e| format!("Invalid {} '{}': {}", MDNS_ADDRESS_ENV, addr, e))?,
+            Err(_) => Self::detect_lan_address()?,
+        };
+
+        let socket = Arc::new(Self::bind_multicast_socket(advertise_ip)?);
+        let names = Arc::new(RwLock::new(HashMap::new()));
+
+        let loop_socket = socket.clone();
+        let loop_names = names.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 9000];
+            loop {
+                match loop_socket.recv_from(&mut buf).await {
+                    Ok((len, src)) => {
+                        let query = match Message::from_vec(&buf[..len]) {
+                            Ok(query) if query.message_type() == MessageType::Query => query,
+                            _ => continue,
+                        };
+                        let response = match Self::answer(&query, &loop_names, advertise_ip) {
+                            Some(response) => response,
+                            None => continue,
+                        };
+                        // Legacy unicast queries (source port != 5353) get a direct reply
+                        let dest = if src.port() == MDNS_PORT {
+                            SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT))
+                        } else {
+                            src
+                        };
+                        if let Ok(bytes) = response.to_vec() {
+                            let _ = loop_socket.send_to(&bytes, dest).await;
+                        }
+                    }
+                    Err(e) => {
+                        ConsoleLogger::warning(&format!("mDNS: Failed to receive packet: {}", e));
+                    }
+                }
+            }
+        });
+
+        ConsoleLogger::info(&format!("mDNS responder advertising containers as <name>.local -> {}", advertise_ip));
+        Ok(Self { names, advertise_ip, socket })
+    }
+
+    /// Advertise a container name and announce it to the LAN
+    pub fn register(&self, container_id: &str, container_name: &str) -> Result<(), String> {
+        let label = Self::hostname_label(container_name)
+            .ok_or_else(|| format!("'{}' is not a valid mDNS hostname", container_name))?;
+
+        self.names.write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+            .insert(label.clone(), container_id.to_string());
+
+        self.announce(&label, MDNS_TTL);
+        ConsoleLogger::debug(&format!("mDNS: Advertised {}.local -> {}", label, self.advertise_ip));
+        Ok(())
+    }
+
+    /// Withdraw all names for a container, sending a goodbye (TTL 0) announcement
+    pub fn unregister(&self, container_id: &str) -> Result<(), String> {
+        let removed: Vec<String> = {
+            let mut names = self.names.write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            let labels: Vec<String> = names.iter()
+                .filter(|(_, id)| id.as_str() == container_id)
+                .map(|(label, _)| label.clone())
+                .collect();
+            for label in &labels {
+                names.remove(label);
+            }
+            labels
+        };
+
+        for label in removed {
+            self.announce(&label, 0);
+            ConsoleLogger::debug(&format!("mDNS: Withdrew {}.local", label));
+        }
+        Ok(())
+    }
+
+    fn announce(&self, label: &str, ttl: u32) {
+        let mut message = Message::new();
+        message.set_message_type(MessageType::Response);
+        message.set_op_code(OpCode::Query);
+        message.set_authoritative(true);
+        if let Some(record) = Self::a_record(label, self.advertise_ip, ttl) {
+            message.add_answer(record);
+        }
+        if let Ok(bytes) = message.to_vec() {
+            let dest = SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT));
+            // Announcements are best-effort; queries are still answered if one is dropped
+            if let Err(e) = self.socket.try_send_to(&bytes, dest) {
+                ConsoleLogger::debug(&format!("mDNS: Announcement for {}.local failed: {}", label, e));
+            }
+        }
+    }
+
+    /// Build a response for the questions we own, or None if nothing matched
+    fn answer(query: &Message, names: &RwLock<HashMap<String, String>>, advertise_ip: Ipv4Addr) -> Option<Message> {
+        let names = names.read().ok()?;
+        let mut response = Message::new();
+        response.set_id(query.id());
+        response.set_message_type(MessageType::Response);
+        response.set_op_code(OpCode::Query);
+        response.set_authoritative(true);
+
+        for question in query.queries() {
+            if !matches!(question.query_type(), RecordType::A | RecordType::ANY) {
+                continue;
+            }
+            let fqdn = question.name().to_string().to_ascii_lowercase();
+            let label = match fqdn.trim_end_matches('.').strip_suffix(".local") {
+                Some(label) => label,
+                None => continue,
+            };
+            if names.contains_key(label) {
+                if let Some(record) = Self::a_record(label, advertise_ip, MDNS_TTL) {
+                    response.add_query(question.clone());
+                    response.add_answer(record);
+                }
+            }
+        }
+
+        if response.answer_count() == 0 {
+            None
+        } else {
+            Some(response)
+        }
+    }
+
+    fn a_record(label: &str, ip: Ipv4Addr, ttl: u32) -> Option<Record> {
+        let name = Name::from_ascii(format!("{}.local.", label)).ok()?;
+        Some(Record::new()
+            .set_name(name)
+            .set_ttl(ttl)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_data(Some(RData::A(trust_dns_proto::rr::rdata::A::from(ip))))
+            .clone())
+    }
+
+    /// Convert a container name into a DNS label (lowercase alphanumerics and hyphens)
+    fn hostname_label(container_name: &str) -> Option<String> {
+        let label: String = container_name.to_ascii_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let label = label.trim_matches('-').to_string();
+        if label.is_empty() || label.len() > 63 {
+            None
+        } else {
+            Some(label)
+        }
+    }
+
+    /// Find the address the host uses to reach the LAN (no packets are sent)
+    fn detect_lan_address() -> Result<Ipv4Addr, String> {
+        let probe = std::net::UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| format!("Failed to create probe socket: {}", e))?;
+        probe.connect(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT))
+            .map_err(|e| format!("Failed to determine LAN address: {}", e))?;
+        match probe.local_addr().map_err(|e| format!("Failed to read probe address: {}", e))?.ip() {
+            IpAddr::V4(ip) if !ip.is_unspecified() => Ok(ip),
+            other => Err(format!("No usable IPv4 LAN address (got {})", other)),
+        }
+    }
+
+    /// Bind 0.0.0.0:5353 with SO_REUSEADDR/SO_REUSEPORT so we coexist with avahi, and join the group
+    fn bind_multicast_socket(interface: Ipv4Addr) -> Result<UdpSocket, String> {
+        use nix::sys::socket::{bind, setsockopt, socket, sockopt, AddressFamily, SockFlag, SockType, SockaddrIn};
+
+        let fd = socket(AddressFamily::Inet, SockType::Datagram, SockFlag::SOCK_CLOEXEC, None)
+            .map_err(|e| format!("Failed to create mDNS socket: {}", e))?;
+        // Take ownership immediately so the fd is closed on any error below
+        let std_socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+
+        setsockopt(fd, sockopt::ReuseAddr, &true)
+            .map_err(|e| format!("Failed to set SO_REUSEADDR: {}", e))?;
+        setsockopt(fd, sockopt::ReusePort, &true)
+            .map_err(|e| format!("Failed to set SO_REUSEPORT: {}", e))?;
+        bind(fd, &SockaddrIn::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT)))
+            .map_err(|e| format!("Failed to bind mDNS port {}: {}", MDNS_PORT, e))?;
+
+        std_socket.join_multicast_v4(&MDNS_GROUP, &interface)
+            .map_err(|e| format!("Failed to join mDNS multicast group: {}", e))?;
+        std_socket.set_multicast_loop_v4(true)
+            .map_err(|e| format!("Failed to enable multicast loopback: {}", e))?;
+        std_socket.set_nonblocking(true)
+            .map_err(|e| format!("Failed to set mDNS socket non-blocking: {}", e))?;
+
+        UdpSocket::from_std(std_socket)
+            .map_err(|e| format!("Failed to register mDNS socket with runtime: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostname_label() {
+        assert_eq!(MdnsResponder::hostname_label("Web_Server"), Some("web-server".to_string()));
+        assert_eq!(MdnsResponder::hostname_label("--"), None);
+    }
+
+    #[test]
+    fn test_answer_matches_registered_names() {
+        let names = RwLock::new(HashMap::from([("web".to_string(), "container-1".to_string())]));
+        let ip = Ipv4Addr::new(192, 168, 1, 20);
+
+        let mut query = Message::new();
+        query.add_query(trust_dns_proto::op::Query::query(Name::from_ascii("web.local.").unwrap(), RecordType::A));
+        let response = MdnsResponder::answer(&query, &names, ip).expect("registered name should be answered");
+        assert_eq!(response.answer_count(), 1);
+
+        let mut other = Message::new();
+        other.add_query(trust_dns_proto::op::Query::query(Name::from_ascii("db.local.").unwrap(), RecordType::A));
+        assert!(MdnsResponder::answer(&other, &names, ip).is_none());
+    }
+}
@@ -3,10 +3,53 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 use crate::utils::console::ConsoleLogger;
 
+pub const DEFAULT_SERVICE_QUEUE_DEPTH: usize = 64;
+pub const MAX_SERVICE_QUEUE_DEPTH: usize = 1024;
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+pub const MAX_PAYLOAD_BYTES: usize = 4 * 1024 * 1024;
+
+/// A request waiting to be picked up by the container that owns the service
+#[derive(Debug, Clone)]
+pub struct ServiceRequest {
+    pub request_id: String,
+    pub from_container: String,
+    pub payload: Vec<u8>,
+}
+
+/// Registered service as reported to clients
+#[derive(Debug, Clone)]
+pub struct ServiceInfo {
+    pub service: String,
+    pub owner: String,
+    pub queued_requests: usize,
+    pub queue_depth: usize,
+}
+
+/// A named service: requests are queued on a bounded channel so a slow
+/// service pushes back on callers instead of growing without limit
+struct ServiceEndpoint {
+    owner: String,
+    queue_depth: usize,
+    sender: mpsc::Sender<ServiceRequest>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<ServiceRequest>>>,
+}
+
+/// A caller awaiting the reply correlated by request ID
+struct PendingRequest {
+    service: String,
+    owner: String,
+    reply: oneshot::Sender<Result<Vec<u8>, String>>,
+}
+
 pub struct MessageBroker {
     channels: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    services: Arc<Mutex<HashMap<String, ServiceEndpoint>>>,
+    pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
 }
 
 #[allow(dead_code)]  // Inter-container messaging methods for future features
@@ -14,6 +57,8 @@ impl MessageBroker {
     pub fn new() -> Self {
         Self {
             channels: Arc::new(Mutex::new(HashMap::new())),
+            services: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
@@ -46,11 +91,264 @@ impl MessageBroker {
         }
     }
     
-    /// Clear all messages for a container (cleanup)
+    /// Clear all messages and services for a container (cleanup)
     pub fn cleanup_container(&self, container_id: &str) {
         if let Ok(mut channels) = self.channels.lock() {
             channels.remove(container_id);
             ConsoleLogger::debug(&format!("🧹 Cleaned up messages for container {}", container_id));
         }
+        
+        let owned: Vec<String> = match self.services.lock() {
+            Ok(services) => services.iter()
+                .filter(|(_, endpoint)| endpoint.owner == container_id)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            Err(_) => return,
+        };
+        for service in owned {
+            let _ = self.unregister_service(&service, container_id);
+        }
+    }
+    
+    /// Register a named request/response service owned by a container
+    pub fn register_service(&self, service: &str, container_id: &str, queue_depth: usize) -> Result<(), String> {
+        Self::validate_service_name(service)?;
+        let queue_depth = if queue_depth == 0 { DEFAULT_SERVICE_QUEUE_DEPTH } else { queue_depth };
+        if queue_depth > MAX_SERVICE_QUEUE_DEPTH {
+            return Err(format!("Queue depth cannot exceed {}", MAX_SERVICE_QUEUE_DEPTH));
+        }
+        
+        let mut services = self.services.lock()
+            .map_err(|_| "Failed to acquire services lock".to_string())?;
+        if let Some(existing) = services.get(service) {
+            if existing.owner == container_id {
+                return Ok(());
+            }
+            return Err(format!("Service '{}' is already registered by container {}", service, existing.owner));
+        }
+        
+        let (sender, receiver) = mpsc::channel(queue_depth);
+        services.insert(service.to_string(), ServiceEndpoint {
+            owner: container_id.to_string(),
+            queue_depth,
+            sender,
+            receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+        });
+        
+        ConsoleLogger::info(&format!("📮 Service '{}' registered by container {}", service, container_id));
+        Ok(())
+    }
+    
+    /// Remove a service; callers still waiting on it fail immediately
+    pub fn unregister_service(&self, service: &str, container_id: &str) -> Result<(), String> {
+        {
+            let mut services = self.services.lock()
+                .map_err(|_| "Failed to acquire services lock".to_string())?;
+            match services.get(service) {
+                Some(endpoint) if endpoint.owner == container_id => {
+                    services.remove(service);
+                }
+                Some(endpoint) => {
+                    return Err(format!("Service '{}' is owned by container {}", service, endpoint.owner));
+                }
+                None => return Err(format!("Service '{}' is not registered", service)),
+            }
+        }
+        
+        // Dropping the reply senders wakes the waiting callers with an error
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|_, request| request.service != service);
+        }
+        
+        ConsoleLogger::info(&format!("📮 Service '{}' unregistered", service));
+        Ok(())
+    }
+    
+    pub fn list_services(&self) -> Vec<ServiceInfo> {
+        let services = match self.services.lock() {
+            Ok(services) => services,
+            Err(_) => return Vec::new(),
+        };
+        let mut infos: Vec<ServiceInfo> = services.iter().map(|(name, endpoint)| ServiceInfo {
+            service: name.clone(),
+            owner: endpoint.owner.clone(),
+            queued_requests: endpoint.queue_depth - endpoint.sender.capacity(),
+            queue_depth: endpoint.queue_depth,
+        }).collect();
+        infos.sort_by(|a, b| a.service.cmp(&b.service));
+        infos
+    }
+    
+    /// Send a request to a service and wait for the correlated reply.
+    /// Fails fast when the service queue is full rather than blocking the caller.
+    pub async fn send_request(&self, from_container: &str, service: &str, payload: Vec<u8>, timeout: Duration) -> Result<Vec<u8>, String> {
+        if payload.len() > MAX_PAYLOAD_BYTES {
+            return Err(format!("Payload exceeds {} bytes", MAX_PAYLOAD_BYTES));
+        }
+        let timeout = timeout.min(MAX_REQUEST_TIMEOUT);
+        
+        let (sender, owner) = {
+            let services = self.services.lock()
+                .map_err(|_| "Failed to acquire services lock".to_string())?;
+            let endpoint = services.get(service)
+                .ok_or_else(|| format!("Service '{}' is not registered", service))?;
+            (endpoint.sender.clone(), endpoint.owner.clone())
+        };
+        
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending.lock()
+            .map_err(|_| "Failed to acquire pending lock".to_string())?
+            .insert(request_id.clone(), PendingRequest {
+                service: service.to_string(),
+                owner,
+                reply: reply_tx,
+            });
+        
+        let request = ServiceRequest {
+            request_id: request_id.clone(),
+            from_container: from_container.to_string(),
+            payload,
+        };
+        if let Err(e) = sender.try_send(request) {
+            self.forget_pending(&request_id);
+            return Err(match e {
+                mpsc::error::TrySendError::Full(_) => format!("Service '{}' is busy: request queue is full", service),
+                mpsc::error::TrySendError::Closed(_) => format!("Service '{}' is no longer available", service),
+            });
+        }
+        
+        ConsoleLogger::debug(&format!("📨 Request {} from {} queued for service '{}'", request_id, from_container, service));
+        
+        match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("Service '{}' went away before replying", service)),
+            Err(_) => {
+                self.forget_pending(&request_id);
+                Err(format!("Service '{}' did not reply within {:?}", service, timeout))
+            }
+        }
+    }
+    
+    /// Wait up to `wait` for the next request addressed to a service owned by `container_id`.
+    /// Requests whose callers have already given up are skipped.
+    pub async fn receive_request(&self, service: &str, container_id: &str, wait: Duration) -> Result<Option<ServiceRequest>, String> {
+        let receiver = {
+            let services = self.services.lock()
+                .map_err(|_| "Failed to acquire services lock".to_string())?;
+            let endpoint = services.get(service)
+                .ok_or_else(|| format!("Service '{}' is not registered", service))?;
+            if endpoint.owner != container_id {
+                return Err(format!("Service '{}' is owned by container {}", service, endpoint.owner));
+            }
+            endpoint.receiver.clone()
+        };
+        
+        let deadline = tokio::time::Instant::now() + wait.min(MAX_REQUEST_TIMEOUT);
+        let mut receiver = match tokio::time::timeout_at(deadline, receiver.lock()).await {
+            Ok(receiver) => receiver,
+            Err(_) => return Ok(None),
+        };
+        
+        loop {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(request)) => {
+                    let still_waiting = self.pending.lock()
+                        .map(|pending| pending.contains_key(&request.request_id))
+                        .unwrap_or(false);
+                    if still_waiting {
+                        return Ok(Some(request));
+                    }
+                }
+                Ok(None) => return Err(format!("Service '{}' was unregistered", service)),
+                Err(_) => return Ok(None),
+            }
+        }
+    }
+    
+    /// Deliver the reply (or an error) for a request to its waiting caller
+    pub fn send_reply(&self, request_id: &str, container_id: &str, result: Result<Vec<u8>, String>) -> Result<(), String> {
+        if let Ok(payload) = &result {
+            if payload.len() > MAX_PAYLOAD_BYTES {
+                return Err(format!("Payload exceeds {} bytes", MAX_PAYLOAD_BYTES));
+            }
+        }
+        
+        let request = {
+            let mut pending = self.pending.lock()
+                .map_err(|_| "Failed to acquire pending lock".to_string())?;
+            match pending.get(request_id) {
+                Some(request) if request.owner != container_id => {
+                    return Err(format!("Request {} belongs to service '{}' owned by another container", request_id, request.service));
+                }
+                Some(_) => pending.remove(request_id),
+                None => None,
+            }
+        };
+        
+        match request {
+            Some(request) => request.reply.send(result)
+                .map_err(|_| format!("Caller for request {} is no longer waiting", request_id)),
+            None => Err(format!("Request {} is unknown or has timed out", request_id)),
+        }
+    }
+    
+    fn forget_pending(&self, request_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(request_id);
+        }
+    }
+    
+    fn validate_service_name(service: &str) -> Result<(), String> {
+        if service.is_empty() || service.len() > 64 {
+            return Err("Service name must be 1-64 characters".to_string());
+        }
+        if !service.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            return Err(format!("Invalid service name '{}': use letters, digits, '-', '_' or '.'", service));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_request_reply_roundtrip() {
+        let broker = Arc::new(MessageBroker::new());
+        broker.register_service("echo", "server", 4).unwrap();
+        
+        let server = broker.clone();
+        let handle = tokio::spawn(async move {
+            let request = server.receive_request("echo", "server", Duration::from_secs(5)).await.unwrap().unwrap();
+            assert_eq!(request.from_container, "client");
+            server.send_reply(&request.request_id, "server", Ok(request.payload)).unwrap();
+        });
+        
+        let reply = broker.send_request("client", "echo", b"hello".to_vec(), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(reply, b"hello".to_vec());
+        handle.await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_backpressure_and_timeout() {
+        let broker = Arc::new(MessageBroker::new());
+        broker.register_service("slow", "server", 1).unwrap();
+        assert!(broker.register_service("slow", "other", 1).is_err());
+        
+        // First request occupies the only queue slot and times out unanswered
+        let first = broker.clone();
+        let waiting = tokio::spawn(async move {
+            first.send_request("client", "slow", vec![], Duration::from_millis(200)).await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        
+        let err = broker.send_request("client", "slow", vec![], Duration::from_millis(200)).await.unwrap_err();
+        assert!(err.contains("busy"));
+        assert!(waiting.await.unwrap().unwrap_err().contains("did not reply"));
+        
+        broker.cleanup_container("server");
+        assert!(broker.list_services().is_empty());
     }
 } 
\ No newline at end of file
@@ -3,6 +3,7 @@
 
 pub mod network;
 pub mod dns;
+pub mod mdns;
 pub mod messaging;
 
 // Re-export key components for easier access (none currently used) 
\ No newline at end of file
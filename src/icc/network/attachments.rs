@@ -0,0 +1,141 @@
+// Additional container networks
+// A running container can join networks besides the bridge it started on. Each network is a
+// bridge of its own, `qn<name>`, holding a /24 of 10.43.0.0/16 with the bridge at .1. Joining
+// plumbs one more veth pair into the container, named `qn<name>` inside it; it gets an address
+// and the subnet route but no default route, which stays on the primary interface. Leaving
+// deletes the pair. Which network has which subnet, and which container has which address on it,
+// is kept by the sync engine.
+
+use crate::icc::network::bridge::BridgeManager;
+use crate::icc::network::veth::VethManager;
+use crate::utils::command::{Argv, CommandExecutor};
+use crate::utils::console::ConsoleLogger;
+use std::path::Path;
+
+/// First two octets of every additional network's subnet
+pub const ATTACHED_NETWORKS_PREFIX: [u8; 2] = [10, 43];
+/// Prefix length of each additional network
+pub const ATTACHED_NETWORK_PREFIX_LEN: u8 = 24;
+
+/// Bridge of network `name` on the host, also the interface name in joined containers
+pub fn network_interface_name(name: &str) -> String {
+    format!("qn{}", name)
+}
+
+/// Address `host` (1 is the bridge) in the subnet with index `subnet_index`
+pub fn network_address(subnet_index: u8, host: u8) -> String {
+    let [a, b] = ATTACHED_NETWORKS_PREFIX;
+    format!("{}.{}.{}.{}", a, b, subnet_index, host)
+}
+
+pub fn network_subnet(subnet_index: u8) -> String {
+    format!("{}/{}", network_address(subnet_index, 0), ATTACHED_NETWORK_PREFIX_LEN)
+}
+
+/// Host-side and (until it is moved and renamed) container-side veth of a container on the
+/// network with `subnet_index`
+pub fn attachment_veth_names(container_id: &str, subnet_index: u8) -> (String, String) {
+    let short = &container_id[..container_id.len().min(8)];
+    (format!("qa{}{:02x}", short, subnet_index), format!("qb{}{:02x}", short, subnet_index))
+}
+
+/// Everything needed to plumb a container into an additional network
+#[derive(Debug, Clone)]
+pub struct NetworkAttachmentPlan {
+    pub container_id: String,
+    pub network: String,
+    pub subnet_index: u8,
+    pub ip_address: String,
+}
+
+impl NetworkAttachmentPlan {
+    pub fn bridge_name(&self) -> String {
+        network_interface_name(&self.network)
+    }
+
+    pub fn gateway_ip(&self) -> String {
+        network_address(self.subnet_index, 1)
+    }
+
+    pub fn veth_names(&self) -> (String, String) {
+        attachment_veth_names(&self.container_id, self.subnet_index)
+    }
+
+    /// Netlink operations that bring the interface up inside the container, for `ip -batch`
+    fn container_batch(&self, veth_container: &str) -> String {
+        let interface_name = self.bridge_name();
+        [
+            format!("link set {} name {}", veth_container, interface_name),
+            format!("addr replace {}/{} dev {}", self.ip_address, ATTACHED_NETWORK_PREFIX_LEN, interface_name),
+            format!("link set {} up", interface_name),
+        ].join("\n")
+    }
+}
+
+/// Create the network's bridge if needed and plumb a veth pair from it into the container whose
+/// init process is `container_pid`. A pair left over from a failed attempt is replaced.
+pub fn connect_container(plan: &NetworkAttachmentPlan, container_pid: i32) -> Result<(), String> {
+    let bridge_name = plan.bridge_name();
+    let (veth_host, veth_container) = plan.veth_names();
+    BridgeManager::new(bridge_name.clone(), plan.gateway_ip())
+        .with_prefix_len(ATTACHED_NETWORK_PREFIX_LEN)
+        .ensure_bridge_ready()?;
+
+    let veth_manager = VethManager::new(bridge_name.clone());
+    veth_manager.create_veth_pair(&veth_host, &veth_container)?;
+    let plumbed = veth_manager.move_veth_to_container(&veth_container, container_pid)
+        .and_then(|_| {
+            let batch = Argv::new("nsenter", ["-t", &container_pid.to_string(), "-n", "ip", "-batch", "-"])
+                .with_input(plan.container_batch(&veth_container));
+            let result = CommandExecutor::execute(&batch)?;
+            if !result.success {
+                return Err(format!("Failed to configure {} in container {}: {}", bridge_name, plan.container_id, result.stderr.trim()));
+            }
+            Ok(())
+        })
+        .and_then(|_| veth_manager.attach_veth_to_bridge_with_retry(&veth_host));
+    if let Err(e) = plumbed {
+        let _ = remove_veth(&veth_host);
+        return Err(e);
+    }
+
+    ConsoleLogger::success(&format!("Container {} joined network {} at {}", plan.container_id, plan.network, plan.ip_address));
+    Ok(())
+}
+
+/// Delete a container's veth pair on an additional network; one that is already gone, e.g.
+/// with the container's namespace, is fine
+pub fn remove_veth(veth_host: &str) -> Result<(), String> {
+    if !Path::new(&format!("/sys/class/net/{}", veth_host)).exists() {
+        return Ok(());
+    }
+    // Deleting one end of a pair removes the other
+    let result = CommandExecutor::run("ip", ["link", "delete", veth_host])?;
+    if !result.success && Path::new(&format!("/sys/class/net/{}", veth_host)).exists() {
+        return Err(format!("Failed to delete {}: {}", veth_host, result.stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_plan() {
+        let plan = NetworkAttachmentPlan {
+            container_id: "4f2a9c1e-77aa-4b1d-9c3e-0123456789ab".to_string(),
+            network: "backend".to_string(),
+            subnet_index: 12,
+            ip_address: network_address(12, 5),
+        };
+        assert_eq!(plan.bridge_name(), "qnbackend");
+        assert_eq!(plan.gateway_ip(), "10.43.12.1");
+        assert_eq!(network_subnet(12), "10.43.12.0/24");
+        let (veth_host, veth_container) = plan.veth_names();
+        assert_eq!((veth_host.as_str(), veth_container.as_str()), ("qa4f2a9c1e0c", "qb4f2a9c1e0c"));
+        assert!(veth_host.len() <= 15 && veth_host.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(plan.container_batch(&veth_container), "link set qb4f2a9c1e0c name qnbackend\n\
+            addr replace 10.43.12.5/24 dev qnbackend\nlink set qnbackend up");
+    }
+}
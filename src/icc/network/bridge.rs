@@ -1,7 +1,7 @@
 // Bridge management module
 // Handles Linux bridge creation, configuration, and lifecycle management
 
-use crate::utils::command::CommandExecutor;
+use crate::utils::command::{Argv, CommandExecutor};
 use crate::utils::console::ConsoleLogger;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
@@ -67,6 +67,8 @@ impl AtomicBridgeState {
 pub struct BridgeManager {
     pub bridge_name: String,
     pub bridge_ip: String,
+    /// Prefix length of the bridge's address, and so of the subnet behind it
+    pub prefix_len: u8,
     pub bridge_state: std::sync::Arc<AtomicBridgeState>,
     pub bridge_ready: AtomicBool,
 }
@@ -77,11 +79,18 @@ impl BridgeManager {
         Self {
             bridge_name,
             bridge_ip,
+            prefix_len: 16,
             bridge_state: std::sync::Arc::new(AtomicBridgeState::new()),
             bridge_ready: AtomicBool::new(false),
         }
     }
 
+    /// A bridge whose address carries a prefix other than /16
+    pub fn with_prefix_len(mut self, prefix_len: u8) -> Self {
+        self.prefix_len = prefix_len;
+        self
+    }
+
     pub fn ensure_bridge_ready(&self) -> Result<(), String> {
         ConsoleLogger::progress(&format!("Initializing network bridge: {}", self.bridge_name));
         
@@ -104,13 +113,13 @@ impl BridgeManager {
     }
 
     pub fn bridge_exists(&self) -> bool {
-        let check_cmd = format!("ip link show {}", self.bridge_name);
+        let check_cmd = Argv::new("ip", ["link", "show", &self.bridge_name]);
         ConsoleLogger::debug(&format!("Checking bridge existence: {}", check_cmd));
         
         // Add namespace debugging
         ConsoleLogger::debug(&format!("🔍 Current PID: {}", std::process::id()));
         
-        match CommandExecutor::execute_shell(&check_cmd) {
+        match CommandExecutor::execute(&check_cmd) {
             Ok(result) => {
                 let exists = result.success && result.stdout.contains(&self.bridge_name);
                 ConsoleLogger::debug(&format!("Bridge {} existence check: {} (success: {})", 
@@ -163,10 +172,11 @@ impl BridgeManager {
         ConsoleLogger::debug(&format!("🔍 [BRIDGE-VERIFY-FULL] Full verification for bridge {}", self.bridge_name));
         
         // Check 1: Bridge device exists
-        let bridge_exists = match CommandExecutor::execute_shell(&format!("ip link show {}", self.bridge_name)) {
-            Ok(result) => result.success && result.stdout.contains(&self.bridge_name),
-            Err(_) => false,
+        let link = match CommandExecutor::run("ip", ["link", "show", &self.bridge_name]) {
+            Ok(result) if result.success => result.stdout,
+            _ => String::new(),
         };
+        let bridge_exists = link.contains(&self.bridge_name);
         
         if !bridge_exists {
             ConsoleLogger::debug(&format!("❌ [BRIDGE-VERIFY] Bridge {} does not exist", self.bridge_name));
@@ -174,10 +184,7 @@ impl BridgeManager {
         }
         
         // Check 2: Bridge is UP
-        let bridge_is_up = match CommandExecutor::execute_shell(&format!("ip link show {} | grep -q 'state UP'", self.bridge_name)) {
-            Ok(result) => result.success,
-            Err(_) => false,
-        };
+        let bridge_is_up = link.contains("state UP");
         
         // Check 3: Bridge has IP address
         let bridge_has_ip = self.verify_bridge_ip_with_retry();
@@ -197,8 +204,7 @@ impl BridgeManager {
             ConsoleLogger::debug(&format!("🔍 [IP-VERIFY] Attempt {} of 3", attempt));
             
             // Method 1: ip addr show
-            let ip_check_1 = format!("ip addr show {} | grep {}", self.bridge_name, self.bridge_ip);
-            if let Ok(result) = CommandExecutor::execute_shell(&ip_check_1) {
+            if let Ok(result) = CommandExecutor::run("ip", ["addr", "show", &self.bridge_name]) {
                 if result.success && result.stdout.contains(&self.bridge_ip) {
                     ConsoleLogger::debug(&format!("✅ [IP-VERIFY] Method 1 success: IP {} found on {}", 
                         self.bridge_ip, self.bridge_name));
@@ -207,8 +213,7 @@ impl BridgeManager {
             }
             
             // Method 2: ip route get (to check if IP is routable via bridge)
-            let route_check = format!("ip route get {} | grep {}", self.bridge_ip, self.bridge_name);
-            if let Ok(result) = CommandExecutor::execute_shell(&route_check) {
+            if let Ok(result) = CommandExecutor::run("ip", ["route", "get", &self.bridge_ip]) {
                 if result.success && result.stdout.contains(&self.bridge_name) {
                     ConsoleLogger::debug(&format!("✅ [IP-VERIFY] Method 2 success: {} routable via {}", 
                         self.bridge_ip, self.bridge_name));
@@ -217,8 +222,7 @@ impl BridgeManager {
             }
             
             // Method 3: ping self test
-            let ping_check = format!("ping -c 1 -W 1 {} >/dev/null 2>&1", self.bridge_ip);
-            if let Ok(result) = CommandExecutor::execute_shell(&ping_check) {
+            if let Ok(result) = CommandExecutor::run("ping", ["-c", "1", "-W", "1", &self.bridge_ip]) {
                 if result.success {
                     ConsoleLogger::debug(&format!("✅ [IP-VERIFY] Method 3 success: {} is pingable", self.bridge_ip));
                     return true;
@@ -238,15 +242,16 @@ impl BridgeManager {
     fn create_bridge_atomic(&self) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Creating bridge atomically: {}", self.bridge_name));
         
-        // ELITE: Single compound command for complete bridge setup
-        let bridge_cidr = format!("{}/16", self.bridge_ip);
-        let atomic_bridge_cmd = format!(
-            "ip link add name {} type bridge && ip addr add {} dev {} && ip link set {} up",
-            self.bridge_name, bridge_cidr, self.bridge_name, self.bridge_name
-        );
+        // ELITE: Single ip batch for complete bridge setup; ip stops at the first failing line
+        let bridge_cidr = format!("{}/{}", self.bridge_ip, self.prefix_len);
+        let batch = [
+            format!("link add name {} type bridge", self.bridge_name),
+            format!("addr add {} dev {}", bridge_cidr, self.bridge_name),
+            format!("link set {} up", self.bridge_name),
+        ].join("\n");
         
-        ConsoleLogger::debug(&format!("Executing atomic bridge creation: {}", atomic_bridge_cmd));
-        let result = CommandExecutor::execute_shell(&atomic_bridge_cmd)?;
+        ConsoleLogger::debug(&format!("Executing atomic bridge creation: {}", batch.replace('\n', "; ")));
+        let result = CommandExecutor::execute(&Argv::new("ip", ["-batch", "-"]).with_input(batch))?;
         
         if !result.success {
             return Err(format!("Atomic bridge creation failed: {}", result.stderr));
@@ -264,19 +269,19 @@ impl BridgeManager {
     }
 
     fn configure_bridge_ip(&self) -> Result<(), String> {
-        let bridge_cidr = format!("{}/16", self.bridge_ip);
-        let check_cmd = format!("ip addr show {} | grep {}", self.bridge_name, self.bridge_ip);
+        let bridge_cidr = format!("{}/{}", self.bridge_ip, self.prefix_len);
+        let check_cmd = Argv::new("ip", ["-4", "-o", "addr", "show", "dev", &self.bridge_name]);
         
         ConsoleLogger::debug(&format!("Checking if bridge IP already assigned: {}", check_cmd));
-        if CommandExecutor::execute_shell(&check_cmd).map_or(false, |r| r.success) {
+        if CommandExecutor::execute(&check_cmd).map_or(false, |r| r.success && has_ipv4_address(&r.stdout, &self.bridge_ip)) {
             ConsoleLogger::debug(&format!("Bridge IP {} already assigned to {}", self.bridge_ip, self.bridge_name));
             return Ok(());
         }
         
-        let assign_cmd = format!("ip addr add {} dev {}", bridge_cidr, self.bridge_name);
+        let assign_cmd = Argv::new("ip", ["addr", "add", &bridge_cidr, "dev", &self.bridge_name]);
         ConsoleLogger::debug(&format!("Assigning IP to bridge: {}", assign_cmd));
         
-        let result = CommandExecutor::execute_shell(&assign_cmd)?;
+        let result = CommandExecutor::execute(&assign_cmd)?;
         if !result.success && !result.stderr.contains("File exists") {
             return Err(format!("Failed to assign IP to bridge: {}", result.stderr));
         }
@@ -286,10 +291,10 @@ impl BridgeManager {
     }
 
     fn bring_bridge_up(&self) -> Result<(), String> {
-        let up_cmd = format!("ip link set {} up", self.bridge_name);
+        let up_cmd = Argv::new("ip", ["link", "set", &self.bridge_name, "up"]);
         ConsoleLogger::debug(&format!("Executing: {}", up_cmd));
         
-        let result = CommandExecutor::execute_shell(&up_cmd)?;
+        let result = CommandExecutor::execute(&up_cmd)?;
         if !result.success {
             return Err(format!("Failed to bring bridge up: {}", result.stderr));
         }
@@ -314,9 +319,9 @@ impl BridgeManager {
     }
 
     pub fn verify_bridge_up(&self) -> Result<(), String> {
-        let check_cmd = format!("ip link show {} | grep -q '<.*UP.*>'", self.bridge_name);
         for attempt in 1..=10 {  // Fast polling instead of single 100ms delay
-            if CommandExecutor::execute_shell(&check_cmd).map_or(false, |r| r.success) {
+            let link = CommandExecutor::run("ip", ["link", "show", &self.bridge_name]);
+            if link.map_or(false, |r| r.success && link_is_admin_up(&r.stdout)) {
                 return Ok(());
             }
             if attempt < 10 {
@@ -359,7 +364,7 @@ impl BridgeManager {
         ConsoleLogger::debug(&format!("🔍 [BRIDGE-DIAG] Diagnosing bridge issues for {}", self.bridge_name));
         
         // Check if bridge exists
-        if let Ok(result) = CommandExecutor::execute_shell(&format!("ip link show {}", self.bridge_name)) {
+        if let Ok(result) = CommandExecutor::run("ip", ["link", "show", &self.bridge_name]) {
             if result.success {
                 ConsoleLogger::debug(&format!("✅ [BRIDGE-DIAG] Bridge {} exists", self.bridge_name));
                 ConsoleLogger::debug(&format!("ℹ️ [BRIDGE-DIAG] Bridge state: {}", result.stdout.trim()));
@@ -372,7 +377,7 @@ impl BridgeManager {
                 }
                 
                 // Check IP configuration
-                if let Ok(ip_result) = CommandExecutor::execute_shell(&format!("ip addr show {}", self.bridge_name)) {
+                if let Ok(ip_result) = CommandExecutor::run("ip", ["addr", "show", &self.bridge_name]) {
                     if ip_result.stdout.contains(&self.bridge_ip) {
                         ConsoleLogger::debug(&format!("✅ [BRIDGE-DIAG] Bridge {} has IP {}", self.bridge_name, self.bridge_ip));
                     } else {
@@ -391,13 +396,11 @@ impl BridgeManager {
         ConsoleLogger::debug(&format!("🔧 [BRIDGE-REPAIR] Repairing bridge {} configuration", self.bridge_name));
         
         // Try to add IP address if missing
-        let bridge_cidr = format!("{}/16", self.bridge_ip);
-        let add_ip_cmd = format!("ip addr add {} dev {} 2>/dev/null || true", bridge_cidr, self.bridge_name);
-        let _ = CommandExecutor::execute_shell(&add_ip_cmd);
+        let bridge_cidr = format!("{}/{}", self.bridge_ip, self.prefix_len);
+        let _ = CommandExecutor::run("ip", ["addr", "add", &bridge_cidr, "dev", &self.bridge_name]);
         
         // Try to bring bridge up if it's down
-        let bring_up_cmd = format!("ip link set {} up 2>/dev/null || true", self.bridge_name);
-        let _ = CommandExecutor::execute_shell(&bring_up_cmd);
+        let _ = CommandExecutor::run("ip", ["link", "set", &self.bridge_name, "up"]);
         
         // Wait a moment for changes to take effect
         std::thread::sleep(Duration::from_millis(100));
@@ -416,6 +419,53 @@ impl BridgeManager {
         }
     }
 
+    /// Re-check the bridge against the host and repair whatever is missing (device, address,
+    /// administrative up state). Returns what was repaired: `bridge` (recreated, so every veth
+    /// lost its master), `address` and/or `link`; empty when the bridge was healthy.
+    pub fn reconcile(&self) -> Result<Vec<&'static str>, String> {
+        let (exists, has_ip, is_up) = self.observe_bridge();
+        if exists && has_ip && is_up {
+            self.bridge_state.update_state(true, true, true);
+            return Ok(Vec::new());
+        }
+        
+        ConsoleLogger::warning(&format!("🔧 [BRIDGE-RECONCILE] Bridge {} drifted: exists={}, has_ip={}, is_up={}",
+            self.bridge_name, exists, has_ip, is_up));
+        self.bridge_ready.store(false, Ordering::Relaxed);
+        
+        let mut repaired = Vec::new();
+        if !exists {
+            self.create_bridge_atomic()?;
+            repaired.push("bridge");
+            return Ok(repaired);
+        }
+        if !has_ip {
+            self.configure_bridge_ip()?;
+            repaired.push("address");
+        }
+        if !is_up {
+            self.bring_bridge_up()?;
+            repaired.push("link");
+        }
+        self.bridge_state.update_state(true, true, true);
+        self.bridge_ready.store(true, Ordering::Relaxed);
+        Ok(repaired)
+    }
+    
+    /// Single read-only look at the bridge: (exists, has its address, administratively up).
+    /// Unlike `verify_bridge_state_full` this ignores carrier, since a bridge without attached
+    /// veths reports `state DOWN` while being perfectly usable.
+    fn observe_bridge(&self) -> (bool, bool, bool) {
+        let link = match CommandExecutor::run("ip", ["-o", "link", "show", &self.bridge_name]) {
+            Ok(result) if result.success => result.stdout,
+            _ => return (false, false, false),
+        };
+        let addresses = CommandExecutor::run("ip", ["-4", "-o", "addr", "show", "dev", &self.bridge_name])
+            .map(|result| result.stdout)
+            .unwrap_or_default();
+        (true, has_ipv4_address(&addresses, &self.bridge_ip), link_is_admin_up(&link))
+    }
+
     pub fn verify_bridge_ready_for_attachment_fast(&self) -> Result<(), String> {
         ConsoleLogger::debug(&format!("⚡ [BRIDGE-FAST] Fast bridge readiness check for {}", self.bridge_name));
         
@@ -436,4 +486,43 @@ impl BridgeManager {
             Err(format!("Bridge {} not ready for attachment", self.bridge_name))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Whether an `ip -o link show` line carries the administrative UP flag (not LOWER_UP)
+fn link_is_admin_up(link: &str) -> bool {
+    link.split_once('<')
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map(|(flags, _)| flags.split(',').any(|flag| flag == "UP"))
+        .unwrap_or(false)
+}
+
+/// Whether `ip -4 -o addr show` output lists `ip` as an address
+fn has_ipv4_address(addresses: &str, ip: &str) -> bool {
+    addresses.lines().any(|line| {
+        line.split_whitespace()
+            .skip_while(|field| *field != "inet")
+            .nth(1)
+            .and_then(|cidr| cidr.split('/').next())
+            == Some(ip)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_is_admin_up() {
+        assert!(link_is_admin_up("5: quilt0: <NO-CARRIER,BROADCAST,MULTICAST,UP> mtu 1500 qdisc noqueue state DOWN"));
+        assert!(!link_is_admin_up("5: quilt0: <BROADCAST,MULTICAST> mtu 1500 qdisc noop state DOWN"));
+        assert!(!link_is_admin_up("5: quilt0: <BROADCAST,MULTICAST,LOWER_UP> mtu 1500"));
+    }
+
+    #[test]
+    fn test_has_ipv4_address() {
+        let output = "5: quilt0    inet 10.42.0.1/16 scope global quilt0\\       valid_lft forever preferred_lft forever\n";
+        assert!(has_ipv4_address(output, "10.42.0.1"));
+        assert!(!has_ipv4_address(output, "10.42.0.10"));
+        assert!(!has_ipv4_address("", "10.42.0.1"));
+    }
+}
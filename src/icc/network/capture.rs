@@ -0,0 +1,208 @@
+// Packet capture module
+// Runs bounded tcpdump captures on a container's host-side veth and streams pcap bytes
+
+use crate::utils::console::ConsoleLogger;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+pub const DEFAULT_CAPTURE_SECONDS: u32 = 10;
+pub const MAX_CAPTURE_SECONDS: u32 = 300;
+pub const DEFAULT_CAPTURE_PACKETS: u32 = 1000;
+pub const MAX_CAPTURE_PACKETS: u32 = 100_000;
+const DEFAULT_SNAPLEN: u32 = 262_144;
+const MAX_FILTER_LEN: usize = 512;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Limits and filter for a single capture; zero values fall back to defaults
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    pub duration_seconds: u32,
+    pub max_packets: u32,
+    pub snaplen: u32,
+    pub filter: String,
+}
+
+impl CaptureOptions {
+    /// Apply defaults and reject limits above the hard caps
+    pub fn normalized(mut self) -> Result<Self, String> {
+        if self.duration_seconds == 0 {
+            self.duration_seconds = DEFAULT_CAPTURE_SECONDS;
+        }
+        if self.max_packets == 0 {
+            self.max_packets = DEFAULT_CAPTURE_PACKETS;
+        }
+        if self.snaplen == 0 {
+            self.snaplen = DEFAULT_SNAPLEN;
+        }
+        if self.duration_seconds > MAX_CAPTURE_SECONDS {
+            return Err(format!("Capture duration cannot exceed {} seconds", MAX_CAPTURE_SECONDS));
+        }
+        if self.max_packets > MAX_CAPTURE_PACKETS {
+            return Err(format!("Capture packet limit cannot exceed {}", MAX_CAPTURE_PACKETS));
+        }
+        if self.filter.len() > MAX_FILTER_LEN || self.filter.chars().any(|c| c.is_control()) {
+            return Err("Invalid capture filter".to_string());
+        }
+        Ok(self)
+    }
+}
+
+/// Output of a running capture
+pub enum CaptureEvent {
+    /// Raw pcap bytes (the first chunk starts with the pcap global header)
+    Data(Vec<u8>),
+    /// Capture finished; carries the packet count reported by tcpdump when available
+    Finished { packets_captured: u64 },
+    Failed(String),
+}
+
+pub struct PacketCapture;
+
+impl PacketCapture {
+    /// Start tcpdump on `interface` and forward its pcap output through the returned channel.
+    /// The capture stops at the packet or time limit, or as soon as the receiver is dropped.
+    pub fn start(interface: &str, options: CaptureOptions) -> Result<mpsc::Receiver<CaptureEvent>, String> {
+        let args = Self::build_args(interface, &options);
+        let mut child = Command::new("tcpdump")
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start tcpdump: {}", e))?;
+
+        let mut stdout = child.stdout.take().ok_or("tcpdump stdout unavailable")?;
+        let mut stderr = child.stderr.take().ok_or("tcpdump stderr unavailable")?;
+        let (tx, rx) = mpsc::channel(16);
+        let interface = interface.to_string();
+        let limit = Duration::from_secs(options.duration_seconds as u64);
+
+        ConsoleLogger::info(&format!("📦 [CAPTURE] Capturing on {} for up to {}s / {} packets",
+            interface, options.duration_seconds, options.max_packets));
+
+        tokio::spawn(async move {
+            let stderr_task = tokio::spawn(async move {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output).await;
+                output
+            });
+
+            let forward = async {
+                let mut buf = vec![0u8; CHUNK_SIZE];
+                loop {
+                    match stdout.read(&mut buf).await {
+                        Ok(0) => return true,
+                        Ok(n) => {
+                            if tx.send(CaptureEvent::Data(buf[..n].to_vec())).await.is_err() {
+                                return false; // Client went away
+                            }
+                        }
+                        Err(e) => {
+                            let _ = tx.send(CaptureEvent::Failed(format!("Failed to read capture: {}", e))).await;
+                            return false;
+                        }
+                    }
+                }
+            };
+
+            let completed = match tokio::time::timeout(limit, forward).await {
+                Ok(completed) => completed,
+                Err(_) => {
+                    // Time limit reached: SIGTERM lets tcpdump flush and print its summary
+                    if let Some(pid) = child.id() {
+                        let _ = nix::sys::signal::kill(
+                            nix::unistd::Pid::from_raw(pid as i32),
+                            nix::sys::signal::Signal::SIGTERM,
+                        );
+                    }
+                    let mut rest = Vec::new();
+                    let _ = stdout.read_to_end(&mut rest).await;
+                    if !rest.is_empty() {
+                        let _ = tx.send(CaptureEvent::Data(rest)).await;
+                    }
+                    true
+                }
+            };
+
+            if !completed {
+                let _ = child.kill().await;
+                ConsoleLogger::debug(&format!("📦 [CAPTURE] Capture on {} cancelled", interface));
+                return;
+            }
+
+            let status = child.wait().await;
+            let stderr_output = stderr_task.await.unwrap_or_default();
+            let packets_captured = Self::parse_packet_count(&stderr_output);
+
+            match (status, packets_captured) {
+                (_, Some(packets_captured)) => {
+                    ConsoleLogger::info(&format!("📦 [CAPTURE] Captured {} packets on {}", packets_captured, interface));
+                    let _ = tx.send(CaptureEvent::Finished { packets_captured }).await;
+                }
+                (Ok(status), None) if status.success() => {
+                    let _ = tx.send(CaptureEvent::Finished { packets_captured: 0 }).await;
+                }
+                _ => {
+                    let _ = tx.send(CaptureEvent::Failed(format!("tcpdump failed: {}", stderr_output.trim()))).await;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    fn build_args(interface: &str, options: &CaptureOptions) -> Vec<String> {
+        let mut args = vec![
+            "-i".to_string(), interface.to_string(),
+            "-U".to_string(),                               // Packet-buffered so data streams promptly
+            "-n".to_string(),
+            "-s".to_string(), options.snaplen.to_string(),
+            "-c".to_string(), options.max_packets.to_string(),
+            "-w".to_string(), "-".to_string(),
+        ];
+        if !options.filter.trim().is_empty() {
+            args.push(options.filter.trim().to_string());
+        }
+        args
+    }
+
+    /// Extract "N packets captured" from tcpdump's exit summary
+    fn parse_packet_count(stderr: &str) -> Option<u64> {
+        stderr.lines()
+            .find_map(|line| line.trim().strip_suffix(" packets captured")
+                .or_else(|| line.trim().strip_suffix(" packet captured")))
+            .and_then(|count| count.trim().parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_options_limits() {
+        let options = CaptureOptions { duration_seconds: 0, max_packets: 0, snaplen: 0, filter: String::new() }
+            .normalized()
+            .unwrap();
+        assert_eq!(options.duration_seconds, DEFAULT_CAPTURE_SECONDS);
+        assert_eq!(options.max_packets, DEFAULT_CAPTURE_PACKETS);
+
+        let too_long = CaptureOptions { duration_seconds: MAX_CAPTURE_SECONDS + 1, max_packets: 0, snaplen: 0, filter: String::new() };
+        assert!(too_long.normalized().is_err());
+    }
+
+    #[test]
+    fn test_build_args_and_summary() {
+        let options = CaptureOptions { duration_seconds: 5, max_packets: 10, snaplen: 128, filter: "tcp port 80".to_string() };
+        let args = PacketCapture::build_args("veth-abc", &options);
+        assert_eq!(&args[..2], &["-i", "veth-abc"]);
+        assert_eq!(args.last().map(String::as_str), Some("tcp port 80"));
+
+        let summary = "tcpdump: listening on veth-abc\n7 packets captured\n9 packets received by filter\n";
+        assert_eq!(PacketCapture::parse_packet_count(summary), Some(7));
+    }
+}
@@ -0,0 +1,236 @@
+// Connectivity test module
+// Probes a target from inside a container's network namespace: ICMP through the host's ping
+// binary under nsenter (so the container image needs no ping), TCP by connecting from a thread
+// that joined the namespace
+
+use crate::utils::command::{Argv, CommandExecutor};
+use nix::sched::CloneFlags;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PROBE_COUNT: u32 = 3;
+pub const MAX_PROBE_COUNT: u32 = 20;
+pub const DEFAULT_PROBE_TIMEOUT_SECS: u32 = 5;
+pub const MAX_PROBE_TIMEOUT_SECS: u32 = 30;
+/// Interval between ICMP probes; root may go below ping's 1s default
+const PING_INTERVAL: &str = "0.2";
+
+/// How to probe; zero values fall back to defaults
+#[derive(Debug, Clone, Default)]
+pub struct ProbeOptions {
+    pub count: u32,
+    pub timeout_seconds: u32,
+    /// TCP port to connect to; ICMP echo when unset
+    pub port: Option<u16>,
+}
+
+impl ProbeOptions {
+    /// Apply defaults and reject values above the hard caps
+    pub fn normalized(mut self) -> Result<Self, String> {
+        if self.count == 0 {
+            self.count = DEFAULT_PROBE_COUNT;
+        }
+        if self.timeout_seconds == 0 {
+            self.timeout_seconds = DEFAULT_PROBE_TIMEOUT_SECS;
+        }
+        if self.count > MAX_PROBE_COUNT {
+            return Err(format!("Probe count cannot exceed {}", MAX_PROBE_COUNT));
+        }
+        if self.timeout_seconds > MAX_PROBE_TIMEOUT_SECS {
+            return Err(format!("Probe timeout cannot exceed {} seconds", MAX_PROBE_TIMEOUT_SECS));
+        }
+        if self.port == Some(0) {
+            return Err("Invalid probe port 0".to_string());
+        }
+        Ok(self)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_seconds as u64)
+    }
+}
+
+/// Outcome of a probe run; latencies are zero when nothing answered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectivityReport {
+    pub protocol: String,
+    pub address: String,
+    pub sent: u32,
+    pub received: u32,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+impl ConnectivityReport {
+    pub fn loss_percent(&self) -> f64 {
+        if self.sent == 0 {
+            return 100.0;
+        }
+        self.sent.saturating_sub(self.received) as f64 * 100.0 / self.sent as f64
+    }
+
+    /// Report from one optional round-trip time per probe
+    fn from_samples(protocol: &str, address: &str, samples: &[Option<f64>]) -> Self {
+        let answered: Vec<f64> = samples.iter().flatten().copied().collect();
+        let mut report = Self {
+            protocol: protocol.to_string(),
+            address: address.to_string(),
+            sent: samples.len() as u32,
+            received: answered.len() as u32,
+            ..Default::default()
+        };
+        if !answered.is_empty() {
+            report.min_ms = answered.iter().copied().fold(f64::INFINITY, f64::min);
+            report.max_ms = answered.iter().copied().fold(0.0, f64::max);
+            report.avg_ms = answered.iter().sum::<f64>() / answered.len() as f64;
+        }
+        report
+    }
+}
+
+pub struct ConnectivityTest;
+
+impl ConnectivityTest {
+    /// Resolve a probe target on the host: an IP address as is, otherwise a hostname lookup
+    /// preferring IPv4, since container networks are IPv4 only
+    pub async fn resolve_target(target: &str) -> Result<IpAddr, String> {
+        if let Ok(address) = target.parse::<IpAddr>() {
+            return Ok(address);
+        }
+        let addresses: Vec<IpAddr> = tokio::net::lookup_host((target, 0)).await
+            .map_err(|e| format!("Unknown host '{}': {}", target, e))?
+            .map(|address| address.ip())
+            .collect();
+        addresses.iter().find(|address| address.is_ipv4()).or_else(|| addresses.first())
+            .copied()
+            .ok_or_else(|| format!("Unknown host '{}': no addresses", target))
+    }
+
+    /// Probe `address` from the network namespace of `container_pid`
+    pub async fn run(container_pid: i32, address: IpAddr, options: ProbeOptions) -> Result<ConnectivityReport, String> {
+        let options = options.normalized()?;
+        match options.port {
+            Some(port) => {
+                let target = SocketAddr::new(address, port);
+                let samples = tokio::task::spawn_blocking(move || Self::tcp_samples(container_pid, target, &options)).await
+                    .map_err(|e| format!("TCP probe task failed: {}", e))??;
+                Ok(ConnectivityReport::from_samples("tcp", &target.to_string(), &samples))
+            }
+            None => Self::ping(container_pid, address, &options).await,
+        }
+    }
+
+    async fn ping(container_pid: i32, address: IpAddr, options: &ProbeOptions) -> Result<ConnectivityReport, String> {
+        let pid = container_pid.to_string();
+        let count = options.count.to_string();
+        let wait = options.timeout_seconds.to_string();
+        let address = address.to_string();
+        let argv = Argv::new("nsenter", [
+            "-t", &pid, "-n", "ping", "-n", "-c", &count, "-W", &wait, "-i", PING_INTERVAL, &address,
+        ]);
+
+        // Every probe may wait out the reply timeout
+        let deadline = options.timeout() * options.count + Duration::from_secs(5);
+        let result = CommandExecutor::execute_with_timeout(&argv, deadline).await?;
+        // ping exits non-zero when replies are lost; that is still a result
+        let mut report = Self::parse_ping_output(&result.stdout).ok_or_else(|| {
+            let reason = result.stderr.trim();
+            format!("Ping to {} failed: {}", address, if reason.is_empty() { "no statistics in ping output" } else { reason })
+        })?;
+        report.address = address;
+        Ok(report)
+    }
+
+    /// Connect `options.count` times, one thread in the container's namespace doing every attempt
+    fn tcp_samples(container_pid: i32, target: SocketAddr, options: &ProbeOptions) -> Result<Vec<Option<f64>>, String> {
+        let netns_path = format!("/proc/{}/ns/net", container_pid);
+        let netns = std::fs::File::open(&netns_path)
+            .map_err(|e| format!("Failed to open {}: {}", netns_path, e))?;
+        let (count, timeout) = (options.count, options.timeout());
+
+        // setns only moves the calling thread, so a throwaway thread keeps pool threads on the host
+        std::thread::spawn(move || -> Result<Vec<Option<f64>>, String> {
+            nix::sched::setns(netns.as_raw_fd(), CloneFlags::CLONE_NEWNET)
+                .map_err(|e| format!("Failed to enter network namespace of PID {}: {}", container_pid, e))?;
+            Ok((0..count).map(|_| {
+                let started = Instant::now();
+                TcpStream::connect_timeout(&target, timeout).ok()
+                    .map(|_| started.elapsed().as_secs_f64() * 1000.0)
+            }).collect())
+        }).join().map_err(|_| "TCP probe thread panicked".to_string())?
+    }
+
+    /// Packet counts and round-trip times from the summary of iputils or busybox ping
+    pub fn parse_ping_output(output: &str) -> Option<ConnectivityReport> {
+        let leading_number = |part: &str| part.split_whitespace().next()?.parse::<u32>().ok();
+
+        let counts = output.lines().find(|line| line.contains("packets transmitted"))?;
+        let mut parts = counts.split(',');
+        let sent = leading_number(parts.next()?)?;
+        let received = leading_number(parts.next()?)?;
+
+        let mut report = ConnectivityReport {
+            protocol: "icmp".to_string(),
+            sent,
+            received,
+            ..Default::default()
+        };
+        // "rtt min/avg/max/mdev = 0.04/0.05/0.06/0.01 ms" or "round-trip min/avg/max = ..."
+        if let Some(times) = output.lines().find(|line| line.contains("min/avg/max")) {
+            let values: Vec<f64> = times.split('=').nth(1)?
+                .split_whitespace().next()?
+                .split('/')
+                .filter_map(|value| value.parse().ok())
+                .collect();
+            if let [min, avg, max, ..] = values[..] {
+                report.min_ms = min;
+                report.avg_ms = avg;
+                report.max_ms = max;
+            }
+        }
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ping_output() {
+        let iputils = "PING 10.42.0.5 (10.42.0.5) 56(84) bytes of data.\n\
+            64 bytes from 10.42.0.5: icmp_seq=1 ttl=64 time=0.051 ms\n\n\
+            --- 10.42.0.5 ping statistics ---\n\
+            3 packets transmitted, 2 received, 33.3333% packet loss, time 401ms\n\
+            rtt min/avg/max/mdev = 0.045/0.061/0.078/0.013 ms\n";
+        let report = ConnectivityTest::parse_ping_output(iputils).unwrap();
+        assert_eq!((report.sent, report.received), (3, 2));
+        assert_eq!((report.min_ms, report.avg_ms, report.max_ms), (0.045, 0.061, 0.078));
+
+        let busybox = "--- 10.42.0.5 ping statistics ---\n\
+            4 packets transmitted, 4 packets received, 0% packet loss\n\
+            round-trip min/avg/max = 0.058/0.072/0.089 ms\n";
+        let report = ConnectivityTest::parse_ping_output(busybox).unwrap();
+        assert_eq!((report.sent, report.received, report.max_ms), (4, 4, 0.089));
+
+        let lost = "2 packets transmitted, 0 received, 100% packet loss, time 1001ms\n";
+        let report = ConnectivityTest::parse_ping_output(lost).unwrap();
+        assert_eq!(report.loss_percent(), 100.0);
+        assert_eq!(report.avg_ms, 0.0);
+
+        assert!(ConnectivityTest::parse_ping_output("ping: unknown host nowhere").is_none());
+    }
+
+    #[test]
+    fn test_report_from_samples() {
+        let report = ConnectivityReport::from_samples("tcp", "10.42.0.5:80", &[Some(2.0), None, Some(4.0), Some(3.0)]);
+        assert_eq!((report.sent, report.received), (4, 3));
+        assert_eq!((report.min_ms, report.avg_ms, report.max_ms), (2.0, 3.0, 4.0));
+        assert_eq!(report.loss_percent(), 25.0);
+
+        assert!(ProbeOptions { count: MAX_PROBE_COUNT + 1, ..Default::default() }.normalized().is_err());
+        assert_eq!(ProbeOptions::default().normalized().unwrap().count, DEFAULT_PROBE_COUNT);
+    }
+}
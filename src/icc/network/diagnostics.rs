@@ -10,30 +10,30 @@ use crate::icc::network::veth::ContainerNetworkConfig;
 pub struct NetworkDiagnostics {
     pub bridge_name: String,
     pub bridge_ip: String,
+    /// Looked up by the in-container DNS check
+    pub cluster_domain: String,
 }
 
 impl NetworkDiagnostics {
     pub fn new(bridge_name: String, bridge_ip: String) -> Self {
-        Self { bridge_name, bridge_ip }
+        Self { bridge_name, bridge_ip, cluster_domain: crate::icc::dns::DEFAULT_CLUSTER_DOMAIN.to_string() }
     }
 
-    pub fn test_gateway_connectivity_comprehensive(&self, container_pid: i32, gateway_ip: &str, interface_name: &str) {
+    pub async fn test_gateway_connectivity_comprehensive(&self, container_pid: i32, gateway_ip: &str, interface_name: &str) {
         ConsoleLogger::debug(&format!("🌐 [GATEWAY-TEST] Comprehensive gateway connectivity test for {}", gateway_ip));
         
         // Test 1: Basic ping test
-        let gateway_ping_cmd = format!("nsenter -t {} -n ping -c 3 -W 2 {} 2>/dev/null", 
-            container_pid, gateway_ip);
-        
-        match CommandExecutor::execute_shell(&gateway_ping_cmd) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ping", "-c", "3", "-W", "2", gateway_ip]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::success(&format!("✅ [GATEWAY-TEST] Gateway {} is reachable (ping success)", gateway_ip));
             }
             Ok(result) => {
                 ConsoleLogger::warning(&format!("⚠️ [GATEWAY-TEST] Gateway {} ping failed: {}", gateway_ip, result.stderr));
                 // Continue with additional diagnostics
-                self.test_gateway_arp_resolution(container_pid, gateway_ip);
-                self.test_gateway_routing(container_pid, gateway_ip, interface_name);
-                self.test_interface_connectivity(container_pid, interface_name);
+                self.test_gateway_arp_resolution(container_pid, gateway_ip).await;
+                self.test_gateway_routing(container_pid, gateway_ip, interface_name).await;
+                self.test_interface_connectivity(container_pid, interface_name).await;
             }
             Err(e) => {
                 ConsoleLogger::error(&format!("❌ [GATEWAY-TEST] Gateway connectivity test failed: {}", e));
@@ -41,19 +41,20 @@ impl NetworkDiagnostics {
         }
         
         // Always run ARP and routing tests for comprehensive diagnostics
-        self.test_gateway_arp_resolution(container_pid, gateway_ip);
-        self.test_gateway_routing(container_pid, gateway_ip, interface_name);
+        self.test_gateway_arp_resolution(container_pid, gateway_ip).await;
+        self.test_gateway_routing(container_pid, gateway_ip, interface_name).await;
         
         // Test bridge connectivity from host side
-        self.diagnose_bridge_connectivity_issues(gateway_ip);
+        self.diagnose_bridge_connectivity_issues(gateway_ip).await;
     }
     
-    fn test_gateway_arp_resolution(&self, container_pid: i32, gateway_ip: &str) {
+    async fn test_gateway_arp_resolution(&self, container_pid: i32, gateway_ip: &str) {
         ConsoleLogger::debug(&format!("🔍 [ARP-TEST] Testing ARP resolution for gateway {}", gateway_ip));
         
         // Check ARP entry for gateway
-        let arp_check_cmd = format!("nsenter -t {} -n ip neigh show {}", container_pid, gateway_ip);
-        match CommandExecutor::execute_shell(&arp_check_cmd) {
+        let pid = container_pid.to_string();
+        let arp_check = ["-t", &pid, "-n", "ip", "neigh", "show", gateway_ip];
+        match CommandExecutor::run_async("nsenter", arp_check).await {
             Ok(result) if result.success && !result.stdout.trim().is_empty() => {
                 ConsoleLogger::debug(&format!("✅ [ARP-TEST] Gateway {} ARP entry: {}", gateway_ip, result.stdout.trim()));
             }
@@ -61,11 +62,10 @@ impl NetworkDiagnostics {
                 ConsoleLogger::debug(&format!("ℹ️ [ARP-TEST] No ARP entry found for gateway {} (may be normal)", gateway_ip));
                 
                 // Try to ping once to populate ARP table
-                let ping_once_cmd = format!("nsenter -t {} -n ping -c 1 -W 1 {} >/dev/null 2>&1", container_pid, gateway_ip);
-                let _ = CommandExecutor::execute_shell(&ping_once_cmd);
+                let _ = CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ping", "-c", "1", "-W", "1", gateway_ip]).await;
                 
                 // Check again
-                if let Ok(result) = CommandExecutor::execute_shell(&arp_check_cmd) {
+                if let Ok(result) = CommandExecutor::run_async("nsenter", arp_check).await {
                     if result.success && !result.stdout.trim().is_empty() {
                         ConsoleLogger::debug(&format!("✅ [ARP-TEST] Gateway {} ARP entry (after ping): {}", gateway_ip, result.stdout.trim()));
                     }
@@ -77,12 +77,12 @@ impl NetworkDiagnostics {
         }
     }
     
-    fn test_gateway_routing(&self, container_pid: i32, gateway_ip: &str, interface_name: &str) {
+    async fn test_gateway_routing(&self, container_pid: i32, gateway_ip: &str, interface_name: &str) {
         ConsoleLogger::debug(&format!("🛣️ [ROUTE-TEST] Testing routing to gateway {} via {}", gateway_ip, interface_name));
         
         // Check specific route to gateway
-        let route_check_cmd = format!("nsenter -t {} -n ip route get {}", container_pid, gateway_ip);
-        match CommandExecutor::execute_shell(&route_check_cmd) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "route", "get", gateway_ip]).await {
             Ok(result) if result.success => {
                 if result.stdout.contains(interface_name) {
                     ConsoleLogger::debug(&format!("✅ [ROUTE-TEST] Route to {} via {}: {}", gateway_ip, interface_name, result.stdout.trim()));
@@ -100,8 +100,7 @@ impl NetworkDiagnostics {
         }
         
         // Check default route
-        let default_route_cmd = format!("nsenter -t {} -n ip route show default", container_pid);
-        match CommandExecutor::execute_shell(&default_route_cmd) {
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "route", "show", "default"]).await {
             Ok(result) if result.success && !result.stdout.trim().is_empty() => {
                 ConsoleLogger::debug(&format!("✅ [ROUTE-TEST] Default route: {}", result.stdout.trim()));
             }
@@ -114,12 +113,12 @@ impl NetworkDiagnostics {
         }
     }
     
-    fn test_interface_connectivity(&self, container_pid: i32, interface_name: &str) {
+    async fn test_interface_connectivity(&self, container_pid: i32, interface_name: &str) {
         ConsoleLogger::debug(&format!("🔌 [IFACE-TEST] Testing interface {} connectivity", interface_name));
         
         // Check interface state
-        let iface_check_cmd = format!("nsenter -t {} -n ip link show {}", container_pid, interface_name);
-        match CommandExecutor::execute_shell(&iface_check_cmd) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "link", "show", interface_name]).await {
             Ok(result) if result.success => {
                 if result.stdout.contains("state UP") {
                     ConsoleLogger::debug(&format!("✅ [IFACE-TEST] Interface {} is UP", interface_name));
@@ -136,8 +135,7 @@ impl NetworkDiagnostics {
         }
         
         // Check interface statistics
-        let stats_cmd = format!("nsenter -t {} -n ip -s link show {}", container_pid, interface_name);
-        match CommandExecutor::execute_shell(&stats_cmd) {
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "-s", "link", "show", interface_name]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("ℹ️ [IFACE-TEST] Interface {} stats: {}", interface_name, 
                     result.stdout.lines().collect::<Vec<_>>().join(" | ")));
@@ -148,12 +146,11 @@ impl NetworkDiagnostics {
         }
     }
     
-    fn diagnose_bridge_connectivity_issues(&self, gateway_ip: &str) {
+    async fn diagnose_bridge_connectivity_issues(&self, gateway_ip: &str) {
         ConsoleLogger::debug(&format!("🌉 [BRIDGE-DIAG] Diagnosing bridge connectivity issues for {}", gateway_ip));
         
         // Check if host can ping the bridge IP
-        let host_ping_cmd = format!("ping -c 1 -W 1 {} >/dev/null 2>&1", gateway_ip);
-        match CommandExecutor::execute_shell(&host_ping_cmd) {
+        match CommandExecutor::run_async("ping", ["-c", "1", "-W", "1", gateway_ip]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("✅ [BRIDGE-DIAG] Host can reach bridge IP {}", gateway_ip));
             }
@@ -163,8 +160,7 @@ impl NetworkDiagnostics {
         }
         
         // Check bridge interface status
-        let bridge_status_cmd = format!("ip link show {}", self.bridge_name);
-        match CommandExecutor::execute_shell(&bridge_status_cmd) {
+        match CommandExecutor::run_async("ip", ["link", "show", &self.bridge_name]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("ℹ️ [BRIDGE-DIAG] Bridge {} status: {}", self.bridge_name, 
                     result.stdout.lines().next().unwrap_or("").trim()));
@@ -175,7 +171,7 @@ impl NetworkDiagnostics {
         }
     }
     
-    pub fn test_bidirectional_connectivity(&self, _container_pid: i32, container_ip: &str, gateway_ip: &str) {
+    pub async fn test_bidirectional_connectivity(&self, _container_pid: i32, container_ip: &str, gateway_ip: &str) {
         ConsoleLogger::debug(&format!("🔄 [BIDIR-TEST] Testing bidirectional connectivity: container {} <-> gateway {}", 
             container_ip, gateway_ip));
         
@@ -186,22 +182,26 @@ impl NetworkDiagnostics {
         ConsoleLogger::debug(&format!("🔼 [BIDIR-TEST] Testing Host -> Container connectivity to {}", container_ip));
         
         // Try to ping container from host
-        let host_to_container_ping = format!("ping -c 2 -W 2 {} >/dev/null 2>&1", container_ip);
-        match CommandExecutor::execute_shell(&host_to_container_ping) {
+        match CommandExecutor::run_async("ping", ["-c", "2", "-W", "2", container_ip]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::success(&format!("✅ [BIDIR-TEST] Host -> Container {} connectivity working", container_ip));
             }
             _ => {
                 ConsoleLogger::warning(&format!("⚠️ [BIDIR-TEST] Host -> Container {} connectivity failed", container_ip));
-                self.diagnose_host_to_container_connectivity_failure(container_ip);
+                self.diagnose_host_to_container_connectivity_failure(container_ip).await;
             }
         }
         
         // Test bridge forwarding table
-        let fdb_check = format!("bridge fdb show | grep {}", container_ip);
-        match CommandExecutor::execute_shell(&fdb_check) {
-            Ok(result) if result.success && !result.stdout.trim().is_empty() => {
-                ConsoleLogger::debug(&format!("✅ [BIDIR-TEST] Bridge FDB entry for {}: {}", container_ip, result.stdout.trim()));
+        let fdb_entries = CommandExecutor::run_async("bridge", ["fdb", "show"]).await
+            .map(|result| result.stdout.lines()
+                .filter(|line| line.contains(container_ip))
+                .collect::<Vec<_>>()
+                .join("\n"))
+            .unwrap_or_default();
+        match fdb_entries {
+            entries if !entries.is_empty() => {
+                ConsoleLogger::debug(&format!("✅ [BIDIR-TEST] Bridge FDB entry for {}: {}", container_ip, entries));
             }
             _ => {
                 ConsoleLogger::debug(&format!("ℹ️ [BIDIR-TEST] No bridge FDB entry for {} (may be normal for new containers)", container_ip));
@@ -209,18 +209,16 @@ impl NetworkDiagnostics {
         }
     }
     
-    fn diagnose_host_to_container_connectivity_failure(&self, container_ip: &str) {
+    async fn diagnose_host_to_container_connectivity_failure(&self, container_ip: &str) {
         ConsoleLogger::debug(&format!("🔍 [HOST-DIAG] Diagnosing host->container connectivity failure for {}", container_ip));
         
         // Check host routing to container IP
-        let host_route_cmd = format!("ip route get {}", container_ip);
-        if let Ok(result) = CommandExecutor::execute_shell(&host_route_cmd) {
+        if let Ok(result) = CommandExecutor::run_async("ip", ["route", "get", container_ip]).await {
             ConsoleLogger::debug(&format!("ℹ️ [HOST-DIAG] Host route to {}: {}", container_ip, result.stdout.trim()));
         }
         
         // Check if bridge knows about this container
-        let bridge_neigh_cmd = format!("ip neigh show {} dev {}", container_ip, self.bridge_name);
-        if let Ok(result) = CommandExecutor::execute_shell(&bridge_neigh_cmd) {
+        if let Ok(result) = CommandExecutor::run_async("ip", ["neigh", "show", container_ip, "dev", &self.bridge_name]).await {
             if result.success && !result.stdout.trim().is_empty() {
                 ConsoleLogger::debug(&format!("ℹ️ [HOST-DIAG] Bridge neighbor entry for {}: {}", container_ip, result.stdout.trim()));
             } else {
@@ -229,20 +227,19 @@ impl NetworkDiagnostics {
         }
         
         // Check bridge port list
-        let bridge_ports_cmd = format!("bridge link show master {}", self.bridge_name);
-        if let Ok(result) = CommandExecutor::execute_shell(&bridge_ports_cmd) {
+        if let Ok(result) = CommandExecutor::run_async("bridge", ["link", "show", "master", &self.bridge_name]).await {
             ConsoleLogger::debug(&format!("ℹ️ [HOST-DIAG] Bridge {} ports: {}", self.bridge_name, result.stdout.trim()));
         }
     }
     
-    pub fn verify_container_network_ready(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
+    pub async fn verify_container_network_ready(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         let interface_name = format!("quilt{}", &config.container_id[..8]);
         
         ConsoleLogger::debug(&format!("🔍 Production network verification for container {} (interface: {})", config.container_id, interface_name));
         
         // Phase 1: Network interface verification (fast check)
-        let interface_check_cmd = format!("nsenter -t {} -n ip link show {}", container_pid, interface_name);
-        match CommandExecutor::execute_shell(&interface_check_cmd) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "link", "show", &interface_name]).await {
             Ok(result) if result.success => {
                 if !result.stdout.contains("state UP") {
                     return Err(format!("Container interface {} is not UP", interface_name));
@@ -258,10 +255,9 @@ impl NetworkDiagnostics {
         }
         
         // Phase 2: IP address verification
-        let ip_check_cmd = format!("nsenter -t {} -n ip addr show {} | grep {}", 
-            container_pid, interface_name, config.ip_address.split('/').next().unwrap());
-        match CommandExecutor::execute_shell(&ip_check_cmd) {
-            Ok(result) if result.success => {
+        let container_ip = config.ip_address.split('/').next().unwrap();
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "addr", "show", &interface_name]).await {
+            Ok(result) if result.success && result.stdout.contains(container_ip) => {
                 ConsoleLogger::debug(&format!("✅ Interface {} has correct IP {}", interface_name, config.ip_address));
             }
             _ => {
@@ -270,8 +266,7 @@ impl NetworkDiagnostics {
         }
         
         // Phase 3: Default route verification
-        let route_check_cmd = format!("nsenter -t {} -n ip route show default", container_pid);
-        match CommandExecutor::execute_shell(&route_check_cmd) {
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "route", "show", "default"]).await {
             Ok(result) if result.success && !result.stdout.trim().is_empty() => {
                 ConsoleLogger::debug(&format!("✅ Default route configured: {}", result.stdout.trim()));
             }
@@ -282,8 +277,7 @@ impl NetworkDiagnostics {
         
         // Phase 4: Gateway reachability test (critical for container networking)
         let gateway_ip = config.gateway_ip.split('/').next().unwrap();
-        let gateway_ping_cmd = format!("nsenter -t {} -n ping -c 2 -W 3 {} >/dev/null 2>&1", container_pid, gateway_ip);
-        match CommandExecutor::execute_shell(&gateway_ping_cmd) {
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ping", "-c", "2", "-W", "3", gateway_ip]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("✅ Gateway {} is reachable from container", gateway_ip));
             }
@@ -293,8 +287,7 @@ impl NetworkDiagnostics {
                 ConsoleLogger::warning(&format!("⚠️ Gateway {} ping failed (may be normal if firewall blocks ping)", gateway_ip));
                 
                 // Try a different connectivity test - check if we can resolve the gateway via ARP
-                let arp_test_cmd = format!("nsenter -t {} -n ip neigh get {}", container_pid, gateway_ip);
-                match CommandExecutor::execute_shell(&arp_test_cmd) {
+                match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "ip", "neigh", "get", gateway_ip]).await {
                     Ok(result) if result.success => {
                         ConsoleLogger::debug(&format!("✅ Gateway {} is reachable via ARP", gateway_ip));
                     }
@@ -307,8 +300,7 @@ impl NetworkDiagnostics {
         }
         
         // Phase 5: DNS resolution test
-        let dns_test_cmd = format!("nsenter -t {} -n nslookup quilt.local 127.0.0.1 >/dev/null 2>&1", container_pid);
-        match CommandExecutor::execute_shell(&dns_test_cmd) {
+        match CommandExecutor::run_async("nsenter", ["-t", &pid, "-n", "nslookup", &self.cluster_domain, "127.0.0.1"]).await {
             Ok(result) if result.success => {
                 ConsoleLogger::debug("✅ DNS resolution working in container");
             }
@@ -1,19 +1,32 @@
 // DNS management module
 // Handles DNS server integration, redirect rules, and container DNS configuration
 
-use crate::utils::command::CommandExecutor;
+use crate::daemon::paths;
+use crate::utils::command::{Argv, CommandExecutor};
 use crate::utils::console::ConsoleLogger;
 use crate::utils::filesystem::FileSystemUtils;
-use crate::icc::dns::DnsServer;
+use crate::icc::dns::{DnsDomainConfig, DnsServer, DnsView};
+use crate::icc::mdns::MdnsResponder;
 use crate::icc::network::veth::ContainerNetworkConfig;
 use std::sync::Arc;
 use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Ports the DNS listener may use, in order of preference; containers reach it via a port 53 DNAT
+const DNS_PORTS: [u16; 5] = [1053, 1153, 1253, 1353, 1453];
+/// How often the supervisor health-checks the listener and its redirect rules
+const DNS_SUPERVISOR_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a health probe waits for the listener to answer
+const DNS_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// DNS management for container networking
 pub struct DnsManager {
     pub bridge_name: String,
     pub bridge_ip: String,
     pub dns_server: Option<Arc<DnsServer>>,
+    pub mdns_responder: Option<Arc<MdnsResponder>>,
+    /// Cluster domain and search list; set before the server starts
+    pub domain: DnsDomainConfig,
 }
 
 impl DnsManager {
@@ -22,60 +35,152 @@ impl DnsManager {
             bridge_name,
             bridge_ip,
             dns_server: None,
+            mdns_responder: None,
+            domain: DnsDomainConfig::default(),
         }
     }
 
     pub async fn start_dns_server(&mut self) -> Result<(), String> {
         ConsoleLogger::debug("Starting DNS server for container networking");
         
-        // Try primary port 1053, with fallback to other ports if needed
-        let primary_port = 1053;
-        let _fallback_ports = vec![1153, 1253, 1353, 1453]; // TODO: Implement port fallback logic
-        
-        // Try to bind to primary port first
-        let dns_bind_address: SocketAddr = format!("{}:{}", self.bridge_ip, primary_port)
+        // Try primary port 1053, falling back to the other ports if it is taken
+        let dns_bind_address: SocketAddr = format!("{}:{}", self.bridge_ip, DNS_PORTS[0])
             .parse()
             .map_err(|e| format!("Invalid DNS bind address: {}", e))?;
         
-        let dns = DnsServer::new(dns_bind_address);
+        let dns = Arc::new(DnsServer::new(dns_bind_address).with_domain(&self.domain.cluster_domain));
+        let port = dns.start_with_fallback(&DNS_PORTS).await
+            .map_err(|e| format!("Failed to start DNS server on any of ports {:?}: {}", DNS_PORTS, e))?;
         
-        // Actually start the DNS server
-        if let Err(e) = dns.start().await {
-            return Err(format!("Failed to start DNS server: {}", e));
-        }
+        ConsoleLogger::success(&format!("DNS server started on {}:{} for {}", self.bridge_ip, port, self.domain.cluster_domain));
+        self.dns_server = Some(dns.clone());
+        Self::update_dns_redirect_rules(&self.bridge_name, &self.bridge_ip, port)?;
+        Self::spawn_dns_supervisor(dns, self.bridge_name.clone(), self.bridge_ip.clone());
         
-        ConsoleLogger::success(&format!("DNS server started on {}:{}", self.bridge_ip, primary_port));
-        self.dns_server = Some(Arc::new(dns));
-        self.update_dns_redirect_rules(primary_port)?;
+        // Optional LAN advertisement - failure never blocks container DNS
+        if MdnsResponder::enabled_from_env() {
+            match MdnsResponder::start().await {
+                Ok(responder) => self.mdns_responder = Some(Arc::new(responder)),
+                Err(e) => ConsoleLogger::warning(&format!("mDNS responder not started: {}", e)),
+            }
+        }
         Ok(())
     }
 
-    fn update_dns_redirect_rules(&self, actual_port: u16) -> Result<(), String> {
+    /// Health-check the DNS listener in the background. A dead or unresponsive listener is rebound
+    /// (current port first, then the fallbacks) and the port 53 redirect is pointed at it again;
+    /// a redirect rule that disappeared (e.g. after an iptables flush) is reapplied.
+    fn spawn_dns_supervisor(dns: Arc<DnsServer>, bridge_name: String, bridge_ip: String) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DNS_SUPERVISOR_INTERVAL);
+            interval.tick().await;
+            let mut restarts = 0u64;
+            
+            loop {
+                interval.tick().await;
+                let current_port = dns.bind_address().port();
+                let failure = if dns.is_listening() {
+                    dns.probe(DNS_PROBE_TIMEOUT).await.err()
+                } else {
+                    Some("listener task exited".to_string())
+                };
+                
+                let reapply_port = match failure {
+                    Some(reason) => {
+                        ConsoleLogger::warning(&format!("🩺 [DNS-SUPERVISOR] DNS server on {} is unhealthy ({}); restarting", dns.bind_address(), reason));
+                        let ports: Vec<u16> = std::iter::once(current_port)
+                            .chain(DNS_PORTS.iter().copied().filter(|port| *port != current_port))
+                            .collect();
+                        match dns.restart(&ports).await {
+                            Ok(port) => {
+                                restarts += 1;
+                                ConsoleLogger::success(&format!("🩺 [DNS-SUPERVISOR] DNS server restarted on port {} ({} restart(s) so far)", port, restarts));
+                                Some(port)
+                            }
+                            Err(e) => {
+                                ConsoleLogger::error(&format!("🩺 [DNS-SUPERVISOR] DNS server restart failed, retrying in {:?}: {}", DNS_SUPERVISOR_INTERVAL, e));
+                                None
+                            }
+                        }
+                    }
+                    None => {
+                        let (name, ip) = (bridge_name.clone(), bridge_ip.clone());
+                        let present = tokio::task::spawn_blocking(move || Self::dns_redirect_rules_present(&name, &ip, current_port))
+                            .await
+                            .unwrap_or(true);
+                        if present {
+                            None
+                        } else {
+                            ConsoleLogger::warning("🩺 [DNS-SUPERVISOR] DNS redirect rules are missing; reapplying");
+                            Some(current_port)
+                        }
+                    }
+                };
+                
+                if let Some(port) = reapply_port {
+                    let (name, ip) = (bridge_name.clone(), bridge_ip.clone());
+                    match tokio::task::spawn_blocking(move || Self::update_dns_redirect_rules(&name, &ip, port)).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => ConsoleLogger::error(&format!("🩺 [DNS-SUPERVISOR] {}", e)),
+                        Err(e) => ConsoleLogger::error(&format!("🩺 [DNS-SUPERVISOR] Redirect rule task failed: {}", e)),
+                    }
+                }
+            }
+        });
+    }
+    
+    /// Address of the DNS listener and whether it is serving; `None` before the server started
+    pub fn dns_listener(&self) -> Option<(SocketAddr, bool)> {
+        self.dns_server.as_ref().map(|dns| (dns.bind_address(), dns.is_listening()))
+    }
+
+    /// Whether the port 53 redirect rules point at the current listener (runs iptables)
+    pub fn dns_redirect_installed(&self) -> bool {
+        self.dns_server.as_ref().map_or(false, |dns| {
+            Self::dns_redirect_rules_present(&self.bridge_name, &self.bridge_ip, dns.bind_address().port())
+        })
+    }
+
+    pub fn mdns_enabled(&self) -> bool {
+        self.mdns_responder.is_some()
+    }
+
+    /// Whether both port 53 DNAT rules to `port` are installed
+    fn dns_redirect_rules_present(bridge_name: &str, bridge_ip: &str, port: u16) -> bool {
+        let destination = format!("{}:{}", bridge_ip, port);
+        ["udp", "tcp"].iter().all(|protocol| {
+            let check = Self::dns_redirect_rule("-C", bridge_name, protocol, Some(&destination));
+            CommandExecutor::execute(&check).map(|result| result.success).unwrap_or(false)
+        })
+    }
+
+    /// iptables invocation for the port 53 DNAT rule of one protocol; without a destination it
+    /// matches the first such rule whatever its port
+    fn dns_redirect_rule(action: &str, bridge_name: &str, protocol: &str, destination: Option<&str>) -> Argv {
+        let mut args = vec!["-t", "nat", action, "PREROUTING", "-i", bridge_name, "-p", protocol, "--dport", "53", "-j", "DNAT"];
+        if let Some(destination) = destination {
+            args.extend(["--to-destination", destination]);
+        }
+        Argv::new("iptables", args)
+    }
+    
+    fn update_dns_redirect_rules(bridge_name: &str, bridge_ip: &str, actual_port: u16) -> Result<(), String> {
         ConsoleLogger::debug(&format!("🔧 [DNS-REDIRECT] Updating iptables to redirect DNS to port {}", actual_port));
         
         // COMPREHENSIVE CLEANUP: Remove ALL possible DNS redirect rules to prevent accumulation
         // We try to remove rules for all possible ports that might have been used
-        let all_possible_ports = vec![1053, 1153, 1253, 1353, 1453];
-        
-        for port in &all_possible_ports {
-            let cleanup_cmds = vec![
-                format!("iptables -t nat -D PREROUTING -i {} -p udp --dport 53 -j DNAT --to-destination {}:{} 2>/dev/null || true", self.bridge_name, self.bridge_ip, port),
-                format!("iptables -t nat -D PREROUTING -i {} -p tcp --dport 53 -j DNAT --to-destination {}:{} 2>/dev/null || true", self.bridge_name, self.bridge_ip, port),
-            ];
-            
-            for cmd in cleanup_cmds {
-                let _ = CommandExecutor::execute_shell(&cmd);
+        for port in &DNS_PORTS {
+            let destination = format!("{}:{}", bridge_ip, port);
+            for protocol in ["udp", "tcp"] {
+                let _ = CommandExecutor::execute(&Self::dns_redirect_rule("-D", bridge_name, protocol, Some(&destination)));
             }
         }
         
         // Add the new rules for the actual port being used
-        let redirect_rules = vec![
-            format!("iptables -t nat -A PREROUTING -i {} -p udp --dport 53 -j DNAT --to-destination {}:{}", self.bridge_name, self.bridge_ip, actual_port),
-            format!("iptables -t nat -A PREROUTING -i {} -p tcp --dport 53 -j DNAT --to-destination {}:{}", self.bridge_name, self.bridge_ip, actual_port),
-        ];
-        
-        for rule in redirect_rules {
-            match CommandExecutor::execute_shell(&rule) {
+        let destination = format!("{}:{}", bridge_ip, actual_port);
+        for protocol in ["udp", "tcp"] {
+            let rule = Self::dns_redirect_rule("-A", bridge_name, protocol, Some(&destination));
+            match CommandExecutor::execute(&rule) {
                 Ok(result) if result.success => {
                     ConsoleLogger::debug(&format!("✅ [DNS-REDIRECT] Added rule: {}", rule));
                 }
@@ -92,12 +197,21 @@ impl DnsManager {
         Ok(())
     }
 
-    pub fn register_container_dns(&self, container_id: &str, container_name: &str, ip_address: &str) -> Result<(), String> {
+    /// Register a container's names and reverse lookup; `wildcard` also answers `*.<name>.<domain>`
+    pub fn register_container_dns(&self, container_id: &str, container_name: &str, ip_address: &str, wildcard: bool) -> Result<(), String> {
         if let Some(dns) = &self.dns_server {
             dns.register_container(container_id, container_name, ip_address)?;
+            if wildcard {
+                dns.set_wildcard(container_id, true)?;
+            }
         } else {
             ConsoleLogger::warning("DNS server not started, skipping container registration");
         }
+        if let Some(mdns) = &self.mdns_responder {
+            if let Err(e) = mdns.register(container_id, container_name) {
+                ConsoleLogger::warning(&format!("mDNS advertisement skipped for {}: {}", container_name, e));
+            }
+        }
         Ok(())
     }
 
@@ -105,6 +219,25 @@ impl DnsManager {
         if let Some(dns) = &self.dns_server {
             dns.unregister_container(container_id)?;
         }
+        if let Some(mdns) = &self.mdns_responder {
+            mdns.unregister(container_id)?;
+        }
+        Ok(())
+    }
+
+    /// Move `container_name` and the aliases of `from_id` over to `to_id` (see `DnsServer::take_over`)
+    pub fn transfer_container_dns(&self, from_id: &str, to_id: &str, container_name: &str) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.take_over(from_id, to_id, container_name)?,
+            None => return Err("DNS server not started".to_string()),
+        }
+        if let Some(mdns) = &self.mdns_responder {
+            let _ = mdns.unregister(from_id);
+            let _ = mdns.unregister(to_id);
+            if let Err(e) = mdns.register(to_id, container_name) {
+                ConsoleLogger::warning(&format!("mDNS advertisement skipped for {}: {}", container_name, e));
+            }
+        }
         Ok(())
     }
 
@@ -116,12 +249,71 @@ impl DnsManager {
         }
     }
 
+    pub fn add_dns_alias(&self, container_id: &str, alias: &str) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.add_alias(container_id, alias),
+            None => Err("DNS server not started".to_string()),
+        }
+    }
+
+    pub fn remove_dns_alias(&self, alias: &str) -> Result<String, String> {
+        match &self.dns_server {
+            Some(dns) => dns.remove_alias(alias),
+            None => Err("DNS server not started".to_string()),
+        }
+    }
+
+    pub fn set_virtual_service_backends(&self, name: &str, backends: Vec<std::net::IpAddr>) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.set_service_backends(name, backends),
+            None => Err("DNS server not started".to_string()),
+        }
+    }
+
+    pub fn remove_virtual_service(&self, name: &str) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.remove_service(name),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_dns_record(&self, name: &str, view: DnsView, addresses: Vec<std::net::IpAddr>) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.set_record(name, view, addresses),
+            None => Err("DNS server not started".to_string()),
+        }
+    }
+
+    pub fn remove_dns_record(&self, name: &str, view: DnsView) -> Result<(), String> {
+        match &self.dns_server {
+            Some(dns) => dns.remove_record(name, view),
+            None => Ok(()),
+        }
+    }
+
+    pub fn dns_cache_stats(&self) -> Option<crate::icc::dns::DnsCacheStats> {
+        self.dns_server.as_ref().map(|dns| dns.cache_stats())
+    }
+
+    pub fn flush_dns_cache(&self) -> Result<usize, String> {
+        match &self.dns_server {
+            Some(dns) => Ok(dns.flush_cache()),
+            None => Err("DNS server not started".to_string()),
+        }
+    }
+
+    /// resolv.conf content for containers: our listener, then the search list
+    pub fn resolv_conf(&self) -> String {
+        self.domain.resolv_conf(&self.bridge_ip)
+    }
+
     pub fn configure_container_dns(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Configuring DNS for container {} (PID: {})", config.container_id, container_pid));
         
-        // SECURITY CRITICAL: This nsenter command MUST NOT run if namespace entry fails
-        // If nsenter fails, the shell command would execute on the HOST and corrupt host DNS
-        let dns_content = format!("nameserver {}\nsearch quilt.local\n", self.bridge_ip);
+        // SECURITY CRITICAL: This write MUST NOT run if namespace entry fails
+        // The shell only runs as nsenter's child and the content arrives on stdin, so a failed
+        // nsenter cannot fall through to the host's /etc/resolv.conf
+        let dns_content = self.resolv_conf();
         
         // First validate the container PID exists and is accessible
         if !self.validate_container_namespace(container_pid) {
@@ -129,13 +321,14 @@ impl DnsManager {
             return self.configure_dns_safe_fallback(config, &dns_content);
         }
         
-        let write_resolv_cmd = format!(
-            "nsenter -t {} -m -p -- sh -c 'mkdir -p /etc && rm -f /etc/resolv.conf && echo \"{}\" > /etc/resolv.conf && ls -la /etc/resolv.conf'",
-            container_pid, dns_content
-        );
+        let pid = container_pid.to_string();
+        let write_resolv_cmd = Argv::new("nsenter", [
+            "-t", &pid, "-m", "-p", "--", "sh", "-c",
+            "mkdir -p /etc && rm -f /etc/resolv.conf && cat > /etc/resolv.conf && ls -la /etc/resolv.conf",
+        ]).with_input(dns_content.clone());
         
         let mut dns_written = false;
-        match CommandExecutor::execute_shell(&write_resolv_cmd) {
+        match CommandExecutor::execute(&write_resolv_cmd) {
             Ok(result) => {
                 if result.success {
                     // Additional verification that we actually wrote to container's resolv.conf
@@ -163,8 +356,7 @@ impl DnsManager {
         // Verify DNS configuration was written
         if dns_written {
             // Verify the file exists and is readable from inside container
-            let verify_cmd = format!("nsenter -t {} -m -p -- cat /etc/resolv.conf", container_pid);
-            match CommandExecutor::execute_shell(&verify_cmd) {
+            match CommandExecutor::run("nsenter", ["-t", &pid, "-m", "-p", "--", "cat", "/etc/resolv.conf"]) {
                 Ok(result) if result.success => {
                     ConsoleLogger::debug(&format!("✅ DNS configuration verified in container: {}", result.stdout.trim()));
                 }
@@ -181,20 +373,16 @@ impl DnsManager {
 
     fn validate_container_namespace(&self, container_pid: i32) -> bool {
         // Check if PID exists and is a valid container process
-        let pid_check = format!("test -d /proc/{} && cat /proc/{}/comm | grep -q quilt", container_pid, container_pid);
-        if let Ok(result) = CommandExecutor::execute_shell(&pid_check) {
-            if !result.success {
-                ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation failed - process not found or invalid", container_pid));
-                return false;
-            }
-        } else {
-            ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation check failed", container_pid));
+        let is_quilt_process = std::fs::read_to_string(format!("/proc/{}/comm", container_pid))
+            .map_or(false, |comm| comm.contains("quilt"));
+        if !is_quilt_process {
+            ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation failed - process not found or invalid", container_pid));
             return false;
         }
 
         // Test namespace entry without dangerous operations
-        let ns_test = format!("nsenter -t {} -m -p -- echo 'namespace_test_ok'", container_pid);
-        match CommandExecutor::execute_shell(&ns_test) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run("nsenter", ["-t", &pid, "-m", "-p", "--", "echo", "namespace_test_ok"]) {
             Ok(result) => {
                 if result.success && result.stdout.trim() == "namespace_test_ok" {
                     return true;
@@ -220,8 +408,8 @@ impl DnsManager {
         }
 
         // Verify container DNS contains expected content
-        let verify_cmd = format!("nsenter -t {} -m -p -- cat /etc/resolv.conf", container_pid);
-        if let Ok(result) = CommandExecutor::execute_shell(&verify_cmd) {
+        let pid = container_pid.to_string();
+        if let Ok(result) = CommandExecutor::run("nsenter", ["-t", &pid, "-m", "-p", "--", "cat", "/etc/resolv.conf"]) {
             if result.success && result.stdout.contains(&self.bridge_ip.to_string()) {
                 return true;
             }
@@ -235,7 +423,7 @@ impl DnsManager {
         ConsoleLogger::debug("Using safe DNS configuration fallback method...");
         if let Some(rootfs_path) = &config.rootfs_path {
             // Validate rootfs path is within expected container directory
-            if !rootfs_path.starts_with("/tmp/quilt-containers/") {
+            if !paths::is_container_rootfs(rootfs_path) {
                 return Err(format!("🚨 [SECURITY] Unsafe rootfs path: {}", rootfs_path));
             }
 
@@ -267,28 +455,19 @@ impl DnsManager {
         ConsoleLogger::info("🧹 [CLEANUP] Starting comprehensive DNS cleanup");
         
         // Step 1: Clean up all DNS redirect rules 
-        let all_possible_ports = vec![1053, 1153, 1253, 1353, 1453];
-        for port in all_possible_ports {
-            let cleanup_cmds = vec![
-                format!("iptables -t nat -D PREROUTING -i {} -p udp --dport 53 -j DNAT --to-destination {}:{} 2>/dev/null || true", self.bridge_name, self.bridge_ip, port),
-                format!("iptables -t nat -D PREROUTING -i {} -p tcp --dport 53 -j DNAT --to-destination {}:{} 2>/dev/null || true", self.bridge_name, self.bridge_ip, port),
-            ];
-            
-            for cmd in cleanup_cmds {
-                let _ = CommandExecutor::execute_shell(&cmd);
+        for port in DNS_PORTS {
+            let destination = format!("{}:{}", self.bridge_ip, port);
+            for protocol in ["udp", "tcp"] {
+                let _ = CommandExecutor::execute(&Self::dns_redirect_rule("-D", &self.bridge_name, protocol, Some(&destination)));
             }
         }
         
         // Step 2: Aggressive cleanup of any remaining DNS DNAT rules
         for _attempt in 0..10 {
-            let generic_cleanup_cmds = vec![
-                format!("iptables -t nat -D PREROUTING -i {} -p udp --dport 53 -j DNAT 2>/dev/null", self.bridge_name),
-                format!("iptables -t nat -D PREROUTING -i {} -p tcp --dport 53 -j DNAT 2>/dev/null", self.bridge_name),
-            ];
-            
             let mut rules_removed = false;
-            for cmd in generic_cleanup_cmds {
-                if let Ok(result) = CommandExecutor::execute_shell(&cmd) {
+            for protocol in ["udp", "tcp"] {
+                let cmd = Self::dns_redirect_rule("-D", &self.bridge_name, protocol, None);
+                if let Ok(result) = CommandExecutor::execute(&cmd) {
                     if result.success {
                         rules_removed = true;
                         ConsoleLogger::debug(&format!("🧹 [CLEANUP] Removed rule: {}", cmd));
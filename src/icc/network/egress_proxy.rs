@@ -0,0 +1,154 @@
+// Egress proxy
+// Sends a container's web traffic through a proxy running in another container, where it can be
+// inspected or restricted. The container's environment always gets HTTP_PROXY/HTTPS_PROXY (and
+// the lowercase spellings curl and most libraries read) pointing at the proxy, with NO_PROXY
+// covering loopback, the bridge subnet and cluster names. In transparent mode clients that ignore
+// the variables are caught as well: TCP connections to ports 80 and 443 leaving the subnet are
+// DNATed to the proxy on the bridge, and masqueraded, so the proxy's replies come back through
+// the host that undoes the DNAT instead of going straight across the bridge. The proxy thus sees
+// the gateway as the client and has to route by Host header or SNI (squid `intercept`/`ssl-bump`,
+// mitmproxy `--mode transparent`). The rules carry a `quilt:<id>:egress` comment, so network
+// teardown sweeps them with the container's other rules.
+
+use crate::icc::network::teardown::{comment_rule_deletions, list_rules};
+use crate::icc::network::PortForwarder;
+use crate::utils::console::ConsoleLogger;
+use serde::{Deserialize, Serialize};
+
+/// Destination ports redirected in transparent mode
+pub const REDIRECTED_PORTS: &str = "80,443";
+
+/// Where a container's egress goes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EgressProxy {
+    /// ID of the container running the proxy
+    pub container_id: String,
+    /// Port the proxy listens on
+    pub port: u16,
+    /// Also redirect connections to ports 80 and 443 to the proxy
+    #[serde(default)]
+    pub transparent: bool,
+}
+
+impl EgressProxy {
+    /// Split a `CONTAINER:PORT` proxy target into the container (name or ID) and port
+    pub fn parse_target(target: &str) -> Result<(String, u16), String> {
+        let (container, port) = target.trim().rsplit_once(':')
+            .ok_or_else(|| format!("Invalid egress proxy '{}': expected CONTAINER:PORT", target))?;
+        let port = port.parse::<u16>().ok().filter(|port| *port > 0)
+            .ok_or_else(|| format!("Invalid egress proxy port '{}'", port))?;
+        if container.is_empty() {
+            return Err(format!("Invalid egress proxy '{}': the container is missing", target));
+        }
+        Ok((container.to_string(), port))
+    }
+
+    /// Proxy variables for the container, with the proxy at `proxy_ip`
+    pub fn environment(&self, proxy_ip: &str, subnet_cidr: &str, cluster_domain: &str) -> Vec<(String, String)> {
+        let url = format!("http://{}:{}", proxy_ip, self.port);
+        let no_proxy = format!("localhost,127.0.0.1,::1,{},.{}", subnet_cidr, cluster_domain);
+        let mut environment = Vec::new();
+        for (name, value) in [("HTTP_PROXY", &url), ("HTTPS_PROXY", &url), ("NO_PROXY", &no_proxy)] {
+            environment.push((name.to_string(), value.clone()));
+            environment.push((name.to_ascii_lowercase(), value.clone()));
+        }
+        environment
+    }
+}
+
+/// Comment on a container's redirection rules
+pub fn redirect_comment(container_id: &str) -> String {
+    format!("quilt:{}:egress", container_id)
+}
+
+/// iptables argument lists for the redirection: the DNAT to the proxy, the masquerade that brings
+/// replies back through the host, and a FORWARD accept for the hairpin across the bridge
+fn redirect_rule_args(action: &str, bridge_name: &str, subnet_cidr: &str, container_id: &str, container_ip: &str,
+    proxy_ip: &str, proxy_port: u16) -> Vec<Vec<String>> {
+    let comment = redirect_comment(container_id);
+    let port = proxy_port.to_string();
+    let destination = format!("{}:{}", proxy_ip, proxy_port);
+    let rules: [Vec<&str>; 3] = [
+        vec![
+            "-t", "nat", action, "PREROUTING", "-i", bridge_name, "-s", container_ip, "!", "-d", subnet_cidr,
+            "-p", "tcp", "-m", "multiport", "--dports", REDIRECTED_PORTS,
+            "-m", "comment", "--comment", &comment, "-j", "DNAT", "--to-destination", &destination,
+        ],
+        vec![
+            "-t", "nat", action, "POSTROUTING", "-s", container_ip, "-d", proxy_ip, "-p", "tcp", "--dport", &port,
+            "-m", "conntrack", "--ctstate", "DNAT", "-m", "comment", "--comment", &comment, "-j", "MASQUERADE",
+        ],
+        vec![
+            action, "FORWARD", "-i", bridge_name, "-o", bridge_name, "-s", container_ip, "-d", proxy_ip,
+            "-p", "tcp", "--dport", &port, "-m", "comment", "--comment", &comment, "-j", "ACCEPT",
+        ],
+    ];
+    rules.iter()
+        .map(|args| args.iter().map(|arg| arg.to_string()).collect())
+        .collect()
+}
+
+/// Redirect the container's web traffic to the proxy, replacing an earlier redirection (the
+/// proxy may have moved)
+pub fn install_redirect(bridge_name: &str, subnet_cidr: &str, container_id: &str, container_ip: &str,
+    proxy_ip: &str, proxy_port: u16) -> Result<(), String> {
+    remove_redirect(container_id);
+    for args in redirect_rule_args("-A", bridge_name, subnet_cidr, container_id, container_ip, proxy_ip, proxy_port) {
+        if let Err(e) = PortForwarder::run_iptables(&args) {
+            remove_redirect(container_id);
+            return Err(format!("Failed to redirect egress of {} to {}:{}: {}", container_id, proxy_ip, proxy_port, e));
+        }
+    }
+    ConsoleLogger::debug(&format!("Redirecting ports {} of {} to proxy {}:{}", REDIRECTED_PORTS, container_id, proxy_ip, proxy_port));
+    Ok(())
+}
+
+/// Remove the container's redirection rules; returns how many were removed
+pub fn remove_redirect(container_id: &str) -> usize {
+    let comment = redirect_comment(container_id);
+    let mut removed = 0;
+    for table in ["nat", "filter"] {
+        for args in comment_rule_deletions(table, &list_rules(table), &comment) {
+            match PortForwarder::run_iptables(&args) {
+                Ok(()) => removed += 1,
+                Err(e) => ConsoleLogger::debug(&format!("Egress rule removal skipped ({}): {}", args.join(" "), e)),
+            }
+        }
+    }
+    removed
+}
+
+/// Whether every redirection rule to the proxy at `proxy_ip` is installed
+pub fn redirect_installed(bridge_name: &str, subnet_cidr: &str, container_id: &str, container_ip: &str,
+    proxy_ip: &str, proxy_port: u16) -> bool {
+    redirect_rule_args("-C", bridge_name, subnet_cidr, container_id, container_ip, proxy_ip, proxy_port)
+        .iter()
+        .all(|args| PortForwarder::run_iptables(args).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_egress_proxy() {
+        assert_eq!(EgressProxy::parse_target("squid:3128"), Ok(("squid".to_string(), 3128)));
+        assert!(EgressProxy::parse_target("squid").is_err());
+        assert!(EgressProxy::parse_target(":3128").is_err());
+        assert!(EgressProxy::parse_target("squid:0").is_err());
+
+        let proxy = EgressProxy { container_id: "proxy1".to_string(), port: 3128, transparent: true };
+        let environment = proxy.environment("10.42.0.9", "10.42.0.0/16", "quilt.local");
+        assert_eq!(environment.len(), 6);
+        assert!(environment.contains(&("https_proxy".to_string(), "http://10.42.0.9:3128".to_string())));
+        assert!(environment.contains(&("NO_PROXY".to_string(), "localhost,127.0.0.1,::1,10.42.0.0/16,.quilt.local".to_string())));
+
+        let rules = redirect_rule_args("-A", "quilt0", "10.42.0.0/16", "web", "10.42.0.5", "10.42.0.9", 3128);
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].join(" "), "-t nat -A PREROUTING -i quilt0 -s 10.42.0.5 ! -d 10.42.0.0/16 -p tcp -m multiport --dports 80,443 \
+            -m comment --comment quilt:web:egress -j DNAT --to-destination 10.42.0.9:3128");
+        // Only redirected flows are masqueraded
+        assert!(rules[1].windows(2).any(|pair| pair == ["--ctstate", "DNAT"]));
+        assert_eq!(rules[2][..2], ["-A", "FORWARD"]);
+    }
+}
@@ -0,0 +1,170 @@
+// Host firewall coexistence
+// A host running firewalld or ufw owns the filter rules and rewrites them on every reload, so
+// accepts inserted behind its back for the bridge disappear (firewalld with the iptables backend
+// even flushes the NAT rules). Instead the bridge is registered through the manager's own
+// interface: firewalld gets a `quilt` zone with an ACCEPT target holding the bridge, ufw gets
+// allow and route-allow rules on the bridge. Both are persistent, so they outlive reloads and
+// reboots. The network reconciler re-checks the registration on every pass and reapplies it,
+// and restores the published-port NAT rules a reload wiped in the same pass.
+
+use crate::utils::command::CommandExecutor;
+use crate::utils::console::ConsoleLogger;
+
+/// `auto` (default) integrates with the firewall manager found running; `none` leaves it alone
+pub const HOST_FIREWALL_ENV: &str = "QUILT_HOST_FIREWALL";
+/// firewalld zone holding the bridge
+pub const FIREWALLD_ZONE: &str = "quilt";
+
+/// Firewall manager that owns the host's rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostFirewall {
+    Firewalld,
+    Ufw,
+}
+
+impl HostFirewall {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HostFirewall::Firewalld => "firewalld",
+            HostFirewall::Ufw => "ufw",
+        }
+    }
+
+    /// The running firewall manager, unless `QUILT_HOST_FIREWALL=none`. Runs firewall-cmd/ufw.
+    pub fn detect() -> Option<HostFirewall> {
+        let setting = std::env::var(HOST_FIREWALL_ENV).unwrap_or_default().trim().to_ascii_lowercase();
+        if matches!(setting.as_str(), "none" | "off" | "0" | "false") {
+            return None;
+        }
+        let firewalld = CommandExecutor::run("firewall-cmd", ["--state"])
+            .map_or(false, |result| result.success && result.stdout.trim() == "running");
+        if firewalld {
+            return Some(HostFirewall::Firewalld);
+        }
+        let ufw = CommandExecutor::run("ufw", ["status"])
+            .map_or(false, |result| result.success && result.stdout.contains("Status: active"));
+        ufw.then_some(HostFirewall::Ufw)
+    }
+
+    /// Register `bridge` with the firewall manager if it is not registered (or lost it).
+    /// Returns whether anything was applied.
+    pub fn ensure_bridge(&self, bridge: &str) -> Result<bool, String> {
+        match self {
+            HostFirewall::Firewalld => ensure_firewalld_zone(bridge),
+            HostFirewall::Ufw => ensure_ufw_rules(bridge),
+        }
+    }
+
+    /// Whether `bridge` is registered; also false when the manager cannot be queried
+    pub fn bridge_registered(&self, bridge: &str) -> bool {
+        match self {
+            HostFirewall::Firewalld => firewalld_zone_of(bridge).as_deref() == Some(FIREWALLD_ZONE),
+            HostFirewall::Ufw => ufw_added_rules().map_or(false, |added| missing_ufw_rules(&added, bridge).is_empty()),
+        }
+    }
+}
+
+fn firewall_cmd(args: &[String]) -> Result<String, String> {
+    let result = CommandExecutor::run("firewall-cmd", args.iter().cloned())?;
+    if !result.success {
+        return Err(format!("firewall-cmd {} failed: {}", args.join(" "), result.stderr.trim()));
+    }
+    Ok(result.stdout)
+}
+
+/// Runtime zone of `interface`; `None` when it has none
+fn firewalld_zone_of(interface: &str) -> Option<String> {
+    CommandExecutor::run("firewall-cmd", [format!("--get-zone-of-interface={}", interface)])
+        .ok()
+        .filter(|result| result.success)
+        .map(|result| result.stdout.trim().to_string())
+}
+
+/// firewall-cmd invocations that put `bridge` in the quilt zone, permanently and at runtime.
+/// Creating the zone needs a reload before the runtime configuration knows it.
+fn firewalld_commands(bridge: &str, create_zone: bool) -> Vec<Vec<String>> {
+    let zone = format!("--zone={}", FIREWALLD_ZONE);
+    let mut commands = Vec::new();
+    if create_zone {
+        commands.push(vec!["--permanent".to_string(), format!("--new-zone={}", FIREWALLD_ZONE)]);
+        commands.push(vec!["--permanent".to_string(), zone.clone(), "--set-target=ACCEPT".to_string()]);
+        commands.push(vec!["--reload".to_string()]);
+    }
+    commands.push(vec!["--permanent".to_string(), zone.clone(), format!("--change-interface={}", bridge)]);
+    commands.push(vec![zone, format!("--change-interface={}", bridge)]);
+    commands
+}
+
+fn ensure_firewalld_zone(bridge: &str) -> Result<bool, String> {
+    if firewalld_zone_of(bridge).as_deref() == Some(FIREWALLD_ZONE) {
+        return Ok(false);
+    }
+    let zones = firewall_cmd(&["--permanent".to_string(), "--get-zones".to_string()])?;
+    let create_zone = !zones.split_whitespace().any(|zone| zone == FIREWALLD_ZONE);
+    for args in firewalld_commands(bridge, create_zone) {
+        firewall_cmd(&args)?;
+    }
+    ConsoleLogger::debug(&format!("🧱 [HOST-FIREWALL] Bridge {} added to firewalld zone {}", bridge, FIREWALLD_ZONE));
+    Ok(true)
+}
+
+/// ufw rules for `bridge`: containers reach the host (gateway, DNS listener) and are forwarded
+/// both ways, as `ufw show added` lists them
+fn ufw_rules(bridge: &str) -> Vec<String> {
+    vec![
+        format!("ufw allow in on {}", bridge),
+        format!("ufw route allow in on {}", bridge),
+        format!("ufw route allow out on {}", bridge),
+    ]
+}
+
+fn ufw_added_rules() -> Result<String, String> {
+    let result = CommandExecutor::run("ufw", ["show", "added"])?;
+    if !result.success {
+        return Err(format!("ufw show added failed: {}", result.stderr.trim()));
+    }
+    Ok(result.stdout)
+}
+
+/// Rules of `bridge` that `ufw show added` output does not list
+fn missing_ufw_rules(added: &str, bridge: &str) -> Vec<String> {
+    ufw_rules(bridge).into_iter()
+        .filter(|rule| !added.lines().any(|line| line.trim() == rule))
+        .collect()
+}
+
+fn ensure_ufw_rules(bridge: &str) -> Result<bool, String> {
+    let missing = missing_ufw_rules(&ufw_added_rules()?, bridge);
+    for rule in &missing {
+        let args: Vec<&str> = rule.split_whitespace().skip(1).collect();
+        let result = CommandExecutor::run("ufw", args)?;
+        if !result.success {
+            return Err(format!("{} failed: {}", rule, result.stderr.trim()));
+        }
+    }
+    if !missing.is_empty() {
+        ConsoleLogger::debug(&format!("🧱 [HOST-FIREWALL] Added {} ufw rule(s) for bridge {}", missing.len(), bridge));
+    }
+    Ok(!missing.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_firewall_rules() {
+        let commands = firewalld_commands("quilt0", true);
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[1].join(" "), "--permanent --zone=quilt --set-target=ACCEPT");
+        assert_eq!(commands[2], vec!["--reload"]);
+        // An existing zone is not reloaded, which would flush the NAT rules
+        let commands = firewalld_commands("quilt0", false);
+        assert!(!commands.iter().any(|args| args[0] == "--reload"));
+        assert_eq!(commands[1].join(" "), "--zone=quilt --change-interface=quilt0");
+
+        let added = "Added user rules (see 'ufw status' for running firewall):\nufw allow 22/tcp\nufw allow in on quilt0\nufw route allow out on quilt0\n";
+        assert_eq!(missing_ufw_rules(added, "quilt0"), vec!["ufw route allow in on quilt0"]);
+        assert_eq!(missing_ufw_rules(added, "quilt1").len(), 3);
+    }
+}
@@ -0,0 +1,126 @@
+// Traffic mirroring
+// Copies the packets on a container's host-side veth to an observer container's veth with tc:
+// a clsact qdisc on the source veth carries matchall filters whose mirred action sends a copy of
+// every packet out of the observer's veth, which delivers it to the observer's interface. The
+// source's own traffic is untouched, so an IDS or capture sidecar sees it without sitting inline.
+// The observer sees packets addressed to other MACs, so its sniffer must put the interface in
+// promiscuous mode, as tcpdump and most IDSes do. One observer per source; the tc state goes
+// with the source veth.
+
+use crate::utils::command::CommandExecutor;
+use crate::utils::console::ConsoleLogger;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Which of the source container's packets are mirrored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorDirection {
+    Both,
+    /// Packets the container receives
+    Ingress,
+    /// Packets the container sends
+    Egress,
+}
+
+impl MirrorDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MirrorDirection::Both => "both",
+            MirrorDirection::Ingress => "ingress",
+            MirrorDirection::Egress => "egress",
+        }
+    }
+
+    /// tc hooks on the host-side veth: what the container sends arrives there as ingress
+    fn veth_hooks(&self) -> &'static [&'static str] {
+        match self {
+            MirrorDirection::Both => &["ingress", "egress"],
+            MirrorDirection::Ingress => &["egress"],
+            MirrorDirection::Egress => &["ingress"],
+        }
+    }
+}
+
+impl FromStr for MirrorDirection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "both" => Ok(MirrorDirection::Both),
+            "ingress" | "in" => Ok(MirrorDirection::Ingress),
+            "egress" | "out" => Ok(MirrorDirection::Egress),
+            other => Err(format!("Invalid mirror direction '{}': expected both, ingress or egress", other)),
+        }
+    }
+}
+
+/// tc invocations that mirror `source_veth` to `observer_veth`, after any previous mirror of the
+/// source was removed
+fn mirror_commands(source_veth: &str, observer_veth: &str, direction: MirrorDirection) -> Vec<Vec<String>> {
+    let mut commands = vec![vec!["qdisc", "add", "dev", source_veth, "clsact"]];
+    for hook in direction.veth_hooks() {
+        commands.push(vec![
+            "filter", "add", "dev", source_veth, hook, "matchall",
+            "action", "mirred", "egress", "mirror", "dev", observer_veth,
+        ]);
+    }
+    commands.into_iter()
+        .map(|args| args.into_iter().map(str::to_string).collect())
+        .collect()
+}
+
+/// Mirror `source_veth`'s packets to `observer_veth`, replacing a mirror the source had
+pub fn start_mirror(source_veth: &str, observer_veth: &str, direction: MirrorDirection) -> Result<(), String> {
+    for veth in [source_veth, observer_veth] {
+        if !Path::new(&format!("/sys/class/net/{}", veth)).exists() {
+            return Err(format!("Interface {} does not exist", veth));
+        }
+    }
+    stop_mirror(source_veth)?;
+    for args in mirror_commands(source_veth, observer_veth, direction) {
+        let result = CommandExecutor::run("tc", &args)?;
+        if !result.success {
+            let _ = stop_mirror(source_veth);
+            return Err(format!("Failed to mirror {} to {}: {}", source_veth, observer_veth, result.stderr.trim()));
+        }
+    }
+    ConsoleLogger::debug(&format!("Mirroring {} traffic of {} to {}", direction.as_str(), source_veth, observer_veth));
+    Ok(())
+}
+
+/// Stop mirroring `source_veth`; a veth that is gone or has no mirror is fine
+pub fn stop_mirror(source_veth: &str) -> Result<(), String> {
+    if !Path::new(&format!("/sys/class/net/{}", source_veth)).exists() {
+        return Ok(());
+    }
+    // The filters go with the qdisc; nothing else on the veths uses tc
+    let result = CommandExecutor::run("tc", ["qdisc", "del", "dev", source_veth, "clsact"])?;
+    let missing = result.stderr.contains("Cannot find specified qdisc") || result.stderr.contains("Invalid handle");
+    if !result.success && !missing {
+        return Err(format!("Failed to stop mirroring {}: {}", source_veth, result.stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_commands() {
+        assert_eq!("".parse::<MirrorDirection>(), Ok(MirrorDirection::Both));
+        assert_eq!("OUT".parse::<MirrorDirection>(), Ok(MirrorDirection::Egress));
+        assert!("sideways".parse::<MirrorDirection>().is_err());
+
+        let commands = mirror_commands("veth-src", "veth-ids", MirrorDirection::Both);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0].join(" "), "qdisc add dev veth-src clsact");
+        assert_eq!(commands[1].join(" "), "filter add dev veth-src ingress matchall action mirred egress mirror dev veth-ids");
+        assert_eq!(commands[2][4], "egress");
+
+        // What the container receives leaves the host-side veth
+        let received = mirror_commands("veth-src", "veth-ids", MirrorDirection::Ingress);
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1][4], "egress");
+    }
+}
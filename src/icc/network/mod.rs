@@ -6,9 +6,19 @@ pub mod veth;
 pub mod dns_manager;
 pub mod diagnostics;
 pub mod security;
+pub mod port_forwarding;
+pub mod capture;
+pub mod connectivity;
+pub mod netns_pool;
+pub mod teardown;
+pub mod attachments;
+pub mod mirror;
+pub mod host_firewall;
+pub mod egress_proxy;
 
 use crate::utils::console::ConsoleLogger;
 use crate::utils::command::CommandExecutor;
+use crate::utils::validation::PortMapping;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
@@ -18,6 +28,15 @@ pub use veth::{VethManager, ContainerNetworkConfig};
 pub use dns_manager::DnsManager;
 pub use diagnostics::NetworkDiagnostics;
 pub use security::NetworkSecurity;
+pub use port_forwarding::PortForwarder;
+pub use capture::{CaptureEvent, CaptureOptions, PacketCapture};
+pub use connectivity::{ConnectivityReport, ConnectivityTest, ProbeOptions};
+pub use netns_pool::{NetnsPool, PooledNetns};
+pub use teardown::NetworkTeardown;
+pub use attachments::NetworkAttachmentPlan;
+pub use mirror::MirrorDirection;
+pub use host_firewall::HostFirewall;
+pub use egress_proxy::EgressProxy;
 
 /// Network configuration for the container networking system
 #[derive(Debug, Clone)]
@@ -36,6 +55,10 @@ pub struct NetworkManager {
     pub dns_manager: DnsManager,
     pub diagnostics: NetworkDiagnostics,
     pub security: NetworkSecurity,
+    pub port_forwarder: PortForwarder,
+    pub netns_pool: Arc<NetnsPool>,
+    /// firewalld or ufw when one manages the host's rules; the bridge is registered with it
+    pub host_firewall: Option<HostFirewall>,
 }
 
 #[allow(dead_code)]
@@ -53,6 +76,9 @@ impl NetworkManager {
         let dns_manager = DnsManager::new(config.bridge_name.clone(), config.bridge_ip.clone());
         let diagnostics = NetworkDiagnostics::new(config.bridge_name.clone(), config.bridge_ip.clone());
         let security = NetworkSecurity::new(config.bridge_ip.clone());
+        let port_forwarder = PortForwarder::new(config.bridge_name.clone());
+        let netns_pool = Arc::new(NetnsPool::new(config.bridge_name.clone(), NetnsPool::size_from_env()));
+        let host_firewall = HostFirewall::detect();
         
         Ok(Self { 
             config,
@@ -61,13 +87,116 @@ impl NetworkManager {
             dns_manager,
             diagnostics,
             security,
+            port_forwarder,
+            netns_pool,
+            host_firewall,
         })
     }
 
+    /// Clear namespaces from a previous run and start filling the warm pool (no-op when disabled).
+    /// Must be called after the bridge is ready.
+    pub fn start_netns_pool(&self) {
+        if !self.netns_pool.enabled() {
+            return;
+        }
+        let pool = Arc::clone(&self.netns_pool);
+        tokio::task::spawn_blocking(move || {
+            pool.remove_stale();
+            pool.refill();
+        });
+    }
+
+    /// Take a warm namespace for a container (the same one again on restart) and top the pool back up
+    pub fn claim_pooled_netns(&self, container_id: &str) -> Option<PooledNetns> {
+        if !self.netns_pool.enabled() {
+            return None;
+        }
+        let interface_name = format!("quilt{}", &container_id[..8]);
+        let slot = self.netns_pool.claim(container_id, &interface_name);
+        let pool = Arc::clone(&self.netns_pool);
+        tokio::task::spawn_blocking(move || pool.refill());
+        slot
+    }
+
+    pub fn pooled_netns(&self, container_id: &str) -> Option<PooledNetns> {
+        self.netns_pool.claimed_slot(container_id)
+    }
+
+    pub fn release_pooled_netns(&self, container_id: &str) {
+        self.netns_pool.release(container_id)
+    }
+
+    /// Remove a container's veth pair, tagged iptables rules and bridge neighbor entry and verify
+    /// they are gone. DNS and a claimed warm namespace are released separately.
+    pub fn teardown_container_network(&self, container_id: &str, container_ip: Option<&str>, recorded_interfaces: &[String]) -> Result<NetworkTeardown, String> {
+        teardown::teardown_container_network(container_id, container_ip, recorded_interfaces, Some(&self.config.bridge_name))
+    }
+
     pub fn ensure_bridge_ready(&self) -> Result<(), String> {
         self.bridge_manager.ensure_bridge_ready()
     }
 
+    /// Register the bridge with the host's firewall manager unless it still is; returns whether
+    /// it had to be (re)applied. A no-op without firewalld or ufw. See `host_firewall`.
+    pub fn reconcile_host_firewall(&self) -> Result<bool, String> {
+        match self.host_firewall {
+            Some(firewall) => firewall.ensure_bridge(&self.config.bridge_name),
+            None => Ok(false),
+        }
+    }
+
+    /// Plumb a running container into an additional network; see `attachments`
+    pub fn connect_container_to_network(&self, plan: &NetworkAttachmentPlan, container_pid: i32) -> Result<(), String> {
+        self.security.validate_container_id(&plan.container_id)?;
+        self.security.validate_container_pid(container_pid)?;
+        self.security.validate_ip_address(&plan.ip_address)?;
+        attachments::connect_container(plan, container_pid)?;
+        self.security.audit_network_operation("NETWORK_CONNECT", &plan.container_id,
+            &format!("Network: {}, IP: {}", plan.network, plan.ip_address));
+        Ok(())
+    }
+
+    /// Take a container off an additional network by deleting its veth pair there
+    pub fn disconnect_container_from_network(&self, container_id: &str, network: &str, veth_host: &str) -> Result<(), String> {
+        attachments::remove_veth(veth_host)?;
+        self.security.audit_network_operation("NETWORK_DISCONNECT", container_id, &format!("Network: {}", network));
+        Ok(())
+    }
+
+    /// Mirror a container's veth traffic to an observer container's veth; see `mirror`
+    pub fn mirror_container_traffic(&self, container_id: &str, source_veth: &str, observer_veth: &str, direction: MirrorDirection) -> Result<(), String> {
+        self.security.validate_container_id(container_id)?;
+        mirror::start_mirror(source_veth, observer_veth, direction)?;
+        self.security.audit_network_operation("MIRROR_START", container_id,
+            &format!("Interface: {}, Observer: {}, Direction: {}", source_veth, observer_veth, direction.as_str()));
+        Ok(())
+    }
+
+    pub fn stop_traffic_mirror(&self, container_id: &str, source_veth: &str) -> Result<(), String> {
+        mirror::stop_mirror(source_veth)?;
+        self.security.audit_network_operation("MIRROR_STOP", container_id, &format!("Interface: {}", source_veth));
+        Ok(())
+    }
+
+    /// Redirect a container's connections to ports 80 and 443 to its egress proxy; see `egress_proxy`
+    pub fn redirect_egress_to_proxy(&self, container_id: &str, container_ip: &str, proxy_ip: &str, proxy_port: u16) -> Result<(), String> {
+        self.security.validate_container_id(container_id)?;
+        self.security.validate_ip_address(container_ip)?;
+        self.security.validate_ip_address(proxy_ip)?;
+        egress_proxy::install_redirect(&self.config.bridge_name, &self.config.subnet_cidr, container_id, container_ip, proxy_ip, proxy_port)?;
+        self.security.audit_network_operation("EGRESS_REDIRECT", container_id, &format!("Proxy: {}:{}", proxy_ip, proxy_port));
+        Ok(())
+    }
+
+    pub fn remove_egress_redirect(&self, container_id: &str) -> usize {
+        egress_proxy::remove_redirect(container_id)
+    }
+
+    /// Whether a container's redirection to the proxy at `proxy_ip` is fully installed
+    pub fn egress_redirect_installed(&self, container_id: &str, container_ip: &str, proxy_ip: &str, proxy_port: u16) -> bool {
+        egress_proxy::redirect_installed(&self.config.bridge_name, &self.config.subnet_cidr, container_id, container_ip, proxy_ip, proxy_port)
+    }
+
     pub fn setup_container_network(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         ConsoleLogger::progress(&format!("Setting up network for container {} (PID: {})", 
             config.container_id, container_pid));
@@ -77,6 +206,10 @@ impl NetworkManager {
         self.security.validate_container_pid(container_pid)?;
         self.security.validate_ip_address(&config.ip_address)?;
 
+        let id = config.container_id.as_str();
+        ConsoleLogger::container_debug(id, &format!("network: ip={} gateway={} veth={}/{}",
+            config.ip_address, config.gateway_ip, config.veth_host_name, config.veth_container_name));
+
         // Step 1: Validate bridge is ready
         if !self.bridge_exists() {
             return Err(format!("Bridge {} does not exist - cannot setup container network", self.config.bridge_name));
@@ -84,8 +217,14 @@ impl NetworkManager {
         
         self.bridge_manager.verify_bridge_up()
             .map_err(|e| format!("Bridge validation failed: {}", e))?;
+        
+        // Fast path: the container joined a warm namespace whose veth is already on the bridge
+        if let Some(slot) = self.netns_pool.claimed_slot(&config.container_id) {
+            return self.setup_pooled_container_network(config, container_pid, &slot);
+        }
 
         // Step 2: Create veth pair
+        ConsoleLogger::container_debug(id, "network: creating veth pair");
         self.veth_manager.create_veth_pair(&config.veth_host_name, &config.veth_container_name)?;
         
         // Step 3: Verify veth pair creation
@@ -98,42 +237,71 @@ impl NetworkManager {
         }
         
         // Step 4.1: Move container-side veth to container namespace
+        ConsoleLogger::container_debug(id, &format!("network: moving {} into namespace of PID {}", config.veth_container_name, container_pid));
         self.veth_manager.move_veth_to_container(&config.veth_container_name, container_pid)?;
         
         // Step 5: Configure container interface (IP, routing, etc.)
+        ConsoleLogger::container_debug(id, "network: configuring container interface");
         self.veth_manager.configure_container_interface(config, container_pid)?;
         
         // Step 6: Attach host-side veth to bridge
+        ConsoleLogger::container_debug(id, &format!("network: attaching {} to {}", config.veth_host_name, self.config.bridge_name));
         self.veth_manager.attach_veth_to_bridge_with_retry(&config.veth_host_name)
             .map_err(|e| format!("Bridge attachment failed: {}", e))?;
         
         // Step 7: Configure DNS for container
+        ConsoleLogger::container_debug(id, "network: configuring DNS");
         self.dns_manager.configure_container_dns(config, container_pid)?;
         
         // Step 7.1: Verify DNS container isolation
-        let dns_content = format!("nameserver {}\nsearch quilt.local\n", self.config.bridge_ip);
+        let dns_content = self.dns_manager.resolv_conf();
         if !self.security.verify_dns_container_isolation(container_pid, &dns_content) {
             ConsoleLogger::warning(&format!("⚠️ DNS container isolation verification failed for {}", config.container_id));
         }
         
+        ConsoleLogger::success(&format!("Network configured for container {} at {}", 
+            config.container_id, config.ip_address));
+        Ok(())
+    }
+
+    fn setup_pooled_container_network(&self, config: &ContainerNetworkConfig, container_pid: i32, slot: &PooledNetns) -> Result<(), String> {
+        ConsoleLogger::debug(&format!("🌊 Using warm namespace {} for container {}", slot.netns_name, config.container_id));
+        
+        ConsoleLogger::container_debug(&config.container_id, &format!("network: configuring {} in warm namespace", slot.veth_container_name));
+        self.veth_manager.configure_pooled_interface(config, &slot.netns_name)?;
+        
+        if !self.veth_manager.verify_bridge_attachment_fast(&slot.veth_host_name) {
+            return Err(format!("Pooled veth {} is no longer attached to {}", slot.veth_host_name, self.config.bridge_name));
+        }
+        
+        self.dns_manager.configure_container_dns(config, container_pid)?;
+        
+        ConsoleLogger::success(&format!("Network configured for container {} at {} (warm namespace)", 
+            config.container_id, config.ip_address));
+        Ok(())
+    }
+
+    /// Run post-setup connectivity diagnostics and readiness checks for a configured container.
+    /// Uses the async command executor so pings and probes do not occupy runtime workers.
+    pub async fn verify_container_network(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         // Step 8: Run comprehensive diagnostics
+        ConsoleLogger::container_debug(&config.container_id, "network: running connectivity diagnostics");
         let gateway_ip = config.gateway_ip.split('/').next().unwrap();
         let interface_name = format!("quilt{}", &config.container_id[..8]);
-        self.diagnostics.test_gateway_connectivity_comprehensive(container_pid, gateway_ip, &interface_name);
+        self.diagnostics.test_gateway_connectivity_comprehensive(container_pid, gateway_ip, &interface_name).await;
         
         // Step 8.1: Test bidirectional connectivity
         let container_ip = config.ip_address.split('/').next().unwrap();
-        self.diagnostics.test_bidirectional_connectivity(container_pid, container_ip, gateway_ip);
+        self.diagnostics.test_bidirectional_connectivity(container_pid, container_ip, gateway_ip).await;
         
         // Step 9: Verify network readiness
-        self.diagnostics.verify_container_network_ready(config, container_pid)?;
+        self.diagnostics.verify_container_network_ready(config, container_pid).await?;
+        ConsoleLogger::container_debug(&config.container_id, "network: readiness verified");
         
         // Step 10: Security audit
         self.security.audit_network_operation("SETUP_COMPLETE", &config.container_id, 
             &format!("IP: {}, Gateway: {}", config.ip_address, config.gateway_ip));
         
-        ConsoleLogger::success(&format!("Network configured for container {} at {}", 
-            config.container_id, config.ip_address));
         Ok(())
     }
 
@@ -147,18 +315,120 @@ impl NetworkManager {
         self.dns_manager.start_dns_server().await
     }
 
-    pub fn register_container_dns(&self, container_id: &str, container_name: &str, ip_address: &str) -> Result<(), String> {
-        self.dns_manager.register_container_dns(container_id, container_name, ip_address)
+    pub fn register_container_dns(&self, container_id: &str, container_name: &str, ip_address: &str, wildcard: bool) -> Result<(), String> {
+        self.dns_manager.register_container_dns(container_id, container_name, ip_address, wildcard)
     }
 
     pub fn unregister_container_dns(&self, container_id: &str) -> Result<(), String> {
         self.dns_manager.unregister_container_dns(container_id)
     }
 
+    pub fn transfer_container_dns(&self, from_id: &str, to_id: &str, container_name: &str) -> Result<(), String> {
+        self.dns_manager.transfer_container_dns(from_id, to_id, container_name)
+    }
+
     pub fn list_dns_entries(&self) -> Result<Vec<crate::icc::dns::DnsEntry>, String> {
         self.dns_manager.list_dns_entries()
     }
 
+    pub fn add_dns_alias(&self, container_id: &str, alias: &str) -> Result<(), String> {
+        self.dns_manager.add_dns_alias(container_id, alias)
+    }
+
+    pub fn remove_dns_alias(&self, alias: &str) -> Result<String, String> {
+        self.dns_manager.remove_dns_alias(alias)
+    }
+
+    pub fn set_virtual_service_backends(&self, name: &str, backends: Vec<std::net::IpAddr>) -> Result<(), String> {
+        self.dns_manager.set_virtual_service_backends(name, backends)
+    }
+
+    pub fn remove_virtual_service(&self, name: &str) -> Result<(), String> {
+        self.dns_manager.remove_virtual_service(name)
+    }
+
+    pub fn set_dns_record(&self, name: &str, view: crate::icc::dns::DnsView, addresses: Vec<std::net::IpAddr>) -> Result<(), String> {
+        self.dns_manager.set_dns_record(name, view, addresses)
+    }
+
+    pub fn remove_dns_record(&self, name: &str, view: crate::icc::dns::DnsView) -> Result<(), String> {
+        self.dns_manager.remove_dns_record(name, view)
+    }
+
+    /// Publish names under `domain` and give containers its search list. Takes effect for the
+    /// DNS server when it starts and for each resolv.conf written afterwards.
+    pub fn set_dns_domain(&mut self, domain: crate::icc::dns::DnsDomainConfig) {
+        self.diagnostics.cluster_domain = domain.cluster_domain.clone();
+        self.dns_manager.domain = domain;
+    }
+
+    pub fn dns_domain(&self) -> &crate::icc::dns::DnsDomainConfig {
+        &self.dns_manager.domain
+    }
+
+    pub fn dns_cache_stats(&self) -> Option<crate::icc::dns::DnsCacheStats> {
+        self.dns_manager.dns_cache_stats()
+    }
+
+    pub fn flush_dns_cache(&self) -> Result<usize, String> {
+        self.dns_manager.flush_dns_cache()
+    }
+
+    pub fn publish_container_ports(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) -> Result<(), String> {
+        self.security.validate_container_id(container_id)?;
+        self.port_forwarder.publish_ports(container_id, container_ip, mappings)
+    }
+
+    pub fn unpublish_container_ports(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) {
+        self.port_forwarder.unpublish_ports(container_id, container_ip, mappings)
+    }
+
+    pub fn dns_listener(&self) -> Option<(std::net::SocketAddr, bool)> {
+        self.dns_manager.dns_listener()
+    }
+
+    pub fn dns_redirect_installed(&self) -> bool {
+        self.dns_manager.dns_redirect_installed()
+    }
+
+    pub fn mdns_enabled(&self) -> bool {
+        self.dns_manager.mdns_enabled()
+    }
+
+    /// Mappings whose NAT rules are no longer installed
+    pub fn missing_port_mappings(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) -> Vec<PortMapping> {
+        self.port_forwarder.missing_mappings(container_id, container_ip, mappings)
+    }
+
+    /// Re-check the bridge and repair drift; see `BridgeManager::reconcile`
+    pub fn reconcile_bridge(&self) -> Result<Vec<&'static str>, String> {
+        self.bridge_manager.reconcile()
+    }
+
+    /// Put a container's host-side veth back on the bridge (after the bridge was recreated)
+    pub fn reattach_veth(&self, veth_name: &str) -> Result<(), String> {
+        self.veth_manager.attach_veth_to_bridge_with_retry(veth_name)
+    }
+
+    /// Start a bounded packet capture on a container's host-side veth
+    pub fn capture_container_traffic(&self, container_id: &str, veth_host_name: &str, options: CaptureOptions)
+        -> Result<tokio::sync::mpsc::Receiver<CaptureEvent>, String> {
+        self.security.validate_container_id(container_id)?;
+        let options = options.normalized()?;
+        self.security.audit_network_operation("CAPTURE_START", container_id,
+            &format!("Interface: {}, Filter: '{}'", veth_host_name, options.filter));
+        PacketCapture::start(veth_host_name, options)
+    }
+
+    /// Ping or TCP-connect to `address` from inside a running container's network namespace
+    pub async fn test_connectivity(&self, container_id: &str, container_pid: i32, address: std::net::IpAddr, options: ProbeOptions)
+        -> Result<ConnectivityReport, String> {
+        self.security.validate_container_id(container_id)?;
+        self.security.audit_network_operation("CONNECTIVITY_TEST", container_id,
+            &format!("Target: {}, Port: {:?}", address, options.port));
+        ConnectivityTest::run(container_pid, address, options).await
+    }
+
     pub fn allocate_next_ip(&self) -> Result<String, String> {
         // ELITE: Lock-free IP allocation using compare-and-swap
         let mut current_ip = self.config.next_ip.load(Ordering::Relaxed);
@@ -218,12 +488,12 @@ impl NetworkManager {
         self.veth_manager.get_container_interface_mac_address(container_pid, interface_name)
     }
 
-    pub fn test_bidirectional_connectivity(&self, container_pid: i32, container_ip: &str, gateway_ip: &str) {
-        self.diagnostics.test_bidirectional_connectivity(container_pid, container_ip, gateway_ip)
+    pub async fn test_bidirectional_connectivity(&self, container_pid: i32, container_ip: &str, gateway_ip: &str) {
+        self.diagnostics.test_bidirectional_connectivity(container_pid, container_ip, gateway_ip).await
     }
 
-    pub fn verify_container_network_ready(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
-        self.diagnostics.verify_container_network_ready(config, container_pid)
+    pub async fn verify_container_network_ready(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
+        self.diagnostics.verify_container_network_ready(config, container_pid).await
     }
 
     pub fn validate_container_namespace(&self, container_pid: i32) -> bool {
@@ -234,36 +504,44 @@ impl NetworkManager {
         self.security.verify_dns_container_isolation(container_pid, expected_content)
     }
     
-    /// Comprehensive network health monitoring service
-    pub fn run_network_health_monitoring(&self) -> Result<NetworkHealthReport, String> {
-        ConsoleLogger::info("🔍 Starting comprehensive network health monitoring...");
+    /// Comprehensive network health monitoring service.
+    /// `targets` are the running containers to probe; when `scoped` is set, host-wide
+    /// interface and namespace scans are restricted to those containers.
+    pub async fn run_network_health_monitoring(&self, targets: &[DiagnosticTarget], scoped: bool) -> Result<NetworkHealthReport, String> {
+        ConsoleLogger::info(&format!("🔍 Starting network health monitoring ({} containers{})...",
+            targets.len(), if scoped { ", scoped" } else { "" }));
         
         let mut report = NetworkHealthReport::new();
         let start_time = std::time::SystemTime::now();
         
         // 1. Bridge attachment verification for all veth interfaces
         ConsoleLogger::debug("Verifying bridge attachments...");
-        let bridge_check_result = self.verify_all_bridge_attachments();
+        let veth_names = if scoped {
+            targets.iter().map(|target| target.veth_host_name.clone()).collect()
+        } else {
+            self.list_host_veth_names().await
+        };
+        let bridge_check_result = self.verify_all_bridge_attachments(&veth_names);
         report.bridge_attachments = bridge_check_result;
         
         // 2. Interface MAC address tracking for security
         ConsoleLogger::debug("Collecting interface MAC addresses for security tracking...");
-        let mac_tracking_result = self.collect_interface_mac_addresses();
+        let mac_tracking_result = self.collect_interface_mac_addresses(&veth_names, targets);
         report.mac_addresses = mac_tracking_result;
         
         // 3. Bidirectional connectivity testing for active containers  
         ConsoleLogger::debug("Testing bidirectional connectivity...");
-        let connectivity_result = self.test_all_container_connectivity();
+        let connectivity_result = self.test_all_container_connectivity(targets).await;
         report.connectivity_tests = connectivity_result;
         
         // 4. Network readiness validation
         ConsoleLogger::debug("Validating network readiness...");
-        let readiness_result = self.validate_all_network_readiness();
+        let readiness_result = self.validate_all_network_readiness(targets).await;
         report.readiness_checks = readiness_result;
         
         // 5. Container namespace validation
         ConsoleLogger::debug("Validating container namespaces...");
-        let namespace_result = self.validate_all_container_namespaces();
+        let namespace_result = self.validate_all_container_namespaces(targets, scoped).await;
         report.namespace_validations = namespace_result;
         
         let duration = start_time.elapsed().unwrap_or_default();
@@ -274,36 +552,32 @@ impl NetworkManager {
         Ok(report)
     }
     
-    /// Verify bridge attachments for all veth interfaces
-    fn verify_all_bridge_attachments(&self) -> Vec<BridgeAttachmentCheck> {
-        let mut results = Vec::new();
-        
-        // Get list of veth interfaces from command output
-        if let Ok(result) = CommandExecutor::execute_shell("ip link show | grep veth") {
-            for line in result.stdout.lines() {
-                if let Some(veth_name) = self.extract_veth_name(line) {
-                    let check_result = match self.verify_bridge_attachment(&veth_name) {
-                        Ok(()) => BridgeAttachmentCheck {
-                            veth_name: veth_name.clone(),
-                            attached: true,
-                            error_message: None,
-                        },
-                        Err(e) => BridgeAttachmentCheck {
-                            veth_name: veth_name.clone(),
-                            attached: false,
-                            error_message: Some(e),
-                        },
-                    };
-                    results.push(check_result);
-                }
-            }
+    /// List host-side veth interfaces currently present on the host
+    async fn list_host_veth_names(&self) -> Vec<String> {
+        match CommandExecutor::run_async("ip", ["link", "show"]).await {
+            Ok(result) => result.stdout.lines().filter_map(|line| self.extract_veth_name(line)).collect(),
+            Err(_) => Vec::new(),
         }
-        
-        results
     }
     
-    /// Collect MAC addresses for all interfaces for security tracking
-    fn collect_interface_mac_addresses(&self) -> Vec<InterfaceMacInfo> {
+    /// Verify bridge attachments for the given veth interfaces
+    fn verify_all_bridge_attachments(&self, veth_names: &[String]) -> Vec<BridgeAttachmentCheck> {
+        veth_names.iter().map(|veth_name| match self.verify_bridge_attachment(veth_name) {
+            Ok(()) => BridgeAttachmentCheck {
+                veth_name: veth_name.clone(),
+                attached: true,
+                error_message: None,
+            },
+            Err(e) => BridgeAttachmentCheck {
+                veth_name: veth_name.clone(),
+                attached: false,
+                error_message: Some(e),
+            },
+        }).collect()
+    }
+    
+    /// Collect MAC addresses for the bridge and given veth interfaces for security tracking
+    fn collect_interface_mac_addresses(&self, veth_names: &[String], targets: &[DiagnosticTarget]) -> Vec<InterfaceMacInfo> {
         let mut results = Vec::new();
         
         // Get bridge interface MAC
@@ -317,51 +591,96 @@ impl NetworkManager {
         }
         
         // Get veth interface MACs
-        if let Ok(result) = CommandExecutor::execute_shell("ip link show | grep veth") {
-            for line in result.stdout.lines() {
-                if let Some(veth_name) = self.extract_veth_name(line) {
-                    if let Ok(mac) = self.get_interface_mac_address(&veth_name) {
-                        results.push(InterfaceMacInfo {
-                            interface_name: veth_name,
-                            mac_address: mac,
-                            interface_type: "veth".to_string(),
-                            container_pid: None,
-                        });
-                    }
-                }
+        for veth_name in veth_names {
+            if let Ok(mac) = self.get_interface_mac_address(veth_name) {
+                results.push(InterfaceMacInfo {
+                    interface_name: veth_name.clone(),
+                    mac_address: mac,
+                    interface_type: "veth".to_string(),
+                    container_pid: targets.iter()
+                        .find(|target| &target.veth_host_name == veth_name)
+                        .map(|target| target.container_pid),
+                });
             }
         }
         
         results
     }
     
-    /// Test bidirectional connectivity for all active containers
-    fn test_all_container_connectivity(&self) -> Vec<ConnectivityTestResult> {
-        let results = Vec::new();
-        
-        // This would need to be integrated with the sync engine to get active containers
-        // For now, we'll demonstrate the concept with placeholder logic
-        
-        ConsoleLogger::debug("Bidirectional connectivity testing requires container registry integration");
-        
-        results
+    /// Test host <-> container connectivity for the given containers, probing them concurrently
+    async fn test_all_container_connectivity(&self, targets: &[DiagnosticTarget]) -> Vec<ConnectivityTestResult> {
+        futures::future::join_all(targets.iter().map(|target| async move {
+            let started = std::time::Instant::now();
+            // Host -> container reachability over the bridge
+            let host_ping = CommandExecutor::run_async("ping", ["-c", "1", "-W", "2", &target.ip_address]);
+            // Container -> gateway reachability from inside the network namespace
+            let container_pid = target.container_pid.to_string();
+            let container_ping = CommandExecutor::run_async("nsenter",
+                ["-t", &container_pid, "-n", "ping", "-c", "1", "-W", "2", &self.config.bridge_ip]);
+
+            let (host_result, container_result) = futures::future::join(host_ping, container_ping).await;
+            let host_ok = matches!(host_result, Ok(ref r) if r.success);
+            let container_ok = matches!(container_result, Ok(ref r) if r.success);
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+
+            let error_message = match (host_ok, container_ok) {
+                (true, true) => None,
+                (false, true) => Some(format!("Host cannot reach container at {}", target.ip_address)),
+                (true, false) => Some(format!("Container cannot reach gateway {}", self.config.bridge_ip)),
+                (false, false) => Some("No connectivity in either direction".to_string()),
+            };
+
+            ConnectivityTestResult {
+                container_pid: target.container_pid,
+                container_ip: target.ip_address.clone(),
+                gateway_ip: self.config.bridge_ip.clone(),
+                success: error_message.is_none(),
+                response_time_ms: if error_message.is_none() { Some(elapsed_ms) } else { None },
+                error_message,
+            }
+        })).await
     }
     
-    /// Validate network readiness for all containers
-    fn validate_all_network_readiness(&self) -> Vec<NetworkReadinessCheck> {
-        let results = Vec::new();
-        
-        ConsoleLogger::debug("Network readiness validation requires container configuration integration");
-        
-        results
+    /// Validate network readiness (interface, address, route, gateway) for the given containers
+    async fn validate_all_network_readiness(&self, targets: &[DiagnosticTarget]) -> Vec<NetworkReadinessCheck> {
+        futures::future::join_all(targets.iter().map(|target| async move {
+            let config = ContainerNetworkConfig {
+                ip_address: target.ip_address.clone(),
+                subnet_mask: "16".to_string(),
+                gateway_ip: self.config.bridge_ip.clone(),
+                container_id: target.container_id.clone(),
+                veth_host_name: target.veth_host_name.clone(),
+                veth_container_name: String::new(),
+                rootfs_path: None,
+            };
+            let result = self.verify_container_network_ready(&config, target.container_pid).await;
+            NetworkReadinessCheck {
+                container_id: target.container_id.clone(),
+                container_pid: target.container_pid,
+                ready: result.is_ok(),
+                error_message: result.err(),
+            }
+        })).await
     }
     
-    /// Validate container namespaces for all active containers
-    fn validate_all_container_namespaces(&self) -> Vec<NamespaceValidationResult> {
+    /// Validate container namespaces for the given containers, or every quilt process when unscoped
+    async fn validate_all_container_namespaces(&self, targets: &[DiagnosticTarget], scoped: bool) -> Vec<NamespaceValidationResult> {
         let mut results = Vec::new();
         
+        if scoped {
+            for target in targets {
+                let is_valid = self.validate_container_namespace(target.container_pid);
+                results.push(NamespaceValidationResult {
+                    container_pid: target.container_pid,
+                    namespace_valid: is_valid,
+                    error_message: if is_valid { None } else { Some("Namespace validation failed".to_string()) },
+                });
+            }
+            return results;
+        }
+        
         // Get running container PIDs from system
-        if let Ok(result) = CommandExecutor::execute_shell("pgrep -f quilt") {
+        if let Ok(result) = CommandExecutor::run_async("pgrep", ["-f", "quilt"]).await {
             for line in result.stdout.lines() {
                 if let Ok(pid) = line.trim().parse::<i32>() {
                     let is_valid = self.validate_container_namespace(pid);
@@ -393,6 +712,15 @@ impl NetworkManager {
     }
 }
 
+/// A running container included in a network health report
+#[derive(Debug, Clone)]
+pub struct DiagnosticTarget {
+    pub container_id: String,
+    pub container_pid: i32,
+    pub ip_address: String,
+    pub veth_host_name: String,
+}
+
 /// Network health monitoring report
 #[derive(Debug)]
 #[allow(dead_code)]
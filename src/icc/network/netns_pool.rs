@@ -0,0 +1,220 @@
+// Warm network namespace pool
+// Keeps pre-created named network namespaces whose veth pair is already attached to the bridge,
+// so a starting container only joins an existing namespace and gets its address assigned
+
+use crate::utils::command::{Argv, CommandExecutor};
+use crate::utils::console::ConsoleLogger;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Environment variable holding the number of warm namespaces to keep (0 or unset disables the pool)
+pub const NETNS_POOL_SIZE_ENV: &str = "QUILT_NETNS_POOL_SIZE";
+const MAX_POOL_SIZE: usize = 256;
+const NETNS_PREFIX: &str = "quilt-pool-";
+const NETNS_DIR: &str = "/var/run/netns";
+
+/// A pre-created namespace and the veth pair plumbed into it
+#[derive(Debug, Clone)]
+pub struct PooledNetns {
+    pub netns_name: String,
+    /// Path a process passes to setns(2) to join the namespace
+    pub netns_path: String,
+    /// Host-side veth, already attached to the bridge and up
+    pub veth_host_name: String,
+    /// Interface name inside the namespace
+    pub veth_container_name: String,
+}
+
+pub struct NetnsPool {
+    bridge_name: String,
+    target_size: usize,
+    ready: Mutex<VecDeque<PooledNetns>>,
+    claimed: Mutex<HashMap<String, PooledNetns>>, // container id -> slot
+    next_slot: AtomicU64,
+    refilling: AtomicBool,
+}
+
+impl NetnsPool {
+    pub fn new(bridge_name: String, target_size: usize) -> Self {
+        Self {
+            bridge_name,
+            target_size: target_size.min(MAX_POOL_SIZE),
+            ready: Mutex::new(VecDeque::new()),
+            claimed: Mutex::new(HashMap::new()),
+            next_slot: AtomicU64::new(0),
+            refilling: AtomicBool::new(false),
+        }
+    }
+
+    /// Pool size requested via the environment
+    pub fn size_from_env() -> usize {
+        std::env::var(NETNS_POOL_SIZE_ENV).ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.target_size > 0
+    }
+
+    /// Remove namespaces left behind by a previous daemon run
+    pub fn remove_stale(&self) {
+        let Ok(entries) = std::fs::read_dir(NETNS_DIR) else { return };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(NETNS_PREFIX) {
+                ConsoleLogger::debug(&format!("🧹 [NETNS-POOL] Removing stale namespace {}", name));
+                Self::delete_netns(&name);
+            }
+        }
+    }
+
+    /// Create namespaces until the pool holds its target size. Blocking; only one refill runs at a time.
+    pub fn refill(&self) {
+        if !self.enabled() || self.refilling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mut created = 0;
+        while self.ready_count() < self.target_size {
+            match self.create_slot() {
+                Ok(slot) => {
+                    self.ready.lock().unwrap().push_back(slot);
+                    created += 1;
+                }
+                Err(e) => {
+                    ConsoleLogger::warning(&format!("⚠️ [NETNS-POOL] Failed to pre-create namespace: {}", e));
+                    break;
+                }
+            }
+        }
+
+        if created > 0 {
+            ConsoleLogger::debug(&format!("🌊 [NETNS-POOL] Pre-created {} namespaces ({} ready)", created, self.ready_count()));
+        }
+        self.refilling.store(false, Ordering::Release);
+    }
+
+    pub fn ready_count(&self) -> usize {
+        self.ready.lock().unwrap().len()
+    }
+
+    /// Hand a warm namespace to a container. A container that already holds one (restart) gets
+    /// the same slot back. The container-side interface is renamed to `interface_name`.
+    pub fn claim(&self, container_id: &str, interface_name: &str) -> Option<PooledNetns> {
+        if let Some(slot) = self.claimed.lock().unwrap().get(container_id) {
+            return Some(slot.clone());
+        }
+
+        let mut slot = self.ready.lock().unwrap().pop_front()?;
+        match CommandExecutor::run("ip", ["-n", &slot.netns_name, "link", "set", &slot.veth_container_name, "name", interface_name]) {
+            Ok(result) if result.success => {}
+            Ok(result) => {
+                ConsoleLogger::warning(&format!("⚠️ [NETNS-POOL] Discarding {}: rename failed: {}", slot.netns_name, result.stderr.trim()));
+                Self::delete_netns(&slot.netns_name);
+                return None;
+            }
+            Err(e) => {
+                ConsoleLogger::warning(&format!("⚠️ [NETNS-POOL] Discarding {}: {}", slot.netns_name, e));
+                Self::delete_netns(&slot.netns_name);
+                return None;
+            }
+        }
+        slot.veth_container_name = interface_name.to_string();
+
+        ConsoleLogger::debug(&format!("🌊 [NETNS-POOL] Container {} claimed {}", container_id, slot.netns_name));
+        self.claimed.lock().unwrap().insert(container_id.to_string(), slot.clone());
+        Some(slot)
+    }
+
+    /// Slot currently held by a container
+    pub fn claimed_slot(&self, container_id: &str) -> Option<PooledNetns> {
+        self.claimed.lock().unwrap().get(container_id).cloned()
+    }
+
+    /// Destroy a container's namespace; deleting it also removes both ends of the veth pair
+    pub fn release(&self, container_id: &str) {
+        let slot = self.claimed.lock().unwrap().remove(container_id);
+        if let Some(slot) = slot {
+            Self::delete_netns(&slot.netns_name);
+            ConsoleLogger::debug(&format!("🌊 [NETNS-POOL] Released {} from container {}", slot.netns_name, container_id));
+        }
+    }
+
+    fn create_slot(&self) -> Result<PooledNetns, String> {
+        let index = self.next_slot.fetch_add(1, Ordering::Relaxed);
+        let slot = Self::slot_names(std::process::id(), index);
+
+        let add = CommandExecutor::run("ip", ["netns", "add", &slot.netns_name])?;
+        if !add.success {
+            return Err(format!("ip netns add {} failed: {}", slot.netns_name, add.stderr.trim()));
+        }
+
+        let batch = Self::slot_batch(&slot, &self.bridge_name);
+        let result = CommandExecutor::execute(&Argv::new("ip", ["-batch", "-"]).with_input(batch));
+        match result {
+            Ok(result) if result.success => {
+                let _ = CommandExecutor::run("ip", ["-n", &slot.netns_name, "link", "set", "lo", "up"]);
+                Ok(slot)
+            }
+            Ok(result) => {
+                Self::delete_netns(&slot.netns_name);
+                Err(format!("Failed to plumb {}: {}", slot.netns_name, result.stderr.trim()))
+            }
+            Err(e) => {
+                Self::delete_netns(&slot.netns_name);
+                Err(e)
+            }
+        }
+    }
+
+    /// Names for a slot; interface names stay within the 15-character kernel limit
+    fn slot_names(daemon_pid: u32, index: u64) -> PooledNetns {
+        let tag = format!("{:x}{:x}", daemon_pid & 0xffff, index & 0xffffff);
+        let netns_name = format!("{}{}", NETNS_PREFIX, tag);
+        PooledNetns {
+            netns_path: format!("{}/{}", NETNS_DIR, netns_name),
+            netns_name,
+            veth_host_name: format!("qph{}", tag),
+            veth_container_name: format!("qpc{}", tag),
+        }
+    }
+
+    /// One `ip -batch` run that creates the pair with its peer already inside the namespace
+    /// and attaches the host end to the bridge
+    fn slot_batch(slot: &PooledNetns, bridge_name: &str) -> String {
+        [
+            format!("link add {} type veth peer name {} netns {}", slot.veth_host_name, slot.veth_container_name, slot.netns_name),
+            format!("link set {} master {}", slot.veth_host_name, bridge_name),
+            format!("link set {} up", slot.veth_host_name),
+        ].join("\n")
+    }
+
+    fn delete_netns(netns_name: &str) {
+        let _ = CommandExecutor::run("ip", ["netns", "delete", netns_name]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_names_fit_interface_limit() {
+        let slot = NetnsPool::slot_names(u32::MAX, u64::MAX);
+        assert!(slot.veth_host_name.len() <= 15);
+        assert!(slot.veth_container_name.len() <= 15);
+        assert!(slot.netns_path.ends_with(&slot.netns_name));
+        assert!(slot.netns_name.starts_with(NETNS_PREFIX));
+    }
+
+    #[test]
+    fn test_disabled_pool_hands_out_nothing() {
+        let pool = NetnsPool::new("quilt0".to_string(), 0);
+        assert!(!pool.enabled());
+        pool.refill();
+        assert_eq!(pool.ready_count(), 0);
+        assert!(pool.claim("container-1", "quiltcontaine").is_none());
+    }
+}
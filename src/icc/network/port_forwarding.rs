@@ -0,0 +1,144 @@
+// Port publishing module
+// Maps host ports to container ports with iptables DNAT rules
+
+use crate::utils::console::ConsoleLogger;
+use crate::utils::validation::PortMapping;
+use std::process::Command;
+
+/// Manages the NAT rules that publish host ports to containers
+pub struct PortForwarder {
+    pub bridge_name: String,
+}
+
+impl PortForwarder {
+    pub fn new(bridge_name: String) -> Self {
+        Self { bridge_name }
+    }
+
+    /// Install DNAT rules for every mapping, rolling back on the first failure
+    pub fn publish_ports(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) -> Result<(), String> {
+        let mut applied: Vec<&PortMapping> = Vec::new();
+
+        for mapping in mappings {
+            for args in self.build_rule_args("-A", container_id, container_ip, mapping) {
+                if let Err(e) = Self::run_iptables(&args) {
+                    for done in &applied {
+                        self.remove_rules(container_id, container_ip, done);
+                    }
+                    self.remove_rules(container_id, container_ip, mapping);
+                    return Err(format!("Failed to publish {}:{}/{}: {}",
+                        mapping.host_ip, mapping.host_port, mapping.protocol, e));
+                }
+            }
+            applied.push(mapping);
+            ConsoleLogger::debug(&format!("🔌 [PORTS] Published {}:{}/{} -> {}:{}",
+                mapping.host_ip, mapping.host_port, mapping.protocol, container_ip, mapping.container_port));
+        }
+
+        if !mappings.is_empty() {
+            ConsoleLogger::success(&format!("Published {} ports for container {}", mappings.len(), container_id));
+        }
+        Ok(())
+    }
+
+    /// Remove DNAT rules for every mapping (missing rules are ignored)
+    pub fn unpublish_ports(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) {
+        for mapping in mappings {
+            self.remove_rules(container_id, container_ip, mapping);
+        }
+    }
+
+    /// Mappings with at least one of their rules missing (e.g. after a firewall reload)
+    pub fn missing_mappings(&self, container_id: &str, container_ip: &str, mappings: &[PortMapping]) -> Vec<PortMapping> {
+        mappings.iter()
+            .filter(|mapping| self.build_rule_args("-C", container_id, container_ip, mapping)
+                .iter()
+                .any(|args| Self::run_iptables(args).is_err()))
+            .cloned()
+            .collect()
+    }
+
+    fn remove_rules(&self, container_id: &str, container_ip: &str, mapping: &PortMapping) {
+        for args in self.build_rule_args("-D", container_id, container_ip, mapping) {
+            if let Err(e) = Self::run_iptables(&args) {
+                ConsoleLogger::debug(&format!("🔌 [PORTS] Rule removal skipped ({}): {}", args.join(" "), e));
+            }
+        }
+    }
+
+    /// Build iptables argument lists: PREROUTING for external traffic, OUTPUT for
+    /// host-local clients, and a FORWARD accept so bridged traffic is not dropped
+    fn build_rule_args(&self, action: &str, container_id: &str, container_ip: &str, mapping: &PortMapping) -> Vec<Vec<String>> {
+        let comment = format!("quilt:{}", container_id);
+        let destination = format!("{}:{}", container_ip, mapping.container_port);
+        let host_port = mapping.host_port.to_string();
+        let container_port = mapping.container_port.to_string();
+        let protocol = mapping.protocol.as_str();
+
+        let mut prerouting = vec!["-t", "nat", action, "PREROUTING", "-p", protocol]
+            .into_iter().map(String::from).collect::<Vec<String>>();
+        let mut output = vec!["-t", "nat", action, "OUTPUT", "-p", protocol]
+            .into_iter().map(String::from).collect::<Vec<String>>();
+        if mapping.host_ip != "0.0.0.0" {
+            for rule in [&mut prerouting, &mut output] {
+                rule.push("-d".to_string());
+                rule.push(mapping.host_ip.clone());
+            }
+        } else {
+            output.extend(["-m", "addrtype", "--dst-type", "LOCAL"].iter().map(|s| s.to_string()));
+        }
+        for rule in [&mut prerouting, &mut output] {
+            rule.extend([
+                "--dport", host_port.as_str(),
+                "-m", "comment", "--comment", comment.as_str(),
+                "-j", "DNAT", "--to-destination", destination.as_str(),
+            ].iter().map(|s| s.to_string()));
+        }
+
+        let forward: Vec<String> = [
+            action, "FORWARD", "-o", self.bridge_name.as_str(), "-p", protocol,
+            "-d", container_ip, "--dport", container_port.as_str(),
+            "-m", "comment", "--comment", comment.as_str(), "-j", "ACCEPT",
+        ].iter().map(|s| s.to_string()).collect();
+
+        vec![prerouting, output, forward]
+    }
+
+    /// Run one iptables invocation; the error is its stderr
+    pub fn run_iptables(args: &[String]) -> Result<(), String> {
+        let output = Command::new("iptables")
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to execute iptables: {}", e))?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_rule_args() {
+        let forwarder = PortForwarder::new("quilt0".to_string());
+        let mapping = PortMapping {
+            host_ip: "0.0.0.0".to_string(),
+            host_port: 8080,
+            container_port: 80,
+            protocol: "tcp".to_string(),
+        };
+
+        let rules = forwarder.build_rule_args("-A", "abc", "10.42.0.5", &mapping);
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(&rules[0][..4], &["-t", "nat", "-A", "PREROUTING"]);
+        assert!(rules[0].contains(&"10.42.0.5:80".to_string()));
+        assert!(rules[1].contains(&"LOCAL".to_string()));
+        assert!(rules[2].contains(&"quilt:abc".to_string()));
+    }
+}
@@ -1,6 +1,7 @@
 // Security module for network operations
 // Contains security-critical validation and isolation verification functions
 
+use crate::daemon::paths;
 use crate::utils::command::CommandExecutor;
 use crate::utils::console::ConsoleLogger;
 use crate::utils::filesystem::FileSystemUtils;
@@ -19,20 +20,16 @@ impl NetworkSecurity {
     /// This prevents nsenter commands from falling back to host execution
     pub fn validate_container_namespace(&self, container_pid: i32) -> bool {
         // Check if PID exists and is a valid container process
-        let pid_check = format!("test -d /proc/{} && cat /proc/{}/comm | grep -q quilt", container_pid, container_pid);
-        if let Ok(result) = CommandExecutor::execute_shell(&pid_check) {
-            if !result.success {
-                ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation failed - process not found or invalid", container_pid));
-                return false;
-            }
-        } else {
-            ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation check failed", container_pid));
+        let is_quilt_process = std::fs::read_to_string(format!("/proc/{}/comm", container_pid))
+            .map_or(false, |comm| comm.contains("quilt"));
+        if !is_quilt_process {
+            ConsoleLogger::warning(&format!("🚨 [SECURITY] Container PID {} validation failed - process not found or invalid", container_pid));
             return false;
         }
 
         // Test namespace entry without dangerous operations
-        let ns_test = format!("nsenter -t {} -m -p -- echo 'namespace_test_ok'", container_pid);
-        match CommandExecutor::execute_shell(&ns_test) {
+        let pid = container_pid.to_string();
+        match CommandExecutor::run("nsenter", ["-t", &pid, "-m", "-p", "--", "echo", "namespace_test_ok"]) {
             Ok(result) => {
                 if result.success && result.stdout.trim() == "namespace_test_ok" {
                     return true;
@@ -59,8 +56,8 @@ impl NetworkSecurity {
         }
 
         // Verify container DNS contains expected content
-        let verify_cmd = format!("nsenter -t {} -m -p -- cat /etc/resolv.conf", container_pid);
-        if let Ok(result) = CommandExecutor::execute_shell(&verify_cmd) {
+        let pid = container_pid.to_string();
+        if let Ok(result) = CommandExecutor::run("nsenter", ["-t", &pid, "-m", "-p", "--", "cat", "/etc/resolv.conf"]) {
             if result.success && result.stdout.contains(&self.bridge_ip.to_string()) {
                 return true;
             }
@@ -73,7 +70,7 @@ impl NetworkSecurity {
     /// SECURITY CRITICAL: Validate rootfs path to prevent directory traversal attacks
     pub fn validate_rootfs_path(&self, rootfs_path: &str) -> Result<(), String> {
         // Validate rootfs path is within expected container directory
-        if !rootfs_path.starts_with("/tmp/quilt-containers/") {
+        if !paths::is_container_rootfs(rootfs_path) {
             return Err(format!("🚨 [SECURITY] Unsafe rootfs path: {}", rootfs_path));
         }
 
@@ -193,13 +190,14 @@ impl NetworkSecurity {
     /// SECURITY CRITICAL: Check for resource exhaustion attacks
     pub fn check_resource_limits(&self, container_id: &str) -> Result<(), String> {
         // Check if we're creating too many interfaces
-        let interface_count_cmd = format!("ip link show | grep -c 'quilt.*@'");
-        if let Ok(result) = CommandExecutor::execute_shell(&interface_count_cmd) {
-            if let Ok(count) = result.stdout.trim().parse::<u32>() {
-                if count > 1000 {  // Reasonable limit
-                    ConsoleLogger::warning(&format!("🚨 [SECURITY] High interface count detected: {}", count));
-                    return Err(format!("🚨 [SECURITY] Too many network interfaces: {}", count));
-                }
+        if let Ok(result) = CommandExecutor::run("ip", ["link", "show"]) {
+            // Quilt veth ends are listed as `quiltXXXX@ifN`
+            let count = result.stdout.lines()
+                .filter(|line| line.find("quilt").map_or(false, |start| line[start..].contains('@')))
+                .count();
+            if count > 1000 {  // Reasonable limit
+                ConsoleLogger::warning(&format!("🚨 [SECURITY] High interface count detected: {}", count));
+                return Err(format!("🚨 [SECURITY] Too many network interfaces: {}", count));
             }
         }
 
@@ -210,8 +208,7 @@ impl NetworkSecurity {
     /// SECURITY CRITICAL: Verify that bridge operations don't affect host networking
     pub fn verify_bridge_isolation(&self, bridge_name: &str) -> Result<(), String> {
         // Check that bridge operations haven't affected default route
-        let default_route_cmd = "ip route show default";
-        match CommandExecutor::execute_shell(&default_route_cmd) {
+        match CommandExecutor::run("ip", ["route", "show", "default"]) {
             Ok(result) if result.success => {
                 if result.stdout.contains(bridge_name) {
                     return Err(format!("🚨 [SECURITY] Bridge {} appears in default route: {}", bridge_name, result.stdout.trim()));
@@ -223,8 +220,7 @@ impl NetworkSecurity {
         }
 
         // Check that bridge hasn't modified main routing table
-        let main_routes_cmd = "ip route show table main";
-        match CommandExecutor::execute_shell(&main_routes_cmd) {
+        match CommandExecutor::run("ip", ["route", "show", "table", "main"]) {
             Ok(result) if result.success => {
                 // This is just informational - we log any routes involving our bridge
                 if result.stdout.contains(bridge_name) {
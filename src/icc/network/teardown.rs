@@ -0,0 +1,177 @@
+// Container network teardown
+// Removes what network setup left on the host for one container: its veth pair, the iptables
+// rules tagged with its `quilt:<id>` comment (published ports) or a `quilt:<id>:<purpose>` one
+// (egress proxy redirection) and the bridge's neighbor entry for its address, then checks that
+// none of them are still there.
+
+use crate::utils::command::CommandExecutor;
+use crate::utils::console::ConsoleLogger;
+use std::path::Path;
+use std::process::Command;
+
+/// Tables swept for the container's tagged rules
+const RULE_TABLES: [&str; 2] = ["nat", "filter"];
+
+/// What a teardown removed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkTeardown {
+    pub interfaces_removed: Vec<String>,
+    pub rules_removed: usize,
+    pub neighbor_removed: bool,
+}
+
+/// Host interface names setup may have created for the container, plus recorded ones
+/// (e.g. a warm-pool veth); the `vethc-` end normally lives in the container's namespace and
+/// only shows up on the host when the move into it failed
+pub fn container_interface_names(container_id: &str, recorded: &[String]) -> Vec<String> {
+    let short = &container_id[..container_id.len().min(8)];
+    let mut names = vec![format!("veth-{}", short), format!("vethc-{}", short), format!("quilt{}", short)];
+    for name in recorded {
+        if !name.is_empty() && !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// Delete arguments (`-t TABLE -D CHAIN ...`) for every rule of an `iptables -t TABLE -S`
+/// listing that carries one of the container's comments
+pub fn tagged_rule_deletions(table: &str, listing: &str, container_id: &str) -> Vec<Vec<String>> {
+    let tag = format!("quilt:{}", container_id);
+    rule_deletions(table, listing, |comment| {
+        comment == tag || comment.strip_prefix(tag.as_str()).map_or(false, |rest| rest.starts_with(':'))
+    })
+}
+
+/// Delete arguments for every rule of the listing whose comment is exactly `comment`
+pub fn comment_rule_deletions(table: &str, listing: &str, comment: &str) -> Vec<Vec<String>> {
+    rule_deletions(table, listing, |tag| tag == comment)
+}
+
+fn rule_deletions(table: &str, listing: &str, matches: impl Fn(&str) -> bool) -> Vec<Vec<String>> {
+    listing.lines()
+        .filter_map(|line| {
+            let tokens: Vec<String> = line.split_whitespace().map(|token| token.trim_matches('"').to_string()).collect();
+            if tokens.first().map(String::as_str) != Some("-A") {
+                return None;
+            }
+            let tagged = tokens.windows(2).any(|pair| pair[0] == "--comment" && matches(&pair[1]));
+            tagged.then(|| {
+                let mut args = vec!["-t".to_string(), table.to_string(), "-D".to_string()];
+                args.extend(tokens.into_iter().skip(1));
+                args
+            })
+        })
+        .collect()
+}
+
+/// `iptables -t TABLE -S`; empty when iptables cannot be run
+pub fn list_rules(table: &str) -> String {
+    Command::new("iptables").args(["-t", table, "-S"]).output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default()
+}
+
+fn neighbor_present(bridge_name: &str, ip: &str) -> bool {
+    CommandExecutor::run("ip", ["neigh", "show", ip, "dev", bridge_name])
+        .map(|result| !result.stdout.trim().is_empty())
+        .unwrap_or(false)
+}
+
+/// Remove the container's interfaces, tagged rules and neighbor entry (when its address and the
+/// bridge are known), failing when any of them survived
+pub fn teardown_container_network(container_id: &str, container_ip: Option<&str>, recorded_interfaces: &[String],
+    bridge_name: Option<&str>) -> Result<NetworkTeardown, String> {
+    let mut teardown = NetworkTeardown::default();
+    let interfaces = container_interface_names(container_id, recorded_interfaces);
+
+    for interface in &interfaces {
+        if !Path::new(&format!("/sys/class/net/{}", interface)).exists() {
+            continue;
+        }
+        // Deleting one end of a pair removes the other
+        match CommandExecutor::run("ip", ["link", "delete", interface]) {
+            Ok(result) if result.success => teardown.interfaces_removed.push(interface.clone()),
+            Ok(result) => ConsoleLogger::debug(&format!("🧹 [NET-TEARDOWN] Could not delete {}: {}", interface, result.stderr.trim())),
+            Err(e) => ConsoleLogger::debug(&format!("🧹 [NET-TEARDOWN] Could not delete {}: {}", interface, e)),
+        }
+    }
+
+    for table in RULE_TABLES {
+        for args in tagged_rule_deletions(table, &list_rules(table), container_id) {
+            match Command::new("iptables").args(&args).output() {
+                Ok(output) if output.status.success() => teardown.rules_removed += 1,
+                _ => ConsoleLogger::debug(&format!("🧹 [NET-TEARDOWN] Rule removal failed: iptables {}", args.join(" "))),
+            }
+        }
+    }
+
+    let neighbor = container_ip.filter(|ip| !ip.is_empty()).zip(bridge_name);
+    if let Some((ip, bridge_name)) = neighbor {
+        if neighbor_present(bridge_name, ip) {
+            teardown.neighbor_removed = CommandExecutor::run("ip", ["neigh", "del", ip, "dev", bridge_name])
+                .map(|result| result.success)
+                .unwrap_or(false);
+        }
+    }
+
+    // Verify instead of trusting exit codes: a busy interface or a concurrent reload can leave things behind
+    let mut leftovers: Vec<String> = interfaces.iter()
+        .filter(|interface| Path::new(&format!("/sys/class/net/{}", interface)).exists())
+        .map(|interface| format!("interface {}", interface))
+        .collect();
+    for table in RULE_TABLES {
+        let remaining = tagged_rule_deletions(table, &list_rules(table), container_id).len();
+        if remaining > 0 {
+            leftovers.push(format!("{} {} rule(s)", remaining, table));
+        }
+    }
+    if let Some((ip, bridge_name)) = neighbor {
+        // Entries the kernel re-learned as FAILED/INCOMPLETE are harmless but still listed; only
+        // a reachable-looking entry counts
+        let present = CommandExecutor::run("ip", ["neigh", "show", ip, "dev", bridge_name, "nud", "reachable", "nud", "stale", "nud", "permanent"])
+            .map(|result| !result.stdout.trim().is_empty())
+            .unwrap_or(false);
+        if present {
+            leftovers.push(format!("neighbor {}", ip));
+        }
+    }
+
+    if !leftovers.is_empty() {
+        return Err(format!("Network teardown of {} incomplete: {}", container_id, leftovers.join(", ")));
+    }
+    ConsoleLogger::debug(&format!("🧹 [NET-TEARDOWN] {}: removed {} interface(s), {} rule(s){}", container_id,
+        teardown.interfaces_removed.len(), teardown.rules_removed,
+        if teardown.neighbor_removed { ", neighbor entry" } else { "" }));
+    Ok(teardown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_rule_deletions() {
+        let listing = "-P PREROUTING ACCEPT\n\
+            -A PREROUTING -p tcp -m tcp --dport 8080 -m comment --comment \"quilt:abcdef123456\" -j DNAT --to-destination 10.42.0.5:80\n\
+            -A PREROUTING -p tcp -m tcp --dport 9090 -m comment --comment quilt:abcdef999999 -j DNAT --to-destination 10.42.0.6:80\n\
+            -A OUTPUT -p udp -m comment --comment quilt:abcdef123456 -j DNAT --to-destination 10.42.0.5:53\n\
+            -A POSTROUTING -s 10.42.0.5/32 -m comment --comment quilt:abcdef123456:egress -j MASQUERADE\n";
+        let deletions = tagged_rule_deletions("nat", listing, "abcdef123456");
+        assert_eq!(deletions.len(), 3);
+        assert_eq!(deletions[0][..5], ["-t", "nat", "-D", "PREROUTING", "-p"]);
+        assert!(deletions[0].contains(&"quilt:abcdef123456".to_string()));
+        assert_eq!(deletions[1][3], "OUTPUT");
+        assert!(tagged_rule_deletions("nat", listing, "abcdef").is_empty());
+        // A purpose-tagged rule can be removed on its own
+        let egress = comment_rule_deletions("nat", listing, "quilt:abcdef123456:egress");
+        assert_eq!(egress.len(), 1);
+        assert_eq!(egress[0][3], "POSTROUTING");
+    }
+
+    #[test]
+    fn test_container_interface_names() {
+        let names = container_interface_names("0123456789ab", &["qph7".to_string(), "veth-01234567".to_string()]);
+        assert_eq!(names, vec!["veth-01234567", "vethc-01234567", "quilt01234567", "qph7"]);
+    }
+}
@@ -1,7 +1,7 @@
 // Virtual Ethernet (veth) pair management module
 // Handles creation, configuration, and attachment of veth pairs
 
-use crate::utils::command::CommandExecutor;
+use crate::utils::command::{Argv, CommandExecutor};
 use crate::utils::console::ConsoleLogger;
 use crate::icc::network::security::NetworkSecurity;
 use std::time::Duration;
@@ -39,14 +39,14 @@ impl VethManager {
         ConsoleLogger::debug(&format!("Creating veth pair: {} <-> {}", host_name, container_name));
         
         // First, clean up any existing interfaces with the same names
-        let _cleanup_host = CommandExecutor::execute_shell(&format!("ip link delete {} 2>/dev/null", host_name));
-        let _cleanup_container = CommandExecutor::execute_shell(&format!("ip link delete {} 2>/dev/null", container_name));
+        let _cleanup_host = CommandExecutor::run("ip", ["link", "delete", host_name]);
+        let _cleanup_container = CommandExecutor::run("ip", ["link", "delete", container_name]);
         
         // Create the veth pair
-        let create_cmd = format!("ip link add {} type veth peer name {}", host_name, container_name);
+        let create_cmd = Argv::new("ip", ["link", "add", host_name, "type", "veth", "peer", "name", container_name]);
         ConsoleLogger::debug(&format!("Executing: {}", create_cmd));
         
-        let result = CommandExecutor::execute_shell(&create_cmd)?;
+        let result = CommandExecutor::execute(&create_cmd)?;
         if !result.success {
             return Err(format!("Failed to create veth pair: {}", result.stderr));
         }
@@ -60,8 +60,8 @@ impl VethManager {
 
     pub fn verify_veth_pair_created(&self, host_name: &str, container_name: &str) -> Result<(), String> {
         for attempt in 1..=10 {  // Fast polling instead of single 100ms delay
-            let verify_host = CommandExecutor::execute_shell(&format!("ip link show {}", host_name));
-            let verify_container = CommandExecutor::execute_shell(&format!("ip link show {}", container_name));
+            let verify_host = CommandExecutor::run("ip", ["link", "show", host_name]);
+            let verify_container = CommandExecutor::run("ip", ["link", "show", container_name]);
             
             if verify_host.map_or(false, |r| r.success) && verify_container.map_or(false, |r| r.success) {
                 ConsoleLogger::debug(&format!("Veth pair verified: {} <-> {}", host_name, container_name));
@@ -78,16 +78,16 @@ impl VethManager {
         ConsoleLogger::debug(&format!("Moving veth interface {} to container PID {}", veth_name, container_pid));
         
         // First verify the veth interface exists
-        let verify_result = CommandExecutor::execute_shell(&format!("ip link show {}", veth_name))?;
+        let verify_result = CommandExecutor::run("ip", ["link", "show", veth_name])?;
         if !verify_result.success {
             return Err(format!("Veth interface {} does not exist", veth_name));
         }
         
         // Move to container namespace
-        let move_cmd = format!("ip link set {} netns {}", veth_name, container_pid);
+        let move_cmd = Argv::new("ip", ["link", "set", veth_name, "netns", &container_pid.to_string()]);
         ConsoleLogger::debug(&format!("Executing: {}", move_cmd));
         
-        let result = CommandExecutor::execute_shell(&move_cmd)?;
+        let result = CommandExecutor::execute(&move_cmd)?;
         if !result.success {
             return Err(format!("Failed to move veth to container: {}", result.stderr));
         }
@@ -97,52 +97,65 @@ impl VethManager {
     }
     
     pub fn configure_container_interface(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
-        let ns_exec = format!("nsenter -t {} -n", container_pid);
-        
         // Use consistent interface naming to avoid eth0 conflicts
         let interface_name = format!("quilt{}", &config.container_id[..8]);
         
         ConsoleLogger::debug(&format!("Configuring container interface as: {}", interface_name));
         
-        // Step 1: Rename the interface inside the container
-        let rename_cmd = format!("{} ip link set {} name {}", ns_exec, config.veth_container_name, interface_name);
-        ConsoleLogger::debug(&format!("Renaming interface: {}", rename_cmd));
-        let rename_result = CommandExecutor::execute_shell(&rename_cmd)?;
-        if !rename_result.success {
-            return Err(format!("Failed to rename container interface: {}", rename_result.stderr));
-        }
-        
-        // Step 2: Assign IP address
+        // Rename, address and link-up are applied as one `ip -batch` run inside the namespace: a
+        // single nsenter instead of three. `replace` keeps the batch idempotent on retries.
         let ip_with_mask = format!("{}/{}", config.ip_address, config.subnet_mask);
-        let ip_cmd = format!("{} ip addr add {} dev {}", ns_exec, ip_with_mask, interface_name);
-        ConsoleLogger::debug(&format!("Assigning IP: {}", ip_cmd));
-        let ip_result = CommandExecutor::execute_shell(&ip_cmd)?;
-        if !ip_result.success && !ip_result.stderr.contains("File exists") {
-            return Err(format!("Failed to assign IP to interface: {}", ip_result.stderr));
-        }
-        
-        // Step 3: Bring the interface up
-        let up_cmd = format!("{} ip link set {} up", ns_exec, interface_name);
-        ConsoleLogger::debug(&format!("Bringing interface up: {}", up_cmd));
-        let up_result = CommandExecutor::execute_shell(&up_cmd)?;
-        if !up_result.success {
-            return Err(format!("Failed to bring interface up: {}", up_result.stderr));
-        }
-        
-        // Step 4: Add default route through the gateway
-        let route_cmd = format!("{} ip route add default via {}", ns_exec, config.gateway_ip);
-        ConsoleLogger::debug(&format!("Adding default route: {}", route_cmd));
-        let route_result = CommandExecutor::execute_shell(&route_cmd);
-        if let Err(e) = route_result {
-            if !e.contains("File exists") {
-                ConsoleLogger::warning(&format!("Failed to add default route: {}", e));
-            }
+        let batch = Self::container_interface_batch(Some(&config.veth_container_name), &interface_name, &ip_with_mask);
+        ConsoleLogger::debug(&format!("Configuring interface in one batch: {}", batch.replace('\n', "; ")));
+        let pid = container_pid.to_string();
+        let batch_cmd = Argv::new("nsenter", ["-t", pid.as_str(), "-n", "ip", "-batch", "-"]).with_input(batch);
+        let batch_result = CommandExecutor::execute(&batch_cmd)?;
+        if !batch_result.success {
+            return Err(format!("Failed to configure container interface {}: {}", interface_name, batch_result.stderr.trim()));
         }
+        Self::add_default_route(&Argv::new("nsenter", ["-t", pid.as_str(), "-n", "ip", "route", "replace", "default", "via", config.gateway_ip.as_str()]));
         
         ConsoleLogger::success(&format!("Container interface {} configured with IP {}", interface_name, config.ip_address));
         Ok(())
     }
 
+    /// Address a pre-plumbed interface inside a named (pooled) namespace; the veth is already
+    /// in place and attached, so only the per-container address and route remain
+    pub fn configure_pooled_interface(&self, config: &ContainerNetworkConfig, netns_name: &str) -> Result<(), String> {
+        let ip_with_mask = format!("{}/{}", config.ip_address, config.subnet_mask);
+        let batch = Self::container_interface_batch(None, &config.veth_container_name, &ip_with_mask);
+        let result = CommandExecutor::execute(&Argv::new("ip", ["-n", netns_name, "-batch", "-"]).with_input(batch))?;
+        if !result.success {
+            return Err(format!("Failed to configure pooled interface {} in {}: {}",
+                config.veth_container_name, netns_name, result.stderr.trim()));
+        }
+        Self::add_default_route(&Argv::new("ip", ["-n", netns_name, "route", "replace", "default", "via", config.gateway_ip.as_str()]));
+        ConsoleLogger::success(&format!("Pooled interface {} configured with IP {}", config.veth_container_name, config.ip_address));
+        Ok(())
+    }
+
+    /// Netlink operations that bring up the container-side interface, one per line for `ip -batch`
+    /// on stdin
+    fn container_interface_batch(rename_from: Option<&str>, interface_name: &str, ip_with_mask: &str) -> String {
+        let mut ops = Vec::new();
+        if let Some(veth_name) = rename_from {
+            ops.push(format!("link set {} name {}", veth_name, interface_name));
+        }
+        ops.push(format!("addr replace {} dev {}", ip_with_mask, interface_name));
+        ops.push(format!("link set {} up", interface_name));
+        ops.join("\n")
+    }
+
+    /// The default route is kept out of the batch: a container that cannot get it still has its
+    /// address and reaches the bridge subnet, so a failure is only logged
+    fn add_default_route(route_cmd: &Argv) {
+        match CommandExecutor::execute(route_cmd) {
+            Ok(result) if result.success => {}
+            Ok(result) => ConsoleLogger::warning(&format!("Failed to add default route: {}", result.stderr.trim())),
+            Err(e) => ConsoleLogger::warning(&format!("Failed to add default route: {}", e)),
+        }
+    }
+    
     pub fn attach_veth_to_bridge_with_retry(&self, veth_name: &str) -> Result<(), String> {
         ConsoleLogger::debug(&format!("🔗 [BRIDGE-ATTACH] Attaching {} to bridge {} with enhanced reliability", veth_name, self.bridge_name));
         
@@ -154,8 +167,7 @@ impl VethManager {
             ConsoleLogger::debug(&format!("🔗 [BRIDGE-ATTACH] Attempt {} of {}", attempt, MAX_ATTEMPTS));
             
             // Perform the attachment
-            let attach_cmd = format!("ip link set {} master {}", veth_name, self.bridge_name);
-            match CommandExecutor::execute_shell(&attach_cmd) {
+            match CommandExecutor::run("ip", ["link", "set", veth_name, "master", &self.bridge_name]) {
                 Ok(result) if result.success => {
                     // Verify the attachment worked
                     if self.verify_bridge_attachment_comprehensive(veth_name).is_ok() {
@@ -187,7 +199,7 @@ impl VethManager {
     fn verify_veth_exists(&self, veth_name: &str) -> Result<(), String> {
         ConsoleLogger::debug(&format!("🔍 [VETH-CHECK] Verifying veth {} exists", veth_name));
         
-        match CommandExecutor::execute_shell(&format!("ip link show {}", veth_name)) {
+        match CommandExecutor::run("ip", ["link", "show", veth_name]) {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("✅ [VETH-CHECK] Veth {} exists and is visible", veth_name));
                 Ok(())
@@ -205,22 +217,16 @@ impl VethManager {
         ConsoleLogger::debug(&format!("🔍 [ATTACH-VERIFY] Comprehensive attachment verification for {}", veth_name));
         
         // Method 1: Check master relationship
-        let master_check = format!("ip link show {} | grep 'master {}'", veth_name, self.bridge_name);
-        let master_ok = match CommandExecutor::execute_shell(&master_check) {
-            Ok(result) => result.success,
-            Err(_) => false,
-        };
+        let master_ok = self.shows_bridge_as_master(veth_name);
         
         // Method 2: Check bridge shows the interface
-        let bridge_check = format!("ip link show master {}", self.bridge_name);
-        let bridge_ok = match CommandExecutor::execute_shell(&bridge_check) {
+        let bridge_ok = match CommandExecutor::run("ip", ["link", "show", "master", &self.bridge_name]) {
             Ok(result) => result.success && result.stdout.contains(veth_name),
             Err(_) => false,
         };
         
         // Method 3: Check bridge command (if available)
-        let brctl_check = format!("bridge link show dev {}", veth_name);
-        let brctl_ok = match CommandExecutor::execute_shell(&brctl_check) {
+        let brctl_ok = match CommandExecutor::run("bridge", ["link", "show", "dev", veth_name]) {
             Ok(result) => result.success && result.stdout.contains(&self.bridge_name),
             Err(_) => false,  // bridge command may not be available
         };
@@ -243,8 +249,7 @@ impl VethManager {
         thread::sleep(Duration::from_millis(100));
         
         // Bring the host-side veth up
-        let up_cmd = format!("ip link set {} up", veth_name);
-        match CommandExecutor::execute_shell(&up_cmd) {
+        match CommandExecutor::run("ip", ["link", "set", veth_name, "up"]) {
             Ok(result) if result.success => {
                 ConsoleLogger::debug(&format!("✅ [POST-ATTACH] {} brought up successfully", veth_name));
                 Ok(())
@@ -258,17 +263,17 @@ impl VethManager {
         ConsoleLogger::debug(&format!("🔍 [ATTACH-DIAG] Diagnosing attachment failure for {} (attempt {})", veth_name, attempt));
         
         // Check veth state
-        if let Ok(result) = CommandExecutor::execute_shell(&format!("ip link show {}", veth_name)) {
+        if let Ok(result) = CommandExecutor::run("ip", ["link", "show", veth_name]) {
             ConsoleLogger::debug(&format!("ℹ️ [ATTACH-DIAG] Veth state: {}", result.stdout.trim()));
         }
         
         // Check bridge state
-        if let Ok(result) = CommandExecutor::execute_shell(&format!("ip link show {}", self.bridge_name)) {
+        if let Ok(result) = CommandExecutor::run("ip", ["link", "show", &self.bridge_name]) {
             ConsoleLogger::debug(&format!("ℹ️ [ATTACH-DIAG] Bridge state: {}", result.stdout.trim()));
         }
         
         // Check if bridge is accepting attachments
-        if let Ok(result) = CommandExecutor::execute_shell(&format!("bridge link show master {}", self.bridge_name)) {
+        if let Ok(result) = CommandExecutor::run("bridge", ["link", "show", "master", &self.bridge_name]) {
             ConsoleLogger::debug(&format!("ℹ️ [ATTACH-DIAG] Bridge interfaces: {}", result.stdout.trim()));
         }
     }
@@ -278,20 +283,15 @@ impl VethManager {
             veth_name, self.bridge_name));
         
         // Check 1: Verify veth shows bridge as master
-        let master_check = format!("ip link show {} | grep 'master {}'", veth_name, self.bridge_name);
-        match CommandExecutor::execute_shell(&master_check) {
-            Ok(result) if result.success => {
-                ConsoleLogger::debug(&format!("✅ [BRIDGE-VERIFY] {} correctly shows {} as master", veth_name, self.bridge_name));
-            }
-            _ => {
-                return Err(format!("Veth {} does not show {} as master", veth_name, self.bridge_name));
-            }
+        if self.shows_bridge_as_master(veth_name) {
+            ConsoleLogger::debug(&format!("✅ [BRIDGE-VERIFY] {} correctly shows {} as master", veth_name, self.bridge_name));
+        } else {
+            return Err(format!("Veth {} does not show {} as master", veth_name, self.bridge_name));
         }
         
         // Check 2: Verify bridge lists the veth
-        let bridge_check = format!("bridge link show master {} | grep {}", self.bridge_name, veth_name);
-        match CommandExecutor::execute_shell(&bridge_check) {
-            Ok(result) if result.success => {
+        match CommandExecutor::run("bridge", ["link", "show", "master", &self.bridge_name]) {
+            Ok(result) if result.success && result.stdout.contains(veth_name) => {
                 ConsoleLogger::debug(&format!("✅ [BRIDGE-VERIFY] Bridge {} correctly lists {}", self.bridge_name, veth_name));
             }
             _ => {
@@ -305,28 +305,39 @@ impl VethManager {
 
     pub fn verify_bridge_attachment_fast(&self, veth_name: &str) -> bool {
         // Single fast check: Verify veth shows bridge as master
-        let master_check = format!("ip link show {} | grep -q 'master {}'", veth_name, self.bridge_name);
-        match CommandExecutor::execute_shell(&master_check) {
-            Ok(result) if result.success => {
-                ConsoleLogger::debug(&format!("✅ [FAST-VERIFY] {} attached to bridge {}", veth_name, self.bridge_name));
-                true
-            }
-            _ => {
-                ConsoleLogger::debug(&format!("❌ [FAST-VERIFY] {} not attached to bridge {}", veth_name, self.bridge_name));
-                false
-            }
+        if self.shows_bridge_as_master(veth_name) {
+            ConsoleLogger::debug(&format!("✅ [FAST-VERIFY] {} attached to bridge {}", veth_name, self.bridge_name));
+            true
+        } else {
+            ConsoleLogger::debug(&format!("❌ [FAST-VERIFY] {} not attached to bridge {}", veth_name, self.bridge_name));
+            false
         }
     }
 
+    /// Whether `ip link show` lists this manager's bridge as the interface's master
+    fn shows_bridge_as_master(&self, veth_name: &str) -> bool {
+        let master = format!("master {} ", self.bridge_name);
+        CommandExecutor::run("ip", ["link", "show", veth_name])
+            .map_or(false, |result| result.success && result.stdout.contains(&master))
+    }
+
+    /// The `link/ether` address in `ip link show` output
+    fn ether_address(link_show: &str) -> String {
+        link_show.lines()
+            .filter_map(|line| line.trim().strip_prefix("link/ether "))
+            .filter_map(|rest| rest.split_whitespace().next())
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
     pub fn get_interface_mac_address(&self, interface_name: &str) -> Result<String, String> {
         ConsoleLogger::debug(&format!("🔍 [MAC-LOOKUP] Getting MAC address for interface: {}", interface_name));
         
         // Use ip link show to get interface details including MAC address
-        let cmd = format!("ip link show {} | grep 'link/ether' | awk '{{print $2}}'", interface_name);
-        
-        match CommandExecutor::execute_shell(&cmd) {
+        match CommandExecutor::run("ip", ["link", "show", interface_name]) {
             Ok(result) if result.success => {
-                let mac = result.stdout.trim().to_string();
+                let mac = Self::ether_address(&result.stdout);
                 if mac.len() == 17 && mac.matches(':').count() == 5 {  // Basic MAC format validation
                     ConsoleLogger::debug(&format!("✅ [MAC-LOOKUP] Interface {} MAC address: {}", interface_name, mac));
                     Ok(mac)
@@ -348,12 +359,9 @@ impl VethManager {
             interface_name, container_pid));
         
         // Use nsenter to get MAC address from within container namespace
-        let cmd = format!("nsenter -t {} -n ip link show {} | grep 'link/ether' | awk '{{print $2}}'", 
-            container_pid, interface_name);
-        
-        match CommandExecutor::execute_shell(&cmd) {
+        match CommandExecutor::run("nsenter", ["-t", &container_pid.to_string(), "-n", "ip", "link", "show", interface_name]) {
             Ok(result) if result.success => {
-                let mac = result.stdout.trim().to_string();
+                let mac = Self::ether_address(&result.stdout);
                 if mac.len() == 17 && mac.matches(':').count() == 5 {  // Basic MAC format validation
                     ConsoleLogger::debug(&format!("✅ [MAC-LOOKUP-NS] Container {} interface {} MAC: {}", 
                         container_pid, interface_name, mac));
@@ -0,0 +1,11 @@
+// quilt-core: the container engine as a library
+// Embed `Engine` to create and manage containers in-process; the quilt daemon wraps the same
+// engine behind its gRPC API
+
+pub mod daemon;
+pub mod utils;
+pub mod icc;
+pub mod sync;
+pub mod engine;
+
+pub use engine::{Engine, EngineConfig};
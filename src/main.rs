@@ -1,18 +1,20 @@
 // Warnings denied at workspace level via Cargo.toml
 
-mod daemon;
-mod utils;
-mod icc;
-mod sync;
 mod grpc;
 
+use quilt_core::{daemon, utils, icc, sync, engine};
+use engine::{Engine, EngineConfig, LifecycleHook, PrestartHook, ReadinessGate, ReadinessGates, PriorityClass, run_container_startup, DEFAULT_STARTUP_TIMEOUT};
+
 use utils::console::ConsoleLogger;
 use utils::filesystem::FileSystemUtils;
 use utils::command::CommandExecutor;
 use utils::validation::InputValidator;
+use grpc::name_policy::NamePolicy;
 use sync::{SyncEngine, MountType, ContainerState};
-use grpc::start_container_process;
-use icc::network::security::NetworkSecurity;
+use sync::operations::global_operations;
+use grpc::deadline::{request_deadline, bounded_by_deadline};
+use grpc::actor::{request_actor, request_token};
+use grpc::errors::{error_code, sync_error_code, error_status, engine_status};
 
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -20,9 +22,15 @@ use std::time::Duration;
 use tonic::{transport::Server, Request, Response, Status};
 use uuid::Uuid;
 
+/// Address of the gRPC API; all interfaces, so containers can reach it too
+const GRPC_LISTEN_ADDRESS: &str = "0.0.0.0:50051";
+
 // Include the generated protobuf code
 pub mod quilt {
     tonic::include_proto!("quilt");
+
+    /// Encoded descriptors for server reflection
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("quilt_descriptor");
 }
 
 use quilt::quilt_service_server::{QuiltService, QuiltServiceServer};
@@ -36,86 +44,466 @@ use quilt::{
     StartContainerRequest, StartContainerResponse,
     KillContainerRequest, KillContainerResponse,
     GetContainerByNameRequest, GetContainerByNameResponse,
+    GrantDeviceAccessRequest, GrantDeviceAccessResponse,
+    RevokeDeviceAccessRequest, RevokeDeviceAccessResponse,
     CreateVolumeRequest, CreateVolumeResponse,
     RemoveVolumeRequest, RemoveVolumeResponse,
     ListVolumesRequest, ListVolumesResponse,
     InspectVolumeRequest, InspectVolumeResponse,
+    AttachMountRequest, AttachMountResponse,
     GetHealthRequest, GetHealthResponse,
     GetMetricsRequest, GetMetricsResponse,
     GetSystemInfoRequest, GetSystemInfoResponse,
     StreamEventsRequest, ContainerEvent as ProtoContainerEvent,
-    ContainerStatus, HealthCheck, ContainerMetric, SystemMetrics as ProtoSystemMetrics,
+    ContainerStatus, ErrorCode, HealthCheck, ContainerMetric, SystemMetrics as ProtoSystemMetrics,
 };
 
 #[derive(Clone)]
 pub struct QuiltServiceImpl {
+    engine: Arc<Engine>,
     sync_engine: Arc<SyncEngine>,
     network_manager: Arc<icc::network::NetworkManager>,
     runtime: Arc<daemon::runtime::ContainerRuntime>,
-    #[allow(dead_code)]  // Available for future inter-container messaging features
     message_broker: Arc<icc::messaging::MessageBroker>,
     start_time: std::time::SystemTime,
 }
 
 impl QuiltServiceImpl {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // Initialize ICC network manager first
-        let mut network_manager = icc::network::NetworkManager::new("quilt0", "10.42.0.0/16")
-            .map_err(|e| format!("Failed to create network manager: {}", e))?;
-        
-        // CRITICAL: Ensure bridge is ready before any other network operations
-        network_manager.ensure_bridge_ready()
-            .map_err(|e| format!("Failed to setup network bridge: {}", e))?;
-            
-        // SECURITY: Verify bridge isolation after setup
-        let security = NetworkSecurity::new("192.168.100.1".to_string());
-        if let Err(e) = security.verify_bridge_isolation("quilt0") {
-            ConsoleLogger::warning(&format!("Bridge isolation verification failed: {}", e));
-        }
-        
-        ConsoleLogger::success("Bridge network initialized - containers can now communicate");
-        
-        // Start DNS server (non-critical - bridge networking works without DNS)
-        match network_manager.start_dns_server().await {
-            Ok(()) => {
-                ConsoleLogger::success("DNS server started - containers can resolve names");
-            }
-            Err(e) => {
-                ConsoleLogger::warning(&format!("DNS server startup failed (non-critical): {}", e));
-                ConsoleLogger::info("Bridge networking is fully functional - containers can communicate via IP addresses");
-            }
-        }
-        
-        ConsoleLogger::success("Network manager initialized with bridge networking");
-        
-        // Initialize sync engine with ICC network manager integration
-        let network_manager_arc = Arc::new(network_manager);
-        let sync_engine = Arc::new(SyncEngine::new_with_network_config(
-            "quilt.db", 
-            Some("10.42.0.0/16".to_string()),
-            Some(network_manager_arc.clone())
-        ).await?);
-        
-        // Start background services for monitoring and cleanup with ICC integration
-        sync_engine.start_background_services().await?;
-        
-        ConsoleLogger::success("✅ Sync engine initialized with ICC network manager integration - enhanced cleanup enabled");
+        // Bridge, DNS, sync engine and runtime come up together in the engine
+        let engine = Engine::new(EngineConfig::default()).await?;
         
         // Initialize MessageBroker for inter-container communication
         let message_broker = icc::messaging::MessageBroker::new();
         message_broker.start();
         
-        // Initialize container runtime
-        let runtime = daemon::runtime::ContainerRuntime::new();
-        
         Ok(Self {
-            sync_engine,
-            network_manager: network_manager_arc,
-            runtime: Arc::new(runtime),
+            sync_engine: engine.sync_engine().clone(),
+            network_manager: engine.network_manager().clone(),
+            runtime: engine.runtime().clone(),
+            engine: Arc::new(engine),
             message_broker: Arc::new(message_broker),
             start_time: std::time::SystemTime::now(),
         })
     }
+
+    /// QuiltService methods, read from the compiled descriptor so the list cannot drift from the proto
+    fn implemented_rpcs() -> Vec<String> {
+        use prost::Message;
+
+        prost_types::FileDescriptorSet::decode(quilt::FILE_DESCRIPTOR_SET)
+            .map(|set| set.file.iter()
+                .flat_map(|file| file.service.iter())
+                .filter(|service| service.name() == "QuiltService")
+                .flat_map(|service| service.method.iter().map(|method| method.name().to_string()))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Optional features and whether this daemon can use them right now
+    fn api_features() -> HashMap<String, bool> {
+        let mut features = HashMap::new();
+        features.insert("exec_stream".to_string(), true);
+        features.insert("exec_timeout".to_string(), true);
+        features.insert("port_publishing".to_string(), true);
+        features.insert("fuse_mounts".to_string(), true);
+        features.insert("device_access".to_string(), true);
+        features.insert("traffic_capture".to_string(), true);
+        features.insert("connectivity_test".to_string(), true);
+        features.insert("virtual_services".to_string(), true);
+        features.insert("replica_scaling".to_string(), true);
+        features.insert("container_replace".to_string(), true);
+        features.insert("port_inventory".to_string(), true);
+        features.insert("exec_accounting".to_string(), true);
+        features.insert("rate_limits".to_string(), true);
+        features.insert("image_scanning".to_string(), true);
+        features.insert("time_offset".to_string(), true);
+        features.insert("time_namespace".to_string(), true);
+        features.insert("cgroup_namespace".to_string(), true);
+        features.insert("user_namespace".to_string(), true);
+        features.insert("idmapped_mounts".to_string(), true);
+        features.insert("proc_hardening".to_string(), true);
+        features.insert("privileged_containers".to_string(), true);
+        features.insert("memory_swap_controls".to_string(), true);
+        features.insert("cpu_burst".to_string(), true);
+        features.insert("metrics_freshness".to_string(), true);
+        features.insert("metrics_collector".to_string(), daemon::metrics::MetricsCollectionPolicy::from_env().is_some());
+        features.insert("health_service".to_string(), true);
+        features.insert("reflection".to_string(), true);
+        features.insert("event_label_filters".to_string(), true);
+        features.insert("container_log_level".to_string(), true);
+        features.insert("diagnostics".to_string(), true);
+        features.insert("host_checks".to_string(), true);
+        features.insert("immutable_containers".to_string(), true);
+        features.insert("container_clone".to_string(), true);
+        features.insert("orphan_reconcile".to_string(), true);
+        features.insert("network_policy".to_string(), true);
+        features.insert("syscall_trace".to_string(), true);
+        features.insert("log_search".to_string(), true);
+        features.insert("list_containers".to_string(), true);
+        features.insert("lifecycle_hooks".to_string(), true);
+        features.insert("prestart_hooks".to_string(), true);
+        features.insert("plugins".to_string(), true);
+        features.insert("priority_classes".to_string(), true);
+        features.insert("readiness_gates".to_string(), true);
+        features.insert("start_wait_ready".to_string(), true);
+        features.insert("operation_locks".to_string(), true);
+        features.insert("error_codes".to_string(), true);
+        features.insert("operation_progress".to_string(), true);
+        features.insert("setup_steps".to_string(), true);
+        features.insert("dns_wildcard".to_string(), true);
+        features.insert("dns_ptr".to_string(), true);
+        features.insert("registry_pull".to_string(), true);
+        features.insert("dns_views".to_string(), true);
+        features.insert("dns_domain".to_string(), true);
+        features.insert("image_store".to_string(), true);
+        features.insert("image_build".to_string(), true);
+        features.insert("image_management".to_string(), true);
+        features.insert("image_import_export".to_string(), true);
+        features.insert("network_connect".to_string(), true);
+        features.insert("traffic_mirroring".to_string(), true);
+        features.insert("host_firewall_integration".to_string(), true);
+        features.insert("egress_proxy".to_string(), true);
+        features.insert("container_timezone".to_string(), true);
+        features.insert("multi_arch_images".to_string(), true);
+        features.insert("cgroup_v2".to_string(), std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists());
+        features.insert("mdns".to_string(), icc::mdns::MdnsResponder::enabled_from_env());
+        features.insert("ebpf_network_accounting".to_string(), daemon::net_accounting::enabled_from_env());
+        features.insert("memory_pressure_responder".to_string(), daemon::memory_pressure::MemoryPressurePolicy::from_env().is_some());
+        features.insert("netns_pool".to_string(), icc::network::netns_pool::NetnsPool::size_from_env() > 0);
+        features.insert("rootfs_pool".to_string(), daemon::rootfs_pool::RootfsPool::global().enabled());
+        features.insert("overlayfs".to_string(), daemon::overlay::enabled());
+        features.insert("checkpointing".to_string(), false);
+        features
+    }
+
+    /// Resolve a running container and grant or revoke a device rule on its devices cgroup
+    async fn apply_device_rule(&self, container_id: &str, container_name: &str, rule: &str, grant: bool, actor: &str) -> Result<(), String> {
+        let container_id = if !container_name.is_empty() {
+            self.sync_engine.get_container_by_name(container_name).await
+                .map_err(|_| format!("Container with name '{}' not found", container_name))?
+        } else {
+            container_id.to_string()
+        };
+
+        let device_rule = daemon::cgroup::DeviceRule::parse(rule)?;
+
+        let status = self.sync_engine.get_container_status(&container_id).await
+            .map_err(|e| format!("Container not found: {}", e))?;
+        let pid = match (status.state, status.pid) {
+            (ContainerState::Running, Some(pid)) => utils::process::ProcessUtils::i32_to_pid(pid as i32),
+            (state, _) => return Err(format!("Container is not running (state: {:?})", state)),
+        };
+
+        let cgroup_manager = daemon::cgroup::CgroupManager::new(container_id.clone());
+        if grant {
+            cgroup_manager.grant_device(pid, &device_rule)?;
+        } else {
+            cgroup_manager.revoke_device(pid, &device_rule)?;
+        }
+
+        let action = if grant { "granted" } else { "revoked" };
+        ConsoleLogger::info(&format!("🔌 [DEVICE] Access {} for {}: {}", action, container_id, device_rule.to_rule_string()));
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info",
+            &format!("Device access {}: {}", action, device_rule.to_rule_string()), actor).await;
+
+        Ok(())
+    }
+
+    /// Resolve the container an ICC service call acts on behalf of
+    async fn resolve_icc_container(&self, container_id: &str, container_name: &str) -> Result<String, Status> {
+        if !container_name.is_empty() {
+            return self.sync_engine.get_container_by_name(container_name).await
+                .map_err(|_| Status::not_found(format!("Container with name '{}' not found", container_name)));
+        }
+        if container_id.is_empty() {
+            return Err(Status::invalid_argument("container_id or container_name is required"));
+        }
+        self.sync_engine.get_container_status(container_id).await
+            .map(|status| status.id)
+            .map_err(|_| Status::not_found(format!("Container {} not found", container_id)))
+    }
+
+    fn grpc_container_status(state: &ContainerState) -> ContainerStatus {
+        match state {
+            ContainerState::Created => ContainerStatus::Pending,
+            ContainerState::Starting => ContainerStatus::Pending,
+            ContainerState::Running => ContainerStatus::Running,
+            ContainerState::Exited => ContainerStatus::Exited,
+            ContainerState::Error => ContainerStatus::Failed,
+            ContainerState::Stopping => ContainerStatus::Stopping,
+            ContainerState::Removing => ContainerStatus::Removing,
+            ContainerState::Restarting => ContainerStatus::Restarting,
+            ContainerState::Paused => ContainerStatus::Paused,
+        }
+    }
+
+    fn operation_progress(progress: sync::operations::OperationProgress) -> quilt::OperationProgress {
+        let error_message = progress.error.unwrap_or_default();
+        quilt::OperationProgress {
+            operation_id: progress.operation_id,
+            kind: progress.kind,
+            container_id: progress.container_id,
+            phase: progress.phase,
+            percent: progress.percent,
+            done: progress.done,
+            success: progress.done && error_message.is_empty(),
+            error_code: error_code(&error_message),
+            error_message,
+            started_at: progress.started_at,
+            updated_at: progress.updated_at,
+        }
+    }
+
+    fn plugin_info(plugin: sync::plugins::PluginRecord) -> quilt::PluginInfo {
+        quilt::PluginInfo {
+            active: engine::plugins::is_active(&plugin.name),
+            name: plugin.name,
+            endpoint: plugin.endpoint,
+            capabilities: plugin.capabilities,
+            created_at: plugin.created_at,
+        }
+    }
+
+    fn image_scan_info(scan: engine::ImageScanRecord, include_findings: bool) -> quilt::ImageScan {
+        let counts = daemon::image_scan::severity_counts(&scan.findings);
+        let count = |severity: engine::Severity| counts.iter()
+            .find(|(counted, _)| *counted == severity)
+            .map_or(0, |(_, count)| *count as u32);
+        let blocked_reason = engine::image_scan::configured_scanner()
+            .and_then(|scanner| scanner.check(&scan.image_path, &scan.findings).err())
+            .unwrap_or_default();
+        let mut findings = if include_findings { scan.findings.clone() } else { Vec::new() };
+        findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+
+        quilt::ImageScan {
+            critical: count(engine::Severity::Critical),
+            high: count(engine::Severity::High),
+            medium: count(engine::Severity::Medium),
+            low: count(engine::Severity::Low),
+            unknown: count(engine::Severity::Unknown),
+            blocked: !blocked_reason.is_empty(),
+            blocked_reason,
+            findings: findings.into_iter().map(|finding| quilt::VulnerabilityFinding {
+                id: finding.id,
+                package: finding.package,
+                installed_version: finding.installed_version,
+                fixed_version: finding.fixed_version,
+                severity: finding.severity.as_str().to_string(),
+            }).collect(),
+            image_path: scan.image_path,
+            scanner: scan.scanner,
+            scanned_at: scan.scanned_at,
+        }
+    }
+
+    fn process_monitor_info(monitor: sync::monitor::ProcessMonitor) -> quilt::ProcessMonitor {
+        quilt::ProcessMonitor {
+            container_id: monitor.container_id,
+            pid: monitor.pid,
+            status: monitor.status.to_string(),
+            started_at: monitor.monitor_started_at as u64,
+            last_check: monitor.last_check_at.unwrap_or(0) as u64,
+            check_count: monitor.check_count as i32,
+            error_message: monitor.last_error.unwrap_or_default(),
+            consecutive_failures: monitor.consecutive_failures as i32,
+        }
+    }
+
+    fn container_metric(metrics: &daemon::metrics::ContainerMetrics, from_cache: bool) -> ContainerMetric {
+        ContainerMetric {
+            container_id: metrics.container_id.clone(),
+            timestamp: metrics.timestamp,
+            cpu_usage_usec: metrics.cpu.usage_usec,
+            cpu_user_usec: metrics.cpu.user_usec,
+            cpu_system_usec: metrics.cpu.system_usec,
+            cpu_throttled_usec: metrics.cpu.throttled_usec,
+            memory_current_bytes: metrics.memory.current_bytes,
+            memory_peak_bytes: metrics.memory.peak_bytes,
+            memory_limit_bytes: metrics.memory.limit_bytes,
+            memory_cache_bytes: metrics.memory.cache_bytes,
+            memory_rss_bytes: metrics.memory.rss_bytes,
+            network_rx_bytes: metrics.network.rx_bytes,
+            network_tx_bytes: metrics.network.tx_bytes,
+            network_rx_packets: metrics.network.rx_packets,
+            network_tx_packets: metrics.network.tx_packets,
+            disk_read_bytes: metrics.disk.read_bytes,
+            disk_write_bytes: metrics.disk.write_bytes,
+            network_rx_dropped: metrics.network.rx_dropped,
+            network_tx_dropped: metrics.network.tx_dropped,
+            network_source: metrics.network.source.clone(),
+            cpu_percent: metrics.cpu_percent,
+            memory_percent: metrics.memory_percent,
+            collected_at: metrics.timestamp,
+            from_cache,
+        }
+    }
+
+    fn exec_usage(usage: engine::ExecUsage) -> quilt::ExecUsage {
+        quilt::ExecUsage {
+            cpu_usage_usec: usage.cpu_usage_usec,
+            memory_peak_bytes: usage.memory_peak_bytes,
+        }
+    }
+
+    fn virtual_service_info(service: sync::virtual_services::VirtualServiceRecord, backends: Vec<engine::ServiceBackend>) -> quilt::VirtualService {
+        quilt::VirtualService {
+            name: service.name,
+            selector: service.selector,
+            backends: backends.into_iter().map(|backend| quilt::VirtualServiceBackend {
+                container_id: backend.container_id,
+                ip_address: backend.ip_address,
+            }).collect(),
+            created_at: service.created_at,
+        }
+    }
+
+    fn dns_record_info(record: engine::DnsRecord) -> quilt::DnsRecord {
+        quilt::DnsRecord {
+            name: record.name,
+            view: record.view,
+            addresses: record.addresses,
+            created_at: record.created_at,
+        }
+    }
+
+    fn network_attachment_info(attachment: engine::NetworkAttachment) -> quilt::NetworkAttachment {
+        quilt::NetworkAttachment {
+            interface: engine::networks::attachment_interface(&attachment),
+            subnet: icc::network::attachments::network_subnet(attachment.subnet_index),
+            gateway: icc::network::attachments::network_address(attachment.subnet_index, 1),
+            network: attachment.network,
+            ip_address: attachment.ip_address,
+            attached_at: attachment.attached_at,
+        }
+    }
+
+    fn image_info(info: engine::ImageInfo) -> quilt::Image {
+        let image = info.record.image;
+        quilt::Image {
+            digest: image.digest,
+            repo_tags: image.repo_tags,
+            layers: image.layers,
+            size_bytes: image.size_bytes,
+            created_at: info.record.created_at,
+            sources: info.record.sources.into_iter()
+                .map(|source| quilt::ImageSource { path: source.path, last_used_at: source.last_used_at.unwrap_or(0) })
+                .collect(),
+            container_ids: info.containers,
+            complete: info.complete,
+            env: image.config.env,
+            working_dir: image.config.working_dir.unwrap_or_default(),
+            user: image.config.user.unwrap_or_default(),
+            architecture: image.config.architecture.unwrap_or_default(),
+        }
+    }
+
+    fn traffic_mirror_info(mirror: engine::TrafficMirror) -> quilt::TrafficMirror {
+        quilt::TrafficMirror {
+            container_id: mirror.source_container_id,
+            observer_container_id: mirror.observer_container_id,
+            direction: mirror.direction,
+            created_at: mirror.created_at,
+        }
+    }
+
+    fn runtime_log_entry(line: daemon::output::LogLine) -> quilt::LogEntry {
+        quilt::LogEntry {
+            timestamp: line.timestamp_ms / 1000,
+            message: format!("[{}] {}", line.stream.as_str().to_uppercase(), line.message),
+            actor: String::new(),
+            stream: line.stream.as_str().to_string(),
+            timestamp_ms: line.timestamp_ms,
+            monotonic_ns: line.monotonic_ns,
+        }
+    }
+
+    fn network_policy_from_proto(policy: quilt::NetworkPolicy) -> Result<daemon::net_policy::NetworkPolicy, String> {
+        use daemon::net_policy::{NetworkPolicyRule, PolicyAction, PolicyDirection};
+        let default_action = |action: &str| if action.is_empty() { Ok(PolicyAction::Allow) } else { PolicyAction::from_str(action) };
+        Ok(daemon::net_policy::NetworkPolicy {
+            ingress_default: default_action(&policy.ingress_default)?,
+            egress_default: default_action(&policy.egress_default)?,
+            rules: policy.rules.into_iter()
+                .map(|rule| Ok(NetworkPolicyRule {
+                    direction: PolicyDirection::from_str(&rule.direction)?,
+                    peer: rule.peer.trim().to_string(),
+                    action: PolicyAction::from_str(&rule.action)?,
+                }))
+                .collect::<Result<_, String>>()?,
+        })
+    }
+
+    fn network_policy_to_proto(policy: &daemon::net_policy::NetworkPolicy) -> quilt::NetworkPolicy {
+        quilt::NetworkPolicy {
+            ingress_default: policy.ingress_default.as_str().to_string(),
+            egress_default: policy.egress_default.as_str().to_string(),
+            rules: policy.rules.iter().map(|rule| quilt::NetworkPolicyRule {
+                direction: rule.direction.as_str().to_string(),
+                peer: rule.peer.clone(),
+                action: rule.action.as_str().to_string(),
+            }).collect(),
+        }
+    }
+
+    /// Collect running containers with completed network setup for diagnostics,
+    /// optionally restricted to a single container
+    async fn collect_diagnostic_targets(&self, only: Option<&str>) -> Result<Vec<icc::network::DiagnosticTarget>, String> {
+        let allocations = match only {
+            Some(container_id) => vec![self.sync_engine.get_network_allocation(container_id).await
+                .map_err(|e| format!("Container {} has no network allocation: {}", container_id, e))?],
+            None => self.sync_engine.list_network_allocations().await
+                .map_err(|e| format!("Failed to list network allocations: {}", e))?,
+        };
+
+        let mut targets = Vec::new();
+        for allocation in allocations {
+            let veth_host_name = match (allocation.setup_completed, allocation.veth_host) {
+                (true, Some(veth)) => veth,
+                _ if only.is_some() => return Err(format!("Network setup for {} has not completed", allocation.container_id)),
+                _ => continue,
+            };
+            let pid = match self.sync_engine.get_container_status(&allocation.container_id).await {
+                Ok(status) if status.state == ContainerState::Running => status.pid,
+                _ => None,
+            };
+            match pid {
+                Some(pid) => targets.push(icc::network::DiagnosticTarget {
+                    container_id: allocation.container_id,
+                    container_pid: pid as i32,
+                    ip_address: allocation.ip_address,
+                    veth_host_name,
+                }),
+                None if only.is_some() => return Err(format!("Container {} is not running", allocation.container_id)),
+                None => {}
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// A real-time sample of a container: the latest stored one if it is no older than the request
+    /// allows (by default the container's metrics interval), otherwise a fresh one, which is stored
+    /// for history unless the container opted out of collection
+    async fn current_container_metric(&self, collector: &daemon::metrics::MetricsCollector, container_id: &str, pid: Option<i64>, max_age_seconds: i32) -> Option<ContainerMetric> {
+        let schedule = self.sync_engine.get_metrics_schedule(container_id).await.ok();
+        let interval = schedule.as_ref().and_then(|schedule| schedule.interval_seconds)
+            .unwrap_or_else(engine::metrics_collection::default_interval_secs);
+        if let Some(max_age) = daemon::metrics::metrics_max_age(max_age_seconds, interval as u64) {
+            if let Ok(Some(latest)) = self.sync_engine.get_latest_metrics(container_id).await {
+                let now_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+                if daemon::metrics::sample_is_fresh(latest.timestamp, now_ms, max_age) {
+                    return Some(Self::container_metric(&latest, true));
+                }
+            }
+        }
+
+        let metrics = collector.collect_container_metrics(container_id, pid.map(|p| p as i32)).ok()?;
+        if schedule.map_or(true, |schedule| schedule.enabled) {
+            let _ = self.sync_engine.store_metrics(&metrics).await;
+        }
+        Some(Self::container_metric(&metrics, false))
+    }
 }
 
 #[tonic::async_trait]
@@ -124,23 +512,174 @@ impl QuiltService for QuiltServiceImpl {
         &self,
         request: Request<CreateContainerRequest>,
     ) -> Result<Response<CreateContainerResponse>, Status> {
+        let actor = request_actor(&request);
+        let token = request_token(&request);
         let req = request.into_inner();
+
+        // Refuse bad names and images before anything is allocated for the container
+        if !req.name.is_empty() {
+            InputValidator::validate_container_name(&req.name).map_err(Status::invalid_argument)?;
+            NamePolicy::global().check(&req.name, token.as_deref())
+                .map_err(|e| error_status(ErrorCode::PermissionDenied, e))?;
+            if self.sync_engine.get_container_by_name(&req.name).await.is_ok() {
+                return Err(error_status(ErrorCode::Conflict, format!("Container with name '{}' already exists", req.name)));
+            }
+        }
+        let image_path = self.engine.resolve_image(&req.image_path).await.map_err(engine_status)?;
+        daemon::docker_archive::check_image(&image_path).map_err(engine_status)?;
+        self.engine.admit_image(&image_path).await.map_err(engine_status)?;
+
         let container_id = Uuid::new_v4().to_string();
 
         ConsoleLogger::container_created(&container_id);
-        
-        // Emit container created event
-        sync::events::global_event_buffer().emit(
-            sync::events::EventType::Created,
-            &container_id,
-            None,
-        );
+
+        if req.labels.keys().any(|key| key.trim().is_empty()) {
+            return Err(Status::invalid_argument("Label keys must not be empty"));
+        }
+
+        let hooks = req.hooks.iter()
+            .map(|hook| LifecycleHook::new(&hook.events, &hook.command, &hook.url, hook.timeout_seconds))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Status::invalid_argument(format!("Invalid lifecycle hook: {}", e)))?;
+        let prestart_hooks = req.prestart_hooks.iter()
+            .map(|hook| PrestartHook::new(&hook.command, &hook.namespace, hook.timeout_seconds))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Status::invalid_argument(format!("Invalid pre-start hook: {}", e)))?;
+        daemon::manager::RuntimeManager::new()
+            .parse_setup_spec(&req.setup_commands.join("\n"))
+            .map_err(|e| Status::invalid_argument(format!("Invalid setup command: {}", e)))?;
+        let setup_commands: Vec<String> = req.setup_commands.iter()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let priority_class = PriorityClass::from_str(&req.priority_class).map_err(Status::invalid_argument)?;
+        if !req.enable_time_namespace && (req.monotonic_offset_seconds != 0 || req.boottime_offset_seconds != 0) {
+            return Err(Status::invalid_argument("Clock offsets require the time namespace to be enabled"));
+        }
+        daemon::time_offset::validate_namespace_offsets(req.monotonic_offset_seconds, req.boottime_offset_seconds)
+            .map_err(Status::invalid_argument)?;
+        // Container root cannot join its cgroup or reach host devices from a user namespace
+        if req.enable_user_namespace && (req.enable_cgroup_namespace || req.privileged) {
+            return Err(Status::invalid_argument("The user namespace cannot be combined with the cgroup namespace or privileged mode"));
+        }
+        let time_offset = if req.time_offset.trim().is_empty() {
+            None
+        } else {
+            Some(daemon::time_offset::parse_time_offset(&req.time_offset).map_err(Status::invalid_argument)?)
+        };
+        let readiness = if req.readiness_gates.is_empty() {
+            None
+        } else {
+            let gates = req.readiness_gates.iter()
+                .map(|gate| ReadinessGate::new(&gate.kind, &gate.target))
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|gates| ReadinessGates::new(gates, req.readiness_timeout_seconds))
+                .map_err(|e| Status::invalid_argument(format!("Invalid readiness gate: {}", e)))?;
+            Some(gates)
+        };
+        if !req.network_plugin.is_empty() && !engine::plugins::is_active(&req.network_plugin) {
+            return Err(Status::invalid_argument(format!("Network plugin '{}' is not registered", req.network_plugin)));
+        }
+        let egress_proxy = if req.egress_proxy.trim().is_empty() {
+            if req.egress_proxy_transparent {
+                return Err(Status::invalid_argument("egress_proxy_transparent needs an egress_proxy"));
+            }
+            None
+        } else {
+            if req.egress_proxy_transparent && !req.enable_network_namespace {
+                return Err(Status::invalid_argument("Transparent egress proxying requires the network namespace to be enabled"));
+            }
+            let (target, port) = engine::EgressProxy::parse_target(&req.egress_proxy).map_err(Status::invalid_argument)?;
+            let proxy_id = match self.sync_engine.get_container_by_name(&target).await {
+                Ok(id) => id,
+                Err(_) => self.resolve_icc_container(&target, "").await?,
+            };
+            Some(engine::EgressProxy { container_id: proxy_id, port, transparent: req.egress_proxy_transparent })
+        };
+
+        if req.memory_high_mb < 0 || req.memory_swap_mb < -1 || !(0..=100).contains(&req.memory_swappiness) {
+            return Err(Status::invalid_argument("memory_high_mb must be >= 0, memory_swap_mb >= -1 and memory_swappiness 0-100"));
+        }
+        // The kernel refuses a burst larger than the quota it tops up
+        if req.cpu_burst_percent < 0.0 || req.cpu_burst_percent > req.cpu_limit_percent.max(0.0) {
+            return Err(Status::invalid_argument("cpu_burst_percent needs a cpu_limit_percent and cannot exceed it"));
+        }
+        if req.memory_limit_mb > 0 && req.memory_high_mb > req.memory_limit_mb {
+            return Err(Status::invalid_argument("memory_high_mb cannot exceed memory_limit_mb"));
+        }
+
+        if req.privileged && req.immutable {
+            return Err(Status::invalid_argument("A container cannot be both privileged and immutable"));
+        }
+        // FUSE mounts need CAP_SYS_ADMIN, which the immutable profile drops
+        if req.allow_fuse && req.immutable {
+            return Err(Status::invalid_argument("allow_fuse cannot be combined with immutable"));
+        }
+        if req.metrics_interval_seconds > daemon::metrics::MAX_METRICS_INTERVAL_SECS {
+            return Err(Status::invalid_argument(format!("metrics_interval_seconds cannot exceed {}", daemon::metrics::MAX_METRICS_INTERVAL_SECS)));
+        }
+        if !req.working_directory.is_empty() && !req.working_directory.starts_with('/') {
+            return Err(Status::invalid_argument("working_directory must be an absolute path"));
+        }
+        let timezone = if req.timezone.trim().is_empty() {
+            None
+        } else {
+            Some(daemon::timezone::resolve_timezone(&req.timezone).map_err(Status::invalid_argument)?)
+        };
+        let locale = Some(req.locale.trim().to_string()).filter(|locale| !locale.is_empty());
+        if let Some(locale) = &locale {
+            daemon::timezone::validate_locale(locale).map_err(Status::invalid_argument)?;
+        }
+        if !req.user.is_empty() {
+            daemon::image_config::validate_user(&req.user).map_err(Status::invalid_argument)?;
+        }
+
+        // Declared FUSE mounts are only honoured for containers that opted into /dev/fuse
+        if !req.fuse_mounts.is_empty() && !req.allow_fuse {
+            return Err(Status::invalid_argument("FUSE mounts require allow_fuse to be enabled"));
+        }
+        let mut fuse_mounts = Vec::with_capacity(req.fuse_mounts.len());
+        for fuse_mount in req.fuse_mounts {
+            let fuse_mount = utils::validation::FuseMount {
+                helper: fuse_mount.helper,
+                source: fuse_mount.source,
+                target: fuse_mount.target,
+                options: fuse_mount.options,
+            };
+            if let Err(e) = InputValidator::validate_fuse_mount(&fuse_mount) {
+                return Err(Status::invalid_argument(format!("Invalid FUSE mount: {}", e)));
+            }
+            fuse_mounts.push(fuse_mount);
+        }
+
+        // Published ports are forwarded into the container's network namespace
+        if !req.ports.is_empty() && !req.enable_network_namespace {
+            return Err(Status::invalid_argument("Port publishing requires the network namespace to be enabled"));
+        }
+        if req.dns_wildcard && !req.enable_network_namespace {
+            return Err(Status::invalid_argument("Wildcard DNS names require the network namespace to be enabled"));
+        }
+        let mut ports = Vec::with_capacity(req.ports.len());
+        for port in req.ports {
+            let mapping = utils::validation::PortMapping {
+                host_ip: if port.host_ip.is_empty() { "0.0.0.0".to_string() } else { port.host_ip },
+                host_port: u16::try_from(port.host_port)
+                    .map_err(|_| Status::invalid_argument(format!("Invalid host port {}", port.host_port)))?,
+                container_port: u16::try_from(port.container_port)
+                    .map_err(|_| Status::invalid_argument(format!("Invalid container port {}", port.container_port)))?,
+                protocol: if port.protocol.is_empty() { "tcp".to_string() } else { port.protocol.to_ascii_lowercase() },
+            };
+            if let Err(e) = InputValidator::validate_port_mapping(&mapping) {
+                return Err(Status::invalid_argument(format!("Invalid port mapping: {}", e)));
+            }
+            ports.push(mapping);
+        }
 
         // Convert gRPC request to sync engine container config
         let config = sync::containers::ContainerConfig {
             id: container_id.clone(),
             name: if req.name.is_empty() { None } else { Some(req.name) },
-            image_path: req.image_path,
+            image_path,
             command: if req.command.is_empty() { 
                 if req.async_mode {
                     // Use tail -f /dev/null as primary, with fallback to while loop
@@ -175,13 +714,38 @@ impl QuiltService for QuiltServiceImpl {
                 }
                 validated_env
             },
+            working_directory: Some(req.working_directory).filter(|dir| !dir.is_empty()),
+            user: Some(req.user).filter(|user| !user.is_empty()),
+            timezone,
+            locale,
             memory_limit_mb: if req.memory_limit_mb > 0 { Some(req.memory_limit_mb as i64) } else { None },
             cpu_limit_percent: if req.cpu_limit_percent > 0.0 { Some(req.cpu_limit_percent as f64) } else { None },
+            cpu_burst_percent: if req.cpu_burst_percent > 0.0 { Some(req.cpu_burst_percent as f64) } else { None },
+            memory_high_mb: if req.memory_high_mb > 0 { Some(req.memory_high_mb as i64) } else { None },
+            memory_swap_mb: match req.memory_swap_mb {
+                -1 => Some(0),
+                0 => None,
+                mb => Some(mb as i64),
+            },
+            memory_swappiness: if req.memory_swappiness > 0 { Some(req.memory_swappiness as i64) } else { None },
+            metrics_interval_seconds: if req.metrics_interval_seconds > 0 { Some(req.metrics_interval_seconds as i64) } else { None },
             enable_network_namespace: req.enable_network_namespace,
             enable_pid_namespace: req.enable_pid_namespace,
             enable_mount_namespace: req.enable_mount_namespace,
             enable_uts_namespace: req.enable_uts_namespace,
             enable_ipc_namespace: req.enable_ipc_namespace,
+            enable_time_namespace: req.enable_time_namespace,
+            monotonic_offset: req.monotonic_offset_seconds,
+            boottime_offset: req.boottime_offset_seconds,
+            enable_cgroup_namespace: req.enable_cgroup_namespace,
+            enable_user_namespace: req.enable_user_namespace,
+            allow_fuse: req.allow_fuse,
+            fuse_mounts,
+            immutable: req.immutable,
+            privileged: req.privileged,
+            ports,
+            dns_wildcard: req.dns_wildcard,
+            labels: req.labels,
         };
 
         // ✅ NON-BLOCKING: Create container with coordinated network allocation
@@ -190,8 +754,37 @@ impl QuiltService for QuiltServiceImpl {
                 // ✅ INSTANT RETURN: Container creation is coordinated but non-blocking
                 ConsoleLogger::success(&format!("Container {} created with network config", container_id));
                 
+                // Emit container created event (after the record exists, so it carries the container's labels)
+                sync::events::global_event_buffer().emit_as(
+                    sync::events::EventType::Created,
+                    &container_id,
+                    &actor,
+                    None,
+                );
+                
+                // Until startup is handed off, a cancelled request or a failed mount drops the half-configured record
+                let creation_guard = scopeguard::guard((self.sync_engine.clone(), container_id.clone()), |(sync_engine, id)| {
+                    ConsoleLogger::warning(&format!("↩️ [ROLLBACK] Create of {} did not complete; removing its record", id));
+                    tokio::spawn(async move {
+                        let _ = sync_engine.remove_container_mounts(&id).await;
+                        let _ = sync_engine.delete_container(&id).await;
+                        sync::events::global_event_buffer().forget_container_labels(&id);
+                    });
+                });
+
+                // Report the stored name, which the sync engine generates when none was given
+                let name = self.sync_engine.get_container_status(&container_id).await
+                    .ok()
+                    .and_then(|status| status.name)
+                    .unwrap_or_default();
+
                 // Store creation log
-                let _ = self.sync_engine.store_container_log(&container_id, "info", "Container created and configured").await;
+                let _ = self.sync_engine.store_container_log_as(&container_id, "info", "Container created and configured", &actor).await;
+                if req.privileged {
+                    ConsoleLogger::warning(&format!("Container {} created privileged by {}", container_id, actor));
+                    let _ = self.sync_engine.store_container_log_as(&container_id, "warn",
+                        "Privileged: all capabilities, host /dev, all devices, no seccomp filter, unhardened /proc and /sys", &actor).await;
+                }
                 
                 // Process mounts BEFORE starting container with security validation
                 for mount in req.mounts {
@@ -242,7 +835,10 @@ impl QuiltService for QuiltServiceImpl {
                         return Ok(Response::new(CreateContainerResponse {
                             container_id: String::new(),
                             success: false,
+                            error_code: ErrorCode::PermissionDenied as i32,
                             error_message: format!("Mount security validation failed: {}", e),
+                            name: String::new(),
+                            operation_id: String::new(),
                         }));
                     }
                     
@@ -281,7 +877,10 @@ impl QuiltService for QuiltServiceImpl {
                         return Ok(Response::new(CreateContainerResponse {
                             container_id: String::new(),
                             success: false,
+                            error_code: ErrorCode::Internal as i32,
                             error_message: format!("Failed to configure mount: {}", e),
+                            name: String::new(),
+                            operation_id: String::new(),
                         }));
                     }
                     
@@ -289,45 +888,130 @@ impl QuiltService for QuiltServiceImpl {
                         container_id, mount.source, mount.target, mount.readonly));
                 }
                 
+                if !hooks.is_empty() {
+                    if let Err(e) = self.engine.set_container_hooks(&container_id, hooks).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if !prestart_hooks.is_empty() {
+                    if let Err(e) = self.engine.set_prestart_hooks(&container_id, prestart_hooks).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if !setup_commands.is_empty() {
+                    if let Err(e) = self.engine.set_setup_commands(&container_id, setup_commands).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if let Some(readiness) = &readiness {
+                    if let Err(e) = self.engine.set_readiness_gates(&container_id, Some(readiness)).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if priority_class != PriorityClass::Normal {
+                    if let Err(e) = self.engine.set_priority_class(&container_id, priority_class).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if !req.network_plugin.is_empty() {
+                    if let Err(e) = self.engine.set_network_plugin(&container_id, Some(&req.network_plugin)).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if let Some(proxy) = &egress_proxy {
+                    if let Err(e) = self.engine.set_egress_proxy(&container_id, Some(proxy)).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
+                if time_offset.is_some() {
+                    if let Err(e) = self.engine.set_time_offset(&container_id, time_offset).await {
+                        return Ok(Response::new(CreateContainerResponse {
+                            container_id: String::new(),
+                            success: false,
+                            error_code: error_code(&e),
+                            error_message: e,
+                            name: String::new(),
+                            operation_id: String::new(),
+                        }));
+                    }
+                }
+                
                 // Now start the container with mounts already configured
+                let _ = scopeguard::ScopeGuard::into_inner(creation_guard);
                 let sync_engine = self.sync_engine.clone();
                 let network_manager = self.network_manager.clone();
+                let runtime = self.runtime.clone();
                 let container_id_clone = container_id.clone();
+                let operation = global_operations().begin("create", &container_id);
+                let operation_id = operation.operation_id();
+                // The response does not wait for the start, so the caller's deadline does not bound it
                 tokio::spawn(async move {
-                    // Add timeout to prevent hanging containers
-                    let startup_timeout = std::time::Duration::from_secs(120); // 2 minute timeout
-                    let task_start = std::time::Instant::now();
-                    
-                    ConsoleLogger::info(&format!("⏰ [TASK-SPAWN] Starting container {} with {:?} timeout", 
-                        container_id_clone, startup_timeout));
-                    
-                    let startup_result = tokio::time::timeout(
-                        startup_timeout,
-                        start_container_process(&sync_engine, &container_id_clone, network_manager)
-                    ).await;
-                    
-                    match startup_result {
-                        Ok(Ok(())) => {
-                            ConsoleLogger::success(&format!("🎯 [TASK-COMPLETE] Container {} startup completed successfully in {:?}", 
-                                container_id_clone, task_start.elapsed()));
-                        }
-                        Ok(Err(e)) => {
-                            ConsoleLogger::error(&format!("💥 [TASK-ERROR] Failed to start container process {} after {:?}: {}", 
-                                container_id_clone, task_start.elapsed(), e));
-                            let _ = sync_engine.update_container_state(&container_id_clone, ContainerState::Error).await;
-                        }
-                        Err(_) => {
-                            ConsoleLogger::error(&format!("⏰ [TASK-TIMEOUT] Container {} startup timed out after {:?} (limit: {:?})", 
-                                container_id_clone, task_start.elapsed(), startup_timeout));
-                            let _ = sync_engine.update_container_state(&container_id_clone, ContainerState::Error).await;
-                        }
-                    }
+                    operation.finish(run_container_startup(&sync_engine, &container_id_clone, network_manager, runtime, DEFAULT_STARTUP_TIMEOUT).await);
                 });
                 
                 Ok(Response::new(CreateContainerResponse {
                     container_id,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
+                    name,
+                    operation_id,
                 }))
             }
             Err(e) => {
@@ -335,7 +1019,10 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(CreateContainerResponse {
                     container_id: String::new(),
                     success: false,
+                    error_code: sync_error_code(&e) as i32,
                     error_message: e.to_string(),
+                    name: String::new(),
+                    operation_id: String::new(),
                 }))
             }
         }
@@ -362,13 +1049,7 @@ impl QuiltService for QuiltServiceImpl {
         // ✅ ALWAYS FAST: Direct database query, never blocks
         match self.sync_engine.get_container_status(&container_id).await {
             Ok(status) => {
-                let grpc_status = match status.state {
-                    ContainerState::Created => ContainerStatus::Pending,
-                    ContainerState::Starting => ContainerStatus::Pending,
-                    ContainerState::Running => ContainerStatus::Running,
-                    ContainerState::Exited => ContainerStatus::Exited,
-                    ContainerState::Error => ContainerStatus::Failed,
-                };
+                let grpc_status = Self::grpc_container_status(&status.state);
 
                 // Get enhanced runtime statistics if container is running
                 let mut memory_usage_bytes = 0i64;
@@ -381,12 +1062,26 @@ impl QuiltService for QuiltServiceImpl {
                     }
                 }
 
+                let setup_steps = self.sync_engine.list_setup_steps(&container_id).await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|step| quilt::SetupStep {
+                        index: step.step_index as u32,
+                        command: step.command,
+                        status: step.status,
+                        output: step.output,
+                        duration_ms: step.duration_ms as u64,
+                    })
+                    .collect();
+
                 ConsoleLogger::debug(&format!("✅ [GRPC] Status for {}: {:?}", req.container_id, grpc_status));
                 
                 Ok(Response::new(GetContainerStatusResponse {
                     container_id: req.container_id,
                     status: grpc_status as i32,
                     exit_code: status.exit_code.unwrap_or(0) as i32,
+                    // The lookup succeeded; a failed container is reported through its status
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: if status.state == ContainerState::Error { "Container failed".to_string() } else { String::new() },
                     pid: status.pid.unwrap_or(0) as i32,
                     created_at: status.created_at as u64,
@@ -395,6 +1090,7 @@ impl QuiltService for QuiltServiceImpl {
                     memory_usage_bytes: memory_usage_bytes as u64,
                     rootfs_path: status.rootfs_path.unwrap_or_default(),
                     ip_address: status.ip_address.unwrap_or_default(),
+                    setup_steps,
                 }))
             }
             Err(_) => {
@@ -432,6 +1128,10 @@ impl QuiltService for QuiltServiceImpl {
                     all_logs.push(quilt::LogEntry {
                         timestamp: log.timestamp as u64,
                         message: format!("[{}] [{}] {}", log.level.to_uppercase(), formatted_timestamp, log.message),
+                        actor: log.actor,
+                        stream: "daemon".to_string(),
+                        timestamp_ms: log.timestamp as u64 * 1000,
+                        monotonic_ns: 0,
                     });
                 }
             }
@@ -440,31 +1140,43 @@ impl QuiltService for QuiltServiceImpl {
             }
         }
         
-        // Also get logs from runtime (container output logs)
-        use crate::daemon::runtime::ContainerRuntime;
-        let runtime = ContainerRuntime::new();
-        if let Some(runtime_logs) = runtime.get_container_logs(&container_id) {
-            for (i, log_line) in runtime_logs.iter().enumerate() {
-                all_logs.push(quilt::LogEntry {
-                    timestamp: (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() - runtime_logs.len() as u64 + i as u64) as u64,
-                    message: format!("[RUNTIME] {}", log_line),
-                });
+        // Also get logs from runtime (runtime events and captured stdout/stderr, stamped on arrival)
+        if let Some(output) = self.runtime.get_container_logs(&container_id) {
+            let mut lines = output.lines();
+            // Readers for stdout and stderr run concurrently; the monotonic stamp is the true order
+            lines.sort_by_key(|line| line.monotonic_ns);
+            if output.dropped() > 0 {
+                let mut note = daemon::output::LogLine::now(daemon::output::LogStream::System,
+                    format!("{} older output lines were dropped", output.dropped()));
+                if let Some(oldest) = lines.first() {
+                    note.timestamp_ms = oldest.timestamp_ms;
+                    note.monotonic_ns = oldest.monotonic_ns;
+                }
+                all_logs.push(Self::runtime_log_entry(note));
             }
+            all_logs.extend(lines.into_iter().map(Self::runtime_log_entry));
         }
         
         // Add comprehensive filesystem inspection for debugging
         use crate::utils::filesystem::FileSystemUtils;
-        let current_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let inspect_entry = |message: String| {
+            let now = daemon::output::LogLine::now(daemon::output::LogStream::System, String::new());
+            quilt::LogEntry {
+                timestamp: now.timestamp_ms / 1000,
+                message,
+                actor: String::new(),
+                stream: "inspect".to_string(),
+                timestamp_ms: now.timestamp_ms,
+                monotonic_ns: now.monotonic_ns,
+            }
+        };
         
         // Inspect container rootfs if accessible
         if let Ok(status) = self.sync_engine.get_container_status(&container_id).await {
             if let Some(rootfs) = status.rootfs_path {
                 if FileSystemUtils::exists(&rootfs) {
                     if FileSystemUtils::is_directory(&rootfs) {
-                        all_logs.push(quilt::LogEntry {
-                            timestamp: current_time,
-                            message: format!("[INSPECT] Container rootfs exists at: {}", rootfs),
-                        });
+                        all_logs.push(inspect_entry(format!("[INSPECT] Container rootfs exists at: {}", rootfs)));
                         
                         // Check key directories
                         let key_dirs = vec!["/bin", "/usr/bin", "/tmp", "/var", "/etc"];
@@ -478,10 +1190,7 @@ impl QuiltService for QuiltServiceImpl {
                                 } else {
                                     "exists (unknown type)"
                                 };
-                                all_logs.push(quilt::LogEntry {
-                                    timestamp: current_time + 1,
-                                    message: format!("[INSPECT] {} - {}", dir, info),
-                                });
+                                all_logs.push(inspect_entry(format!("[INSPECT] {} - {}", dir, info)));
                             }
                         }
                         
@@ -497,16 +1206,10 @@ impl QuiltService for QuiltServiceImpl {
                                         "not executable"
                                     };
                                     if let Ok(size) = FileSystemUtils::get_file_size(&full_path) {
-                                        all_logs.push(quilt::LogEntry {
-                                            timestamp: current_time + 2,
-                                            message: format!("[INSPECT] {} - {} ({} bytes, {})", file, "exists", size, executable),
-                                        });
+                                        all_logs.push(inspect_entry(format!("[INSPECT] {} - {} ({} bytes, {})", file, "exists", size, executable)));
                                     }
                                 } else if FileSystemUtils::is_broken_symlink(&full_path) {
-                                    all_logs.push(quilt::LogEntry {
-                                        timestamp: current_time + 2,
-                                        message: format!("[INSPECT] {} - broken symlink detected", file),
-                                    });
+                                    all_logs.push(inspect_entry(format!("[INSPECT] {} - broken symlink detected", file)));
                                 }
                             }
                         }
@@ -515,8 +1218,9 @@ impl QuiltService for QuiltServiceImpl {
             }
         }
         
-        // Sort logs by timestamp for chronological order
-        all_logs.sort_by_key(|log| log.timestamp);
+        // Sort logs by timestamp for chronological order; the sort is stable, so entries from the
+        // same millisecond keep the order they were collected in
+        all_logs.sort_by_key(|log| log.timestamp_ms);
         
         Ok(Response::new(GetContainerLogsResponse {
             container_id,
@@ -524,64 +1228,121 @@ impl QuiltService for QuiltServiceImpl {
         }))
     }
 
-    async fn stop_container(
+    async fn search_container_logs(
         &self,
-        request: Request<StopContainerRequest>,
-    ) -> Result<Response<StopContainerResponse>, Status> {
-        use crate::daemon::runtime::ContainerRuntime;
-        
+        request: Request<quilt::SearchContainerLogsRequest>,
+    ) -> Result<Response<quilt::SearchContainerLogsResponse>, Status> {
+        const DEFAULT_LOG_SEARCH_LIMIT: usize = 100;
+        const MAX_LOG_SEARCH_LIMIT: usize = 10_000;
+        const RUNTIME_LEVEL: &str = "runtime";
+
         let req = request.into_inner();
-        
-        // Resolve container name to ID if needed
-        let container_id = if !req.container_name.is_empty() {
-            match self.sync_engine.get_container_by_name(&req.container_name).await {
-                Ok(id) => id,
-                Err(_) => return Ok(Response::new(StopContainerResponse {
-                    success: false,
-                    error_message: format!("Container with name '{}' not found", req.container_name),
-                })),
-            }
+        let container_id = if req.container_id.is_empty() && req.container_name.is_empty() {
+            None
         } else {
-            req.container_id.clone()
+            Some(self.resolve_icc_container(&req.container_id, &req.container_name).await?)
         };
+        let pattern = if req.query.is_empty() {
+            None
+        } else {
+            Some(sync::containers::LogPattern::new(&req.query, req.regex, req.case_sensitive).map_err(Status::invalid_argument)?)
+        };
+        let limit = match req.limit as usize {
+            0 => DEFAULT_LOG_SEARCH_LIMIT,
+            limit if limit > MAX_LOG_SEARCH_LIMIT => return Err(Status::invalid_argument(format!("limit cannot exceed {}", MAX_LOG_SEARCH_LIMIT))),
+            limit => limit,
+        };
+        let search = sync::containers::LogSearch {
+            container_id: container_id.clone(),
+            pattern,
+            levels: req.levels.iter().map(|level| level.trim().to_lowercase())
+                .filter(|level| !level.is_empty() && level != RUNTIME_LEVEL).collect(),
+            since: (req.since > 0).then_some(req.since as i64),
+            until: (req.until > 0).then_some(req.until as i64),
+            // One extra row tells whether the result was truncated
+            limit: limit + 1,
+        };
+        let include_stored = req.levels.is_empty() || !search.levels.is_empty();
+        let include_runtime = req.levels.is_empty() || req.levels.iter().any(|level| level.trim().eq_ignore_ascii_case(RUNTIME_LEVEL));
 
-        // Use the comprehensive runtime stop_container method
-        let runtime = ContainerRuntime::new();
-        match runtime.stop_container(&container_id) {
-            Ok(()) => {
-                // Update sync engine state
-                if let Err(e) = self.sync_engine.update_container_state(&container_id, ContainerState::Exited).await {
-                    ConsoleLogger::warning(&format!("Failed to update container state in sync engine: {}", e));
-                }
-                
-                // Stop monitoring in sync engine
-                let _ = self.sync_engine.stop_monitoring(&container_id).await;
-                
-                // Store stop log
-                let _ = self.sync_engine.store_container_log(&container_id, "info", "Container stopped successfully").await;
-                
-                // Emit container stopped event
-                sync::events::global_event_buffer().emit(
-                    sync::events::EventType::Stopped,
-                    &container_id,
-                    None,
-                );
-
-                Ok(Response::new(StopContainerResponse {
-                    success: true,
-                    error_message: String::new(),
-                }))
+        let mut matches = Vec::new();
+        if include_stored {
+            let stored = self.sync_engine.search_container_logs(&search).await
+                .map_err(|e| Status::internal(format!("Failed to search logs: {}", e)))?;
+            matches.extend(stored.into_iter().map(|(container_id, entry)| quilt::LogMatch {
+                container_id,
+                timestamp: entry.timestamp as u64,
+                level: entry.level,
+                message: entry.message,
+                actor: entry.actor,
+                stream: "daemon".to_string(),
+                timestamp_ms: entry.timestamp as u64 * 1000,
+            }));
+        }
+
+        // Container output and runtime events, stamped when they were written
+        if include_runtime {
+            let container_ids = match container_id {
+                Some(id) => vec![id],
+                None => self.sync_engine.list_containers(None).await
+                    .map_err(|e| Status::internal(format!("Failed to list containers: {}", e)))?
+                    .into_iter().map(|container| container.id).collect(),
+            };
+            for id in container_ids {
+                let lines = self.runtime.get_container_logs(&id).map(|output| output.lines()).unwrap_or_default();
+                matches.extend(lines.into_iter()
+                    .filter(|line| search.accepts_time((line.timestamp_ms / 1000) as i64) && search.accepts_message(&line.message))
+                    .map(|line| quilt::LogMatch {
+                        container_id: id.clone(),
+                        timestamp: line.timestamp_ms / 1000,
+                        level: RUNTIME_LEVEL.to_string(),
+                        message: line.message,
+                        actor: String::new(),
+                        stream: line.stream.as_str().to_string(),
+                        timestamp_ms: line.timestamp_ms,
+                    }));
             }
-            Err(e) => {
-                // Store error log
-                let _ = self.sync_engine.store_container_log(&container_id, "error", &format!("Failed to stop container: {}", e)).await;
-                
-                ConsoleLogger::error(&format!("Failed to stop container {}: {}", container_id, e));
-                Ok(Response::new(StopContainerResponse {
+        }
+
+        // Newest first across stored and runtime lines
+        matches.sort_by(|a, b| b.timestamp_ms.cmp(&a.timestamp_ms));
+        let truncated = matches.len() > limit;
+        matches.truncate(limit);
+        Ok(Response::new(quilt::SearchContainerLogsResponse { matches, truncated }))
+    }
+
+    async fn stop_container(
+        &self,
+        request: Request<StopContainerRequest>,
+    ) -> Result<Response<StopContainerResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        
+        // Resolve container name to ID if needed
+        let container_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => id,
+                Err(_) => return Ok(Response::new(StopContainerResponse {
                     success: false,
-                    error_message: e,
-                }))
+                    error_code: ErrorCode::NotFound as i32,
+                    error_message: format!("Container with name '{}' not found", req.container_name),
+                })),
             }
+        } else {
+            req.container_id.clone()
+        };
+
+        match self.engine.stop_container(&container_id, &actor).await {
+            Ok(()) => Ok(Response::new(StopContainerResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(StopContainerResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
         }
     }
 
@@ -589,6 +1350,7 @@ impl QuiltService for QuiltServiceImpl {
         &self,
         request: Request<RemoveContainerRequest>,
     ) -> Result<Response<RemoveContainerResponse>, Status> {
+        let actor = request_actor(&request);
         let req = request.into_inner();
         
         // Resolve container name to ID if needed
@@ -597,6 +1359,7 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(id) => id,
                 Err(_) => return Ok(Response::new(RemoveContainerResponse {
                     success: false,
+                    error_code: ErrorCode::NotFound as i32,
                     error_message: format!("Container with name '{}' not found", req.container_name),
                 })),
             }
@@ -604,72 +1367,22 @@ impl QuiltService for QuiltServiceImpl {
             req.container_id.clone()
         };
 
-        // Use both runtime cleanup and sync engine cleanup for comprehensive removal
-        use crate::daemon::runtime::ContainerRuntime;
-        let runtime = ContainerRuntime::new();
-        
-        // First, attempt runtime removal (handles process stopping and resource cleanup)
-        let runtime_result = runtime.remove_container(&container_id);
-        
-        // Then, remove from sync engine (handles database cleanup)
-        match self.sync_engine.delete_container(&container_id).await {
+        match self.engine.remove_container(&container_id, req.force, &actor).await {
             Ok(()) => {
-                // Comprehensive cleanup using all sync engine methods
-                
-                // Remove container mounts
-                if let Err(e) = self.sync_engine.remove_container_mounts(&container_id).await {
-                    ConsoleLogger::warning(&format!("Failed to remove mounts for {}: {}", container_id, e));
-                }
-                
-                // Cleanup container logs (keep last 10 for debugging)
-                if let Ok(cleaned_count) = self.sync_engine.cleanup_container_logs(&container_id, 10).await {
-                    ConsoleLogger::debug(&format!("Cleaned up {} log entries for {}", cleaned_count, container_id));
-                }
-                
-                // Unregister from DNS
-                let _ = self.network_manager.unregister_container_dns(&container_id);
-                
-                // Enhanced resource cleanup with correlation
-                use crate::daemon::resource::ResourceManager;
-                let resource_manager = ResourceManager::new();
-                let container_pid = runtime.get_container_info(&container_id)
-                    .and_then(|info| info.pid);
-                
-                if let Err(e) = resource_manager.cleanup_container_with_correlation(&container_id, container_pid) {
-                    ConsoleLogger::warning(&format!("Resource correlation cleanup issues for {}: {}", container_id, e));
-                } else {
-                    ConsoleLogger::debug(&format!("✅ Resource correlation cleanup completed for {}", container_id));
-                }
-                
-                // Log runtime result for debugging
-                if let Err(e) = runtime_result {
-                    ConsoleLogger::warning(&format!("Runtime cleanup issues for {}: {}", container_id, e));
-                }
-                
-                ConsoleLogger::success(&format!("Container {} removed with comprehensive cleanup", container_id));
-                
-                // Store removal log
-                let _ = self.sync_engine.store_container_log(&container_id, "info", "Container removed successfully").await;
-                
-                // Emit container removed event
-                sync::events::global_event_buffer().emit(
-                    sync::events::EventType::Removed,
-                    &container_id,
-                    None,
-                );
-                
+                // Drop queued ICC messages and any services the container owned
+                self.message_broker.cleanup_container(&container_id);
+
                 Ok(Response::new(RemoveContainerResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
-            Err(e) => {
-                ConsoleLogger::error(&format!("Failed to remove container {}: {}", container_id, e));
-                Ok(Response::new(RemoveContainerResponse {
-                    success: false,
-                    error_message: e.to_string(),
-                }))
-            }
+            Err(e) => Ok(Response::new(RemoveContainerResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
         }
     }
 
@@ -677,6 +1390,7 @@ impl QuiltService for QuiltServiceImpl {
         &self,
         request: Request<ExecContainerRequest>,
     ) -> Result<Response<ExecContainerResponse>, Status> {
+        let deadline = request_deadline(&request);
         let req = request.into_inner();
         
         // Resolve container name to ID if needed
@@ -688,7 +1402,9 @@ impl QuiltService for QuiltServiceImpl {
                     exit_code: -1,
                     stdout: String::new(),
                     stderr: String::new(),
+                    error_code: ErrorCode::NotFound as i32,
                     error_message: format!("Container with name '{}' not found", req.container_name),
+                    usage: None,
                 })),
             }
         } else {
@@ -697,6 +1413,20 @@ impl QuiltService for QuiltServiceImpl {
         
         ConsoleLogger::debug(&format!("🔍 [GRPC] Exec request for: {} with command: {:?}", container_id, req.command));
         
+        // Stop and remove wait until the container is checked and the script copied in
+        let exec_guard = match self.sync_engine.lock_container_shared(&container_id, "exec").await {
+            Ok(guard) => guard,
+            Err(e) => return Ok(Response::new(ExecContainerResponse {
+                success: false,
+                exit_code: -1,
+                stdout: String::new(),
+                stderr: String::new(),
+                error_code: sync_error_code(&e) as i32,
+                error_message: e.to_string(),
+                usage: None,
+            })),
+        };
+        
         // Handle script copying if needed
         if req.copy_script && req.command.len() == 1 {
             let script_path = &req.command[0];
@@ -712,7 +1442,9 @@ impl QuiltService for QuiltServiceImpl {
                                     exit_code: -1,
                                     stdout: String::new(),
                                     stderr: String::new(),
+                                    error_code: ErrorCode::Internal as i32,
                                     error_message: format!("Failed to copy script: {}", e),
+                                    usage: None,
                                 }));
                             }
                             // Make script executable
@@ -725,7 +1457,9 @@ impl QuiltService for QuiltServiceImpl {
                             exit_code: -1,
                             stdout: String::new(),
                             stderr: String::new(),
+                            error_code: sync_error_code(&e) as i32,
                             error_message: format!("Failed to get container info: {}", e),
+                            usage: None,
                         }));
                     }
                 }
@@ -741,7 +1475,9 @@ impl QuiltService for QuiltServiceImpl {
                         exit_code: -1,
                         stdout: String::new(),
                         stderr: String::new(),
+                        error_code: ErrorCode::InvalidState as i32,
                         error_message: format!("Container {} is not running (state: {:?})", container_id, status.state),
+                        usage: None,
                     }));
                 }
 
@@ -753,13 +1489,59 @@ impl QuiltService for QuiltServiceImpl {
                             exit_code: -1,
                             stdout: String::new(),
                             stderr: String::new(),
+                            error_code: ErrorCode::InvalidState as i32,
                             error_message: "Container has no PID".to_string(),
+                            usage: None,
+                        }));
+                    }
+                };
+                let rootfs_path = match status.rootfs_path {
+                    Some(rootfs_path) => rootfs_path,
+                    None => {
+                        return Ok(Response::new(ExecContainerResponse {
+                            success: false,
+                            exit_code: -1,
+                            stdout: String::new(),
+                            stderr: String::new(),
+                            error_code: ErrorCode::InvalidState as i32,
+                            error_message: "Container has no rootfs".to_string(),
+                            usage: None,
+                        }));
+                    }
+                };
+
+                let working_directory = match Engine::exec_working_directory(pid, &req.working_directory, req.create_working_directory) {
+                    Ok(working_directory) => working_directory,
+                    Err(e) => {
+                        return Ok(Response::new(ExecContainerResponse {
+                            success: false,
+                            exit_code: -1,
+                            stdout: String::new(),
+                            stderr: String::new(),
+                            error_code: ErrorCode::InvalidArgument as i32,
+                            error_message: e,
+                            usage: None,
+                        }));
+                    }
+                };
+
+                let environment = match self.engine.exec_environment(&container_id, &req.environment).await {
+                    Ok(environment) => environment,
+                    Err(e) => {
+                        return Ok(Response::new(ExecContainerResponse {
+                            success: false,
+                            exit_code: -1,
+                            stdout: String::new(),
+                            stderr: String::new(),
+                            error_code: error_code(&e),
+                            error_message: e,
+                            usage: None,
                         }));
                     }
                 };
 
                 // Handle script copying if requested
-                let command_to_execute = if req.copy_script && req.command.len() == 1 {
+                let (command_to_execute, temp_script) = if req.copy_script && req.command.len() == 1 {
                     let script_path = &req.command[0];
                     
                     // Read the local script file
@@ -772,19 +1554,32 @@ impl QuiltService for QuiltServiceImpl {
                                 .as_secs();
                             let temp_script = format!("/tmp/quilt_exec_{}", timestamp);
                             
-                            // Copy script to container using nsenter with chroot
-                            // SECURITY NOTE: This nsenter command is validated - PID checked before execution
-                            let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
-                            let copy_cmd = format!(
-                                "nsenter -t {} -p -m -n -u -- chroot {} /bin/sh -c 'cat > {} << 'EOF'\n{}\nEOF\nchmod +x {}'",
-                                pid, rootfs_path, temp_script, script_content, temp_script
-                            );
+                            // Copy script to container using nsenter with chroot; the content goes
+                            // through stdin and the path is an argument, so neither reaches a shell line
+                            let copy_command = vec![
+                                "/bin/sh".to_string(), "-c".to_string(),
+                                "cat > \"$0\" && chmod +x \"$0\"".to_string(),
+                                temp_script.clone(),
+                            ];
+                            let copy = Engine::exec_argv(pid, &rootfs_path, &copy_command, &HashMap::new())
+                                .with_input(script_content);
                             
-                            match utils::command::CommandExecutor::execute_shell(&copy_cmd) {
-                                Ok(_) => {
+                            match CommandExecutor::execute_with_timeout(&copy, utils::executor::DEFAULT_COMMAND_TIMEOUT).await {
+                                Ok(result) if result.success => {
                                     ConsoleLogger::debug(&format!("✅ Copied script to container: {}", temp_script));
-                                    // Return the temporary script path to execute
-                                    temp_script
+                                    // Execute the temporary script
+                                    (vec![temp_script.clone()], Some(temp_script))
+                                }
+                                Ok(result) => {
+                                    return Ok(Response::new(ExecContainerResponse {
+                                        success: false,
+                                        exit_code: -1,
+                                        stdout: String::new(),
+                                        stderr: String::new(),
+                                        error_code: ErrorCode::Internal as i32,
+                                        error_message: format!("Failed to copy script to container: {}", result.stderr.trim()),
+                                        usage: None,
+                                    }));
                                 }
                                 Err(e) => {
                                     return Ok(Response::new(ExecContainerResponse {
@@ -792,7 +1587,9 @@ impl QuiltService for QuiltServiceImpl {
                                         exit_code: -1,
                                         stdout: String::new(),
                                         stderr: String::new(),
+                                        error_code: ErrorCode::Internal as i32,
                                         error_message: format!("Failed to copy script to container: {}", e),
+                                        usage: None,
                                     }));
                                 }
                             }
@@ -803,53 +1600,54 @@ impl QuiltService for QuiltServiceImpl {
                                 exit_code: -1,
                                 stdout: String::new(),
                                 stderr: String::new(),
+                                error_code: ErrorCode::Internal as i32,
                                 error_message: format!("Failed to read script file: {}", e),
+                                usage: None,
                             }));
                         }
                     }
                 } else {
-                    req.command.join(" ")
+                    (req.command.clone(), None)
                 };
+                let remove_script = |temp_script: &str| Engine::exec_argv(pid, &rootfs_path, &["rm".to_string(), "-f".to_string(), temp_script.to_string()], &HashMap::new());
 
                 // Execute command using nsenter with chroot to match container's view
                 // SECURITY NOTE: Container PID validated before reaching this point
-                // Get the rootfs path for the container
-                let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
-                
-                // Escape the command for shell execution
-                // Using double quotes to allow shell expansion (redirects, pipes, etc.)
-                let escaped_command = command_to_execute.replace("\\", "\\\\")
-                    .replace("\"", "\\\"")
-                    .replace("$", "\\$")
-                    .replace("`", "\\`");
-                
-                // Set PATH to include busybox binaries
-                let path_prefix = "export PATH=/bin:/usr/bin:/sbin:/usr/sbin:$PATH; ";
-                // Note: We're not using IPC namespace (-i) by default as it's disabled in NamespaceConfig::default()
-                let exec_cmd = if req.capture_output {
-                    format!("nsenter -t {} -p -m -n -u -- chroot {} /bin/sh -c \"{}{}\"", pid, rootfs_path, path_prefix, escaped_command)
-                } else {
-                    format!("nsenter -t {} -p -m -n -u -- chroot {} /bin/sh -c \"{}{}\" >/dev/null 2>&1", pid, rootfs_path, path_prefix, escaped_command)
+                let exec_argv = Engine::exec_argv_in(pid, &rootfs_path, working_directory.as_deref(), &command_to_execute, &environment);
+                let exec_session = Engine::exec_session_cgroup(&container_id);
+                let exec_argv = match &exec_session {
+                    Some(session) => exec_argv.with_cgroup(session.procs_file()),
+                    None => exec_argv,
                 };
 
                 // Primary execution using CommandExecutor with fallback to runtime method
-                match CommandExecutor::execute_shell(&exec_cmd) {
-                    Ok(result) => {
+                // Commands that outlive the timeout are killed rather than pinning the request forever
+                const DEFAULT_EXEC_TIMEOUT: Duration = Duration::from_secs(300);
+                let exec_timeout = bounded_by_deadline(if req.timeout_seconds > 0 {
+                    Duration::from_secs(req.timeout_seconds as u64)
+                } else {
+                    DEFAULT_EXEC_TIMEOUT
+                }, deadline);
+                drop(exec_guard);
+                let outcome = CommandExecutor::execute_with_timeout(&exec_argv, exec_timeout).await;
+                let usage = exec_session.map(|session| Self::exec_usage(session.remove()));
+                match outcome {
+                    Ok(mut result) => {
                         ConsoleLogger::debug(&format!("✅ [GRPC] Exec completed with exit code: {}", result.exit_code.unwrap_or(-1)));
                         
                         // Clean up temporary script if we created one
-                        if req.copy_script && command_to_execute.starts_with("/tmp/quilt_exec_") {
-                            let cleanup_cmd = format!(
-                                "nsenter -t {} -p -m -n -u -- chroot {} rm -f {}",
-                                pid, rootfs_path, command_to_execute
-                            );
-                            let _ = CommandExecutor::execute_shell(&cleanup_cmd);
+                        if let Some(temp_script) = &temp_script {
+                            let _ = CommandExecutor::execute_with_timeout(&remove_script(temp_script), utils::executor::DEFAULT_COMMAND_TIMEOUT).await;
                         }
                         
                         // Check if command failed due to "command not found" or similar
                         let command_not_found = result.stderr.contains("not found") || 
                                               result.stderr.contains("No such file") ||
                                               result.stderr.contains("can't execute");
+                        if !req.capture_output {
+                            result.stdout.clear();
+                            result.stderr.clear();
+                        }
                         
                         // Set success based on exit code AND command existence
                         let success = result.success && !command_not_found;
@@ -866,27 +1664,30 @@ impl QuiltService for QuiltServiceImpl {
                             exit_code: result.exit_code.unwrap_or(-1),
                             stdout: result.stdout,
                             stderr: result.stderr,
+                            // A command that ran is reported through exit_code
+                            error_code: if command_not_found { ErrorCode::NotFound as i32 } else { ErrorCode::Unspecified as i32 },
                             error_message,
+                            usage,
                         }))
                     }
                     Err(e) => {
                         ConsoleLogger::warning(&format!("⚠️ [GRPC] CommandExecutor failed, trying runtime exec: {}", e));
                         
                         // Fallback to runtime exec_container method for enhanced reliability
-                        use crate::daemon::runtime::ContainerRuntime;
-                        let runtime = ContainerRuntime::new();
+                        let runtime = self.runtime.clone();
+                        let fallback_id = container_id.clone();
+                        let fallback_command = req.command.clone();
+                        let fallback_result = tokio::task::spawn_blocking(move || {
+                            runtime.exec_container(&fallback_id, fallback_command, working_directory, environment, true)
+                        }).await.unwrap_or_else(|e| Err(format!("Exec task failed: {}", e)));
                         
-                        match runtime.exec_container(&container_id, req.command.clone(), Some(req.working_directory), req.environment, true) {
+                        match fallback_result {
                             Ok((exit_code, stdout, stderr)) => {
                                 ConsoleLogger::debug(&format!("✅ [GRPC] Runtime exec completed with exit code: {}", exit_code));
                                 
                                 // Clean up temporary script on success
-                                if req.copy_script && command_to_execute.starts_with("/tmp/quilt_exec_") {
-                                    let cleanup_cmd = format!(
-                                        "nsenter -t {} -p -m -n -u -- chroot {} rm -f {}",
-                                        pid, rootfs_path, command_to_execute
-                                    );
-                                    let _ = CommandExecutor::execute_shell(&cleanup_cmd);
+                                if let Some(temp_script) = &temp_script {
+                                    let _ = CommandExecutor::execute_with_timeout(&remove_script(temp_script), utils::executor::DEFAULT_COMMAND_TIMEOUT).await;
                                 }
                                 
                                 Ok(Response::new(ExecContainerResponse {
@@ -894,19 +1695,18 @@ impl QuiltService for QuiltServiceImpl {
                                     exit_code,
                                     stdout,
                                     stderr,
+                                    // The command ran; its own failure is reported through exit_code
+                                    error_code: ErrorCode::Unspecified as i32,
                                     error_message: if exit_code != 0 { format!("Command failed with exit code {}", exit_code) } else { String::new() },
+                                    usage: None,
                                 }))
                             }
                             Err(runtime_error) => {
                                 ConsoleLogger::error(&format!("❌ [GRPC] Both exec methods failed. CommandExecutor: {}, Runtime: {}", e, runtime_error));
                                 
                                 // Clean up temporary script on error
-                                if req.copy_script && command_to_execute.starts_with("/tmp/quilt_exec_") {
-                                    let cleanup_cmd = format!(
-                                        "nsenter -t {} -p -m -n -u -- chroot {} rm -f {}",
-                                        pid, rootfs_path, command_to_execute
-                                    );
-                                    let _ = CommandExecutor::execute_shell(&cleanup_cmd);
+                                if let Some(temp_script) = &temp_script {
+                                    let _ = CommandExecutor::execute_with_timeout(&remove_script(temp_script), utils::executor::DEFAULT_COMMAND_TIMEOUT).await;
                                 }
                                 
                                 Ok(Response::new(ExecContainerResponse {
@@ -914,7 +1714,9 @@ impl QuiltService for QuiltServiceImpl {
                                     exit_code: -1,
                                     stdout: String::new(),
                                     stderr: String::new(),
+                                    error_code: ErrorCode::Internal as i32,
                                     error_message: format!("Exec failed: {} (Runtime fallback: {})", e, runtime_error),
+                                    usage: None,
                                 }))
                             }
                         }
@@ -927,11 +1729,114 @@ impl QuiltService for QuiltServiceImpl {
         }
     }
     
+    async fn exec_container_stream(
+        &self,
+        request: Request<ExecContainerRequest>,
+    ) -> Result<Response<Self::ExecContainerStreamStream>, Status> {
+        use tokio_stream::wrappers::ReceiverStream;
+        use futures::stream::StreamExt;
+        use utils::executor::CommandOutput;
+
+        let deadline = request_deadline(&request);
+        let req = request.into_inner();
+
+        // Resolve container name to ID if needed
+        let container_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => id,
+                Err(_) => return Err(Status::not_found(format!("Container with name '{}' not found", req.container_name))),
+            }
+        } else {
+            req.container_id.clone()
+        };
+
+        if req.command.is_empty() {
+            return Err(Status::invalid_argument("Command is required"));
+        }
+
+        // Held until the command is launched
+        let _exec_guard = self.sync_engine.lock_container_shared(&container_id, "exec").await
+            .map_err(|e| error_status(sync_error_code(&e), e.to_string()))?;
+        let status = self.sync_engine.get_container_status(&container_id).await
+            .map_err(|_| Status::not_found(format!("Container {} not found", container_id)))?;
+        if status.state != ContainerState::Running {
+            return Err(Status::failed_precondition(format!("Container {} is not running (state: {:?})", container_id, status.state)));
+        }
+        let pid = status.pid
+            .ok_or_else(|| Status::failed_precondition("Container has no PID"))?;
+        let rootfs_path = status.rootfs_path
+            .ok_or_else(|| Status::failed_precondition("Container has no rootfs"))?;
+
+        ConsoleLogger::debug(&format!("🔍 [GRPC] Streaming exec for: {} with command: {:?}", container_id, req.command));
+
+        let environment = self.engine.exec_environment(&container_id, &req.environment).await
+            .map_err(Status::invalid_argument)?;
+        let working_directory = Engine::exec_working_directory(pid, &req.working_directory, req.create_working_directory)
+            .map_err(Status::invalid_argument)?;
+        let exec_argv = Engine::exec_argv_in(pid, &rootfs_path, working_directory.as_deref(), &req.command, &environment);
+        // Streaming is meant for long builds and test runs, so only an explicit timeout bounds it
+        let exec_timeout = bounded_by_deadline(if req.timeout_seconds > 0 {
+            Duration::from_secs(req.timeout_seconds as u64)
+        } else {
+            Duration::from_secs(24 * 60 * 60)
+        }, deadline);
+
+        let mut exec_session = Engine::exec_session_cgroup(&container_id);
+        let exec_argv = match &exec_session {
+            Some(session) => exec_argv.with_cgroup(session.procs_file()),
+            None => exec_argv,
+        };
+
+        let receiver = CommandExecutor::stream(&exec_argv, exec_timeout).await
+            .map_err(Status::internal)?;
+
+        // The session cgroup lives as long as the stream, so a client that disconnects early
+        // still has it removed
+        let stream = ReceiverStream::new(receiver).map(move |output| {
+            Ok(match output {
+                CommandOutput::Stdout(stdout) => quilt::ExecOutputChunk {
+                    stdout,
+                    ..Default::default()
+                },
+                CommandOutput::Stderr(stderr) => quilt::ExecOutputChunk {
+                    stderr,
+                    ..Default::default()
+                },
+                CommandOutput::Exited(exit_code) => quilt::ExecOutputChunk {
+                    done: true,
+                    exit_code: exit_code.unwrap_or(-1),
+                    usage: exec_session.take().map(|session| Self::exec_usage(session.remove())),
+                    ..Default::default()
+                },
+                CommandOutput::Failed(error_message) => quilt::ExecOutputChunk {
+                    done: true,
+                    exit_code: -1,
+                    error_code: error_code(&error_message),
+                    error_message,
+                    usage: exec_session.take().map(|session| Self::exec_usage(session.remove())),
+                    ..Default::default()
+                },
+            })
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type ExecContainerStreamStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<quilt::ExecOutputChunk, Status>> + Send>>;
+    
     async fn start_container(
         &self,
         request: Request<StartContainerRequest>,
     ) -> Result<Response<StartContainerResponse>, Status> {
+        let deadline = request_deadline(&request);
+        let actor = request_actor(&request);
         let req = request.into_inner();
+        // Only a caller waiting for the start bounds it with its deadline
+        let startup_timeout = if req.wait_ready {
+            bounded_by_deadline(DEFAULT_STARTUP_TIMEOUT, deadline)
+        } else {
+            DEFAULT_STARTUP_TIMEOUT
+        };
         
         // Resolve container name to ID if needed
         let container_id = if !req.container_name.is_empty() {
@@ -939,8 +1844,11 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(id) => id,
                 Err(_) => return Ok(Response::new(StartContainerResponse {
                     success: false,
+                    error_code: ErrorCode::NotFound as i32,
                     error_message: format!("Container with name '{}' not found", req.container_name),
                     pid: 0,
+                    readiness_gates: vec![],
+                    operation_id: String::new(),
                 })),
             }
         } else {
@@ -955,43 +1863,91 @@ impl QuiltService for QuiltServiceImpl {
                 if status.state == ContainerState::Running {
                     return Ok(Response::new(StartContainerResponse {
                         success: false,
+                        error_code: ErrorCode::InvalidState as i32,
                         error_message: "Container is already running".to_string(),
                         pid: status.pid.unwrap_or(0) as i32,
+                        readiness_gates: vec![],
+                        operation_id: String::new(),
                     }));
                 }
                 
                 if status.state != ContainerState::Created && status.state != ContainerState::Exited {
                     return Ok(Response::new(StartContainerResponse {
                         success: false,
+                        error_code: ErrorCode::InvalidState as i32,
                         error_message: format!("Cannot start container in state: {:?}", status.state),
                         pid: 0,
+                        readiness_gates: vec![],
+                        operation_id: String::new(),
                     }));
                 }
             }
             Err(e) => {
                 return Ok(Response::new(StartContainerResponse {
                     success: false,
+                    error_code: sync_error_code(&e) as i32,
                     error_message: format!("Container not found: {}", e),
                     pid: 0,
+                    readiness_gates: vec![],
+                    operation_id: String::new(),
                 }));
             }
         }
         
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info", "Start requested", &actor).await;
+        
         // Start the container process in background
         let sync_engine = self.sync_engine.clone();
         let network_manager = self.network_manager.clone();
+        let runtime = self.runtime.clone();
         let container_id_clone = container_id.clone();
-        tokio::spawn(async move {
-            if let Err(e) = start_container_process(&sync_engine, &container_id_clone, network_manager).await {
+        let operation = global_operations().begin("start", &container_id);
+        let operation_id = operation.operation_id();
+        // Spawned even when waiting, so a caller that goes away does not abort the start halfway
+        let startup = tokio::spawn(async move {
+            let result = run_container_startup(&sync_engine, &container_id_clone, network_manager, runtime, startup_timeout).await;
+            if let Err(e) = &result {
                 ConsoleLogger::error(&format!("Failed to start container process {}: {}", container_id_clone, e));
-                let _ = sync_engine.update_container_state(&container_id_clone, ContainerState::Error).await;
             }
+            operation.finish(result.clone());
+            result
         });
         
+        if !req.wait_ready {
+            return Ok(Response::new(StartContainerResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                pid: 0, // Will be set once container starts
+                readiness_gates: vec![],
+                operation_id,
+            }));
+        }
+        
+        let started = startup.await.unwrap_or_else(|e| Err(format!("Container start task failed: {}", e)));
+        if let Err(e) = started {
+            return Ok(Response::new(StartContainerResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                pid: 0,
+                readiness_gates: vec![],
+                operation_id,
+            }));
+        }
+        let pid = self.sync_engine.get_container_status(&container_id).await.ok()
+            .and_then(|status| status.pid)
+            .unwrap_or(0) as i32;
+        let readiness_gates = self.sync_engine.get_readiness_gates(&container_id).await.ok().flatten()
+            .map(|readiness| readiness.gates.iter().map(|gate| gate.describe()).collect())
+            .unwrap_or_default();
         Ok(Response::new(StartContainerResponse {
             success: true,
+            error_code: ErrorCode::Unspecified as i32,
             error_message: String::new(),
-            pid: 0, // Will be set once container starts
+            pid,
+            readiness_gates,
+            operation_id,
         }))
     }
     
@@ -999,6 +1955,7 @@ impl QuiltService for QuiltServiceImpl {
         &self,
         request: Request<KillContainerRequest>,
     ) -> Result<Response<KillContainerResponse>, Status> {
+        let actor = request_actor(&request);
         let req = request.into_inner();
         
         // Resolve container name to ID if needed
@@ -1007,6 +1964,7 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(id) => id,
                 Err(_) => return Ok(Response::new(KillContainerResponse {
                     success: false,
+                    error_code: ErrorCode::NotFound as i32,
                     error_message: format!("Container with name '{}' not found", req.container_name),
                 })),
             }
@@ -1016,12 +1974,22 @@ impl QuiltService for QuiltServiceImpl {
         
         ConsoleLogger::warning(&format!("Killing container {}", container_id));
         
+        let _guard = match self.sync_engine.lock_container(&container_id, "kill").await {
+            Ok(guard) => guard,
+            Err(e) => return Ok(Response::new(KillContainerResponse {
+                success: false,
+                error_code: sync_error_code(&e) as i32,
+                error_message: e.to_string(),
+            })),
+        };
+        
         // Get container PID
         match self.sync_engine.get_container_status(&container_id).await {
             Ok(status) => {
                 if status.state != ContainerState::Running {
                     return Ok(Response::new(KillContainerResponse {
                         success: false,
+                        error_code: ErrorCode::InvalidState as i32,
                         error_message: format!("Container is not running (state: {:?})", status.state),
                     }));
                 }
@@ -1049,6 +2017,17 @@ impl QuiltService for QuiltServiceImpl {
                             // Update state to exited
                             let _ = self.sync_engine.update_container_state(&container_id, ContainerState::Exited).await;
                             let _ = self.sync_engine.set_container_exit_code(&container_id, -9).await;
+                            let _ = self.sync_engine.store_container_log_as(&container_id, "warn", "Container killed with SIGKILL", &actor).await;
+                            
+                            let mut attributes = HashMap::new();
+                            attributes.insert("exit_code".to_string(), "-9".to_string());
+                            attributes.insert("signal".to_string(), "SIGKILL".to_string());
+                            sync::events::global_event_buffer().emit_as(
+                                sync::events::EventType::Died,
+                                &container_id,
+                                &actor,
+                                Some(attributes),
+                            );
                             
                             // Stop monitoring
                             let _ = self.sync_engine.stop_monitoring(&container_id).await;
@@ -1058,6 +2037,7 @@ impl QuiltService for QuiltServiceImpl {
                             
                             Ok(Response::new(KillContainerResponse {
                                 success: true,
+                                error_code: ErrorCode::Unspecified as i32,
                                 error_message: String::new(),
                             }))
                         }
@@ -1069,11 +2049,13 @@ impl QuiltService for QuiltServiceImpl {
                                 
                                 Ok(Response::new(KillContainerResponse {
                                     success: true,
+                                    error_code: ErrorCode::Unspecified as i32,
                                     error_message: String::new(),
                                 }))
                             } else {
                                 Ok(Response::new(KillContainerResponse {
                                     success: false,
+                                    error_code: ErrorCode::Internal as i32,
                                     error_message: format!("Failed to kill process: {}", e),
                                 }))
                             }
@@ -1082,12 +2064,14 @@ impl QuiltService for QuiltServiceImpl {
                 } else {
                     Ok(Response::new(KillContainerResponse {
                         success: false,
+                        error_code: ErrorCode::InvalidState as i32,
                         error_message: "Container has no PID".to_string(),
                     }))
                 }
             }
             Err(e) => Ok(Response::new(KillContainerResponse {
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: format!("Failed to get container status: {}", e),
             }))
         }
@@ -1103,23 +2087,152 @@ impl QuiltService for QuiltServiceImpl {
             Ok(container_id) => Ok(Response::new(GetContainerByNameResponse {
                 container_id,
                 found: true,
+                error_code: ErrorCode::Unspecified as i32,
                 error_message: String::new(),
             })),
             Err(_) => Ok(Response::new(GetContainerByNameResponse {
                 container_id: String::new(),
                 found: false,
+                error_code: ErrorCode::NotFound as i32,
                 error_message: format!("Container with name '{}' not found", req.name),
             }))
         }
     }
 
+    async fn grant_device_access(
+        &self,
+        request: Request<GrantDeviceAccessRequest>,
+    ) -> Result<Response<GrantDeviceAccessResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+
+        match self.apply_device_rule(&req.container_id, &req.container_name, &req.rule, true, &actor).await {
+            Ok(()) => Ok(Response::new(GrantDeviceAccessResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => {
+                ConsoleLogger::warning(&format!("Failed to grant device access: {}", e));
+                Ok(Response::new(GrantDeviceAccessResponse {
+                    success: false,
+                    error_code: error_code(&e),
+                    error_message: e,
+                }))
+            }
+        }
+    }
+
+    async fn revoke_device_access(
+        &self,
+        request: Request<RevokeDeviceAccessRequest>,
+    ) -> Result<Response<RevokeDeviceAccessResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+
+        match self.apply_device_rule(&req.container_id, &req.container_name, &req.rule, false, &actor).await {
+            Ok(()) => Ok(Response::new(RevokeDeviceAccessResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => {
+                ConsoleLogger::warning(&format!("Failed to revoke device access: {}", e));
+                Ok(Response::new(RevokeDeviceAccessResponse {
+                    success: false,
+                    error_code: error_code(&e),
+                    error_message: e,
+                }))
+            }
+        }
+    }
+
+    async fn set_container_log_level(
+        &self,
+        request: Request<quilt::SetContainerLogLevelRequest>,
+    ) -> Result<Response<quilt::SetContainerLogLevelResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+
+        let Some(level) = utils::container_log::ContainerLogLevel::parse(&req.level) else {
+            return Err(Status::invalid_argument(format!("Unknown log level '{}' (expected info or debug)", req.level)));
+        };
+
+        let container_id = if !req.container_name.is_empty() {
+            self.sync_engine.get_container_by_name(&req.container_name).await
+                .map_err(|_| Status::not_found(format!("Container with name '{}' not found", req.container_name)))?
+        } else {
+            self.sync_engine.get_container_status(&req.container_id).await
+                .map_err(|_| Status::not_found(format!("Container {} not found", req.container_id)))?;
+            req.container_id
+        };
+
+        let previous = ConsoleLogger::set_container_log_level(&container_id, level);
+        ConsoleLogger::info(&format!("🔍 [LOG-LEVEL] {} for container {} (was {})", level.as_str(), container_id, previous.as_str()));
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info",
+            &format!("Daemon log level set to {}", level.as_str()), &actor).await;
+
+        Ok(Response::new(quilt::SetContainerLogLevelResponse {
+            success: true,
+            error_code: ErrorCode::Unspecified as i32,
+            error_message: String::new(),
+            previous_level: previous.as_str().to_string(),
+        }))
+    }
+
+    async fn set_metrics_collection(
+        &self,
+        request: Request<quilt::SetMetricsCollectionRequest>,
+    ) -> Result<Response<quilt::SetMetricsCollectionResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+
+        if req.interval_seconds > daemon::metrics::MAX_METRICS_INTERVAL_SECS {
+            return Err(Status::invalid_argument(format!("interval_seconds cannot exceed {}", daemon::metrics::MAX_METRICS_INTERVAL_SECS)));
+        }
+        let container_id = if !req.container_name.is_empty() {
+            self.sync_engine.get_container_by_name(&req.container_name).await
+                .map_err(|_| Status::not_found(format!("Container with name '{}' not found", req.container_name)))?
+        } else {
+            req.container_id
+        };
+
+        let interval = if req.interval_seconds > 0 { Some(req.interval_seconds) } else { None };
+        if let Err(e) = self.engine.set_metrics_collection(&container_id, req.enabled, interval).await {
+            return Ok(Response::new(quilt::SetMetricsCollectionResponse {
+                success: false,
+                error_code: ErrorCode::NotFound as i32,
+                error_message: e,
+                interval_seconds: 0,
+            }));
+        }
+        let interval_seconds = self.sync_engine.get_metrics_schedule(&container_id).await.ok()
+            .and_then(|schedule| schedule.interval_seconds)
+            .unwrap_or_else(engine::metrics_collection::default_interval_secs);
+
+        let message = if req.enabled {
+            format!("Background metrics collection on, every {}s", interval_seconds)
+        } else {
+            "Background metrics collection off".to_string()
+        };
+        ConsoleLogger::info(&format!("📊 [METRICS] {}: {}", container_id, message));
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info", &message, &actor).await;
+
+        Ok(Response::new(quilt::SetMetricsCollectionResponse {
+            success: true,
+            error_code: ErrorCode::Unspecified as i32,
+            error_message: String::new(),
+            interval_seconds,
+        }))
+    }
+
     async fn create_volume(
         &self,
         request: Request<CreateVolumeRequest>,
     ) -> Result<Response<CreateVolumeResponse>, Status> {
         let req = request.into_inner();
         
-        match self.sync_engine.create_volume(
+        match self.engine.create_volume(
             &req.name,
             if req.driver.is_empty() { None } else { Some(&req.driver) },
             req.labels,
@@ -1128,6 +2241,7 @@ impl QuiltService for QuiltServiceImpl {
             Ok(volume) => {
                 Ok(Response::new(CreateVolumeResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                     volume: Some(quilt::Volume {
                         name: volume.name,
@@ -1142,6 +2256,7 @@ impl QuiltService for QuiltServiceImpl {
             Err(e) => {
                 Ok(Response::new(CreateVolumeResponse {
                     success: false,
+                    error_code: error_code(&e),
                     error_message: e.to_string(),
                     volume: None,
                 }))
@@ -1155,16 +2270,18 @@ impl QuiltService for QuiltServiceImpl {
     ) -> Result<Response<RemoveVolumeResponse>, Status> {
         let req = request.into_inner();
         
-        match self.sync_engine.remove_volume(&req.name, req.force).await {
+        match self.engine.remove_volume(&req.name, req.force).await {
             Ok(()) => {
                 Ok(Response::new(RemoveVolumeResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => {
                 Ok(Response::new(RemoveVolumeResponse {
                     success: false,
+                    error_code: error_code(&e),
                     error_message: e.to_string(),
                 }))
             }
@@ -1223,6 +2340,7 @@ impl QuiltService for QuiltServiceImpl {
                         options: volume.options,
                         created_at: volume.created_at,
                     }),
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
@@ -1230,6 +2348,7 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(InspectVolumeResponse {
                     found: false,
                     volume: None,
+                    error_code: ErrorCode::NotFound as i32,
                     error_message: format!("Volume '{}' not found", req.name),
                 }))
             }
@@ -1237,28 +2356,205 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(InspectVolumeResponse {
                     found: false,
                     volume: None,
+                    error_code: sync_error_code(&e) as i32,
                     error_message: e.to_string(),
                 }))
             }
         }
     }
 
-    async fn get_health(
+    async fn attach_mount(
         &self,
-        _request: Request<GetHealthRequest>,
-    ) -> Result<Response<GetHealthResponse>, Status> {
-        use std::time::Instant;
-        
-        let mut checks = Vec::new();
-        let mut overall_healthy = true;
-        
-        // Check database connection
-        let db_start = Instant::now();
-        let db_healthy = match sqlx::query("SELECT 1").fetch_one(self.sync_engine.pool()).await {
-            Ok(_) => true,
-            Err(_) => {
-                overall_healthy = false;
-                false
+        request: Request<AttachMountRequest>,
+    ) -> Result<Response<AttachMountResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+
+        // Resolve container name to ID if needed
+        let container_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => id,
+                Err(_) => return Ok(Response::new(AttachMountResponse {
+                    success: false,
+                    error_code: ErrorCode::NotFound as i32,
+                    error_message: format!("Container with name '{}' not found", req.container_name),
+                })),
+            }
+        } else {
+            req.container_id.clone()
+        };
+
+        let mount = match req.mount {
+            Some(mount) => mount,
+            None => return Err(Status::invalid_argument("Mount configuration is required")),
+        };
+
+        let mount_type = match mount.r#type() {
+            quilt::MountType::Bind => MountType::Bind,
+            quilt::MountType::Volume => MountType::Volume,
+            quilt::MountType::Tmpfs => MountType::Tmpfs,
+        };
+
+        ConsoleLogger::info(&format!("📎 [ATTACH] Attaching {} -> {} to container {}", mount.source, mount.target, container_id));
+
+        // Hot-attach only makes sense for a live mount namespace
+        let status = match self.sync_engine.get_container_status(&container_id).await {
+            Ok(status) => status,
+            Err(e) => return Ok(Response::new(AttachMountResponse {
+                success: false,
+                error_code: sync_error_code(&e) as i32,
+                error_message: format!("Container not found: {}", e),
+            })),
+        };
+
+        let pid = match (status.state, status.pid) {
+            (ContainerState::Running, Some(pid)) => pid,
+            (state, _) => return Ok(Response::new(AttachMountResponse {
+                success: false,
+                error_code: ErrorCode::InvalidState as i32,
+                error_message: format!("Container is not running (state: {:?})", state),
+            })),
+        };
+
+        // Refuse to stack a second mount on an already mounted target
+        match self.sync_engine.get_container_mounts(&container_id).await {
+            Ok(existing) => {
+                if existing.iter().any(|m| m.target == mount.target) {
+                    return Ok(Response::new(AttachMountResponse {
+                        success: false,
+                        error_code: ErrorCode::Conflict as i32,
+                        error_message: format!("Target '{}' is already mounted in container {}", mount.target, container_id),
+                    }));
+                }
+            }
+            Err(e) => return Ok(Response::new(AttachMountResponse {
+                success: false,
+                error_code: ErrorCode::Internal as i32,
+                error_message: format!("Failed to read existing mounts: {}", e),
+            })),
+        }
+
+        // Same security validation as mounts supplied at create time
+        use crate::utils::security::SecurityValidator;
+        use crate::utils::validation::{VolumeMount, MountType as ValidationMountType};
+
+        let validation_mount = VolumeMount {
+            source: mount.source.clone(),
+            target: mount.target.clone(),
+            mount_type: match mount_type {
+                MountType::Bind => ValidationMountType::Bind,
+                MountType::Volume => ValidationMountType::Volume,
+                MountType::Tmpfs => ValidationMountType::Tmpfs,
+            },
+            readonly: mount.readonly,
+            options: mount.options.clone(),
+        };
+
+        if let Err(e) = SecurityValidator::validate_mount(&validation_mount) {
+            ConsoleLogger::error(&format!("Mount security validation failed for container {}: {}", container_id, e));
+            return Ok(Response::new(AttachMountResponse {
+                success: false,
+                error_code: ErrorCode::PermissionDenied as i32,
+                error_message: format!("Mount security validation failed: {}", e),
+            }));
+        }
+
+        // For named volumes, auto-create if needed and resolve to the host path
+        let source = if mount_type == MountType::Volume {
+            match self.sync_engine.get_volume(&mount.source).await {
+                Ok(None) => {
+                    ConsoleLogger::info(&format!("Auto-creating volume '{}'", mount.source));
+                    if let Err(e) = self.sync_engine.create_volume(&mount.source, None, HashMap::new(), HashMap::new()).await {
+                        return Ok(Response::new(AttachMountResponse {
+                            success: false,
+                            error_code: ErrorCode::Internal as i32,
+                            error_message: format!("Failed to create volume '{}': {}", mount.source, e),
+                        }));
+                    }
+                }
+                Ok(Some(_)) => {}
+                Err(e) => {
+                    ConsoleLogger::warning(&format!("Error checking volume '{}': {}", mount.source, e));
+                }
+            }
+            self.sync_engine.get_volume_path(&mount.source).to_string_lossy().to_string()
+        } else {
+            mount.source.clone()
+        };
+
+        let daemon_mount = daemon::MountConfig {
+            source,
+            target: mount.target.clone(),
+            mount_type: match mount_type {
+                MountType::Bind => daemon::MountType::Bind,
+                MountType::Volume => daemon::MountType::Volume,
+                MountType::Tmpfs => daemon::MountType::Tmpfs,
+            },
+            readonly: mount.readonly,
+            options: mount.options.clone(),
+        };
+
+        let nix_pid = utils::process::ProcessUtils::i32_to_pid(pid as i32);
+        if let Err(e) = daemon::namespace::NamespaceManager::new().attach_mount_to_process(nix_pid, &daemon_mount) {
+            ConsoleLogger::error(&format!("❌ [ATTACH] Failed to attach mount to {}: {}", container_id, e));
+            return Ok(Response::new(AttachMountResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            }));
+        }
+
+        // Persist so the mount is tracked for cleanup and reapplied on restart
+        if let Err(e) = self.sync_engine.add_container_mount(
+            &container_id,
+            &mount.source,
+            &mount.target,
+            mount_type,
+            mount.readonly,
+            mount.options,
+        ).await {
+            ConsoleLogger::warning(&format!("Mount attached but failed to persist for {}: {}", container_id, e));
+        }
+
+        let _ = self.sync_engine.store_container_log_as(&container_id, "info",
+            &format!("Mount attached: {} -> {} (readonly: {})", mount.source, mount.target, mount.readonly), &actor).await;
+
+        let mut attributes = HashMap::new();
+        attributes.insert("source".to_string(), mount.source.clone());
+        attributes.insert("target".to_string(), mount.target.clone());
+        attributes.insert("readonly".to_string(), mount.readonly.to_string());
+        sync::events::global_event_buffer().emit_as(
+            sync::events::EventType::VolumeMount,
+            &container_id,
+            &actor,
+            Some(attributes),
+        );
+
+        ConsoleLogger::success(&format!("📎 [ATTACH] Mount {} attached to container {}", mount.target, container_id));
+
+        Ok(Response::new(AttachMountResponse {
+            success: true,
+            error_code: ErrorCode::Unspecified as i32,
+            error_message: String::new(),
+        }))
+    }
+
+    async fn get_health(
+        &self,
+        _request: Request<GetHealthRequest>,
+    ) -> Result<Response<GetHealthResponse>, Status> {
+        use std::time::Instant;
+        
+        let mut checks = Vec::new();
+        let mut overall_healthy = true;
+        
+        // Check database connection
+        let db_start = Instant::now();
+        let db_healthy = match sqlx::query("SELECT 1").fetch_one(self.sync_engine.pool()).await {
+            Ok(_) => true,
+            Err(_) => {
+                overall_healthy = false;
+                false
             }
         };
         checks.push(HealthCheck {
@@ -1305,7 +2601,9 @@ impl QuiltService for QuiltServiceImpl {
         request: Request<GetMetricsRequest>,
     ) -> Result<Response<GetMetricsResponse>, Status> {
         let req = request.into_inner();
-        use crate::daemon::metrics::{MetricsCollector, SystemMetrics};
+        use crate::daemon::metrics::{BridgeThroughput, ConntrackStats, DirectoryUsage, MetricsCollector, SystemMetrics};
+        const CONNTRACK_TOP_ENDPOINTS: usize = 5;
+        const NETWORK_TOP_FLOWS: usize = 10;
         
         let mut container_metrics = Vec::new();
         
@@ -1319,129 +2617,19 @@ impl QuiltService for QuiltServiceImpl {
                     req.end_time, 
                     Some(1000)
                 ).await {
-                    for metrics in historical_metrics {
-                        container_metrics.push(ContainerMetric {
-                            container_id: metrics.container_id.clone(),
-                            timestamp: metrics.timestamp,
-                            cpu_usage_usec: metrics.cpu.usage_usec,
-                            cpu_user_usec: metrics.cpu.user_usec,
-                            cpu_system_usec: metrics.cpu.system_usec,
-                            cpu_throttled_usec: metrics.cpu.throttled_usec,
-                            memory_current_bytes: metrics.memory.current_bytes,
-                            memory_peak_bytes: metrics.memory.peak_bytes,
-                            memory_limit_bytes: metrics.memory.limit_bytes,
-                            memory_cache_bytes: metrics.memory.cache_bytes,
-                            memory_rss_bytes: metrics.memory.rss_bytes,
-                            network_rx_bytes: metrics.network.rx_bytes,
-                            network_tx_bytes: metrics.network.tx_bytes,
-                            network_rx_packets: metrics.network.rx_packets,
-                            network_tx_packets: metrics.network.tx_packets,
-                            disk_read_bytes: metrics.disk.read_bytes,
-                            disk_write_bytes: metrics.disk.write_bytes,
-                        });
-                    }
-                }
-            } else {
-                // Real-time metrics requested - try cached first, then fresh collection
-                if let Ok(status) = self.sync_engine.get_container_status(&req.container_id).await {
-                    // First try to get cached metrics (within last 30 seconds)
-                    let use_cached = if let Ok(Some(latest_metrics)) = self.sync_engine.get_latest_metrics(&req.container_id).await {
-                        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-                        let metrics_age = now - latest_metrics.timestamp;
-                        if metrics_age <= 30 {  // Use cached if less than 30 seconds old
-                            // Use cached metrics
-                            container_metrics.push(ContainerMetric {
-                                container_id: latest_metrics.container_id.clone(),
-                                timestamp: latest_metrics.timestamp,
-                                cpu_usage_usec: latest_metrics.cpu.usage_usec,
-                                cpu_user_usec: latest_metrics.cpu.user_usec,
-                                cpu_system_usec: latest_metrics.cpu.system_usec,
-                                cpu_throttled_usec: latest_metrics.cpu.throttled_usec,
-                                memory_current_bytes: latest_metrics.memory.current_bytes,
-                                memory_peak_bytes: latest_metrics.memory.peak_bytes,
-                                memory_limit_bytes: latest_metrics.memory.limit_bytes,
-                                memory_cache_bytes: latest_metrics.memory.cache_bytes,
-                                memory_rss_bytes: latest_metrics.memory.rss_bytes,
-                                network_rx_bytes: latest_metrics.network.rx_bytes,
-                                network_tx_bytes: latest_metrics.network.tx_bytes,
-                                network_rx_packets: latest_metrics.network.rx_packets,
-                                network_tx_packets: latest_metrics.network.tx_packets,
-                                disk_read_bytes: latest_metrics.disk.read_bytes,
-                                disk_write_bytes: latest_metrics.disk.write_bytes,
-                            });
-                            true
-                        } else {
-                            false  // Cached metrics too old
-                        }
-                    } else {
-                        false  // No cached metrics
-                    };
-                    
-                    // If no cached metrics or they're stale, collect fresh metrics
-                    if !use_cached {
-                        let collector = MetricsCollector::new();
-                        
-                        // Also get stats from runtime for additional details
-                        use crate::daemon::runtime::ContainerRuntime;
-                        let runtime = ContainerRuntime::new();
-                        let _runtime_stats = runtime.get_container_stats(&req.container_id).unwrap_or_default();
-                        
-                        if let Ok(metrics) = collector.collect_container_metrics(&req.container_id, status.pid.map(|p| p as i32)) {
-                        container_metrics.push(ContainerMetric {
-                                container_id: metrics.container_id.clone(),
-                                timestamp: metrics.timestamp,
-                                cpu_usage_usec: metrics.cpu.usage_usec,
-                                cpu_user_usec: metrics.cpu.user_usec,
-                                cpu_system_usec: metrics.cpu.system_usec,
-                                cpu_throttled_usec: metrics.cpu.throttled_usec,
-                                memory_current_bytes: metrics.memory.current_bytes,
-                                memory_peak_bytes: metrics.memory.peak_bytes,
-                                memory_limit_bytes: metrics.memory.limit_bytes,
-                                memory_cache_bytes: metrics.memory.cache_bytes,
-                                memory_rss_bytes: metrics.memory.rss_bytes,
-                                network_rx_bytes: metrics.network.rx_bytes,
-                                network_tx_bytes: metrics.network.tx_bytes,
-                                network_rx_packets: metrics.network.rx_packets,
-                                network_tx_packets: metrics.network.tx_packets,
-                                disk_read_bytes: metrics.disk.read_bytes,
-                                disk_write_bytes: metrics.disk.write_bytes,
-                            });
-                        
-                            // Store metrics in database for history
-                            let _ = self.sync_engine.store_metrics(&metrics).await;
-                        }
-                    }
+                    container_metrics.extend(historical_metrics.iter().map(|metrics| Self::container_metric(metrics, true)));
                 }
+            } else if let Ok(status) = self.sync_engine.get_container_status(&req.container_id).await {
+                // Real-time metrics requested - stored sample if recent enough, else fresh collection
+                let collector = MetricsCollector::new();
+                container_metrics.extend(self.current_container_metric(&collector, &req.container_id, status.pid, req.max_age_seconds).await);
             }
         } else {
             // Get metrics for all running containers
             if let Ok(containers) = self.sync_engine.list_containers(Some(ContainerState::Running)).await {
                 let collector = MetricsCollector::new();
                 for container in containers {
-                    if let Ok(metrics) = collector.collect_container_metrics(&container.id, container.pid.map(|p| p as i32)) {
-                        container_metrics.push(ContainerMetric {
-                            container_id: metrics.container_id.clone(),
-                            timestamp: metrics.timestamp,
-                            cpu_usage_usec: metrics.cpu.usage_usec,
-                            cpu_user_usec: metrics.cpu.user_usec,
-                            cpu_system_usec: metrics.cpu.system_usec,
-                            cpu_throttled_usec: metrics.cpu.throttled_usec,
-                            memory_current_bytes: metrics.memory.current_bytes,
-                            memory_peak_bytes: metrics.memory.peak_bytes,
-                            memory_limit_bytes: metrics.memory.limit_bytes,
-                            memory_cache_bytes: metrics.memory.cache_bytes,
-                            memory_rss_bytes: metrics.memory.rss_bytes,
-                            network_rx_bytes: metrics.network.rx_bytes,
-                            network_tx_bytes: metrics.network.tx_bytes,
-                            network_rx_packets: metrics.network.rx_packets,
-                            network_tx_packets: metrics.network.tx_packets,
-                            disk_read_bytes: metrics.disk.read_bytes,
-                            disk_write_bytes: metrics.disk.write_bytes,
-                        });
-                    
-                    // Store metrics in database for history
-                    let _ = self.sync_engine.store_metrics(&metrics).await;
-                    }
+                    container_metrics.extend(self.current_container_metric(&collector, &container.id, container.pid, req.max_age_seconds).await);
                 }
             }
         }
@@ -1456,6 +2644,16 @@ impl QuiltService for QuiltServiceImpl {
                     sys_metrics.containers_stopped = (total - running) as u64;
                 }
                 
+                let directories = [
+                    ("rootfs", daemon::paths::rootfs_base_dir().to_string()),
+                    ("images", daemon::paths::rootfs_pool_dir()),
+                    ("volumes", sync::volumes::VOLUMES_BASE_DIR.to_string()),
+                ];
+                sys_metrics.disk_usage = directories.iter()
+                    .filter_map(|(name, path)| DirectoryUsage::collect(name, path).ok())
+                    .collect();
+                sys_metrics.bridge = BridgeThroughput::collect(&self.network_manager.config.bridge_name).ok();
+                
                 Some(ProtoSystemMetrics {
                     timestamp: sys_metrics.timestamp,
                     memory_used_mb: sys_metrics.memory_used_mb,
@@ -1465,6 +2663,29 @@ impl QuiltService for QuiltServiceImpl {
                     containers_total: sys_metrics.containers_total,
                     containers_running: sys_metrics.containers_running,
                     containers_stopped: sys_metrics.containers_stopped,
+                    cpu_percent: sys_metrics.cpu_percent,
+                    disk_usage: sys_metrics.disk_usage.into_iter().map(|usage| quilt::DirectoryUsage {
+                        name: usage.name,
+                        path: usage.path,
+                        total_bytes: usage.total_bytes,
+                        used_bytes: usage.used_bytes,
+                        available_bytes: usage.available_bytes,
+                    }).collect(),
+                    bridge: sys_metrics.bridge.map(|bridge| quilt::BridgeThroughput {
+                        interface: bridge.interface,
+                        rx_bytes: bridge.rx_bytes,
+                        tx_bytes: bridge.tx_bytes,
+                        rx_bytes_per_sec: bridge.rx_bytes_per_sec,
+                        tx_bytes_per_sec: bridge.tx_bytes_per_sec,
+                    }),
+                    daemon: Some(quilt::DaemonProcessMetrics {
+                        pid: sys_metrics.daemon.pid,
+                        open_fds: sys_metrics.daemon.open_fds,
+                        fd_limit: sys_metrics.daemon.fd_limit,
+                        threads: sys_metrics.daemon.threads,
+                        rss_bytes: sys_metrics.daemon.rss_bytes,
+                        child_processes: sys_metrics.daemon.child_processes,
+                    }),
                 })
             } else {
                 None
@@ -1473,9 +2694,86 @@ impl QuiltService for QuiltServiceImpl {
             None
         };
         
+        // DNS cache counters are daemon-wide, so they ride along with system metrics
+        let dns_cache_metrics = if req.include_system {
+            self.network_manager.dns_cache_stats().map(|stats| quilt::DnsCacheMetrics {
+                hits: stats.hits,
+                misses: stats.misses,
+                evictions: stats.evictions,
+                forwarded_queries: stats.forwarded_queries,
+                entries: stats.entries,
+                capacity: stats.capacity,
+            })
+        } else {
+            None
+        };
+        
+        let rate_limits = if req.include_system {
+            grpc::rate_limit::rate_limit_stats().into_iter()
+                .map(|(method, stats)| quilt::RateLimitMetrics {
+                    method,
+                    allowed: stats.allowed,
+                    rejected: stats.rejected,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        
+        // Connection tracking, link drops and flows reflect live state, so skip them for historical queries
+        let historical = req.start_time > 0 && req.end_time > 0;
+        let mut network_flows = Vec::new();
+        let conntrack_metrics = if historical {
+            Vec::new()
+        } else {
+            let mut container_ips = Vec::new();
+            for metric in container_metrics.iter_mut() {
+                if let Ok(allocation) = self.sync_engine.get_network_allocation(&metric.container_id).await {
+                    if let Some(veth) = allocation.veth_host.as_deref() {
+                        let (rx_dropped, tx_dropped) = crate::daemon::net_accounting::veth_drop_counters(veth);
+                        metric.network_rx_dropped = rx_dropped;
+                        metric.network_tx_dropped = tx_dropped;
+                    }
+                    container_ips.push((metric.container_id.clone(), allocation.ip_address));
+                }
+                network_flows.extend(crate::daemon::net_accounting::container_flows(&metric.container_id, NETWORK_TOP_FLOWS)
+                    .into_iter()
+                    .map(|flow| quilt::NetworkFlowMetrics {
+                        container_id: metric.container_id.clone(),
+                        remote_address: flow.remote_address.to_string(),
+                        direction: if flow.egress { "egress" } else { "ingress" }.to_string(),
+                        packets: flow.packets,
+                        bytes: flow.bytes,
+                    }));
+            }
+            let ips: Vec<String> = container_ips.iter().map(|(_, ip)| ip.clone()).collect();
+            match ConntrackStats::collect_for_ips(&ips, CONNTRACK_TOP_ENDPOINTS) {
+                Ok(mut stats) => container_ips.into_iter()
+                    .filter_map(|(container_id, ip)| stats.remove(&ip).map(|s| quilt::ConntrackMetrics {
+                        container_id,
+                        container_ip: s.container_ip,
+                        total_entries: s.total_entries,
+                        tcp_entries: s.tcp_entries,
+                        udp_entries: s.udp_entries,
+                        top_remote_endpoints: s.top_remote_endpoints.into_iter()
+                            .map(|(endpoint, connections)| quilt::ConntrackEndpoint { endpoint, connections })
+                            .collect(),
+                    }))
+                    .collect(),
+                Err(e) => {
+                    ConsoleLogger::debug(&format!("Conntrack metrics unavailable: {}", e));
+                    Vec::new()
+                }
+            }
+        };
+        
         Ok(Response::new(GetMetricsResponse {
             container_metrics,
             system_metrics,
+            dns_cache_metrics,
+            conntrack_metrics,
+            network_flows,
+            rate_limits,
         }))
     }
 
@@ -1489,6 +2787,9 @@ impl QuiltService for QuiltServiceImpl {
         features.insert("storage".to_string(), "sqlite".to_string());
         features.insert("networking".to_string(), "bridge,veth".to_string());
         features.insert("volumes".to_string(), "bind,volume,tmpfs".to_string());
+        let dns_domain = self.network_manager.dns_domain();
+        features.insert("dns_domain".to_string(), dns_domain.cluster_domain.clone());
+        features.insert("dns_search".to_string(), dns_domain.search_list().join(","));
         
         let mut limits = HashMap::new();
         limits.insert("max_containers".to_string(), "1000".to_string());
@@ -1543,6 +2844,27 @@ impl QuiltService for QuiltServiceImpl {
             start_time: self.start_time.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64,
             features: stats_features,
             limits,
+            reserved_name_prefixes: NamePolicy::global().reserved_prefixes.clone(),
+            architecture: daemon::platform::host_architecture().to_string(),
+        }))
+    }
+
+    async fn get_api_capabilities(
+        &self,
+        request: Request<quilt::GetApiCapabilitiesRequest>,
+    ) -> Result<Response<quilt::GetApiCapabilitiesResponse>, Status> {
+        use utils::api_version::API_VERSION;
+        use grpc::api_version::MIN_SUPPORTED_API_VERSION;
+
+        let req = request.into_inner();
+        ConsoleLogger::debug(&format!("🔢 [GRPC] Capabilities requested by client API version {}", req.client_api_version));
+
+        Ok(Response::new(quilt::GetApiCapabilitiesResponse {
+            api_version: API_VERSION,
+            min_supported_api_version: MIN_SUPPORTED_API_VERSION,
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            rpcs: Self::implemented_rpcs(),
+            features: Self::api_features(),
         }))
     }
 
@@ -1570,6 +2892,10 @@ impl QuiltService for QuiltServiceImpl {
             }
         };
         
+        // Label selectors are matched inside the buffer query, so unrelated tenants' events never leave it
+        let label_selector = if req.label_selector.is_empty() { None } else { Some(req.label_selector) };
+        let volume_label_selector = if req.volume_label_selector.is_empty() { None } else { Some(req.volume_label_selector) };
+        
         // Create a stream that polls for new events every 100ms
         let stream = IntervalStream::new(tokio::time::interval(Duration::from_millis(100)))
             .map(move |_| {
@@ -1577,6 +2903,8 @@ impl QuiltService for QuiltServiceImpl {
                     if req.container_ids.is_empty() { None } else { Some(&req.container_ids) },
                     event_types.as_deref(),
                     None,
+                    label_selector.as_ref(),
+                    volume_label_selector.as_ref(),
                 );
                 
                 // Convert to proto events
@@ -1586,6 +2914,7 @@ impl QuiltService for QuiltServiceImpl {
                         container_id: e.container_id,
                         timestamp: e.timestamp,
                         attributes: e.attributes,
+                        labels: e.labels,
                     })
                     .collect();
                 
@@ -1605,25 +2934,19 @@ impl QuiltService for QuiltServiceImpl {
     ) -> Result<Response<quilt::ListActiveMonitorsResponse>, Status> {
         match self.sync_engine.list_active_monitors().await {
             Ok(monitors) => {
-                let proto_monitors = monitors.into_iter().map(|m| quilt::ProcessMonitor {
-                    container_id: m.container_id,
-                    pid: m.pid,
-                    status: m.status.to_string(),
-                    started_at: m.monitor_started_at as u64,
-                    last_check: m.last_check_at.unwrap_or(0) as u64,
-                    check_count: 0, // TODO: Add check count to database schema
-                    error_message: String::new(),
-                }).collect();
+                let proto_monitors = monitors.into_iter().map(|m| Self::process_monitor_info(m)).collect();
 
                 Ok(Response::new(quilt::ListActiveMonitorsResponse {
                     monitors: proto_monitors,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::ListActiveMonitorsResponse {
                 monitors: vec![],
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1637,25 +2960,19 @@ impl QuiltService for QuiltServiceImpl {
         
         match self.sync_engine.get_monitor_status(&req.container_id).await {
             Ok(monitor) => {
-                let proto_monitor = quilt::ProcessMonitor {
-                    container_id: monitor.container_id,
-                    pid: monitor.pid,
-                    status: monitor.status.to_string(),
-                    started_at: monitor.monitor_started_at as u64,
-                    last_check: monitor.last_check_at.unwrap_or(0) as u64,
-                    check_count: 0, // TODO: Add check count to database schema
-                    error_message: String::new(),
-                };
+                let proto_monitor = Self::process_monitor_info(monitor);
 
                 Ok(Response::new(quilt::GetMonitorStatusResponse {
                     monitor: Some(proto_monitor),
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::GetMonitorStatusResponse {
                 monitor: None,
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1668,25 +2985,19 @@ impl QuiltService for QuiltServiceImpl {
         // Same as list_active_monitors for now - could be different in the future
         match self.sync_engine.list_active_monitors().await {
             Ok(monitors) => {
-                let proto_monitors = monitors.into_iter().map(|m| quilt::ProcessMonitor {
-                    container_id: m.container_id,
-                    pid: m.pid,
-                    status: m.status.to_string(),
-                    started_at: m.monitor_started_at as u64,
-                    last_check: m.last_check_at.unwrap_or(0) as u64,
-                    check_count: 0, // TODO: Add check count to database schema
-                    error_message: String::new(),
-                }).collect();
+                let proto_monitors = monitors.into_iter().map(|m| Self::process_monitor_info(m)).collect();
 
                 Ok(Response::new(quilt::ListMonitoringProcessesResponse {
                     processes: proto_monitors,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::ListMonitoringProcessesResponse {
                 processes: vec![],
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1716,12 +3027,14 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(quilt::GetCleanupStatusResponse {
                     tasks: proto_tasks,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::GetCleanupStatusResponse {
                 tasks: vec![],
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1752,12 +3065,14 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(quilt::ListCleanupTasksResponse {
                     tasks: proto_tasks,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::ListCleanupTasksResponse {
                 tasks: vec![],
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1784,12 +3099,14 @@ impl QuiltService for QuiltServiceImpl {
 
                 Ok(Response::new(quilt::GetCleanupTaskStatusResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                     task: Some(proto_task),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::GetCleanupTaskStatusResponse {
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
                 task: None,
             }))
@@ -1818,12 +3135,14 @@ impl QuiltService for QuiltServiceImpl {
                 Ok(Response::new(quilt::ListContainerCleanupTasksResponse {
                     tasks: proto_tasks,
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 }))
             }
             Err(e) => Ok(Response::new(quilt::ListContainerCleanupTasksResponse {
                 tasks: vec![],
                 success: false,
+                error_code: sync_error_code(&e) as i32,
                 error_message: e.to_string(),
             }))
         }
@@ -1866,52 +3185,36 @@ impl QuiltService for QuiltServiceImpl {
         request: Request<quilt::ForceCleanupRequest>,
     ) -> Result<Response<quilt::ForceCleanupResponse>, Status> {
         let req = request.into_inner();
-        let mut all_cleaned_resources = Vec::new();
         
-        // Force cleanup specific container if provided
-        if !req.container_id.is_empty() {
-            match self.sync_engine.cleanup_service.force_cleanup(&req.container_id).await {
-                Ok(cleaned_resources) => {
-                    all_cleaned_resources.extend(cleaned_resources);
-                }
-                Err(e) => return Ok(Response::new(quilt::ForceCleanupResponse {
+        let mut targets = Vec::new();
+        for resource_type in &req.resource_types {
+            match sync::cleanup::CleanupTarget::from_str(resource_type.trim()) {
+                Some(target) => targets.push(target),
+                None => return Ok(Response::new(quilt::ForceCleanupResponse {
                     success: false,
-                    error_message: format!("Container cleanup failed: {}", e),
+                    error_code: ErrorCode::InvalidArgument as i32,
+                    error_message: format!("Unknown resource type '{}' (expected one of: {})", resource_type,
+                        sync::cleanup::CleanupTarget::ALL.iter().map(|t| t.as_str()).collect::<Vec<_>>().join(", ")),
                     cleaned_resources: vec![],
-                }))
-            }
-        }
-        
-        // Run general cleanup tasks
-        let mut cleanup_messages = Vec::new();
-        
-        // Clean up orphaned volumes
-        match self.sync_engine.cleanup_orphaned_volumes().await {
-            Ok(cleaned_volumes) => {
-                if cleaned_volumes > 0 {
-                    cleanup_messages.push(format!("Cleaned {} orphaned volumes", cleaned_volumes));
-                }
+                })),
             }
-            Err(e) => cleanup_messages.push(format!("Volume cleanup failed: {}", e)),
         }
         
-        // Clean up old metrics
-        match self.sync_engine.cleanup_old_metrics(7).await {
-            Ok(cleaned_metrics) => {
-                if cleaned_metrics > 0 {
-                    cleanup_messages.push(format!("Cleaned {} old metric records", cleaned_metrics));
-                }
-            }
-            Err(e) => cleanup_messages.push(format!("Metrics cleanup failed: {}", e)),
+        let container_id = if req.container_id.is_empty() { None } else { Some(req.container_id.as_str()) };
+        match self.sync_engine.force_cleanup(container_id, &targets, req.dry_run).await {
+            Ok(cleaned_resources) => Ok(Response::new(quilt::ForceCleanupResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                cleaned_resources,
+            })),
+            Err(e) => Ok(Response::new(quilt::ForceCleanupResponse {
+                success: false,
+                error_code: ErrorCode::Internal as i32,
+                error_message: format!("Cleanup failed: {}", e),
+                cleaned_resources: vec![],
+            })),
         }
-        
-        all_cleaned_resources.extend(cleanup_messages);
-        
-        Ok(Response::new(quilt::ForceCleanupResponse {
-            success: true,
-            error_message: String::new(),
-            cleaned_resources: all_cleaned_resources,
-        }))
     }
 
     async fn get_container_network(
@@ -1919,6 +3222,11 @@ impl QuiltService for QuiltServiceImpl {
         request: Request<quilt::GetContainerNetworkRequest>,
     ) -> Result<Response<quilt::GetContainerNetworkResponse>, Status> {
         let req = request.into_inner();
+        let attachments: Vec<_> = self.sync_engine.get_network_attachments(&req.container_id).await
+            .unwrap_or_default()
+            .into_iter()
+            .map(Self::network_attachment_info)
+            .collect();
         
         // Get comprehensive network status from sync engine
         match self.sync_engine.get_network_allocation(&req.container_id).await {
@@ -1934,8 +3242,10 @@ impl QuiltService for QuiltServiceImpl {
                 
                 Ok(Response::new(quilt::GetContainerNetworkResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                     network_config: Some(proto_config),
+                    attachments,
                 }))
             }
             Err(_) => {
@@ -1953,14 +3263,18 @@ impl QuiltService for QuiltServiceImpl {
                         
                         Ok(Response::new(quilt::GetContainerNetworkResponse {
                             success: true,
+                            error_code: ErrorCode::Unspecified as i32,
                             error_message: String::new(),
                             network_config: Some(proto_config),
+                            attachments,
                         }))
                     }
                     None => Ok(Response::new(quilt::GetContainerNetworkResponse {
                         success: false,
+                        error_code: ErrorCode::NotFound as i32,
                         error_message: format!("No network configuration found for container {}", req.container_id),
                         network_config: None,
+                        attachments,
                     }))
                 }
             }
@@ -1989,16 +3303,19 @@ impl QuiltService for QuiltServiceImpl {
             match self.runtime.set_container_network(&req.container_id, network_config) {
                 Ok(_) => Ok(Response::new(quilt::SetContainerNetworkResponse {
                     success: true,
+                    error_code: ErrorCode::Unspecified as i32,
                     error_message: String::new(),
                 })),
                 Err(e) => Ok(Response::new(quilt::SetContainerNetworkResponse {
                     success: false,
+                    error_code: error_code(&e),
                     error_message: e,
                 }))
             }
         } else {
             Ok(Response::new(quilt::SetContainerNetworkResponse {
                 success: false,
+                error_code: ErrorCode::InvalidArgument as i32,
                 error_message: "Network configuration is required".to_string(),
             }))
         }
@@ -2011,78 +3328,1362 @@ impl QuiltService for QuiltServiceImpl {
         let req = request.into_inner();
         
         // Use the unused runtime method to setup container network post-start
-        match self.runtime.setup_container_network_post_start(&req.container_id, &self.network_manager) {
+        let setup_result = self.runtime.setup_container_network_post_start(&req.container_id, &self.network_manager);
+        let verify_result = match (setup_result, self.runtime.get_container_network(&req.container_id)) {
+            (Ok(()), Some(network_config)) => {
+                match self.runtime.get_container_info(&req.container_id).and_then(|info| info.pid) {
+                    Some(pid) => self.network_manager.verify_container_network(&network_config, pid.as_raw()).await,
+                    None => Err(format!("Container {} is not running", req.container_id)),
+                }
+            }
+            (Ok(()), None) => Err(format!("No network config for container {}", req.container_id)),
+            (Err(e), _) => Err(e),
+        };
+        match verify_result {
             Ok(_) => Ok(Response::new(quilt::SetupContainerNetworkPostStartResponse {
                 success: true,
+                error_code: ErrorCode::Unspecified as i32,
                 error_message: String::new(),
             })),
             Err(e) => Ok(Response::new(quilt::SetupContainerNetworkPostStartResponse {
                 success: false,
+                error_code: error_code(&e),
                 error_message: e,
             }))
         }
     }
 
-    async fn list_dns_entries(
+    async fn connect_container_to_network(
         &self,
-        _request: Request<quilt::ListDnsEntriesRequest>,
-    ) -> Result<Response<quilt::ListDnsEntriesResponse>, Status> {
-        // Access network manager DNS entries directly
-        match self.network_manager.list_dns_entries() {
-            Ok(entries) => {
-                let proto_entries = entries.into_iter().map(|e| quilt::DnsEntry {
-                    container_id: e.container_id,
-                    container_name: e.container_name,
-                    ip_address: e.ip_address.to_string(),
-                }).collect();
+        request: Request<quilt::ConnectContainerToNetworkRequest>,
+    ) -> Result<Response<quilt::ConnectContainerToNetworkResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
 
-                Ok(Response::new(quilt::ListDnsEntriesResponse {
-                    entries: proto_entries,
-                    success: true,
-                    error_message: String::new(),
-                }))
-            }
-            Err(e) => Ok(Response::new(quilt::ListDnsEntriesResponse {
-                entries: vec![],
+        let attachment = self.engine.connect_container_to_network(&container_id, &req.network, &actor).await
+            .map_err(engine_status)?;
+        ConsoleLogger::info(&format!("🔗 [NETWORKS] {} connected {} to {} at {}", actor, container_id, attachment.network, attachment.ip_address));
+        Ok(Response::new(quilt::ConnectContainerToNetworkResponse {
+            container_id,
+            attachment: Some(Self::network_attachment_info(attachment)),
+        }))
+    }
+
+    async fn disconnect_container_from_network(
+        &self,
+        request: Request<quilt::DisconnectContainerFromNetworkRequest>,
+    ) -> Result<Response<quilt::DisconnectContainerFromNetworkResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        self.engine.disconnect_container_from_network(&container_id, &req.network, &actor).await
+            .map_err(engine_status)?;
+        ConsoleLogger::info(&format!("🔗 [NETWORKS] {} disconnected {} from {}", actor, container_id, req.network));
+        Ok(Response::new(quilt::DisconnectContainerFromNetworkResponse { container_id }))
+    }
+
+    async fn mirror_container_traffic(
+        &self,
+        request: Request<quilt::MirrorContainerTrafficRequest>,
+    ) -> Result<Response<quilt::MirrorContainerTrafficResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        if req.observer.is_empty() {
+            return Err(Status::invalid_argument("observer is required"));
+        }
+        let observer_id = match self.sync_engine.get_container_by_name(&req.observer).await {
+            Ok(id) => id,
+            Err(_) => self.resolve_icc_container(&req.observer, "").await?,
+        };
+        let direction = req.direction.parse::<engine::MirrorDirection>().map_err(Status::invalid_argument)?;
+
+        let mirror = self.engine.mirror_container_traffic(&container_id, &observer_id, direction, &actor).await
+            .map_err(engine_status)?;
+        ConsoleLogger::info(&format!("🪞 [MIRROR] {} mirrored {} traffic of {} to {}", actor, mirror.direction, container_id, observer_id));
+        Ok(Response::new(quilt::MirrorContainerTrafficResponse { mirror: Some(Self::traffic_mirror_info(mirror)) }))
+    }
+
+    async fn stop_traffic_mirror(
+        &self,
+        request: Request<quilt::StopTrafficMirrorRequest>,
+    ) -> Result<Response<quilt::StopTrafficMirrorResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        let mirror = self.engine.stop_traffic_mirror(&container_id, &actor).await
+            .map_err(engine_status)?;
+        ConsoleLogger::info(&format!("🪞 [MIRROR] {} stopped mirroring {} to {}", actor, container_id, mirror.observer_container_id));
+        Ok(Response::new(quilt::StopTrafficMirrorResponse { mirror: Some(Self::traffic_mirror_info(mirror)) }))
+    }
+
+    async fn list_traffic_mirrors(
+        &self,
+        _request: Request<quilt::ListTrafficMirrorsRequest>,
+    ) -> Result<Response<quilt::ListTrafficMirrorsResponse>, Status> {
+        let mirrors = self.engine.list_traffic_mirrors().await.map_err(engine_status)?;
+        Ok(Response::new(quilt::ListTrafficMirrorsResponse {
+            mirrors: mirrors.into_iter().map(Self::traffic_mirror_info).collect(),
+        }))
+    }
+
+    async fn register_icc_service(
+        &self,
+        request: Request<quilt::RegisterIccServiceRequest>,
+    ) -> Result<Response<quilt::RegisterIccServiceResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        match self.message_broker.register_service(&req.service, &container_id, req.queue_depth as usize) {
+            Ok(()) => Ok(Response::new(quilt::RegisterIccServiceResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::RegisterIccServiceResponse {
                 success: false,
+                error_code: error_code(&e),
                 error_message: e,
-            }))
+            })),
         }
     }
 
-    async fn comprehensive_network_cleanup(
+    async fn unregister_icc_service(
         &self,
-        _request: Request<quilt::ComprehensiveNetworkCleanupRequest>,
-    ) -> Result<Response<quilt::ComprehensiveNetworkCleanupResponse>, Status> {
-        match self.sync_engine.comprehensive_network_cleanup().await {
-            Ok(cleaned_resources) => Ok(Response::new(quilt::ComprehensiveNetworkCleanupResponse {
-                cleaned_resources,
+        request: Request<quilt::UnregisterIccServiceRequest>,
+    ) -> Result<Response<quilt::UnregisterIccServiceResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        match self.message_broker.unregister_service(&req.service, &container_id) {
+            Ok(()) => Ok(Response::new(quilt::UnregisterIccServiceResponse {
                 success: true,
+                error_code: ErrorCode::Unspecified as i32,
                 error_message: String::new(),
             })),
-            Err(e) => Ok(Response::new(quilt::ComprehensiveNetworkCleanupResponse {
-                cleaned_resources: vec![],
+            Err(e) => Ok(Response::new(quilt::UnregisterIccServiceResponse {
                 success: false,
-                error_message: e.to_string(),
-            }))
+                error_code: error_code(&e),
+                error_message: e,
+            })),
         }
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger
-    // Logger initialization not needed - ConsoleLogger is used directly
-    
-    // ✅ SYNC ENGINE INITIALIZATION
-    let service = QuiltServiceImpl::new().await
-        .map_err(|e| format!("Failed to initialize sync engine: {}", e))?;
-    
-    // Bind to all interfaces so containers can access the gRPC server
-    let addr: std::net::SocketAddr = "0.0.0.0:50051".parse()?;
+    async fn list_icc_services(
+        &self,
+        _request: Request<quilt::ListIccServicesRequest>,
+    ) -> Result<Response<quilt::ListIccServicesResponse>, Status> {
+        let services = self.message_broker.list_services().into_iter().map(|s| quilt::IccService {
+            service: s.service,
+            container_id: s.owner,
+            queued_requests: s.queued_requests as u32,
+            queue_depth: s.queue_depth as u32,
+        }).collect();
 
-    ConsoleLogger::server_starting(&addr.to_string());
-    ConsoleLogger::success("🚀 Quilt server running with SQLite sync engine - non-blocking operations enabled");
+        Ok(Response::new(quilt::ListIccServicesResponse { services }))
+    }
+
+    async fn call_icc_service(
+        &self,
+        request: Request<quilt::CallIccServiceRequest>,
+    ) -> Result<Response<quilt::CallIccServiceResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        let timeout = if req.timeout_ms == 0 {
+            icc::messaging::DEFAULT_REQUEST_TIMEOUT
+        } else {
+            Duration::from_millis(req.timeout_ms as u64)
+        };
+
+        match self.message_broker.send_request(&container_id, &req.service, req.payload, timeout).await {
+            Ok(payload) => Ok(Response::new(quilt::CallIccServiceResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                payload,
+            })),
+            Err(e) => Ok(Response::new(quilt::CallIccServiceResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                payload: Vec::new(),
+            })),
+        }
+    }
+
+    async fn poll_icc_service(
+        &self,
+        request: Request<quilt::PollIccServiceRequest>,
+    ) -> Result<Response<quilt::PollIccServiceResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        let wait = Duration::from_millis(req.wait_ms as u64);
+
+        match self.message_broker.receive_request(&req.service, &container_id, wait).await {
+            Ok(Some(service_request)) => Ok(Response::new(quilt::PollIccServiceResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                has_request: true,
+                request_id: service_request.request_id,
+                from_container: service_request.from_container,
+                payload: service_request.payload,
+            })),
+            Ok(None) => Ok(Response::new(quilt::PollIccServiceResponse {
+                success: true,
+                ..Default::default()
+            })),
+            Err(e) => Ok(Response::new(quilt::PollIccServiceResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                ..Default::default()
+            })),
+        }
+    }
+
+    async fn reply_icc_service(
+        &self,
+        request: Request<quilt::ReplyIccServiceRequest>,
+    ) -> Result<Response<quilt::ReplyIccServiceResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        let result = if req.error.is_empty() { Ok(req.payload) } else { Err(req.error) };
+
+        match self.message_broker.send_reply(&req.request_id, &container_id, result) {
+            Ok(()) => Ok(Response::new(quilt::ReplyIccServiceResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::ReplyIccServiceResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn test_connectivity(
+        &self,
+        request: Request<quilt::TestConnectivityRequest>,
+    ) -> Result<Response<quilt::TestConnectivityResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        if req.target.is_empty() {
+            return Err(Status::invalid_argument("target is required"));
+        }
+        let port = match req.port {
+            0 => None,
+            port => Some(u16::try_from(port).map_err(|_| Status::invalid_argument(format!("Invalid port {}", port)))?),
+        };
+
+        let probe = async {
+            let status = self.sync_engine.get_container_status(&container_id).await
+                .map_err(|e| format!("Container not found: {}", e))?;
+            let pid = match (status.state, status.pid) {
+                (ContainerState::Running, Some(pid)) => pid as i32,
+                (state, _) => return Err(format!("Container {} is not running (state: {:?})", container_id, state)),
+            };
+
+            // A container target is probed at its bridge address; anything else is resolved on the host
+            let target_id = match self.sync_engine.get_container_by_name(&req.target).await {
+                Ok(id) => Some(id),
+                Err(_) => self.sync_engine.get_container_status(&req.target).await.ok().map(|status| status.id),
+            };
+            let address = match target_id {
+                Some(target_id) => self.sync_engine.get_network_allocation(&target_id).await
+                    .map_err(|_| format!("Target container {} has no network", target_id))?
+                    .ip_address.parse::<std::net::IpAddr>()
+                    .map_err(|e| format!("Invalid IP address for {}: {}", target_id, e))?,
+                None => icc::network::ConnectivityTest::resolve_target(&req.target).await?,
+            };
+
+            let options = icc::network::ProbeOptions {
+                count: req.count,
+                timeout_seconds: req.timeout_seconds,
+                port,
+            };
+            self.network_manager.test_connectivity(&container_id, pid, address, options).await
+        };
+
+        match probe.await {
+            Ok(report) => Ok(Response::new(quilt::TestConnectivityResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                packet_loss_percent: report.loss_percent(),
+                protocol: report.protocol,
+                target_address: report.address,
+                packets_sent: report.sent,
+                packets_received: report.received,
+                min_latency_ms: report.min_ms,
+                avg_latency_ms: report.avg_ms,
+                max_latency_ms: report.max_ms,
+            })),
+            Err(e) => Ok(Response::new(quilt::TestConnectivityResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                ..Default::default()
+            })),
+        }
+    }
+
+    async fn list_dns_entries(
+        &self,
+        _request: Request<quilt::ListDnsEntriesRequest>,
+    ) -> Result<Response<quilt::ListDnsEntriesResponse>, Status> {
+        // Access network manager DNS entries directly
+        match self.network_manager.list_dns_entries() {
+            Ok(entries) => {
+                let proto_entries = entries.into_iter().map(|e| quilt::DnsEntry {
+                    container_id: e.container_id,
+                    container_name: e.container_name,
+                    ip_address: e.ip_address.to_string(),
+                    aliases: e.aliases,
+                    wildcard: e.wildcard,
+                }).collect();
+
+                Ok(Response::new(quilt::ListDnsEntriesResponse {
+                    entries: proto_entries,
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::ListDnsEntriesResponse {
+                entries: vec![],
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            }))
+        }
+    }
+
+    async fn flush_dns_cache(
+        &self,
+        _request: Request<quilt::FlushDnsCacheRequest>,
+    ) -> Result<Response<quilt::FlushDnsCacheResponse>, Status> {
+        match self.network_manager.flush_dns_cache() {
+            Ok(flushed) => {
+                ConsoleLogger::info(&format!("🧹 [DNS] Flushed {} cached DNS answers", flushed));
+                Ok(Response::new(quilt::FlushDnsCacheResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                    entries_flushed: flushed as u64,
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::FlushDnsCacheResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                entries_flushed: 0,
+            }))
+        }
+    }
+
+    async fn add_dns_alias(
+        &self,
+        request: Request<quilt::AddDnsAliasRequest>,
+    ) -> Result<Response<quilt::AddDnsAliasResponse>, Status> {
+        let req = request.into_inner();
+        InputValidator::validate_dns_alias(&req.alias).map_err(Status::invalid_argument)?;
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        // Container names resolve too, so an alias may not shadow another container's name
+        if let Ok(owner) = self.sync_engine.get_container_by_name(&req.alias).await {
+            if owner != container_id {
+                return Err(error_status(ErrorCode::Conflict,
+                    format!("DNS name '{}' is already in use by container {}", req.alias, owner)));
+            }
+        }
+
+        match self.network_manager.add_dns_alias(&container_id, &req.alias) {
+            Ok(()) => Ok(Response::new(quilt::AddDnsAliasResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::AddDnsAliasResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn remove_dns_alias(
+        &self,
+        request: Request<quilt::RemoveDnsAliasRequest>,
+    ) -> Result<Response<quilt::RemoveDnsAliasResponse>, Status> {
+        let req = request.into_inner();
+        match self.network_manager.remove_dns_alias(&req.alias) {
+            Ok(container_id) => Ok(Response::new(quilt::RemoveDnsAliasResponse {
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+                container_id,
+            })),
+            Err(e) => Ok(Response::new(quilt::RemoveDnsAliasResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                container_id: String::new(),
+            })),
+        }
+    }
+
+    async fn set_dns_record(
+        &self,
+        request: Request<quilt::SetDnsRecordRequest>,
+    ) -> Result<Response<quilt::SetDnsRecordResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        match self.engine.set_dns_record(&req.name, &req.view, &req.addresses).await {
+            Ok(record) => {
+                ConsoleLogger::info(&format!("🌐 [DNS] {} set {} ({}) to {}", actor, record.name, record.view, record.addresses.join(", ")));
+                Ok(Response::new(quilt::SetDnsRecordResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                    record: Some(Self::dns_record_info(record)),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::SetDnsRecordResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                record: None,
+            })),
+        }
+    }
+
+    async fn remove_dns_record(
+        &self,
+        request: Request<quilt::RemoveDnsRecordRequest>,
+    ) -> Result<Response<quilt::RemoveDnsRecordResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        match self.engine.remove_dns_record(&req.name, &req.view).await {
+            Ok(()) => {
+                ConsoleLogger::info(&format!("🌐 [DNS] {} removed {} ({})", actor, req.name,
+                    if req.view.is_empty() { "all" } else { req.view.as_str() }));
+                Ok(Response::new(quilt::RemoveDnsRecordResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::RemoveDnsRecordResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn list_dns_records(
+        &self,
+        _request: Request<quilt::ListDnsRecordsRequest>,
+    ) -> Result<Response<quilt::ListDnsRecordsResponse>, Status> {
+        match self.engine.list_dns_records().await {
+            Ok(records) => Ok(Response::new(quilt::ListDnsRecordsResponse {
+                records: records.into_iter().map(Self::dns_record_info).collect(),
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::ListDnsRecordsResponse {
+                records: vec![],
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn create_virtual_service(
+        &self,
+        request: Request<quilt::CreateVirtualServiceRequest>,
+    ) -> Result<Response<quilt::CreateVirtualServiceResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        match self.engine.create_virtual_service(&req.name, &req.selector).await {
+            Ok((service, backends)) => {
+                ConsoleLogger::info(&format!("⚖️ [VSERVICE] {} created {} with {} backend(s)", actor, service.name, backends.len()));
+                Ok(Response::new(quilt::CreateVirtualServiceResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                    service: Some(Self::virtual_service_info(service, backends)),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::CreateVirtualServiceResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                service: None,
+            })),
+        }
+    }
+
+    async fn delete_virtual_service(
+        &self,
+        request: Request<quilt::DeleteVirtualServiceRequest>,
+    ) -> Result<Response<quilt::DeleteVirtualServiceResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        match self.engine.delete_virtual_service(&req.name).await {
+            Ok(()) => {
+                ConsoleLogger::info(&format!("⚖️ [VSERVICE] {} deleted {}", actor, req.name));
+                Ok(Response::new(quilt::DeleteVirtualServiceResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::DeleteVirtualServiceResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn list_virtual_services(
+        &self,
+        _request: Request<quilt::ListVirtualServicesRequest>,
+    ) -> Result<Response<quilt::ListVirtualServicesResponse>, Status> {
+        match self.engine.list_virtual_services().await {
+            Ok(services) => Ok(Response::new(quilt::ListVirtualServicesResponse {
+                services: services.into_iter()
+                    .map(|(service, backends)| Self::virtual_service_info(service, backends))
+                    .collect(),
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::ListVirtualServicesResponse {
+                services: vec![],
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+            })),
+        }
+    }
+
+    async fn comprehensive_network_cleanup(
+        &self,
+        _request: Request<quilt::ComprehensiveNetworkCleanupRequest>,
+    ) -> Result<Response<quilt::ComprehensiveNetworkCleanupResponse>, Status> {
+        match self.sync_engine.comprehensive_network_cleanup().await {
+            Ok(cleaned_resources) => Ok(Response::new(quilt::ComprehensiveNetworkCleanupResponse {
+                cleaned_resources,
+                success: true,
+                error_code: ErrorCode::Unspecified as i32,
+                error_message: String::new(),
+            })),
+            Err(e) => Ok(Response::new(quilt::ComprehensiveNetworkCleanupResponse {
+                cleaned_resources: vec![],
+                success: false,
+                error_code: sync_error_code(&e) as i32,
+                error_message: e.to_string(),
+            }))
+        }
+    }
+
+    async fn capture_traffic(
+        &self,
+        request: Request<quilt::CaptureTrafficRequest>,
+    ) -> Result<Response<Self::CaptureTrafficStream>, Status> {
+        use tokio_stream::wrappers::ReceiverStream;
+        use futures::stream::StreamExt;
+
+        let req = request.into_inner();
+
+        // Resolve container name to ID if needed
+        let container_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => id,
+                Err(_) => return Err(Status::not_found(format!("Container with name '{}' not found", req.container_name))),
+            }
+        } else {
+            req.container_id.clone()
+        };
+
+        let allocation = self.sync_engine.get_network_allocation(&container_id).await
+            .map_err(|_| Status::failed_precondition(format!("Container {} has no network", container_id)))?;
+        let veth_host_name = match (allocation.setup_completed, allocation.veth_host) {
+            (true, Some(veth)) => veth,
+            _ => return Err(Status::failed_precondition(format!("Network setup for {} has not completed", container_id))),
+        };
+
+        let options = icc::network::CaptureOptions {
+            duration_seconds: req.duration_seconds,
+            max_packets: req.max_packets,
+            snaplen: req.snaplen,
+            filter: req.filter,
+        };
+        let receiver = self.network_manager.capture_container_traffic(&container_id, &veth_host_name, options)
+            .map_err(Status::invalid_argument)?;
+
+        let stream = ReceiverStream::new(receiver).map(|event| {
+            Ok(match event {
+                icc::network::CaptureEvent::Data(data) => quilt::CaptureTrafficChunk {
+                    data,
+                    ..Default::default()
+                },
+                icc::network::CaptureEvent::Finished { packets_captured } => quilt::CaptureTrafficChunk {
+                    done: true,
+                    packets_captured,
+                    ..Default::default()
+                },
+                icc::network::CaptureEvent::Failed(error_message) => quilt::CaptureTrafficChunk {
+                    done: true,
+                    error_code: error_code(&error_message),
+                    error_message,
+                    ..Default::default()
+                },
+            })
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type CaptureTrafficStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<quilt::CaptureTrafficChunk, Status>> + Send>>;
+
+    async fn collect_diagnostics(
+        &self,
+        request: Request<quilt::CollectDiagnosticsRequest>,
+    ) -> Result<Response<Self::CollectDiagnosticsStream>, Status> {
+        let req = request.into_inner();
+
+        // Resolve container name to ID if a container was requested
+        let container_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => Some(id),
+                Err(_) => return Err(Status::not_found(format!("Container with name '{}' not found", req.container_name))),
+            }
+        } else if !req.container_id.is_empty() {
+            Some(req.container_id)
+        } else {
+            None
+        };
+
+        let archive = self.engine.collect_diagnostics(container_id.as_deref(), req.log_lines).await
+            .map_err(engine_status)?;
+
+        // Stay well below the default 4 MiB message limit
+        const CHUNK_SIZE: usize = 1024 * 1024;
+        let total_bytes = archive.len() as u64;
+        let mut chunks: Vec<quilt::DiagnosticsChunk> = archive.chunks(CHUNK_SIZE)
+            .map(|data| quilt::DiagnosticsChunk { data: data.to_vec(), ..Default::default() })
+            .collect();
+        chunks.push(quilt::DiagnosticsChunk { done: true, total_bytes, ..Default::default() });
+
+        Ok(Response::new(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok)))))
+    }
+
+    type CollectDiagnosticsStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<quilt::DiagnosticsChunk, Status>> + Send>>;
+
+    async fn clone_container(
+        &self,
+        request: Request<quilt::CloneContainerRequest>,
+    ) -> Result<Response<quilt::CloneContainerResponse>, Status> {
+        let actor = request_actor(&request);
+        let token = request_token(&request);
+        let req = request.into_inner();
+
+        let source_id = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => id,
+                Err(_) => return Err(Status::not_found(format!("Container with name '{}' not found", req.container_name))),
+            }
+        } else if !req.container_id.is_empty() {
+            req.container_id
+        } else {
+            return Err(Status::invalid_argument("container_id or container_name is required"));
+        };
+        if req.labels.keys().any(|key| key.trim().is_empty()) {
+            return Err(Status::invalid_argument("Label keys must not be empty"));
+        }
+        if !req.name.is_empty() {
+            InputValidator::validate_container_name(&req.name).map_err(Status::invalid_argument)?;
+            NamePolicy::global().check(&req.name, token.as_deref())
+                .map_err(|e| error_status(ErrorCode::PermissionDenied, e))?;
+        }
+
+        let options = engine::CloneOptions {
+            name: if req.name.is_empty() { None } else { Some(req.name) },
+            snapshot_rootfs: req.snapshot_rootfs,
+            snapshot_volumes: req.snapshot_volumes,
+            labels: req.labels,
+            ..Default::default()
+        };
+        let clone_id = self.engine.clone_container(&source_id, options, &actor).await
+            .map_err(engine_status)?;
+
+        let name = self.sync_engine.get_container_status(&clone_id).await
+            .ok()
+            .and_then(|status| status.name)
+            .unwrap_or_default();
+
+        if req.start {
+            let engine = self.engine.clone();
+            let start_id = clone_id.clone();
+            tokio::spawn(async move {
+                let _ = engine.start_container(&start_id, None).await;
+            });
+        }
+
+        Ok(Response::new(quilt::CloneContainerResponse { container_id: clone_id, name }))
+    }
+
+    async fn scale_container(
+        &self,
+        request: Request<quilt::ScaleContainerRequest>,
+    ) -> Result<Response<quilt::ScaleContainerResponse>, Status> {
+        let actor = request_actor(&request);
+        let token = request_token(&request);
+        let req = request.into_inner();
+        if req.target.is_empty() {
+            return Err(Status::invalid_argument("target is required"));
+        }
+
+        let template = self.engine.resolve_scale_template(&req.target).await
+            .map_err(engine_status)?;
+        NamePolicy::global().check(&template.replica_name(1), token.as_deref())
+            .map_err(|e| error_status(ErrorCode::PermissionDenied, e))?;
+
+        let report = self.engine.scale(&template, req.replicas, &actor).await
+            .map_err(engine_status)?;
+
+        for replica_id in &report.created {
+            let engine = self.engine.clone();
+            let start_id = replica_id.clone();
+            tokio::spawn(async move {
+                let _ = engine.start_container(&start_id, None).await;
+            });
+        }
+
+        Ok(Response::new(quilt::ScaleContainerResponse {
+            template_id: template.container_id,
+            created: report.created,
+            removed: report.removed,
+            replicas: report.replicas,
+        }))
+    }
+
+    async fn replace_container(
+        &self,
+        request: Request<quilt::ReplaceContainerRequest>,
+    ) -> Result<Response<quilt::ReplaceContainerResponse>, Status> {
+        let deadline = request_deadline(&request);
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        let image_path = self.engine.resolve_image(&req.image_path).await.map_err(engine_status)?;
+        if !image_path.is_empty() {
+            daemon::docker_archive::check_image(&image_path).map_err(engine_status)?;
+            self.engine.admit_image(&image_path).await.map_err(engine_status)?;
+        }
+        if req.environment.keys().chain(req.labels.keys()).any(|key| key.trim().is_empty()) {
+            return Err(Status::invalid_argument("Environment and label keys must not be empty"));
+        }
+        let timeout = if req.timeout_seconds > 0 {
+            Duration::from_secs(req.timeout_seconds as u64)
+        } else {
+            DEFAULT_STARTUP_TIMEOUT
+        };
+
+        let options = engine::DeployOptions {
+            image_path: Some(image_path).filter(|path| !path.is_empty()),
+            command: Some(req.command.join(" ")).filter(|command| !command.is_empty()),
+            environment: req.environment,
+            labels: req.labels,
+            timeout: Some(bounded_by_deadline(timeout, deadline)),
+        };
+        let report = self.engine.replace_container(&container_id, options, &actor).await
+            .map_err(engine_status)?;
+
+        Ok(Response::new(quilt::ReplaceContainerResponse {
+            old_container_id: report.old_container_id,
+            container_id: report.container_id,
+            name: report.name,
+        }))
+    }
+
+    async fn reconcile_orphans(
+        &self,
+        request: Request<quilt::ReconcileOrphansRequest>,
+    ) -> Result<Response<quilt::ReconcileOrphansResponse>, Status> {
+        let req = request.into_inner();
+        let report = self.sync_engine.reconcile_orphans(req.dry_run).await
+            .map_err(|e| Status::internal(format!("Orphan reconciliation failed: {}", e)))?;
+
+        ConsoleLogger::info(&format!("🧹 [ORPHANS] {} orphaned resource(s){}", report.resources.len(),
+            if report.dry_run { " (dry run)" } else { "" }));
+
+        Ok(Response::new(quilt::ReconcileOrphansResponse {
+            dry_run: report.dry_run,
+            resources: report.resources.into_iter().map(|orphan| quilt::OrphanedResource {
+                kind: orphan.kind.as_str().to_string(),
+                container_id: orphan.container_id,
+                resource: orphan.resource,
+                detail: orphan.detail,
+                cleanable: orphan.cleanable,
+                task_ids: orphan.task_ids,
+            }).collect(),
+        }))
+    }
+
+    async fn set_network_policy(
+        &self,
+        request: Request<quilt::SetNetworkPolicyRequest>,
+    ) -> Result<Response<quilt::SetNetworkPolicyResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+
+        let policy = if req.clear {
+            None
+        } else {
+            let policy = req.policy.ok_or_else(|| Status::invalid_argument("policy is required unless clear is set"))?;
+            let policy = Self::network_policy_from_proto(policy).map_err(Status::invalid_argument)?;
+            policy.validate().map_err(Status::invalid_argument)?;
+            Some(policy)
+        };
+        let enforced = self.engine.set_network_policy(&container_id, policy, &actor).await
+            .map_err(engine_status)?;
+
+        ConsoleLogger::info(&format!("🛡️ [NET-POLICY] Policy for {} {}", container_id,
+            if req.clear { "cleared" } else if enforced { "enforced" } else { "stored" }));
+        Ok(Response::new(quilt::SetNetworkPolicyResponse { enforced, container_id }))
+    }
+
+    async fn get_network_policy(
+        &self,
+        request: Request<quilt::GetNetworkPolicyRequest>,
+    ) -> Result<Response<quilt::GetNetworkPolicyResponse>, Status> {
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        let policy = self.sync_engine.get_network_policy(&container_id).await
+            .map_err(|e| Status::internal(format!("Failed to load network policy: {}", e)))?;
+
+        Ok(Response::new(quilt::GetNetworkPolicyResponse {
+            has_policy: policy.is_some(),
+            policy: policy.as_ref().map(Self::network_policy_to_proto),
+            enforced: daemon::net_policy::is_enforced(&container_id),
+        }))
+    }
+
+    async fn trace_syscalls(
+        &self,
+        request: Request<quilt::TraceSyscallsRequest>,
+    ) -> Result<Response<Self::TraceSyscallsStream>, Status> {
+        use tokio_stream::wrappers::ReceiverStream;
+        use futures::stream::StreamExt;
+
+        let req = request.into_inner();
+        let container_id = self.resolve_icc_container(&req.container_id, &req.container_name).await?;
+        let status = self.sync_engine.get_container_status(&container_id).await
+            .map_err(|_| Status::not_found(format!("Container {} not found", container_id)))?;
+        let pid = match (status.state, status.pid) {
+            (ContainerState::Running, Some(pid)) => pid as i32,
+            _ => return Err(Status::failed_precondition(format!("Container {} is not running", container_id))),
+        };
+
+        let options = daemon::syscall_trace::TraceOptions {
+            duration_seconds: req.duration_seconds,
+            summary_interval_ms: req.summary_interval_ms,
+        };
+        let receiver = daemon::syscall_trace::SyscallTracer::start(pid, options)
+            .map_err(Status::invalid_argument)?;
+        ConsoleLogger::info(&format!("🔎 [TRACE] Tracing syscalls of {} (PID {})", container_id, pid));
+
+        let update = |summary: daemon::syscall_trace::TraceSummary, done: bool| quilt::SyscallTraceUpdate {
+            elapsed_ms: summary.elapsed_ms,
+            method: "ptrace".to_string(),
+            arch: daemon::syscall_table::seccomp_arch().to_string(),
+            threads_traced: summary.threads_traced,
+            total_syscalls: summary.total_syscalls,
+            syscalls: summary.syscalls.into_iter().map(|syscall| quilt::SyscallCount {
+                name: syscall.name,
+                count: syscall.count,
+                errors: syscall.errors,
+            }).collect(),
+            new_syscalls: summary.new_syscalls,
+            done,
+            error_code: ErrorCode::Unspecified as i32,
+            error_message: String::new(),
+        };
+        let stream = ReceiverStream::new(receiver).map(move |event| {
+            Ok(match event {
+                daemon::syscall_trace::TraceEvent::Summary(summary) => update(summary, false),
+                daemon::syscall_trace::TraceEvent::Finished(summary) => update(summary, true),
+                daemon::syscall_trace::TraceEvent::Failed(error_message) => quilt::SyscallTraceUpdate {
+                    method: "ptrace".to_string(),
+                    done: true,
+                    error_code: error_code(&error_message),
+                    error_message,
+                    ..Default::default()
+                },
+            })
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type TraceSyscallsStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<quilt::SyscallTraceUpdate, Status>> + Send>>;
+
+    async fn list_containers(
+        &self,
+        request: Request<quilt::ListContainersRequest>,
+    ) -> Result<Response<quilt::ListContainersResponse>, Status> {
+        let req = request.into_inner();
+        let state_filter = if req.running_only { Some(ContainerState::Running) } else { None };
+        let containers = self.sync_engine.list_containers(state_filter).await
+            .map_err(|e| Status::internal(format!("Failed to list containers: {}", e)))?;
+        let mut labels = self.sync_engine.list_container_labels().await
+            .map_err(|e| Status::internal(format!("Failed to load container labels: {}", e)))?;
+        let priority_classes = self.sync_engine.list_priority_classes().await
+            .map_err(|e| Status::internal(format!("Failed to load priority classes: {}", e)))?;
+
+        let containers = containers.into_iter()
+            .filter_map(|container| {
+                let container_labels = labels.remove(&container.id).unwrap_or_default();
+                if !req.labels.iter().all(|(key, value)| container_labels.get(key) == Some(value)) {
+                    return None;
+                }
+                Some(quilt::ContainerSummary {
+                    status: Self::grpc_container_status(&container.state) as i32,
+                    name: container.name.unwrap_or_default(),
+                    pid: container.pid.unwrap_or(0) as i32,
+                    exit_code: container.exit_code.unwrap_or(0) as i32,
+                    created_at: container.created_at as u64,
+                    started_at: container.started_at.unwrap_or(0) as u64,
+                    exited_at: container.exited_at.unwrap_or(0) as u64,
+                    ip_address: container.ip_address.unwrap_or_default(),
+                    labels: container_labels,
+                    priority_class: priority_classes.get(&container.id).copied().unwrap_or_default().as_str().to_string(),
+                    container_id: container.id,
+                })
+            })
+            .collect();
+
+        Ok(Response::new(quilt::ListContainersResponse { containers }))
+    }
+
+    async fn register_plugin(
+        &self,
+        request: Request<quilt::RegisterPluginRequest>,
+    ) -> Result<Response<quilt::RegisterPluginResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        engine::plugins::validate_plugin_name(&req.name).map_err(Status::invalid_argument)?;
+        engine::plugins::validate_endpoint(&req.endpoint).map_err(Status::invalid_argument)?;
+
+        match self.engine.register_plugin(&req.name, &req.endpoint).await {
+            Ok(plugin) => {
+                ConsoleLogger::info(&format!("🔌 [PLUGINS] {} registered {} at {}", actor, plugin.name, plugin.endpoint));
+                Ok(Response::new(quilt::RegisterPluginResponse {
+                    success: true,
+                    error_code: ErrorCode::Unspecified as i32,
+                    error_message: String::new(),
+                    plugin: Some(Self::plugin_info(plugin)),
+                }))
+            }
+            Err(e) => Ok(Response::new(quilt::RegisterPluginResponse {
+                success: false,
+                error_code: error_code(&e),
+                error_message: e,
+                plugin: None,
+            })),
+        }
+    }
+
+    async fn list_plugins(
+        &self,
+        request: Request<quilt::ListPluginsRequest>,
+    ) -> Result<Response<quilt::ListPluginsResponse>, Status> {
+        let req = request.into_inner();
+        let plugins = self.engine.list_plugins().await.map_err(engine_status)?;
+        let plugins = plugins.into_iter()
+            .filter(|plugin| req.capability.is_empty() || plugin.capabilities.contains(&req.capability))
+            .map(Self::plugin_info)
+            .collect();
+        Ok(Response::new(quilt::ListPluginsResponse { plugins }))
+    }
+
+    async fn drain_containers(
+        &self,
+        request: Request<quilt::DrainContainersRequest>,
+    ) -> Result<Response<quilt::DrainContainersResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        let up_to = if req.up_to_priority_class.is_empty() {
+            PriorityClass::BestEffort
+        } else {
+            PriorityClass::from_str(&req.up_to_priority_class).map_err(Status::invalid_argument)?
+        };
+
+        ConsoleLogger::info(&format!("⚖️ [DRAIN] {} draining containers up to {}", actor, up_to.as_str()));
+        let drained = self.engine.drain_containers(up_to, &actor).await.map_err(engine_status)?;
+        Ok(Response::new(quilt::DrainContainersResponse {
+            containers: drained.into_iter().map(|(container_id, class, result)| quilt::DrainedContainer {
+                container_id,
+                priority_class: class.as_str().to_string(),
+                stopped: result.is_ok(),
+                error_message: result.err().unwrap_or_default(),
+            }).collect(),
+        }))
+    }
+
+    async fn stream_operation_progress(
+        &self,
+        request: Request<quilt::StreamOperationProgressRequest>,
+    ) -> Result<Response<Self::StreamOperationProgressStream>, Status> {
+        use tokio_stream::wrappers::WatchStream;
+        use futures::stream::StreamExt;
+
+        let req = request.into_inner();
+        let receiver = global_operations().subscribe(&req.operation_id)
+            .ok_or_else(|| error_status(ErrorCode::NotFound, format!("Operation {} not found", req.operation_id)))?;
+
+        // Starts with the current state; ends after the final update
+        let stream = WatchStream::new(receiver).scan(false, |finished, progress| {
+            if *finished {
+                return futures::future::ready(None);
+            }
+            *finished = progress.done;
+            futures::future::ready(Some(Ok(Self::operation_progress(progress))))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type StreamOperationProgressStream = std::pin::Pin<Box<dyn futures::Stream<Item = Result<quilt::OperationProgress, Status>> + Send>>;
+
+    async fn unregister_plugin(
+        &self,
+        request: Request<quilt::UnregisterPluginRequest>,
+    ) -> Result<Response<quilt::UnregisterPluginResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        match self.engine.unregister_plugin(&req.name).await {
+            Ok(true) => {
+                ConsoleLogger::info(&format!("🔌 [PLUGINS] {} unregistered {}", actor, req.name));
+                Ok(Response::new(quilt::UnregisterPluginResponse { success: true, error_message: String::new(), error_code: ErrorCode::Unspecified as i32 }))
+            }
+            Ok(false) => Ok(Response::new(quilt::UnregisterPluginResponse {
+                success: false,
+                error_code: ErrorCode::NotFound as i32,
+                error_message: format!("Plugin '{}' is not registered", req.name),
+            })),
+            Err(e) => Ok(Response::new(quilt::UnregisterPluginResponse { success: false, error_code: error_code(&e), error_message: e })),
+        }
+    }
+
+    async fn list_port_bindings(
+        &self,
+        _request: Request<quilt::ListPortBindingsRequest>,
+    ) -> Result<Response<quilt::ListPortBindingsResponse>, Status> {
+        let mut ports = self.engine.list_host_ports().await.map_err(engine_status)?;
+        if let Ok(grpc) = GRPC_LISTEN_ADDRESS.parse::<std::net::SocketAddr>() {
+            // Answering this request is proof enough that the listener is up
+            ports.push(engine::HostPort {
+                kind: engine::HostPortKind::Grpc,
+                protocol: "tcp".to_string(),
+                host_ip: grpc.ip().to_string(),
+                host_port: grpc.port(),
+                container_id: None,
+                container_name: None,
+                target: "quilt gRPC API".to_string(),
+                active: true,
+            });
+        }
+
+        Ok(Response::new(quilt::ListPortBindingsResponse {
+            bindings: ports.into_iter().map(|port| quilt::HostPortBinding {
+                kind: port.kind.as_str().to_string(),
+                protocol: port.protocol,
+                host_ip: port.host_ip,
+                host_port: port.host_port as u32,
+                container_id: port.container_id.unwrap_or_default(),
+                container_name: port.container_name.unwrap_or_default(),
+                target: port.target,
+                active: port.active,
+            }).collect(),
+        }))
+    }
+
+    async fn scan_image(
+        &self,
+        request: Request<quilt::ScanImageRequest>,
+    ) -> Result<Response<quilt::ImageScan>, Status> {
+        let req = request.into_inner();
+        if req.image_path.is_empty() {
+            return Err(Status::invalid_argument("image_path is required"));
+        }
+        let scan = self.engine.scan_image(&req.image_path, req.force).await.map_err(engine_status)?;
+        Ok(Response::new(Self::image_scan_info(scan, true)))
+    }
+
+    async fn pull_image(
+        &self,
+        request: Request<quilt::PullImageRequest>,
+    ) -> Result<Response<quilt::PulledImage>, Status> {
+        let req = request.into_inner();
+        if req.reference.is_empty() {
+            return Err(Status::invalid_argument("reference is required"));
+        }
+        let image = self.engine.pull_image(&req.reference, req.force).await.map_err(engine_status)?;
+        Ok(Response::new(quilt::PulledImage {
+            reference: image.reference,
+            digest: image.digest,
+            image_path: image.image_path,
+            layers: image.layers as u32,
+            size_bytes: image.size_bytes,
+            pulled_at: image.pulled_at,
+            cached: image.cached,
+        }))
+    }
+
+    async fn build_image(
+        &self,
+        request: Request<quilt::BuildImageRequest>,
+    ) -> Result<Response<quilt::BuildImageResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        if req.quiltfile.trim().is_empty() || req.context_dir.is_empty() || req.tag.is_empty() {
+            return Err(Status::invalid_argument("quiltfile, context_dir and tag are required"));
+        }
+        ConsoleLogger::info(&format!("🏗️ [BUILD] {} requested a build of {} from {}", actor, req.tag, req.context_dir));
+        let report = self.engine.build_image(&req.quiltfile, &req.context_dir, &req.tag, &actor).await.map_err(engine_status)?;
+        Ok(Response::new(quilt::BuildImageResponse {
+            tag: report.tag,
+            digest: report.digest,
+            image_path: report.image_path,
+            steps: report.steps as u32,
+            log: report.log,
+        }))
+    }
+
+    async fn list_images(
+        &self,
+        _request: Request<quilt::ListImagesRequest>,
+    ) -> Result<Response<quilt::ListImagesResponse>, Status> {
+        let images = self.engine.list_images().await.map_err(engine_status)?;
+        Ok(Response::new(quilt::ListImagesResponse {
+            images: images.into_iter().map(Self::image_info).collect(),
+        }))
+    }
+
+    async fn inspect_image(
+        &self,
+        request: Request<quilt::InspectImageRequest>,
+    ) -> Result<Response<quilt::Image>, Status> {
+        let req = request.into_inner();
+        if req.reference.is_empty() {
+            return Err(Status::invalid_argument("reference is required"));
+        }
+        let image = self.engine.inspect_image(&req.reference).await.map_err(engine_status)?;
+        Ok(Response::new(Self::image_info(image)))
+    }
+
+    async fn remove_image(
+        &self,
+        request: Request<quilt::RemoveImageRequest>,
+    ) -> Result<Response<quilt::RemoveImageResponse>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        if req.reference.is_empty() {
+            return Err(Status::invalid_argument("reference is required"));
+        }
+        let removed = self.engine.remove_image(&req.reference, &actor).await.map_err(engine_status)?;
+        Ok(Response::new(quilt::RemoveImageResponse {
+            digest: removed.digest,
+            removed_layers: removed.layers,
+            removed_files: removed.files,
+        }))
+    }
+
+    async fn import_image(
+        &self,
+        request: Request<quilt::ImportImageRequest>,
+    ) -> Result<Response<quilt::Image>, Status> {
+        let actor = request_actor(&request);
+        let req = request.into_inner();
+        if req.path.is_empty() {
+            return Err(Status::invalid_argument("path is required"));
+        }
+        let image = self.engine.import_image(&req.path, &actor).await.map_err(engine_status)?;
+        Ok(Response::new(Self::image_info(image)))
+    }
+
+    async fn export_image(
+        &self,
+        request: Request<quilt::ExportImageRequest>,
+    ) -> Result<Response<quilt::ExportImageResponse>, Status> {
+        let req = request.into_inner();
+        if req.reference.is_empty() || req.output_path.is_empty() {
+            return Err(Status::invalid_argument("reference and output_path are required"));
+        }
+        let format = req.format.parse::<engine::ExportFormat>().map_err(Status::invalid_argument)?;
+        let tag = Some(req.tag.as_str()).filter(|tag| !tag.is_empty());
+        let exported = self.engine.export_image(&req.reference, &req.output_path, format, tag).await.map_err(engine_status)?;
+        Ok(Response::new(quilt::ExportImageResponse {
+            digest: exported.digest,
+            output_path: exported.path,
+            format: exported.format.as_str().to_string(),
+            size_bytes: exported.size_bytes,
+        }))
+    }
+
+    async fn list_image_scans(
+        &self,
+        request: Request<quilt::ListImageScansRequest>,
+    ) -> Result<Response<quilt::ListImageScansResponse>, Status> {
+        let req = request.into_inner();
+        let scans = self.engine.list_image_scans().await.map_err(engine_status)?;
+        Ok(Response::new(quilt::ListImageScansResponse {
+            scans: scans.into_iter().map(|scan| Self::image_scan_info(scan, req.include_findings)).collect(),
+        }))
+    }
+
+    async fn check_host_requirements(
+        &self,
+        _request: Request<quilt::CheckHostRequirementsRequest>,
+    ) -> Result<Response<quilt::CheckHostRequirementsResponse>, Status> {
+        let checks = self.engine.check_host_requirements().await.map_err(engine_status)?;
+
+        let failed = checks.iter().filter(|check| check.status == engine::CheckStatus::Fail).count() as u32;
+        let warnings = checks.iter().filter(|check| check.status == engine::CheckStatus::Warn).count() as u32;
+        if failed > 0 {
+            ConsoleLogger::warning(&format!("🩺 [DOCTOR] {} host requirement check(s) failed", failed));
+        }
+
+        Ok(Response::new(quilt::CheckHostRequirementsResponse {
+            checks: checks.into_iter().map(|check| quilt::HostRequirementCheck {
+                name: check.name,
+                category: check.category.to_string(),
+                status: check.status.as_str().to_string(),
+                detail: check.detail,
+                remediation: check.remediation,
+            }).collect(),
+            ready: failed == 0,
+            failed,
+            warnings,
+        }))
+    }
+
+    async fn run_network_diagnostics(
+        &self,
+        request: Request<quilt::RunNetworkDiagnosticsRequest>,
+    ) -> Result<Response<quilt::RunNetworkDiagnosticsResponse>, Status> {
+        let req = request.into_inner();
+
+        let failure = |error_message: String| quilt::RunNetworkDiagnosticsResponse {
+            success: false,
+            error_code: error_code(&error_message),
+            error_message,
+            ..Default::default()
+        };
+
+        // Resolve container name to ID if a scope was requested
+        let scope = if !req.container_name.is_empty() {
+            match self.sync_engine.get_container_by_name(&req.container_name).await {
+                Ok(id) => Some(id),
+                Err(_) => return Err(Status::not_found(format!("Container with name '{}' not found", req.container_name))),
+            }
+        } else if !req.container_id.is_empty() {
+            Some(req.container_id)
+        } else {
+            None
+        };
+
+        let targets = match self.collect_diagnostic_targets(scope.as_deref()).await {
+            Ok(targets) => targets,
+            Err(e) => return Ok(Response::new(failure(e))),
+        };
+
+        // Checks shell out to ip/ping/nsenter, so keep them off the async workers
+        let report = match self.network_manager.run_network_health_monitoring(&targets, scope.is_some()).await {
+            Ok(report) => report,
+            Err(e) => return Ok(Response::new(failure(e))),
+        };
+
+        ConsoleLogger::info(&format!("🩺 [NETWORK] Diagnostics completed: {} issues in {}ms",
+            report.get_issues_count(), report.total_duration_ms));
+
+        Ok(Response::new(quilt::RunNetworkDiagnosticsResponse {
+            success: true,
+            error_code: ErrorCode::Unspecified as i32,
+            error_message: String::new(),
+            healthy: report.is_healthy(),
+            issues_count: report.get_issues_count() as u32,
+            total_duration_ms: report.total_duration_ms,
+            bridge_attachments: report.bridge_attachments.into_iter().map(|c| quilt::BridgeAttachmentCheck {
+                veth_name: c.veth_name,
+                attached: c.attached,
+                error_message: c.error_message.unwrap_or_default(),
+            }).collect(),
+            mac_addresses: report.mac_addresses.into_iter().map(|m| quilt::InterfaceMacInfo {
+                interface_name: m.interface_name,
+                mac_address: m.mac_address,
+                interface_type: m.interface_type,
+                container_pid: m.container_pid.unwrap_or(0),
+            }).collect(),
+            connectivity_tests: report.connectivity_tests.into_iter().map(|t| quilt::ConnectivityTestResult {
+                container_pid: t.container_pid,
+                container_ip: t.container_ip,
+                gateway_ip: t.gateway_ip,
+                success: t.success,
+                response_time_ms: t.response_time_ms.unwrap_or(0),
+                error_message: t.error_message.unwrap_or_default(),
+            }).collect(),
+            readiness_checks: report.readiness_checks.into_iter().map(|c| quilt::NetworkReadinessCheck {
+                container_id: c.container_id,
+                container_pid: c.container_pid,
+                ready: c.ready,
+                error_message: c.error_message.unwrap_or_default(),
+            }).collect(),
+            namespace_validations: report.namespace_validations.into_iter().map(|v| quilt::NamespaceValidationResult {
+                container_pid: v.container_pid,
+                namespace_valid: v.namespace_valid,
+                error_message: v.error_message.unwrap_or_default(),
+            }).collect(),
+        }))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logger
+    // Logger initialization not needed - ConsoleLogger is used directly
+    
+    // ✅ SYNC ENGINE INITIALIZATION
+    let service = QuiltServiceImpl::new().await
+        .map_err(|e| format!("Failed to initialize sync engine: {}", e))?;
+    
+    let addr: std::net::SocketAddr = GRPC_LISTEN_ADDRESS.parse()?;
+
+    ConsoleLogger::server_starting(&addr.to_string());
+    ConsoleLogger::success("🚀 Quilt server running with SQLite sync engine - non-blocking operations enabled");
+
+    // Standard grpc.health.v1 service and reflection so grpcurl and load balancers can probe the API
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter.set_serving::<QuiltServiceServer<QuiltServiceImpl>>().await;
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(quilt::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::proto::GRPC_HEALTH_V1_FILE_DESCRIPTOR_SET)
+        .build()?;
 
     // ✅ GRACEFUL SHUTDOWN
     let service_clone = service.clone();
@@ -2091,13 +4692,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .http2_keepalive_interval(Some(Duration::from_secs(30)))
             .http2_keepalive_timeout(Some(Duration::from_secs(60)))
             .tcp_keepalive(Some(Duration::from_secs(60)))
+            .layer(grpc::api_version::ApiVersionLayer)
+            .layer(grpc::plugin_auth::PluginAuthLayer)
+            .layer(grpc::rate_limit::RateLimitLayer::new(grpc::rate_limit::RateLimits::from_env()))
+            .layer(grpc::concurrency_limit::ConcurrencyLimitLayer::new(grpc::concurrency_limit::MethodLimits::from_env()))
             .add_service(QuiltServiceServer::new(service.clone()))
+            .add_service(health_service)
+            .add_service(reflection_service)
             .serve(addr) => {
             result?;
         }
         _ = tokio::signal::ctrl_c() => {
             ConsoleLogger::info("Received shutdown signal, cleaning up...");
-            service_clone.sync_engine.close().await;
+            health_reporter.set_not_serving::<QuiltServiceServer<QuiltServiceImpl>>().await;
+            service_clone.engine.shutdown().await;
             ConsoleLogger::success("Sync engine closed gracefully");
         }
     }
@@ -1,11 +1,17 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, Row};
+use std::collections::HashSet;
 use std::path::Path;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::fs;
 use crate::sync::error::{SyncError, SyncResult};
 use crate::utils::process::ProcessUtils;
 
+/// Environment variable overriding the cleanup policy as comma-separated `<target>=<value>` pairs:
+/// `on`/`off` for any target, a number for `logs` (rows kept per container) and `metrics` (days
+/// kept), and `force=<target>+<target>` for what ForceCleanup touches by default
+pub const CLEANUP_POLICY_ENV: &str = "QUILT_CLEANUP_POLICY";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CleanupStatus {
     Pending,
@@ -68,6 +74,138 @@ impl ResourceType {
     }
 }
 
+/// Everything the cleanup machinery can remove: the per-container task types plus the
+/// periodic sweeps of unused volumes, old metrics and excess log rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CleanupTarget {
+    Rootfs,
+    Network,
+    Cgroup,
+    Mounts,
+    Volumes,
+    Metrics,
+    Logs,
+}
+
+impl CleanupTarget {
+    pub const ALL: [CleanupTarget; 7] = [
+        CleanupTarget::Rootfs,
+        CleanupTarget::Network,
+        CleanupTarget::Cgroup,
+        CleanupTarget::Mounts,
+        CleanupTarget::Volumes,
+        CleanupTarget::Metrics,
+        CleanupTarget::Logs,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CleanupTarget::Rootfs => "rootfs",
+            CleanupTarget::Network => "network",
+            CleanupTarget::Cgroup => "cgroup",
+            CleanupTarget::Mounts => "mounts",
+            CleanupTarget::Volumes => "volumes",
+            CleanupTarget::Metrics => "metrics",
+            CleanupTarget::Logs => "logs",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.as_str() == s)
+    }
+
+    /// Target a scheduled cleanup task belongs to
+    pub fn for_resource(resource_type: &ResourceType) -> Self {
+        match resource_type {
+            ResourceType::Rootfs => CleanupTarget::Rootfs,
+            ResourceType::Network => CleanupTarget::Network,
+            ResourceType::Cgroup => CleanupTarget::Cgroup,
+            ResourceType::Mounts => CleanupTarget::Mounts,
+        }
+    }
+}
+
+/// What automatic cleanup may remove and how much history it keeps
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanupPolicy {
+    /// Targets the cleanup worker and periodic sweeps act on; tasks of a disabled type stay
+    /// pending until forced
+    pub enabled: HashSet<CleanupTarget>,
+    /// Stored log rows kept per container
+    pub log_retention_rows: u32,
+    /// Days of metrics kept
+    pub metrics_retention_days: u32,
+    /// Targets ForceCleanup touches when a request selects none
+    pub force_targets: Vec<CleanupTarget>,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: CleanupTarget::ALL.into_iter().collect(),
+            log_retention_rows: 1000,
+            metrics_retention_days: 7,
+            force_targets: CleanupTarget::ALL.into_iter().filter(|target| *target != CleanupTarget::Logs).collect(),
+        }
+    }
+}
+
+impl CleanupPolicy {
+    /// Defaults with any `QUILT_CLEANUP_POLICY` entries applied on top
+    pub fn from_env() -> Self {
+        match std::env::var(CLEANUP_POLICY_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parse `logs=500,metrics=3,volumes=off,force=rootfs+network`; malformed entries are skipped with a warning
+    pub fn parse(value: &str) -> Self {
+        let mut policy = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let applied = entry.split_once('=').and_then(|(key, value)| {
+                let (key, value) = (key.trim(), value.trim());
+                if key == "force" {
+                    let targets: Option<Vec<_>> = value.split('+').map(|t| CleanupTarget::from_str(t.trim())).collect();
+                    policy.force_targets = targets?;
+                    return Some(());
+                }
+                let target = CleanupTarget::from_str(key)?;
+                match value {
+                    "on" => { policy.enabled.insert(target); }
+                    "off" => { policy.enabled.remove(&target); }
+                    number => {
+                        let number: u32 = number.parse().ok()?;
+                        match target {
+                            CleanupTarget::Logs => policy.log_retention_rows = number,
+                            CleanupTarget::Metrics => policy.metrics_retention_days = number,
+                            _ => return None,
+                        }
+                        policy.enabled.insert(target);
+                    }
+                }
+                Some(())
+            });
+            if applied.is_none() {
+                tracing::warn!("Ignoring malformed {} entry: {}", CLEANUP_POLICY_ENV, entry);
+            }
+        }
+        policy
+    }
+
+    pub fn allows(&self, target: CleanupTarget) -> bool {
+        self.enabled.contains(&target)
+    }
+
+    /// Resource types the cleanup worker may execute
+    fn enabled_resource_types(&self) -> Vec<String> {
+        [ResourceType::Rootfs, ResourceType::Network, ResourceType::Cgroup, ResourceType::Mounts].iter()
+            .filter(|resource_type| self.allows(CleanupTarget::for_resource(resource_type)))
+            .map(|resource_type| resource_type.to_string())
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CleanupTask {
     pub id: i64,
@@ -95,6 +233,7 @@ impl CleanupTask {
 pub struct CleanupService {
     pool: SqlitePool,
     icc_network_manager: Option<std::sync::Arc<crate::icc::network::NetworkManager>>,
+    policy: CleanupPolicy,
 }
 
 impl CleanupService {
@@ -102,6 +241,7 @@ impl CleanupService {
         Self { 
             pool,
             icc_network_manager: None,
+            policy: CleanupPolicy::from_env(),
         }
     }
     
@@ -112,9 +252,14 @@ impl CleanupService {
         Self { 
             pool,
             icc_network_manager: Some(icc_network_manager),
+            policy: CleanupPolicy::from_env(),
         }
     }
     
+    pub fn policy(&self) -> &CleanupPolicy {
+        &self.policy
+    }
+    
     pub async fn schedule_cleanup(&self, container_id: &str, resource_type: ResourceType, resource_path: &str) -> SyncResult<i64> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
@@ -186,9 +331,10 @@ impl CleanupService {
             for task in pending_tasks {
                 let pool = self.pool.clone();
                 let task_clone = task.clone();
+                let icc_network_manager = self.icc_network_manager.clone();
                 
                 let handle = tokio::spawn(async move {
-                    CleanupService::execute_cleanup_task(&pool, task_clone).await
+                    CleanupService::execute_cleanup_task(&pool, task_clone, icc_network_manager.as_deref()).await
                 });
                 
                 handles.push(handle);
@@ -203,18 +349,29 @@ impl CleanupService {
         }
     }
     
+    /// Oldest pending tasks of the resource types the policy enables
     pub async fn get_pending_tasks(&self, limit: usize) -> SyncResult<Vec<CleanupTask>> {
-        let rows = sqlx::query(r#"
+        let enabled = self.policy.enabled_resource_types();
+        if enabled.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; enabled.len()].join(", ");
+        let sql = format!(r#"
             SELECT id, container_id, resource_type, resource_path, status, 
                    created_at, completed_at, error_message
             FROM cleanup_tasks 
-            WHERE status = 'pending'
+            WHERE status = 'pending' AND resource_type IN ({})
             ORDER BY created_at ASC
             LIMIT ?
-        "#)
-        .bind(limit as i64)
-        .fetch_all(&self.pool)
-        .await?;
+        "#, placeholders);
+        let mut query = sqlx::query(&sql);
+        for resource_type in &enabled {
+            query = query.bind(resource_type);
+        }
+        let rows = query
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
         
         let mut tasks = Vec::new();
         for row in rows {
@@ -311,13 +468,14 @@ impl CleanupService {
         Ok(tasks)
     }
     
-    async fn execute_cleanup_task(pool: &SqlitePool, task: CleanupTask) -> SyncResult<()> {
+    async fn execute_cleanup_task(pool: &SqlitePool, task: CleanupTask,
+        icc_network_manager: Option<&crate::icc::network::NetworkManager>) -> SyncResult<()> {
         // Mark task as in progress
         Self::update_task_status(pool, task.id, CleanupStatus::InProgress, None).await?;
         
         let result = match task.resource_type {
             ResourceType::Rootfs => Self::cleanup_rootfs(&task.resource_path).await,
-            ResourceType::Network => Self::cleanup_network(&task.container_id).await,
+            ResourceType::Network => Self::cleanup_network(pool, &task.container_id, icc_network_manager).await,
             ResourceType::Cgroup => Self::cleanup_cgroup(&task.resource_path).await,
             ResourceType::Mounts => Self::cleanup_mounts(&task.container_id).await,
         };
@@ -389,75 +547,54 @@ impl CleanupService {
         Ok(())
     }
     
-    async fn cleanup_network(container_id: &str) -> SyncResult<()> {
+    async fn cleanup_network(pool: &SqlitePool, container_id: &str,
+        icc_network_manager: Option<&crate::icc::network::NetworkManager>) -> SyncResult<()> {
         tracing::info!("Starting network cleanup for container: {}", container_id);
         
-        // Step 1: Remove veth interfaces using the naming pattern from NetworkManager
-        let veth_host_name = format!("veth-{}", &container_id[..8]);
-        let veth_container_name = format!("vethc-{}", &container_id[..8]);
-        let quilt_bridge_veth = format!("quilt{}", &container_id[..8]); // Alternative naming pattern
-        
-        // Try to remove all possible veth interfaces for this container
-        let veth_interfaces = vec![
-            veth_host_name,
-            veth_container_name,
-            quilt_bridge_veth,
-        ];
-        
-        for interface in veth_interfaces {
-            let cleanup_cmd = format!("ip link delete {} 2>/dev/null || true", interface);
-            match tokio::process::Command::new("sh")
-                .arg("-c")
-                .arg(&cleanup_cmd)
-                .output()
-                .await 
-            {
-                Ok(output) => {
-                    if output.status.success() {
-                        tracing::debug!("Successfully removed interface: {}", interface);
-                    } else {
-                        tracing::debug!("Interface {} not found or already removed", interface);
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to execute cleanup command for {}: {}", interface, e);
-                }
-            }
+        // Tasks are scheduled when a container exits; one started again since then owns its
+        // interfaces and rules anew
+        let state: Option<String> = sqlx::query_scalar("SELECT state FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(pool)
+            .await?;
+        if matches!(state.as_deref(), Some("starting" | "running" | "paused" | "stopping" | "restarting")) {
+            tracing::debug!("Container {} is active again, keeping its network", container_id);
+            return Ok(());
         }
         
-        // Step 2: Clean up any container-specific iptables rules 
-        // (This is defensive - the current implementation doesn't create per-container rules)
-        let container_ip_pattern = format!("10.42.0."); // IP range pattern
-        let iptables_cleanup_cmds = vec![
-            // Clean up any potential FORWARD rules for this container's IP range
-            format!("iptables -D FORWARD -s {} -j ACCEPT 2>/dev/null || true", container_ip_pattern),
-            format!("iptables -D FORWARD -d {} -j ACCEPT 2>/dev/null || true", container_ip_pattern),
-        ];
-        
-        for cmd in iptables_cleanup_cmds {
-            match tokio::process::Command::new("sh")
-                .arg("-c")
-                .arg(&cmd)
-                .output()
-                .await 
-            {
-                Ok(_) => {
-                    tracing::debug!("Executed iptables cleanup: {}", cmd);
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to execute iptables cleanup: {}: {}", cmd, e);
-                }
+        let allocation = sqlx::query("SELECT ip_address, veth_host, veth_container FROM network_allocations WHERE container_id = ?")
+            .bind(container_id)
+            .fetch_optional(pool)
+            .await?;
+        let container_ip: Option<String> = allocation.as_ref().map(|row| row.get("ip_address"));
+        let recorded_interfaces: Vec<String> = allocation.as_ref()
+            .map(|row| [row.get::<Option<String>, _>("veth_host"), row.get::<Option<String>, _>("veth_container")])
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        let bridge_name = icc_network_manager.map(|manager| manager.config.bridge_name.clone());
+        
+        let id = container_id.to_string();
+        let teardown = tokio::task::spawn_blocking(move || {
+            crate::icc::network::teardown::teardown_container_network(&id, container_ip.as_deref(), &recorded_interfaces, bridge_name.as_deref())
+        }).await;
+        match teardown {
+            Ok(Ok(teardown)) => {
+                tracing::debug!("Network cleanup completed for container {}: {:?}", container_id, teardown);
+                Ok(())
             }
+            Ok(Err(message)) => Err(SyncError::CleanupFailed {
+                resource_type: "network".to_string(),
+                path: container_id.to_string(),
+                message,
+            }),
+            Err(e) => Err(SyncError::CleanupFailed {
+                resource_type: "network".to_string(),
+                path: container_id.to_string(),
+                message: format!("Teardown task failed: {}", e),
+            }),
         }
-        
-        // Step 3: Remove DNS registrations (if DNS server is running)
-        // This is best-effort since DNS server may not be running
-        // NOTE: The NetworkManager.unregister_container_dns() would be called here
-        // if we had a reference to the network manager, but since the cleanup service
-        // is independent, DNS cleanup happens when containers are removed normally
-        tracing::debug!("Network cleanup completed for container: {}", container_id);
-        
-        Ok(())
     }
     
     async fn cleanup_cgroup(cgroup_path: &str) -> SyncResult<()> {
@@ -467,7 +604,8 @@ impl CleanupService {
         }
         
         tracing::debug!("Removing cgroup directory: {}", cgroup_path);
-        fs::remove_dir_all(cgroup_path).await.map_err(|e| {
+        // cgroupfs control files cannot be unlinked; an empty cgroup is removed with rmdir
+        fs::remove_dir(cgroup_path).await.map_err(|e| {
             SyncError::CleanupFailed {
                 resource_type: "cgroup".to_string(),
                 path: cgroup_path.to_string(),
@@ -533,8 +671,9 @@ impl CleanupService {
         Ok(tasks)
     }
 
-    /// Force cleanup of a container - immediately execute all pending cleanup tasks
-    pub async fn force_cleanup(&self, container_id: &str) -> SyncResult<Vec<String>> {
+    /// Force cleanup of a container - immediately execute its pending and failed cleanup tasks of
+    /// `targets` (regardless of policy); with `dry_run` only report what would be cleaned
+    pub async fn force_cleanup(&self, container_id: &str, targets: &[CleanupTarget], dry_run: bool) -> SyncResult<Vec<String>> {
         // Get all pending cleanup tasks for this container
         let pending_tasks = sqlx::query(r#"
             SELECT id, container_id, resource_type, resource_path, status, 
@@ -564,8 +703,16 @@ impl CleanupService {
                 error_message: row.get("error_message"),
             };
 
+            if !targets.contains(&CleanupTarget::for_resource(&task.resource_type)) {
+                continue;
+            }
+            if dry_run {
+                cleaned_resources.push(format!("would clean {}:{}", task.resource_type.to_string(), task.resource_path));
+                continue;
+            }
+
             // Execute the cleanup task immediately
-            match Self::execute_cleanup_task(&self.pool, task.clone()).await {
+            match Self::execute_cleanup_task(&self.pool, task.clone(), self.icc_network_manager.as_deref()).await {
                 Ok(()) => {
                     cleaned_resources.push(format!("{}:{}", task.resource_type.to_string(), task.resource_path));
                 }
@@ -729,7 +876,7 @@ mod tests {
         let task = cleanup_service.get_task_status(task_id).await.unwrap();
         
         // Execute cleanup
-        CleanupService::execute_cleanup_task(&cleanup_service.pool, task).await.unwrap();
+        CleanupService::execute_cleanup_task(&cleanup_service.pool, task, None).await.unwrap();
         
         // Verify directory was removed
         assert!(!temp_dir.path().exists());
@@ -739,4 +886,33 @@ mod tests {
         assert_eq!(updated_task.status, CleanupStatus::Completed);
         assert!(updated_task.completed_at.is_some());
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_cleanup_policy_parse() {
+        let policy = CleanupPolicy::parse("logs=500, metrics=3,volumes=off,force=rootfs+network,bogus=1,cgroup=12");
+        assert_eq!(policy.log_retention_rows, 500);
+        assert_eq!(policy.metrics_retention_days, 3);
+        assert!(!policy.allows(CleanupTarget::Volumes));
+        assert!(policy.allows(CleanupTarget::Cgroup));
+        assert_eq!(policy.force_targets, vec![CleanupTarget::Rootfs, CleanupTarget::Network]);
+        assert_eq!(CleanupPolicy::parse(""), CleanupPolicy::default());
+    }
+    
+    #[tokio::test]
+    async fn test_force_cleanup_dry_run_and_selection() {
+        let (_conn, cleanup_service) = setup_test_db().await;
+        let temp_dir = TempDir::new().unwrap();
+        let rootfs = temp_dir.path().to_str().unwrap();
+        
+        cleanup_service.schedule_cleanup("test-container", ResourceType::Rootfs, rootfs).await.unwrap();
+        cleanup_service.schedule_cleanup("test-container", ResourceType::Mounts, "test-container").await.unwrap();
+        
+        let report = cleanup_service.force_cleanup("test-container", &[CleanupTarget::Rootfs], true).await.unwrap();
+        assert_eq!(report, vec![format!("would clean rootfs:{}", rootfs)]);
+        assert!(temp_dir.path().exists());
+        
+        let cleaned = cleanup_service.force_cleanup("test-container", &[CleanupTarget::Mounts], false).await.unwrap();
+        assert_eq!(cleaned, vec!["mounts:test-container".to_string()]);
+        assert!(temp_dir.path().exists());
+    }
+}
@@ -2,6 +2,12 @@ use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, Row};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::daemon::hooks::LifecycleHook;
+use crate::daemon::prestart::PrestartHook;
+use crate::daemon::readiness_gates::ReadinessGates;
+use crate::daemon::priority::PriorityClass;
+use crate::daemon::net_policy::NetworkPolicy;
+use crate::icc::network::EgressProxy;
 use crate::sync::error::{SyncError, SyncResult};
 use crate::utils::process::ProcessUtils;
 
@@ -10,6 +16,8 @@ pub struct LogEntry {
     pub timestamp: i64,
     pub level: String,
     pub message: String,
+    /// Who triggered the logged action
+    pub actor: String,
 }
 
 impl LogEntry {
@@ -19,6 +27,67 @@ impl LogEntry {
     }
 }
 
+/// Text a log message must contain to match a search
+#[derive(Debug, Clone)]
+pub enum LogPattern {
+    Substring { text: String, case_sensitive: bool },
+    Regex(regex::Regex),
+}
+
+impl LogPattern {
+    /// Build a pattern; regexes are compiled here so a bad expression fails before any rows are read
+    pub fn new(query: &str, is_regex: bool, case_sensitive: bool) -> Result<Self, String> {
+        if is_regex {
+            regex::RegexBuilder::new(query)
+                .case_insensitive(!case_sensitive)
+                .size_limit(1 << 20)
+                .build()
+                .map(LogPattern::Regex)
+                .map_err(|e| format!("Invalid regex: {}", e))
+        } else if case_sensitive {
+            Ok(LogPattern::Substring { text: query.to_string(), case_sensitive })
+        } else {
+            Ok(LogPattern::Substring { text: query.to_lowercase(), case_sensitive })
+        }
+    }
+
+    pub fn matches(&self, message: &str) -> bool {
+        match self {
+            LogPattern::Substring { text, case_sensitive: true } => message.contains(text.as_str()),
+            LogPattern::Substring { text, case_sensitive: false } => message.to_lowercase().contains(text.as_str()),
+            LogPattern::Regex(regex) => regex.is_match(message),
+        }
+    }
+}
+
+/// Filters for [`ContainerManager::search_logs`]; every set filter must match
+#[derive(Debug, Clone, Default)]
+pub struct LogSearch {
+    /// None searches every container
+    pub container_id: Option<String>,
+    pub pattern: Option<LogPattern>,
+    /// Accepted levels (case-insensitive); empty accepts all
+    pub levels: Vec<String>,
+    /// Inclusive unix-second bounds
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: usize,
+}
+
+impl LogSearch {
+    pub fn accepts_level(&self, level: &str) -> bool {
+        self.levels.is_empty() || self.levels.iter().any(|accepted| accepted.eq_ignore_ascii_case(level))
+    }
+
+    pub fn accepts_time(&self, timestamp: i64) -> bool {
+        self.since.map_or(true, |since| timestamp >= since) && self.until.map_or(true, |until| timestamp <= until)
+    }
+
+    pub fn accepts_message(&self, message: &str) -> bool {
+        self.pattern.as_ref().map_or(true, |pattern| pattern.matches(message))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ContainerState {
     Created,
@@ -26,6 +95,14 @@ pub enum ContainerState {
     Running,
     Exited,
     Error,
+    /// Stop requested, process not yet gone
+    Stopping,
+    /// Removal in progress; the record disappears once it finishes
+    Removing,
+    /// Being stopped and started again as one operation
+    Restarting,
+    /// Processes frozen in their cgroup
+    Paused,
 }
 
 impl ContainerState {
@@ -36,6 +113,10 @@ impl ContainerState {
             ContainerState::Running => "running".to_string(),
             ContainerState::Exited => "exited".to_string(),
             ContainerState::Error => "error".to_string(),
+            ContainerState::Stopping => "stopping".to_string(),
+            ContainerState::Removing => "removing".to_string(),
+            ContainerState::Restarting => "restarting".to_string(),
+            ContainerState::Paused => "paused".to_string(),
         }
     }
     
@@ -46,6 +127,10 @@ impl ContainerState {
             "running" => Ok(ContainerState::Running),
             "exited" => Ok(ContainerState::Exited),
             "error" => Ok(ContainerState::Error),
+            "stopping" => Ok(ContainerState::Stopping),
+            "removing" => Ok(ContainerState::Removing),
+            "restarting" => Ok(ContainerState::Restarting),
+            "paused" => Ok(ContainerState::Paused),
             _ => Err(SyncError::ValidationFailed {
                 message: format!("Invalid container state: {}", s),
             }),
@@ -62,21 +147,57 @@ impl ContainerState {
             (ContainerState::Exited, ContainerState::Starting) => true, // Allow restart
             (ContainerState::Exited, ContainerState::Created) => true, // Allow reset
             (ContainerState::Error, ContainerState::Starting) => true, // Allow retry
+            (ContainerState::Starting | ContainerState::Running | ContainerState::Paused
+                | ContainerState::Restarting, ContainerState::Stopping) => true,
+            (ContainerState::Stopping, ContainerState::Exited) => true,
+            (ContainerState::Stopping, ContainerState::Running) => true, // Stop failed
+            (ContainerState::Running | ContainerState::Exited | ContainerState::Error
+                | ContainerState::Paused, ContainerState::Restarting) => true,
+            (ContainerState::Restarting, ContainerState::Starting | ContainerState::Running
+                | ContainerState::Exited) => true,
+            (ContainerState::Running, ContainerState::Paused) => true,
+            (ContainerState::Paused, ContainerState::Running | ContainerState::Exited) => true,
+            (ContainerState::Removing, ContainerState::Removing) => false,
+            (_, ContainerState::Removing) => true,
             (_, ContainerState::Error) => true, // Can always transition to error
             _ => false,
         }
     }
+
+    /// Whether an operation on the container is still in progress, so other operations should
+    /// wait or be refused instead of racing it
+    pub fn is_transitional(&self) -> bool {
+        matches!(self, ContainerState::Starting | ContainerState::Stopping
+            | ContainerState::Removing | ContainerState::Restarting)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContainerConfig {
     pub id: String,
     pub name: Option<String>,
     pub image_path: String,
     pub command: String,
     pub environment: HashMap<String, String>,
+    /// Directory the main process starts in; None means the image's WORKDIR, else `/`
+    pub working_directory: Option<String>,
+    /// `user[:group]` the main process runs as; None means the image's USER, else root
+    pub user: Option<String>,
+    /// Zone mounted at /etc/localtime and named by TZ; None means the daemon default, if any
+    pub timezone: Option<String>,
+    /// LANG of the container; None means the daemon default, if any
+    pub locale: Option<String>,
     pub memory_limit_mb: Option<i64>,
     pub cpu_limit_percent: Option<f64>,
+    /// CPU above the limit that may be drawn from unused quota, same unit as the limit
+    pub cpu_burst_percent: Option<f64>,
+    /// memory.high (v1: soft limit) in megabytes
+    pub memory_high_mb: Option<i64>,
+    /// Swap allowed besides memory in megabytes; Some(0) means no swap, None the host default
+    pub memory_swap_mb: Option<i64>,
+    pub memory_swappiness: Option<i64>,
+    /// Seconds between metrics samples; None means the default interval
+    pub metrics_interval_seconds: Option<i64>,
     
     // Namespace configuration
     pub enable_network_namespace: bool,
@@ -84,6 +205,40 @@ pub struct ContainerConfig {
     pub enable_mount_namespace: bool,
     pub enable_uts_namespace: bool,
     pub enable_ipc_namespace: bool,
+    pub enable_time_namespace: bool,
+    /// Seconds added to CLOCK_MONOTONIC and CLOCK_BOOTTIME in the time namespace
+    pub monotonic_offset: i64,
+    pub boottime_offset: i64,
+    pub enable_cgroup_namespace: bool,
+    pub enable_user_namespace: bool,
+    
+    // FUSE configuration
+    pub allow_fuse: bool,
+    pub fuse_mounts: Vec<crate::utils::validation::FuseMount>,
+    
+    // Read-only rootfs hardening profile
+    pub immutable: bool,
+    // Host-level access: unhardened /proc and /sys, host /dev, all devices, full capabilities
+    pub privileged: bool,
+    
+    // Published host ports
+    pub ports: Vec<crate::utils::validation::PortMapping>,
+    /// Whether names below `<name>.quilt.local` resolve to the container
+    pub dns_wildcard: bool,
+    
+    // Labels used to select the container (e.g., in event subscriptions)
+    pub labels: HashMap<String, String>,
+}
+
+/// Background metrics collection settings of a container
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSchedule {
+    pub container_id: String,
+    pub pid: Option<i64>,
+    /// Whether the background collector samples the container
+    pub enabled: bool,
+    /// Seconds between samples; None means the collector's default
+    pub interval_seconds: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -140,8 +295,10 @@ impl ContainerManager {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
         // Handle state-specific updates
+        // Resuming from Paused or a failed stop keeps the original start time
+        let resumed = matches!(current_state, ContainerState::Paused | ContainerState::Stopping);
         let query = match new_state {
-            ContainerState::Running => {
+            ContainerState::Running if !resumed => {
                 sqlx::query("UPDATE containers SET state = ?, started_at = ?, updated_at = ? WHERE id = ?")
                     .bind(new_state.to_string())
                     .bind(now)
@@ -300,6 +457,362 @@ impl ContainerManager {
         }
     }
     
+    /// Stored creation settings of a container. Published ports live in their own table and are
+    /// not included.
+    pub async fn get_container_config(&self, container_id: &str) -> SyncResult<ContainerConfig> {
+        let row = sqlx::query(r#"
+            SELECT id, name, image_path, command, environment, working_directory, run_as_user, timezone, locale, memory_limit_mb, cpu_limit_percent,
+                   cpu_burst_percent, memory_high_mb, memory_swap_mb, memory_swappiness, metrics_interval_seconds,
+                   enable_network_namespace, enable_pid_namespace, enable_mount_namespace,
+                   enable_uts_namespace, enable_ipc_namespace,
+                   enable_time_namespace, monotonic_offset, boottime_offset, enable_cgroup_namespace,
+                   enable_user_namespace, allow_fuse, fuse_mounts, labels, immutable, privileged, dns_wildcard
+            FROM containers WHERE id = ?
+        "#)
+        .bind(container_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?;
+        
+        let json_or_default = |column: &str| row.get::<Option<String>, _>(column).unwrap_or_default();
+        Ok(ContainerConfig {
+            id: row.get("id"),
+            name: row.get("name"),
+            image_path: row.get("image_path"),
+            command: row.get("command"),
+            environment: serde_json::from_str(&json_or_default("environment")).unwrap_or_default(),
+            working_directory: row.get("working_directory"),
+            user: row.get("run_as_user"),
+            timezone: row.get("timezone"),
+            locale: row.get("locale"),
+            memory_limit_mb: row.get("memory_limit_mb"),
+            cpu_limit_percent: row.get("cpu_limit_percent"),
+            cpu_burst_percent: row.get("cpu_burst_percent"),
+            memory_high_mb: row.get("memory_high_mb"),
+            memory_swap_mb: row.get("memory_swap_mb"),
+            memory_swappiness: row.get("memory_swappiness"),
+            metrics_interval_seconds: row.get("metrics_interval_seconds"),
+            enable_network_namespace: row.get("enable_network_namespace"),
+            enable_pid_namespace: row.get("enable_pid_namespace"),
+            enable_mount_namespace: row.get("enable_mount_namespace"),
+            enable_uts_namespace: row.get("enable_uts_namespace"),
+            enable_ipc_namespace: row.get("enable_ipc_namespace"),
+            enable_time_namespace: row.get("enable_time_namespace"),
+            monotonic_offset: row.get("monotonic_offset"),
+            boottime_offset: row.get("boottime_offset"),
+            enable_cgroup_namespace: row.get("enable_cgroup_namespace"),
+            enable_user_namespace: row.get("enable_user_namespace"),
+            allow_fuse: row.get("allow_fuse"),
+            fuse_mounts: serde_json::from_str(&json_or_default("fuse_mounts")).unwrap_or_default(),
+            immutable: row.get("immutable"),
+            privileged: row.get("privileged"),
+            ports: Vec::new(),
+            dns_wildcard: row.get("dns_wildcard"),
+            labels: serde_json::from_str(&json_or_default("labels")).unwrap_or_default(),
+        })
+    }
+    
+    /// Labels of every container that has any, keyed by container ID
+    pub async fn list_container_labels(&self) -> SyncResult<HashMap<String, HashMap<String, String>>> {
+        let rows = sqlx::query("SELECT id, labels FROM containers WHERE labels IS NOT NULL AND labels != '{}'")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        let mut labels = HashMap::new();
+        for row in rows {
+            let container_labels: HashMap<String, String> = row.get::<Option<String>, _>("labels")
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            if !container_labels.is_empty() {
+                labels.insert(row.get("id"), container_labels);
+            }
+        }
+        Ok(labels)
+    }
+    
+    /// Store (or with `None`, clear) the container's network policy
+    pub async fn set_network_policy(&self, container_id: &str, policy: Option<&NetworkPolicy>) -> SyncResult<()> {
+        let policy_json = match policy {
+            Some(policy) => Some(serde_json::to_string(policy)?),
+            None => None,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET network_policy = ?, updated_at = ? WHERE id = ?")
+            .bind(policy_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_network_policy(&self, container_id: &str) -> SyncResult<Option<NetworkPolicy>> {
+        let policy_json: Option<Option<String>> = sqlx::query_scalar("SELECT network_policy FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match policy_json {
+            Some(json) => Ok(json.and_then(|json| serde_json::from_str(&json).ok())),
+            None => Err(SyncError::NotFound { container_id: container_id.to_string() }),
+        }
+    }
+    
+    /// Policies of every container that has one, keyed by container ID
+    pub async fn list_network_policies(&self) -> SyncResult<HashMap<String, NetworkPolicy>> {
+        let rows = sqlx::query("SELECT id, network_policy FROM containers WHERE network_policy IS NOT NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(rows.into_iter()
+            .filter_map(|row| {
+                let policy = row.get::<Option<String>, _>("network_policy")
+                    .and_then(|json| serde_json::from_str(&json).ok())?;
+                Some((row.get("id"), policy))
+            })
+            .collect())
+    }
+    
+    /// Store a container's lifecycle hooks; an empty list clears them
+    pub async fn set_container_hooks(&self, container_id: &str, hooks: &[LifecycleHook]) -> SyncResult<()> {
+        let hooks_json = if hooks.is_empty() { None } else { Some(serde_json::to_string(hooks)?) };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET hooks = ?, updated_at = ? WHERE id = ?")
+            .bind(hooks_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    /// Store the hooks run before each start of the container; an empty list clears them
+    pub async fn set_prestart_hooks(&self, container_id: &str, hooks: &[PrestartHook]) -> SyncResult<()> {
+        let hooks_json = if hooks.is_empty() { None } else { Some(serde_json::to_string(hooks)?) };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET prestart_hooks = ?, updated_at = ? WHERE id = ?")
+            .bind(hooks_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    /// Store the setup commands run inside the container before its command; an empty list clears them
+    pub async fn set_setup_commands(&self, container_id: &str, commands: &[String]) -> SyncResult<()> {
+        let commands_json = if commands.is_empty() { None } else { Some(serde_json::to_string(commands)?) };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET setup_commands = ?, updated_at = ? WHERE id = ?")
+            .bind(commands_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    /// Store the gates checked before the container is reported Running; `None` clears them
+    pub async fn set_readiness_gates(&self, container_id: &str, readiness: Option<&ReadinessGates>) -> SyncResult<()> {
+        let readiness_json = readiness.map(serde_json::to_string).transpose()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET readiness_gates = ?, updated_at = ? WHERE id = ?")
+            .bind(readiness_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_readiness_gates(&self, container_id: &str) -> SyncResult<Option<ReadinessGates>> {
+        let readiness_json: Option<Option<String>> = sqlx::query_scalar("SELECT readiness_gates FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let readiness_json = readiness_json.ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?;
+        Ok(readiness_json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+    
+    /// Set or clear the network plugin asked to plumb the container after quilt's own network setup
+    pub async fn set_network_plugin(&self, container_id: &str, plugin: Option<&str>) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET network_plugin = ?, updated_at = ? WHERE id = ?")
+            .bind(plugin)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_network_plugin(&self, container_id: &str) -> SyncResult<Option<String>> {
+        let plugin: Option<Option<String>> = sqlx::query_scalar("SELECT network_plugin FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(plugin.flatten())
+    }
+    
+    /// Set or clear the proxy a container's web traffic goes through
+    pub async fn set_egress_proxy(&self, container_id: &str, proxy: Option<&EgressProxy>) -> SyncResult<()> {
+        let proxy_json = proxy.map(serde_json::to_string).transpose()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET egress_proxy = ?, updated_at = ? WHERE id = ?")
+            .bind(proxy_json)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_egress_proxy(&self, container_id: &str) -> SyncResult<Option<EgressProxy>> {
+        let proxy_json: Option<Option<String>> = sqlx::query_scalar("SELECT egress_proxy FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let proxy_json = proxy_json.ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?;
+        Ok(proxy_json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+    
+    /// Set or clear the seconds a container's clocks are shifted from host time
+    pub async fn set_time_offset(&self, container_id: &str, offset: Option<i64>) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET time_offset = ?, updated_at = ? WHERE id = ?")
+            .bind(offset)
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_time_offset(&self, container_id: &str) -> SyncResult<Option<i64>> {
+        let offset: Option<Option<i64>> = sqlx::query_scalar("SELECT time_offset FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(offset.flatten())
+    }
+    
+    /// Turn background metrics collection of a container on or off; `interval_seconds` replaces
+    /// its interval when given
+    pub async fn set_metrics_collection(&self, container_id: &str, enabled: bool, interval_seconds: Option<u32>) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query(
+            "UPDATE containers SET metrics_enabled = ?, metrics_interval_seconds = COALESCE(?, metrics_interval_seconds), updated_at = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(interval_seconds.map(|seconds| seconds as i64))
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_metrics_schedule(&self, container_id: &str) -> SyncResult<MetricsSchedule> {
+        let row = sqlx::query("SELECT id, pid, metrics_enabled, metrics_interval_seconds FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?;
+        Ok(Self::metrics_schedule(&row))
+    }
+    
+    /// Collection settings of every running container
+    pub async fn list_metrics_schedules(&self) -> SyncResult<Vec<MetricsSchedule>> {
+        let rows = sqlx::query("SELECT id, pid, metrics_enabled, metrics_interval_seconds FROM containers WHERE state = ?")
+            .bind(ContainerState::Running.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::metrics_schedule).collect())
+    }
+    
+    fn metrics_schedule(row: &sqlx::sqlite::SqliteRow) -> MetricsSchedule {
+        MetricsSchedule {
+            container_id: row.get("id"),
+            pid: row.get("pid"),
+            enabled: row.get("metrics_enabled"),
+            interval_seconds: row.get::<Option<i64>, _>("metrics_interval_seconds").map(|seconds| seconds as u32),
+        }
+    }
+    
+    pub async fn set_priority_class(&self, container_id: &str, class: PriorityClass) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let result = sqlx::query("UPDATE containers SET priority_class = ?, updated_at = ? WHERE id = ?")
+            .bind(class.as_str())
+            .bind(now)
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(SyncError::NotFound { container_id: container_id.to_string() });
+        }
+        Ok(())
+    }
+    
+    pub async fn get_priority_class(&self, container_id: &str) -> SyncResult<PriorityClass> {
+        let class: Option<String> = sqlx::query_scalar("SELECT priority_class FROM containers WHERE id = ?")
+            .bind(container_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let class = class.ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?;
+        Ok(PriorityClass::from_str(&class).unwrap_or_default())
+    }
+    
+    /// Priority class of every container, keyed by container ID
+    pub async fn list_priority_classes(&self) -> SyncResult<HashMap<String, PriorityClass>> {
+        let rows = sqlx::query("SELECT id, priority_class FROM containers")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter()
+            .map(|row| (row.get("id"), PriorityClass::from_str(&row.get::<String, _>("priority_class")).unwrap_or_default()))
+            .collect())
+    }
+    
+    /// Name and hooks of every container that has hooks, keyed by container ID
+    pub async fn list_container_hooks(&self) -> SyncResult<HashMap<String, (String, Vec<LifecycleHook>)>> {
+        let rows = sqlx::query("SELECT id, name, hooks FROM containers WHERE hooks IS NOT NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(rows.into_iter()
+            .filter_map(|row| {
+                let hooks: Vec<LifecycleHook> = row.get::<Option<String>, _>("hooks")
+                    .and_then(|json| serde_json::from_str(&json).ok())?;
+                let name = row.get::<Option<String>, _>("name").unwrap_or_default();
+                Some((row.get("id"), (name, hooks)))
+            })
+            .collect())
+    }
+    
     pub async fn list_containers(&self, state_filter: Option<ContainerState>) -> SyncResult<Vec<ContainerStatus>> {
         let mut query = "
             SELECT 
@@ -366,22 +879,54 @@ impl ContainerManager {
         tracing::info!("Deleted container {} from database", container_id);
         Ok(())
     }
+
+    /// Exchange the names of two containers in one transaction, so the name is never unassigned
+    pub async fn swap_container_names(&self, first_id: &str, second_id: &str) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut transaction = self.pool.begin().await?;
+
+        let mut names = Vec::with_capacity(2);
+        for container_id in [first_id, second_id] {
+            let name: Option<Option<String>> = sqlx::query_scalar("SELECT name FROM containers WHERE id = ?")
+                .bind(container_id)
+                .fetch_optional(&mut *transaction)
+                .await?;
+            names.push(name.ok_or_else(|| SyncError::NotFound { container_id: container_id.to_string() })?);
+        }
+        for (container_id, name) in [(first_id, &names[1]), (second_id, &names[0])] {
+            sqlx::query("UPDATE containers SET name = ?, updated_at = ? WHERE id = ?")
+                .bind(name)
+                .bind(now)
+                .bind(container_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
     
     
     
     /// Store a log entry for a container
     pub async fn store_log(&self, container_id: &str, level: &str, message: &str) -> SyncResult<()> {
+        self.store_log_as(container_id, level, message, crate::sync::events::DAEMON_ACTOR).await
+    }
+    
+    /// Store a log entry attributed to `actor`
+    pub async fn store_log_as(&self, container_id: &str, level: &str, message: &str, actor: &str) -> SyncResult<()> {
         use std::time::{SystemTime, UNIX_EPOCH};
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
         sqlx::query(r#"
-            INSERT INTO container_logs (container_id, timestamp, level, message)
-            VALUES (?, ?, ?, ?)
+            INSERT INTO container_logs (container_id, timestamp, level, message, actor)
+            VALUES (?, ?, ?, ?, ?)
         "#)
         .bind(container_id)
         .bind(timestamp)
         .bind(level)
         .bind(message)
+        .bind(actor)
         .execute(&self.pool)
         .await?;
         
@@ -397,7 +942,7 @@ impl ContainerManager {
         };
         
         let query = format!(r#"
-            SELECT timestamp, level, message
+            SELECT timestamp, level, message, actor
             FROM container_logs
             WHERE container_id = ?
             ORDER BY timestamp DESC
@@ -415,13 +960,82 @@ impl ContainerManager {
                 timestamp: row.get("timestamp"),
                 level: row.get("level"),
                 message: row.get("message"),
+                actor: row.get("actor"),
             });
         }
         
         Ok(logs)
     }
     
+    /// Stored log rows matching `search`, newest first, with their container IDs. Container, level
+    /// and time filters run in SQLite; the text pattern is applied while streaming rows, so only as
+    /// many rows are read as it takes to find `limit` matches.
+    pub async fn search_logs(&self, search: &LogSearch) -> SyncResult<Vec<(String, LogEntry)>> {
+        use futures::TryStreamExt;
+
+        let mut conditions = Vec::new();
+        if search.container_id.is_some() {
+            conditions.push("container_id = ?".to_string());
+        }
+        if !search.levels.is_empty() {
+            conditions.push(format!("LOWER(level) IN ({})", vec!["?"; search.levels.len()].join(", ")));
+        }
+        if search.since.is_some() {
+            conditions.push("timestamp >= ?".to_string());
+        }
+        if search.until.is_some() {
+            conditions.push("timestamp <= ?".to_string());
+        }
+        let where_clause = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+        let sql = format!(r#"
+            SELECT container_id, timestamp, level, message, actor
+            FROM container_logs
+            {}
+            ORDER BY timestamp DESC, id DESC
+        "#, where_clause);
+
+        let mut query = sqlx::query(&sql);
+        if let Some(container_id) = &search.container_id {
+            query = query.bind(container_id);
+        }
+        for level in &search.levels {
+            query = query.bind(level.to_lowercase());
+        }
+        if let Some(since) = search.since {
+            query = query.bind(since);
+        }
+        if let Some(until) = search.until {
+            query = query.bind(until);
+        }
+
+        let mut matches = Vec::new();
+        let mut rows = query.fetch(&self.pool);
+        while matches.len() < search.limit {
+            let Some(row) = rows.try_next().await? else { break };
+            let message: String = row.get("message");
+            if !search.accepts_message(&message) {
+                continue;
+            }
+            matches.push((row.get("container_id"), LogEntry {
+                timestamp: row.get("timestamp"),
+                level: row.get("level"),
+                message,
+                actor: row.get("actor"),
+            }));
+        }
+        Ok(matches)
+    }
+
     /// Clean up old logs for a container (keep last N entries)
+    /// Log rows beyond the newest `keep_count`, i.e. what `cleanup_container_logs` would delete
+    pub async fn count_excess_container_logs(&self, container_id: &str, keep_count: u32) -> SyncResult<u64> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM container_logs WHERE container_id = ?")
+            .bind(container_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok((total as u64).saturating_sub(keep_count as u64))
+    }
+    
     pub async fn cleanup_container_logs(&self, container_id: &str, keep_count: u32) -> SyncResult<u64> {
         let result = sqlx::query(r#"
             DELETE FROM container_logs
@@ -480,6 +1094,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         // Create container
@@ -528,6 +1143,7 @@ mod tests {
             enable_mount_namespace: false,
             enable_uts_namespace: false,
             enable_ipc_namespace: false,
+            ..Default::default()
         };
         
         container_manager.create_container(config).await.unwrap();
@@ -544,6 +1160,44 @@ mod tests {
         }
     }
     
+    #[tokio::test]
+    async fn test_intermediate_states() {
+        let (_conn, container_manager) = setup_test_db().await;
+        
+        let config = ContainerConfig {
+            id: "test-container-3".to_string(),
+            image_path: "/path/to/image".to_string(),
+            command: "sleep 60".to_string(),
+            ..Default::default()
+        };
+        container_manager.create_container(config).await.unwrap();
+        
+        container_manager.update_container_state("test-container-3", ContainerState::Starting).await.unwrap();
+        container_manager.update_container_state("test-container-3", ContainerState::Running).await.unwrap();
+        let started_at = container_manager.get_container_status("test-container-3").await.unwrap().started_at;
+        
+        // Pausing and resuming keeps the original start time
+        container_manager.update_container_state("test-container-3", ContainerState::Paused).await.unwrap();
+        container_manager.update_container_state("test-container-3", ContainerState::Running).await.unwrap();
+        let status = container_manager.get_container_status("test-container-3").await.unwrap();
+        assert_eq!(status.started_at, started_at);
+        
+        container_manager.update_container_state("test-container-3", ContainerState::Stopping).await.unwrap();
+        assert!(container_manager.get_container_state("test-container-3").await.unwrap().is_transitional());
+        container_manager.update_container_state("test-container-3", ContainerState::Exited).await.unwrap();
+        
+        // Nothing left to stop
+        assert!(container_manager.update_container_state("test-container-3", ContainerState::Stopping).await.is_err());
+        
+        container_manager.update_container_state("test-container-3", ContainerState::Removing).await.unwrap();
+        assert_eq!(container_manager.get_container_state("test-container-3").await.unwrap(), ContainerState::Removing);
+        assert!(container_manager.update_container_state("test-container-3", ContainerState::Removing).await.is_err());
+        
+        for state in ["stopping", "removing", "restarting", "paused"] {
+            assert_eq!(ContainerState::from_string(state).unwrap().to_string(), state);
+        }
+    }
+    
     #[tokio::test]
     async fn test_container_name_uniqueness() {
         let (_conn, container_manager) = setup_test_db().await;
@@ -562,6 +1216,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         container_manager.create_container(config1).await.unwrap();
@@ -580,15 +1235,16 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         let result = container_manager.create_container(config2).await;
         assert!(result.is_err());
         
-        if let Err(SyncError::ValidationFailed { message }) = result {
+        if let Err(SyncError::Conflict { message }) = result {
             assert!(message.contains("already exists"));
         } else {
-            panic!("Expected ValidationFailed error for duplicate name");
+            panic!("Expected Conflict error for duplicate name");
         }
     }
     
@@ -610,6 +1266,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         container_manager.create_container(config).await.unwrap();
@@ -648,6 +1305,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         // Should succeed (empty name is ignored)
@@ -685,6 +1343,7 @@ mod tests {
                 enable_mount_namespace: true,
                 enable_uts_namespace: true,
                 enable_ipc_namespace: true,
+                ..Default::default()
             };
             
             container_manager.create_container(config).await.unwrap();
@@ -695,4 +1354,49 @@ mod tests {
             assert_eq!(result.unwrap(), format!("special-char-{}", i));
         }
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_search_logs() {
+        let (_conn, container_manager) = setup_test_db().await;
+        for id in ["search-a", "search-b"] {
+            let config = ContainerConfig {
+                id: id.to_string(),
+                image_path: "/path/to/image".to_string(),
+                command: "echo test".to_string(),
+                ..Default::default()
+            };
+            container_manager.create_container(config).await.unwrap();
+        }
+        container_manager.store_log("search-a", "info", "Container started").await.unwrap();
+        container_manager.store_log("search-a", "error", "Connection refused on port 5432").await.unwrap();
+        container_manager.store_log("search-b", "warn", "Retrying connection (attempt 2)").await.unwrap();
+        
+        // Substring matching is case-insensitive unless asked otherwise, across all containers
+        let search = LogSearch {
+            pattern: Some(LogPattern::new("CONNECTION", false, false).unwrap()),
+            limit: 10,
+            ..Default::default()
+        };
+        let matches = container_manager.search_logs(&search).await.unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].0, "search-b");
+        
+        let search = LogSearch {
+            container_id: Some("search-a".to_string()),
+            pattern: Some(LogPattern::new(r"port \d+$", true, true).unwrap()),
+            levels: vec!["ERROR".to_string()],
+            limit: 10,
+            ..Default::default()
+        };
+        let matches = container_manager.search_logs(&search).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].1.message, "Connection refused on port 5432");
+        
+        let search = LogSearch { since: Some(i64::MAX), limit: 10, ..Default::default() };
+        assert!(container_manager.search_logs(&search).await.unwrap().is_empty());
+        let search = LogSearch { limit: 1, ..Default::default() };
+        assert_eq!(container_manager.search_logs(&search).await.unwrap().len(), 1);
+        
+        assert!(LogPattern::new("(unclosed", true, false).is_err());
+    }
+}
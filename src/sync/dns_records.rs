@@ -0,0 +1,104 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// A DNS name answered with fixed addresses for the queries of one view: `all`, `containers` or
+/// `host`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsRecord {
+    pub name: String,
+    pub view: String,
+    pub addresses: Vec<String>,
+    pub created_at: i64,
+}
+
+pub struct DnsRecordManager {
+    pool: SqlitePool,
+}
+
+impl DnsRecordManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store the record of `name` in `view`, replacing the addresses of an earlier one
+    pub async fn set_dns_record(&self, name: &str, view: &str, addresses: &[String]) -> SyncResult<DnsRecord> {
+        if addresses.is_empty() {
+            return Err(SyncError::ValidationFailed {
+                message: format!("At least one address is required for DNS record '{}'", name),
+            });
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let row = sqlx::query(r#"
+            INSERT INTO dns_records (name, view, addresses, created_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(name, view) DO UPDATE SET addresses = excluded.addresses
+            RETURNING name, view, addresses, created_at
+        "#)
+            .bind(name)
+            .bind(view)
+            .bind(serde_json::to_string(addresses)?)
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(Self::record(&row))
+    }
+
+    /// Returns whether the record existed
+    pub async fn delete_dns_record(&self, name: &str, view: &str) -> SyncResult<bool> {
+        let result = sqlx::query("DELETE FROM dns_records WHERE name = ? AND view = ?")
+            .bind(name)
+            .bind(view)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_dns_records(&self) -> SyncResult<Vec<DnsRecord>> {
+        let rows = sqlx::query("SELECT name, view, addresses, created_at FROM dns_records ORDER BY name, view")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::record).collect())
+    }
+
+    fn record(row: &sqlx::sqlite::SqliteRow) -> DnsRecord {
+        DnsRecord {
+            name: row.get("name"),
+            view: row.get("view"),
+            addresses: serde_json::from_str(&row.get::<String, _>("addresses")).unwrap_or_default(),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_dns_record_lifecycle() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let records = DnsRecordManager::new(conn_manager.pool().clone());
+
+        let vip = vec!["10.42.255.10".to_string()];
+        let first = records.set_dns_record("api", "containers", &vip).await.unwrap();
+        records.set_dns_record("api", "host", &["203.0.113.7".to_string()]).await.unwrap();
+        assert!(matches!(records.set_dns_record("empty", "all", &[]).await, Err(SyncError::ValidationFailed { .. })));
+        assert!(records.set_dns_record("api", "guests", &vip).await.is_err());
+
+        let moved = vec!["10.42.255.11".to_string(), "10.42.255.12".to_string()];
+        let updated = records.set_dns_record("api", "containers", &moved).await.unwrap();
+        assert_eq!(updated.addresses, moved);
+        assert_eq!(updated.created_at, first.created_at);
+
+        let listed = records.list_dns_records().await.unwrap();
+        assert_eq!(listed.iter().map(|record| record.view.as_str()).collect::<Vec<_>>(), vec!["containers", "host"]);
+
+        assert!(records.delete_dns_record("api", "containers").await.unwrap());
+        assert!(!records.delete_dns_record("api", "containers").await.unwrap());
+        assert_eq!(records.list_dns_records().await.unwrap().len(), 1);
+    }
+}
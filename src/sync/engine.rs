@@ -7,20 +7,47 @@ use crate::sync::{
     containers::{ContainerManager, ContainerConfig, ContainerStatus, ContainerState},
     network::{NetworkManager, NetworkConfig, NetworkAllocation},
     monitor::ProcessMonitorService,
-    cleanup::CleanupService,
+    cleanup::{CleanupService, CleanupTarget, ResourceType},
+    orphans::{scan_orphans, OrphanKind, OrphanReport},
     volumes::{VolumeManager, Volume, Mount, MountType},
+    ports::{PortManager, PortBinding},
+    plugins::{PluginManager, PluginRecord},
+    setup_steps::{SetupStepManager, SetupStep},
+    virtual_services::{VirtualServiceManager, VirtualServiceRecord},
+    dns_records::{DnsRecordManager, DnsRecord},
+    image_scans::{ImageScanManager, ImageScanRecord},
+    images::ImageManager,
+    network_attachments::{NetworkAttachmentManager, NetworkAttachment},
+    traffic_mirrors::{TrafficMirrorManager, TrafficMirror},
+    locks::{OperationGuard, OperationLocks, LOCK_WAIT_TIMEOUT},
     error::{SyncResult, SyncError},
 };
 use crate::utils::validation::InputValidator;
 
+/// Generated-name candidates tried before creation fails
+const MAX_NAME_ATTEMPTS: u32 = 32;
+
+/// How often leaked veths, cgroups, rootfs dirs and dead-process rows are swept up
+const ORPHAN_RECONCILE_INTERVAL: Duration = Duration::from_secs(600);
+
 /// Main sync engine that coordinates all stateful resources
 pub struct SyncEngine {
     connection_manager: Arc<ConnectionManager>,
     container_manager: Arc<ContainerManager>,
     network_manager: Arc<NetworkManager>,
     volume_manager: Arc<VolumeManager>,
+    port_manager: Arc<PortManager>,
+    plugin_manager: Arc<PluginManager>,
+    setup_step_manager: Arc<SetupStepManager>,
+    virtual_service_manager: Arc<VirtualServiceManager>,
+    dns_record_manager: Arc<DnsRecordManager>,
+    image_scan_manager: Arc<ImageScanManager>,
+    image_manager: Arc<ImageManager>,
+    network_attachment_manager: Arc<NetworkAttachmentManager>,
+    traffic_mirror_manager: Arc<TrafficMirrorManager>,
     pub monitor_service: Arc<ProcessMonitorService>,
     pub cleanup_service: Arc<CleanupService>,
+    operation_locks: Arc<OperationLocks>,
     
     // Background services control
     background_tasks: Arc<RwLock<Vec<tokio::task::JoinHandle<()>>>>,
@@ -33,8 +60,18 @@ impl Clone for SyncEngine {
             container_manager: Arc::clone(&self.container_manager),
             network_manager: Arc::clone(&self.network_manager),
             volume_manager: Arc::clone(&self.volume_manager),
+            port_manager: Arc::clone(&self.port_manager),
+            plugin_manager: Arc::clone(&self.plugin_manager),
+            setup_step_manager: Arc::clone(&self.setup_step_manager),
+            virtual_service_manager: Arc::clone(&self.virtual_service_manager),
+            dns_record_manager: Arc::clone(&self.dns_record_manager),
+            image_scan_manager: Arc::clone(&self.image_scan_manager),
+            image_manager: Arc::clone(&self.image_manager),
+            network_attachment_manager: Arc::clone(&self.network_attachment_manager),
+            traffic_mirror_manager: Arc::clone(&self.traffic_mirror_manager),
             monitor_service: Arc::clone(&self.monitor_service),
             cleanup_service: Arc::clone(&self.cleanup_service),
+            operation_locks: Arc::clone(&self.operation_locks),
             background_tasks: Arc::clone(&self.background_tasks),
         }
     }
@@ -54,6 +91,15 @@ impl SyncEngine {
         let container_manager = Arc::new(ContainerManager::new(connection_manager.pool().clone()));
         let network_manager = Arc::new(NetworkManager::new(connection_manager.pool().clone()));
         let volume_manager = Arc::new(VolumeManager::new(connection_manager.pool().clone()));
+        let port_manager = Arc::new(PortManager::new(connection_manager.pool().clone()));
+        let plugin_manager = Arc::new(PluginManager::new(connection_manager.pool().clone()));
+        let setup_step_manager = Arc::new(SetupStepManager::new(connection_manager.pool().clone()));
+        let virtual_service_manager = Arc::new(VirtualServiceManager::new(connection_manager.pool().clone()));
+        let dns_record_manager = Arc::new(DnsRecordManager::new(connection_manager.pool().clone()));
+        let image_scan_manager = Arc::new(ImageScanManager::new(connection_manager.pool().clone()));
+        let image_manager = Arc::new(ImageManager::new(connection_manager.pool().clone()));
+        let network_attachment_manager = Arc::new(NetworkAttachmentManager::new(connection_manager.pool().clone()));
+        let traffic_mirror_manager = Arc::new(TrafficMirrorManager::new(connection_manager.pool().clone()));
         let monitor_service = Arc::new(ProcessMonitorService::new(connection_manager.pool().clone()));
         let cleanup_service = Arc::new(CleanupService::new(connection_manager.pool().clone()));
         
@@ -65,8 +111,18 @@ impl SyncEngine {
             container_manager,
             network_manager,
             volume_manager,
+            port_manager,
+            plugin_manager,
+            setup_step_manager,
+            virtual_service_manager,
+            dns_record_manager,
+            image_scan_manager,
+            image_manager,
+            network_attachment_manager,
+            traffic_mirror_manager,
             monitor_service,
             cleanup_service,
+            operation_locks: Arc::new(OperationLocks::new()),
             background_tasks: Arc::new(RwLock::new(Vec::new())),
         };
         
@@ -108,6 +164,15 @@ impl SyncEngine {
         };
         
         let volume_manager = Arc::new(VolumeManager::new(connection_manager.pool().clone()));
+        let port_manager = Arc::new(PortManager::new(connection_manager.pool().clone()));
+        let plugin_manager = Arc::new(PluginManager::new(connection_manager.pool().clone()));
+        let setup_step_manager = Arc::new(SetupStepManager::new(connection_manager.pool().clone()));
+        let virtual_service_manager = Arc::new(VirtualServiceManager::new(connection_manager.pool().clone()));
+        let dns_record_manager = Arc::new(DnsRecordManager::new(connection_manager.pool().clone()));
+        let image_scan_manager = Arc::new(ImageScanManager::new(connection_manager.pool().clone()));
+        let image_manager = Arc::new(ImageManager::new(connection_manager.pool().clone()));
+        let network_attachment_manager = Arc::new(NetworkAttachmentManager::new(connection_manager.pool().clone()));
+        let traffic_mirror_manager = Arc::new(TrafficMirrorManager::new(connection_manager.pool().clone()));
         let monitor_service = Arc::new(ProcessMonitorService::new(connection_manager.pool().clone()));
         
         // Create CleanupService with ICC integration if available
@@ -126,8 +191,18 @@ impl SyncEngine {
             container_manager,
             network_manager,
             volume_manager,
+            port_manager,
+            plugin_manager,
+            setup_step_manager,
+            virtual_service_manager,
+            dns_record_manager,
+            image_scan_manager,
+            image_manager,
+            network_attachment_manager,
+            traffic_mirror_manager,
             monitor_service,
             cleanup_service,
+            operation_locks: Arc::new(OperationLocks::new()),
             background_tasks: Arc::new(RwLock::new(Vec::new())),
         };
         
@@ -148,6 +223,15 @@ impl SyncEngine {
         let container_manager = Arc::new(ContainerManager::new(connection_manager.pool().clone()));
         let network_manager = Arc::new(NetworkManager::with_ip_range(connection_manager.pool().clone(), start_ip, end_ip));
         let volume_manager = Arc::new(VolumeManager::new(connection_manager.pool().clone()));
+        let port_manager = Arc::new(PortManager::new(connection_manager.pool().clone()));
+        let plugin_manager = Arc::new(PluginManager::new(connection_manager.pool().clone()));
+        let setup_step_manager = Arc::new(SetupStepManager::new(connection_manager.pool().clone()));
+        let virtual_service_manager = Arc::new(VirtualServiceManager::new(connection_manager.pool().clone()));
+        let dns_record_manager = Arc::new(DnsRecordManager::new(connection_manager.pool().clone()));
+        let image_scan_manager = Arc::new(ImageScanManager::new(connection_manager.pool().clone()));
+        let image_manager = Arc::new(ImageManager::new(connection_manager.pool().clone()));
+        let network_attachment_manager = Arc::new(NetworkAttachmentManager::new(connection_manager.pool().clone()));
+        let traffic_mirror_manager = Arc::new(TrafficMirrorManager::new(connection_manager.pool().clone()));
         let monitor_service = Arc::new(ProcessMonitorService::new(connection_manager.pool().clone()));
         let cleanup_service = Arc::new(CleanupService::new(connection_manager.pool().clone()));
         
@@ -159,8 +243,18 @@ impl SyncEngine {
             container_manager,
             network_manager,
             volume_manager,
+            port_manager,
+            plugin_manager,
+            setup_step_manager,
+            virtual_service_manager,
+            dns_record_manager,
+            image_scan_manager,
+            image_manager,
+            network_attachment_manager,
+            traffic_mirror_manager,
             monitor_service,
             cleanup_service,
+            operation_locks: Arc::new(OperationLocks::new()),
             background_tasks: Arc::new(RwLock::new(Vec::new())),
         };
         
@@ -195,18 +289,23 @@ impl SyncEngine {
         });
         tasks.push(monitor_cleanup_task);
         
+        let policy = self.cleanup_service.policy().clone();
+        tracing::info!("Cleanup policy: {:?}", policy);
+        
         // Start volume cleanup task (runs every 30 minutes)
-        let volume_manager = self.volume_manager.clone();
-        let volume_cleanup_task = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(1800)); // 30 minutes
-            loop {
-                interval.tick().await;
-                if let Err(e) = volume_manager.cleanup_orphaned_volumes().await {
-                    tracing::warn!("Failed to cleanup orphaned volumes: {}", e);
+        if policy.allows(CleanupTarget::Volumes) {
+            let volume_manager = self.volume_manager.clone();
+            let volume_cleanup_task = tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(1800)); // 30 minutes
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = volume_manager.cleanup_orphaned_volumes().await {
+                        tracing::warn!("Failed to cleanup orphaned volumes: {}", e);
+                    }
                 }
-            }
-        });
-        tasks.push(volume_cleanup_task);
+            });
+            tasks.push(volume_cleanup_task);
+        }
         
         // Start network cleanup task (runs every 15 minutes)
         let network_manager = self.network_manager.clone();
@@ -229,36 +328,60 @@ impl SyncEngine {
         tasks.push(network_cleanup_task);
         
         // Start metrics cleanup task (runs daily)
-        let pool = self.connection_manager.pool().clone();
-        let metrics_cleanup_task = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(86400)); // 24 hours
-            loop {
-                interval.tick().await;
-                let metrics_store = crate::sync::metrics::MetricsStore::new(pool.clone());
-                if let Err(e) = metrics_store.cleanup_old_metrics(7).await { // Keep 7 days
-                    tracing::warn!("Failed to cleanup old metrics: {}", e);
+        if policy.allows(CleanupTarget::Metrics) {
+            let pool = self.connection_manager.pool().clone();
+            let retention_days = policy.metrics_retention_days;
+            let metrics_cleanup_task = tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(86400)); // 24 hours
+                loop {
+                    interval.tick().await;
+                    let metrics_store = crate::sync::metrics::MetricsStore::new(pool.clone());
+                    if let Err(e) = metrics_store.cleanup_old_metrics(retention_days).await {
+                        tracing::warn!("Failed to cleanup old metrics: {}", e);
+                    }
                 }
-            }
-        });
-        tasks.push(metrics_cleanup_task);
+            });
+            tasks.push(metrics_cleanup_task);
+        }
         
         // Start log cleanup task (runs every 6 hours)
-        let container_manager = self.container_manager.clone();
-        let log_cleanup_task = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(21600)); // 6 hours
+        if policy.allows(CleanupTarget::Logs) {
+            let container_manager = self.container_manager.clone();
+            let keep_rows = policy.log_retention_rows;
+            let log_cleanup_task = tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(21600)); // 6 hours
+                loop {
+                    interval.tick().await;
+                    // Get all containers and cleanup logs (keep the newest `keep_rows` entries per container)
+                    if let Ok(containers) = container_manager.list_containers(None).await {
+                        for container in containers {
+                            if let Err(e) = container_manager.cleanup_container_logs(&container.id, keep_rows).await {
+                                tracing::warn!("Failed to cleanup logs for container {}: {}", container.id, e);
+                            }
+                        }
+                    }
+                }
+            });
+            tasks.push(log_cleanup_task);
+        }
+        
+        // Start orphaned resource reconciliation (runs every 10 minutes)
+        let engine = self.clone();
+        let orphan_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ORPHAN_RECONCILE_INTERVAL);
+            interval.tick().await; // Containers restored at startup are still settling
             loop {
                 interval.tick().await;
-                // Get all containers and cleanup logs (keep last 1000 entries per container)
-                if let Ok(containers) = container_manager.list_containers(None).await {
-                    for container in containers {
-                        if let Err(e) = container_manager.cleanup_container_logs(&container.id, 1000).await {
-                            tracing::warn!("Failed to cleanup logs for container {}: {}", container.id, e);
-                        }
+                match engine.reconcile_orphans(false).await {
+                    Ok(report) if !report.resources.is_empty() => {
+                        tracing::info!("Orphan reconciliation found {} leaked resources", report.resources.len());
                     }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Orphan reconciliation failed: {}", e),
                 }
             }
         });
-        tasks.push(log_cleanup_task);
+        tasks.push(orphan_task);
         
         tracing::info!("Started {} background services", tasks.len());
         Ok(())
@@ -275,6 +398,50 @@ impl SyncEngine {
         tracing::info!("Stopped all background services");
     }
     
+    /// Find resources that outlived their container and, unless `dry_run`, schedule cleanup tasks
+    /// for them. Rows marked running whose process is gone are moved to exited first.
+    pub async fn reconcile_orphans(&self, dry_run: bool) -> SyncResult<OrphanReport> {
+        let containers = self.container_manager.list_containers(None).await?;
+        let mut resources = tokio::task::spawn_blocking(move || scan_orphans(&containers)).await
+            .map_err(|e| SyncError::ValidationFailed { message: format!("Orphan scan failed: {}", e) })?;
+        
+        if !dry_run {
+            for orphan in resources.iter_mut().filter(|orphan| orphan.cleanable) {
+                let scheduled = match orphan.kind {
+                    OrphanKind::Veth => self.cleanup_service
+                        .schedule_cleanup(&orphan.container_id, ResourceType::Network, &orphan.resource).await
+                        .map(|id| vec![id]),
+                    OrphanKind::Cgroup => self.cleanup_service
+                        .schedule_cleanup(&orphan.container_id, ResourceType::Cgroup, &orphan.resource).await
+                        .map(|id| vec![id]),
+                    OrphanKind::Rootfs => self.cleanup_service
+                        .schedule_cleanup(&orphan.container_id, ResourceType::Rootfs, &orphan.resource).await
+                        .map(|id| vec![id]),
+                    OrphanKind::DeadProcess => {
+                        // The rootfs stays so the container can be restarted
+                        let _ = self.monitor_service.stop_monitoring(&orphan.container_id).await;
+                        self.container_manager.update_container_state(&orphan.container_id, ContainerState::Exited).await?;
+                        let attributes = std::collections::HashMap::from([("reason".to_string(), "process_gone".to_string())]);
+                        crate::sync::events::global_event_buffer().emit(crate::sync::events::EventType::Died, &orphan.container_id, Some(attributes));
+                        let _ = self.store_container_log(&orphan.container_id, "warn",
+                            &format!("Process {} disappeared without an exit being recorded; marked exited", orphan.resource)).await;
+                        self.cleanup_service.schedule_container_cleanup(&orphan.container_id, None).await
+                    }
+                };
+                match scheduled {
+                    Ok(task_ids) => orphan.task_ids = task_ids,
+                    Err(e) => tracing::warn!("Failed to schedule cleanup of orphaned {} {}: {}", orphan.kind.as_str(), orphan.resource, e),
+                }
+            }
+        }
+        
+        for orphan in &resources {
+            tracing::info!("{} orphaned {} {} ({}): {}", if dry_run { "Found" } else { "Reconciling" },
+                orphan.kind.as_str(), orphan.resource, orphan.container_id, orphan.detail);
+        }
+        Ok(OrphanReport { dry_run, resources })
+    }
+    
     /// Close the sync engine and all connections
     pub async fn close(&self) {
         self.stop_background_services().await;
@@ -286,7 +453,7 @@ impl SyncEngine {
     
     /// PRODUCTION-GRADE: Atomic container + network creation
     /// Eliminates database lock contention by using single transaction for both operations
-    pub async fn create_container(&self, config: ContainerConfig) -> SyncResult<NetworkConfig> {
+    pub async fn create_container(&self, mut config: ContainerConfig) -> SyncResult<NetworkConfig> {
         // Store container ID and network namespace flag before moving config
         let container_id = config.id.clone();
         let enable_network = config.enable_network_namespace;
@@ -298,35 +465,98 @@ impl SyncEngine {
         println!("🔧 [SYNC-CREATE] Creating container {} with networking: {} (atomic)", container_id, enable_network);
         ConsoleLogger::info(&format!("🔧 [SYNC-CREATE] Creating container {} with networking: {} (atomic)", container_id, enable_network));
         
+        // Fail fast on port conflicts before anything is written
+        if !config.ports.is_empty() {
+            self.port_manager.check_conflicts(&config.ports).await?;
+        }
+        
         // ATOMIC TRANSACTION: Container + Network creation in single database operation
         let mut transaction = self.connection_manager.pool().begin().await?;
         
+        // Step 0.5: Names are unique; unnamed containers get a generated adjective_noun name
+        match config.name.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => {
+                let taken: Option<String> = sqlx::query_scalar("SELECT id FROM containers WHERE name = ?")
+                    .bind(name)
+                    .fetch_optional(&mut *transaction)
+                    .await?;
+                if taken.is_some() {
+                    return Err(SyncError::Conflict {
+                        message: format!("Container with name '{}' already exists", name),
+                    });
+                }
+            }
+            None => {
+                let mut generated = None;
+                for attempt in 0..MAX_NAME_ATTEMPTS {
+                    let candidate = crate::sync::names::generate_name(attempt);
+                    let taken: Option<String> = sqlx::query_scalar("SELECT id FROM containers WHERE name = ?")
+                        .bind(&candidate)
+                        .fetch_optional(&mut *transaction)
+                        .await?;
+                    if taken.is_none() {
+                        generated = Some(candidate);
+                        break;
+                    }
+                }
+                let name = generated.ok_or_else(|| SyncError::ValidationFailed {
+                    message: "Could not generate a unique container name".to_string(),
+                })?;
+                ConsoleLogger::debug(&format!("🏷️ [SYNC-CREATE] Generated name {} for {}", name, container_id));
+                config.name = Some(name);
+            }
+        }
+        
         // Step 1: Insert container record within transaction
         let environment_json = serde_json::to_string(&config.environment)?;
+        let fuse_mounts_json = serde_json::to_string(&config.fuse_mounts)?;
+        let labels_json = serde_json::to_string(&config.labels)?;
         let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
         sqlx::query(r#"
             INSERT INTO containers (
-                id, name, image_path, command, environment, state,
-                memory_limit_mb, cpu_limit_percent,
-                enable_network_namespace, enable_pid_namespace, enable_mount_namespace,
+                id, name, image_path, command, environment, working_directory, run_as_user, timezone, locale, state,
+                memory_limit_mb, cpu_limit_percent, cpu_burst_percent, memory_high_mb, memory_swap_mb, memory_swappiness,
+                metrics_interval_seconds, enable_network_namespace, enable_pid_namespace, enable_mount_namespace,
                 enable_uts_namespace, enable_ipc_namespace,
+                enable_time_namespace, monotonic_offset, boottime_offset, enable_cgroup_namespace,
+                enable_user_namespace, allow_fuse, fuse_mounts, labels, immutable, privileged, dns_wildcard,
                 created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
         .bind(&config.id)
         .bind(&config.name)
         .bind(&config.image_path)
         .bind(&config.command)
         .bind(&environment_json)
+        .bind(&config.working_directory)
+        .bind(&config.user)
+        .bind(&config.timezone)
+        .bind(&config.locale)
         .bind(crate::sync::containers::ContainerState::Created.to_string())
         .bind(config.memory_limit_mb)
         .bind(config.cpu_limit_percent)
+        .bind(config.cpu_burst_percent)
+        .bind(config.memory_high_mb)
+        .bind(config.memory_swap_mb)
+        .bind(config.memory_swappiness)
+        .bind(config.metrics_interval_seconds)
         .bind(config.enable_network_namespace)
         .bind(config.enable_pid_namespace)
         .bind(config.enable_mount_namespace)
         .bind(config.enable_uts_namespace)
         .bind(config.enable_ipc_namespace)
+        .bind(config.enable_time_namespace)
+        .bind(config.monotonic_offset)
+        .bind(config.boottime_offset)
+        .bind(config.enable_cgroup_namespace)
+        .bind(config.enable_user_namespace)
+        .bind(config.allow_fuse)
+        .bind(&fuse_mounts_json)
+        .bind(&labels_json)
+        .bind(config.immutable)
+        .bind(config.privileged)
+        .bind(config.dns_wildcard)
         .bind(created_at)
         .bind(created_at)
         .execute(&mut *transaction)
@@ -337,6 +567,19 @@ impl SyncEngine {
         // Step 2: Commit container creation first
         transaction.commit().await?;
         ConsoleLogger::debug(&format!("✅ [ATOMIC] Container record committed for {}", container_id));
+        crate::sync::events::global_event_buffer().set_container_labels(&container_id, config.labels.clone());
+        
+        // Step 2.5: Allocate published host ports
+        if !config.ports.is_empty() {
+            if let Err(e) = self.port_manager.add_port_bindings(&container_id, &config.ports).await {
+                ConsoleLogger::error(&format!("❌ [PORTS] Failed to allocate ports for {}: {}", container_id, e));
+                let _ = self.port_manager.remove_port_bindings(&container_id).await;
+                if let Err(cleanup_err) = self.container_manager.delete_container(&container_id).await {
+                    ConsoleLogger::error(&format!("❌ [CLEANUP] Failed to cleanup container {} after port failure: {}", container_id, cleanup_err));
+                }
+                return Err(e);
+            }
+        }
         
         // Step 3: Network allocation using proper NetworkManager (separate transaction, if enabled)
         let network_config = if enable_network {
@@ -412,6 +655,17 @@ impl SyncEngine {
         Ok(())
     }
     
+    /// Serialize a mutating operation on the container against every other operation on it;
+    /// fails with [`SyncError::Busy`] when the one in progress does not finish in time
+    pub async fn lock_container(&self, container_id: &str, operation: &str) -> SyncResult<OperationGuard> {
+        self.operation_locks.exclusive(container_id, operation, LOCK_WAIT_TIMEOUT).await
+    }
+    
+    /// Hold off mutating operations on the container while `operation` runs alongside others
+    pub async fn lock_container_shared(&self, container_id: &str, operation: &str) -> SyncResult<OperationGuard> {
+        self.operation_locks.shared(container_id, operation, LOCK_WAIT_TIMEOUT).await
+    }
+    
     /// Set container PID and start monitoring
     pub async fn set_container_pid(&self, container_id: &str, pid: nix::unistd::Pid) -> SyncResult<()> {
         // Update container record
@@ -428,6 +682,11 @@ impl SyncEngine {
         self.container_manager.set_container_exit_code(container_id, exit_code).await
     }
     
+    /// Exchange two containers' names atomically (used to hand a name over to a replacement)
+    pub async fn swap_container_names(&self, first_id: &str, second_id: &str) -> SyncResult<()> {
+        self.container_manager.swap_container_names(first_id, second_id).await
+    }
+    
     /// Set rootfs path
     pub async fn set_rootfs_path(&self, container_id: &str, rootfs_path: &str) -> SyncResult<()> {
         self.container_manager.set_rootfs_path(container_id, rootfs_path).await
@@ -469,6 +728,26 @@ impl SyncEngine {
         Ok(())
     }
     
+    // === Port Publishing ===
+    
+    pub async fn get_port_bindings(&self, container_id: &str) -> SyncResult<Vec<PortBinding>> {
+        self.port_manager.get_port_bindings(container_id).await
+    }
+    
+    pub async fn list_port_bindings(&self) -> SyncResult<Vec<PortBinding>> {
+        self.port_manager.list_port_bindings().await
+    }
+    
+    pub async fn remove_port_bindings(&self, container_id: &str) -> SyncResult<()> {
+        self.port_manager.remove_port_bindings(container_id).await
+    }
+    
+    /// Allocate host ports for an existing container; publishing them is up to the caller
+    pub async fn add_port_bindings(&self, container_id: &str, mappings: &[crate::utils::validation::PortMapping]) -> SyncResult<()> {
+        self.port_manager.check_conflicts(mappings).await?;
+        self.port_manager.add_port_bindings(container_id, mappings).await
+    }
+    
     // === Network Management ===
     
     /// Check if container should have network setup
@@ -538,6 +817,126 @@ impl SyncEngine {
         self.container_manager.get_container_by_name(name).await
     }
     
+    /// Stored creation settings of a container (without its published ports)
+    pub async fn get_container_config(&self, container_id: &str) -> SyncResult<ContainerConfig> {
+        self.container_manager.get_container_config(container_id).await
+    }
+    
+    /// Labels of every labelled container, keyed by container ID
+    pub async fn list_container_labels(&self) -> SyncResult<std::collections::HashMap<String, std::collections::HashMap<String, String>>> {
+        self.container_manager.list_container_labels().await
+    }
+    
+    /// Store (or with `None`, clear) a container's network policy; enforcement is up to the caller
+    pub async fn set_network_policy(&self, container_id: &str, policy: Option<&crate::daemon::net_policy::NetworkPolicy>) -> SyncResult<()> {
+        self.container_manager.set_network_policy(container_id, policy).await
+    }
+    
+    pub async fn get_network_policy(&self, container_id: &str) -> SyncResult<Option<crate::daemon::net_policy::NetworkPolicy>> {
+        self.container_manager.get_network_policy(container_id).await
+    }
+    
+    /// Network policies of every container that has one, keyed by container ID
+    pub async fn list_network_policies(&self) -> SyncResult<std::collections::HashMap<String, crate::daemon::net_policy::NetworkPolicy>> {
+        self.container_manager.list_network_policies().await
+    }
+    
+    /// Store a container's lifecycle hooks (an empty list clears them); registering them is up to the caller
+    pub async fn set_container_hooks(&self, container_id: &str, hooks: &[crate::daemon::hooks::LifecycleHook]) -> SyncResult<()> {
+        self.container_manager.set_container_hooks(container_id, hooks).await
+    }
+    
+    /// Store the hooks run before each start of a container; an empty list clears them
+    pub async fn set_prestart_hooks(&self, container_id: &str, hooks: &[crate::daemon::prestart::PrestartHook]) -> SyncResult<()> {
+        self.container_manager.set_prestart_hooks(container_id, hooks).await
+    }
+    
+    /// Store the setup commands run inside a container before its command; an empty list clears them
+    pub async fn set_setup_commands(&self, container_id: &str, commands: &[String]) -> SyncResult<()> {
+        self.container_manager.set_setup_commands(container_id, commands).await
+    }
+    
+    /// Set or clear the network plugin that plumbs a container after quilt's own network setup
+    pub async fn set_network_plugin(&self, container_id: &str, plugin: Option<&str>) -> SyncResult<()> {
+        self.container_manager.set_network_plugin(container_id, plugin).await
+    }
+    
+    pub async fn get_network_plugin(&self, container_id: &str) -> SyncResult<Option<String>> {
+        self.container_manager.get_network_plugin(container_id).await
+    }
+    
+    /// Set or clear the proxy a container's web traffic goes through
+    pub async fn set_egress_proxy(&self, container_id: &str, proxy: Option<&crate::icc::network::EgressProxy>) -> SyncResult<()> {
+        self.container_manager.set_egress_proxy(container_id, proxy).await
+    }
+    
+    pub async fn get_egress_proxy(&self, container_id: &str) -> SyncResult<Option<crate::icc::network::EgressProxy>> {
+        self.container_manager.get_egress_proxy(container_id).await
+    }
+    
+    /// Store the gates checked before a container is reported Running; `None` clears them
+    pub async fn set_readiness_gates(&self, container_id: &str, readiness: Option<&crate::daemon::readiness_gates::ReadinessGates>) -> SyncResult<()> {
+        self.container_manager.set_readiness_gates(container_id, readiness).await
+    }
+    
+    pub async fn get_readiness_gates(&self, container_id: &str) -> SyncResult<Option<crate::daemon::readiness_gates::ReadinessGates>> {
+        self.container_manager.get_readiness_gates(container_id).await
+    }
+    
+    /// Set or clear the seconds a container's clocks are shifted from host time
+    pub async fn set_time_offset(&self, container_id: &str, offset: Option<i64>) -> SyncResult<()> {
+        self.container_manager.set_time_offset(container_id, offset).await
+    }
+    
+    pub async fn get_time_offset(&self, container_id: &str) -> SyncResult<Option<i64>> {
+        self.container_manager.get_time_offset(container_id).await
+    }
+    
+    pub async fn set_metrics_collection(&self, container_id: &str, enabled: bool, interval_seconds: Option<u32>) -> SyncResult<()> {
+        self.container_manager.set_metrics_collection(container_id, enabled, interval_seconds).await
+    }
+    
+    pub async fn get_metrics_schedule(&self, container_id: &str) -> SyncResult<crate::sync::containers::MetricsSchedule> {
+        self.container_manager.get_metrics_schedule(container_id).await
+    }
+    
+    pub async fn list_metrics_schedules(&self) -> SyncResult<Vec<crate::sync::containers::MetricsSchedule>> {
+        self.container_manager.list_metrics_schedules().await
+    }
+    
+    pub async fn set_priority_class(&self, container_id: &str, class: crate::daemon::priority::PriorityClass) -> SyncResult<()> {
+        self.container_manager.set_priority_class(container_id, class).await
+    }
+    
+    pub async fn get_priority_class(&self, container_id: &str) -> SyncResult<crate::daemon::priority::PriorityClass> {
+        self.container_manager.get_priority_class(container_id).await
+    }
+    
+    /// Priority class of every container, keyed by container ID
+    pub async fn list_priority_classes(&self) -> SyncResult<std::collections::HashMap<String, crate::daemon::priority::PriorityClass>> {
+        self.container_manager.list_priority_classes().await
+    }
+    
+    /// Register the stored lifecycle hooks of every container after a restart
+    pub async fn load_container_hooks(&self) -> SyncResult<()> {
+        for (container_id, (name, hooks)) in self.container_manager.list_container_hooks().await? {
+            crate::daemon::hooks::register(&container_id, &name, hooks);
+        }
+        Ok(())
+    }
+    
+    /// Seed the event buffer's label index from stored containers and volumes after a restart
+    pub async fn load_event_labels(&self) -> SyncResult<()> {
+        let events = crate::sync::events::global_event_buffer();
+        for (container_id, labels) in self.list_container_labels().await? {
+            events.set_container_labels(&container_id, labels);
+        }
+        for volume in self.list_volumes(None).await? {
+            events.set_volume_labels(&volume.name, volume.labels);
+        }
+        Ok(())
+    }
+    
     /// Get database connection pool for advanced operations
     pub fn pool(&self) -> &sqlx::SqlitePool {
         self.connection_manager.pool()
@@ -631,7 +1030,23 @@ impl SyncEngine {
         labels: std::collections::HashMap<String, String>,
         options: std::collections::HashMap<String, String>,
     ) -> SyncResult<Volume> {
-        self.volume_manager.create_volume(name, driver, labels, options).await
+        let volume = self.volume_manager.create_volume(name, driver, labels, options).await?;
+        crate::sync::events::global_event_buffer().set_volume_labels(&volume.name, volume.labels.clone());
+        Ok(volume)
+    }
+    
+    /// Record a volume a plugin driver created; `mount_point` is the host path the plugin reported
+    pub async fn create_driver_volume(
+        &self,
+        name: &str,
+        driver: &str,
+        mount_point: String,
+        labels: std::collections::HashMap<String, String>,
+        options: std::collections::HashMap<String, String>,
+    ) -> SyncResult<Volume> {
+        let volume = self.volume_manager.create_driver_volume(name, driver, mount_point, labels, options).await?;
+        crate::sync::events::global_event_buffer().set_volume_labels(&volume.name, volume.labels.clone());
+        Ok(volume)
     }
     
     /// Get volume by name
@@ -646,7 +1061,162 @@ impl SyncEngine {
     
     /// Remove a volume
     pub async fn remove_volume(&self, name: &str, force: bool) -> SyncResult<()> {
-        self.volume_manager.remove_volume(name, force).await
+        self.volume_manager.remove_volume(name, force).await?;
+        crate::sync::events::global_event_buffer().forget_volume_labels(name);
+        Ok(())
+    }
+    
+    // Plugin registry methods
+    
+    /// Store a plugin registration, replacing an earlier one with the same name
+    pub async fn register_plugin(&self, name: &str, endpoint: &str, capabilities: &[String]) -> SyncResult<PluginRecord> {
+        self.plugin_manager.register_plugin(name, endpoint, capabilities).await
+    }
+    
+    /// Returns whether the plugin was registered
+    pub async fn unregister_plugin(&self, name: &str) -> SyncResult<bool> {
+        self.plugin_manager.unregister_plugin(name).await
+    }
+    
+    pub async fn list_plugins(&self) -> SyncResult<Vec<PluginRecord>> {
+        self.plugin_manager.list_plugins().await
+    }
+    
+    // Virtual service methods
+    
+    /// Store a virtual service; fails with a conflict when the name is taken
+    pub async fn create_virtual_service(&self, name: &str, selector: &std::collections::HashMap<String, String>) -> SyncResult<VirtualServiceRecord> {
+        self.virtual_service_manager.create_virtual_service(name, selector).await
+    }
+    
+    /// Returns whether the virtual service existed
+    pub async fn delete_virtual_service(&self, name: &str) -> SyncResult<bool> {
+        self.virtual_service_manager.delete_virtual_service(name).await
+    }
+    
+    pub async fn list_virtual_services(&self) -> SyncResult<Vec<VirtualServiceRecord>> {
+        self.virtual_service_manager.list_virtual_services().await
+    }
+    
+    // DNS record methods
+    
+    /// Store the record of `name` in `view`, replacing an earlier one
+    pub async fn set_dns_record(&self, name: &str, view: &str, addresses: &[String]) -> SyncResult<DnsRecord> {
+        self.dns_record_manager.set_dns_record(name, view, addresses).await
+    }
+    
+    /// Returns whether the record existed
+    pub async fn delete_dns_record(&self, name: &str, view: &str) -> SyncResult<bool> {
+        self.dns_record_manager.delete_dns_record(name, view).await
+    }
+    
+    pub async fn list_dns_records(&self) -> SyncResult<Vec<DnsRecord>> {
+        self.dns_record_manager.list_dns_records().await
+    }
+    
+    // Image scan methods
+    
+    /// Store the findings of a scan of `image_path`, replacing any earlier scan
+    pub async fn store_image_scan(&self, image_path: &str, fingerprint: &str, scanner: &str, findings: &[crate::daemon::image_scan::VulnerabilityFinding]) -> SyncResult<ImageScanRecord> {
+        self.image_scan_manager.store_image_scan(image_path, fingerprint, scanner, findings).await
+    }
+    
+    pub async fn get_image_scan(&self, image_path: &str) -> SyncResult<Option<ImageScanRecord>> {
+        self.image_scan_manager.get_image_scan(image_path).await
+    }
+    
+    pub async fn list_image_scans(&self) -> SyncResult<Vec<ImageScanRecord>> {
+        self.image_scan_manager.list_image_scans().await
+    }
+    
+    // Image store methods
+    
+    /// Index an image imported into the local store from the file `source`
+    pub async fn record_image(&self, source: &str, fingerprint: &str, image: &crate::daemon::images::StoredImage) -> SyncResult<()> {
+        self.image_manager.record_image(source, fingerprint, image).await
+    }
+    
+    /// The stored image of `source`, unless the file changed since it was imported
+    pub async fn find_image_by_source(&self, source: &str, fingerprint: &str) -> SyncResult<Option<crate::daemon::images::StoredImage>> {
+        self.image_manager.find_image_by_source(source, fingerprint).await
+    }
+    
+    pub async fn find_image_sources_by_tag(&self, tag: &str) -> SyncResult<Vec<String>> {
+        self.image_manager.find_image_sources_by_tag(tag).await
+    }
+    
+    pub async fn mark_image_source_used(&self, source: &str) -> SyncResult<()> {
+        self.image_manager.mark_image_source_used(source).await
+    }
+    
+    pub async fn list_images(&self) -> SyncResult<Vec<crate::sync::images::ImageRecord>> {
+        self.image_manager.list_images().await
+    }
+    
+    pub async fn get_image(&self, digest: &str) -> SyncResult<Option<crate::sync::images::ImageRecord>> {
+        self.image_manager.get_image(digest).await
+    }
+    
+    /// Forget a stored image; its files in the store are removed by the caller
+    pub async fn remove_image(&self, digest: &str) -> SyncResult<bool> {
+        self.image_manager.remove_image(digest).await
+    }
+    
+    // Network attachment methods
+    
+    /// Allocate the container an address on an additional network
+    pub async fn attach_network(&self, container_id: &str, network: &str) -> SyncResult<NetworkAttachment> {
+        self.network_attachment_manager.attach_network(container_id, network).await
+    }
+    
+    pub async fn detach_network(&self, container_id: &str, network: &str) -> SyncResult<Option<NetworkAttachment>> {
+        self.network_attachment_manager.detach_network(container_id, network).await
+    }
+    
+    pub async fn get_network_attachments(&self, container_id: &str) -> SyncResult<Vec<NetworkAttachment>> {
+        self.network_attachment_manager.get_network_attachments(container_id).await
+    }
+    
+    pub async fn list_network_attachments(&self) -> SyncResult<Vec<NetworkAttachment>> {
+        self.network_attachment_manager.list_network_attachments().await
+    }
+    
+    // Traffic mirror methods
+    
+    pub async fn set_traffic_mirror(&self, source_container_id: &str, observer_container_id: &str, direction: &str) -> SyncResult<TrafficMirror> {
+        self.traffic_mirror_manager.set_traffic_mirror(source_container_id, observer_container_id, direction).await
+    }
+    
+    pub async fn remove_traffic_mirror(&self, source_container_id: &str) -> SyncResult<Option<TrafficMirror>> {
+        self.traffic_mirror_manager.remove_traffic_mirror(source_container_id).await
+    }
+    
+    pub async fn get_traffic_mirror(&self, source_container_id: &str) -> SyncResult<Option<TrafficMirror>> {
+        self.traffic_mirror_manager.get_traffic_mirror(source_container_id).await
+    }
+    
+    /// Mirrors the container is the source or the observer of
+    pub async fn get_container_traffic_mirrors(&self, container_id: &str) -> SyncResult<Vec<TrafficMirror>> {
+        self.traffic_mirror_manager.get_container_traffic_mirrors(container_id).await
+    }
+    
+    pub async fn list_traffic_mirrors(&self) -> SyncResult<Vec<TrafficMirror>> {
+        self.traffic_mirror_manager.list_traffic_mirrors().await
+    }
+    
+    // Setup step methods
+    
+    /// Start a container's setup over with every command pending
+    pub async fn reset_setup_steps(&self, container_id: &str, commands: &[String]) -> SyncResult<()> {
+        self.setup_step_manager.reset_steps(container_id, commands).await
+    }
+    
+    pub async fn finish_setup_step(&self, container_id: &str, step_index: i64, succeeded: bool, output: &str, duration_ms: i64) -> SyncResult<()> {
+        self.setup_step_manager.finish_step(container_id, step_index, succeeded, output, duration_ms).await
+    }
+    
+    pub async fn list_setup_steps(&self, container_id: &str) -> SyncResult<Vec<SetupStep>> {
+        self.setup_step_manager.list_steps(container_id).await
     }
     
     /// Clean up orphaned volumes
@@ -654,6 +1224,63 @@ impl SyncEngine {
         self.volume_manager.cleanup_orphaned_volumes().await
     }
 
+    /// Run cleanup now for `targets` (the policy's `force_targets` when empty), ignoring whether the
+    /// policy enables them: a container's pending/failed tasks, unused volumes, metrics past
+    /// retention and log rows past retention (for `container_id`, or every container when `None`).
+    /// With `dry_run` nothing is removed and the report says what would be.
+    pub async fn force_cleanup(&self, container_id: Option<&str>, targets: &[CleanupTarget], dry_run: bool) -> SyncResult<Vec<String>> {
+        let policy = self.cleanup_service.policy();
+        let targets: Vec<CleanupTarget> = if targets.is_empty() { policy.force_targets.clone() } else { targets.to_vec() };
+        let mut report = Vec::new();
+        
+        if let Some(container_id) = container_id {
+            report.extend(self.cleanup_service.force_cleanup(container_id, &targets, dry_run).await?);
+        }
+        
+        if targets.contains(&CleanupTarget::Volumes) {
+            if dry_run {
+                report.extend(self.volume_manager.list_orphaned_volumes().await?
+                    .into_iter()
+                    .map(|volume| format!("would remove unused volume {}", volume)));
+            } else {
+                let cleaned = self.volume_manager.cleanup_orphaned_volumes().await?;
+                if cleaned > 0 {
+                    report.push(format!("Cleaned {} orphaned volumes", cleaned));
+                }
+            }
+        }
+        
+        if targets.contains(&CleanupTarget::Metrics) {
+            let store = crate::sync::metrics::MetricsStore::new(self.connection_manager.pool().clone());
+            let days = policy.metrics_retention_days;
+            let count = if dry_run { store.count_old_metrics(days).await? } else { store.cleanup_old_metrics(days).await? };
+            if count > 0 {
+                report.push(format!("{} {} metric records older than {} days", if dry_run { "would remove" } else { "Cleaned" }, count, days));
+            }
+        }
+        
+        if targets.contains(&CleanupTarget::Logs) {
+            let container_ids = match container_id {
+                Some(id) => vec![id.to_string()],
+                None => self.container_manager.list_containers(None).await?.into_iter().map(|c| c.id).collect(),
+            };
+            let keep = policy.log_retention_rows;
+            let mut count = 0;
+            for id in &container_ids {
+                count += if dry_run {
+                    self.container_manager.count_excess_container_logs(id, keep).await?
+                } else {
+                    self.container_manager.cleanup_container_logs(id, keep).await?
+                };
+            }
+            if count > 0 {
+                report.push(format!("{} {} log rows beyond the newest {} per container", if dry_run { "would remove" } else { "Cleaned" }, count, keep));
+            }
+        }
+        
+        Ok(report)
+    }
+    
     /// Perform comprehensive network cleanup using ICC NetworkManager integration
     pub async fn comprehensive_network_cleanup(&self) -> SyncResult<Vec<String>> {
         self.cleanup_service.perform_comprehensive_network_cleanup().await
@@ -717,11 +1344,21 @@ impl SyncEngine {
         self.container_manager.store_log(container_id, level, message).await
     }
     
+    /// Store a log entry recording an action taken on behalf of `actor`
+    pub async fn store_container_log_as(&self, container_id: &str, level: &str, message: &str, actor: &str) -> SyncResult<()> {
+        self.container_manager.store_log_as(container_id, level, message, actor).await
+    }
+    
     /// Get logs for a container
     pub async fn get_container_logs(&self, container_id: &str, limit: Option<u32>) -> SyncResult<Vec<crate::sync::containers::LogEntry>> {
         self.container_manager.get_container_logs(container_id, limit).await
     }
     
+    /// Stored log rows matching a search, newest first, with their container IDs
+    pub async fn search_container_logs(&self, search: &crate::sync::containers::LogSearch) -> SyncResult<Vec<(String, crate::sync::containers::LogEntry)>> {
+        self.container_manager.search_logs(search).await
+    }
+    
     /// Clean up old logs for a container
     pub async fn cleanup_container_logs(&self, container_id: &str, keep_count: u32) -> SyncResult<u64> {
         self.container_manager.cleanup_container_logs(container_id, keep_count).await
@@ -787,6 +1424,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         // Create container
@@ -842,6 +1480,7 @@ mod tests {
             enable_mount_namespace: true,
             enable_uts_namespace: true,
             enable_ipc_namespace: true,
+            ..Default::default()
         };
         
         // Create container
@@ -858,6 +1497,148 @@ mod tests {
         engine.close().await;
     }
     
+    #[tokio::test]
+    async fn test_swap_container_names() {
+        let engine = setup_test_engine().await;
+        for (id, name) in [("blue", "web"), ("green", "brave_otter")] {
+            engine.create_container(ContainerConfig {
+                id: id.to_string(),
+                name: Some(name.to_string()),
+                image_path: "/path/to/image".to_string(),
+                command: "tail -f /dev/null".to_string(),
+                enable_network_namespace: false,
+                ..Default::default()
+            }).await.unwrap();
+        }
+        
+        engine.swap_container_names("blue", "green").await.unwrap();
+        assert_eq!(engine.get_container_by_name("web").await.unwrap(), "green");
+        assert_eq!(engine.get_container_by_name("brave_otter").await.unwrap(), "blue");
+        
+        // Nothing changes when either container is missing
+        assert!(matches!(engine.swap_container_names("blue", "missing").await, Err(SyncError::NotFound { .. })));
+        assert_eq!(engine.get_container_by_name("web").await.unwrap(), "green");
+        
+        engine.close().await;
+    }
+    
+    #[tokio::test]
+    async fn test_unnamed_container_gets_generated_name() {
+        let engine = setup_test_engine().await;
+        
+        let config = ContainerConfig {
+            id: "unnamed-container".to_string(),
+            name: None,
+            image_path: "/path/to/image".to_string(),
+            command: "echo hello".to_string(),
+            enable_network_namespace: false,
+            ..Default::default()
+        };
+        engine.create_container(config).await.unwrap();
+        
+        let status = engine.get_container_status("unnamed-container").await.unwrap();
+        let name = status.name.expect("generated name");
+        assert!(name.contains('_'));
+        assert_eq!(engine.get_container_by_name(&name).await.unwrap(), "unnamed-container");
+        
+        // The generated name is reserved like a user-supplied one
+        let duplicate = ContainerConfig {
+            id: "duplicate-name".to_string(),
+            name: Some(name),
+            image_path: "/path/to/image".to_string(),
+            command: "echo hello".to_string(),
+            enable_network_namespace: false,
+            ..Default::default()
+        };
+        assert!(matches!(engine.create_container(duplicate).await, Err(SyncError::ValidationFailed { .. })));
+        
+        engine.close().await;
+    }
+    
+    #[tokio::test]
+    async fn test_get_container_config_round_trip() {
+        let engine = setup_test_engine().await;
+        
+        let config = ContainerConfig {
+            id: "source-container".to_string(),
+            name: Some("source".to_string()),
+            image_path: "/path/to/image".to_string(),
+            command: "sleep 60".to_string(),
+            environment: HashMap::from([("MODE".to_string(), "agent".to_string())]),
+            working_directory: Some("/srv".to_string()),
+            user: Some("app:staff".to_string()),
+            timezone: Some("Europe/Berlin".to_string()),
+            memory_limit_mb: Some(256),
+            enable_network_namespace: false,
+            enable_pid_namespace: true,
+            immutable: true,
+            privileged: true,
+            dns_wildcard: true,
+            cpu_limit_percent: Some(50.0),
+            cpu_burst_percent: Some(25.0),
+            memory_high_mb: Some(256),
+            memory_swap_mb: Some(0),
+            metrics_interval_seconds: Some(10),
+            enable_time_namespace: true,
+            boottime_offset: -60,
+            enable_cgroup_namespace: true,
+            labels: HashMap::from([("app".to_string(), "sandbox".to_string())]),
+            ..Default::default()
+        };
+        engine.create_container(config).await.unwrap();
+        
+        let stored = engine.get_container_config("source-container").await.unwrap();
+        assert_eq!(stored.name.as_deref(), Some("source"));
+        assert_eq!(stored.command, "sleep 60");
+        assert_eq!(stored.environment.get("MODE").map(String::as_str), Some("agent"));
+        assert_eq!((stored.working_directory.as_deref(), stored.user.as_deref()), (Some("/srv"), Some("app:staff")));
+        assert_eq!((stored.timezone.as_deref(), stored.locale.as_deref()), (Some("Europe/Berlin"), None));
+        assert_eq!(stored.memory_limit_mb, Some(256));
+        assert!(stored.enable_pid_namespace && !stored.enable_network_namespace);
+        assert!(stored.immutable && stored.privileged && stored.dns_wildcard);
+        assert_eq!((stored.memory_high_mb, stored.memory_swap_mb, stored.memory_swappiness), (Some(256), Some(0), None));
+        assert_eq!((stored.cpu_limit_percent, stored.cpu_burst_percent), (Some(50.0), Some(25.0)));
+        assert_eq!(stored.metrics_interval_seconds, Some(10));
+        assert_eq!(engine.get_metrics_schedule("source-container").await.unwrap().interval_seconds, Some(10));
+        assert!(stored.enable_time_namespace && stored.enable_cgroup_namespace);
+        assert_eq!((stored.monotonic_offset, stored.boottime_offset), (0, -60));
+        assert_eq!(stored.labels.get("app").map(String::as_str), Some("sandbox"));
+        assert!(matches!(engine.get_container_config("missing").await, Err(SyncError::NotFound { .. })));
+        
+        engine.close().await;
+    }
+    
+    #[tokio::test]
+    async fn test_metrics_schedules() {
+        let engine = setup_test_engine().await;
+        
+        for id in ["sampled", "quiet"] {
+            let config = ContainerConfig {
+                id: id.to_string(),
+                image_path: "/path/to/image".to_string(),
+                command: "sleep 60".to_string(),
+                ..Default::default()
+            };
+            engine.create_container(config).await.unwrap();
+            engine.update_container_state(id, ContainerState::Starting).await.unwrap();
+            engine.update_container_state(id, ContainerState::Running).await.unwrap();
+        }
+        
+        // Collection is on by default; disabling keeps the interval unless one is given
+        let schedule = engine.get_metrics_schedule("quiet").await.unwrap();
+        assert_eq!((schedule.enabled, schedule.interval_seconds), (true, None));
+        engine.set_metrics_collection("quiet", false, None).await.unwrap();
+        engine.set_metrics_collection("sampled", true, Some(5)).await.unwrap();
+        
+        let mut schedules = engine.list_metrics_schedules().await.unwrap();
+        schedules.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+        let settings: Vec<_> = schedules.iter().map(|s| (s.container_id.as_str(), s.enabled, s.interval_seconds)).collect();
+        assert_eq!(settings, vec![("quiet", false, None), ("sampled", true, Some(5))]);
+        assert!(matches!(engine.set_metrics_collection("missing", true, None).await, Err(SyncError::NotFound { .. })));
+        
+        engine.close().await;
+    }
+    
     #[tokio::test]
     async fn test_stats_collection() {
         let engine = setup_test_engine().await;
@@ -877,6 +1658,7 @@ mod tests {
                 enable_mount_namespace: true,
                 enable_uts_namespace: true,
                 enable_ipc_namespace: true,
+                ..Default::default()
             };
             
             engine.create_container(config).await.unwrap();
@@ -29,8 +29,14 @@ pub enum SyncError {
     #[error("System time error: {0}")]
     SystemTime(#[from] std::time::SystemTimeError),
     
+    #[error("Container {container_id} is busy: {operation} in progress")]
+    Busy { container_id: String, operation: String },
+    
     #[error("Resource validation failed: {message}")]
     ValidationFailed { message: String },
+    
+    #[error("{message}")]
+    Conflict { message: String },
 }
 
 pub type SyncResult<T> = Result<T, SyncError>; 
\ No newline at end of file
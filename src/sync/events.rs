@@ -1,10 +1,18 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use parking_lot::RwLock;
 use serde::{Serialize, Deserialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const DEFAULT_BUFFER_SIZE: usize = 1000;
+/// Events a slow subscriber may fall behind by before it starts missing some
+const SUBSCRIBER_CAPACITY: usize = 256;
+
+/// Event attribute naming who triggered the event
+pub const ACTOR_ATTRIBUTE: &str = "actor";
+
+/// Actor recorded for actions the daemon takes on its own (monitor exits, cleanup, restarts)
+pub const DAEMON_ACTOR: &str = "daemon";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerEvent {
@@ -12,6 +20,12 @@ pub struct ContainerEvent {
     pub container_id: String,
     pub timestamp: u64,
     pub attributes: std::collections::HashMap<String, String>,
+    /// Labels of the container at the time of the event
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Labels of the volume a volume event refers to
+    #[serde(default)]
+    pub volume_labels: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -29,6 +43,13 @@ pub enum EventType {
     NetworkDisconnect,
     VolumeMount,
     VolumeUnmount,
+    /// The daemon repaired host networking (bridge, veth attachment or NAT rules) that drifted
+    NetworkRepaired,
+    /// The process monitor lost track of the container process: it vanished without an exit
+    /// status, or checking it failed repeatedly
+    MonitorLost,
+    /// The container's PID now belongs to a different process
+    PidReused,
 }
 
 impl EventType {
@@ -47,9 +68,17 @@ impl EventType {
             EventType::NetworkDisconnect => "network_disconnect",
             EventType::VolumeMount => "volume_mount",
             EventType::VolumeUnmount => "volume_unmount",
+            EventType::NetworkRepaired => "network_repaired",
+            EventType::MonitorLost => "monitor_lost",
+            EventType::PidReused => "pid_reused",
         }
     }
 
+    /// Whether the event concerns a volume; its `source` attribute names the volume
+    pub fn is_volume_event(&self) -> bool {
+        matches!(self, EventType::VolumeMount | EventType::VolumeUnmount)
+    }
+
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "created" => Some(EventType::Created),
@@ -65,15 +94,27 @@ impl EventType {
             "network_disconnect" => Some(EventType::NetworkDisconnect),
             "volume_mount" => Some(EventType::VolumeMount),
             "volume_unmount" => Some(EventType::VolumeUnmount),
+            "network_repaired" => Some(EventType::NetworkRepaired),
+            "monitor_lost" => Some(EventType::MonitorLost),
+            "pid_reused" => Some(EventType::PidReused),
             _ => None,
         }
     }
 }
 
+/// True when `labels` carries every key/value pair of `selector`
+pub fn matches_selector(labels: &HashMap<String, String>, selector: &HashMap<String, String>) -> bool {
+    selector.iter().all(|(key, value)| labels.get(key) == Some(value))
+}
+
 /// Ring buffer for container events
 pub struct EventRingBuffer {
     buffer: Arc<RwLock<VecDeque<ContainerEvent>>>,
     max_size: usize,
+    // Labels stamped onto events as they are recorded, keyed by container ID and volume name
+    container_labels: RwLock<HashMap<String, HashMap<String, String>>>,
+    volume_labels: RwLock<HashMap<String, HashMap<String, String>>>,
+    subscribers: tokio::sync::broadcast::Sender<ContainerEvent>,
 }
 
 impl EventRingBuffer {
@@ -81,11 +122,49 @@ impl EventRingBuffer {
         Self {
             buffer: Arc::new(RwLock::new(VecDeque::new())),
             max_size: max_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+            container_labels: RwLock::new(HashMap::new()),
+            volume_labels: RwLock::new(HashMap::new()),
+            subscribers: tokio::sync::broadcast::channel(SUBSCRIBER_CAPACITY).0,
         }
     }
 
+    /// Receive every event pushed from now on, in order
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<ContainerEvent> {
+        self.subscribers.subscribe()
+    }
+
+    /// Remember a container's labels so its events can be selected by label
+    pub fn set_container_labels(&self, container_id: &str, labels: HashMap<String, String>) {
+        if labels.is_empty() {
+            self.container_labels.write().remove(container_id);
+        } else {
+            self.container_labels.write().insert(container_id.to_string(), labels);
+        }
+    }
+
+    /// Drop a removed container's labels; events already recorded keep theirs
+    pub fn forget_container_labels(&self, container_id: &str) {
+        self.container_labels.write().remove(container_id);
+    }
+
+    /// Remember a volume's labels so mount events can be selected by volume label
+    pub fn set_volume_labels(&self, volume_name: &str, labels: HashMap<String, String>) {
+        if labels.is_empty() {
+            self.volume_labels.write().remove(volume_name);
+        } else {
+            self.volume_labels.write().insert(volume_name.to_string(), labels);
+        }
+    }
+
+    pub fn forget_volume_labels(&self, volume_name: &str) {
+        self.volume_labels.write().remove(volume_name);
+    }
+
     /// Add an event to the ring buffer
     pub fn push(&self, event: ContainerEvent) {
+        if self.subscribers.receiver_count() > 0 {
+            let _ = self.subscribers.send(event.clone());
+        }
         let mut buffer = self.buffer.write();
         
         // Remove oldest events if at capacity
@@ -96,13 +175,34 @@ impl EventRingBuffer {
         buffer.push_back(event);
     }
 
-    /// Create and push an event
+    /// Create and push an event triggered by the daemon itself
     pub fn emit(
         &self,
         event_type: EventType,
         container_id: &str,
         attributes: Option<std::collections::HashMap<String, String>>,
     ) {
+        self.emit_as(event_type, container_id, DAEMON_ACTOR, attributes);
+    }
+
+    /// Create and push an event, attributing it to `actor` (an API caller or the daemon)
+    pub fn emit_as(
+        &self,
+        event_type: EventType,
+        container_id: &str,
+        actor: &str,
+        attributes: Option<std::collections::HashMap<String, String>>,
+    ) {
+        let mut attributes = attributes.unwrap_or_default();
+        attributes.insert(ACTOR_ATTRIBUTE.to_string(), actor.to_string());
+        let labels = self.container_labels.read().get(container_id).cloned().unwrap_or_default();
+        let volume_labels = if event_type.is_volume_event() {
+            attributes.get("source")
+                .and_then(|volume| self.volume_labels.read().get(volume).cloned())
+                .unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
         let event = ContainerEvent {
             event_type,
             container_id: container_id.to_string(),
@@ -110,7 +210,9 @@ impl EventRingBuffer {
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
-            attributes: attributes.unwrap_or_default(),
+            attributes,
+            labels,
+            volume_labels,
         };
         
         self.push(event);
@@ -118,12 +220,14 @@ impl EventRingBuffer {
 
     // get_all() method removed - not used in core functionality
 
-    /// Get events filtered by criteria
+    /// Get events filtered by criteria. A volume label selector only matches volume events.
     pub fn get_filtered(
         &self,
         container_ids: Option<&[String]>,
         event_types: Option<&[EventType]>,
         since_timestamp: Option<u64>,
+        label_selector: Option<&HashMap<String, String>>,
+        volume_label_selector: Option<&HashMap<String, String>>,
     ) -> Vec<ContainerEvent> {
         let buffer = self.buffer.read();
         
@@ -152,6 +256,21 @@ impl EventRingBuffer {
                     }
                 }
                 
+                // Filter by container labels
+                if let Some(selector) = label_selector {
+                    if !matches_selector(&event.labels, selector) {
+                        return false;
+                    }
+                }
+                
+                // Filter by volume labels
+                if let Some(selector) = volume_label_selector {
+                    if !selector.is_empty()
+                        && (!event.event_type.is_volume_event() || !matches_selector(&event.volume_labels, selector)) {
+                        return false;
+                    }
+                }
+                
                 true
             })
             .cloned()
@@ -208,6 +327,8 @@ mod tests {
             Some(&["container-1".to_string()]),
             None,
             None,
+            None,
+            None,
         );
         assert_eq!(events.len(), 3);
         
@@ -216,7 +337,73 @@ mod tests {
             None,
             Some(&[EventType::Created]),
             None,
+            None,
+            None,
         );
         assert_eq!(events.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_label_selector_filtering() {
+        let buffer = EventRingBuffer::new(None);
+        let labels = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        
+        buffer.set_container_labels("web-1", labels(&[("app", "web"), ("tenant", "acme")]));
+        buffer.set_container_labels("db-1", labels(&[("app", "db"), ("tenant", "acme")]));
+        buffer.set_volume_labels("web-data", labels(&[("tier", "ssd")]));
+        
+        buffer.emit(EventType::Created, "web-1", None);
+        buffer.emit(EventType::Created, "db-1", None);
+        buffer.emit(EventType::Created, "unlabelled", None);
+        let mut mount = HashMap::new();
+        mount.insert("source".to_string(), "web-data".to_string());
+        buffer.emit(EventType::VolumeMount, "web-1", Some(mount));
+        
+        // Labels are captured when the event is recorded
+        buffer.forget_container_labels("web-1");
+        buffer.emit(EventType::Removed, "web-1", None);
+        
+        let tenant = labels(&[("tenant", "acme")]);
+        assert_eq!(buffer.get_filtered(None, None, None, Some(&tenant), None).len(), 3);
+        
+        let web = labels(&[("app", "web"), ("tenant", "acme")]);
+        let events = buffer.get_filtered(None, None, None, Some(&web), None);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.container_id == "web-1"));
+        
+        let ssd = labels(&[("tier", "ssd")]);
+        let events = buffer.get_filtered(None, None, None, None, Some(&ssd));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EventType::VolumeMount);
+        
+        // An empty selector matches everything
+        assert_eq!(buffer.get_filtered(None, None, None, Some(&HashMap::new()), None).len(), 5);
+    }
+
+    #[test]
+    fn test_events_record_actor() {
+        let buffer = EventRingBuffer::new(None);
+        
+        buffer.emit_as(EventType::Stopped, "container-1", "alice@10.0.0.5:41000", None);
+        buffer.emit(EventType::Died, "container-2", None);
+        
+        let events = buffer.get_filtered(None, None, None, None, None);
+        assert_eq!(events[0].attributes.get(ACTOR_ATTRIBUTE).map(String::as_str), Some(DAEMON_ACTOR));
+        assert_eq!(events[1].attributes.get(ACTOR_ATTRIBUTE).map(String::as_str), Some("alice@10.0.0.5:41000"));
+    }
+    
+    #[test]
+    fn test_subscribers_receive_events() {
+        let buffer = EventRingBuffer::new(None);
+        buffer.emit(EventType::Created, "container-1", None);
+        
+        let mut events = buffer.subscribe();
+        buffer.emit(EventType::Started, "container-1", None);
+        
+        let event = events.try_recv().expect("event after subscribing");
+        assert_eq!(event.event_type, EventType::Started);
+        assert!(events.try_recv().is_err());
+    }
+}
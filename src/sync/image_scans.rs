@@ -0,0 +1,110 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::daemon::image_scan::VulnerabilityFinding;
+use crate::sync::error::SyncResult;
+
+/// The latest scan of an image file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageScanRecord {
+    pub image_path: String,
+    /// Size and modification time of the file when it was scanned
+    pub fingerprint: String,
+    pub scanner: String,
+    pub findings: Vec<VulnerabilityFinding>,
+    pub scanned_at: i64,
+}
+
+pub struct ImageScanManager {
+    pool: SqlitePool,
+}
+
+impl ImageScanManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a scan, replacing any earlier one of the same image
+    pub async fn store_image_scan(&self, image_path: &str, fingerprint: &str, scanner: &str, findings: &[VulnerabilityFinding]) -> SyncResult<ImageScanRecord> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        sqlx::query(r#"
+            INSERT INTO image_scans (image_path, fingerprint, scanner, findings, scanned_at) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(image_path) DO UPDATE SET
+                fingerprint = excluded.fingerprint, scanner = excluded.scanner,
+                findings = excluded.findings, scanned_at = excluded.scanned_at
+        "#)
+            .bind(image_path)
+            .bind(fingerprint)
+            .bind(scanner)
+            .bind(serde_json::to_string(findings)?)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ImageScanRecord {
+            image_path: image_path.to_string(),
+            fingerprint: fingerprint.to_string(),
+            scanner: scanner.to_string(),
+            findings: findings.to_vec(),
+            scanned_at: now,
+        })
+    }
+
+    pub async fn get_image_scan(&self, image_path: &str) -> SyncResult<Option<ImageScanRecord>> {
+        let row = sqlx::query("SELECT image_path, fingerprint, scanner, findings, scanned_at FROM image_scans WHERE image_path = ?")
+            .bind(image_path)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.as_ref().map(Self::record))
+    }
+
+    pub async fn list_image_scans(&self) -> SyncResult<Vec<ImageScanRecord>> {
+        let rows = sqlx::query("SELECT image_path, fingerprint, scanner, findings, scanned_at FROM image_scans ORDER BY image_path")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::record).collect())
+    }
+
+    fn record(row: &sqlx::sqlite::SqliteRow) -> ImageScanRecord {
+        ImageScanRecord {
+            image_path: row.get("image_path"),
+            fingerprint: row.get("fingerprint"),
+            scanner: row.get("scanner"),
+            findings: serde_json::from_str(&row.get::<String, _>("findings")).unwrap_or_default(),
+            scanned_at: row.get("scanned_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::image_scan::Severity;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_image_scan_storage() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let scans = ImageScanManager::new(conn_manager.pool().clone());
+
+        assert_eq!(scans.get_image_scan("/images/web.tar").await.unwrap(), None);
+        let finding = VulnerabilityFinding {
+            id: "CVE-2024-0001".to_string(),
+            package: "openssl".to_string(),
+            installed_version: "3.1.0".to_string(),
+            fixed_version: "3.1.5".to_string(),
+            severity: Severity::Critical,
+        };
+        scans.store_image_scan("/images/web.tar", "10:1", "trivy", &[finding.clone()]).await.unwrap();
+        let stored = scans.get_image_scan("/images/web.tar").await.unwrap().unwrap();
+        assert_eq!((stored.fingerprint.as_str(), stored.findings.clone()), ("10:1", vec![finding]));
+
+        // A rescan replaces the stored findings
+        scans.store_image_scan("/images/web.tar", "12:2", "grype", &[]).await.unwrap();
+        let listed = scans.list_image_scans().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].scanner.as_str(), listed[0].findings.len()), ("grype", 0));
+    }
+}
@@ -0,0 +1,242 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::daemon::images::StoredImage;
+use crate::sync::error::SyncResult;
+
+/// A file a stored image was imported from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSource {
+    pub path: String,
+    /// Size and modification time of the file when it was imported
+    pub fingerprint: String,
+    /// Last container start from the file; `None` until one started since the import
+    pub last_used_at: Option<i64>,
+}
+
+/// A stored image with what the index knows about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRecord {
+    pub image: StoredImage,
+    /// When the image was first stored
+    pub created_at: i64,
+    pub sources: Vec<ImageSource>,
+}
+
+pub struct ImageManager {
+    pool: SqlitePool,
+}
+
+impl ImageManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Index a stored image and the image file it was imported from. `fingerprint` is the file's
+    /// size and modification time, so a rewritten file is imported again.
+    pub async fn record_image(&self, source: &str, fingerprint: &str, image: &StoredImage) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"
+            INSERT INTO images (digest, repo_tags, layers, config, size_bytes, created_at) VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(digest) DO UPDATE SET repo_tags = excluded.repo_tags
+        "#)
+            .bind(&image.digest)
+            .bind(serde_json::to_string(&image.repo_tags)?)
+            .bind(serde_json::to_string(&image.layers)?)
+            .bind(serde_json::to_string(&image.config)?)
+            .bind(image.size_bytes as i64)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(r#"
+            INSERT INTO image_sources (source, fingerprint, digest) VALUES (?, ?, ?)
+            ON CONFLICT(source) DO UPDATE SET fingerprint = excluded.fingerprint, digest = excluded.digest
+        "#)
+            .bind(source)
+            .bind(fingerprint)
+            .bind(&image.digest)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The stored image of an image file, while the file is unchanged since its import
+    pub async fn find_image_by_source(&self, source: &str, fingerprint: &str) -> SyncResult<Option<StoredImage>> {
+        let row = sqlx::query(r#"
+            SELECT images.digest, repo_tags, layers, config, size_bytes FROM images
+            JOIN image_sources ON image_sources.digest = images.digest
+            WHERE image_sources.source = ? AND image_sources.fingerprint = ?
+        "#)
+            .bind(source)
+            .bind(fingerprint)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.as_ref().map(Self::image))
+    }
+
+    /// Image files holding an image tagged `tag`, most recently imported first
+    pub async fn find_image_sources_by_tag(&self, tag: &str) -> SyncResult<Vec<String>> {
+        let rows = sqlx::query(r#"
+            SELECT image_sources.source FROM image_sources
+            JOIN images ON images.digest = image_sources.digest
+            WHERE EXISTS (SELECT 1 FROM json_each(images.repo_tags) WHERE json_each.value = ?)
+            ORDER BY image_sources.rowid DESC
+        "#)
+            .bind(tag)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| row.get("source")).collect())
+    }
+
+    /// Note a container start from the image file `source`
+    pub async fn mark_image_source_used(&self, source: &str) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        sqlx::query("UPDATE image_sources SET last_used_at = ? WHERE source = ?")
+            .bind(now)
+            .bind(source)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Every stored image with its source files, most recently stored first
+    pub async fn list_images(&self) -> SyncResult<Vec<ImageRecord>> {
+        self.image_records(None).await
+    }
+
+    pub async fn get_image(&self, digest: &str) -> SyncResult<Option<ImageRecord>> {
+        Ok(self.image_records(Some(digest)).await?.into_iter().next())
+    }
+
+    /// Drop an image and its source files from the index; false when it was not indexed
+    pub async fn remove_image(&self, digest: &str) -> SyncResult<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM image_sources WHERE digest = ?")
+            .bind(digest)
+            .execute(&mut *tx)
+            .await?;
+        let removed = sqlx::query("DELETE FROM images WHERE digest = ?")
+            .bind(digest)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() > 0;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn image_records(&self, digest: Option<&str>) -> SyncResult<Vec<ImageRecord>> {
+        let rows = sqlx::query(r#"
+            SELECT digest, repo_tags, layers, config, size_bytes, created_at FROM images
+            WHERE ? IS NULL OR digest = ?
+            ORDER BY created_at DESC, digest
+        "#)
+            .bind(digest)
+            .bind(digest)
+            .fetch_all(&self.pool)
+            .await?;
+        let source_rows = sqlx::query(r#"
+            SELECT source, fingerprint, digest, last_used_at FROM image_sources
+            WHERE ? IS NULL OR digest = ?
+            ORDER BY source
+        "#)
+            .bind(digest)
+            .bind(digest)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| {
+            let image = Self::image(row);
+            let sources = source_rows.iter()
+                .filter(|source| source.get::<String, _>("digest") == image.digest)
+                .map(|source| ImageSource {
+                    path: source.get("source"),
+                    fingerprint: source.get("fingerprint"),
+                    last_used_at: source.get("last_used_at"),
+                })
+                .collect();
+            ImageRecord { image, created_at: row.get("created_at"), sources }
+        }).collect())
+    }
+
+    fn image(row: &sqlx::sqlite::SqliteRow) -> StoredImage {
+        StoredImage {
+            digest: row.get("digest"),
+            repo_tags: serde_json::from_str(&row.get::<String, _>("repo_tags")).unwrap_or_default(),
+            layers: serde_json::from_str(&row.get::<String, _>("layers")).unwrap_or_default(),
+            config: serde_json::from_str(&row.get::<String, _>("config")).unwrap_or_default(),
+            size_bytes: row.get::<i64, _>("size_bytes") as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::image_config::ImageConfig;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_image_index() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let images = ImageManager::new(conn_manager.pool().clone());
+
+        let image = StoredImage {
+            digest: format!("sha256:{}", "a".repeat(64)),
+            repo_tags: vec!["demo:latest".to_string()],
+            layers: vec![format!("sha256:{}", "1".repeat(64)), format!("sha256:{}", "2".repeat(64))],
+            config: ImageConfig { env: vec!["PATH=/usr/bin".to_string()], working_dir: Some("/srv".to_string()), user: None, architecture: None },
+            size_bytes: 4096,
+        };
+        images.record_image("/images/demo.tar", "4096:1", &image).await.unwrap();
+        images.record_image("/images/copy.tar", "4096:2", &image).await.unwrap();
+
+        assert_eq!(images.find_image_by_source("/images/demo.tar", "4096:1").await.unwrap(), Some(image.clone()));
+        assert_eq!(images.find_image_by_source("/images/demo.tar", "4096:9").await.unwrap(), None);
+        assert_eq!(images.find_image_sources_by_tag("demo:latest").await.unwrap(), vec!["/images/copy.tar", "/images/demo.tar"]);
+        assert!(images.find_image_sources_by_tag("demo").await.unwrap().is_empty());
+
+        // A rewritten file points at whatever it holds now
+        let other = StoredImage { digest: format!("sha256:{}", "b".repeat(64)), repo_tags: vec![], ..image.clone() };
+        images.record_image("/images/demo.tar", "8192:3", &other).await.unwrap();
+        assert_eq!(images.find_image_by_source("/images/demo.tar", "8192:3").await.unwrap(), Some(other));
+        assert_eq!(images.find_image_sources_by_tag("demo:latest").await.unwrap(), vec!["/images/copy.tar"]);
+    }
+
+    #[tokio::test]
+    async fn test_image_records() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let images = ImageManager::new(conn_manager.pool().clone());
+
+        let image = StoredImage {
+            digest: format!("sha256:{}", "a".repeat(64)),
+            repo_tags: vec!["demo:latest".to_string()],
+            layers: vec![format!("sha256:{}", "1".repeat(64))],
+            config: ImageConfig::default(),
+            size_bytes: 4096,
+        };
+        let other = StoredImage { digest: format!("sha256:{}", "b".repeat(64)), repo_tags: vec![], ..image.clone() };
+        images.record_image("/images/demo.tar", "4096:1", &image).await.unwrap();
+        images.record_image("/images/copy.tar", "4096:2", &image).await.unwrap();
+        images.record_image("/images/base.tar", "4096:3", &other).await.unwrap();
+        images.mark_image_source_used("/images/demo.tar").await.unwrap();
+
+        assert_eq!(images.list_images().await.unwrap().len(), 2);
+        let record = images.get_image(&image.digest).await.unwrap().unwrap();
+        assert_eq!(record.image, image);
+        let sources: Vec<(&str, bool)> = record.sources.iter()
+            .map(|source| (source.path.as_str(), source.last_used_at.is_some()))
+            .collect();
+        assert_eq!(sources, vec![("/images/copy.tar", false), ("/images/demo.tar", true)]);
+
+        assert!(images.remove_image(&image.digest).await.unwrap());
+        assert!(!images.remove_image(&image.digest).await.unwrap());
+        assert_eq!(images.get_image(&image.digest).await.unwrap(), None);
+        assert!(images.find_image_sources_by_tag("demo:latest").await.unwrap().is_empty());
+        assert_eq!(images.list_images().await.unwrap()[0].sources.len(), 1);
+    }
+}
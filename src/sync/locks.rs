@@ -0,0 +1,170 @@
+// Per-container operation locks
+// Mutating operations (start, stop, kill, remove, evict) hold a container's lock exclusively for
+// their duration; exec holds it shared while it prepares, so a remove cannot pull the rootfs out
+// from under a script copy. Waiting is bounded: an operation that cannot get the lock in time is
+// rejected as busy, naming the operation holding it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+use crate::sync::error::{SyncError, SyncResult};
+
+/// How long an operation waits for the one in progress before it is rejected
+pub const LOCK_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Entry {
+    lock: Arc<RwLock<()>>,
+    /// Operations currently holding the lock, for busy errors
+    holders: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct OperationLocks {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+enum Held {
+    Exclusive(#[allow(dead_code)] OwnedRwLockWriteGuard<()>),
+    Shared(#[allow(dead_code)] OwnedRwLockReadGuard<()>),
+}
+
+/// Held for the duration of an operation; releases the lock when dropped
+pub struct OperationGuard {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+    container_id: String,
+    operation: String,
+    held: Option<Held>,
+}
+
+impl OperationLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for sole access to the container for `operation`
+    pub async fn exclusive(&self, container_id: &str, operation: &str, wait: Duration) -> SyncResult<OperationGuard> {
+        let wait_for_lock = self.entry(container_id).write_owned();
+        let held = tokio::time::timeout(wait, wait_for_lock).await
+            .map(Held::Exclusive);
+        self.finish(container_id, operation, held)
+    }
+
+    /// Wait until no exclusive operation runs; other shared holders are not excluded
+    pub async fn shared(&self, container_id: &str, operation: &str, wait: Duration) -> SyncResult<OperationGuard> {
+        let wait_for_lock = self.entry(container_id).read_owned();
+        let held = tokio::time::timeout(wait, wait_for_lock).await
+            .map(Held::Shared);
+        self.finish(container_id, operation, held)
+    }
+
+    /// Operations holding the container's lock right now
+    pub fn holders(&self, container_id: &str) -> Vec<String> {
+        lock_entries(&self.entries).get(container_id)
+            .map(|entry| entry.holders.clone())
+            .unwrap_or_default()
+    }
+
+    fn entry(&self, container_id: &str) -> Arc<RwLock<()>> {
+        let mut entries = lock_entries(&self.entries);
+        let entry = entries.entry(container_id.to_string()).or_insert_with(|| Entry {
+            lock: Arc::new(RwLock::new(())),
+            holders: Vec::new(),
+        });
+        Arc::clone(&entry.lock)
+    }
+
+    /// Record the outcome of a wait. The wait future, and with it its reference to the lock, is
+    /// gone by now, so on timeout the entry is pruned unless someone else holds or waits for it.
+    fn finish(&self, container_id: &str, operation: &str, held: Result<Held, tokio::time::error::Elapsed>) -> SyncResult<OperationGuard> {
+        let mut entries = lock_entries(&self.entries);
+        match held {
+            Ok(held) => {
+                if let Some(entry) = entries.get_mut(container_id) {
+                    entry.holders.push(operation.to_string());
+                }
+                Ok(OperationGuard {
+                    entries: Arc::clone(&self.entries),
+                    container_id: container_id.to_string(),
+                    operation: operation.to_string(),
+                    held: Some(held),
+                })
+            }
+            Err(_) => {
+                let busy_with = entries.get(container_id)
+                    .map(|entry| entry.holders.join(", "))
+                    .filter(|holders| !holders.is_empty())
+                    .unwrap_or_else(|| "another operation".to_string());
+                prune(&mut entries, container_id);
+                Err(SyncError::Busy {
+                    container_id: container_id.to_string(),
+                    operation: busy_with,
+                })
+            }
+        }
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        // Release before pruning so the entry's lock is no longer referenced by this guard
+        drop(self.held.take());
+        let mut entries = lock_entries(&self.entries);
+        if let Some(entry) = entries.get_mut(&self.container_id) {
+            if let Some(position) = entry.holders.iter().position(|holder| *holder == self.operation) {
+                entry.holders.remove(position);
+            }
+        }
+        prune(&mut entries, &self.container_id);
+    }
+}
+
+/// Forget a container's entry once nobody holds or waits for its lock
+fn prune(entries: &mut HashMap<String, Entry>, container_id: &str) {
+    if entries.get(container_id).map_or(false, |entry| Arc::strong_count(&entry.lock) == 1) {
+        entries.remove(container_id);
+    }
+}
+
+fn lock_entries(entries: &Mutex<HashMap<String, Entry>>) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+    entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_operation_locks() {
+        let locks = OperationLocks::new();
+        let wait = Duration::from_millis(50);
+
+        let first = locks.shared("c1", "exec", wait).await.unwrap();
+        let second = locks.shared("c1", "exec", wait).await.unwrap();
+        assert_eq!(locks.holders("c1"), vec!["exec", "exec"]);
+
+        // Exclusive operations wait for shared holders and give up as busy
+        match locks.exclusive("c1", "remove", wait).await {
+            Err(SyncError::Busy { operation, .. }) => assert_eq!(operation, "exec, exec"),
+            other => panic!("expected busy, got {:?}", other.map(|_| ())),
+        }
+        // Other containers are not affected
+        let other = locks.exclusive("c2", "stop", wait).await.unwrap();
+
+        drop(first);
+        drop(second);
+        let removing = locks.exclusive("c1", "remove", wait).await.unwrap();
+        assert!(locks.shared("c1", "exec", wait).await.is_err());
+        drop(removing);
+        drop(other);
+        assert!(lock_entries(&locks.entries).is_empty());
+
+        // A timed-out wait leaves nothing behind once the holder is gone
+        let stopping = locks.exclusive("c3", "stop", wait).await.unwrap();
+        assert!(locks.exclusive("c3", "kill", wait).await.is_err());
+        assert_eq!(Arc::strong_count(&lock_entries(&locks.entries)["c3"].lock), 2);
+        drop(stopping);
+        assert!(lock_entries(&locks.entries).is_empty());
+    }
+}
@@ -22,9 +22,10 @@ impl MetricsStore {
                 memory_cache_bytes, memory_rss_bytes,
                 network_rx_bytes, network_tx_bytes, network_rx_packets, 
                 network_tx_packets, network_rx_errors, network_tx_errors,
-                disk_read_bytes, disk_write_bytes, disk_read_ops, disk_write_ops
+                disk_read_bytes, disk_write_bytes, disk_read_ops, disk_write_ops,
+                cpu_percent, memory_percent
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23
             )
         "#)
         .bind(&metrics.container_id)
@@ -52,6 +53,9 @@ impl MetricsStore {
         .bind(metrics.disk.write_bytes as i64)
         .bind(metrics.disk.read_ops as i64)
         .bind(metrics.disk.write_ops as i64)
+        // Derived percentages, stored so cached samples match what was collected
+        .bind(metrics.cpu_percent)
+        .bind(metrics.memory_percent)
         .execute(&self.pool)
         .await;
 
@@ -106,18 +110,32 @@ impl MetricsStore {
         Ok(rows.into_iter().map(Into::into).collect())
     }
 
-    /// Clean up old metrics (keep last N days)
-    pub async fn cleanup_old_metrics(&self, retention_days: u32) -> SyncResult<u64> {
-        let cutoff_time = std::time::SystemTime::now()
+    /// Metric records older than `retention_days`, i.e. what `cleanup_old_metrics` would delete
+    pub async fn count_old_metrics(&self, retention_days: u32) -> SyncResult<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM container_metrics WHERE timestamp < ?1")
+            .bind(Self::retention_cutoff(retention_days))
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    fn retention_cutoff(retention_days: u32) -> i64 {
+        let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_millis() as u64 - (retention_days as u64 * 24 * 60 * 60 * 1000);
+            .as_millis() as u64;
+        now.saturating_sub(retention_days as u64 * 24 * 60 * 60 * 1000) as i64
+    }
+
+    /// Clean up old metrics (keep last N days)
+    pub async fn cleanup_old_metrics(&self, retention_days: u32) -> SyncResult<u64> {
+        let cutoff_time = Self::retention_cutoff(retention_days);
 
         let result = sqlx::query(r#"
             DELETE FROM container_metrics 
             WHERE timestamp < ?1
         "#)
-        .bind(cutoff_time as i64)
+        .bind(cutoff_time)
         .execute(&self.pool)
         .await?;
 
@@ -156,6 +174,8 @@ struct MetricsRow {
     disk_write_bytes: Option<i64>,
     disk_read_ops: Option<i64>,
     disk_write_ops: Option<i64>,
+    cpu_percent: Option<f64>,
+    memory_percent: Option<f64>,
 }
 
 impl From<MetricsRow> for ContainerMetrics {
@@ -185,6 +205,7 @@ impl From<MetricsRow> for ContainerMetrics {
                 tx_packets: row.network_tx_packets.unwrap_or(0) as u64,
                 rx_errors: row.network_rx_errors.unwrap_or(0) as u64,
                 tx_errors: row.network_tx_errors.unwrap_or(0) as u64,
+                ..Default::default()
             },
             disk: DiskMetrics {
                 read_bytes: row.disk_read_bytes.unwrap_or(0) as u64,
@@ -192,6 +213,8 @@ impl From<MetricsRow> for ContainerMetrics {
                 read_ops: row.disk_read_ops.unwrap_or(0) as u64,
                 write_ops: row.disk_write_ops.unwrap_or(0) as u64,
             },
+            cpu_percent: row.cpu_percent.unwrap_or(0.0),
+            memory_percent: row.memory_percent.unwrap_or(0.0),
         }
     }
 }
@@ -7,8 +7,21 @@ pub mod monitor;
 pub mod cleanup;
 pub mod error;
 pub mod volumes;
+pub mod ports;
 pub mod metrics;
 pub mod events;
+pub mod names;
+pub mod orphans;
+pub mod plugins;
+pub mod locks;
+pub mod operations;
+pub mod setup_steps;
+pub mod virtual_services;
+pub mod dns_records;
+pub mod image_scans;
+pub mod images;
+pub mod network_attachments;
+pub mod traffic_mirrors;
 
 pub use engine::SyncEngine;
 pub use containers::ContainerState;
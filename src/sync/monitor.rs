@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, Row};
 use std::collections::HashSet;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::unix::AsyncFd;
 use tokio::sync::Mutex;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
@@ -46,13 +48,52 @@ pub struct ProcessMonitor {
     pub monitor_started_at: i64,
     pub last_check_at: Option<i64>,
     pub status: MonitorStatus,
+    pub check_count: i64,
+    /// Failed checks since the last successful one
+    pub consecutive_failures: i64,
+    /// Last check error, or why the monitor failed
+    pub last_error: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProcessStatus {
     Running,
     Exited(i32),
-    Error,
+    /// Gone without an exit status for the monitor: another waiter reaped it, or it was never
+    /// the daemon's child
+    Vanished,
+    /// The PID is alive but belongs to a process started after the monitored one
+    Reused,
+    Error(String),
+}
+
+impl ProcessStatus {
+    /// Event and `reason` attribute for a monitor that ended without an exit code
+    fn lost_event(&self) -> (crate::sync::events::EventType, &'static str) {
+        use crate::sync::events::EventType;
+        match self {
+            ProcessStatus::Reused => (EventType::PidReused, "pid_reused"),
+            ProcessStatus::Vanished => (EventType::MonitorLost, "pid_vanished"),
+            _ => (EventType::MonitorLost, "check_failed"),
+        }
+    }
+}
+
+/// How often a pidfd-backed monitor refreshes its heartbeat while the process runs
+const PIDFD_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Consecutive failed checks after which a monitor gives the process up as lost
+const MAX_FAILED_CHECKS: u32 = 3;
+
+/// Start time of a process in clock ticks since boot (field 22 of /proc/<pid>/stat); with the
+/// PID it identifies the process, since PIDs are reused
+fn process_start_time(pid: Pid) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    parse_start_time(&stat)
+}
+
+/// The command name may contain spaces and parentheses, so fields are counted after the last ')'
+fn parse_start_time(stat: &str) -> Option<u64> {
+    stat.rsplit_once(')')?.1.split_whitespace().nth(19)?.parse().ok()
 }
 
 pub struct ProcessMonitorService {
@@ -103,55 +144,69 @@ impl ProcessMonitorService {
         let active_monitors = self.active_monitors.clone();
         let container_id = container_id.to_string();
         let check_interval = self.check_interval;
+        let start_time = process_start_time(pid);
         
         tokio::spawn(async move {
             tracing::info!("Started background monitoring for container {} (PID: {})", container_id, pid);
             
-            loop {
-                match Self::check_process_status(pid).await {
-                    ProcessStatus::Running => {
-                        // Update heartbeat in database
-                        if let Err(e) = Self::update_monitor_heartbeat(&pool, &container_id).await {
-                            tracing::warn!("Failed to update monitor heartbeat for {}: {}", container_id, e);
-                        }
-                        
-                        tokio::time::sleep(check_interval).await;
-                    },
-                    ProcessStatus::Exited(exit_code) => {
-                        tracing::info!("Process {} exited with code {}", pid, exit_code);
-                        
-                        // Update database with completion
-                        if let Err(e) = Self::complete_process_monitor(&pool, &container_id, exit_code).await {
-                            tracing::error!("Failed to mark process monitor completed for {}: {}", container_id, e);
-                        }
-                        
-                        // Remove from active monitors
-                        {
-                            let mut active = active_monitors.lock().await;
-                            active.remove(&container_id);
-                        }
-                        
-                        break;
-                    },
-                    ProcessStatus::Error => {
-                        tracing::warn!("Error checking process status for {}", container_id);
-                        
-                        // Mark as failed in database
-                        if let Err(e) = Self::fail_process_monitor(&pool, &container_id, "Process check failed").await {
-                            tracing::error!("Failed to mark process monitor failed for {}: {}", container_id, e);
-                        }
-                        
-                        // Remove from active monitors
-                        {
-                            let mut active = active_monitors.lock().await;
-                            active.remove(&container_id);
-                        }
-                        
-                        break;
+            // Prefer exit notification through a pidfd; fall back to polling on older kernels
+            let status = match Self::open_pidfd(pid) {
+                Ok(pidfd) => Self::wait_with_pidfd(pidfd, pid, start_time, &pool, &container_id, check_interval).await,
+                Err(e) => {
+                    tracing::debug!("pidfd unavailable for PID {} ({}), falling back to polling", pid, e);
+                    Self::wait_with_polling(pid, start_time, &pool, &container_id, check_interval).await
+                }
+            };
+            
+            crate::utils::console::ConsoleLogger::container_debug(&container_id, &format!("monitor: PID {} finished with {:?}", pid, status));
+            match status {
+                ProcessStatus::Exited(exit_code) => {
+                    tracing::info!("Process {} exited with code {}", pid, exit_code);
+                    
+                    // Update database with completion
+                    if let Err(e) = Self::complete_process_monitor(&pool, &container_id, exit_code).await {
+                        tracing::error!("Failed to mark process monitor completed for {}: {}", container_id, e);
+                    }
+                    
+                    let mut attributes = std::collections::HashMap::new();
+                    attributes.insert("exit_code".to_string(), exit_code.to_string());
+                    crate::sync::events::global_event_buffer().emit(
+                        crate::sync::events::EventType::Died,
+                        &container_id,
+                        Some(attributes),
+                    );
+                },
+                ProcessStatus::Running | ProcessStatus::Vanished | ProcessStatus::Reused | ProcessStatus::Error(_) => {
+                    let (event_type, reason) = status.lost_event();
+                    let message = match &status {
+                        ProcessStatus::Vanished => format!("Process {} vanished without an exit status", pid),
+                        ProcessStatus::Reused => format!("PID {} now belongs to another process", pid),
+                        ProcessStatus::Error(error) => format!("Checking process {} failed repeatedly: {}", pid, error),
+                        _ => format!("Checking process {} failed", pid),
+                    };
+                    tracing::warn!("Lost track of container {}: {}", container_id, message);
+                    
+                    // Mark as failed in database
+                    if let Err(e) = Self::fail_process_monitor(&pool, &container_id, &message).await {
+                        tracing::error!("Failed to mark process monitor failed for {}: {}", container_id, e);
                     }
+                    
+                    // Orchestrators cannot rely on a died event for this container
+                    let attributes = std::collections::HashMap::from([
+                        ("pid".to_string(), pid.to_string()),
+                        ("reason".to_string(), reason.to_string()),
+                        ("message".to_string(), message),
+                    ]);
+                    crate::sync::events::global_event_buffer().emit(event_type, &container_id, Some(attributes));
                 }
             }
             
+            // Remove from active monitors
+            {
+                let mut active = active_monitors.lock().await;
+                active.remove(&container_id);
+            }
+            
             tracing::info!("Finished monitoring container {}", container_id);
         });
         
@@ -174,7 +229,8 @@ impl ProcessMonitorService {
     
     pub async fn get_monitor_status(&self, container_id: &str) -> SyncResult<ProcessMonitor> {
         let row = sqlx::query(r#"
-            SELECT container_id, pid, monitor_started_at, last_check_at, status
+            SELECT container_id, pid, monitor_started_at, last_check_at, status,
+                   check_count, consecutive_failures, last_error
             FROM process_monitors WHERE container_id = ?
         "#)
         .bind(container_id)
@@ -182,18 +238,7 @@ impl ProcessMonitorService {
         .await?;
         
         match row {
-            Some(row) => {
-                let status_str: String = row.get("status");
-                let status = MonitorStatus::from_string(&status_str)?;
-                
-                Ok(ProcessMonitor {
-                    container_id: row.get("container_id"),
-                    pid: row.get("pid"),
-                    monitor_started_at: row.get("monitor_started_at"),
-                    last_check_at: row.get("last_check_at"),
-                    status,
-                })
-            }
+            Some(row) => Self::process_monitor(&row),
             None => Err(SyncError::NotFound {
                 container_id: container_id.to_string(),
             }),
@@ -202,28 +247,29 @@ impl ProcessMonitorService {
     
     pub async fn list_active_monitors(&self) -> SyncResult<Vec<ProcessMonitor>> {
         let rows = sqlx::query(r#"
-            SELECT container_id, pid, monitor_started_at, last_check_at, status
+            SELECT container_id, pid, monitor_started_at, last_check_at, status,
+                   check_count, consecutive_failures, last_error
             FROM process_monitors WHERE status = 'monitoring'
             ORDER BY monitor_started_at ASC
         "#)
         .fetch_all(&self.pool)
         .await?;
         
-        let mut monitors = Vec::new();
-        for row in rows {
-            let status_str: String = row.get("status");
-            let status = MonitorStatus::from_string(&status_str)?;
-            
-            monitors.push(ProcessMonitor {
-                container_id: row.get("container_id"),
-                pid: row.get("pid"),
-                monitor_started_at: row.get("monitor_started_at"),
-                last_check_at: row.get("last_check_at"),
-                status,
-            });
-        }
-        
-        Ok(monitors)
+        rows.iter().map(Self::process_monitor).collect()
+    }
+    
+    fn process_monitor(row: &sqlx::sqlite::SqliteRow) -> SyncResult<ProcessMonitor> {
+        let status_str: String = row.get("status");
+        Ok(ProcessMonitor {
+            container_id: row.get("container_id"),
+            pid: row.get("pid"),
+            monitor_started_at: row.get("monitor_started_at"),
+            last_check_at: row.get("last_check_at"),
+            status: MonitorStatus::from_string(&status_str)?,
+            check_count: row.get("check_count"),
+            consecutive_failures: row.get("consecutive_failures"),
+            last_error: row.get("last_error"),
+        })
     }
     
     pub async fn cleanup_stale_monitors(&self, stale_threshold: Duration) -> SyncResult<usize> {
@@ -254,8 +300,8 @@ impl ProcessMonitorService {
         
         sqlx::query(r#"
             INSERT OR REPLACE INTO process_monitors (
-                container_id, pid, monitor_started_at, status
-            ) VALUES (?, ?, ?, ?)
+                container_id, pid, monitor_started_at, status, check_count, consecutive_failures, last_error
+            ) VALUES (?, ?, ?, ?, 0, 0, NULL)
         "#)
         .bind(container_id)
         .bind(pid)
@@ -267,14 +313,20 @@ impl ProcessMonitorService {
         Ok(())
     }
     
-    async fn update_monitor_heartbeat(pool: &SqlitePool, container_id: &str) -> SyncResult<()> {
+    /// Count a check of the monitored process; an error extends the failure streak, a success
+    /// ends it and refreshes the heartbeat
+    async fn record_check(pool: &SqlitePool, container_id: &str, error: Option<&str>) -> SyncResult<()> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
-        sqlx::query("UPDATE process_monitors SET last_check_at = ? WHERE container_id = ?")
-            .bind(now)
-            .bind(container_id)
-            .execute(pool)
-            .await?;
+        let query = match error {
+            None => sqlx::query(
+                "UPDATE process_monitors SET last_check_at = ?, check_count = check_count + 1, consecutive_failures = 0 WHERE container_id = ?")
+                .bind(now),
+            Some(error) => sqlx::query(
+                "UPDATE process_monitors SET check_count = check_count + 1, consecutive_failures = consecutive_failures + 1, last_error = ? WHERE container_id = ?")
+                .bind(error.to_string()),
+        };
+        query.bind(container_id).execute(pool).await?;
         
         Ok(())
     }
@@ -289,10 +341,11 @@ impl ProcessMonitorService {
         
         // Update container state to Exited
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
-        sqlx::query("UPDATE containers SET state = ?, exit_code = ?, updated_at = ? WHERE id = ?")
+        sqlx::query("UPDATE containers SET state = ?, exit_code = ?, exited_at = ?, updated_at = ? WHERE id = ?")
             .bind("exited")
             .bind(exit_code as i64)
             .bind(now)
+            .bind(now)
             .bind(container_id)
             .execute(pool)
             .await?;
@@ -304,8 +357,9 @@ impl ProcessMonitorService {
     
     async fn fail_process_monitor(pool: &SqlitePool, container_id: &str, error_message: &str) -> SyncResult<()> {
         // Update process monitor status
-        sqlx::query("UPDATE process_monitors SET status = ? WHERE container_id = ?")
+        sqlx::query("UPDATE process_monitors SET status = ?, last_error = ? WHERE container_id = ?")
             .bind(MonitorStatus::Failed.to_string())
+            .bind(error_message)
             .bind(container_id)
             .execute(pool)
             .await?;
@@ -333,7 +387,89 @@ impl ProcessMonitorService {
         Ok(())
     }
     
-    async fn check_process_status(pid: Pid) -> ProcessStatus {
+    /// Obtain a pidfd for the process (Linux 5.3+); it becomes readable once the process exits
+    fn open_pidfd(pid: Pid) -> std::io::Result<OwnedFd> {
+        let fd = unsafe { nix::libc::syscall(nix::libc::SYS_pidfd_open, pid.as_raw(), 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+    }
+    
+    /// Sleep until the kernel signals exit on the pidfd, refreshing the heartbeat meanwhile
+    async fn wait_with_pidfd(pidfd: OwnedFd, pid: Pid, start_time: Option<u64>, pool: &SqlitePool, container_id: &str, check_interval: Duration) -> ProcessStatus {
+        let async_fd = match AsyncFd::new(pidfd) {
+            Ok(async_fd) => async_fd,
+            Err(e) => {
+                tracing::debug!("Failed to register pidfd for PID {} ({}), falling back to polling", pid, e);
+                return Self::wait_with_polling(pid, start_time, pool, container_id, check_interval).await;
+            }
+        };
+        
+        let mut heartbeat = tokio::time::interval(PIDFD_HEARTBEAT_INTERVAL);
+        loop {
+            tokio::select! {
+                ready = async_fd.readable() => {
+                    if let Err(e) = ready {
+                        tracing::warn!("pidfd wait failed for PID {}: {}", pid, e);
+                        return Self::wait_with_polling(pid, start_time, pool, container_id, check_interval).await;
+                    }
+                    break;
+                }
+                _ = heartbeat.tick() => {
+                    crate::utils::console::ConsoleLogger::container_debug(container_id, &format!("monitor: PID {} alive (pidfd heartbeat)", pid));
+                    if let Err(e) = Self::record_check(pool, container_id, None).await {
+                        tracing::warn!("Failed to update monitor heartbeat for {}: {}", container_id, e);
+                    }
+                }
+            }
+        }
+        
+        // The process has exited; reap it to collect the exit status. The zombie may take a
+        // moment to become waitable, so retry briefly before giving up.
+        for _ in 0..10 {
+            match Self::check_process_status(pid, start_time).await {
+                ProcessStatus::Running | ProcessStatus::Error(_) => tokio::time::sleep(Duration::from_millis(5)).await,
+                status => return status,
+            }
+        }
+        ProcessStatus::Error(format!("exit of PID {} signalled but its status could not be collected", pid))
+    }
+    
+    /// Interval-based fallback for kernels without pidfd support
+    async fn wait_with_polling(pid: Pid, start_time: Option<u64>, pool: &SqlitePool, container_id: &str, check_interval: Duration) -> ProcessStatus {
+        let mut failed_checks = 0;
+        loop {
+            match Self::check_process_status(pid, start_time).await {
+                ProcessStatus::Running => {
+                    failed_checks = 0;
+                    crate::utils::console::ConsoleLogger::container_debug(container_id, &format!("monitor: PID {} running (poll)", pid));
+                    // Update heartbeat in database
+                    if let Err(e) = Self::record_check(pool, container_id, None).await {
+                        tracing::warn!("Failed to update monitor heartbeat for {}: {}", container_id, e);
+                    }
+                    
+                    tokio::time::sleep(check_interval).await;
+                },
+                ProcessStatus::Error(error) => {
+                    if let Err(e) = Self::record_check(pool, container_id, Some(&error)).await {
+                        tracing::warn!("Failed to record failed check for {}: {}", container_id, e);
+                    }
+                    failed_checks += 1;
+                    if failed_checks >= MAX_FAILED_CHECKS {
+                        return ProcessStatus::Error(error);
+                    }
+                    tokio::time::sleep(check_interval).await;
+                },
+                status => return status,
+            }
+        }
+    }
+    
+    /// Check the monitored process without blocking. `start_time` (from [`process_start_time`]
+    /// when monitoring began) tells a PID that was reaped elsewhere and handed to a new process
+    /// from the original one.
+    async fn check_process_status(pid: Pid, start_time: Option<u64>) -> ProcessStatus {
         // Use tokio task to avoid blocking the async runtime
         tokio::task::spawn_blocking(move || {
             match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
@@ -348,15 +484,20 @@ impl ProcessMonitorService {
                     ProcessStatus::Exited(1) // Treat other statuses as generic failure
                 },
                 Err(nix::errno::Errno::ECHILD) => {
-                    // Process doesn't exist or is not a child
-                    ProcessStatus::Exited(0) // Assume it exited normally
+                    // Not (or no longer) our child: still the same process, or gone without a
+                    // status we can collect
+                    match (process_start_time(pid), start_time) {
+                        (None, _) => ProcessStatus::Vanished,
+                        (Some(now), Some(then)) if now != then => ProcessStatus::Reused,
+                        (Some(_), _) => ProcessStatus::Running,
+                    }
                 },
                 Err(e) => {
                     tracing::error!("Error checking process status for {}: {}", pid, e);
-                    ProcessStatus::Error
+                    ProcessStatus::Error(format!("waitpid failed: {}", e))
                 }
             }
-        }).await.unwrap_or(ProcessStatus::Error)
+        }).await.unwrap_or_else(|e| ProcessStatus::Error(format!("status check did not complete: {}", e)))
     }
 }
 
@@ -441,6 +582,110 @@ mod tests {
         child.wait().expect("Failed to wait for child");
     }
     
+    #[tokio::test]
+    async fn test_monitor_detects_exit_without_polling() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        
+        // A polling interval this long would never notice the exit within the test
+        let monitor_service = ProcessMonitorService::with_check_interval(
+            conn_manager.pool().clone(),
+            Duration::from_secs(60)
+        );
+        
+        let child = Command::new("sleep")
+            .arg("0.1")
+            .spawn()
+            .expect("Failed to start test process");
+        let pid = Pid::from_raw(child.id() as i32);
+        
+        monitor_service.start_monitoring("pidfd-container", pid).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        
+        let monitor = monitor_service.get_monitor_status("pidfd-container").await.unwrap();
+        assert_eq!(monitor.status, MonitorStatus::Completed);
+    }
+    
+    #[test]
+    fn test_parse_start_time() {
+        let stat = "4242 (weird) name) S 1 4242 4242 0 -1 4194560 100 0 0 0 5 3 0 0 20 0 1 0 987654 1000000 200";
+        assert_eq!(parse_start_time(stat), Some(987654));
+        assert_eq!(parse_start_time("4242 (sleep) S 1"), None);
+        assert!(process_start_time(Pid::this()).is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_check_detects_reuse_and_vanish() {
+        // The test process is nobody's child here: alive and the same process while the start
+        // time matches, reused once it does not
+        let this = Pid::this();
+        let start_time = process_start_time(this);
+        assert_eq!(ProcessMonitorService::check_process_status(this, start_time).await, ProcessStatus::Running);
+        assert_eq!(ProcessMonitorService::check_process_status(this, start_time.map(|t| t + 1)).await, ProcessStatus::Reused);
+        
+        let mut child = Command::new("true").spawn().expect("Failed to start test process");
+        let pid = Pid::from_raw(child.id() as i32);
+        child.wait().expect("Failed to wait for child");
+        assert_eq!(ProcessMonitorService::check_process_status(pid, None).await, ProcessStatus::Vanished);
+    }
+    
+    #[tokio::test]
+    async fn test_lost_monitor_emits_event() {
+        let (_conn, monitor_service) = setup_test_db().await;
+        
+        // Reaped before monitoring starts, so no exit status is left for the monitor
+        let mut child = Command::new("true").spawn().expect("Failed to start test process");
+        let pid = Pid::from_raw(child.id() as i32);
+        child.wait().expect("Failed to wait for child");
+        
+        monitor_service.start_monitoring("lost-container", pid).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        
+        let monitor = monitor_service.get_monitor_status("lost-container").await.unwrap();
+        assert_eq!(monitor.status, MonitorStatus::Failed);
+        let events = crate::sync::events::global_event_buffer().get_filtered(
+            Some(&["lost-container".to_string()]),
+            Some(&[crate::sync::events::EventType::MonitorLost]),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].attributes.get("reason").map(String::as_str), Some("pid_vanished"));
+        assert_eq!(ProcessStatus::Reused.lost_event().1, "pid_reused");
+        assert_eq!(ProcessStatus::Error("waitpid failed".to_string()).lost_event().1, "check_failed");
+        assert_eq!(monitor.last_error.as_deref(), Some(format!("Process {} vanished without an exit status", pid).as_str()));
+    }
+    
+    #[tokio::test]
+    async fn test_check_counts() {
+        let (conn, monitor_service) = setup_test_db().await;
+        let pool = conn.pool();
+        
+        monitor_service.start_process_monitor("counted-container", 99999).await.unwrap();
+        ProcessMonitorService::record_check(pool, "counted-container", None).await.unwrap();
+        ProcessMonitorService::record_check(pool, "counted-container", Some("waitpid failed: EINTR")).await.unwrap();
+        ProcessMonitorService::record_check(pool, "counted-container", Some("waitpid failed: EIO")).await.unwrap();
+        
+        let monitor = monitor_service.get_monitor_status("counted-container").await.unwrap();
+        assert_eq!(monitor.check_count, 3);
+        assert_eq!(monitor.consecutive_failures, 2);
+        assert_eq!(monitor.last_error.as_deref(), Some("waitpid failed: EIO"));
+        assert!(monitor.last_check_at.is_some());
+        
+        // A success ends the streak but keeps the last error for inspection
+        ProcessMonitorService::record_check(pool, "counted-container", None).await.unwrap();
+        let monitor = monitor_service.get_monitor_status("counted-container").await.unwrap();
+        assert_eq!((monitor.check_count, monitor.consecutive_failures), (4, 0));
+        assert_eq!(monitor.last_error.as_deref(), Some("waitpid failed: EIO"));
+        
+        // Monitoring again starts from zero
+        monitor_service.start_process_monitor("counted-container", 99999).await.unwrap();
+        let monitor = monitor_service.get_monitor_status("counted-container").await.unwrap();
+        assert_eq!((monitor.check_count, monitor.consecutive_failures, monitor.last_error), (0, 0, None));
+    }
+    
     #[tokio::test]
     async fn test_stale_monitor_cleanup() {
         let (_conn, monitor_service) = setup_test_db().await;
@@ -0,0 +1,66 @@
+// Automatic container names
+// Containers created without a name get a memorable adjective_noun name so they can be
+// addressed like user-named containers instead of by UUID
+
+/// Attempts with a plain adjective_noun before a numeric suffix is added
+const PLAIN_ATTEMPTS: u32 = 8;
+
+const ADJECTIVES: &[&str] = &[
+    "agile", "amber", "ancient", "bold", "brave", "bright", "brisk", "calm",
+    "clever", "cosmic", "crisp", "curious", "dapper", "daring", "eager", "electric",
+    "fancy", "fearless", "fuzzy", "gentle", "gleaming", "golden", "happy", "hidden",
+    "humble", "jolly", "keen", "kind", "lively", "lucid", "lucky", "mellow",
+    "mighty", "misty", "nimble", "noble", "patient", "plucky", "polished", "proud",
+    "quick", "quiet", "rapid", "serene", "sharp", "shiny", "silent", "sleek",
+    "snappy", "solid", "spry", "stellar", "steady", "sturdy", "sunny", "swift",
+    "tidy", "tranquil", "trusty", "vivid", "wandering", "witty", "young", "zesty",
+];
+
+const NOUNS: &[&str] = &[
+    "albatross", "badger", "beacon", "bison", "canyon", "cedar", "comet", "condor",
+    "coral", "crane", "delta", "dolphin", "ember", "falcon", "fern", "fjord",
+    "fox", "galaxy", "gecko", "glacier", "harbor", "hawk", "heron", "horizon",
+    "ibis", "island", "jaguar", "kestrel", "koala", "lagoon", "lantern", "lynx",
+    "maple", "meadow", "meteor", "moose", "nebula", "newt", "oasis", "orca",
+    "otter", "panda", "pebble", "pelican", "pine", "quasar", "raven", "reef",
+    "river", "robin", "sequoia", "sparrow", "summit", "tiger", "tundra", "turtle",
+    "valley", "vortex", "walrus", "willow", "wombat", "yak", "zebra", "zephyr",
+];
+
+/// Name candidate for the given attempt. Early attempts are plain `adjective_noun`; later ones
+/// add a numeric suffix so a crowded namespace still yields a free name quickly.
+pub fn generate_name(attempt: u32) -> String {
+    let random = uuid::Uuid::new_v4();
+    let bytes = random.as_bytes();
+    let adjective = ADJECTIVES[bytes[0] as usize % ADJECTIVES.len()];
+    let noun = NOUNS[bytes[1] as usize % NOUNS.len()];
+
+    if attempt < PLAIN_ATTEMPTS {
+        format!("{}_{}", adjective, noun)
+    } else {
+        let suffix = u16::from_le_bytes([bytes[2], bytes[3]]) % 10_000;
+        format!("{}_{}_{}", adjective, noun, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_names_are_valid_container_names() {
+        for attempt in 0..(PLAIN_ATTEMPTS * 2) {
+            let name = generate_name(attempt);
+            let parts: Vec<&str> = name.split('_').collect();
+            if attempt < PLAIN_ATTEMPTS {
+                assert_eq!(parts.len(), 2);
+            } else {
+                assert_eq!(parts.len(), 3);
+                assert!(parts[2].parse::<u16>().is_ok());
+            }
+            assert!(ADJECTIVES.contains(&parts[0]));
+            assert!(NOUNS.contains(&parts[1]));
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::icc::network::attachments::{attachment_veth_names, network_address};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// A container's place on an additional network
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkAttachment {
+    pub container_id: String,
+    pub network: String,
+    /// Third octet of the network's subnet
+    pub subnet_index: u8,
+    pub ip_address: String,
+    pub veth_host: String,
+    pub attached_at: i64,
+}
+
+pub struct NetworkAttachmentManager {
+    pool: SqlitePool,
+}
+
+impl NetworkAttachmentManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Give the container an address on `network`, creating the network with the lowest free
+    /// subnet when it is new. Addresses start at .2; .1 is the network's bridge.
+    pub async fn attach_network(&self, container_id: &str, network: &str) -> SyncResult<NetworkAttachment> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<i64> = sqlx::query_scalar("SELECT subnet_index FROM networks WHERE name = ?")
+            .bind(network)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let subnet_index = match existing {
+            Some(index) => index,
+            None => {
+                let used: Vec<i64> = sqlx::query_scalar("SELECT subnet_index FROM networks")
+                    .fetch_all(&mut *tx)
+                    .await?;
+                let index = Self::lowest_free(&used, 1..=254).ok_or_else(|| SyncError::ValidationFailed {
+                    message: format!("No free subnet left for network '{}'", network),
+                })?;
+                sqlx::query("INSERT INTO networks (name, subnet_index, created_at) VALUES (?, ?, ?)")
+                    .bind(network)
+                    .bind(index)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                index
+            }
+        };
+
+        let connected: Option<i64> = sqlx::query_scalar("SELECT 1 FROM network_attachments WHERE container_id = ? AND network = ?")
+            .bind(container_id)
+            .bind(network)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if connected.is_some() {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Container {} is already connected to network '{}'", container_id, network),
+            });
+        }
+
+        let used: Vec<i64> = sqlx::query_scalar("SELECT host FROM network_attachments WHERE network = ?")
+            .bind(network)
+            .fetch_all(&mut *tx)
+            .await?;
+        let host = Self::lowest_free(&used, 2..=254).ok_or(SyncError::NoAvailableIp)?;
+        let attachment = NetworkAttachment {
+            container_id: container_id.to_string(),
+            network: network.to_string(),
+            subnet_index: subnet_index as u8,
+            ip_address: network_address(subnet_index as u8, host as u8),
+            veth_host: attachment_veth_names(container_id, subnet_index as u8).0,
+            attached_at: now,
+        };
+        sqlx::query(r#"
+            INSERT INTO network_attachments (container_id, network, host, ip_address, veth_host, attached_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#)
+            .bind(&attachment.container_id)
+            .bind(&attachment.network)
+            .bind(host)
+            .bind(&attachment.ip_address)
+            .bind(&attachment.veth_host)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(attachment)
+    }
+
+    /// Release the container's address on `network`; `None` when it was not connected
+    pub async fn detach_network(&self, container_id: &str, network: &str) -> SyncResult<Option<NetworkAttachment>> {
+        let attachment = self.list(Some(container_id)).await?
+            .into_iter()
+            .find(|attachment| attachment.network == network);
+        if attachment.is_some() {
+            sqlx::query("DELETE FROM network_attachments WHERE container_id = ? AND network = ?")
+                .bind(container_id)
+                .bind(network)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(attachment)
+    }
+
+    /// The container's additional networks, by name
+    pub async fn get_network_attachments(&self, container_id: &str) -> SyncResult<Vec<NetworkAttachment>> {
+        self.list(Some(container_id)).await
+    }
+
+    /// Every container's additional networks
+    pub async fn list_network_attachments(&self) -> SyncResult<Vec<NetworkAttachment>> {
+        self.list(None).await
+    }
+
+    async fn list(&self, container_id: Option<&str>) -> SyncResult<Vec<NetworkAttachment>> {
+        let rows = sqlx::query(r#"
+            SELECT container_id, network, subnet_index, ip_address, veth_host, attached_at
+            FROM network_attachments JOIN networks ON networks.name = network_attachments.network
+            WHERE ? IS NULL OR container_id = ?
+            ORDER BY container_id, network
+        "#)
+            .bind(container_id)
+            .bind(container_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| NetworkAttachment {
+            container_id: row.get("container_id"),
+            network: row.get("network"),
+            subnet_index: row.get::<i64, _>("subnet_index") as u8,
+            ip_address: row.get("ip_address"),
+            veth_host: row.get("veth_host"),
+            attached_at: row.get("attached_at"),
+        }).collect())
+    }
+
+    fn lowest_free(used: &[i64], range: std::ops::RangeInclusive<i64>) -> Option<i64> {
+        range.into_iter().find(|candidate| !used.contains(candidate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_network_attachments() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        for id in ["c1aaaaaaaa", "c2bbbbbbbb"] {
+            sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES (?, 'img', 'cmd', 'running', 0, 0)")
+                .bind(id)
+                .execute(conn_manager.pool())
+                .await
+                .unwrap();
+        }
+        let attachments = NetworkAttachmentManager::new(conn_manager.pool().clone());
+
+        let first = attachments.attach_network("c1aaaaaaaa", "backend").await.unwrap();
+        assert_eq!((first.subnet_index, first.ip_address.as_str(), first.veth_host.as_str()), (1, "10.43.1.2", "qac1aaaaaa01"));
+        let second = attachments.attach_network("c2bbbbbbbb", "backend").await.unwrap();
+        assert_eq!(second.ip_address, "10.43.1.3");
+        assert_eq!(attachments.attach_network("c1aaaaaaaa", "metrics").await.unwrap().ip_address, "10.43.2.2");
+        assert!(matches!(attachments.attach_network("c1aaaaaaaa", "backend").await, Err(SyncError::ValidationFailed { .. })));
+        assert_eq!(attachments.get_network_attachments("c1aaaaaaaa").await.unwrap().len(), 2);
+
+        // A released address is handed out again, the network keeps its subnet
+        assert_eq!(attachments.detach_network("c1aaaaaaaa", "backend").await.unwrap(), Some(first.clone()));
+        assert_eq!(attachments.detach_network("c1aaaaaaaa", "backend").await.unwrap(), None);
+        let again = attachments.attach_network("c1aaaaaaaa", "backend").await.unwrap();
+        assert_eq!(again.ip_address, first.ip_address);
+
+        sqlx::query("DELETE FROM containers WHERE id = 'c1aaaaaaaa'").execute(conn_manager.pool()).await.unwrap();
+        let remaining = attachments.list_network_attachments().await.unwrap();
+        assert_eq!(remaining, vec![second]);
+    }
+}
@@ -0,0 +1,221 @@
+// Long-running operation tracking
+// Create and start return an operation ID right away and finish in the background; the work
+// reports its phase and percent here so callers can follow it until it completes. Finished
+// operations are kept for a while so a client that subscribes late still sees the outcome.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Finished operations kept for late subscribers before the oldest are forgotten
+const FINISHED_RETENTION: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    /// What the operation does, e.g. `create` or `start`
+    pub kind: String,
+    pub container_id: String,
+    pub phase: String,
+    /// 0-100; only reaches 100 once the operation succeeded
+    pub percent: u32,
+    pub done: bool,
+    pub error: Option<String>,
+    pub started_at: u64,
+    pub updated_at: u64,
+}
+
+struct Entry {
+    /// Registration order, so the oldest finished operations are forgotten first
+    sequence: u64,
+    sender: Arc<watch::Sender<OperationProgress>>,
+}
+
+pub struct OperationRegistry {
+    operations: RwLock<HashMap<String, Entry>>,
+    next_sequence: AtomicU64,
+}
+
+/// Reports on one operation; finishing it is up to the owner
+#[derive(Clone)]
+pub struct OperationHandle {
+    sender: Arc<watch::Sender<OperationProgress>>,
+}
+
+impl Default for OperationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        Self {
+            operations: RwLock::new(HashMap::new()),
+            next_sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a new operation of `kind` on a container
+    pub fn begin(&self, kind: &str, container_id: &str) -> OperationHandle {
+        let now = now_millis();
+        let progress = OperationProgress {
+            operation_id: Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            container_id: container_id.to_string(),
+            phase: "queued".to_string(),
+            percent: 0,
+            done: false,
+            error: None,
+            started_at: now,
+            updated_at: now,
+        };
+        let operation_id = progress.operation_id.clone();
+        let sender = Arc::new(watch::channel(progress).0);
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+
+        let mut operations = self.operations.write();
+        prune_finished(&mut operations);
+        operations.insert(operation_id, Entry { sequence, sender: Arc::clone(&sender) });
+        OperationHandle { sender }
+    }
+
+    /// Current state of an operation
+    pub fn get(&self, operation_id: &str) -> Option<OperationProgress> {
+        self.operations.read().get(operation_id).map(|entry| entry.sender.borrow().clone())
+    }
+
+    /// Follow an operation: the receiver starts at its current state
+    pub fn subscribe(&self, operation_id: &str) -> Option<watch::Receiver<OperationProgress>> {
+        self.operations.read().get(operation_id).map(|entry| entry.sender.subscribe())
+    }
+
+    /// Report progress on every unfinished operation of a container; for code deep in a
+    /// container's startup that does not know which request started it
+    pub fn report(&self, container_id: &str, percent: u32, phase: &str) {
+        for entry in self.operations.read().values() {
+            let pending = {
+                let progress = entry.sender.borrow();
+                progress.container_id == container_id && !progress.done
+            };
+            if pending {
+                OperationHandle { sender: Arc::clone(&entry.sender) }.progress(percent, phase);
+            }
+        }
+    }
+}
+
+impl OperationHandle {
+    pub fn operation_id(&self) -> String {
+        self.sender.borrow().operation_id.clone()
+    }
+
+    /// Move to a new phase; percent never goes backwards and stays below 100 until success
+    pub fn progress(&self, percent: u32, phase: &str) {
+        self.sender.send_modify(|progress| {
+            if progress.done {
+                return;
+            }
+            progress.percent = progress.percent.max(percent.min(99));
+            progress.phase = phase.to_string();
+            progress.updated_at = now_millis();
+        });
+    }
+
+    /// Record the outcome; later reports are ignored
+    pub fn finish(&self, result: Result<(), String>) {
+        self.sender.send_modify(|progress| {
+            if progress.done {
+                return;
+            }
+            progress.done = true;
+            progress.updated_at = now_millis();
+            match result {
+                Ok(()) => {
+                    progress.percent = 100;
+                    progress.phase = "completed".to_string();
+                }
+                Err(error) => {
+                    progress.phase = "failed".to_string();
+                    progress.error = Some(error);
+                }
+            }
+        });
+    }
+}
+
+/// Drop the oldest finished operations beyond the retention limit; running ones are never dropped
+fn prune_finished(operations: &mut HashMap<String, Entry>) {
+    let mut finished: Vec<(u64, String)> = operations.iter()
+        .filter(|(_, entry)| entry.sender.borrow().done)
+        .map(|(id, entry)| (entry.sequence, id.clone()))
+        .collect();
+    if finished.len() <= FINISHED_RETENTION {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() - FINISHED_RETENTION) {
+        operations.remove(id);
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Global operation registry instance
+static OPERATIONS: once_cell::sync::OnceCell<OperationRegistry> = once_cell::sync::OnceCell::new();
+
+pub fn global_operations() -> &'static OperationRegistry {
+    OPERATIONS.get_or_init(OperationRegistry::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_progress() {
+        let registry = OperationRegistry::new();
+        let operation = registry.begin("create", "c1");
+        let id = operation.operation_id();
+        let mut receiver = registry.subscribe(&id).unwrap();
+
+        registry.report("c1", 40, "extracting image");
+        registry.report("c2", 90, "other container");
+        operation.progress(20, "late report");
+        assert!(receiver.has_changed().unwrap());
+        let progress = receiver.borrow_and_update().clone();
+        assert_eq!((progress.percent, progress.phase.as_str()), (40, "late report"));
+
+        operation.progress(100, "starting process");
+        assert_eq!(registry.get(&id).unwrap().percent, 99);
+
+        operation.finish(Err("boom".to_string()));
+        operation.finish(Ok(()));
+        let progress = registry.get(&id).unwrap();
+        assert!(progress.done);
+        assert_eq!(progress.error.as_deref(), Some("boom"));
+        assert_eq!(progress.phase, "failed");
+        assert!(registry.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_finished_operations_pruned() {
+        let registry = OperationRegistry::new();
+        let running = registry.begin("start", "c0").operation_id();
+        let first = registry.begin("create", "c1");
+        first.finish(Ok(()));
+        for i in 0..FINISHED_RETENTION {
+            registry.begin("create", &format!("c{}", i + 2)).finish(Ok(()));
+        }
+        registry.begin("create", "last");
+        assert!(registry.get(&first.operation_id()).is_none());
+        assert!(registry.get(&running).is_some());
+        assert_eq!(registry.operations.read().len(), FINISHED_RETENTION + 2);
+    }
+}
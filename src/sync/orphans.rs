@@ -0,0 +1,204 @@
+// Orphaned resource detection
+// Finds host resources that outlived the container they belonged to: host-side veths, cgroup
+// directories and rootfs directories nobody references anymore, and running rows whose process
+// is gone. The sync engine turns each finding into cleanup tasks (or only reports it on a dry run).
+
+use crate::daemon::paths;
+use crate::sync::containers::{ContainerState, ContainerStatus};
+use crate::utils::process::ProcessUtils;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Interfaces named after a container ID prefix: `veth-<8>` on the host and `vethc-<8>` when the
+/// container end never made it into the namespace. Warm-pool veths (`qph*`) belong to the pool.
+const VETH_PREFIXES: &[&str] = &["veth-", "vethc-"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanKind {
+    Veth,
+    Cgroup,
+    Rootfs,
+    /// A row still marked running whose process no longer exists
+    DeadProcess,
+}
+
+impl OrphanKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrphanKind::Veth => "veth",
+            OrphanKind::Cgroup => "cgroup",
+            OrphanKind::Rootfs => "rootfs",
+            OrphanKind::DeadProcess => "dead_process",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrphanedResource {
+    pub kind: OrphanKind,
+    /// Owning container ID, or the ID prefix a veth was named after
+    pub container_id: String,
+    /// Interface name, directory, or PID
+    pub resource: String,
+    pub detail: String,
+    /// False when the resource is reported but left alone (e.g. a rootfs with live mounts)
+    pub cleanable: bool,
+    /// Cleanup tasks scheduled for it; empty on a dry run
+    pub task_ids: Vec<i64>,
+}
+
+impl OrphanedResource {
+    fn new(kind: OrphanKind, container_id: &str, resource: &str, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            container_id: container_id.to_string(),
+            resource: resource.to_string(),
+            detail: detail.into(),
+            cleanable: true,
+            task_ids: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OrphanReport {
+    pub dry_run: bool,
+    pub resources: Vec<OrphanedResource>,
+}
+
+/// Scan the host for orphans of `containers` (every known row). Blocking: runs `ip link` and
+/// walks /proc, /sys/fs/cgroup and the rootfs directory.
+pub fn scan_orphans(containers: &[ContainerStatus]) -> Vec<OrphanedResource> {
+    let mut found = Vec::new();
+
+    let mut live_ids = HashSet::new();
+    for container in containers {
+        if !matches!(container.state, ContainerState::Running | ContainerState::Starting) {
+            continue;
+        }
+        // Starting rows belong to the startup pipeline, which handles its own failures
+        match container.pid.filter(|pid| *pid > 0) {
+            Some(pid) if container.state == ContainerState::Running
+                && !ProcessUtils::is_process_running(ProcessUtils::i32_to_pid(pid as i32)) => {
+                found.push(OrphanedResource::new(OrphanKind::DeadProcess, &container.id, &pid.to_string(),
+                    format!("marked running but process {} is gone", pid)));
+            }
+            _ => {
+                live_ids.insert(container.id.clone());
+            }
+        }
+    }
+
+    let links = crate::utils::command::CommandExecutor::run("ip", ["-o", "link", "show"])
+        .map(|result| result.stdout)
+        .unwrap_or_default();
+    for (name, prefix) in leaked_veths(&links, &live_ids) {
+        found.push(OrphanedResource::new(OrphanKind::Veth, &prefix, &name, "no running container owns this interface"));
+    }
+
+    for cgroup in cgroup_dirs() {
+        let Some(id) = cgroup.file_name().map(|name| name.to_string_lossy().to_string()) else { continue };
+        if live_ids.contains(&id) {
+            continue;
+        }
+        let procs = std::fs::read_to_string(cgroup.join("cgroup.procs")).unwrap_or_default();
+        if procs.trim().is_empty() {
+            found.push(OrphanedResource::new(OrphanKind::Cgroup, &id, &cgroup.to_string_lossy(), "no running container and no processes"));
+        }
+    }
+
+    let known_ids: HashSet<&str> = containers.iter().map(|container| container.id.as_str()).collect();
+    let referenced: HashSet<&str> = containers.iter().filter_map(|container| container.rootfs_path.as_deref()).collect();
+    let base_dir = paths::rootfs_base_dir();
+    let entries: Vec<String> = std::fs::read_dir(base_dir)
+        .map(|entries| entries.flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect())
+        .unwrap_or_default();
+    let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+    for name in orphaned_rootfs_dirs(base_dir, &entries, &known_ids, &referenced) {
+        let path = format!("{}/{}", base_dir, name);
+        let mut orphan = OrphanedResource::new(OrphanKind::Rootfs, &name, &path, "no container record references this rootfs");
+        if has_mounts_below(&mounts, &path) {
+            // Deleting through a live bind mount would delete volume data
+            orphan.cleanable = false;
+            orphan.detail = "no container record references this rootfs, but it still has mounts; unmount manually".to_string();
+        }
+        found.push(orphan);
+    }
+
+    found
+}
+
+/// `(interface, container ID prefix)` for container veths in `ip -o link show` output whose prefix
+/// matches no live container
+fn leaked_veths(links: &str, live_ids: &HashSet<String>) -> Vec<(String, String)> {
+    links.lines()
+        .filter_map(|line| line.split(':').nth(1))
+        .map(|name| name.trim().split('@').next().unwrap_or("").to_string())
+        .filter_map(|name| {
+            let prefix = VETH_PREFIXES.iter().find_map(|p| name.strip_prefix(p))?.to_string();
+            (prefix.len() == 8 && !live_ids.iter().any(|id| id.starts_with(&prefix))).then(|| (name, prefix))
+        })
+        .collect()
+}
+
+/// Rootfs directory names that belong to no container row; dot-directories (the rootfs pool) are skipped
+fn orphaned_rootfs_dirs<'a>(base_dir: &str, entries: &'a [String], known_ids: &HashSet<&str>, referenced: &HashSet<&str>) -> Vec<&'a str> {
+    entries.iter()
+        .map(|name| name.as_str())
+        .filter(|name| !name.starts_with('.'))
+        .filter(|name| !known_ids.contains(name))
+        .filter(|name| !referenced.contains(format!("{}/{}", base_dir, name).as_str()))
+        .collect()
+}
+
+fn has_mounts_below(mounts: &str, path: &str) -> bool {
+    mounts.lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .any(|target| target == path || target.starts_with(&format!("{}/", path)))
+}
+
+/// Per-container cgroup directories: the single v2 tree, or the v1 memory hierarchy
+fn cgroup_dirs() -> Vec<std::path::PathBuf> {
+    let root = Path::new("/sys/fs/cgroup");
+    let parent = if root.join("cgroup.controllers").exists() {
+        root.join("quilt")
+    } else {
+        root.join("memory").join("quilt")
+    };
+    std::fs::read_dir(parent)
+        .map(|entries| entries.flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|entry| entry.path())
+            .collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaked_veths() {
+        let links = "1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536\n\
+                     7: veth-0123abcd@if6: <BROADCAST,MULTICAST,UP> mtu 1500 master quilt0\n\
+                     9: veth-feedbeef@if8: <BROADCAST,MULTICAST,UP> mtu 1500 master quilt0\n\
+                     11: qph3@if10: <BROADCAST,MULTICAST,UP> mtu 1500 master quilt0\n";
+        let live: HashSet<String> = ["0123abcd-1111-2222".to_string()].into_iter().collect();
+        assert_eq!(leaked_veths(links, &live), vec![("veth-feedbeef".to_string(), "feedbeef".to_string())]);
+    }
+
+    #[test]
+    fn test_orphaned_rootfs_dirs() {
+        let entries = vec![".pool".to_string(), "known".to_string(), "restored".to_string(), "stray".to_string()];
+        let known: HashSet<&str> = ["known"].into_iter().collect();
+        let referenced: HashSet<&str> = ["/tmp/quilt-containers/restored"].into_iter().collect();
+        assert_eq!(orphaned_rootfs_dirs("/tmp/quilt-containers", &entries, &known, &referenced), vec!["stray"]);
+
+        let mounts = "overlay / overlay rw 0 0\n/dev/sda1 /tmp/quilt-containers/stray/data ext4 rw 0 0\n";
+        assert!(has_mounts_below(mounts, "/tmp/quilt-containers/stray"));
+        assert!(!has_mounts_below(mounts, "/tmp/quilt-containers/str"));
+    }
+}
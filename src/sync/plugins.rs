@@ -0,0 +1,101 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::SyncResult;
+
+/// A registered plugin: where it listens and which extension services it implements
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginRecord {
+    pub name: String,
+    pub endpoint: String,
+    pub capabilities: Vec<String>,
+    pub created_at: i64,
+}
+
+pub struct PluginManager {
+    pool: SqlitePool,
+}
+
+impl PluginManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a plugin, replacing an earlier registration under the same name
+    pub async fn register_plugin(&self, name: &str, endpoint: &str, capabilities: &[String]) -> SyncResult<PluginRecord> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        sqlx::query(r#"
+            INSERT INTO plugins (name, endpoint, capabilities, created_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET endpoint = excluded.endpoint, capabilities = excluded.capabilities
+        "#)
+        .bind(name)
+        .bind(endpoint)
+        .bind(serde_json::to_string(capabilities)?)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(self.get_plugin(name).await?.unwrap_or(PluginRecord {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            capabilities: capabilities.to_vec(),
+            created_at: now,
+        }))
+    }
+
+    /// Returns whether the plugin was registered
+    pub async fn unregister_plugin(&self, name: &str) -> SyncResult<bool> {
+        let result = sqlx::query("DELETE FROM plugins WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_plugin(&self, name: &str) -> SyncResult<Option<PluginRecord>> {
+        let row = sqlx::query("SELECT name, endpoint, capabilities, created_at FROM plugins WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| Self::record(&row)))
+    }
+
+    pub async fn list_plugins(&self) -> SyncResult<Vec<PluginRecord>> {
+        let rows = sqlx::query("SELECT name, endpoint, capabilities, created_at FROM plugins ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::record).collect())
+    }
+
+    fn record(row: &sqlx::sqlite::SqliteRow) -> PluginRecord {
+        PluginRecord {
+            name: row.get("name"),
+            endpoint: row.get("endpoint"),
+            capabilities: serde_json::from_str(&row.get::<String, _>("capabilities")).unwrap_or_default(),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_plugin_registration() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let plugins = PluginManager::new(conn_manager.pool().clone());
+
+        plugins.register_plugin("nfs", "unix:///run/quilt/plugins/nfs.sock", &["volume_driver".to_string()]).await.unwrap();
+        let updated = plugins.register_plugin("nfs", "http://127.0.0.1:7000", &["volume_driver".to_string()]).await.unwrap();
+        assert_eq!(updated.endpoint, "http://127.0.0.1:7000");
+        assert_eq!(plugins.list_plugins().await.unwrap().len(), 1);
+
+        assert!(plugins.unregister_plugin("nfs").await.unwrap());
+        assert!(!plugins.unregister_plugin("nfs").await.unwrap());
+        assert!(plugins.get_plugin("nfs").await.unwrap().is_none());
+    }
+}
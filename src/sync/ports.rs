@@ -0,0 +1,230 @@
+use sqlx::{SqlitePool, Row};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, UdpSocket};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+use crate::utils::validation::PortMapping;
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Allocation metadata kept for inspection
+pub struct PortBinding {
+    pub container_id: String,
+    pub host_ip: String,
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: String,
+    pub created_at: i64,
+}
+
+impl PortBinding {
+    pub fn to_mapping(&self) -> PortMapping {
+        PortMapping {
+            host_ip: self.host_ip.clone(),
+            host_port: self.host_port,
+            container_port: self.container_port,
+            protocol: self.protocol.clone(),
+        }
+    }
+}
+
+pub struct PortManager {
+    pool: SqlitePool,
+}
+
+impl PortManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Reject mappings that collide with each other, with ports already allocated to
+    /// other containers, or with ports some other process has bound on the host
+    pub async fn check_conflicts(&self, mappings: &[PortMapping]) -> SyncResult<()> {
+        for (i, mapping) in mappings.iter().enumerate() {
+            if mappings[..i].iter().any(|other| Self::overlaps(other, mapping)) {
+                return Err(SyncError::ValidationFailed {
+                    message: format!("Host port {}/{} is requested more than once", mapping.host_port, mapping.protocol),
+                });
+            }
+
+            let owner: Option<String> = sqlx::query_scalar(r#"
+                SELECT container_id FROM port_bindings
+                WHERE host_port = ? AND protocol = ?
+                  AND (host_ip = ? OR host_ip = '0.0.0.0' OR ? = '0.0.0.0')
+                LIMIT 1
+            "#)
+            .bind(mapping.host_port as i64)
+            .bind(&mapping.protocol)
+            .bind(&mapping.host_ip)
+            .bind(&mapping.host_ip)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            if let Some(container_id) = owner {
+                return Err(SyncError::ValidationFailed {
+                    message: format!("Host port {}:{}/{} is already published by container {}",
+                        mapping.host_ip, mapping.host_port, mapping.protocol, container_id),
+                });
+            }
+
+            if Self::host_port_in_use(mapping) {
+                return Err(SyncError::ValidationFailed {
+                    message: format!("Host port {}:{}/{} is already in use by another process on the host",
+                        mapping.host_ip, mapping.host_port, mapping.protocol),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Two mappings overlap when they share port and protocol and either binds all addresses
+    fn overlaps(a: &PortMapping, b: &PortMapping) -> bool {
+        a.host_port == b.host_port && a.protocol == b.protocol
+            && (a.host_ip == b.host_ip || a.host_ip == "0.0.0.0" || b.host_ip == "0.0.0.0")
+    }
+
+    /// Probe whether a host process already holds the port by trying to bind it
+    fn host_port_in_use(mapping: &PortMapping) -> bool {
+        let ip = mapping.host_ip.parse::<Ipv4Addr>().unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let addr = SocketAddrV4::new(ip, mapping.host_port);
+
+        let result = if mapping.protocol == "udp" {
+            UdpSocket::bind(addr).map(|_| ())
+        } else {
+            TcpListener::bind(addr).map(|_| ())
+        };
+
+        matches!(result, Err(ref e) if e.kind() == std::io::ErrorKind::AddrInUse)
+    }
+
+    pub async fn add_port_bindings(&self, container_id: &str, mappings: &[PortMapping]) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+        for mapping in mappings {
+            sqlx::query(r#"
+                INSERT INTO port_bindings (container_id, host_ip, host_port, container_port, protocol, created_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+            "#)
+            .bind(container_id)
+            .bind(&mapping.host_ip)
+            .bind(mapping.host_port as i64)
+            .bind(mapping.container_port as i64)
+            .bind(&mapping.protocol)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        tracing::debug!("Allocated {} port bindings for container {}", mappings.len(), container_id);
+        Ok(())
+    }
+
+    pub async fn get_port_bindings(&self, container_id: &str) -> SyncResult<Vec<PortBinding>> {
+        let rows = sqlx::query(
+            "SELECT container_id, host_ip, host_port, container_port, protocol, created_at
+             FROM port_bindings WHERE container_id = ? ORDER BY host_port"
+        )
+        .bind(container_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::row_to_binding).collect())
+    }
+
+    /// Every allocated host port, across all containers
+    pub async fn list_port_bindings(&self) -> SyncResult<Vec<PortBinding>> {
+        let rows = sqlx::query(
+            "SELECT container_id, host_ip, host_port, container_port, protocol, created_at
+             FROM port_bindings ORDER BY host_port, protocol"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::row_to_binding).collect())
+    }
+
+    pub async fn remove_port_bindings(&self, container_id: &str) -> SyncResult<()> {
+        sqlx::query("DELETE FROM port_bindings WHERE container_id = ?")
+            .bind(container_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    fn row_to_binding(row: &sqlx::sqlite::SqliteRow) -> PortBinding {
+        PortBinding {
+            container_id: row.get("container_id"),
+            host_ip: row.get("host_ip"),
+            host_port: row.get::<i64, _>("host_port") as u16,
+            container_port: row.get::<i64, _>("container_port") as u16,
+            protocol: row.get("protocol"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    async fn setup_test_db() -> (ConnectionManager, PortManager) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let schema_manager = SchemaManager::new(conn_manager.pool().clone());
+        schema_manager.initialize_schema().await.unwrap();
+
+        let port_manager = PortManager::new(conn_manager.pool().clone());
+
+        (conn_manager, port_manager)
+    }
+
+    fn mapping(host_ip: &str, host_port: u16, container_port: u16) -> PortMapping {
+        PortMapping {
+            host_ip: host_ip.to_string(),
+            host_port,
+            container_port,
+            protocol: "tcp".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_port_conflicts() {
+        let (conn, port_manager) = setup_test_db().await;
+
+        sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES ('c1', 'img', 'cmd', 'created', 0, 0)")
+            .execute(conn.pool())
+            .await
+            .unwrap();
+
+        port_manager.add_port_bindings("c1", &[mapping("0.0.0.0", 48080, 80)]).await.unwrap();
+        let listed = port_manager.list_port_bindings().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].container_id.as_str(), listed[0].host_port), ("c1", 48080));
+
+        // Same port on a specific address collides with the wildcard binding
+        let err = port_manager.check_conflicts(&[mapping("127.0.0.1", 48080, 8080)]).await.unwrap_err();
+        assert!(err.to_string().contains("c1"));
+
+        // Duplicate ports within one request are rejected
+        assert!(port_manager.check_conflicts(&[mapping("0.0.0.0", 48081, 80), mapping("0.0.0.0", 48081, 81)]).await.is_err());
+
+        // A UDP mapping on the same number does not collide with TCP
+        let mut udp = mapping("0.0.0.0", 48080, 53);
+        udp.protocol = "udp".to_string();
+        assert!(port_manager.check_conflicts(&[udp]).await.is_ok());
+
+        port_manager.remove_port_bindings("c1").await.unwrap();
+        assert!(port_manager.get_port_bindings("c1").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_host_port_in_use() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(PortManager::host_port_in_use(&mapping("127.0.0.1", port, 80)));
+    }
+}
@@ -12,14 +12,31 @@ impl SchemaManager {
     
     pub async fn initialize_schema(&self) -> SyncResult<()> {
         self.create_containers_table().await?;
+        self.migrate_containers_table().await?;
         self.create_network_allocations_table().await?;
         self.create_network_state_table().await?;
         self.create_process_monitors_table().await?;
+        self.add_column_if_missing("process_monitors", "check_count", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("process_monitors", "consecutive_failures", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("process_monitors", "last_error", "TEXT").await?;
         self.create_container_logs_table().await?;
+        self.add_column_if_missing("container_logs", "actor", "TEXT NOT NULL DEFAULT 'daemon'").await?;
         self.create_cleanup_tasks_table().await?;
         self.create_volumes_table().await?;
         self.create_container_mounts_table().await?;
+        self.create_port_bindings_table().await?;
         self.create_container_metrics_table().await?;
+        self.add_column_if_missing("container_metrics", "cpu_percent", "REAL").await?;
+        self.add_column_if_missing("container_metrics", "memory_percent", "REAL").await?;
+        self.create_plugins_table().await?;
+        self.create_setup_steps_table().await?;
+        self.create_virtual_services_table().await?;
+        self.create_dns_records_table().await?;
+        self.create_image_scans_table().await?;
+        self.create_images_tables().await?;
+        self.add_column_if_missing("image_sources", "last_used_at", "INTEGER").await?;
+        self.create_network_attachments_tables().await?;
+        self.create_traffic_mirrors_table().await?;
         self.create_indexes().await?;
         
         tracing::info!("Database schema initialized successfully");
@@ -27,34 +44,97 @@ impl SchemaManager {
     }
     
     async fn create_containers_table(&self) -> SyncResult<()> {
-        sqlx::query(r#"
-            CREATE TABLE IF NOT EXISTS containers (
-                id TEXT PRIMARY KEY,
-                name TEXT,
-                image_path TEXT NOT NULL,
-                command TEXT NOT NULL,
-                environment TEXT, -- JSON blob
-                state TEXT CHECK(state IN ('created', 'starting', 'running', 'exited', 'error')) NOT NULL,
-                exit_code INTEGER,
-                pid INTEGER,
-                rootfs_path TEXT,
-                created_at INTEGER NOT NULL,
-                started_at INTEGER,
-                exited_at INTEGER,
-                memory_limit_mb INTEGER,
-                cpu_limit_percent REAL,
-                
-                -- Resource configuration
-                enable_network_namespace BOOLEAN NOT NULL DEFAULT 1,
-                enable_pid_namespace BOOLEAN NOT NULL DEFAULT 1,
-                enable_mount_namespace BOOLEAN NOT NULL DEFAULT 1,
-                enable_uts_namespace BOOLEAN NOT NULL DEFAULT 1,
-                enable_ipc_namespace BOOLEAN NOT NULL DEFAULT 1,
-                
-                -- Metadata
-                updated_at INTEGER NOT NULL
-            )
-        "#).execute(&self.pool).await?;
+        sqlx::query(&containers_table_sql("containers")).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    /// Add columns introduced after the initial containers schema to existing databases
+    async fn migrate_containers_table(&self) -> SyncResult<()> {
+        self.add_column_if_missing("containers", "allow_fuse", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "fuse_mounts", "TEXT").await?;
+        self.add_column_if_missing("containers", "labels", "TEXT").await?;
+        self.add_column_if_missing("containers", "immutable", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "network_policy", "TEXT").await?;
+        self.add_column_if_missing("containers", "hooks", "TEXT").await?;
+        self.add_column_if_missing("containers", "prestart_hooks", "TEXT").await?;
+        self.add_column_if_missing("containers", "network_plugin", "TEXT").await?;
+        self.add_column_if_missing("containers", "egress_proxy", "TEXT").await?;
+        self.add_column_if_missing("containers", "priority_class", "TEXT NOT NULL DEFAULT 'normal'").await?;
+        self.add_column_if_missing("containers", "readiness_gates", "TEXT").await?;
+        self.add_column_if_missing("containers", "setup_commands", "TEXT").await?;
+        self.add_column_if_missing("containers", "time_offset", "INTEGER").await?;
+        self.add_column_if_missing("containers", "enable_time_namespace", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "monotonic_offset", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "boottime_offset", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "enable_cgroup_namespace", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "enable_user_namespace", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "memory_high_mb", "INTEGER").await?;
+        self.add_column_if_missing("containers", "memory_swap_mb", "INTEGER").await?;
+        self.add_column_if_missing("containers", "memory_swappiness", "INTEGER").await?;
+        self.add_column_if_missing("containers", "cpu_burst_percent", "REAL").await?;
+        self.add_column_if_missing("containers", "metrics_interval_seconds", "INTEGER").await?;
+        self.add_column_if_missing("containers", "metrics_enabled", "BOOLEAN NOT NULL DEFAULT 1").await?;
+        self.add_column_if_missing("containers", "working_directory", "TEXT").await?;
+        self.add_column_if_missing("containers", "run_as_user", "TEXT").await?;
+        self.add_column_if_missing("containers", "timezone", "TEXT").await?;
+        self.add_column_if_missing("containers", "locale", "TEXT").await?;
+        self.add_column_if_missing("containers", "dns_wildcard", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("containers", "privileged", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        self.widen_container_states().await?;
+        Ok(())
+    }
+    
+    /// SQLite cannot alter a CHECK constraint, so databases created before the intermediate
+    /// lifecycle states existed get their containers table rebuilt with the current definition
+    async fn widen_container_states(&self) -> SyncResult<()> {
+        let sql: String = sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'containers'")
+            .fetch_one(&self.pool)
+            .await?;
+        if sql.contains("'removing'") {
+            return Ok(());
+        }
+        
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('containers')")
+            .fetch_all(&self.pool)
+            .await?;
+        let columns = columns.join(", ");
+        
+        // Foreign keys are per connection and cannot change inside a transaction
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+        let rebuilt = async {
+            sqlx::query("BEGIN").execute(&mut *conn).await?;
+            sqlx::query("DROP TABLE IF EXISTS containers_new").execute(&mut *conn).await?;
+            sqlx::query(&containers_table_sql("containers_new")).execute(&mut *conn).await?;
+            sqlx::query(&format!("INSERT INTO containers_new ({0}) SELECT {0} FROM containers", columns))
+                .execute(&mut *conn).await?;
+            sqlx::query("DROP TABLE containers").execute(&mut *conn).await?;
+            sqlx::query("ALTER TABLE containers_new RENAME TO containers").execute(&mut *conn).await?;
+            sqlx::query("COMMIT").execute(&mut *conn).await?;
+            Ok::<(), sqlx::Error>(())
+        }.await;
+        if rebuilt.is_err() {
+            let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+        }
+        sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+        rebuilt?;
+        
+        tracing::info!("Rebuilt containers table to allow intermediate lifecycle states");
+        Ok(())
+    }
+    
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> SyncResult<()> {
+        let columns: Vec<String> = sqlx::query_scalar(&format!("SELECT name FROM pragma_table_info('{}')", table))
+            .fetch_all(&self.pool)
+            .await?;
+        
+        if !columns.iter().any(|c| c == column) {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await?;
+            tracing::info!("Added column {}.{} to existing database", table, column);
+        }
         
         Ok(())
     }
@@ -97,6 +177,9 @@ impl SchemaManager {
                 monitor_started_at INTEGER NOT NULL,
                 last_check_at INTEGER,
                 status TEXT CHECK(status IN ('monitoring', 'completed', 'failed', 'aborted')) NOT NULL,
+                check_count INTEGER NOT NULL DEFAULT 0,
+                consecutive_failures INTEGER NOT NULL DEFAULT 0, -- Reset by a successful check
+                last_error TEXT,
                 FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
             )
         "#).execute(&self.pool).await?;
@@ -112,6 +195,7 @@ impl SchemaManager {
                 timestamp INTEGER NOT NULL,
                 level TEXT CHECK(level IN ('debug', 'info', 'warn', 'error')) NOT NULL,
                 message TEXT NOT NULL,
+                actor TEXT NOT NULL DEFAULT 'daemon', -- API caller or 'daemon' for background actions
                 FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
             )
         "#).execute(&self.pool).await?;
@@ -171,6 +255,162 @@ impl SchemaManager {
         Ok(())
     }
     
+    async fn create_port_bindings_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS port_bindings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                container_id TEXT NOT NULL,
+                host_ip TEXT NOT NULL DEFAULT '0.0.0.0',
+                host_port INTEGER CHECK(host_port BETWEEN 1 AND 65535) NOT NULL,
+                container_port INTEGER CHECK(container_port BETWEEN 1 AND 65535) NOT NULL,
+                protocol TEXT CHECK(protocol IN ('tcp', 'udp')) NOT NULL,
+                created_at INTEGER NOT NULL,
+                UNIQUE(host_ip, host_port, protocol),
+                FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_plugins_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS plugins (
+                name TEXT PRIMARY KEY,
+                endpoint TEXT NOT NULL,
+                capabilities TEXT NOT NULL DEFAULT '[]',
+                created_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_virtual_services_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS virtual_services (
+                name TEXT PRIMARY KEY,
+                selector TEXT NOT NULL DEFAULT '{}',
+                created_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_dns_records_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS dns_records (
+                name TEXT NOT NULL,
+                view TEXT NOT NULL CHECK (view IN ('all', 'containers', 'host')),
+                addresses TEXT NOT NULL DEFAULT '[]',
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (name, view)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_image_scans_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS image_scans (
+                image_path TEXT PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                scanner TEXT NOT NULL,
+                findings TEXT NOT NULL DEFAULT '[]',
+                scanned_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_images_tables(&self) -> SyncResult<()> {
+        // Images unpacked in the local store, by digest
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS images (
+                digest TEXT PRIMARY KEY,
+                repo_tags TEXT NOT NULL DEFAULT '[]',
+                layers TEXT NOT NULL DEFAULT '[]',
+                config TEXT NOT NULL DEFAULT '{}',
+                size_bytes INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        // Image files and the stored image each held when it was imported
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS image_sources (
+                source TEXT PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                digest TEXT NOT NULL REFERENCES images(digest)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_network_attachments_tables(&self) -> SyncResult<()> {
+        // Additional networks and the /24 each was given
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS networks (
+                name TEXT PRIMARY KEY,
+                subnet_index INTEGER NOT NULL UNIQUE CHECK (subnet_index BETWEEN 1 AND 254),
+                created_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        // Containers connected to them at runtime, with their address on each
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS network_attachments (
+                container_id TEXT NOT NULL REFERENCES containers(id) ON DELETE CASCADE,
+                network TEXT NOT NULL REFERENCES networks(name),
+                host INTEGER NOT NULL CHECK (host BETWEEN 2 AND 254),
+                ip_address TEXT NOT NULL,
+                veth_host TEXT NOT NULL,
+                attached_at INTEGER NOT NULL,
+                PRIMARY KEY (container_id, network),
+                UNIQUE (network, host)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_traffic_mirrors_table(&self) -> SyncResult<()> {
+        // One observer per mirrored container; either leaving ends the mirror
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS traffic_mirrors (
+                source_container_id TEXT PRIMARY KEY REFERENCES containers(id) ON DELETE CASCADE,
+                observer_container_id TEXT NOT NULL REFERENCES containers(id) ON DELETE CASCADE,
+                direction TEXT NOT NULL CHECK (direction IN ('both', 'ingress', 'egress')),
+                created_at INTEGER NOT NULL,
+                CHECK (source_container_id != observer_container_id)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_setup_steps_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS setup_steps (
+                container_id TEXT NOT NULL,
+                step_index INTEGER NOT NULL,
+                command TEXT NOT NULL,
+                status TEXT CHECK(status IN ('pending', 'succeeded', 'failed')) NOT NULL,
+                output TEXT NOT NULL DEFAULT '',
+                duration_ms INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (container_id, step_index),
+                FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
     async fn create_container_metrics_table(&self) -> SyncResult<()> {
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS container_metrics (
@@ -205,6 +445,10 @@ impl SchemaManager {
                 disk_read_ops INTEGER,
                 disk_write_ops INTEGER,
                 
+                -- Derived usage (percent)
+                cpu_percent REAL,
+                memory_percent REAL,
+                
                 FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
             )
         "#).execute(&self.pool).await?;
@@ -229,6 +473,7 @@ impl SchemaManager {
             "CREATE INDEX IF NOT EXISTS idx_volumes_name ON volumes(name)",
             "CREATE INDEX IF NOT EXISTS idx_container_mounts_container ON container_mounts(container_id)",
             "CREATE INDEX IF NOT EXISTS idx_container_mounts_type ON container_mounts(mount_type)",
+            "CREATE INDEX IF NOT EXISTS idx_port_bindings_container ON port_bindings(container_id)",
             "CREATE INDEX IF NOT EXISTS idx_container_metrics_container_time ON container_metrics(container_id, timestamp)",
             "CREATE INDEX IF NOT EXISTS idx_container_metrics_timestamp ON container_metrics(timestamp)",
         ];
@@ -242,6 +487,86 @@ impl SchemaManager {
     
 }
 
+/// Definition of the containers table under `table`, shared by creation and rebuild migrations
+fn containers_table_sql(table: &str) -> String {
+    format!(r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id TEXT PRIMARY KEY,
+                name TEXT,
+                image_path TEXT NOT NULL,
+                command TEXT NOT NULL,
+                environment TEXT, -- JSON blob
+                state TEXT CHECK(state IN ('created', 'starting', 'running', 'exited', 'error', 'stopping', 'removing', 'restarting', 'paused')) NOT NULL,
+                exit_code INTEGER,
+                pid INTEGER,
+                rootfs_path TEXT,
+                created_at INTEGER NOT NULL,
+                started_at INTEGER,
+                exited_at INTEGER,
+                memory_limit_mb INTEGER,
+                cpu_limit_percent REAL,
+                cpu_burst_percent REAL, -- Extra CPU from unused quota, at most cpu_limit_percent
+                memory_high_mb INTEGER, -- Reclaim/throttle threshold
+                memory_swap_mb INTEGER, -- Swap besides memory, 0 = none, NULL = host default
+                memory_swappiness INTEGER, -- cgroup v1 only
+                metrics_interval_seconds INTEGER, -- Seconds between metrics samples, NULL = default
+                metrics_enabled BOOLEAN NOT NULL DEFAULT 1, -- Sampled by the background collector
+                working_directory TEXT, -- Main process start directory, NULL = image WORKDIR
+                run_as_user TEXT, -- user[:group] of the main process, NULL = image USER
+                timezone TEXT, -- Zone mounted at /etc/localtime and set as TZ, NULL = daemon default
+                locale TEXT, -- LANG, NULL = daemon default
+                
+                -- Resource configuration
+                enable_network_namespace BOOLEAN NOT NULL DEFAULT 1,
+                enable_pid_namespace BOOLEAN NOT NULL DEFAULT 1,
+                enable_mount_namespace BOOLEAN NOT NULL DEFAULT 1,
+                enable_uts_namespace BOOLEAN NOT NULL DEFAULT 1,
+                enable_ipc_namespace BOOLEAN NOT NULL DEFAULT 1,
+                enable_time_namespace BOOLEAN NOT NULL DEFAULT 0,
+                monotonic_offset INTEGER NOT NULL DEFAULT 0, -- Seconds, time namespace only
+                boottime_offset INTEGER NOT NULL DEFAULT 0,
+                enable_cgroup_namespace BOOLEAN NOT NULL DEFAULT 0,
+                enable_user_namespace BOOLEAN NOT NULL DEFAULT 0, -- Mounts are ID-mapped
+                
+                -- FUSE configuration
+                allow_fuse BOOLEAN NOT NULL DEFAULT 0,
+                fuse_mounts TEXT, -- JSON blob
+                
+                -- Labels
+                labels TEXT, -- JSON blob
+                
+                -- DNS
+                dns_wildcard BOOLEAN NOT NULL DEFAULT 0, -- Names below <name>.quilt.local resolve to the container
+                
+                -- Hardening
+                immutable BOOLEAN NOT NULL DEFAULT 0,
+                privileged BOOLEAN NOT NULL DEFAULT 0, -- Unhardened /proc and /sys
+                network_policy TEXT, -- JSON blob
+                
+                -- Lifecycle hooks
+                hooks TEXT, -- JSON blob
+                prestart_hooks TEXT, -- JSON blob
+                
+                -- Plugins
+                network_plugin TEXT,
+                egress_proxy TEXT, -- JSON blob: proxy container, port, transparent
+                
+                -- Scheduling
+                priority_class TEXT NOT NULL DEFAULT 'normal',
+                readiness_gates TEXT, -- JSON blob
+                
+                -- Provisioning
+                setup_commands TEXT, -- JSON blob
+                
+                -- Clock offset in seconds from host time
+                time_offset INTEGER,
+                
+                -- Metadata
+                updated_at INTEGER NOT NULL
+            )
+        "#, table)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,4 +598,4 @@ mod tests {
         
         conn_manager.close().await;
     }
-} 
\ No newline at end of file
+} 
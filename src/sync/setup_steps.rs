@@ -0,0 +1,122 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::SyncResult;
+
+/// Outcome of one setup command of a container's latest start
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupStep {
+    pub container_id: String,
+    pub step_index: i64,
+    /// The setup line as given, e.g. `python: requests flask`
+    pub command: String,
+    /// `pending`, `succeeded` or `failed`
+    pub status: String,
+    /// Captured stdout/stderr of the step, or the error that stopped it
+    pub output: String,
+    pub duration_ms: i64,
+    pub updated_at: i64,
+}
+
+pub struct SetupStepManager {
+    pool: SqlitePool,
+}
+
+impl SetupStepManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Replace a container's steps with pending ones for the given commands, before a start runs them
+    pub async fn reset_steps(&self, container_id: &str, commands: &[String]) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM setup_steps WHERE container_id = ?")
+            .bind(container_id)
+            .execute(&mut *tx)
+            .await?;
+        for (index, command) in commands.iter().enumerate() {
+            sqlx::query(r#"
+                INSERT INTO setup_steps (container_id, step_index, command, status, output, duration_ms, updated_at)
+                VALUES (?, ?, ?, 'pending', '', 0, ?)
+            "#)
+            .bind(container_id)
+            .bind(index as i64)
+            .bind(command)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record how a step finished
+    pub async fn finish_step(&self, container_id: &str, step_index: i64, succeeded: bool, output: &str, duration_ms: i64) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        sqlx::query("UPDATE setup_steps SET status = ?, output = ?, duration_ms = ?, updated_at = ? WHERE container_id = ? AND step_index = ?")
+            .bind(if succeeded { "succeeded" } else { "failed" })
+            .bind(output)
+            .bind(duration_ms)
+            .bind(now)
+            .bind(container_id)
+            .bind(step_index)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_steps(&self, container_id: &str) -> SyncResult<Vec<SetupStep>> {
+        let rows = sqlx::query(r#"
+            SELECT container_id, step_index, command, status, output, duration_ms, updated_at
+            FROM setup_steps WHERE container_id = ? ORDER BY step_index
+        "#)
+        .bind(container_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(|row| SetupStep {
+            container_id: row.get("container_id"),
+            step_index: row.get("step_index"),
+            command: row.get("command"),
+            status: row.get("status"),
+            output: row.get("output"),
+            duration_ms: row.get("duration_ms"),
+            updated_at: row.get("updated_at"),
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_setup_steps() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES ('c1', 'img', 'cmd', 'created', 0, 0)")
+            .execute(conn_manager.pool())
+            .await
+            .unwrap();
+        let steps = SetupStepManager::new(conn_manager.pool().clone());
+
+        let commands = vec!["python: requests".to_string(), "npm: left-pad".to_string()];
+        steps.reset_steps("c1", &commands).await.unwrap();
+        steps.finish_step("c1", 0, true, "Successfully installed requests", 1200).await.unwrap();
+        steps.finish_step("c1", 1, false, "npm ERR! 404 Not Found", 300).await.unwrap();
+
+        let listed = steps.list_steps("c1").await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!((listed[0].status.as_str(), listed[0].duration_ms), ("succeeded", 1200));
+        assert_eq!(listed[1].command, "npm: left-pad");
+        assert_eq!(listed[1].status, "failed");
+
+        // A restart starts over with pending steps
+        steps.reset_steps("c1", &commands[..1]).await.unwrap();
+        let listed = steps.list_steps("c1").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].status, "pending");
+    }
+}
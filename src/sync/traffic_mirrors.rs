@@ -0,0 +1,138 @@
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::SyncResult;
+
+/// A container whose veth traffic is copied to an observer container
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrafficMirror {
+    pub source_container_id: String,
+    pub observer_container_id: String,
+    /// both, ingress or egress, from the source's point of view
+    pub direction: String,
+    pub created_at: i64,
+}
+
+pub struct TrafficMirrorManager {
+    pool: SqlitePool,
+}
+
+impl TrafficMirrorManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record the source's mirror, replacing the one it had
+    pub async fn set_traffic_mirror(&self, source_container_id: &str, observer_container_id: &str, direction: &str) -> SyncResult<TrafficMirror> {
+        let mirror = TrafficMirror {
+            source_container_id: source_container_id.to_string(),
+            observer_container_id: observer_container_id.to_string(),
+            direction: direction.to_string(),
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+        };
+        sqlx::query(r#"
+            INSERT OR REPLACE INTO traffic_mirrors (source_container_id, observer_container_id, direction, created_at)
+            VALUES (?, ?, ?, ?)
+        "#)
+            .bind(&mirror.source_container_id)
+            .bind(&mirror.observer_container_id)
+            .bind(&mirror.direction)
+            .bind(mirror.created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(mirror)
+    }
+
+    /// Forget the source's mirror; `None` when it had none
+    pub async fn remove_traffic_mirror(&self, source_container_id: &str) -> SyncResult<Option<TrafficMirror>> {
+        let mirror = self.get_traffic_mirror(source_container_id).await?;
+        if mirror.is_some() {
+            sqlx::query("DELETE FROM traffic_mirrors WHERE source_container_id = ?")
+                .bind(source_container_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(mirror)
+    }
+
+    pub async fn get_traffic_mirror(&self, source_container_id: &str) -> SyncResult<Option<TrafficMirror>> {
+        Ok(self.list(Some(source_container_id)).await?.into_iter().next())
+    }
+
+    /// Mirrors the container takes part in, as source or as observer
+    pub async fn get_container_traffic_mirrors(&self, container_id: &str) -> SyncResult<Vec<TrafficMirror>> {
+        let rows = sqlx::query(r#"
+            SELECT source_container_id, observer_container_id, direction, created_at FROM traffic_mirrors
+            WHERE source_container_id = ? OR observer_container_id = ?
+            ORDER BY source_container_id
+        "#)
+            .bind(container_id)
+            .bind(container_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::mirror).collect())
+    }
+
+    pub async fn list_traffic_mirrors(&self) -> SyncResult<Vec<TrafficMirror>> {
+        self.list(None).await
+    }
+
+    async fn list(&self, source_container_id: Option<&str>) -> SyncResult<Vec<TrafficMirror>> {
+        let rows = sqlx::query(r#"
+            SELECT source_container_id, observer_container_id, direction, created_at FROM traffic_mirrors
+            WHERE ? IS NULL OR source_container_id = ?
+            ORDER BY source_container_id
+        "#)
+            .bind(source_container_id)
+            .bind(source_container_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::mirror).collect())
+    }
+
+    fn mirror(row: &sqlx::sqlite::SqliteRow) -> TrafficMirror {
+        TrafficMirror {
+            source_container_id: row.get("source_container_id"),
+            observer_container_id: row.get("observer_container_id"),
+            direction: row.get("direction"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_traffic_mirrors() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        for id in ["web", "api", "ids"] {
+            sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES (?, 'img', 'cmd', 'running', 0, 0)")
+                .bind(id)
+                .execute(conn_manager.pool())
+                .await
+                .unwrap();
+        }
+        let mirrors = TrafficMirrorManager::new(conn_manager.pool().clone());
+
+        mirrors.set_traffic_mirror("web", "api", "both").await.unwrap();
+        // A source has one observer; mirroring it again replaces the first
+        mirrors.set_traffic_mirror("web", "ids", "egress").await.unwrap();
+        mirrors.set_traffic_mirror("api", "ids", "both").await.unwrap();
+        assert!(mirrors.set_traffic_mirror("ids", "ids", "both").await.is_err());
+        assert_eq!(mirrors.get_traffic_mirror("web").await.unwrap().unwrap().observer_container_id, "ids");
+        assert_eq!(mirrors.get_container_traffic_mirrors("ids").await.unwrap().len(), 2);
+        assert_eq!(mirrors.get_container_traffic_mirrors("api").await.unwrap().len(), 1);
+
+        assert_eq!(mirrors.remove_traffic_mirror("web").await.unwrap().unwrap().direction, "egress");
+        assert_eq!(mirrors.remove_traffic_mirror("web").await.unwrap(), None);
+
+        // Removing the observer ends the mirrors into it
+        sqlx::query("DELETE FROM containers WHERE id = 'ids'").execute(conn_manager.pool()).await.unwrap();
+        assert!(mirrors.list_traffic_mirrors().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use sqlx::{SqlitePool, Row};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// A DNS name that load-balances across every running container carrying the selector's labels
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualServiceRecord {
+    pub name: String,
+    pub selector: HashMap<String, String>,
+    pub created_at: i64,
+}
+
+pub struct VirtualServiceManager {
+    pool: SqlitePool,
+}
+
+impl VirtualServiceManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new virtual service; names are unique
+    pub async fn create_virtual_service(&self, name: &str, selector: &HashMap<String, String>) -> SyncResult<VirtualServiceRecord> {
+        if selector.is_empty() {
+            return Err(SyncError::ValidationFailed {
+                message: format!("A selector label is required for virtual service '{}'", name),
+            });
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let inserted = sqlx::query("INSERT INTO virtual_services (name, selector, created_at) VALUES (?, ?, ?) ON CONFLICT(name) DO NOTHING")
+            .bind(name)
+            .bind(serde_json::to_string(selector)?)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        if inserted.rows_affected() == 0 {
+            return Err(SyncError::Conflict { message: format!("Virtual service '{}' already exists", name) });
+        }
+
+        Ok(VirtualServiceRecord {
+            name: name.to_string(),
+            selector: selector.clone(),
+            created_at: now,
+        })
+    }
+
+    /// Returns whether the virtual service existed
+    pub async fn delete_virtual_service(&self, name: &str) -> SyncResult<bool> {
+        let result = sqlx::query("DELETE FROM virtual_services WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_virtual_services(&self) -> SyncResult<Vec<VirtualServiceRecord>> {
+        let rows = sqlx::query("SELECT name, selector, created_at FROM virtual_services ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::record).collect())
+    }
+
+    fn record(row: &sqlx::sqlite::SqliteRow) -> VirtualServiceRecord {
+        VirtualServiceRecord {
+            name: row.get("name"),
+            selector: serde_json::from_str(&row.get::<String, _>("selector")).unwrap_or_default(),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{connection::ConnectionManager, schema::SchemaManager};
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_virtual_service_lifecycle() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn_manager = ConnectionManager::new(temp_file.path().to_str().unwrap()).await.unwrap();
+        SchemaManager::new(conn_manager.pool().clone()).initialize_schema().await.unwrap();
+        let services = VirtualServiceManager::new(conn_manager.pool().clone());
+
+        let selector = HashMap::from([("app".to_string(), "web".to_string())]);
+        services.create_virtual_service("web", &selector).await.unwrap();
+        assert!(matches!(services.create_virtual_service("web", &selector).await, Err(SyncError::Conflict { .. })));
+        assert!(matches!(services.create_virtual_service("empty", &HashMap::new()).await, Err(SyncError::ValidationFailed { .. })));
+
+        let listed = services.list_virtual_services().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].selector, selector);
+
+        assert!(services.delete_virtual_service("web").await.unwrap());
+        assert!(!services.delete_virtual_service("web").await.unwrap());
+    }
+}
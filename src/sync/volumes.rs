@@ -45,6 +45,9 @@ pub enum MountType {
     Tmpfs,
 }
 
+/// Where local-driver volumes are stored
+pub const VOLUMES_BASE_DIR: &str = "/var/lib/quilt/volumes";
+
 pub struct VolumeManager {
     pool: SqlitePool,
     base_path: PathBuf,
@@ -54,7 +57,7 @@ impl VolumeManager {
     pub fn new(pool: SqlitePool) -> Self {
         Self {
             pool,
-            base_path: PathBuf::from("/var/lib/quilt/volumes"),
+            base_path: PathBuf::from(VOLUMES_BASE_DIR),
         }
     }
     
@@ -75,6 +78,32 @@ impl VolumeManager {
         labels: HashMap<String, String>,
         options: HashMap<String, String>,
     ) -> SyncResult<Volume> {
+        self.check_new_volume(name).await?;
+        
+        let driver = driver.unwrap_or("local");
+        let mount_point = self.base_path.join(name).to_string_lossy().to_string();
+        
+        // Create volume directory
+        fs::create_dir_all(&mount_point).await
+            .map_err(|e| SyncError::ValidationFailed { message: format!("Failed to create volume directory: {}", e) })?;
+        
+        self.insert_volume(name, driver, mount_point, labels, options).await
+    }
+    
+    /// Record a volume whose storage a volume driver plugin provides at `mount_point`
+    pub async fn create_driver_volume(
+        &self,
+        name: &str,
+        driver: &str,
+        mount_point: String,
+        labels: HashMap<String, String>,
+        options: HashMap<String, String>,
+    ) -> SyncResult<Volume> {
+        self.check_new_volume(name).await?;
+        self.insert_volume(name, driver, mount_point, labels, options).await
+    }
+    
+    async fn check_new_volume(&self, name: &str) -> SyncResult<()> {
         // Validate volume name
         if name.is_empty() || name.contains('/') {
             return Err(SyncError::ValidationFailed { message: "Invalid volume name".to_string() });
@@ -84,15 +113,19 @@ impl VolumeManager {
         if self.get_volume(name).await?.is_some() {
             return Err(SyncError::ValidationFailed { message: format!("Volume '{}' already exists", name) });
         }
-        
-        let driver = driver.unwrap_or("local");
-        let mount_point = self.base_path.join(name).to_string_lossy().to_string();
+        Ok(())
+    }
+    
+    async fn insert_volume(
+        &self,
+        name: &str,
+        driver: &str,
+        mount_point: String,
+        labels: HashMap<String, String>,
+        options: HashMap<String, String>,
+    ) -> SyncResult<Volume> {
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
         
-        // Create volume directory
-        fs::create_dir_all(&mount_point).await
-            .map_err(|e| SyncError::ValidationFailed { message: format!("Failed to create volume directory: {}", e) })?;
-        
         // Insert into database
         let labels_json = serde_json::to_string(&labels).unwrap();
         let options_json = serde_json::to_string(&options).unwrap();
@@ -227,9 +260,11 @@ impl VolumeManager {
             .execute(&self.pool)
             .await?;
         
-        // Remove volume directory
-        if let Err(e) = fs::remove_dir_all(&volume.mount_point).await {
-            ConsoleLogger::warning(&format!("Failed to remove volume directory: {}", e));
+        // Remove volume directory; a driver plugin owns the storage of its volumes
+        if volume.driver == "local" {
+            if let Err(e) = fs::remove_dir_all(&volume.mount_point).await {
+                ConsoleLogger::warning(&format!("Failed to remove volume directory: {}", e));
+            }
         }
         
         // Delete from database
@@ -342,8 +377,8 @@ impl VolumeManager {
     }
     
     /// Clean up orphaned volumes that are no longer referenced by any containers
-    pub async fn cleanup_orphaned_volumes(&self) -> SyncResult<u32> {
-        // Find volumes marked for cleanup or not in use
+    /// Volumes marked for cleanup or not mounted by any container
+    pub async fn list_orphaned_volumes(&self) -> SyncResult<Vec<String>> {
         let orphaned = sqlx::query_scalar::<_, String>(
             "SELECT name FROM volumes WHERE status = 'cleanup_pending' 
              OR name NOT IN (SELECT DISTINCT source FROM container_mounts WHERE mount_type = 'volume')"
@@ -351,6 +386,12 @@ impl VolumeManager {
         .fetch_all(&self.pool)
         .await?;
         
+        Ok(orphaned)
+    }
+    
+    pub async fn cleanup_orphaned_volumes(&self) -> SyncResult<u32> {
+        let orphaned = self.list_orphaned_volumes().await?;
+        
         let mut cleaned = 0;
         for volume_name in orphaned {
             if let Ok(()) = self.remove_volume(&volume_name, true).await {
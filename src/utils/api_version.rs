@@ -0,0 +1,9 @@
+// API version handshake shared by the daemon and the CLI
+// Each side sends its version in request/response metadata so either one can tell
+// when the other is older and avoid RPCs or fields it does not know
+
+/// Metadata key carrying the sender's API version
+pub const API_VERSION_HEADER: &str = "x-quilt-api-version";
+
+/// Current API version; bump when RPCs or request fields are added
+pub const API_VERSION: u32 = 1;
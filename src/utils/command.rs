@@ -1,5 +1,9 @@
-use std::process::Command;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use std::fs;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct CommandResult {
@@ -9,16 +13,120 @@ pub struct CommandResult {
     pub exit_code: Option<i32>,
 }
 
+/// A program and its arguments, run directly rather than through `sh -c` so that nothing in the
+/// arguments is interpreted by a shell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argv {
+    pub program: String,
+    pub args: Vec<String>,
+    /// When set, the program gets exactly these variables instead of the daemon's environment
+    pub environment: Option<HashMap<String, String>>,
+    /// Written to the program's stdin, which is otherwise empty
+    pub input: Option<Vec<u8>>,
+    /// `cgroup.procs` of a cgroup the program joins before it starts
+    pub cgroup: Option<PathBuf>,
+}
+
+impl Argv {
+    pub fn new<I, S>(program: &str, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            program: program.to_string(),
+            args: args.into_iter().map(Into::into).collect(),
+            environment: None,
+            input: None,
+            cgroup: None,
+        }
+    }
+
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(self.resolve_program());
+        command.args(&self.args);
+        if let Some(environment) = &self.environment {
+            command.env_clear().envs(environment);
+        }
+        command.stdin(if self.input.is_some() { Stdio::piped() } else { Stdio::null() });
+        if let Some(procs_file) = &self.cgroup {
+            join_cgroup_before_exec(&mut command, procs_file);
+        }
+        command
+    }
+
+    /// The program is looked up on the daemon's PATH, so a replaced environment changes what the
+    /// program sees but not which program runs
+    fn resolve_program(&self) -> String {
+        if self.environment.is_none() {
+            return self.program.clone();
+        }
+        CommandExecutor::program_path(&self.program)
+    }
+}
+
+/// The child writes itself into the cgroup between fork and exec, so nothing the program starts
+/// is ever counted outside it
+fn join_cgroup_before_exec(command: &mut Command, procs_file: &Path) {
+    use nix::libc;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+
+    // Allocated before the fork: the child may only make async-signal-safe calls
+    let path = CString::new(procs_file.as_os_str().as_bytes()).unwrap_or_default();
+    unsafe {
+        command.pre_exec(move || {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // "0" is the writing process itself
+            let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+            let error = std::io::Error::last_os_error();
+            libc::close(fd);
+            if written < 0 {
+                return Err(error);
+            }
+            Ok(())
+        });
+    }
+}
+
+impl std::fmt::Display for Argv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
 pub struct CommandExecutor;
 
 impl CommandExecutor {
-    /// Execute a shell command and return result
-    pub fn execute_shell(command: &str) -> Result<CommandResult, String> {
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .map_err(|e| format!("Failed to execute command '{}': {}", command, e))?;
+    /// Run `program` with `args`, without a shell
+    pub fn run<I, S>(program: &str, args: I) -> Result<CommandResult, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::execute(&Argv::new(program, args))
+    }
+
+    /// Run a program with its arguments, without a shell
+    pub fn execute(argv: &Argv) -> Result<CommandResult, String> {
+        let mut child = argv.command()
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute command '{}': {}", argv, e))?;
+        if let (Some(input), Some(mut stdin)) = (&argv.input, child.stdin.take()) {
+            stdin.write_all(input)
+                .map_err(|e| format!("Failed to write input to '{}': {}", argv, e))?;
+        }
+        let output = child.wait_with_output()
+            .map_err(|e| format!("Failed to execute command '{}': {}", argv, e))?;
 
         Ok(CommandResult {
             success: output.status.success(),
@@ -28,6 +136,20 @@ impl CommandExecutor {
         })
     }
 
+    /// Absolute path of `program` on the daemon's PATH, or `program` itself when it is a path
+    /// already or cannot be found
+    pub fn program_path(program: &str) -> String {
+        if program.contains('/') {
+            return program.to_string();
+        }
+        std::env::var_os("PATH")
+            .and_then(|path| std::env::split_paths(&path)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file()))
+            .map(|candidate| candidate.to_string_lossy().to_string())
+            .unwrap_or_else(|| program.to_string())
+    }
+
     /// Check if a command is available in the system PATH
     pub fn is_command_available(command: &str) -> bool {
         Command::new("which")
@@ -120,4 +242,4 @@ impl CommandExecutor {
 
         false
     }
-}
\ No newline at end of file
+}
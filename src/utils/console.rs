@@ -1,3 +1,6 @@
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
@@ -11,13 +14,18 @@ pub enum LogLevel {
     Debug,
 }
 
+/// Daemon log lines kept in memory for diagnostic bundles
+const RECENT_LINES_CAPACITY: usize = 2000;
+
+static RECENT_LINES: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_LINES_CAPACITY)));
+
 pub struct ConsoleLogger;
 
 #[allow(dead_code)]
 impl ConsoleLogger {
     /// Log a message with appropriate emoji and formatting
     pub fn log(level: LogLevel, message: &str) {
-        let (emoji, _prefix) = match level {
+        let (emoji, prefix) = match level {
             LogLevel::Info => ("ℹ️", "INFO"),
             LogLevel::Success => ("✅", "SUCCESS"),
             LogLevel::Warning => ("⚠️", "WARNING"),
@@ -27,6 +35,18 @@ impl ConsoleLogger {
         };
         
         println!("{} {}", emoji, message);
+        
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let mut recent = RECENT_LINES.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() >= RECENT_LINES_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(format!("{} {} {}", timestamp, prefix, message));
+    }
+
+    /// Most recent log lines (oldest first), as `<unix secs> <LEVEL> <message>`
+    pub fn recent_lines() -> Vec<String> {
+        RECENT_LINES.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
     }
 
     /// Log info message
@@ -231,4 +251,4 @@ impl ConsoleLogger {
             }
         }
     }
-} 
\ No newline at end of file
+}
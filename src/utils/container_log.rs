@@ -0,0 +1,80 @@
+// Per-container log levels
+// The daemon can trace a single container (network setup steps, monitor checks) without turning
+// on debug logging for everything; see SetContainerLogLevel.
+
+use crate::utils::console::{ConsoleLogger, LogLevel};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+/// Daemon-side log verbosity for a single container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerLogLevel {
+    /// Regular daemon logging only
+    Info,
+    /// Also emit per-container traces (network setup steps, monitor checks)
+    Debug,
+}
+
+impl ContainerLogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContainerLogLevel::Info => "info",
+            ContainerLogLevel::Debug => "debug",
+        }
+    }
+
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_ascii_lowercase().as_str() {
+            "info" | "" => Some(ContainerLogLevel::Info),
+            "debug" | "trace" | "verbose" => Some(ContainerLogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// Containers whose traces are currently enabled
+static DEBUG_CONTAINERS: Lazy<RwLock<HashSet<String>>> = Lazy::new(|| RwLock::new(HashSet::new()));
+
+impl ConsoleLogger {
+    /// Set the log level for one container; returns the level it replaced
+    pub fn set_container_log_level(container_id: &str, level: ContainerLogLevel) -> ContainerLogLevel {
+        let mut containers = DEBUG_CONTAINERS.write().unwrap_or_else(|e| e.into_inner());
+        let was_debug = match level {
+            ContainerLogLevel::Debug => !containers.insert(container_id.to_string()),
+            ContainerLogLevel::Info => containers.remove(container_id),
+        };
+        if was_debug { ContainerLogLevel::Debug } else { ContainerLogLevel::Info }
+    }
+
+    pub fn container_log_level(container_id: &str) -> ContainerLogLevel {
+        let containers = DEBUG_CONTAINERS.read().unwrap_or_else(|e| e.into_inner());
+        if containers.contains(container_id) { ContainerLogLevel::Debug } else { ContainerLogLevel::Info }
+    }
+
+    /// Trace message for one container, printed only while that container is at debug level
+    pub fn container_debug(container_id: &str, message: &str) {
+        if Self::container_log_level(container_id) == ContainerLogLevel::Debug {
+            Self::log(LogLevel::Debug, &format!("[{}] {}", container_id, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_log_level() {
+        assert_eq!(ContainerLogLevel::parse("DEBUG"), Some(ContainerLogLevel::Debug));
+        assert_eq!(ContainerLogLevel::parse("loud"), None);
+
+        let id = "log-level-test";
+        assert_eq!(ConsoleLogger::container_log_level(id), ContainerLogLevel::Info);
+        assert_eq!(ConsoleLogger::set_container_log_level(id, ContainerLogLevel::Debug), ContainerLogLevel::Info);
+        assert_eq!(ConsoleLogger::container_log_level(id), ContainerLogLevel::Debug);
+        assert_eq!(ConsoleLogger::container_log_level("other"), ContainerLogLevel::Info);
+        assert_eq!(ConsoleLogger::set_container_log_level(id, ContainerLogLevel::Info), ContainerLogLevel::Debug);
+        assert_eq!(ConsoleLogger::container_log_level(id), ContainerLogLevel::Info);
+    }
+}
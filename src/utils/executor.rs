@@ -0,0 +1,288 @@
+// Command execution for the daemon
+// Runs commands on the tokio process driver with a timeout and a cap on how many run at once,
+// streams their output, and prepares the environment and argv of commands run in containers.
+// The plain blocking executor in `command` is shared with the CLI; this half is daemon-only.
+
+use crate::utils::command::{Argv, CommandExecutor, CommandResult};
+use crate::utils::validation::InputValidator;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{mpsc, Semaphore};
+
+/// Default timeout for commands run through the async executor
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// PATH of a command run in a container when its environment does not set one
+pub const DEFAULT_EXEC_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+/// Upper bound on commands the async executor runs at once; further callers wait for a slot
+const MAX_CONCURRENT_COMMANDS: usize = 64;
+
+static COMMAND_SLOTS: once_cell::sync::Lazy<Semaphore> =
+    once_cell::sync::Lazy::new(|| Semaphore::new(MAX_CONCURRENT_COMMANDS));
+
+/// Output of a command started with `CommandExecutor::stream_shell`
+#[derive(Debug)]
+pub enum CommandOutput {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    /// The command exited; `None` when it was terminated by a signal
+    Exited(Option<i32>),
+    Failed(String),
+}
+
+impl Argv {
+    /// Run the program with exactly `environment`
+    pub fn with_environment(mut self, environment: HashMap<String, String>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Feed `input` to the program's stdin
+    pub fn with_input(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+
+    /// Start the program inside the cgroup whose `cgroup.procs` is `procs_file`
+    pub fn with_cgroup(mut self, procs_file: PathBuf) -> Self {
+        self.cgroup = Some(procs_file);
+        self
+    }
+
+    fn async_command(&self) -> tokio::process::Command {
+        tokio::process::Command::from(self.command())
+    }
+}
+
+impl CommandExecutor {
+    /// Execute a shell command and return result
+    pub fn execute_shell(command: &str) -> Result<CommandResult, String> {
+        Self::execute(&Argv::new("sh", ["-c", command]))
+    }
+
+    /// Execute a shell command on the tokio process driver without blocking a worker thread
+    pub async fn execute_shell_async(command: &str) -> Result<CommandResult, String> {
+        Self::execute_shell_with_timeout(command, DEFAULT_COMMAND_TIMEOUT).await
+    }
+
+    /// Run `program` with `args` on the tokio process driver, without a shell
+    pub async fn run_async<I, S>(program: &str, args: I) -> Result<CommandResult, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::execute_with_timeout(&Argv::new(program, args), DEFAULT_COMMAND_TIMEOUT).await
+    }
+
+    /// Execute a shell command asynchronously, killing it if it outlives `timeout`
+    pub async fn execute_shell_with_timeout(command: &str, timeout: Duration) -> Result<CommandResult, String> {
+        Self::execute_with_timeout(&Argv::new("sh", ["-c", command]), timeout).await
+    }
+
+    /// Run a program on the tokio process driver without a shell, killing it if it outlives `timeout`
+    pub async fn execute_with_timeout(argv: &Argv, timeout: Duration) -> Result<CommandResult, String> {
+        let _permit = COMMAND_SLOTS.acquire().await
+            .map_err(|e| format!("Command executor unavailable: {}", e))?;
+
+        let mut child = argv.async_command()
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to execute command '{}': {}", argv, e))?;
+
+        // Dropping the wait future on timeout kills the child via kill_on_drop
+        let run = async {
+            if let (Some(input), Some(mut stdin)) = (&argv.input, child.stdin.take()) {
+                stdin.write_all(input).await?;
+            }
+            child.wait_with_output().await
+        };
+        let output = match tokio::time::timeout(timeout, run).await {
+            Ok(output) => output.map_err(|e| format!("Failed to execute command '{}': {}", argv, e))?,
+            Err(_) => return Err(format!("Command timed out after {}s: {}", timeout.as_secs(), argv)),
+        };
+
+        Ok(CommandResult {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: output.status.code(),
+        })
+    }
+
+    /// Start a shell command and forward its stdout/stderr through the returned channel as it is
+    /// produced. The last message is `Exited` or `Failed`; the command is killed on timeout or as
+    /// soon as the receiver is dropped.
+    pub async fn stream_shell(command: &str, timeout: Duration) -> Result<mpsc::Receiver<CommandOutput>, String> {
+        Self::stream(&Argv::new("sh", ["-c", command]), timeout).await
+    }
+
+    /// Start a program without a shell and forward its output like `stream_shell`
+    pub async fn stream(argv: &Argv, timeout: Duration) -> Result<mpsc::Receiver<CommandOutput>, String> {
+        let permit = COMMAND_SLOTS.acquire().await
+            .map_err(|e| format!("Command executor unavailable: {}", e))?;
+
+        let mut child = argv.async_command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to execute command '{}': {}", argv, e))?;
+
+        let stdout = child.stdout.take().ok_or("Command stdout unavailable")?;
+        let stderr = child.stderr.take().ok_or("Command stderr unavailable")?;
+        let (tx, rx) = mpsc::channel(32);
+
+        tokio::spawn(async move {
+            let _permit = permit;
+            let run = async {
+                let (stdout_open, stderr_open) = tokio::join!(
+                    Self::forward_output(stdout, &tx, CommandOutput::Stdout),
+                    Self::forward_output(stderr, &tx, CommandOutput::Stderr),
+                );
+                if !(stdout_open && stderr_open) {
+                    return None; // Receiver went away
+                }
+                Some(child.wait().await)
+            };
+
+            match tokio::time::timeout(timeout, run).await {
+                Ok(Some(Ok(status))) => {
+                    let _ = tx.send(CommandOutput::Exited(status.code())).await;
+                }
+                Ok(Some(Err(e))) => {
+                    let _ = tx.send(CommandOutput::Failed(format!("Failed to wait for command: {}", e))).await;
+                }
+                Ok(None) => {
+                    let _ = child.kill().await;
+                }
+                Err(_) => {
+                    let _ = child.kill().await;
+                    let _ = tx.send(CommandOutput::Failed(format!("Command timed out after {}s", timeout.as_secs()))).await;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Copy a pipe into the channel; returns false once the receiver has been dropped
+    async fn forward_output<R: AsyncRead + Unpin>(
+        mut reader: R,
+        tx: &mpsc::Sender<CommandOutput>,
+        wrap: fn(Vec<u8>) -> CommandOutput,
+    ) -> bool {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => return true,
+                Ok(n) => {
+                    if tx.send(wrap(buf[..n].to_vec())).await.is_err() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The complete environment of a command run in a container: `environment` without names a
+    /// shell could not export, and `DEFAULT_EXEC_PATH` unless it sets PATH. Values are passed
+    /// as they are; nothing in them is expanded.
+    pub fn process_environment(environment: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut process_environment: HashMap<String, String> = environment.iter()
+            .filter(|(name, _)| InputValidator::validate_env_name(name).is_ok())
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        process_environment.entry("PATH".to_string()).or_insert_with(|| DEFAULT_EXEC_PATH.to_string());
+        process_environment
+    }
+
+    /// Argv for a command run in a container: a single element is a command line for the
+    /// container's `/bin/sh`, several are a program and its arguments, run as given
+    pub fn exec_form(command: &[String]) -> Vec<String> {
+        match command {
+            [line] => vec!["/bin/sh".to_string(), "-c".to_string(), line.clone()],
+            _ => command.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_execute_shell_async() {
+        let result = CommandExecutor::execute_shell_async("echo hello; exit 3").await.unwrap();
+        assert_eq!(result.stdout.trim(), "hello");
+        assert_eq!(result.exit_code, Some(3));
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_stream_shell_orders_output_before_exit() {
+        let mut rx = CommandExecutor::stream_shell("echo out; echo err >&2; exit 2", Duration::from_secs(5)).await.unwrap();
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_code = None;
+        while let Some(output) = rx.recv().await {
+            match output {
+                CommandOutput::Stdout(data) => stdout.extend(data),
+                CommandOutput::Stderr(data) => stderr.extend(data),
+                CommandOutput::Exited(code) => exit_code = code,
+                CommandOutput::Failed(e) => panic!("unexpected failure: {}", e),
+            }
+        }
+        assert_eq!(String::from_utf8_lossy(&stdout).trim(), "out");
+        assert_eq!(String::from_utf8_lossy(&stderr).trim(), "err");
+        assert_eq!(exit_code, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_process_environment() {
+        let environment = HashMap::from([
+            ("GREETING".to_string(), "it's $HOME".to_string()),
+            ("A_1".to_string(), "x".to_string()),
+            ("1BAD".to_string(), "y".to_string()),
+        ]);
+        let process_environment = CommandExecutor::process_environment(&environment);
+        assert_eq!(process_environment.len(), 3);
+        assert_eq!(process_environment["PATH"], DEFAULT_EXEC_PATH);
+        assert!(!process_environment.contains_key("1BAD"));
+        let form = CommandExecutor::exec_form(&["echo \"$GREETING\"".to_string()]);
+        assert_eq!(form, vec!["/bin/sh", "-c", "echo \"$GREETING\""]);
+        let argv = Argv::new(&form[0], &form[1..]).with_environment(process_environment);
+        let result = CommandExecutor::execute_with_timeout(&argv, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(result.stdout.trim(), "it's $HOME");
+
+        let custom_path = HashMap::from([("PATH".to_string(), "/opt/bin".to_string())]);
+        assert_eq!(CommandExecutor::process_environment(&custom_path)["PATH"], "/opt/bin");
+        assert_eq!(CommandExecutor::exec_form(&["ls".to_string(), "-la".to_string()]), vec!["ls", "-la"]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_argv_without_shell() {
+        let argv = Argv::new("echo", ["$HOME", "a; rm -rf /", "`id`"]);
+        let result = CommandExecutor::execute_with_timeout(&argv, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(result.stdout.trim(), "$HOME a; rm -rf / `id`");
+
+        let argv = Argv::new("/bin/sh", ["-c", "echo \"$MODE\" \"${HOME:-unset}\"; cat"])
+            .with_environment(HashMap::from([("MODE".to_string(), "it's $prod".to_string())]))
+            .with_input("from stdin");
+        let result = CommandExecutor::execute(&argv).unwrap();
+        assert_eq!(result.stdout, "it's $prod unset\nfrom stdin");
+        assert_eq!(argv.to_string(), "/bin/sh -c echo \"$MODE\" \"${HOME:-unset}\"; cat");
+    }
+
+    #[tokio::test]
+    async fn test_execute_shell_timeout() {
+        let started = std::time::Instant::now();
+        let result = CommandExecutor::execute_shell_with_timeout("sleep 5", Duration::from_millis(100)).await;
+        assert!(result.unwrap_err().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -1,11 +1,16 @@
 // Utility modules for common functionality
 pub mod console;
+pub mod container_log;
 pub mod logger;
 pub mod process;
 pub mod validation;
+pub mod names;
 pub mod security;
 pub mod command;
+pub mod executor;
 pub mod filesystem;
+pub mod api_version;
+pub mod name_policy;
 
 // Re-export actually used utilities
 // Note: Direct module access is preferred throughout the codebase 
\ No newline at end of file
@@ -0,0 +1,9 @@
+// Caller tokens
+// The CLI sends the token from QUILT_TOKEN in the `x-quilt-token` header; the daemon uses it to
+// admit names under reserved prefixes (see `grpc::name_policy`) and to key per-client limits.
+
+/// Metadata key carrying the caller's token
+pub const TOKEN_HEADER: &str = "x-quilt-token";
+
+/// Environment variable the CLI reads the token it sends from
+pub const CLIENT_TOKEN_ENV: &str = "QUILT_TOKEN";
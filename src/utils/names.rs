@@ -0,0 +1,113 @@
+// Name validation for the daemon
+// Names the daemon publishes or hands to the kernel: container names, DNS aliases, records and
+// domains, network names and the environment variable names of exec'd commands. The CLI leaves
+// these checks to the daemon, so they live apart from the parsers it shares.
+
+use crate::utils::validation::InputValidator;
+
+/// Longest container name accepted, the length of a DNS label
+pub const MAX_CONTAINER_NAME_LEN: usize = 63;
+
+/// Longest additional network name: its bridge, `qn<name>`, must fit an interface name
+pub const MAX_NETWORK_NAME_LEN: usize = 13;
+
+impl InputValidator {
+    /// Environment variable names a POSIX shell can export: letters, digits and `_`, not
+    /// starting with a digit
+    pub fn validate_env_name(name: &str) -> Result<(), String> {
+        let valid = name.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid environment variable name '{}'", name))
+        }
+    }
+
+    /// Container names: 1-63 letters, digits, `_` and `-`, starting with a letter or digit. Names
+    /// are published as `<name>.quilt.local`, so they follow DNS label limits.
+    pub fn validate_container_name(name: &str) -> Result<(), String> {
+        Self::validate_dns_label("container name", name)
+    }
+
+    /// DNS aliases follow the same rules as container names, since both resolve the same way
+    pub fn validate_dns_alias(alias: &str) -> Result<(), String> {
+        Self::validate_dns_label("DNS alias", alias)
+    }
+
+    /// Virtual service names share the namespace of container names and DNS aliases
+    pub fn validate_virtual_service_name(name: &str) -> Result<(), String> {
+        Self::validate_dns_label("virtual service name", name)
+    }
+
+    /// DNS record names may be fully qualified: every dot-separated label follows the rules of a
+    /// container name, up to the 253 characters of a DNS name
+    pub fn validate_dns_record_name(name: &str) -> Result<(), String> {
+        Self::validate_dns_name("DNS record name", name)
+    }
+
+    /// Cluster and search domains are DNS names under the same rules as record names
+    pub fn validate_dns_domain(domain: &str) -> Result<(), String> {
+        Self::validate_dns_name("DNS domain", domain)
+    }
+
+    /// Additional network names: 1-13 lowercase letters and digits. They name a bridge and an
+    /// interface, which allow nothing else, and a DNS label.
+    pub fn validate_network_name(name: &str) -> Result<(), String> {
+        let valid = !name.is_empty() && name.len() <= MAX_NETWORK_NAME_LEN
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid network name '{}': use 1-{} lowercase letters and digits", name, MAX_NETWORK_NAME_LEN))
+        }
+    }
+
+    fn validate_dns_name(kind: &str, name: &str) -> Result<(), String> {
+        let name = name.trim_end_matches('.');
+        if name.len() > 253 {
+            return Err(format!("Invalid {} '{}': must be at most 253 characters", kind, name));
+        }
+        name.split('.').try_for_each(|label| Self::validate_dns_label(&format!("{} label", kind), label))
+    }
+
+    fn validate_dns_label(kind: &str, name: &str) -> Result<(), String> {
+        if name.is_empty() || name.len() > MAX_CONTAINER_NAME_LEN {
+            return Err(format!("Invalid {} '{}': must be 1-{} characters", kind, name, MAX_CONTAINER_NAME_LEN));
+        }
+        let valid = name.chars().next().map_or(false, |c| c.is_ascii_alphanumeric())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid {} '{}': use letters, digits, '_' and '-', starting with a letter or digit", kind, name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_container_name() {
+        for name in ["web", "api-1", "clever_turing", "0cache", &"a".repeat(MAX_CONTAINER_NAME_LEN)] {
+            assert!(InputValidator::validate_container_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "-web", "_web", "web.local", "web 1", "web/1", "wéb", &"a".repeat(MAX_CONTAINER_NAME_LEN + 1)] {
+            assert!(InputValidator::validate_container_name(name).is_err(), "{}", name);
+        }
+        assert!(InputValidator::validate_dns_alias("www").is_ok());
+        assert!(InputValidator::validate_dns_alias("www.quilt.local").unwrap_err().contains("DNS alias"));
+        assert!(InputValidator::validate_dns_record_name("api").is_ok());
+        assert!(InputValidator::validate_dns_record_name("db.example.com.").is_ok());
+        assert!(InputValidator::validate_dns_record_name("db..example.com").is_err());
+        assert!(InputValidator::validate_dns_record_name("").is_err());
+        assert!(InputValidator::validate_dns_domain("cluster.internal").is_ok());
+        assert!(InputValidator::validate_dns_domain("cluster internal").unwrap_err().contains("DNS domain"));
+        assert!(InputValidator::validate_network_name("backend2").is_ok());
+        for name in ["", "Backend", "back-end", &"a".repeat(MAX_NETWORK_NAME_LEN + 1)] {
+            assert!(InputValidator::validate_network_name(name).is_err(), "{}", name);
+        }
+    }
+}
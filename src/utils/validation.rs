@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 
 /// Mount type enumeration
 #[derive(Debug, Clone, PartialEq)]
@@ -18,6 +19,32 @@ pub struct VolumeMount {
     pub options: HashMap<String, String>,
 }
 
+/// FUSE filesystem mounted inside a container by a host helper binary (sshfs, s3fs, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuseMount {
+    pub helper: String,
+    pub source: String,
+    pub target: String,
+    pub options: Vec<String>,
+}
+
+/// Lifecycle hook as given on the command line; the daemon validates event names
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookSpec {
+    pub events: Vec<String>,    // Empty fires on every lifecycle event
+    pub command: String,        // Host shell command, or
+    pub url: String,            // webhook URL
+}
+
+/// Host port published to a container port through NAT
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub host_ip: String,       // "0.0.0.0" publishes on all host addresses
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: String,      // "tcp" or "udp"
+}
+
 pub struct InputValidator;
 
 impl InputValidator {
@@ -37,6 +64,20 @@ impl InputValidator {
         Ok((key, value))
     }
 
+    /// Parse a `TARGET=REPLICAS` scale spec
+    pub fn parse_scale_spec(s: &str) -> Result<(String, u32), String> {
+        let (target, replicas) = s.rsplit_once('=').ok_or_else(|| {
+            format!("Invalid TARGET=REPLICAS format: '{}'", s)
+        })?;
+        let target = target.trim();
+        if target.is_empty() {
+            return Err("Empty target in TARGET=REPLICAS".to_string());
+        }
+        let replicas = replicas.trim().parse::<u32>()
+            .map_err(|_| format!("Invalid replica count: '{}'", replicas.trim()))?;
+        Ok((target.to_string(), replicas))
+    }
+
     /// Parse volume mount specification (-v flag format)
     /// Format: source:target[:options] or name:target[:options]
     pub fn parse_volume(s: &str) -> Result<VolumeMount, String> {
@@ -143,4 +184,147 @@ impl InputValidator {
         
         Ok(mount)
     }
-}
\ No newline at end of file
+    
+    /// Parse FUSE mount specification (--fuse-mount flag format)
+    /// Format: helper=sshfs,source=user@host:/path,target=/container/path[,opt=allow_other]...
+    pub fn parse_fuse_mount(s: &str) -> Result<FuseMount, String> {
+        let mut fuse_mount = FuseMount {
+            helper: String::new(),
+            source: String::new(),
+            target: String::new(),
+            options: Vec::new(),
+        };
+        
+        for part in s.split(',') {
+            let (key, value) = part.split_once('=')
+                .ok_or_else(|| format!("Invalid FUSE mount option: '{}'", part))?;
+            let value = value.trim();
+            
+            match key.trim() {
+                "helper" => fuse_mount.helper = value.to_string(),
+                "source" | "src" => fuse_mount.source = value.to_string(),
+                "target" | "dst" | "destination" => fuse_mount.target = value.to_string(),
+                "opt" | "option" => fuse_mount.options.push(value.to_string()),
+                other => return Err(format!("Unknown FUSE mount key: '{}'", other)),
+            }
+        }
+        
+        Self::validate_fuse_mount(&fuse_mount)?;
+        Ok(fuse_mount)
+    }
+    
+    /// Validate a FUSE mount declaration before it is stored or executed
+    pub fn validate_fuse_mount(fuse_mount: &FuseMount) -> Result<(), String> {
+        if fuse_mount.helper.is_empty() {
+            return Err("FUSE mount helper is required".to_string());
+        }
+        
+        // The helper is executed directly (no shell), but keep it to a plain binary name or path
+        if fuse_mount.helper.starts_with('-')
+            || !fuse_mount.helper.chars().all(|c| c.is_ascii_alphanumeric() || "/._-".contains(c)) {
+            return Err(format!("Invalid FUSE mount helper: '{}'", fuse_mount.helper));
+        }
+        
+        if fuse_mount.source.is_empty() {
+            return Err("FUSE mount source is required".to_string());
+        }
+        
+        if !fuse_mount.target.starts_with('/') {
+            return Err("FUSE mount target must be an absolute path".to_string());
+        }
+        
+        if fuse_mount.target.split('/').any(|component| component == "..") {
+            return Err("FUSE mount target must not contain '..'".to_string());
+        }
+        
+        Ok(())
+    }
+    
+    /// Parse port publishing specification (-p flag format)
+    /// Format: [host_ip:]host_port:container_port[/tcp|/udp]
+    pub fn parse_port_mapping(s: &str) -> Result<PortMapping, String> {
+        let (ports, protocol) = match s.rsplit_once('/') {
+            Some((ports, protocol)) => (ports, protocol.to_ascii_lowercase()),
+            None => (s, "tcp".to_string()),
+        };
+        
+        let parts: Vec<&str> = ports.split(':').collect();
+        let (host_ip, host_port, container_port) = match parts.as_slice() {
+            [host_port, container_port] => ("0.0.0.0", *host_port, *container_port),
+            [host_ip, host_port, container_port] => (*host_ip, *host_port, *container_port),
+            _ => return Err(format!("Invalid port mapping '{}': expected [host_ip:]host_port:container_port[/protocol]", s)),
+        };
+        
+        let mapping = PortMapping {
+            host_ip: host_ip.to_string(),
+            host_port: host_port.parse().map_err(|_| format!("Invalid host port '{}'", host_port))?,
+            container_port: container_port.parse().map_err(|_| format!("Invalid container port '{}'", container_port))?,
+            protocol,
+        };
+        
+        Self::validate_port_mapping(&mapping)?;
+        Ok(mapping)
+    }
+    
+    /// Parse a lifecycle hook: `EVENTS=ACTION`, where EVENTS is a comma-separated list of
+    /// started/stopped/died/removed or `all`, and ACTION an http(s) URL or a host shell command
+    pub fn parse_hook(s: &str) -> Result<HookSpec, String> {
+        let (events, action) = s.split_once('=')
+            .ok_or_else(|| format!("Invalid hook '{}': expected EVENTS=COMMAND or EVENTS=URL", s))?;
+        let action = action.trim();
+        if action.is_empty() {
+            return Err(format!("Hook '{}' has no command or URL", s));
+        }
+        
+        let events: Vec<String> = events.split(',')
+            .map(|event| event.trim().to_ascii_lowercase())
+            .filter(|event| !event.is_empty() && event != "all")
+            .collect();
+        let is_url = action.starts_with("http://") || action.starts_with("https://");
+        Ok(HookSpec {
+            events,
+            command: if is_url { String::new() } else { action.to_string() },
+            url: if is_url { action.to_string() } else { String::new() },
+        })
+    }
+    
+    /// Parse a pre-start hook: `[host:|container:]COMMAND`, returning (namespace, command); host is the default
+    pub fn parse_prestart_hook(s: &str) -> Result<(String, String), String> {
+        let (namespace, command) = match s.split_once(':') {
+            Some((namespace @ ("host" | "container"), command)) => (namespace, command),
+            _ => ("host", s),
+        };
+        if command.trim().is_empty() {
+            return Err(format!("Pre-start hook '{}' has no command", s));
+        }
+        Ok((namespace.to_string(), command.trim().to_string()))
+    }
+    
+    /// Parse a readiness gate: `KIND:TARGET` with kind tcp, http, file or log, returning (kind, target);
+    /// the daemon validates the target
+    pub fn parse_readiness_gate(s: &str) -> Result<(String, String), String> {
+        match s.split_once(':') {
+            Some((kind @ ("tcp" | "http" | "file" | "log"), target)) if !target.trim().is_empty() => {
+                Ok((kind.to_string(), target.trim().to_string()))
+            }
+            _ => Err(format!("Invalid readiness gate '{}' (expected tcp:PORT, http:PORT[/PATH], file:PATH or log:REGEX)", s)),
+        }
+    }
+    
+    /// Validate a port mapping before it is allocated
+    pub fn validate_port_mapping(mapping: &PortMapping) -> Result<(), String> {
+        if mapping.host_port == 0 || mapping.container_port == 0 {
+            return Err("Port numbers must be between 1 and 65535".to_string());
+        }
+        
+        if mapping.protocol != "tcp" && mapping.protocol != "udp" {
+            return Err(format!("Unsupported protocol '{}': expected tcp or udp", mapping.protocol));
+        }
+        
+        if mapping.host_ip.parse::<std::net::Ipv4Addr>().is_err() {
+            return Err(format!("Invalid host IP '{}'", mapping.host_ip));
+        }
+        
+        Ok(())
+    }
+}